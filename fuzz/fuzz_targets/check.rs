@@ -0,0 +1,12 @@
+#![no_main]
+use elz::parser::Parser;
+use elz::semantic::SemanticChecker;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(code) = std::str::from_utf8(data) {
+        if let Ok(module) = Parser::try_parse("fuzz.elz", code) {
+            let _ = SemanticChecker::new().try_check(module);
+        }
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+use elz::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(code) = std::str::from_utf8(data) {
+        let _ = Parser::try_parse("fuzz.elz", code);
+    }
+});
@@ -0,0 +1,183 @@
+// elz.mjs runs `main` of a module built by `elz build --target wasm32`, and provides the C
+// functions the prelude calls, since the web has no libc
+//
+// in browsers: `import { run } from "./elz.mjs"; run(fetch("hello.wasm"));`
+// in node: `node elz.mjs hello.wasm`
+
+// Exit is thrown by `exit` to stop the program
+export class Exit extends Error {
+  constructor(code) {
+    super(`program exits with code ${code}`);
+    this.code = code;
+  }
+}
+
+// run instantiates the module from a response or bytes, calls its `main` and returns the exit
+// code, lines printed by the program to stdout are passed to `print`, and to stderr to `eprint`
+export async function run(source, print = console.log, eprint = console.error) {
+  let exports;
+  // standard input is only read in node, browsers have none
+  const fs = globalThis.process?.versions?.node ? await import("node:fs") : undefined;
+  // text written to stdout and stderr after their last newline
+  const pending = { 1: "", 2: "" };
+  const printers = { 1: print, 2: eprint };
+  // memory is allocated from the end of static data, and freed memory is never reused
+  let heap = 0;
+  // sizes of allocations, `realloc` copies as many bytes
+  const sizes = new Map();
+  // environment variables set by the program, to copies of their values, since the web has none
+  const variables = new Map();
+  const text = (pointer) => {
+    const bytes = new Uint8Array(exports.memory.buffer);
+    return new TextDecoder().decode(bytes.subarray(pointer, bytes.indexOf(0, pointer)));
+  };
+  const env = {
+    puts(pointer) {
+      const bytes = new Uint8Array(exports.memory.buffer);
+      const end = bytes.indexOf(0, pointer);
+      print(new TextDecoder().decode(bytes.subarray(pointer, end)));
+      return 0;
+    },
+    write(fd, pointer, size) {
+      const bytes = new Uint8Array(exports.memory.buffer, pointer, size);
+      const lines = (pending[fd] + new TextDecoder().decode(bytes)).split("\n");
+      pending[fd] = lines.pop();
+      lines.forEach(printers[fd]);
+      return size;
+    },
+    read(fd, pointer, size) {
+      try {
+        return fs?.readSync(fd, new Uint8Array(exports.memory.buffer, pointer, size)) ?? 0;
+      } catch {
+        return 0;
+      }
+    },
+    malloc(size) {
+      const pointer = (heap + 7) & ~7;
+      heap = pointer + size;
+      const { buffer } = exports.memory;
+      if (heap > buffer.byteLength) {
+        exports.memory.grow(Math.ceil((heap - buffer.byteLength) / 65536));
+      }
+      sizes.set(pointer, size);
+      return pointer;
+    },
+    realloc(pointer, size) {
+      const moved = env.malloc(size);
+      const bytes = new Uint8Array(exports.memory.buffer);
+      bytes.copyWithin(moved, pointer, pointer + Math.min(sizes.get(pointer) ?? 0, size));
+      return moved;
+    },
+    free(pointer) {
+      sizes.delete(pointer);
+    },
+    // llvm.memcpy of large sizes is lowered to a call of `memcpy`
+    memcpy(destination, source, size) {
+      const bytes = new Uint8Array(exports.memory.buffer);
+      bytes.copyWithin(destination, source, source + size);
+      return destination;
+    },
+    strlen(pointer) {
+      return new Uint8Array(exports.memory.buffer).indexOf(0, pointer) - pointer;
+    },
+    strcpy(destination, source) {
+      env.memcpy(destination, source, env.strlen(source) + 1);
+      return destination;
+    },
+    strcat(destination, source) {
+      env.strcpy(destination + env.strlen(destination), source);
+      return destination;
+    },
+    strndup(pointer, size) {
+      size = Math.min(env.strlen(pointer), size);
+      const copy = env.malloc(size + 1);
+      env.memcpy(copy, pointer, size);
+      new Uint8Array(exports.memory.buffer)[copy + size] = 0;
+      return copy;
+    },
+    strncmp(left, right, size) {
+      const bytes = new Uint8Array(exports.memory.buffer);
+      for (let i = 0; i < size; i++) {
+        if (bytes[left + i] !== bytes[right + i] || bytes[left + i] === 0) {
+          return bytes[left + i] - bytes[right + i];
+        }
+      }
+      return 0;
+    },
+    atof(pointer) {
+      const bytes = new Uint8Array(exports.memory.buffer);
+      return parseFloat(new TextDecoder().decode(bytes.subarray(pointer, bytes.indexOf(0, pointer))));
+    },
+    // gcvt formats as `%.<digits>g` does
+    gcvt(x, digits, buffer) {
+      const [mantissa, exponent] = x.toExponential(digits - 1).split("e");
+      const trim = (text) => (text.includes(".") ? text.replace(/\.?0+$/, "") : text);
+      const e = Number(exponent);
+      let text;
+      if (!Number.isFinite(x)) {
+        text = Number.isNaN(x) ? "nan" : x < 0 ? "-inf" : "inf";
+      } else if (e < -4 || e >= digits) {
+        text = `${trim(mantissa)}e${e < 0 ? "-" : "+"}${String(Math.abs(e)).padStart(2, "0")}`;
+      } else {
+        text = trim(x.toFixed(digits - 1 - e));
+      }
+      const bytes = new TextEncoder().encode(text);
+      new Uint8Array(exports.memory.buffer).set([...bytes, 0], buffer);
+      return buffer;
+    },
+    getenv(name) {
+      return variables.get(text(name)) ?? 0;
+    },
+    setenv(name, value, overwrite) {
+      const key = text(name);
+      if (key === "" || key.includes("=")) {
+        return -1;
+      }
+      if (overwrite !== 0 || !variables.has(key)) {
+        variables.set(key, env.strndup(value, env.strlen(value)));
+      }
+      return 0;
+    },
+    exit(code) {
+      throw new Exit(code);
+    },
+    // functions of libm are the ones of `Math`
+    sqrt: Math.sqrt,
+    pow: Math.pow,
+    fabs: Math.abs,
+    floor: Math.floor,
+    ceil: Math.ceil,
+    exp: Math.exp,
+    log: Math.log,
+    sin: Math.sin,
+    cos: Math.cos,
+    tan: Math.tan,
+    atan2: Math.atan2,
+  };
+  const { instance } =
+    source instanceof Response || source instanceof Promise
+      ? await WebAssembly.instantiateStreaming(source, { env })
+      : await WebAssembly.instantiate(source, { env });
+  exports = instance.exports;
+  heap = exports.__heap_base.value;
+  try {
+    const code = exports.main();
+    return typeof code === "number" ? code : 0;
+  } catch (err) {
+    if (err instanceof Exit) {
+      return err.code;
+    }
+    throw err;
+  } finally {
+    for (const fd of [1, 2]) {
+      if (pending[fd] !== "") {
+        printers[fd](pending[fd]);
+      }
+    }
+  }
+}
+
+if (globalThis.process?.argv[1] && import.meta.url === new URL(`file://${process.argv[1]}`).href) {
+  const { readFile } = await import("node:fs/promises");
+  process.exitCode = await run(await readFile(process.argv[2]));
+}
@@ -1,8 +1,12 @@
 use super::lexer::{TkType, Token};
 use crate::lexer::Location;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Clone, Debug, PartialEq)]
+pub mod visit;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Tag {
     pub name: String,
     pub properties: Vec<String>,
@@ -28,13 +32,13 @@ impl Tag {
 /// ```elz
 /// module io.utils
 /// ```
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Module {
     pub name: String,
     pub top_list: Vec<TopAst>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum TopAst {
     Import(Import),
     Function(Function),
@@ -54,14 +58,14 @@ pub enum TopAst {
 ///   println("Hello, {user_name}");
 /// }
 /// ```
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Import {
     pub location: Location,
     pub import_path: String,
     pub imported_component: Vec<String>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct TypeParameter {
     pub name: String,
     pub parent_types: Vec<ParsedType>,
@@ -76,7 +80,7 @@ impl TypeParameter {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Trait {
     pub location: Location,
     pub tag: Option<Tag>,
@@ -86,7 +90,7 @@ pub struct Trait {
     pub members: Vec<TraitMember>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum TraitMember {
     Field(Field),
     Method(Function),
@@ -112,7 +116,7 @@ impl Trait {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Class {
     pub location: Location,
     pub tag: Option<Tag>,
@@ -122,7 +126,11 @@ pub struct Class {
     pub members: Vec<ClassMember>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// DROP_METHOD names the method called once before an instance is freed, e.g. to release what it
+/// holds out of memory
+pub const DROP_METHOD: &str = "drop";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum ClassMember {
     Field(Field),
     Method(Function),
@@ -147,9 +155,16 @@ impl Class {
             members,
         }
     }
+    /// drop_method returns the method `drop` of the class, if it defines one
+    pub fn drop_method(&self) -> Option<&Function> {
+        self.members.iter().find_map(|member| match member {
+            ClassMember::Method(method) if method.name == DROP_METHOD => Some(method),
+            _ => None,
+        })
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Field {
     pub location: Location,
     pub name: String,
@@ -173,7 +188,7 @@ impl Field {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum ParsedType {
     TypeName(String),
     GenericType {
@@ -209,7 +224,7 @@ impl ParsedType {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Variable {
     pub location: Location,
     pub tag: Option<Tag>,
@@ -236,11 +251,14 @@ impl Variable {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Function {
     pub location: Location,
     pub tag: Option<Tag>,
     pub name: String,
+    /// type_parameters of a generic top-level function, e.g. `T` of `f[T](x: T): T`, it's compiled
+    /// once for each type it's called with
+    pub type_parameters: Vec<TypeParameter>,
     pub parameters: Vec<Parameter>,
     pub ret_typ: ParsedType,
     pub body: Option<Body>,
@@ -259,6 +277,7 @@ impl Function {
             location,
             tag,
             name: name.to_string(),
+            type_parameters: vec![],
             parameters,
             ret_typ,
             body: Some(body),
@@ -275,6 +294,7 @@ impl Function {
             location,
             tag,
             name: name.to_string(),
+            type_parameters: vec![],
             parameters,
             ret_typ,
             body: None,
@@ -282,13 +302,13 @@ impl Function {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum Body {
     Block(Block),
     Expr(Expr),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Block {
     pub location: Location,
     pub statements: Vec<Statement>,
@@ -309,22 +329,24 @@ impl Block {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Parameter {
+    pub location: Location,
     pub name: String,
     pub typ: ParsedType,
 }
 
 impl Parameter {
-    pub fn new<T: ToString>(name: T, typ: ParsedType) -> Parameter {
+    pub fn new<T: ToString>(location: Location, name: T, typ: ParsedType) -> Parameter {
         Parameter {
+            location,
             name: name.to_string(),
             typ,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Statement {
     pub location: Location,
     pub value: StatementVariant,
@@ -349,6 +371,12 @@ impl Statement {
             value: StatementVariant::Expression(expr),
         }
     }
+    pub fn assign(location: Location, target: Expr, value: Expr) -> Statement {
+        Statement {
+            location,
+            value: StatementVariant::Assign { target, value },
+        }
+    }
     pub fn if_block(
         location: Location,
         clauses: Vec<(Expr, Block)>,
@@ -362,9 +390,26 @@ impl Statement {
             },
         }
     }
+    pub fn for_loop<T: ToString>(
+        location: Location,
+        name: T,
+        name_location: Location,
+        iterable: Expr,
+        block: Block,
+    ) -> Statement {
+        Statement {
+            location,
+            value: StatementVariant::For {
+                name: name.to_string(),
+                name_location,
+                iterable,
+                block,
+            },
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum StatementVariant {
     /// `return 1;`
     Return(Option<Expr>),
@@ -373,14 +418,23 @@ pub enum StatementVariant {
     /// `println("hello");`
     /// `foo.bar();`
     Expression(Expr),
+    /// `foo.bar = 1;`
+    Assign { target: Expr, value: Expr },
     /// `if <condition> {} else if <condition> else {}`
     IfBlock {
         clauses: Vec<(Expr, Block)>,
         else_block: Block,
     },
+    /// `for <name> in <iterable> {}`
+    For {
+        name: String,
+        name_location: Location,
+        iterable: Expr,
+        block: Block,
+    },
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Expr {
     pub location: Location,
     pub value: ExprVariant,
@@ -420,13 +474,13 @@ impl Expr {
     pub fn list(location: Location, lst: Vec<Expr>) -> Expr {
         Expr {
             location,
-            value: ExprVariant::List(lst),
+            value: ExprVariant::List(lst.into()),
         }
     }
     pub fn func_call(location: Location, expr: Expr, args: Vec<Argument>) -> Expr {
         Expr {
             location,
-            value: ExprVariant::FuncCall(expr.into(), args),
+            value: ExprVariant::FuncCall(expr.into(), args.into()),
         }
     }
     pub fn member_access<T: ToString>(location: Location, from: Expr, access: T) -> Expr {
@@ -441,6 +495,12 @@ impl Expr {
             value: ExprVariant::Identifier(id.to_string()),
         }
     }
+    pub fn if_expr(location: Location, condition: Expr, then_expr: Expr, else_expr: Expr) -> Expr {
+        Expr {
+            location,
+            value: ExprVariant::If(condition.into(), then_expr.into(), else_expr.into()),
+        }
+    }
     pub fn class_construction<T: ToString>(
         location: Location,
         class_name: T,
@@ -448,15 +508,20 @@ impl Expr {
     ) -> Expr {
         Expr {
             location,
-            value: ExprVariant::ClassConstruction(class_name.to_string(), field_inits),
+            value: ExprVariant::ClassConstruction(class_name.to_string(), field_inits.into()),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// ExprVariant shares its subexpressions, lists, arguments and fields through `Arc`, so cloning an
+/// expression copies pointers to them rather than the whole tree, and walking it with `VisitorMut`
+/// clones any shared child it visits. Nodes are shared rather than allocated in an arena of IDs,
+/// since passes modify the AST in place through `VisitorMut`, and `elz::compile` returns it, an
+/// arena would have to be passed along with every node
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum ExprVariant {
     /// `x + y`
-    Binary(Box<Expr>, Box<Expr>, Operator),
+    Binary(Arc<Expr>, Arc<Expr>, Operator),
     /// `1.345`
     F64(f64),
     /// `1`
@@ -466,21 +531,23 @@ pub enum ExprVariant {
     /// `"str"`
     String(String),
     /// `[1, 2, 3]`
-    List(Vec<Expr>),
+    List(Arc<Vec<Expr>>),
     /// `a(b)`
-    FuncCall(Box<Expr>, Vec<Argument>),
+    FuncCall(Arc<Expr>, Arc<Vec<Argument>>),
     /// `foo.bar`, `foo.bar()`, `foo().bar`
-    MemberAccess(Box<Expr>, String),
+    MemberAccess(Arc<Expr>, String),
     /// `n`
     Identifier(String),
+    /// `if x < y { x } else { y }`, `else if` is an if expression in the else branch
+    If(Arc<Expr>, Arc<Expr>, Arc<Expr>),
     /// We can have a class construction expression: `Foo { bar: 0 }` for definition `class Foo { bar: int; }`
-    ClassConstruction(String, HashMap<String, Expr>),
+    ClassConstruction(String, Arc<HashMap<String, Expr>>),
 }
 
 /// Argument:
 ///
 /// `assert(n, equal_to: 1)`
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Argument {
     pub location: Location,
     pub name: Option<String>,
@@ -497,16 +564,77 @@ impl Argument {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum Operator {
     Plus,
+    Minus,
+    Multiply,
+    Divide,
+    /// `**`, integers and `f64` raised to a power
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
 }
 
 impl Operator {
     pub fn from_token(token: Token) -> Operator {
         match token.tk_type() {
             TkType::Plus => Operator::Plus,
+            TkType::Minus => Operator::Minus,
+            TkType::Multiple => Operator::Multiply,
+            TkType::Divide => Operator::Divide,
+            TkType::Power => Operator::Power,
+            TkType::EqualEqual => Operator::Equal,
+            TkType::NotEqual => Operator::NotEqual,
+            TkType::Less => Operator::Less,
+            TkType::LessEqual => Operator::LessEqual,
+            TkType::Greater => Operator::Greater,
+            TkType::GreaterEqual => Operator::GreaterEqual,
+            TkType::And => Operator::And,
+            TkType::Or => Operator::Or,
             tok => unimplemented!("{:?} is not a operator", tok),
         }
     }
 }
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Operator::*;
+        match self {
+            Plus => write!(f, "+"),
+            Minus => write!(f, "-"),
+            Multiply => write!(f, "*"),
+            Divide => write!(f, "/"),
+            Power => write!(f, "**"),
+            Equal => write!(f, "=="),
+            NotEqual => write!(f, "!="),
+            Less => write!(f, "<"),
+            LessEqual => write!(f, "<="),
+            Greater => write!(f, ">"),
+            GreaterEqual => write!(f, ">="),
+            And => write!(f, "and"),
+            Or => write!(f, "or"),
+        }
+    }
+}
+
+impl Operator {
+    /// is_comparison returns true for operators producing `bool`
+    pub fn is_comparison(&self) -> bool {
+        use Operator::*;
+        match self {
+            Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual => true,
+            Plus | Minus | Multiply | Divide | Power | And | Or => false,
+        }
+    }
+    /// is_logical returns true for operators skipping the right hand side by the left hand side
+    pub fn is_logical(&self) -> bool {
+        *self == Operator::And || *self == Operator::Or
+    }
+}
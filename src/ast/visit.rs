@@ -0,0 +1,334 @@
+//! visit walks the AST, `Visitor` reads it and `VisitorMut` modifies it in place. Each method
+//! walks into the children by default through the `walk` function of the node, so an override only
+//! handles the nodes it cares about, and calls the `walk` function to keep walking, e.g.
+//!
+//! ```
+//! use elz::ast::visit::{walk_expr, Visitor};
+//! use elz::ast::{Expr, ExprVariant};
+//!
+//! /// counts calls in the code
+//! struct Calls(usize);
+//!
+//! impl Visitor for Calls {
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if let ExprVariant::FuncCall(..) = expr.value {
+//!             self.0 += 1;
+//!         }
+//!         walk_expr(self, expr);
+//!     }
+//! }
+//! ```
+use super::*;
+
+pub trait Visitor: Sized {
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module)
+    }
+    fn visit_top(&mut self, top: &TopAst) {
+        walk_top(self, top)
+    }
+    fn visit_import(&mut self, _import: &Import) {}
+    fn visit_function(&mut self, f: &Function) {
+        walk_function(self, f)
+    }
+    fn visit_variable(&mut self, v: &Variable) {
+        walk_variable(self, v)
+    }
+    fn visit_class(&mut self, c: &Class) {
+        walk_class(self, c)
+    }
+    fn visit_trait(&mut self, t: &Trait) {
+        walk_trait(self, t)
+    }
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field)
+    }
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt)
+    }
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+}
+
+pub fn walk_module<V: Visitor>(visitor: &mut V, module: &Module) {
+    for top in &module.top_list {
+        visitor.visit_top(top);
+    }
+}
+
+pub fn walk_top<V: Visitor>(visitor: &mut V, top: &TopAst) {
+    match top {
+        TopAst::Import(import) => visitor.visit_import(import),
+        TopAst::Function(f) => visitor.visit_function(f),
+        TopAst::Variable(v) => visitor.visit_variable(v),
+        TopAst::Class(c) => visitor.visit_class(c),
+        TopAst::Trait(t) => visitor.visit_trait(t),
+    }
+}
+
+pub fn walk_function<V: Visitor>(visitor: &mut V, f: &Function) {
+    match &f.body {
+        Some(Body::Block(block)) => visitor.visit_block(block),
+        Some(Body::Expr(expr)) => visitor.visit_expr(expr),
+        None => (),
+    }
+}
+
+pub fn walk_variable<V: Visitor>(visitor: &mut V, v: &Variable) {
+    visitor.visit_expr(&v.expr)
+}
+
+pub fn walk_class<V: Visitor>(visitor: &mut V, c: &Class) {
+    for member in &c.members {
+        match member {
+            ClassMember::Field(field) => visitor.visit_field(field),
+            ClassMember::Method(f) | ClassMember::StaticMethod(f) => visitor.visit_function(f),
+        }
+    }
+}
+
+pub fn walk_trait<V: Visitor>(visitor: &mut V, t: &Trait) {
+    for member in &t.members {
+        match member {
+            TraitMember::Field(field) => visitor.visit_field(field),
+            TraitMember::Method(f) => visitor.visit_function(f),
+        }
+    }
+}
+
+pub fn walk_field<V: Visitor>(visitor: &mut V, field: &Field) {
+    if let Some(expr) = &field.expr {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_block<V: Visitor>(visitor: &mut V, block: &Block) {
+    for stmt in &block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: Visitor>(visitor: &mut V, stmt: &Statement) {
+    use StatementVariant::*;
+    match &stmt.value {
+        Return(Some(expr)) | Expression(expr) => visitor.visit_expr(expr),
+        Return(None) => (),
+        Variable(v) => visitor.visit_variable(v),
+        Assign { target, value } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        IfBlock {
+            clauses,
+            else_block,
+        } => {
+            for (condition, block) in clauses {
+                visitor.visit_expr(condition);
+                visitor.visit_block(block);
+            }
+            visitor.visit_block(else_block);
+        }
+        For {
+            iterable, block, ..
+        } => {
+            visitor.visit_expr(iterable);
+            visitor.visit_block(block);
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    use ExprVariant::*;
+    match &expr.value {
+        Binary(l, r, _) => {
+            visitor.visit_expr(l);
+            visitor.visit_expr(r);
+        }
+        List(exprs) => {
+            for e in exprs.iter() {
+                visitor.visit_expr(e);
+            }
+        }
+        FuncCall(f, args) => {
+            visitor.visit_expr(f);
+            for arg in args.iter() {
+                visitor.visit_expr(&arg.expr);
+            }
+        }
+        MemberAccess(from, _) => visitor.visit_expr(from),
+        If(condition, then_expr, else_expr) => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_expr);
+            visitor.visit_expr(else_expr);
+        }
+        ClassConstruction(_, field_inits) => {
+            for e in field_inits.values() {
+                visitor.visit_expr(e);
+            }
+        }
+        F64(_) | Int(_) | Bool(_) | String(_) | Identifier(_) => (),
+    }
+}
+
+pub trait VisitorMut: Sized {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module)
+    }
+    fn visit_top_mut(&mut self, top: &mut TopAst) {
+        walk_top_mut(self, top)
+    }
+    fn visit_import_mut(&mut self, _import: &mut Import) {}
+    fn visit_function_mut(&mut self, f: &mut Function) {
+        walk_function_mut(self, f)
+    }
+    fn visit_variable_mut(&mut self, v: &mut Variable) {
+        walk_variable_mut(self, v)
+    }
+    fn visit_class_mut(&mut self, c: &mut Class) {
+        walk_class_mut(self, c)
+    }
+    fn visit_trait_mut(&mut self, t: &mut Trait) {
+        walk_trait_mut(self, t)
+    }
+    fn visit_field_mut(&mut self, field: &mut Field) {
+        walk_field_mut(self, field)
+    }
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+    fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+        walk_statement_mut(self, stmt)
+    }
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+}
+
+pub fn walk_module_mut<V: VisitorMut>(visitor: &mut V, module: &mut Module) {
+    for top in &mut module.top_list {
+        visitor.visit_top_mut(top);
+    }
+}
+
+pub fn walk_top_mut<V: VisitorMut>(visitor: &mut V, top: &mut TopAst) {
+    match top {
+        TopAst::Import(import) => visitor.visit_import_mut(import),
+        TopAst::Function(f) => visitor.visit_function_mut(f),
+        TopAst::Variable(v) => visitor.visit_variable_mut(v),
+        TopAst::Class(c) => visitor.visit_class_mut(c),
+        TopAst::Trait(t) => visitor.visit_trait_mut(t),
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut>(visitor: &mut V, f: &mut Function) {
+    match &mut f.body {
+        Some(Body::Block(block)) => visitor.visit_block_mut(block),
+        Some(Body::Expr(expr)) => visitor.visit_expr_mut(expr),
+        None => (),
+    }
+}
+
+pub fn walk_variable_mut<V: VisitorMut>(visitor: &mut V, v: &mut Variable) {
+    visitor.visit_expr_mut(&mut v.expr)
+}
+
+pub fn walk_class_mut<V: VisitorMut>(visitor: &mut V, c: &mut Class) {
+    for member in &mut c.members {
+        match member {
+            ClassMember::Field(field) => visitor.visit_field_mut(field),
+            ClassMember::Method(f) | ClassMember::StaticMethod(f) => visitor.visit_function_mut(f),
+        }
+    }
+}
+
+pub fn walk_trait_mut<V: VisitorMut>(visitor: &mut V, t: &mut Trait) {
+    for member in &mut t.members {
+        match member {
+            TraitMember::Field(field) => visitor.visit_field_mut(field),
+            TraitMember::Method(f) => visitor.visit_function_mut(f),
+        }
+    }
+}
+
+pub fn walk_field_mut<V: VisitorMut>(visitor: &mut V, field: &mut Field) {
+    if let Some(expr) = &mut field.expr {
+        visitor.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+    for stmt in &mut block.statements {
+        visitor.visit_statement_mut(stmt);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut>(visitor: &mut V, stmt: &mut Statement) {
+    use StatementVariant::*;
+    match &mut stmt.value {
+        Return(Some(expr)) | Expression(expr) => visitor.visit_expr_mut(expr),
+        Return(None) => (),
+        Variable(v) => visitor.visit_variable_mut(v),
+        Assign { target, value } => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        IfBlock {
+            clauses,
+            else_block,
+        } => {
+            for (condition, block) in clauses {
+                visitor.visit_expr_mut(condition);
+                visitor.visit_block_mut(block);
+            }
+            visitor.visit_block_mut(else_block);
+        }
+        For {
+            iterable, block, ..
+        } => {
+            visitor.visit_expr_mut(iterable);
+            visitor.visit_block_mut(block);
+        }
+    }
+}
+
+/// walk_expr_mut clones every child it visits which is shared with another expression, whether
+/// the visitor changes it or not, so the other expression is never changed
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: &mut Expr) {
+    use ExprVariant::*;
+    match &mut expr.value {
+        Binary(l, r, _) => {
+            visitor.visit_expr_mut(Arc::make_mut(l));
+            visitor.visit_expr_mut(Arc::make_mut(r));
+        }
+        List(exprs) => {
+            for e in Arc::make_mut(exprs) {
+                visitor.visit_expr_mut(e);
+            }
+        }
+        FuncCall(f, args) => {
+            visitor.visit_expr_mut(Arc::make_mut(f));
+            for arg in Arc::make_mut(args) {
+                visitor.visit_expr_mut(&mut arg.expr);
+            }
+        }
+        MemberAccess(from, _) => visitor.visit_expr_mut(Arc::make_mut(from)),
+        If(condition, then_expr, else_expr) => {
+            visitor.visit_expr_mut(Arc::make_mut(condition));
+            visitor.visit_expr_mut(Arc::make_mut(then_expr));
+            visitor.visit_expr_mut(Arc::make_mut(else_expr));
+        }
+        ClassConstruction(_, field_inits) => {
+            for e in Arc::make_mut(field_inits).values_mut() {
+                visitor.visit_expr_mut(e);
+            }
+        }
+        F64(_) | Int(_) | Bool(_) | String(_) | Identifier(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests;
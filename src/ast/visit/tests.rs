@@ -0,0 +1,82 @@
+use super::*;
+use crate::lexer::TkType;
+use crate::parser::Parser;
+
+#[test]
+fn visitor_reaches_every_expression() {
+    let module = parse(
+        "module main
+    class Point {
+      x: int = 1 + 2;
+      ::new(): Point = Point {};
+    }
+    main(): void {
+      if f(3) {
+        y: int = g([4], 0);
+      }
+    }",
+    );
+    let mut ints = Ints(vec![]);
+    ints.visit_module(&module);
+    assert_eq!(ints.0, vec![1, 2, 3, 4, 0]);
+}
+
+#[test]
+fn mutable_visitor_replaces_expressions() {
+    let mut module = parse(
+        "module main
+    main(): int {
+      return 1 + f(2);
+    }",
+    );
+    Double.visit_module_mut(&mut module);
+    let mut ints = Ints(vec![]);
+    ints.visit_module(&module);
+    assert_eq!(ints.0, vec![2, 4]);
+}
+
+#[test]
+fn clones_share_subexpressions_and_mutable_visits_clone_them() {
+    let mut parser = Parser::new("", "1 + f(2)");
+    let expr = parser.parse_expression(None, None).unwrap();
+    let mut copy = expr.clone();
+    let (l, r) = match (&expr.value, &copy.value) {
+        (ExprVariant::Binary(l, _, _), ExprVariant::Binary(r, _, _)) => (l, r),
+        _ => unreachable!(),
+    };
+    assert!(Arc::ptr_eq(l, r));
+    Double.visit_expr_mut(&mut copy);
+    let mut ints = Ints(vec![]);
+    ints.visit_expr(&expr);
+    ints.visit_expr(&copy);
+    assert_eq!(ints.0, vec![1, 2, 2, 4]);
+}
+
+// helpers, must put tests before this line
+fn parse(code: &str) -> Module {
+    Parser::new("", code).parse_module(TkType::EOF).unwrap()
+}
+
+/// Ints collects integer literals in the order of walking
+struct Ints(Vec<i64>);
+
+impl Visitor for Ints {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprVariant::Int(i) = expr.value {
+            self.0.push(i);
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Double doubles integer literals
+struct Double;
+
+impl VisitorMut for Double {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let ExprVariant::Int(i) = &mut expr.value {
+            *i *= 2;
+        }
+        walk_expr_mut(self, expr);
+    }
+}
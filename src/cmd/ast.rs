@@ -0,0 +1,46 @@
+use super::compile::read_input;
+use crate::diagnostic::Reporter;
+use crate::parser::Parser;
+
+pub const CMD_NAME: &str = "ast";
+
+/// Format is the output format of `ast`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// pretty printed Rust debug representation
+    Debug,
+    /// JSON for external tools, enum variants are objects keyed by variant name, e.g.
+    /// `{"Identifier": "x"}`
+    JSON,
+}
+
+impl Format {
+    /// from_name accepts the value of `--format`, i.e. `debug` or `json`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "debug" => Some(Format::Debug),
+            "json" => Some(Format::JSON),
+            _ => None,
+        }
+    }
+}
+
+/// ast prints the parsed module of input file, prelude is not included
+pub fn ast(file: &str, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let (file, code) = read_input(file)?;
+    let module = match Parser::parse_program(file.as_str(), &code) {
+        Ok(module) => module,
+        Err(err) => {
+            let mut reporter = Reporter::new();
+            let mut file_reporter = reporter.for_file(file.as_str(), &code);
+            file_reporter.add_error(&err);
+            file_reporter.report(&reporter);
+            return Err(err.into());
+        }
+    };
+    match format {
+        Format::Debug => println!("{:#?}", module),
+        Format::JSON => println!("{}", serde_json::to_string_pretty(&module)?),
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use super::compile::check;
+use super::run::sources_to_run;
+use super::test::{failure_reason, tagged_functions};
+use crate::ast::TopAst;
+use crate::diagnostic::Reporter;
+use crate::interpreter::Interpreter;
+use crate::semantic::tag::SemanticTag;
+use crate::semantic::{Lints, TypeMap};
+use crate::timing::Timings;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+pub const CMD_NAME: &str = "bench";
+
+/// Stats are timings of iterations of a benchmark
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub mean: Duration,
+    pub median: Duration,
+}
+
+impl Stats {
+    /// of computes stats of timings, there must be at least one timing
+    pub(crate) fn of(timings: &mut [Duration]) -> Stats {
+        timings.sort();
+        let total: Duration = timings.iter().sum();
+        let middle = timings.len() / 2;
+        let median = if timings.len().is_multiple_of(2) {
+            (timings[middle - 1] + timings[middle]) / 2
+        } else {
+            timings[middle]
+        };
+        Stats {
+            mean: total / timings.len() as u32,
+            median,
+        }
+    }
+}
+
+/// bench checks the input files and runs their functions tagged `@bench` by interpreter for
+/// `iterations` times each, only benchmarks whose name contains `filter` are run, without input
+/// files the package found from the current directory is run, returns false when a benchmark fails
+pub fn bench(
+    files: Vec<&str>,
+    filter: Option<&str>,
+    iterations: u32,
+    lints: &Lints,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let (sources, bench_files) = sources_to_run(files)?;
+    let (program, expr_types) = check(
+        &mut Reporter::new(),
+        &mut Timings::disabled(),
+        sources,
+        lints,
+    )?;
+    Ok(run_benches(
+        &program,
+        &expr_types,
+        &bench_files,
+        filter,
+        iterations,
+        &mut std::io::stdout(),
+    )?)
+}
+
+/// run_benches runs every benchmark in `files` with a new interpreter, an extra iteration warms up
+/// before timing, output of a benchmark is only shown when it fails
+pub(crate) fn run_benches<W: Write>(
+    program: &[TopAst],
+    expr_types: &TypeMap,
+    files: &[String],
+    filter: Option<&str>,
+    iterations: u32,
+    out: &mut W,
+) -> std::io::Result<bool> {
+    let (benches, filtered_out) = tagged_functions(program, files, filter, |tag| tag.is_bench());
+    writeln!(out, "running {} benchmark(s)", benches.len())?;
+    let mut failed = 0;
+    for f in &benches {
+        let mut interpreter = Interpreter::new(vec![]).with_expr_types(expr_types.clone());
+        let mut timings = vec![];
+        let mut result = interpreter
+            .load(program)
+            .and_then(|_| interpreter.run_function(&f.name));
+        for _ in 0..iterations {
+            if result.is_err() {
+                break;
+            }
+            // Instant is monotonic
+            let start = Instant::now();
+            result = interpreter.run_function(&f.name);
+            timings.push(start.elapsed());
+        }
+        match result {
+            Ok(..) => {
+                let stats = Stats::of(&mut timings);
+                writeln!(
+                    out,
+                    "bench {} ... mean {:?}, median {:?} ({} iterations)",
+                    f.name, stats.mean, stats.median, iterations
+                )?;
+            }
+            Err(err) => {
+                failed += 1;
+                let output = String::from_utf8_lossy(interpreter.output()).to_string();
+                writeln!(
+                    out,
+                    "bench {} ... FAILED\n{}{}",
+                    f.name,
+                    output,
+                    failure_reason(&err)
+                )?;
+            }
+        }
+    }
+    writeln!(
+        out,
+        "\nbench result: {}. {} finished; {} failed; {} filtered out",
+        if failed == 0 { "ok" } else { "FAILED" },
+        benches.len() - failed,
+        failed,
+        filtered_out
+    )?;
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests;
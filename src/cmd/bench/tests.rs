@@ -0,0 +1,83 @@
+use super::*;
+use crate::ast::Module;
+use crate::parser::{parse_prelude, Parser};
+use crate::semantic::CallerLocations;
+
+#[test]
+fn stats_of_timings() {
+    let ms = Duration::from_millis;
+    assert_eq!(
+        Stats::of(&mut [ms(3), ms(1), ms(8)]),
+        Stats {
+            mean: ms(4),
+            median: ms(3),
+        }
+    );
+    assert_eq!(
+        Stats::of(&mut [ms(4), ms(1), ms(2), ms(9)]),
+        Stats {
+            mean: ms(4),
+            median: ms(3),
+        }
+    );
+}
+
+#[test]
+fn benchmarks_are_run_and_filtered() {
+    let code = "module main
+fib(n: int): int = if n < 2 { n } else { fib(n - 1) + fib(n - 2) };
+@bench
+fib_10(): void {
+  _: int = fib(10);
+}
+@bench
+fib_broken(): void {
+  println(\"computing\");
+  assert_eq(fib(2), 2);
+}
+@bench
+other(): void {}";
+    let (finished, output) = run_code(code, Some("fib"));
+    assert!(!finished);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "running 2 benchmark(s)");
+    assert!(lines[1].starts_with("bench fib_10 ... mean "));
+    assert!(lines[1].ends_with(" (3 iterations)"));
+    assert_eq!(
+        lines[2..],
+        [
+            "bench fib_broken ... FAILED",
+            "computing",
+            "assertion `left == right` failed",
+            "  left: 1",
+            " right: 2",
+            "test.elz:10:2",
+            "exits with code 101",
+            "",
+            "bench result: FAILED. 1 finished; 1 failed; 1 filtered out",
+        ]
+    );
+}
+
+// helpers, must put tests before this line
+/// run_code runs benchmarks of code named `test.elz` with prelude for 3 iterations
+fn run_code(code: &str, filter: Option<&str>) -> (bool, String) {
+    let mut program = parse_prelude().top_list;
+    program.extend(Parser::parse_program("test.elz", code).unwrap().top_list);
+    let modules = [Module {
+        name: "test".to_string(),
+        top_list: program.clone(),
+    }];
+    CallerLocations::new(&modules).insert(&mut program);
+    let mut out = vec![];
+    let finished = run_benches(
+        &program,
+        &TypeMap::default(),
+        &["test.elz".to_string()],
+        filter,
+        3,
+        &mut out,
+    )
+    .unwrap();
+    (finished, String::from_utf8(out).unwrap())
+}
@@ -0,0 +1,293 @@
+use super::compile::{generate, output_base};
+use crate::codegen::ir;
+#[cfg(feature = "llvm")]
+use crate::codegen::native::{self, OutputKind};
+use crate::codegen::optimize::OptLevel;
+use crate::codegen::target::Target;
+use crate::diagnostic::{MessageFormat, Reporter};
+use crate::package::{default_cache_dir, Kind, Package, Source};
+use crate::semantic::Lints;
+use crate::timing::{Timings, PROGRAM};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const CMD_NAME: &str = "build";
+
+/// C compilers tried in order to link the executable when `CC` is not set
+const LINKERS: [&str; 2] = ["cc", "clang"];
+/// WASM_LINKER links wasm modules when `WASM_LD` is not set
+const WASM_LINKER: &str = "wasm-ld";
+/// WASM_RUNTIME provides C functions of the prelude to wasm modules, since the web has no libc,
+/// it's put next to the module built
+const WASM_RUNTIME: &str = include_str!("../../lib/runtime/elz.mjs");
+/// WASM_RUNTIME_FUNCTIONS are C functions `WASM_RUNTIME` provides
+const WASM_RUNTIME_FUNCTIONS: [&str; 28] = [
+    "atan2", "atof", "ceil", "cos", "exit", "exp", "fabs", "floor", "free", "gcvt", "getenv",
+    "log", "malloc", "memcpy", "pow", "puts", "read", "realloc", "setenv", "sin", "sqrt", "strcat",
+    "strcpy", "strlen", "strncmp", "strndup", "tan", "write",
+];
+
+/// build compiles the input file to an object file, then links it with the C runtime into an
+/// executable, which is put next to the input file by default, e.g. `hello.elz` to `hello`,
+/// without input files the package found from the current directory is built, for wasm the
+/// module is put with `elz.mjs` running it, e.g. `hello.wasm`
+#[allow(clippy::too_many_arguments)]
+pub fn build(
+    files: Vec<&str>,
+    lints: &Lints,
+    opt_level: OptLevel,
+    debug_info: bool,
+    verify: bool,
+    target: Target,
+    output: Option<&str>,
+    message_format: MessageFormat,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reporter = Reporter::with_format(message_format);
+    if files.is_empty() {
+        return build_package(
+            &mut reporter,
+            timings,
+            lints,
+            opt_level,
+            debug_info,
+            verify,
+            target,
+            output,
+        );
+    }
+    let input = output_base(files[0]);
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => input.with_extension(target.executable_extension()),
+    };
+    let sources = files.into_iter().map(Source::from).collect();
+    build_files(
+        &mut reporter,
+        timings,
+        sources,
+        lints,
+        opt_level,
+        debug_info,
+        verify,
+        target,
+        &output,
+        true,
+    )
+}
+
+/// build_package compiles all sources of the package found from the current directory together,
+/// a `bin` package is linked into `target/<name>`, a `lib` package is left as `target/<name>.o`
+#[allow(clippy::too_many_arguments)]
+fn build_package(
+    reporter: &mut Reporter,
+    timings: &mut Timings,
+    lints: &Lints,
+    opt_level: OptLevel,
+    debug_info: bool,
+    verify: bool,
+    target: Target,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (package, sources) = package_sources()?;
+    let link = package.kind() == Kind::Bin;
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let output_dir = package.output_dir();
+            std::fs::create_dir_all(&output_dir)?;
+            if link {
+                output_dir
+                    .join(package.name())
+                    .with_extension(target.executable_extension())
+            } else {
+                output_dir.join(package.name()).with_extension("o")
+            }
+        }
+    };
+    build_files(
+        reporter, timings, sources, lints, opt_level, debug_info, verify, target, &output, link,
+    )
+}
+
+/// package_sources returns the package found from the current directory and source files of it
+/// and its dependencies
+pub(crate) fn package_sources() -> Result<(Package, Vec<Source>), Box<dyn std::error::Error>> {
+    let package = Package::find(&std::env::current_dir()?).map_err(|err| {
+        println!("{}", err);
+        err
+    })?;
+    let sources = package.all_sources(&default_cache_dir()).map_err(|err| {
+        println!("{}", err);
+        err
+    })?;
+    Ok((package, sources))
+}
+
+/// build_files compiles files to an object file, with `link` the object file is linked into an
+/// executable at `output`, else the object file is put at `output`
+#[allow(clippy::too_many_arguments)]
+fn build_files(
+    reporter: &mut Reporter,
+    timings: &mut Timings,
+    sources: Vec<Source>,
+    lints: &Lints,
+    opt_level: OptLevel,
+    debug_info: bool,
+    verify: bool,
+    target: Target,
+    output: &Path,
+    link: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = sources[0].path.clone();
+    let is_wasm = target.is_wasm();
+    let mut module = generate(
+        reporter, timings, sources, lints, false, opt_level, debug_info, verify, target,
+    )?;
+    if link && !is_wasm {
+        module.wrap_main();
+    }
+    let result = if link {
+        let object = std::env::temp_dir().join(format!(
+            "elz-{}-{}.o",
+            std::process::id(),
+            input.file_stem().unwrap_or_default().to_string_lossy()
+        ));
+        let result = timings
+            .time("emit", PROGRAM, || emit_object(&module, &object))
+            .and_then(|_| {
+                timings.time("link", PROGRAM, || {
+                    if is_wasm {
+                        link_wasm(&module, &object, output)
+                    } else {
+                        self::link(&object, output)
+                    }
+                })
+            });
+        let _ = std::fs::remove_file(&object);
+        result
+    } else {
+        timings.time("emit", PROGRAM, || emit_object(&module, output))
+    };
+    result.map_err(|err| {
+        println!("{}", err);
+        err.into()
+    })
+}
+
+#[cfg(feature = "llvm")]
+fn emit_object(module: &ir::Module, object: &Path) -> Result<(), String> {
+    native::emit(module, OutputKind::Object, object).map_err(|err| err.to_string())
+}
+
+/// emit_object writes textual IR and lets `llc` turn it into an object file
+#[cfg(not(feature = "llvm"))]
+fn emit_object(module: &ir::Module, object: &Path) -> Result<(), String> {
+    let ir_file = object.with_extension("ll");
+    std::fs::File::create(&ir_file)
+        .and_then(|file| module.write_llvm(io::BufWriter::new(file)))
+        .map_err(|err| format!("failed to write `{}`: {}", ir_file.display(), err))?;
+    let status = Command::new("llc")
+        .arg("-filetype=obj")
+        .arg("-relocation-model=pic")
+        .arg("-o")
+        .arg(object)
+        .arg(&ir_file)
+        .status();
+    let _ = std::fs::remove_file(&ir_file);
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("`llc` failed with {}", status)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err("`llc` not found, install LLVM or build elz with `--features llvm`".to_string())
+        }
+        Err(err) => Err(format!("failed to run `llc`: {}", err)),
+    }
+}
+
+/// link invokes the C compiler named by `CC`, or the first one of `LINKERS` found, `libm` is linked
+/// for functions of prelude `math`
+fn link(object: &Path, output: &Path) -> Result<(), String> {
+    let linkers = match std::env::var("CC") {
+        Ok(cc) => vec![cc],
+        Err(..) => LINKERS.iter().map(|l| l.to_string()).collect(),
+    };
+    for linker in &linkers {
+        let status = Command::new(linker)
+            .arg(object)
+            .arg("-lm")
+            .arg("-o")
+            .arg(output)
+            .status();
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => return Err(format!("`{}` failed with {}", linker, status)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("failed to run `{}`: {}", linker, err)),
+        }
+    }
+    Err(format!(
+        "no linker found, tried {}, set `CC` to a C compiler",
+        linkers
+            .iter()
+            .map(|l| format!("`{}`", l))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// link_wasm links the object into a wasm module exporting `main`, C functions are left as imports
+/// provided by `WASM_RUNTIME`, which is written next to the module
+fn link_wasm(module: &ir::Module, object: &Path, output: &Path) -> Result<(), String> {
+    let mut missing: Vec<&String> = module
+        .functions
+        .values()
+        .filter(|f| f.body.is_none())
+        .map(|f| &f.name)
+        // intrinsics are lowered by llc
+        .filter(|name| !name.starts_with("@llvm."))
+        .filter(|name| !WASM_RUNTIME_FUNCTIONS.contains(&name.trim_start_matches('@')))
+        .collect();
+    if !missing.is_empty() {
+        missing.sort();
+        return Err(format!(
+            "{} can't be called on wasm, the runtime only provides {}",
+            missing
+                .iter()
+                .map(|name| format!("`{}`", name.trim_start_matches('@')))
+                .collect::<Vec<_>>()
+                .join(", "),
+            WASM_RUNTIME_FUNCTIONS
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let linker = std::env::var("WASM_LD").unwrap_or_else(|_| WASM_LINKER.to_string());
+    let status = Command::new(&linker)
+        .arg("--no-entry")
+        .arg("--export=main")
+        // the runtime allocates from the end of static data
+        .arg("--export=__heap_base")
+        .arg("--allow-undefined")
+        .arg(object)
+        .arg("-o")
+        .arg(output)
+        .status();
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => return Err(format!("`{}` failed with {}", linker, status)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(format!(
+                "`{}` not found, install LLD or set `WASM_LD` to a wasm linker",
+                linker
+            ))
+        }
+        Err(err) => return Err(format!("failed to run `{}`: {}", linker, err)),
+    }
+    let runtime = output.with_file_name("elz.mjs");
+    std::fs::write(&runtime, WASM_RUNTIME)
+        .map_err(|err| format!("failed to write `{}`: {}", runtime.display(), err))
+}
@@ -0,0 +1,30 @@
+use super::build::package_sources;
+use super::compile;
+use crate::diagnostic::{MessageFormat, Reporter};
+use crate::package::Source;
+use crate::semantic::Lints;
+use crate::timing::Timings;
+
+pub const CMD_NAME: &str = "check";
+
+/// check reports errors and warnings of input files without generating code, without input files
+/// sources of the package found from the current directory are checked
+pub fn check(
+    files: Vec<&str>,
+    lints: &Lints,
+    message_format: MessageFormat,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = if files.is_empty() {
+        package_sources()?.1
+    } else {
+        files.into_iter().map(Source::from).collect()
+    };
+    compile::check(
+        &mut Reporter::with_format(message_format),
+        timings,
+        sources,
+        lints,
+    )?;
+    Ok(())
+}
@@ -1,65 +1,460 @@
-use crate::ast::{Import, TopAst};
-use crate::codegen::llvm::LLVMValue;
+use crate::ast::{Import, Module, TopAst};
+use crate::codegen::ir;
+#[cfg(feature = "llvm")]
+use crate::codegen::native::{self, OutputKind};
+use crate::codegen::optimize::OptLevel;
+use crate::codegen::target::Target;
 use crate::codegen::CodeGenerator;
-use crate::diagnostic::Reporter;
-use crate::lexer::Location;
+use crate::diagnostic::{Diagnose, Diagnostic, FileID, MessageFormat, Reporter};
+use crate::ice;
+#[cfg(feature = "llvm")]
+use crate::ice::{InternalError, Phase};
+use crate::lexer::{self, Location, TkType};
+use crate::package::Source;
 use crate::parser::{parse_prelude, Parser};
-use crate::semantic::SemanticChecker;
+use crate::semantic::{
+    derive_traits, CallerLocations, Lints, SemanticChecker, SemanticError, Severity, TypeMap,
+};
+use crate::timing::{Timings, PROGRAM};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 
 pub const CMD_NAME: &'static str = "compile";
+/// STDIN is the input file meaning standard input
+pub const STDIN: &str = "-";
+/// STDIN_NAME is the file name of standard input in diagnostics
+pub(crate) const STDIN_NAME: &str = "<stdin>";
 
-pub fn compile(files: Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut reporter = Reporter::new();
-    // FIXME: comment out code generator for now to focus on semantic checking
-    let program = check(&mut reporter, files)?;
-    let code_generator = CodeGenerator::new();
-    let module = code_generator.generate_module(&program);
-    println!("{}", module.llvm_represent());
-    Ok(())
+/// Emit is the kind of output of `compile`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    /// textual LLVM IR printed to stdout
+    LLVMIR,
+    /// object file built through LLVM, needs the `llvm` feature
+    Object,
+    /// LLVM bitcode, needs the `llvm` feature
+    Bitcode,
 }
 
-fn check(
-    reporter: &mut Reporter,
+impl Emit {
+    /// from_name accepts the value of `--emit`, i.e. `llvm-ir`, `obj` or `bc`
+    pub fn from_name(name: &str) -> Option<Emit> {
+        match name {
+            "llvm-ir" => Some(Emit::LLVMIR),
+            "obj" => Some(Emit::Object),
+            "bc" => Some(Emit::Bitcode),
+            _ => None,
+        }
+    }
+    fn extension(&self) -> &'static str {
+        match self {
+            Emit::LLVMIR => "ll",
+            Emit::Object => "o",
+            Emit::Bitcode => "bc",
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn compile(
     files: Vec<&str>,
-) -> Result<Vec<TopAst>, Box<dyn std::error::Error>> {
-    // FIXME: for now to make code simple we only handle the first input file.
-    let code = std::fs::read_to_string(files[0])?;
-    let mut file_reporter = reporter.for_file(files[0], &code);
-    let mut module = match Parser::parse_program(files[0], &code) {
-        Ok(p) => p,
-        Err(err) => {
-            file_reporter.add_diagnostic(err.location(), format!("{}", err), err.message());
-            file_reporter.report(reporter);
-            return Err(err.into());
+    lints: &Lints,
+    eliminate_dead_functions: bool,
+    opt_level: OptLevel,
+    debug_info: bool,
+    verify: bool,
+    target: Target,
+    emit: Emit,
+    output: Option<&str>,
+    message_format: MessageFormat,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = output_base(files[0]);
+    let mut reporter = Reporter::with_format(message_format);
+    let module = generate(
+        &mut reporter,
+        timings,
+        files,
+        lints,
+        eliminate_dead_functions,
+        opt_level,
+        debug_info,
+        verify,
+        target,
+    )?;
+    if emit == Emit::LLVMIR {
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        timings.time("emit", PROGRAM, || module.write_llvm(&mut out))?;
+        writeln!(out)?;
+        return Ok(out.flush()?);
+    }
+    // output is put next to the input file by default, e.g. `hello.elz` to `hello.o`
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => input.with_extension(emit.extension()),
+    };
+    timings.time("emit", PROGRAM, || emit_native(&module, emit, &output))
+}
+
+/// generate checks `files` and generates the module for `target`, optimized by `opt_level`, with
+/// `debug_info` the module carries debug info, with `verify` the module is checked to be well
+/// formed
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate<S: Into<Source>>(
+    reporter: &mut Reporter,
+    timings: &mut Timings,
+    files: Vec<S>,
+    lints: &Lints,
+    eliminate_dead_functions: bool,
+    opt_level: OptLevel,
+    debug_info: bool,
+    verify: bool,
+    target: Target,
+) -> Result<ir::Module, Box<dyn std::error::Error>> {
+    let (program, expr_types) = check_modules(reporter, timings, files, lints)?;
+    let mut module = timings.time("codegen", PROGRAM, || {
+        let mut code_generator = CodeGenerator::with_target(target).with_expr_types(expr_types);
+        if debug_info {
+            code_generator = code_generator.with_debug_info();
+        }
+        let mut module = code_generator.generate_program(&program);
+        if eliminate_dead_functions {
+            module.eliminate_dead_functions();
         }
+        module
+    });
+    timings.time("optimize", PROGRAM, || module.optimize(opt_level));
+    timings.time("type check", PROGRAM, || type_check_module(&module))?;
+    if verify {
+        timings.time("verify", PROGRAM, || verify_module(&module))?;
+    }
+    Ok(module)
+}
+
+/// type_check_module checks operand types of the IR before it's emitted, a mismatch is a bug of
+/// elz rather than of the program
+fn type_check_module(module: &ir::Module) -> Result<(), Box<dyn std::error::Error>> {
+    module.type_check().map_err(|errors| {
+        ice::report(&errors);
+        format!(
+            "aborting due to {} internal compiler error(s)",
+            errors.len()
+        )
+        .into()
+    })
+}
+
+/// verify_module checks `module` by the IR verifier, and by LLVM's verifier when elz is built
+/// with LLVM, problems are bugs of elz rather than of the program
+fn verify_module(module: &ir::Module) -> Result<(), Box<dyn std::error::Error>> {
+    if let Err(errors) = module.verify() {
+        ice::report(&errors);
+        return Err(format!(
+            "aborting due to {} internal compiler error(s)",
+            errors.len()
+        )
+        .into());
+    }
+    #[cfg(feature = "llvm")]
+    native::verify(module).map_err(|err| {
+        ice::report(&[InternalError::new(
+            Phase::Verify,
+            &Location::none(),
+            err.to_string(),
+        )]);
+        err
+    })?;
+    Ok(())
+}
+
+#[cfg(feature = "llvm")]
+fn emit_native(
+    module: &ir::Module,
+    emit: Emit,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let kind = match emit {
+        Emit::Object => OutputKind::Object,
+        Emit::Bitcode => OutputKind::Bitcode,
+        Emit::LLVMIR => unreachable!("textual IR is printed without LLVM"),
     };
-    // insert import prelude
-    module.top_list.push(TopAst::Import(Import {
+    native::emit(module, kind, output).map_err(|err| {
+        println!("{}", err);
+        err.into()
+    })
+}
+
+#[cfg(not(feature = "llvm"))]
+fn emit_native(
+    _module: &ir::Module,
+    emit: Emit,
+    _output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let err = format!(
+        "`--emit {}` needs elz built with `--features llvm`",
+        match emit {
+            Emit::Bitcode => "bc",
+            _ => "obj",
+        }
+    );
+    println!("{}", err);
+    Err(err.into())
+}
+
+/// prelude_import imports the names of prelude that every module can use
+pub(crate) fn prelude_import() -> TopAst {
+    TopAst::Import(Import {
         location: Location::none(),
         import_path: "prelude".to_string(),
         imported_component: vec![
             "int".to_string(),
+            "i32".to_string(),
+            "u8".to_string(),
             "void".to_string(),
             "f64".to_string(),
             "bool".to_string(),
             "string".to_string(),
+            "StringBuilder".to_string(),
             "List".to_string(),
+            "Option".to_string(),
+            "Map".to_string(),
+            "ToString".to_string(),
+            "Eq".to_string(),
+            "Ord".to_string(),
+            "Hash".to_string(),
+            "Iterator".to_string(),
+            "ListIterator".to_string(),
+            "io".to_string(),
+            "env".to_string(),
+            "process".to_string(),
+            "math".to_string(),
+            "print".to_string(),
             "println".to_string(),
+            "eprint".to_string(),
+            "eprintln".to_string(),
+            "panic".to_string(),
+            "assert".to_string(),
+            "assert_eq".to_string(),
         ],
-    }));
+    })
+}
+
+/// check parses every input file as a module, then checks them with prelude together, returns
+/// top list of all modules, with types of expressions inferred
+pub(crate) fn check<S: Into<Source>>(
+    reporter: &mut Reporter,
+    timings: &mut Timings,
+    files: Vec<S>,
+    lints: &Lints,
+) -> Result<(Vec<TopAst>, TypeMap), Box<dyn std::error::Error>> {
+    let (program, expr_types) = check_modules(reporter, timings, files, lints)?;
+    let top_list = program
+        .into_iter()
+        .flat_map(|module| module.top_list)
+        .collect();
+    Ok((top_list, expr_types))
+}
+
+/// check_modules is `check` keeping modules apart, prelude is the first one, for code generation,
+/// with types of expressions inferred
+pub(crate) fn check_modules<S: Into<Source>>(
+    reporter: &mut Reporter,
+    timings: &mut Timings,
+    files: Vec<S>,
+    lints: &Lints,
+) -> Result<(Vec<Module>, TypeMap), Box<dyn std::error::Error>> {
+    let sources: Vec<Source> = files.into_iter().map(|f| f.into()).collect();
+    let mut files: Vec<String> = sources
+        .iter()
+        .map(|source| source.path.to_string_lossy().to_string())
+        .collect();
+    let mut file_reporters = HashMap::new();
+    let mut program = vec![timings.time("parse", "prelude", parse_prelude)];
+    for file in &mut files {
+        let (name, code) = read_input(file)?;
+        *file = name;
+        let mut file_reporter = reporter.for_file(file.as_str(), &code);
+        let tokens = timings.time("lex", file, || lexer::lex(file.as_str(), &code));
+        // boxed since the error is large
+        let parsed = timings.time("parse", file, || {
+            Parser::from_tokens(file.as_str(), tokens)
+                .parse_module(TkType::EOF)
+                .map_err(Box::new)
+        });
+        match parsed {
+            Ok(mut module) => {
+                module.top_list.push(prelude_import());
+                program.push(module);
+            }
+            Err(err) => {
+                file_reporter.add_error(&*err);
+                file_reporter.report(reporter);
+                return Err(err.into());
+            }
+        };
+        file_reporters.insert(file.clone(), file_reporter);
+    }
+    name_dependency_modules(&sources, &mut program[1..]);
+    let caller_locations = CallerLocations::new(&program);
+    for module in &mut program {
+        derive_traits(&mut module.top_list);
+        caller_locations.insert(&mut module.top_list);
+    }
+    let report = |file_reporters: &HashMap<String, FileID>| {
+        for file in &files {
+            file_reporters[file].report(reporter);
+        }
+    };
+
+    let unknown_imports = unknown_imports(&program);
+    if !unknown_imports.is_empty() {
+        for err in &unknown_imports {
+            file_reporter_of(&mut file_reporters, &files[0], &err.location()).add_error(err);
+        }
+        report(&file_reporters);
+        return Err(format!(
+            "aborting due to {} previous error(s)",
+            unknown_imports.len()
+        )
+        .into());
+    }
 
-    let prelude = parse_prelude();
-    let mut l = prelude.top_list.clone();
-    l.append(module.top_list.as_mut());
-    let program = vec![prelude, module];
     // check program
     let mut semantic_checker = SemanticChecker::new();
-    match semantic_checker.check_program(&program) {
-        Ok(..) => Ok(l),
-        Err(err) => {
-            file_reporter.add_diagnostic(err.location(), format!("{}", err), err.message());
-            file_reporter.report(reporter);
-            Err(err.into())
+    let result = timings.time("check", PROGRAM, || {
+        semantic_checker.check_program(&program)
+    });
+    let mut denied_warnings = 0;
+    for warning in semantic_checker.warnings() {
+        // only report warnings in input files, prelude and dependencies are not controlled by
+        // users
+        let file_reporter = match files
+            .iter()
+            .position(|file| file == warning.location().file_name())
+        {
+            Some(index) if sources[index].dependency.is_none() => {
+                file_reporters.get_mut(&files[index]).unwrap()
+            }
+            _ => continue,
+        };
+        match lints.severity_of(warning) {
+            Severity::Allow => (),
+            Severity::Warning => {
+                file_reporter.add_warning(warning);
+            }
+            Severity::Error => {
+                denied_warnings += 1;
+                let diagnostic = Diagnostic {
+                    severity: Severity::Error,
+                    ..warning.diagnostic()
+                };
+                file_reporter
+                    .add(diagnostic.with_note(format!("`{}` is denied", warning.lint_name())));
+            }
+        }
+    }
+    match result {
+        Ok(..) => {
+            report(&file_reporters);
+            if denied_warnings > 0 {
+                Err(format!("aborting due to {} denied warning(s)", denied_warnings).into())
+            } else {
+                Ok((program, semantic_checker.expr_types()))
+            }
+        }
+        Err(errors) => {
+            for err in &errors {
+                file_reporter_of(&mut file_reporters, &files[0], &err.location()).add_error(err);
+            }
+            report(&file_reporters);
+            Err(format!("aborting due to {} previous error(s)", errors.len()).into())
         }
     }
 }
+
+/// read_input reads the input file, `-` is standard input named `<stdin>`, returns name and content
+/// of the input
+pub(crate) fn read_input(file: &str) -> std::io::Result<(String, String)> {
+    if file == STDIN {
+        let mut code = String::new();
+        std::io::stdin().read_to_string(&mut code)?;
+        Ok((STDIN_NAME.to_string(), code))
+    } else {
+        Ok((file.to_string(), std::fs::read_to_string(file)?))
+    }
+}
+
+/// output_base is the path that outputs of the input file are named after, e.g. `hello.elz` to
+/// `hello.o`, outputs of standard input are named after `out`
+pub(crate) fn output_base(file: &str) -> PathBuf {
+    if file == STDIN {
+        PathBuf::from("out")
+    } else {
+        PathBuf::from(file)
+    }
+}
+
+/// name_dependency_modules puts modules of dependency under the dependency, e.g. module `math` of
+/// dependency `util` is renamed to `util.math`, so are imports of it in the dependency
+pub(crate) fn name_dependency_modules(sources: &[Source], modules: &mut [Module]) {
+    let mut dependency_modules: HashMap<&String, HashSet<String>> = HashMap::new();
+    for (source, module) in sources.iter().zip(modules.iter()) {
+        if let Some(dependency) = &source.dependency {
+            dependency_modules
+                .entry(dependency)
+                .or_default()
+                .insert(module.name.clone());
+        }
+    }
+    for (source, module) in sources.iter().zip(modules.iter_mut()) {
+        let dependency = match &source.dependency {
+            Some(dependency) => dependency,
+            None => continue,
+        };
+        let module_names = &dependency_modules[dependency];
+        module.name = format!("{}.{}", dependency, module.name);
+        for top in &mut module.top_list {
+            if let TopAst::Import(import) = top {
+                if module_names.contains(&import.import_path) {
+                    import.import_path = format!("{}.{}", dependency, import.import_path);
+                }
+            }
+        }
+    }
+}
+
+/// unknown_imports returns errors of imports of modules not in the program
+pub(crate) fn unknown_imports(program: &[Module]) -> Vec<SemanticError> {
+    let module_names: HashSet<&String> = program.iter().map(|m| &m.name).collect();
+    let mut imports = vec![];
+    for module in program {
+        for top in &module.top_list {
+            match top {
+                TopAst::Import(import) if !module_names.contains(&import.import_path) => imports
+                    .push(SemanticError::no_module_named(
+                        &import.location,
+                        &import.import_path,
+                    )),
+                _ => (),
+            }
+        }
+    }
+    imports
+}
+
+/// file_reporter_of returns reporter of the file where location is, diagnostics of prelude are
+/// put on the first input file, since prelude is not a file
+fn file_reporter_of<'a>(
+    file_reporters: &'a mut HashMap<String, FileID>,
+    first_file: &str,
+    location: &Location,
+) -> &'a mut FileID {
+    let file_name = if file_reporters.contains_key(location.file_name()) {
+        location.file_name()
+    } else {
+        first_file
+    };
+    file_reporters.get_mut(file_name).unwrap()
+}
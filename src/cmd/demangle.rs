@@ -0,0 +1,21 @@
+use crate::mangle::demangle_text;
+use std::io::{self, BufRead, Write};
+
+pub const CMD_NAME: &str = "demangle";
+
+/// demangle prints the path of each symbol, e.g. `_E4main5Point3newE` is `main::Point::new`,
+/// without symbols lines of standard input are printed with their symbols demangled, e.g. a
+/// backtrace piped in, names not mangled by elz are printed as they are
+pub fn demangle(symbols: Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if symbols.is_empty() {
+        for line in io::stdin().lock().lines() {
+            writeln!(out, "{}", demangle_text(&line?))?;
+        }
+    }
+    for symbol in symbols {
+        writeln!(out, "{}", demangle_text(symbol))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use super::compile::check;
+use crate::diagnostic::Reporter;
+use crate::interpreter::Interpreter;
+use crate::semantic::Lints;
+use crate::timing::Timings;
+
+pub const CMD_NAME: &str = "eval";
+
+/// eval checks the input file and interprets its `main` with `args` without LLVM, returns the exit
+/// code of the program
+pub fn eval(
+    files: Vec<&str>,
+    args: Vec<&str>,
+    lints: &Lints,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    // the program is run as the first file, as a program built from it would be
+    let args = std::iter::once(files[0])
+        .chain(args)
+        .map(String::from)
+        .collect();
+    let (program, expr_types) =
+        check(&mut Reporter::new(), &mut Timings::disabled(), files, lints)?;
+    let mut interpreter = Interpreter::new(std::io::stdout())
+        .with_args(args)
+        .with_expr_types(expr_types);
+    match interpreter
+        .load(&program)
+        .and_then(|_| interpreter.run_main())
+    {
+        Ok(code) => Ok(code),
+        Err(err) => match err.exit_code() {
+            Some(code) => Ok(code),
+            None => {
+                println!("{}", err);
+                Err(err.into())
+            }
+        },
+    }
+}
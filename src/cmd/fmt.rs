@@ -1,42 +1,52 @@
 use crate::codegen::formatter::format_elz;
 use std::ffi::OsStr;
-use std::io::Write;
 use std::path::Path;
 use walkdir::WalkDir;
 
 pub const CMD_NAME: &'static str = "fmt";
 
-pub fn format(paths: Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
+/// format rewrites all elz files under paths to canonical style, with `check` files are only
+/// reported rather than modified
+pub fn format(paths: Vec<&str>, check: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed = 0;
     for path_str in paths {
-        let path = Path::new(path_str);
-        path.extension().map_or_else(
-            || handle_dir(path),
-            |extension| {
-                if extension == "elz" {
-                    handle_entry(path)
-                } else {
-                    handle_dir(path)
-                }
-            },
-        )?;
+        for entry in WalkDir::new(path_str).into_iter().filter_map(|e| e.ok()) {
+            if !handle_entry(entry.path(), check)? {
+                failed += 1;
+            }
+        }
     }
-    Ok(())
-}
-
-fn handle_dir(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-        handle_entry(entry.path())?;
+    if failed > 0 {
+        if check {
+            Err(format!("{} file(s) are not formatted", failed).into())
+        } else {
+            Err(format!("{} file(s) can not be formatted", failed).into())
+        }
+    } else {
+        Ok(())
     }
-    Ok(())
 }
 
-fn handle_entry(file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    if file_path.extension() == Some(OsStr::new("elz")) {
-        let code = std::fs::read_to_string(file_path)?;
-        let result = format_elz(code.to_string());
-        let mut origin_file = std::fs::File::create(file_path)?;
-        origin_file.write_all(result.as_bytes())?;
-        origin_file.write_all("\n".as_bytes())?;
+/// handle_entry returns false if the file is not formatted in check mode or can not be parsed
+fn handle_entry(file_path: &Path, check: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    if file_path.extension() != Some(OsStr::new("elz")) {
+        return Ok(true);
+    }
+    let code = std::fs::read_to_string(file_path)?;
+    let result = match format_elz(&file_path.to_string_lossy(), &code) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("{}", err);
+            return Ok(false);
+        }
+    };
+    if result == code {
+        Ok(true)
+    } else if check {
+        println!("{} is not formatted", file_path.display());
+        Ok(false)
+    } else {
+        std::fs::write(file_path, result)?;
+        Ok(true)
     }
-    Ok(())
 }
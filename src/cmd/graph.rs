@@ -0,0 +1,168 @@
+use super::build::package_sources;
+use super::compile::{name_dependency_modules, read_input};
+use crate::ast::{Module, TopAst};
+use crate::diagnostic::Reporter;
+use crate::package::Source;
+use crate::parser::Parser;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub const CMD_NAME: &str = "graph";
+
+/// Format is the output format of `graph`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// GraphViz dot, e.g. `elz graph | dot -Tsvg > modules.svg`
+    Dot,
+    /// JSON for external tools, see `ModuleGraph`
+    JSON,
+}
+
+impl Format {
+    /// from_name accepts the value of `--format`, i.e. `dot` or `json`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "dot" => Some(Format::Dot),
+            "json" => Some(Format::JSON),
+            _ => None,
+        }
+    }
+}
+
+/// ModuleGraph is the import graph of modules, e.g.
+///
+/// ```json
+/// {"modules":[{"name":"main","file":"main.elz"},{"name":"util","file":"util.elz"}],
+/// "imports":[{"from":"main","to":"util","symbols":["add","sub"]}]}
+/// ```
+///
+/// modules only imported, e.g. `prelude`, have no file
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ModuleGraph {
+    modules: Vec<ModuleNode>,
+    imports: Vec<ImportEdge>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ModuleNode {
+    name: String,
+    file: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ImportEdge {
+    from: String,
+    to: String,
+    symbols: Vec<String>,
+}
+
+impl ModuleGraph {
+    /// new collects imports of modules parsed from files, imports of a module from the same
+    /// module are merged into an edge, modules and edges are sorted by name
+    pub fn new(modules: &[(String, Module)]) -> ModuleGraph {
+        let mut nodes: BTreeMap<&String, Option<String>> = BTreeMap::new();
+        let mut edges: BTreeMap<(&String, &String), Vec<String>> = BTreeMap::new();
+        for (file, module) in modules {
+            nodes.insert(&module.name, Some(file.clone()));
+        }
+        for (_, module) in modules {
+            for top in &module.top_list {
+                if let TopAst::Import(import) = top {
+                    nodes.entry(&import.import_path).or_insert(None);
+                    let symbols = edges
+                        .entry((&module.name, &import.import_path))
+                        .or_default();
+                    for symbol in &import.imported_component {
+                        if !symbols.contains(symbol) {
+                            symbols.push(symbol.clone());
+                        }
+                    }
+                }
+            }
+        }
+        ModuleGraph {
+            modules: nodes
+                .into_iter()
+                .map(|(name, file)| ModuleNode {
+                    name: name.clone(),
+                    file,
+                })
+                .collect(),
+            imports: edges
+                .into_iter()
+                .map(|((from, to), symbols)| ImportEdge {
+                    from: from.clone(),
+                    to: to.clone(),
+                    symbols,
+                })
+                .collect(),
+        }
+    }
+
+    /// dot_represent labels every edge with the imported symbols, modules without file are drawn
+    /// dashed
+    pub fn dot_represent(&self) -> String {
+        let mut s = String::from("digraph modules {\n");
+        s.push_str("  node [shape=box, fontname=monospace];\n");
+        for module in &self.modules {
+            match &module.file {
+                Some(file) => s.push_str(&format!(
+                    "  \"{}\" [tooltip=\"{}\"];\n",
+                    escape(&module.name),
+                    escape(file)
+                )),
+                None => s.push_str(&format!("  \"{}\" [style=dashed];\n", escape(&module.name))),
+            }
+        }
+        for import in &self.imports {
+            s.push_str(&format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                escape(&import.from),
+                escape(&import.to),
+                escape(&import.symbols.join(", "))
+            ));
+        }
+        s.push('}');
+        s
+    }
+}
+
+/// graph prints the import graph of input files, without input files the package found from the
+/// current directory and its dependencies are shown
+pub fn graph(files: Vec<&str>, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = if files.is_empty() {
+        package_sources()?.1
+    } else {
+        files.into_iter().map(Source::from).collect()
+    };
+    let mut files = vec![];
+    let mut modules = vec![];
+    for source in &sources {
+        let (file, code) = read_input(&source.path.to_string_lossy())?;
+        match Parser::parse_program(file.as_str(), &code) {
+            Ok(module) => modules.push(module),
+            Err(err) => {
+                let mut reporter = Reporter::new();
+                let mut file_reporter = reporter.for_file(file.as_str(), &code);
+                file_reporter.add_error(&err);
+                file_reporter.report(&reporter);
+                return Err(err.into());
+            }
+        }
+        files.push(file);
+    }
+    name_dependency_modules(&sources, &mut modules);
+    let module_graph = ModuleGraph::new(&files.into_iter().zip(modules).collect::<Vec<_>>());
+    match format {
+        Format::Dot => println!("{}", module_graph.dot_represent()),
+        Format::JSON => println!("{}", serde_json::to_string(&module_graph)?),
+    }
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn imports_of_the_same_module_are_merged() {
+    let graph = module_graph(&[
+        (
+            "main.elz",
+            "module main
+import util (add)
+import util (sub, add)
+import prelude (println)",
+        ),
+        ("util.elz", "module util"),
+    ]);
+    assert_eq!(
+        graph.dot_represent(),
+        "digraph modules {
+  node [shape=box, fontname=monospace];
+  \"main\" [tooltip=\"main.elz\"];
+  \"prelude\" [style=dashed];
+  \"util\" [tooltip=\"util.elz\"];
+  \"main\" -> \"prelude\" [label=\"println\"];
+  \"main\" -> \"util\" [label=\"add, sub\"];
+}"
+    );
+}
+
+#[test]
+fn module_graph_to_json() {
+    let graph = module_graph(&[
+        ("main.elz", "module main\nimport util (add)"),
+        ("util.elz", "module util"),
+    ]);
+    assert_eq!(
+        serde_json::to_string(&graph).unwrap(),
+        r#"{"modules":[{"name":"main","file":"main.elz"},{"name":"util","file":"util.elz"}],"imports":[{"from":"main","to":"util","symbols":["add"]}]}"#
+    );
+}
+
+// helpers, must put tests before this line
+fn module_graph(files: &[(&str, &str)]) -> ModuleGraph {
+    let modules: Vec<(String, Module)> = files
+        .iter()
+        .map(|(file, code)| {
+            (
+                file.to_string(),
+                Parser::parse_program(*file, *code).unwrap(),
+            )
+        })
+        .collect();
+    ModuleGraph::new(&modules)
+}
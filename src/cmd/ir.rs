@@ -0,0 +1,30 @@
+use super::compile::check_modules;
+use crate::codegen::dump::IRDump;
+use crate::codegen::graph::DotGraph;
+use crate::codegen::target::Target;
+use crate::codegen::CodeGenerator;
+use crate::diagnostic::Reporter;
+use crate::semantic::Lints;
+use crate::timing::Timings;
+
+pub const CMD_NAME: &'static str = "ir";
+
+/// ir shows the IR of input file before it's printed as LLVM IR, or the control-flow graphs of
+/// functions with `graph`
+pub fn ir(files: Vec<&str>, graph: bool, target: Target) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reporter = Reporter::new();
+    let (program, expr_types) = check_modules(
+        &mut reporter,
+        &mut Timings::disabled(),
+        files,
+        &Lints::new(),
+    )?;
+    let code_generator = CodeGenerator::with_target(target).with_expr_types(expr_types);
+    let module = code_generator.generate_program(&program);
+    if graph {
+        println!("{}", module.dot_represent());
+    } else {
+        print!("{}", module.dump_represent());
+    }
+    Ok(())
+}
@@ -1,2 +1,14 @@
+pub mod ast;
+pub mod bench;
+pub mod build;
+pub mod check;
 pub mod compile;
+pub mod demangle;
+pub mod eval;
 pub mod fmt;
+pub mod graph;
+pub mod ir;
+pub mod repl;
+pub mod run;
+pub mod test;
+pub mod tokens;
@@ -0,0 +1,29 @@
+use crate::repl::{needs_more_input, Repl};
+use std::io::{self, BufRead, Write};
+
+pub const CMD_NAME: &str = "repl";
+
+/// repl evaluates input from stdin line by line until EOF, input goes on to the next line while it
+/// has unclosed `{`
+pub fn repl() -> Result<(), Box<dyn std::error::Error>> {
+    let mut repl = Repl::new(io::stdout());
+    let stdin = io::stdin();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "elz> " } else { "...> " });
+        io::stdout().flush()?;
+        if stdin.lock().read_line(&mut input)? == 0 {
+            println!();
+            return Ok(());
+        }
+        if needs_more_input(&input) {
+            continue;
+        }
+        match repl.eval(&input) {
+            Ok(Some(result)) => println!("{}", result),
+            Ok(None) => (),
+            Err(err) => println!("{}", err),
+        }
+        input.clear();
+    }
+}
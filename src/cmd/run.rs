@@ -0,0 +1,178 @@
+use super::build::package_sources;
+use super::compile::{generate, STDIN, STDIN_NAME};
+use crate::codegen::coverage::{read_counts, CoverageMap};
+use crate::codegen::ir;
+#[cfg(not(feature = "llvm"))]
+use crate::codegen::llvm::LLVMValue;
+#[cfg(feature = "llvm")]
+use crate::codegen::native;
+use crate::codegen::optimize::OptLevel;
+use crate::codegen::target::Target;
+use crate::diagnostic::Reporter;
+use crate::package::Source;
+use crate::semantic::Lints;
+use crate::timing::Timings;
+use std::sync::Mutex;
+
+pub const CMD_NAME: &str = "run";
+
+/// run checks the input file and executes its `main` right away with `args`, returns the exit code
+/// of the program, without input files sources of the package found from the current directory are
+/// run, with `coverage` lines of the input files run are reported to stderr at exit
+pub fn run(
+    files: Vec<&str>,
+    args: Vec<&str>,
+    lints: &Lints,
+    opt_level: OptLevel,
+    coverage: bool,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let (sources, run_files) = sources_to_run(files)?;
+    // the program is run as the first file, as a program built from it would be
+    let args: Vec<String> = std::iter::once(run_files.first().cloned().unwrap_or_default())
+        .chain(args.into_iter().map(String::from))
+        .collect();
+    let mut module = generate(
+        &mut Reporter::new(),
+        &mut Timings::disabled(),
+        sources,
+        lints,
+        false,
+        opt_level,
+        false,
+        false,
+        Target::host(),
+    )?;
+    if !coverage {
+        module.wrap_main();
+        return execute(&module, &args).map_err(|err| {
+            println!("{}", err);
+            err.into()
+        });
+    }
+    let counts_file = std::env::temp_dir().join(format!("elz-coverage-{}.bin", std::process::id()));
+    let counts_file = counts_file.to_string_lossy().to_string();
+    let coverage_map = module.instrument_coverage(&counts_file);
+    *PENDING_REPORT.lock().unwrap() = Some((coverage_map, run_files, counts_file));
+    // after instrumenting, so the counts are written when `main` of the program returns
+    module.wrap_main();
+    #[cfg(feature = "llvm")]
+    unsafe {
+        atexit(report_at_exit);
+    }
+    let code = execute(&module, &args);
+    report_coverage();
+    code.map_err(|err| {
+        println!("{}", err);
+        err.into()
+    })
+}
+
+/// PENDING_REPORT is the coverage report not shown yet, with the files reported and the file of
+/// counts, the program run by `--features llvm` exits elz itself when it calls `exit`, so the
+/// report is shown at exit then
+static PENDING_REPORT: Mutex<Option<(CoverageMap, Vec<String>, String)>> = Mutex::new(None);
+
+#[cfg(feature = "llvm")]
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> i32;
+}
+
+#[cfg(feature = "llvm")]
+extern "C" fn report_at_exit() {
+    report_coverage();
+}
+
+/// report_coverage shows the pending coverage report once
+fn report_coverage() {
+    let pending = match PENDING_REPORT.lock() {
+        Ok(mut pending) => pending.take(),
+        Err(..) => None,
+    };
+    if let Some((coverage_map, files, counts_file)) = pending {
+        let counts = read_counts(&counts_file);
+        let _ = std::fs::remove_file(&counts_file);
+        match counts {
+            Ok(counts) => eprint!("{}", coverage_map.report(&counts, &files)),
+            Err(err) => eprintln!("no coverage data from `{}`: {}", counts_file, err),
+        }
+    }
+}
+
+/// sources_to_run returns the input files, or sources of the package without input files, with
+/// names of the files whose functions are run, i.e. files not from dependencies
+pub(crate) fn sources_to_run(
+    files: Vec<&str>,
+) -> Result<(Vec<Source>, Vec<String>), Box<dyn std::error::Error>> {
+    let sources: Vec<Source> = if files.is_empty() {
+        package_sources()?.1
+    } else {
+        files.into_iter().map(Source::from).collect()
+    };
+    let files = sources
+        .iter()
+        .filter(|source| source.dependency.is_none())
+        .map(|source| match source.path.to_string_lossy().to_string() {
+            file if file == STDIN => STDIN_NAME.to_string(),
+            file => file,
+        })
+        .collect();
+    Ok((sources, files))
+}
+
+#[cfg(feature = "llvm")]
+fn execute(module: &ir::Module, args: &[String]) -> Result<i32, String> {
+    native::run(module, args).map_err(|err| err.to_string())
+}
+
+/// execute feeds textual IR to `lli` with `args`, the first one is taken as the program
+#[cfg(not(feature = "llvm"))]
+fn execute(module: &ir::Module, args: &[String]) -> Result<i32, String> {
+    use std::io::{self, Write};
+    use std::process::Command;
+
+    if !module.functions.contains_key("@main") {
+        return Err("no `main` function".to_string());
+    }
+    let ir_file = std::env::temp_dir().join(format!("elz-run-{}.ll", std::process::id()));
+    // `lli` calls `exit` after the entry returns, which fails since `exit` of prelude takes `int`
+    // rather than `i32`, so the wrapper exits by itself
+    let int_type = module.target.int_type();
+    let exit_code = if int_type == ir::Type::Int(32) {
+        "  call void @exit(i32 %code)\n".to_string()
+    } else {
+        format!(
+            "  %exit_code = sext i32 %code to {0}\n  call void @exit({0} %exit_code)\n",
+            int_type.llvm_represent()
+        )
+    };
+    std::fs::File::create(&ir_file)
+        .and_then(|file| {
+            let mut file = io::BufWriter::new(file);
+            module.write_llvm(&mut file)?;
+            writeln!(
+                file,
+                "define i32 @elz.run(i32 %argc, i8** %argv) {{\n  %code = call i32 @main(i32 %argc, i8** %argv)\n{}  unreachable\n}}",
+                exit_code
+            )?;
+            file.flush()
+        })
+        .map_err(|err| format!("failed to write `{}`: {}", ir_file.display(), err))?;
+    // ORC JIT of LLVM 14 `lli` crashes on unnamed globals, e.g. string literals
+    let status = Command::new("lli")
+        .arg("--jit-kind=mcjit")
+        .arg("--entry-function=elz.run")
+        .arg(format!("--fake-argv0={}", args[0]))
+        .arg(&ir_file)
+        .args(&args[1..])
+        .status();
+    let _ = std::fs::remove_file(&ir_file);
+    match status {
+        Ok(status) => status
+            .code()
+            .ok_or_else(|| format!("program is terminated by {}", status)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err("`lli` not found, install LLVM or build elz with `--features llvm`".to_string())
+        }
+        Err(err) => Err(format!("failed to run `lli`: {}", err)),
+    }
+}
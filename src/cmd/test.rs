@@ -0,0 +1,135 @@
+use super::compile::check;
+use super::run::sources_to_run;
+use crate::ast::{Function, Tag, TopAst};
+use crate::diagnostic::Reporter;
+use crate::interpreter::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::semantic::tag::SemanticTag;
+use crate::semantic::{Lints, TypeMap};
+use crate::timing::Timings;
+use std::io::Write;
+
+pub const CMD_NAME: &str = "test";
+
+/// Summary counts tests of a run
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    /// filtered_out are tests skipped by the name filter
+    pub filtered_out: usize,
+}
+
+/// test checks the input files and runs their functions tagged `@test` by interpreter, only tests
+/// whose name contains `filter` are run, without input files the package found from the current
+/// directory is tested, tests of dependencies are never run
+pub fn test(
+    files: Vec<&str>,
+    filter: Option<&str>,
+    lints: &Lints,
+) -> Result<Summary, Box<dyn std::error::Error>> {
+    let (sources, test_files) = sources_to_run(files)?;
+    let (program, expr_types) = check(
+        &mut Reporter::new(),
+        &mut Timings::disabled(),
+        sources,
+        lints,
+    )?;
+    Ok(run_tests(
+        &program,
+        &expr_types,
+        &test_files,
+        filter,
+        &mut std::io::stdout(),
+    )?)
+}
+
+/// tagged_functions returns functions in `files` with the tag whose name contains `filter`, and the
+/// number of the others with the tag, which are filtered out
+pub(crate) fn tagged_functions<'a>(
+    program: &'a [TopAst],
+    files: &[String],
+    filter: Option<&str>,
+    is_tagged: impl Fn(&Option<Tag>) -> bool,
+) -> (Vec<&'a Function>, usize) {
+    let mut functions = vec![];
+    let mut filtered_out = 0;
+    for top in program {
+        match top {
+            TopAst::Function(f)
+                if is_tagged(&f.tag) && files.iter().any(|file| file == f.location.file_name()) =>
+            {
+                match filter {
+                    Some(filter) if !f.name.contains(filter) => filtered_out += 1,
+                    _ => functions.push(f),
+                }
+            }
+            _ => (),
+        }
+    }
+    (functions, filtered_out)
+}
+
+/// run_tests runs every test in `files` with a new interpreter, so tests can't affect each other,
+/// output of a test is only shown when it fails
+pub(crate) fn run_tests<W: Write>(
+    program: &[TopAst],
+    expr_types: &TypeMap,
+    files: &[String],
+    filter: Option<&str>,
+    out: &mut W,
+) -> std::io::Result<Summary> {
+    let (tests, filtered_out) = tagged_functions(program, files, filter, |tag| tag.is_test());
+    let mut summary = Summary {
+        filtered_out,
+        ..Summary::default()
+    };
+
+    writeln!(out, "running {} test(s)", tests.len())?;
+    let mut failures = vec![];
+    for f in tests {
+        let mut interpreter = Interpreter::new(vec![]).with_expr_types(expr_types.clone());
+        let result = interpreter
+            .load(program)
+            .and_then(|_| interpreter.run_function(&f.name));
+        match result {
+            Ok(..) => {
+                summary.passed += 1;
+                writeln!(out, "test {} ... ok", f.name)?;
+            }
+            Err(err) => {
+                summary.failed += 1;
+                writeln!(out, "test {} ... FAILED", f.name)?;
+                let output = String::from_utf8_lossy(interpreter.output()).to_string();
+                failures.push((&f.name, output, failure_reason(&err)));
+            }
+        }
+    }
+    if !failures.is_empty() {
+        writeln!(out, "\nfailures:")?;
+        for (name, output, reason) in failures {
+            write!(out, "\n---- {} ----\n{}{}\n", name, output, reason)?;
+        }
+    }
+    writeln!(
+        out,
+        "\ntest result: {}. {} passed; {} failed; {} filtered out",
+        if summary.failed == 0 { "ok" } else { "FAILED" },
+        summary.passed,
+        summary.failed,
+        summary.filtered_out
+    )?;
+    Ok(summary)
+}
+
+/// failure_reason describes why the function being run fails
+pub(crate) fn failure_reason(err: &RuntimeError) -> String {
+    match err.exit_code() {
+        // e.g. failed assertion, which has shown where it failed
+        Some(code) => format!("exits with code {}", code),
+        None => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,95 @@
+use super::*;
+use crate::ast::Module;
+use crate::parser::{parse_prelude, Parser};
+use crate::semantic::CallerLocations;
+
+#[test]
+fn failed_test_shows_its_output() {
+    let code = "module main
+@test
+add_works(): void {
+  assert(1 + 2 == 3);
+}
+@test
+sub_works(): void {
+  assert_eq(3 - 2, 2);
+}
+not_test(): void {
+  panic(\"not a test\");
+}";
+    let (summary, output) = run_code(code, None);
+    assert_eq!(
+        summary,
+        Summary {
+            passed: 1,
+            failed: 1,
+            filtered_out: 0,
+        }
+    );
+    assert_eq!(
+        output,
+        "running 2 test(s)
+test add_works ... ok
+test sub_works ... FAILED
+
+failures:
+
+---- sub_works ----
+assertion `left == right` failed
+  left: 1
+ right: 2
+test.elz:8:2
+exits with code 101
+
+test result: FAILED. 1 passed; 1 failed; 0 filtered out
+"
+    );
+}
+
+#[test]
+fn tests_are_filtered_by_name() {
+    let code = "module main
+@test
+add_works(): void {}
+@test
+sub_works(): void {}";
+    let (summary, output) = run_code(code, Some("add"));
+    assert_eq!(
+        summary,
+        Summary {
+            passed: 1,
+            failed: 0,
+            filtered_out: 1,
+        }
+    );
+    assert_eq!(
+        output,
+        "running 1 test(s)
+test add_works ... ok
+
+test result: ok. 1 passed; 0 failed; 1 filtered out
+"
+    );
+}
+
+// helpers, must put tests before this line
+/// run_code runs tests of code named `test.elz` with prelude
+fn run_code(code: &str, filter: Option<&str>) -> (Summary, String) {
+    let mut program = parse_prelude().top_list;
+    program.extend(Parser::parse_program("test.elz", code).unwrap().top_list);
+    let modules = [Module {
+        name: "test".to_string(),
+        top_list: program.clone(),
+    }];
+    CallerLocations::new(&modules).insert(&mut program);
+    let mut out = vec![];
+    let summary = run_tests(
+        &program,
+        &TypeMap::default(),
+        &["test.elz".to_string()],
+        filter,
+        &mut out,
+    )
+    .unwrap();
+    (summary, String::from_utf8(out).unwrap())
+}
@@ -0,0 +1,26 @@
+use super::ast::Format;
+use super::compile::read_input;
+use crate::lexer::lex;
+
+pub const CMD_NAME: &str = "tokens";
+
+/// tokens prints tokens of input file, a token per line as `<location> <kind> <text>` for `debug`
+/// or an array of tokens for `json`, comments are not included
+pub fn tokens(file: &str, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let (file, code) = read_input(file)?;
+    let tokens = lex(file, code);
+    match format {
+        Format::Debug => {
+            for token in tokens {
+                println!(
+                    "{} {:?} {:?}",
+                    token.location(),
+                    token.tk_type(),
+                    token.value()
+                );
+            }
+        }
+        Format::JSON => println!("{}", serde_json::to_string_pretty(&tokens)?),
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! args keeps arguments of the program in a global, `main` stores `argc` and `argv` there before
+//! calling `main` of the program, and `env::args` of the prelude converts them to strings from
+//! there when it's called
+use super::ir::*;
+
+/// args_type is the type of the global, `argc` and `argv` are kept as 64 bits integers
+fn args_type() -> Type {
+    Type::Array {
+        len: 2,
+        element_type: Type::Int(64).into(),
+    }
+}
+
+/// argv_type is `i8**`, the type of `argv`
+pub(crate) fn argv_type() -> Type {
+    Type::Pointer(c_string_type().into())
+}
+
+fn c_string_type() -> Type {
+    Type::Pointer(Type::Int(8).into())
+}
+
+impl Module {
+    /// args returns the pointer to the global of arguments, which is defined by the first use, so
+    /// programs not reading arguments don't have it
+    fn args(&mut self) -> Expr {
+        let id = match self.args {
+            Some(id) => id,
+            None => {
+                let id = self.push_constant(Expr::Zero(args_type()));
+                self.args = Some(id);
+                id
+            }
+        };
+        Expr::global_id(Type::Pointer(args_type().into()), id)
+    }
+    /// store_args returns instructions storing parameters `argc` and `argv` of `main` into the
+    /// global
+    pub(crate) fn store_args(&mut self) -> Vec<Instruction> {
+        let args = self.args();
+        let mut instructions = vec![];
+        let parameters = [
+            ("argc", Type::Int(32), "sext"),
+            ("argv", argv_type(), "ptrtoint"),
+        ];
+        for (index, (name, typ, op_name)) in parameters.iter().enumerate() {
+            let value = ID::new();
+            let slot = ID::new();
+            instructions.push(Instruction::Cast {
+                id: value,
+                op_name: op_name.to_string(),
+                from: Expr::Identifier(typ.clone(), name.to_string()),
+                target_type: Type::Int(64),
+            });
+            instructions.push(Instruction::GEP {
+                id: slot,
+                load_from: args.clone(),
+                indices: vec![0, index as u64],
+            });
+            instructions.push(Instruction::Store {
+                source: Expr::local_id(Type::Int(64), value),
+                destination: slot,
+            });
+        }
+        instructions
+    }
+}
+
+impl Body {
+    /// argc loads `argc` of `main` as `ret_type`, it's 0 when `main` of the program is called
+    /// directly, e.g. on `wasm32`
+    pub(crate) fn argc(&mut self, ret_type: Type, module: &mut Module) -> Expr {
+        let argc = self.load_arg(0, module);
+        if ret_type == Type::Int(64) {
+            argc
+        } else {
+            self.cast("trunc", argc, ret_type)
+        }
+    }
+    /// argv loads the argument at `index` of `main`
+    pub(crate) fn argv(&mut self, index: Expr, module: &mut Module) -> Expr {
+        let argv = self.load_arg(1, module);
+        let argv = self.cast("inttoptr", argv, argv_type());
+        let pointer = ID::new();
+        self.push(Instruction::ElementPointer {
+            id: pointer,
+            from: argv,
+            index,
+        });
+        let id = ID::new();
+        self.push(Instruction::Load {
+            id,
+            load_from: Expr::local_id(c_string_type(), pointer),
+        });
+        Expr::local_id(c_string_type(), id)
+    }
+    fn load_arg(&mut self, index: u64, module: &mut Module) -> Expr {
+        let slot = ID::new();
+        self.push(Instruction::GEP {
+            id: slot,
+            load_from: module.args(),
+            indices: vec![0, index],
+        });
+        let id = ID::new();
+        self.push(Instruction::Load {
+            id,
+            load_from: Expr::local_id(Type::Int(64), slot),
+        });
+        Expr::local_id(Type::Int(64), id)
+    }
+}
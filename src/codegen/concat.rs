@@ -0,0 +1,64 @@
+//! concat generates a string template, or a chain of `+` starting with a string literal, of three
+//! or more pieces by appending them to a `StringBuilder` of prelude, since `string::concat` copies
+//! the string built so far at each `+`, e.g. `"{a}, {b}"` is
+//! ```ignore
+//! builder = StringBuilder::new();
+//! StringBuilder::append(builder, a.to_string());
+//! StringBuilder::append(builder, ", ");
+//! StringBuilder::append(builder, b.to_string());
+//! StringBuilder::build(builder)
+//! ```
+use super::ir::*;
+use crate::ast::{self, ExprVariant, Operator};
+use crate::mangle::method_path;
+
+impl Body {
+    /// concat_pieces generates `expr` by a `StringBuilder` when it concatenates three or more
+    /// pieces, `None` for other expressions
+    pub(crate) fn concat_pieces(&mut self, expr: &ast::Expr, module: &mut Module) -> Option<Expr> {
+        let mut pieces = vec![];
+        collect_pieces(expr, &mut pieces);
+        // `+` of strings starts with a string, the semantic checker sees both operands have the
+        // same type, so any chain with a string literal at its left is a concatenation
+        match pieces.first() {
+            Some(ast::Expr {
+                value: ExprVariant::String(_),
+                ..
+            }) => {}
+            _ => return None,
+        }
+        // templates have empty strings around values, e.g. `"{a}"` is `"" + a.to_string() + ""`
+        pieces.retain(|piece| !matches!(&piece.value, ExprVariant::String(s) if s.is_empty()));
+        if pieces.len() < 3 {
+            return None;
+        }
+        let builder = self.call(&method_path("StringBuilder", "new"), vec![], module);
+        let builder = self.own(builder, module);
+        for piece in pieces {
+            let value = self.expr_from_ast(piece, module);
+            self.call(
+                &method_path("StringBuilder", "append"),
+                vec![builder.clone(), value],
+                module,
+            );
+        }
+        let s = self.call(
+            &method_path("StringBuilder", "build"),
+            vec![builder],
+            module,
+        );
+        Some(self.own(s, module))
+    }
+}
+
+/// collect_pieces collects operands of `+` in `expr` from left to right, which are the pieces
+/// `expr` concatenates when it's a concatenation
+fn collect_pieces<'a>(expr: &'a ast::Expr, pieces: &mut Vec<&'a ast::Expr>) {
+    match &expr.value {
+        ExprVariant::Binary(lhs, rhs, Operator::Plus) => {
+            collect_pieces(lhs, pieces);
+            collect_pieces(rhs, pieces);
+        }
+        _ => pieces.push(expr),
+    }
+}
@@ -0,0 +1,275 @@
+//! coverage instruments generated code to count how many times each basic block runs, counts are
+//! written to a file when the program exits, and `CoverageMap` maps them back to source lines
+use super::ir::*;
+use crate::lexer::Location;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+/// DUMP_FUNCTION writes counters to the file given to `instrument_coverage`
+const DUMP_FUNCTION: &str = "@elz.coverage.dump";
+
+/// CoverageMap is the source locations of each counter, i.e. statements of its block, or the
+/// start of the block without statements, e.g. the branches of an if expression
+#[derive(Debug)]
+pub struct CoverageMap {
+    locations: Vec<Vec<Location>>,
+}
+
+impl Module {
+    /// instrument_coverage counts runs of basic blocks in a global array, which is written to
+    /// `counts_file` as native endian 64 bits integers when `main` returns or the program calls
+    /// `exit`, e.g. a failed assertion
+    pub fn instrument_coverage(&mut self, counts_file: &str) -> CoverageMap {
+        // sorted, so counters are numbered in the same order every time
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        names.sort();
+        let mut locations = vec![];
+        for name in &names {
+            if let Some(body) = &self.functions[name].body {
+                for block in body.blocks.iter().filter(|block| is_counted(block)) {
+                    if block.statements.is_empty() {
+                        locations.push(vec![block.label.location.clone()]);
+                    } else {
+                        locations.push(block.statements.clone());
+                    }
+                }
+            }
+        }
+        let counters_type = Type::Array {
+            len: locations.len(),
+            element_type: Type::Int(64).into(),
+        };
+        let counters_id = self.push_constant(Expr::Zero(counters_type.clone()));
+        let counters = Expr::global_id(Type::Pointer(counters_type.into()), counters_id);
+
+        let mut index = 0;
+        for name in &names {
+            let is_main = name == "@main";
+            let body = match &mut self.functions.get_mut(name).unwrap().body {
+                Some(body) => body,
+                None => continue,
+            };
+            for block in &mut body.blocks {
+                let counted = is_counted(block);
+                let mut instructions = vec![];
+                let mut rest = block.instructions.drain(..).peekable();
+                // phi must be the first instructions of a block
+                while let Some(phi) = rest.next_if(|inst| matches!(inst, Instruction::Phi { .. })) {
+                    instructions.push(phi);
+                }
+                if counted {
+                    instructions.append(&mut increment(&counters, index));
+                    index += 1;
+                }
+                for inst in rest {
+                    if matches!(&inst, Instruction::FunctionCall { func_name, .. } if func_name == "@exit")
+                    {
+                        instructions.push(call(DUMP_FUNCTION, Type::Void, vec![]));
+                    }
+                    instructions.push(inst);
+                }
+                if is_main && matches!(block.terminator, Terminator::Return(..)) {
+                    instructions.push(call(DUMP_FUNCTION, Type::Void, vec![]));
+                }
+                block.instructions = instructions;
+            }
+            body.number();
+        }
+        self.push_dump_function(counts_file, counters, index);
+        CoverageMap { locations }
+    }
+
+    /// push_dump_function defines the function writing `count` counters to `counts_file`, through
+    /// `creat` and `write` of POSIX, which fail quietly if the file can't be created
+    fn push_dump_function(&mut self, counts_file: &str, counters: Expr, count: usize) {
+        let int_type = self.target.int_type();
+        let c_string = Type::Pointer(Type::Int(8).into());
+        for (name, parameters, ret_typ) in [
+            (
+                "@creat",
+                vec![("path", c_string.clone()), ("mode", Type::Int(32))],
+                Type::Int(32),
+            ),
+            // as the prelude declares it, so it's the same function when the program prints
+            (
+                "@write",
+                vec![
+                    ("fd", int_type.clone()),
+                    ("buffer", c_string.clone()),
+                    ("size", int_type.clone()),
+                ],
+                int_type.clone(),
+            ),
+            ("@close", vec![("fd", Type::Int(32))], Type::Int(32)),
+        ] {
+            self.functions
+                .entry(name.to_string())
+                .or_insert_with(|| Function {
+                    location: Location::none(),
+                    name: name.to_string(),
+                    parameters: parameters
+                        .into_iter()
+                        .map(|(name, typ)| (name.to_string(), typ))
+                        .collect(),
+                    ret_typ,
+                    body: None,
+                    inline: false,
+                });
+        }
+
+        let path = Expr::CString(counts_file.to_string());
+        let path_type = Type::Pointer(path.type_().into());
+        let path_id = self.push_constant(path);
+        let path_pointer = ID::new();
+        let mut instructions = vec![Instruction::GEP {
+            id: path_pointer,
+            load_from: Expr::global_id(path_type, path_id),
+            indices: vec![0, 0],
+        }];
+        let creat = call(
+            "@creat",
+            Type::Int(32),
+            vec![
+                Expr::local_id(c_string.clone(), path_pointer),
+                // rw-r--r--
+                Expr::Int(Type::Int(32), 0o644),
+            ],
+        );
+        let fd = match &creat {
+            Instruction::FunctionCall { id, .. } => Expr::local_id(Type::Int(32), *id),
+            _ => unreachable!(),
+        };
+        instructions.push(creat);
+        let fd_of_write = match &int_type {
+            Type::Int(32) => fd.clone(),
+            _ => {
+                let id = ID::new();
+                instructions.push(Instruction::Cast {
+                    id,
+                    op_name: "sext".to_string(),
+                    from: fd.clone(),
+                    target_type: int_type.clone(),
+                });
+                Expr::local_id(int_type.clone(), id)
+            }
+        };
+        let buffer = ID::new();
+        instructions.push(Instruction::BitCast {
+            id: buffer,
+            from: counters,
+            target_type: c_string.clone(),
+        });
+        let size = Expr::Int(int_type.clone(), (count * 8) as i64);
+        instructions.push(call(
+            "@write",
+            int_type,
+            vec![fd_of_write, Expr::local_id(c_string, buffer), size],
+        ));
+        instructions.push(call("@close", Type::Int(32), vec![fd]));
+        let mut body = Body::with_blocks(vec![BasicBlock {
+            label: Label::new(ID::new(), &Location::none()),
+            instructions,
+            terminator: Terminator::Return(None),
+            statements: vec![],
+        }]);
+        body.number();
+        self.push_function(Function {
+            location: Location::none(),
+            name: DUMP_FUNCTION.to_string(),
+            parameters: vec![],
+            ret_typ: Type::Void,
+            body: Some(body),
+            inline: false,
+        });
+    }
+}
+
+impl CoverageMap {
+    /// report shows how many lines of each file in `files` run, and lines never run, a line runs
+    /// if any block with it runs
+    pub fn report(&self, counts: &[u64], files: &[String]) -> String {
+        let mut s = String::new();
+        for file in files {
+            let mut lines: BTreeMap<u32, u64> = BTreeMap::new();
+            for (locations, count) in self.locations.iter().zip(counts) {
+                for location in locations.iter().filter(|l| l.file_name() == file) {
+                    let line_count = lines.entry(location.line()).or_default();
+                    *line_count = (*line_count).max(*count);
+                }
+            }
+            if lines.is_empty() {
+                continue;
+            }
+            let not_covered: Vec<String> = lines
+                .iter()
+                .filter(|(_, count)| **count == 0)
+                .map(|(line, _)| line.to_string())
+                .collect();
+            let covered = lines.len() - not_covered.len();
+            s.push_str(&format!(
+                "{}: {}/{} lines covered ({:.1}%)\n",
+                file,
+                covered,
+                lines.len(),
+                covered as f64 * 100.0 / lines.len() as f64
+            ));
+            if !not_covered.is_empty() {
+                s.push_str(&format!("  not covered: {}\n", not_covered.join(", ")));
+            }
+        }
+        s
+    }
+}
+
+/// read_counts reads counters written by the instrumented program
+pub fn read_counts(counts_file: &str) -> std::io::Result<Vec<u64>> {
+    let bytes = std::fs::read(counts_file)?;
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// is_counted is false for the block after branches which all return, it never runs, and
+/// counting it would report its line as not covered
+fn is_counted(block: &BasicBlock) -> bool {
+    !(block.instructions.is_empty() && block.terminator == Terminator::Unreachable)
+}
+
+/// increment adds 1 to the counter at `index`
+fn increment(counters: &Expr, index: usize) -> Vec<Instruction> {
+    let counter = ID::new();
+    let count = ID::new();
+    let sum = ID::new();
+    let counter_type = Type::Int(64);
+    vec![
+        Instruction::GEP {
+            id: counter,
+            load_from: counters.clone(),
+            indices: vec![0, index as u64],
+        },
+        Instruction::Load {
+            id: count,
+            load_from: Expr::local_id(counter_type.clone(), counter),
+        },
+        Instruction::BinaryOperation {
+            id: sum,
+            op_name: "add".to_string(),
+            lhs: Expr::local_id(counter_type.clone(), count),
+            rhs: Expr::Int(counter_type.clone(), 1),
+        },
+        Instruction::Store {
+            source: Expr::local_id(counter_type, sum),
+            destination: counter,
+        },
+    ]
+}
+
+fn call(func_name: &str, ret_type: Type, args_expr: Vec<Expr>) -> Instruction {
+    Instruction::FunctionCall {
+        id: ID::new(),
+        func_name: func_name.to_string(),
+        ret_type: ret_type.into(),
+        args_expr,
+    }
+}
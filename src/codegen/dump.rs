@@ -0,0 +1,284 @@
+use super::ir;
+
+/// IRDump shows IR in its own structure rather than LLVM text, e.g. the layout of types, the
+/// source line of blocks, and the ID of every value, for debugging the compiler
+pub trait IRDump {
+    fn dump_represent(&self) -> String;
+}
+
+impl IRDump for ir::Module {
+    fn dump_represent(&self) -> String {
+        let mut s = format!("target {}\n", self.target.triple);
+        let mut type_names: Vec<&String> = self.types.keys().collect();
+        type_names.sort();
+        for name in type_names {
+            let typ = &self.types[name];
+            s.push_str(&format!(
+                "\ntype {} (size {}, align {})\n",
+                name,
+                typ.size(&self.target),
+                typ.align(&self.target)
+            ));
+            if let ir::Type::Struct { fields, .. } = typ {
+                for (field, offset) in fields.iter().zip(typ.field_offsets(&self.target)) {
+                    s.push_str(&format!(
+                        "  {}: {} (offset {})\n",
+                        field.name,
+                        field.typ.dump_represent(),
+                        offset
+                    ));
+                }
+            }
+        }
+        if !self.variables.is_empty() {
+            s.push('\n');
+        }
+        for v in &self.variables {
+            s.push_str(&v.dump_represent());
+            s.push('\n');
+        }
+        let mut function_names: Vec<&String> = self.functions.keys().collect();
+        function_names.sort();
+        for name in function_names {
+            s.push('\n');
+            s.push_str(&self.functions[name].dump_represent());
+        }
+        s
+    }
+}
+
+impl IRDump for ir::Variable {
+    fn dump_represent(&self) -> String {
+        let name = match &self.name {
+            ir::GlobalName::ID(id) => format!("@{}", id),
+            ir::GlobalName::String(name) => name.clone(),
+        };
+        format!(
+            "global {}: {} = {}",
+            name,
+            self.expr.type_().dump_represent(),
+            self.expr.dump_represent()
+        )
+    }
+}
+
+impl IRDump for ir::Function {
+    fn dump_represent(&self) -> String {
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(name, typ)| format!("%{}: {}", name, typ.dump_represent()))
+            .collect();
+        let signature = format!(
+            "{}({}): {}",
+            self.name,
+            parameters.join(", "),
+            self.ret_typ.dump_represent()
+        );
+        let body = match &self.body {
+            Some(body) => body,
+            None => return format!("declare {}\n", signature),
+        };
+        let mut s = format!("function {}\n", signature);
+        for (index, block) in body.blocks.iter().enumerate() {
+            // entry block starts from the function
+            let (kind, line) = if index == 0 {
+                ("entry, ", self.location.line())
+            } else {
+                ("", block.label.location.line())
+            };
+            s.push_str(&format!(
+                "  block %{} ({}line {})\n",
+                block.label.id, kind, line
+            ));
+            for inst in &block.instructions {
+                s.push_str(&format!("    {}\n", inst.dump_represent()));
+            }
+            s.push_str(&format!("    {}\n", block.terminator.dump_represent()));
+        }
+        s
+    }
+}
+
+impl IRDump for ir::Instruction {
+    fn dump_represent(&self) -> String {
+        use ir::Instruction::*;
+        match self {
+            GEP {
+                id,
+                load_from,
+                indices,
+            } => {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                format!(
+                    "%{} = gep {} [{}]",
+                    id,
+                    load_from.dump_represent(),
+                    indices.join(", ")
+                )
+            }
+            FunctionCall {
+                id,
+                func_name,
+                ret_type,
+                args_expr,
+            } => {
+                let args: Vec<String> = args_expr.iter().map(|e| e.dump_represent()).collect();
+                let call = format!("call {}({})", func_name, args.join(", "));
+                if **ret_type == ir::Type::Void {
+                    call
+                } else {
+                    format!("%{} = {}: {}", id, call, ret_type.dump_represent())
+                }
+            }
+            BinaryOperation {
+                id,
+                op_name,
+                lhs,
+                rhs,
+            } => format!(
+                "%{} = {} {}, {}",
+                id,
+                op_name,
+                lhs.dump_represent(),
+                rhs.dump_represent()
+            ),
+            Malloca { id, size } => {
+                format!("%{} = malloc {}", id, size.dump_represent())
+            }
+            Alloca { id, typ } => format!("%{} = alloca {}", id, typ.dump_represent()),
+            BitCast {
+                id,
+                from,
+                target_type,
+            } => format!(
+                "%{} = bitcast {} to {}",
+                id,
+                from.dump_represent(),
+                target_type.dump_represent()
+            ),
+            Cast {
+                id,
+                op_name,
+                from,
+                target_type,
+            } => format!(
+                "%{} = {} {} to {}",
+                id,
+                op_name,
+                from.dump_represent(),
+                target_type.dump_represent()
+            ),
+            ElementPointer { id, from, index } => format!(
+                "%{} = element {}[{}]",
+                id,
+                from.dump_represent(),
+                index.dump_represent()
+            ),
+            Load { id, load_from } => {
+                format!("%{} = load {}", id, load_from.dump_represent())
+            }
+            Store {
+                source,
+                destination,
+            } => format!("store {} to %{}", source.dump_represent(), destination),
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => format!(
+                "memcpy {} to {}, {}",
+                source.dump_represent(),
+                destination.dump_represent(),
+                size.dump_represent()
+            ),
+            Phi { id, typ, incoming } => {
+                let incoming: Vec<String> = incoming
+                    .iter()
+                    .map(|(e, label)| format!("{} from %{}", e.dump_represent(), label.id))
+                    .collect();
+                format!(
+                    "%{} = phi {} [{}]",
+                    id,
+                    typ.dump_represent(),
+                    incoming.join(", ")
+                )
+            }
+            DebugLocation(location) => format!("; {}", location),
+        }
+    }
+}
+
+impl IRDump for ir::Terminator {
+    fn dump_represent(&self) -> String {
+        use ir::Terminator::*;
+        match self {
+            Return(None) => "return".to_string(),
+            Return(Some(e)) => format!("return {}", e.dump_represent()),
+            Branch {
+                cond,
+                if_true,
+                if_false,
+            } => format!(
+                "branch {} then %{} else %{}",
+                cond.dump_represent(),
+                if_true.id,
+                if_false.id
+            ),
+            Goto(label) => format!("goto %{}", label.id),
+            Switch {
+                value,
+                default,
+                cases,
+            } => {
+                let cases: Vec<String> = cases
+                    .iter()
+                    .map(|(case, label)| format!("{} then %{}", case, label.id))
+                    .collect();
+                format!(
+                    "switch {} [{}] else %{}",
+                    value.dump_represent(),
+                    cases.join(", "),
+                    default.id
+                )
+            }
+            Unreachable => "unreachable".to_string(),
+        }
+    }
+}
+
+impl IRDump for ir::Expr {
+    fn dump_represent(&self) -> String {
+        use ir::Expr::*;
+        match self {
+            Int(_, i) => i.to_string(),
+            F64(f) => format!("{:?}", f),
+            Bool(b) => b.to_string(),
+            CString(s) => format!("{:?}", s),
+            Identifier(_, name) => format!("%{}", name),
+            LocalIdentifier(_, id) => format!("%{}", id),
+            GlobalIdentifier(_, id) => format!("@{}", id),
+            Zero(_) => "zeroinitializer".to_string(),
+            Struct(_, values) => {
+                let values: Vec<String> = values.iter().map(|v| v.dump_represent()).collect();
+                format!("{{{}}}", values.join(", "))
+            }
+        }
+    }
+}
+
+impl IRDump for ir::Type {
+    fn dump_represent(&self) -> String {
+        use ir::Type::*;
+        match self {
+            Void => "void".to_string(),
+            Int(bits) => format!("i{}", bits),
+            Float(bits) => format!("f{}", bits),
+            Pointer(element_type) => format!("*{}", element_type.dump_represent()),
+            Array { len, element_type } => {
+                format!("[{}; {}]", element_type.dump_represent(), len)
+            }
+            Struct { name, .. } | Named(name) => name.clone(),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! erasure compiles a generic class once for all of its type arguments. Values of type parameters
+//! are erased to 64-bit slots, which fit a value of any type, so `List[u8]` and `List[string]`
+//! share the code of `List`. Arguments are erased where a generic method is called, and results
+//! are restored to the types the semantic checker inferred for the calls, e.g. `int` for
+//! `List::get(numbers, 0)` of `numbers: List[int]`.
+//!
+//! An erased argument of a class takes a reference to the instance, e.g. the one pushed to a list.
+//! It's never released, since the type of a slot is unknown where the slot is dropped.
+use super::ir::*;
+use crate::ast::{self, Class, ClassMember, ParsedType, TypeParameter};
+use crate::lexer::Location;
+use crate::mangle::method_path;
+
+/// ERASED_TYPE names the type of erased values in signatures of generic classes, an `i64`
+pub(crate) const ERASED_TYPE: &str = "_erased";
+
+/// ErasedSignature records which parameters of a generic method and whether its result are erased
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ErasedSignature {
+    pub(crate) parameters: Vec<bool>,
+    pub(crate) ret: bool,
+}
+
+impl ErasedSignature {
+    /// of returns the signature of `f`, `None` when it has nothing erased
+    fn of(f: &ast::Function, type_parameters: &[TypeParameter]) -> Option<ErasedSignature> {
+        let is_erased = |typ: &ParsedType| type_parameters.iter().any(|p| p.name == typ.name());
+        let signature = ErasedSignature {
+            parameters: f.parameters.iter().map(|p| is_erased(&p.typ)).collect(),
+            ret: is_erased(&f.ret_typ),
+        };
+        if signature.ret || signature.parameters.contains(&true) {
+            Some(signature)
+        } else {
+            None
+        }
+    }
+}
+
+/// erase_class replaces type parameters in fields and method signatures of the generic class with
+/// the erased type
+pub(crate) fn erase_class(c: &Class) -> Class {
+    let erase = |typ: &mut ParsedType| {
+        if c.type_parameters.iter().any(|p| p.name == typ.name()) {
+            *typ = ParsedType::type_name(ERASED_TYPE);
+        }
+    };
+    let mut c = c.clone();
+    for member in &mut c.members {
+        match member {
+            ClassMember::Field(field) => erase(&mut field.typ),
+            ClassMember::Method(f) | ClassMember::StaticMethod(f) => {
+                for p in &mut f.parameters {
+                    erase(&mut p.typ);
+                }
+                erase(&mut f.ret_typ);
+            }
+        }
+    }
+    c
+}
+
+impl Module {
+    /// inferred_type returns the type the semantic checker inferred for the expression at
+    /// `location`, e.g. `List` of `List[int]`, `None` for a type parameter not bound, which is shown
+    /// as `'1`
+    pub(crate) fn inferred_type(&self, location: &Location) -> Option<Type> {
+        let name = self.expr_types.type_of(location)?;
+        if name.starts_with('\'') || name.starts_with('(') {
+            return None;
+        }
+        let name = name.split('[').next().unwrap_or(name);
+        // a value of a trait is one of a type unknown here, e.g. an element of `List[Ord]`
+        if self.traits.contains(name) {
+            return None;
+        }
+        Some(Type::from_ast(&ParsedType::type_name(name), &self.target))
+    }
+    /// remember_erased remembers what the static method of the generic class erases
+    pub(crate) fn remember_erased(&mut self, c: &Class, f: &ast::Function) {
+        if let Some(signature) = ErasedSignature::of(f, &c.type_parameters) {
+            self.erased_signatures
+                .insert(method_path(&c.name, &f.name), signature);
+        }
+    }
+}
+
+impl Body {
+    /// erase converts `value` to a slot, a class instance is taken by the slot
+    pub(crate) fn erase(&mut self, value: Expr, module: &mut Module) -> Expr {
+        let value = self.take(value, module);
+        let op_name = match value.type_() {
+            Type::Int(64) => return value,
+            // bool and u8 are unsigned
+            Type::Int(1) | Type::Int(8) => "zext",
+            Type::Int(..) => "sext",
+            Type::Float(..) => "bitcast",
+            Type::Pointer(..) => "ptrtoint",
+            typ => unreachable!("value of `{:?}` can't be erased", typ),
+        };
+        self.cast(op_name, value, Type::Int(64))
+    }
+    /// restore converts the slot `value` to the type of the expression at `location`, a class
+    /// instance gets a new reference, as results of calls do
+    pub(crate) fn restore(
+        &mut self,
+        value: Expr,
+        location: &Location,
+        module: &mut Module,
+    ) -> Expr {
+        let typ = match module.inferred_type(location) {
+            Some(typ) => typ,
+            // the type is unknown in the generic class itself, where the slot is kept as is
+            None => return value,
+        };
+        let value = self.unerase(value, &typ);
+        // the borrowed instance is retained
+        let value = self.take(value, module);
+        self.own(value, module)
+    }
+    /// unerase converts the slot `value` to `typ`, a class instance is borrowed from the slot
+    pub(crate) fn unerase(&mut self, value: Expr, typ: &Type) -> Expr {
+        let op_name = match typ {
+            Type::Int(64) => return value,
+            Type::Int(..) => "trunc",
+            Type::Float(..) => "bitcast",
+            Type::Pointer(..) => "inttoptr",
+            _ => return value,
+        };
+        self.cast(op_name, value, typ.clone())
+    }
+    /// call_builtin generates the code of the builtin static method of prelude in place of a
+    /// call, arguments of generic methods are erased as for other generic methods, conversions
+    /// convert to `ret_type`
+    pub(crate) fn call_builtin(
+        &mut self,
+        name: &str,
+        args: Vec<Expr>,
+        ret_type: Type,
+        module: &mut Module,
+    ) -> Expr {
+        match (name, args.as_slice()) {
+            ("List::_load", [buffer, index]) => {
+                let slot = self.slot_pointer(buffer, index);
+                let id = ID::new();
+                self.push(Instruction::Load {
+                    id,
+                    load_from: Expr::local_id(Type::Int(64), slot),
+                });
+                Expr::local_id(Type::Int(64), id)
+            }
+            ("List::_store", [buffer, index, value]) => {
+                let slot = self.slot_pointer(buffer, index);
+                self.push(Instruction::Store {
+                    source: value.clone(),
+                    destination: slot,
+                });
+                Expr::local_id(Type::Void, ID::new())
+            }
+            ("string::_offset", [bytes, index]) => {
+                let id = ID::new();
+                self.push(Instruction::ElementPointer {
+                    id,
+                    from: bytes.clone(),
+                    index: index.clone(),
+                });
+                Expr::local_id(bytes.type_(), id)
+            }
+            ("string::_byte_at", [bytes, index]) => {
+                let pointer = ID::new();
+                self.push(Instruction::ElementPointer {
+                    id: pointer,
+                    from: bytes.clone(),
+                    index: index.clone(),
+                });
+                let id = ID::new();
+                self.push(Instruction::Load {
+                    id,
+                    load_from: Expr::local_id(Type::Int(8), pointer),
+                });
+                Expr::local_id(Type::Int(8), id)
+            }
+            ("string::_is_null", [bytes]) => {
+                let id = ID::new();
+                self.push(Instruction::BinaryOperation {
+                    id,
+                    op_name: "icmp eq".to_string(),
+                    lhs: bytes.clone(),
+                    rhs: Expr::Zero(bytes.type_()),
+                });
+                Expr::local_id(Type::Int(1), id)
+            }
+            ("int::to_f64", [n]) => self.cast("sitofp", n.clone(), ret_type),
+            // `int` is `i32` on 32 bits targets
+            ("i32::to_int", [n]) if n.type_() == ret_type => n.clone(),
+            ("i32::to_int", [n]) => self.cast("sext", n.clone(), ret_type),
+            ("u8::to_int", [b]) => self.cast("zext", b.clone(), ret_type),
+            ("f64::to_int", [x]) => self.cast("fptosi", x.clone(), ret_type),
+            ("List::_compare", [_, _]) => {
+                self.call_element_method("compare", args, ret_type, module)
+            }
+            ("Map::_hash", [_]) => self.call_element_method("hash", args, ret_type, module),
+            ("Map::_equals", [_, _]) => self.call_element_method("eq", args, ret_type, module),
+            ("env::_argc", []) => self.argc(ret_type, module),
+            ("env::_argv", [index]) => self.argv(index.clone(), module),
+            _ => unreachable!("no builtin function `{}` takes {:?}", name, args),
+        }
+    }
+    /// slot_pointer points to the slot at `index` of `buffer`, which is an `i8*`
+    fn slot_pointer(&mut self, buffer: &Expr, index: &Expr) -> ID {
+        let slots_type = Type::Pointer(Type::Int(64).into());
+        let slots = ID::new();
+        self.push(Instruction::BitCast {
+            id: slots,
+            from: buffer.clone(),
+            target_type: slots_type.clone(),
+        });
+        let id = ID::new();
+        self.push(Instruction::ElementPointer {
+            id,
+            from: Expr::local_id(slots_type, slots),
+            index: index.clone(),
+        });
+        id
+    }
+    pub(crate) fn cast(&mut self, op_name: &str, from: Expr, target_type: Type) -> Expr {
+        let id = ID::new();
+        self.push(Instruction::Cast {
+            id,
+            op_name: op_name.to_string(),
+            from,
+            target_type: target_type.clone(),
+        });
+        Expr::local_id(target_type, id)
+    }
+}
@@ -0,0 +1,103 @@
+//! for_loop generates `for x in iterable { ... }`, which calls `next` of the iterator and runs the
+//! body with the value, until `next` returns none. A list is iterated by its `List::iter`.
+//!
+//! The iterator and the loop variable are held by the loop rather than owned by a statement, since
+//! temporaries are released after each statement of the body, a `return` in the body releases them
+//! as well.
+use super::ir::*;
+use crate::ast;
+use crate::lexer::Location;
+use crate::mangle::method_path;
+
+impl Body {
+    pub(crate) fn for_loop(
+        &mut self,
+        name: &str,
+        name_location: &Location,
+        iterable: &ast::Expr,
+        block: &ast::Block,
+        module: &mut Module,
+    ) {
+        let mut iterator = self.expr_from_ast(iterable, module);
+        if module.class_of(&iterator.type_()) == "List" {
+            iterator = self.call(&method_path("List", "iter"), vec![iterator], module);
+            iterator = self.own(iterator, module);
+        }
+        let iterator = self.take(iterator, module);
+        self.release_temporaries(module);
+        self.held.push(iterator.clone());
+
+        let next_label = Label::new(ID::new(), &iterable.location);
+        let body_label = Label::new(ID::new(), &block.location);
+        let leave_label = Label::new(ID::new(), name_location);
+        self.start_block(next_label.clone());
+        let next = method_path(&module.class_of(&iterator.type_()), "next");
+        let option = self.call(&next, vec![iterator.clone()], module);
+        let is_some = self.call(
+            &method_path("Option", "is_some"),
+            vec![option.clone()],
+            module,
+        );
+        self.terminate(Terminator::Branch {
+            cond: is_some,
+            if_true: body_label.clone(),
+            if_false: leave_label.clone(),
+        });
+
+        // the option isn't none here, so no location is printed
+        self.start_block(body_label);
+        let location = self.expr_from_ast(&ast::Expr::string(Location::none(), ""), module);
+        let value = self.call(
+            &method_path("Option", "unwrap"),
+            vec![option.clone(), location],
+            module,
+        );
+        let value = self.restore(value, name_location, module);
+        let value = self.take(value, module);
+        self.release_temporaries(module);
+        self.release(option.clone(), module);
+        self.held.push(value.clone());
+        let shadowed = self
+            .variables
+            .insert(name.to_string(), LocalVariable::Value(value.clone()));
+        self.generate_instructions(&block.statements, module);
+        match shadowed {
+            Some(variable) => self.variables.insert(name.to_string(), variable),
+            None => self.variables.remove(name),
+        };
+        self.held.pop();
+        // a body ending with `return` released what it held
+        if self.current_block.is_some() {
+            self.release_held_value(value, module);
+        }
+        self.goto(&next_label);
+
+        self.start_block(leave_label);
+        self.release(option, module);
+        self.held.pop();
+        self.release_held_value(iterator, module);
+    }
+    /// call calls the function or method at `path`, e.g. `Option::is_some`, with `args`
+    pub(crate) fn call(&mut self, path: &str, args_expr: Vec<Expr>, module: &Module) -> Expr {
+        let ret_type = module.known_functions[path].clone();
+        let id = ID::new();
+        self.push(Instruction::FunctionCall {
+            id,
+            func_name: format!("@{}", module.function_symbol(path)),
+            ret_type: ret_type.clone().into(),
+            args_expr,
+        });
+        Expr::local_id(ret_type, id)
+    }
+    /// release_held releases values held by loops and locals, before returning from inside of them
+    pub(crate) fn release_held(&mut self, module: &mut Module) {
+        for value in self.held.clone() {
+            self.release_held_value(value, module);
+        }
+    }
+    pub(crate) fn release_held_value(&mut self, value: Expr, module: &mut Module) {
+        if module.is_refcounted(&value.type_()) {
+            self.release(value, module);
+        }
+    }
+}
@@ -1,236 +1,556 @@
-pub fn format_elz(code: String) -> String {
-    let mut indent = 0i32;
-    let mut comment_line = false;
-    let mut module_line = false;
-    let mut extern_line = false;
-    let mut semicolon_symbol = false;
-    let mut past_symbol = false;
-    let mut s = String::from("");
-    let clear_multiple_blank = code
-        .replace("  ", "")
-        .replace("\r", "")
-        .replace("\n", "\\n");
-    let code_to_char: Vec<&str> = clear_multiple_blank.split("").collect();
+use crate::ast::*;
+use crate::lexer::{self, Location, TkType, Token};
+use crate::parser::precedence::{is_operator_right_associative, operator_precedence};
+use crate::parser::{ParseError, Parser};
+use std::collections::VecDeque;
 
-    let mut i = 0;
-    while i < code_to_char.len() {
-        if comment_line {
-            if code_to_char[i] == "\\" && code_to_char[i + 1] == "n" {
-                s.push_str("\n");
-                s.push_str(add_indent(indent).as_str());
-                comment_line = false;
-                i += 1;
-            } else {
-                s.push_str(code_to_char[i]);
-            }
-        } else if module_line {
-            if code_to_char[i] == "\\" && code_to_char[i + 1] == "n" {
-                s.push_str("\n\n");
-                module_line = false;
-                i += 1;
-            } else {
-                s.push_str(code_to_char[i]);
-            }
-        } else if extern_line {
-            if code_to_char[i] == "\\" && code_to_char[i + 1] == "n" {
-                s.push_str("\n");
-                extern_line = false;
-                i += 1;
-            } else {
-                s.push_str(code_to_char[i]);
-            }
-        } else {
-            match code_to_char[i] {
-                // { } ; need to add newline
-                // add double blank
-                "{" => {
-                    indent += 1;
-                    if code_to_char[i - 1] != " " && !past_symbol {
-                        s.push_str(" ");
-                    }
+/// format_elz pretty-prints the module in code to canonical style
+///
+/// comments are dropped by lexer, so they are collected from source again and put before the
+/// next item, a comment after code stays at the end of the line
+pub fn format_elz(file_name: &str, code: &str) -> Result<String, ParseError> {
+    let module = Parser::parse_program(file_name, code)?;
+    let mut formatter = Formatter::new(file_name, code);
+    formatter.module(&module);
+    Ok(formatter.output)
+}
 
-                    if code_to_char[i + 1] == "}" || past_symbol {
-                        s.push_str("{");
-                        past_symbol = false;
-                    } else {
-                        s.push_str("{");
-                        s.push_str("\n");
-                        s.push_str(add_indent(indent).as_str());
-                    }
-                }
-                // delete double blank
-                "}" => {
-                    indent -= 1;
-                    if code_to_char[i + 1] == ";" {
-                        s.push_str("}");
-                    } else if code_to_char[i - 1] == "{" {
-                        s.push_str("}");
-                        s.push_str("\n");
-                    } else {
-                        s.push_str(add_indent(indent).as_str());
-                        s.push_str("}");
-                        s.push_str("\n");
-                    }
-                }
-                ";" => {
-                    s.push_str(";");
-                    s.push_str("\n");
-                    if code_to_char[i + 1] == "\\"
-                        && code_to_char[i + 2] == "n"
-                        && code_to_char[i + 3] == "}"
-                    {
-                    } else if code_to_char[i + 1] == "}" {
-                    } else {
-                        s.push_str(add_indent(indent).as_str());
-                    }
-                    semicolon_symbol = true;
-                    past_symbol = false;
-                }
+struct Comment {
+    line: u32,
+    text: String,
+    /// trailing comment has code before it in the same line
+    trailing: bool,
+}
 
-                // add blank in the next position and check double symbol
-                ":" => {
-                    if code_to_char[i + 1] == ":" {
-                        s.push_str(":");
-                        s.push_str(":");
-                        i += 1;
-                    } else {
-                        s.push_str(add_blank(":", " ", code_to_char[i + 1]).as_str());
-                    }
-                }
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+    tokens: Vec<Token>,
+    comments: VecDeque<Comment>,
+    output: String,
+    indent: usize,
+    /// fresh is true at the start of file or block, where no blank line is needed
+    fresh: bool,
+    /// last_line is the line of the last printed item or comment in source
+    last_line: u32,
+}
 
-                // add blank in the next position
-                "," => {
-                    s.push_str(add_blank(",", " ", code_to_char[i + 1]).as_str());
-                }
+impl<'a> Formatter<'a> {
+    fn new(file_name: &str, code: &'a str) -> Formatter<'a> {
+        Formatter {
+            lines: code.lines().collect(),
+            tokens: lexer::lex(file_name, code),
+            comments: collect_comments(code),
+            output: String::new(),
+            indent: 0,
+            fresh: true,
+            last_line: 0,
+        }
+    }
 
-                // add blank in the pre and next position
-                "+" => {
-                    s.push_str(add_blank("+", code_to_char[i - 1], code_to_char[i + 1]).as_str());
+    fn module(&mut self, module: &Module) {
+        let module_line = self.tokens[0].location().line();
+        self.item_start(module_line);
+        self.output.push_str(&format!("module {}\n", module.name));
+        if !module.top_list.is_empty() {
+            self.output.push('\n');
+        }
+        self.fresh = true;
+        for top in &module.top_list {
+            self.top_ast(top);
+        }
+        self.comments_before(u32::MAX);
+    }
+    fn top_ast(&mut self, top: &TopAst) {
+        match top {
+            TopAst::Import(import) => {
+                self.item_start(import.location.line());
+                self.push_indent();
+                self.output
+                    .push_str(&format!("import {}", import.import_path));
+                if !import.imported_component.is_empty() {
+                    self.output
+                        .push_str(&format!(" ({})", import.imported_component.join(", ")));
                 }
-                "-" => {
-                    s.push_str(add_blank("-", code_to_char[i - 1], code_to_char[i + 1]).as_str());
+                self.end_line();
+            }
+            TopAst::Variable(v) => {
+                self.item_start(self.start_line(&v.location, &v.tag));
+                self.tag(&v.tag);
+                self.push_indent();
+                self.output.push_str(&format!("{};", variable(v)));
+                self.end_line();
+            }
+            TopAst::Function(f) => self.function(f, "", 0),
+            TopAst::Class(c) => self.class(c),
+            TopAst::Trait(t) => self.trait_(t),
+        }
+    }
+    fn class(&mut self, c: &Class) {
+        self.item_start(self.start_line(&c.location, &c.tag));
+        self.tag(&c.tag);
+        self.push_indent();
+        self.output.push_str(&format!("class {}", c.name));
+        self.output.push_str(&type_parameters(&c.type_parameters));
+        if !c.parents.is_empty() {
+            self.output
+                .push_str(&format!(" <: {}", c.parents.join(", ")));
+        }
+        self.output.push(' ');
+        let close = self.closing_line(&c.location);
+        self.members(close, c.members.is_empty(), |formatter| {
+            for member in &c.members {
+                match member {
+                    ClassMember::Field(field) => formatter.field(field),
+                    ClassMember::Method(method) => formatter.function(method, "", 0),
+                    ClassMember::StaticMethod(method) => formatter.function(method, "::", 0),
                 }
-                "*" => {
-                    s.push_str(add_blank("*", code_to_char[i - 1], code_to_char[i + 1]).as_str());
+            }
+        });
+        self.end_line();
+    }
+    fn trait_(&mut self, t: &Trait) {
+        self.item_start(self.start_line(&t.location, &t.tag));
+        self.tag(&t.tag);
+        self.push_indent();
+        self.output.push_str(&format!("trait {}", t.name));
+        self.output.push_str(&type_parameters(&t.type_parameters));
+        if !t.with_traits.is_empty() {
+            self.output
+                .push_str(&format!(" <: {}", t.with_traits.join(", ")));
+        }
+        self.output.push(' ');
+        let close = self.closing_line(&t.location);
+        self.members(close, t.members.is_empty(), |formatter| {
+            for member in &t.members {
+                match member {
+                    TraitMember::Field(field) => formatter.field(field),
+                    // parser inserts `self` as the first parameter of trait method
+                    TraitMember::Method(method) => formatter.function(method, "", 1),
                 }
-                "/" => {
-                    if code_to_char[i + 1] == "/" {
-                        s.push_str("//");
-                        if code_to_char[i + 2] != " " {
-                            s.push_str(" ");
-                        }
-                        comment_line = true;
-                        i += 1;
-                    } else {
-                        s.push_str(
-                            add_blank("/", code_to_char[i - 1], code_to_char[i + 1]).as_str(),
-                        );
+            }
+        });
+        self.end_line();
+    }
+    /// members prints `{`, the members and `}` of class or trait, `close` is the line of `}`
+    fn members<F: FnOnce(&mut Formatter)>(&mut self, close: u32, empty: bool, f: F) {
+        if empty && !self.has_comment_before(close) {
+            self.output.push_str("{}");
+            return;
+        }
+        self.open_block();
+        f(self);
+        self.close_block(close);
+    }
+    fn field(&mut self, field: &Field) {
+        self.item_start(field.location.line());
+        self.push_indent();
+        self.output
+            .push_str(&format!("{}: {}", field.name, parsed_type(&field.typ)));
+        if let Some(expr) = &field.expr {
+            self.output.push_str(&format!(" = {}", expr_str(expr)));
+        }
+        self.output.push(';');
+        self.end_line();
+    }
+    /// function prints function with `prefix` before its name, the first `skip` parameters are
+    /// implicit
+    fn function(&mut self, f: &Function, prefix: &str, skip: usize) {
+        self.item_start(self.start_line(&f.location, &f.tag));
+        self.tag(&f.tag);
+        self.push_indent();
+        let parameters: Vec<String> = f
+            .parameters
+            .iter()
+            .skip(skip)
+            .map(|p| format!("{}: {}", p.name, parsed_type(&p.typ)))
+            .collect();
+        self.output.push_str(&format!(
+            "{}{}{}({}): {}",
+            prefix,
+            f.name,
+            type_parameters(&f.type_parameters),
+            parameters.join(", "),
+            parsed_type(&f.ret_typ)
+        ));
+        match &f.body {
+            None => self.output.push(';'),
+            Some(Body::Expr(expr)) => self.output.push_str(&format!(" = {};", expr_str(expr))),
+            Some(Body::Block(block)) => {
+                self.output.push(' ');
+                self.block(block);
+            }
+        }
+        self.end_line();
+    }
+    /// block prints `{`, the statements and `}`, the text before `{` is put by caller
+    fn block(&mut self, block: &Block) {
+        let close = self.closing_line(&block.location);
+        if block.statements.is_empty() && !self.has_comment_before(close) {
+            self.output.push_str("{}");
+            return;
+        }
+        self.open_block();
+        for statement in &block.statements {
+            self.statement(statement);
+        }
+        self.close_block(close);
+    }
+    fn statement(&mut self, statement: &Statement) {
+        self.item_start(statement.location.line());
+        self.push_indent();
+        match &statement.value {
+            StatementVariant::Return(None) => self.output.push_str("return;"),
+            StatementVariant::Return(Some(expr)) => {
+                self.output.push_str(&format!("return {};", expr_str(expr)))
+            }
+            StatementVariant::Variable(v) => self.output.push_str(&format!("{};", variable(v))),
+            StatementVariant::Expression(expr) => {
+                self.output.push_str(&format!("{};", expr_str(expr)))
+            }
+            StatementVariant::Assign { target, value } => {
+                self.output
+                    .push_str(&format!("{} = {};", expr_str(target), expr_str(value)))
+            }
+            StatementVariant::IfBlock {
+                clauses,
+                else_block,
+            } => {
+                for (i, (condition, block)) in clauses.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(" else ");
                     }
+                    self.output
+                        .push_str(&format!("if {} ", expr_str(condition)));
+                    self.block(block);
                 }
-                "=" => {
-                    s.push_str(add_blank("=", code_to_char[i - 1], code_to_char[i + 1]).as_str());
-                    past_symbol = true;
-                }
-                ">" => {
-                    s.push_str(add_blank(">", code_to_char[i - 1], code_to_char[i + 1]).as_str());
-                }
-                "<" => {
-                    if code_to_char[i + 1] == ":" {
-                        s.push_str(add_blank("<", code_to_char[i - 1], " ").as_str());
-                    } else {
-                        s.push_str(
-                            add_blank("<", code_to_char[i - 1], code_to_char[i + 1]).as_str(),
-                        );
-                    }
+                // parser gives the location of `if` to the missing else block
+                if else_block.location != statement.location {
+                    self.output.push_str(" else ");
+                    self.block(else_block);
                 }
-                "\\" => {
-                    if code_to_char[i + 1] == "n" {
-                        i += 1;
-                    }
-                    if indent == 0
-                        && code_to_char.len() >= i + 3
-                        && code_to_char[i + 1] == "\\"
-                        && code_to_char[i + 2] == "n"
-                    {
-                        s.push_str("\n");
+            }
+            StatementVariant::For {
+                name,
+                iterable,
+                block,
+                ..
+            } => {
+                self.output
+                    .push_str(&format!("for {} in {} ", name, expr_str(iterable)));
+                self.block(block);
+            }
+        }
+        self.end_line();
+    }
+    fn tag(&mut self, tag: &Option<Tag>) {
+        if let Some(tag) = tag {
+            self.push_indent();
+            self.output.push_str(&format!("@{}", tag.name));
+            if !tag.properties.is_empty() {
+                self.output
+                    .push_str(&format!("({})", tag.properties.join(", ")));
+            }
+            self.output.push('\n');
+        }
+    }
+}
+
+// layout helpers
+impl<'a> Formatter<'a> {
+    fn push_indent(&mut self) {
+        self.output.push_str(&"  ".repeat(self.indent));
+    }
+    fn end_line(&mut self) {
+        self.output.push('\n');
+        self.fresh = false;
+    }
+    fn open_block(&mut self) {
+        self.output.push_str("{\n");
+        self.indent += 1;
+        self.fresh = true;
+    }
+    fn close_block(&mut self, close: u32) {
+        self.comments_before(close);
+        self.indent -= 1;
+        self.push_indent();
+        self.output.push('}');
+    }
+    /// item_start puts the comments before the item at line, and keeps a blank line above the
+    /// item if the source has one
+    fn item_start(&mut self, line: u32) {
+        self.comments_before(line);
+        self.blank_line_before(line);
+    }
+    fn comments_before(&mut self, line: u32) {
+        while self.has_comment_before(line) {
+            let comment = self.comments.pop_front().unwrap();
+            if comment.trailing && self.output.ends_with('\n') {
+                self.output.pop();
+                self.output.push(' ');
+                self.output.push_str(&comment.text);
+                self.output.push('\n');
+            } else {
+                self.blank_line_before(comment.line);
+                self.push_indent();
+                self.output.push_str(&comment.text);
+                self.end_line();
+            }
+        }
+    }
+    fn has_comment_before(&self, line: u32) -> bool {
+        self.comments.front().is_some_and(|c| c.line < line)
+    }
+    fn blank_line_before(&mut self, line: u32) {
+        let blank_above = line >= self.last_line + 2
+            && self
+                .lines
+                .get(line as usize - 2)
+                .is_some_and(|l| l.trim().is_empty());
+        if !self.fresh && blank_above {
+            self.output.push('\n');
+        }
+        self.last_line = line;
+    }
+    /// start_line is the line of the tag or the item at location
+    fn start_line(&self, location: &Location, tag: &Option<Tag>) -> u32 {
+        let index = self.token_index(location);
+        if tag.is_some() {
+            if let Some(at_sign) = self.tokens[..index]
+                .iter()
+                .rev()
+                .find(|tok| tok.tk_type() == &TkType::AtSign)
+            {
+                return at_sign.location().line();
+            }
+        }
+        location.line()
+    }
+    /// closing_line is the line of `}` closing the first `{` since location
+    fn closing_line(&self, location: &Location) -> u32 {
+        let mut depth = 0;
+        for tok in &self.tokens[self.token_index(location)..] {
+            match tok.tk_type() {
+                TkType::OpenBrace => depth += 1,
+                TkType::CloseBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return tok.location().line();
                     }
                 }
-                "@" => {
-                    s.push_str("@");
-                    extern_line = true;
-                }
+                _ => (),
+            }
+        }
+        u32::MAX
+    }
+    fn token_index(&self, location: &Location) -> usize {
+        self.tokens
+            .iter()
+            .position(|tok| tok.location() == *location)
+            .unwrap_or(0)
+    }
+}
 
-                _ => {
-                    if indent == 0 {
-                        if code_to_char[i] == "m"
-                            && code_to_char[i + 1] == "o"
-                            && code_to_char[i + 2] == "d"
-                            && code_to_char[i + 3] == "u"
-                            && code_to_char[i + 4] == "l"
-                            && code_to_char[i + 5] == "e"
-                        {
-                            module_line = true;
-                            s.push_str("m");
-                            i += 1;
-                        }
-                    }
-                    if semicolon_symbol {
-                        if code_to_char[i] != " " {
-                            s.push_str(code_to_char[i]);
-                            semicolon_symbol = false;
-                        }
+fn collect_comments(code: &str) -> VecDeque<Comment> {
+    let mut comments = VecDeque::new();
+    for (i, line) in code.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut in_string = false;
+        let mut j = 0;
+        while j < chars.len() {
+            match chars[j] {
+                '\\' if in_string => j += 1,
+                '"' => in_string = !in_string,
+                '/' if !in_string && chars.get(j + 1) == Some(&'/') => {
+                    let content: String = chars[j + 2..].iter().collect();
+                    let content = content.trim_end();
+                    let text = if content.is_empty() || content.starts_with(char::is_whitespace) {
+                        format!("//{}", content)
                     } else {
-                        if code_to_char[i] == " " && check_symbol_behind_space(code_to_char[i + 1])
-                        {
-                        } else {
-                            s.push_str(code_to_char[i]);
-                        }
-                    }
+                        format!("// {}", content)
+                    };
+                    comments.push_back(Comment {
+                        line: i as u32 + 1,
+                        text,
+                        trailing: chars[..j].iter().any(|c| !c.is_whitespace()),
+                    });
+                    break;
                 }
+                _ => (),
             }
+            j += 1;
         }
-        i += 1;
     }
-    s
+    comments
+}
+
+fn variable(v: &Variable) -> String {
+    format!(
+        "{}: {} = {}",
+        v.name,
+        parsed_type(&v.typ),
+        expr_str(&v.expr)
+    )
 }
 
-fn add_indent(level: i32) -> String {
-    let mut count = 0i32;
-    let mut s = String::from("");
-    loop {
-        count += 1;
-        if count > level {
-            break;
+fn parsed_type(typ: &ParsedType) -> String {
+    match typ {
+        ParsedType::TypeName(name) => name.clone(),
+        ParsedType::GenericType {
+            name,
+            type_parameters,
+        } => {
+            let parameters: Vec<String> = type_parameters.iter().map(parsed_type).collect();
+            format!("{}[{}]", name, parameters.join(", "))
         }
-        s.push_str("  ");
     }
-    s
 }
 
-fn add_blank(c: &str, pre: &str, next: &str) -> String {
-    let mut s = String::from("");
-    if pre != " " {
-        s.push_str(" ");
+fn type_parameters(parameters: &[TypeParameter]) -> String {
+    if parameters.is_empty() {
+        return String::new();
     }
-    s.push_str(c);
-    if next != " " {
-        s.push_str(" ");
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|p| {
+            let parents: Vec<String> = p.parent_types.iter().map(parsed_type).collect();
+            if parents.is_empty() {
+                p.name.clone()
+            } else {
+                format!("{} <: {}", p.name, parents.join(" + "))
+            }
+        })
+        .collect();
+    format!("[{}]", parameters.join(", "))
+}
+
+fn expr_str(expr: &Expr) -> String {
+    if let Some(template) = string_template(expr) {
+        return format!("\"{}\"", template);
+    }
+    match &expr.value {
+        ExprVariant::Binary(l, r, op) => format!(
+            "{} {} {}",
+            operand_str(l, op, false),
+            op,
+            operand_str(r, op, true)
+        ),
+        ExprVariant::F64(f) => format!("{:?}", f),
+        ExprVariant::Int(i) => i.to_string(),
+        ExprVariant::Bool(b) => b.to_string(),
+        ExprVariant::String(s) => format!("\"{}\"", escape(s)),
+        ExprVariant::List(exprs) => {
+            let exprs: Vec<String> = exprs.iter().map(expr_str).collect();
+            format!("[{}]", exprs.join(", "))
+        }
+        ExprVariant::FuncCall(f, args) => {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| match &arg.name {
+                    Some(name) => format!("{}: {}", name, expr_str(&arg.expr)),
+                    None => expr_str(&arg.expr),
+                })
+                .collect();
+            format!("{}({})", expr_str(f), args.join(", "))
+        }
+        ExprVariant::MemberAccess(from, name) => match &from.value {
+            ExprVariant::Binary(..) => format!("({}).{}", expr_str(from), name),
+            _ => format!("{}.{}", expr_str(from), name),
+        },
+        ExprVariant::Identifier(name) => name.clone(),
+        ExprVariant::If(condition, then_expr, else_expr) => {
+            let else_part = match &else_expr.value {
+                ExprVariant::If(..) => expr_str(else_expr),
+                _ => format!("{{ {} }}", expr_str(else_expr)),
+            };
+            format!(
+                "if {} {{ {} }} else {}",
+                expr_str(condition),
+                expr_str(then_expr),
+                else_part
+            )
+        }
+        ExprVariant::ClassConstruction(name, field_inits) => {
+            if field_inits.is_empty() {
+                return format!("{} {{}}", name);
+            }
+            // field inits are kept in a map, the order in source is recovered by location
+            let mut fields: Vec<(&String, &Expr)> = field_inits.iter().collect();
+            fields.sort_by_key(|(_, e)| (e.location.line(), e.location.column()));
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, e)| format!("{}: {}", name, expr_str(e)))
+                .collect();
+            format!("{} {{ {} }}", name, fields.join(", "))
+        }
     }
-    s
 }
 
-fn check_symbol_behind_space(symbol: &str) -> bool {
-    if symbol == ";" {
-        true
-    } else if symbol == ":" {
-        true
-    } else if symbol == "(" {
-        true
-    } else if symbol == "[" {
-        true
+/// operand_str prints an operand of `op` in parentheses if it's a binary expression binding looser
+/// than `op`, or as loose on the side `op` doesn't group to, e.g. `(a + b) * c` and `a - (b - c)`
+fn operand_str(operand: &Expr, op: &Operator, right: bool) -> String {
+    let inner = match &operand.value {
+        ExprVariant::Binary(_, _, inner) if string_template(operand).is_none() => inner,
+        _ => return expr_str(operand),
+    };
+    let (outer, inner) = (operator_precedence(op), operator_precedence(inner));
+    if inner < outer || (inner == outer && right != is_operator_right_associative(op)) {
+        format!("({})", expr_str(operand))
     } else {
-        false
+        expr_str(operand)
+    }
+}
+
+/// string_template returns the content of `"a{b}c"`, which is parsed as `"a" + b.to_string() + "c"`
+/// with all parts except `b.to_string()` located at the string literal
+fn string_template(expr: &Expr) -> Option<String> {
+    let (lhs, rest) = match &expr.value {
+        ExprVariant::Binary(lhs, rest, Operator::Plus) if rest.location == expr.location => {
+            (lhs, rest)
+        }
+        _ => return None,
+    };
+    let (left, mid) = match &lhs.value {
+        ExprVariant::Binary(left, mid, Operator::Plus)
+            if lhs.location == expr.location && left.location == expr.location =>
+        {
+            (left, mid)
+        }
+        _ => return None,
+    };
+    let left = match &left.value {
+        ExprVariant::String(s) => escape(s),
+        _ => return None,
+    };
+    let rest = match &rest.value {
+        ExprVariant::String(s) => escape(s),
+        _ => string_template(rest)?,
+    };
+    let mid = match &mid.value {
+        ExprVariant::FuncCall(f, args) if args.is_empty() => match &f.value {
+            ExprVariant::MemberAccess(value, name) if name == "to_string" => value,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(format!("{}{{{}}}{}", left, expr_str(mid), rest))
+}
+
+fn escape(s: &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            '"' | '\\' | '{' => {
+                result.push('\\');
+                result.push(c);
+            }
+            c => result.push(c),
+        }
     }
+    result
 }
 
 #[cfg(test)]
@@ -2,203 +2,290 @@ use super::*;
 
 #[test]
 fn simple_variable() {
-    let formatted_code = format_elz("x:int=1;".to_string());
-    assert_eq!(formatted_code, "x: int = 1;\n");
+    assert_formatted("x:int=1;", "x: int = 1;\n");
 }
 
 #[test]
 fn simple_function() {
-    let formatted_code = format_elz("add(x:int,y:int):int=x+y;".to_string());
-    assert_eq!(formatted_code, "add(x: int, y: int): int = x + y;\n");
+    assert_formatted(
+        "add(x:int,y:int):int=x+y;",
+        "add(x: int, y: int): int = x + y;\n",
+    );
 }
 
 #[test]
 fn simple_function_block() {
-    let formatted_code = format_elz("add(x:int,y:int):int{return x+y;}".to_string());
-    assert_eq!(
-        formatted_code,
+    assert_formatted(
+        "add(x:int,y:int):int{return x+y;}",
         "add(x: int, y: int): int {
   return x + y;
 }
-"
+",
     );
 }
 
 #[test]
 fn local_variable_in_function() {
-    let formatted_code = format_elz("foo():void{x:int=1;}".to_string());
-    assert_eq!(
-        formatted_code,
+    assert_formatted(
+        "foo():void{x:int=1;}",
         "foo(): void {
   x: int = 1;
 }
-"
+",
     );
 }
 
 #[test]
 fn comment_in_function() {
-    let formatted_code = format_elz(
+    assert_formatted(
         "foo():void{//comment
   x(a:1,2);}
-"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
-        "foo(): void {
-  // comment
+",
+        "foo(): void { // comment
   x(a: 1, 2);
 }
-"
+",
     );
 }
 
 #[test]
-fn simple_class() {
-    let formatted_code = format_elz("class Foo{}".to_string());
-    assert_eq!(
-        formatted_code,
-        "class Foo {}
-"
+fn trailing_comment() {
+    assert_formatted(
+        "foo():void{ // start
+  x: int = 1;   // one
+  // end
+}",
+        "foo(): void { // start
+  x: int = 1; // one
+  // end
+}
+",
     );
 }
 
+#[test]
+fn simple_class() {
+    assert_formatted("class Foo{}", "class Foo {}\n");
+}
+
 #[test]
 fn class_super_type() {
-    let formatted_code = format_elz("class Foo<:Bar{}".to_string());
-    assert_eq!(
-        formatted_code,
-        "class Foo <: Bar {}
-"
-    );
+    assert_formatted("class Foo<:Bar{}", "class Foo <: Bar {}\n");
 }
 
 #[test]
 fn class_multiple_super_types() {
-    let formatted_code = format_elz("class Foo<:Bar,Tool{}".to_string());
-    assert_eq!(
-        formatted_code,
-        "class Foo <: Bar, Tool {}
-"
+    assert_formatted("class Foo<:Bar,Tool{}", "class Foo <: Bar, Tool {}\n");
+}
+
+#[test]
+fn class_type_parameters() {
+    assert_formatted("class List[T]{}", "class List[T] {}\n");
+}
+
+#[test]
+fn class_type_parameters_before_super_types() {
+    assert_formatted("class Foo<:Bar[T]{}", "class Foo[T] <: Bar {}\n");
+}
+
+#[test]
+fn function_type_parameters() {
+    assert_formatted(
+        "max[T<:Ord+ToString](a:T,b:T):T=a;",
+        "max[T <: Ord + ToString](a: T, b: T): T = a;\n",
     );
 }
 
 #[test]
 fn class_members() {
-    let formatted_code = format_elz(
+    assert_formatted(
         "class Foo{
 x:int;
 ::new() :Foo =Foo{x:1};
 bar(): void{}
-}"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
+}",
         "class Foo {
   x: int;
-  ::new(): Foo = Foo{x: 1};
+  ::new(): Foo = Foo { x: 1 };
   bar(): void {}
 }
-"
+",
     );
 }
 
 #[test]
 fn nested_block() {
-    let formatted_code = format_elz(
+    assert_formatted(
         "class Foo{
 ::bar(): void{return;}
-}"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
+}",
         "class Foo {
   ::bar(): void {
     return;
   }
 }
-"
+",
     );
 }
 
 #[test]
 fn simple_trait() {
-    let formatted_code = format_elz("trait Foo{}".to_string());
-    assert_eq!(
-        formatted_code,
-        "trait Foo {}
-"
+    assert_formatted("trait Foo{}", "trait Foo {}\n");
+}
+
+#[test]
+fn trait_with_parents() {
+    assert_formatted("trait Foo<:Bar,Baz{}", "trait Foo <: Bar, Baz {}\n");
+}
+
+#[test]
+fn trait_method_without_implicit_self() {
+    assert_formatted(
+        "trait Foo{ name(): string; }",
+        "trait Foo {
+  name(): string;
+}
+",
     );
 }
 
 #[test]
 fn simple_comment() {
-    let formatted_code = format_elz(
+    assert_formatted(
         "//this is comment line
 trait Foo {}
-"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
+",
         "// this is comment line
 trait Foo {}
-"
+",
+    );
+}
+
+#[test]
+fn tag() {
+    assert_formatted(
+        "@extern(c) puts(str: _c_string): int;",
+        "@extern(c)
+puts(str: _c_string): int;
+",
     );
 }
 
 #[test]
 fn multi_blank() {
-    let formatted_code = format_elz("
-    class                 Car                               {name               :   string          ;new(name:string):Car;}
-    ".to_string());
-    assert_eq!(
-        formatted_code,
+    assert_formatted(
+        "
+    class                 Car                               {name               :   string          ;::new(name:string):Car;}
+    ",
         "class Car {
   name: string;
-  new(name: string): Car;
+  ::new(name: string): Car;
 }
-"
+",
     );
 }
 
 #[test]
 fn multi_newline() {
-    let formatted_code = format_elz(
+    assert_formatted(
         "
     class Car
     {
         name:string
 ;
-new(name:string):Car
+
+
+::new(name:string):Car
 ;
 }
-"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
+",
         "class Car {
   name: string;
-  new(name: string): Car;
+
+  ::new(name: string): Car;
 }
-"
+",
+    );
+}
+
+#[test]
+fn if_statement() {
+    assert_formatted(
+        "foo(x: int): void { if x<0{return;}else if x==0 {println(\"zero\");}
+  else{ y: int = if x > 1 { 1 } else { 2 }; } }",
+        "foo(x: int): void {
+  if x < 0 {
+    return;
+  } else if x == 0 {
+    println(\"zero\");
+  } else {
+    y: int = if x > 1 { 1 } else { 2 };
+  }
+}
+",
+    );
+}
+
+#[test]
+fn for_statement() {
+    assert_formatted(
+        "foo(xs: List[int]): void { for x in xs{println(\"{x}\");} }",
+        "foo(xs: List[int]): void {
+  for x in xs {
+    println(\"{x}\");
+  }
+}
+",
+    );
+}
+
+#[test]
+fn string_literal_keeps_escape_and_template() {
+    assert_formatted(
+        "foo(): void { println(\"a\\n\\\"{name}\\{\" + \"b\"); }",
+        "foo(): void {
+  println(\"a\\n\\\"{name}\\{\" + \"b\");
+}
+",
+    );
+}
+
+#[test]
+fn class_construction_keeps_field_order() {
+    assert_formatted(
+        "p: Point = Point{y:1,x:2};",
+        "p: Point = Point { y: 1, x: 2 };\n",
+    );
+}
+
+#[test]
+fn parentheses_are_kept_where_they_group() {
+    assert_formatted(
+        "x: int = ((a+b))*(c-(d-e))-(f*g)+(h**i)**j;",
+        "x: int = (a + b) * (c - (d - e)) - f * g + (h ** i) ** j;\n",
+    );
+    assert_formatted(
+        "foo(): void { (f()); println((a+b).to_string()); }",
+        "foo(): void {
+  f();
+  println((a + b).to_string());
+}
+",
     );
 }
 
 #[test]
 fn test() {
     let formatted_code = format_elz(
+        "",
         "module main
 //this is comment line
 trait Foo {}
     class Car{name:string       ;//comment line one
 //comment line two;
-    :  :     new(name:string):Car;   bar(i: int):void  ;  fn foo()   {b=555*10 ; a=1+b;}}class CarFoo{::bar(): void {return;}}".to_string(),
-    );
+    ::     new(name:string):Car;   bar(i: int):void  ;  foo():void   {b.x=555*10 ; a:int=1+b.x;}}class CarFoo{::bar(): void {return;}}",
+    )
+    .unwrap();
     assert_eq!(
         formatted_code,
         "module main
@@ -206,14 +293,13 @@ trait Foo {}
 // this is comment line
 trait Foo {}
 class Car {
-  name: string;
-  // comment line one
+  name: string; // comment line one
   // comment line two;
   ::new(name: string): Car;
   bar(i: int): void;
-  fn foo() {
-    b = 555 * 10;
-    a = 1 + b;
+  foo(): void {
+    b.x = 555 * 10;
+    a: int = 1 + b.x;
   }
 }
 class CarFoo {
@@ -227,22 +313,23 @@ class CarFoo {
 
 #[test]
 fn simple_module() {
-    let formatted_code = format_elz(
-        "module main
+    let code = "module main
 
 main(): void {
   println(\"Hello, World!\");
 }
-"
-        .to_string(),
-    );
-    assert_eq!(
-        formatted_code,
-        "module main
+";
+    assert_eq!(format_elz("", code).unwrap(), code);
+}
 
-main(): void {
-  println(\"Hello, World!\");
+#[test]
+fn invalid_code_is_not_formatted() {
+    assert!(format_elz("", "module main\nfn foo() {}").is_err());
 }
-"
-    );
+
+// helpers, must put tests before this line
+/// assert_formatted formats code as the body of `module main`
+fn assert_formatted(code: &str, expected: &str) {
+    let formatted_code = format_elz("", &format!("module main\n{}", code)).unwrap();
+    assert_eq!(formatted_code, format!("module main\n\n{}", expected));
 }
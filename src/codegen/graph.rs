@@ -0,0 +1,94 @@
+use super::ir;
+use super::llvm::LLVMValue;
+
+pub trait DotGraph {
+    fn dot_represent(&self) -> String;
+}
+
+impl DotGraph for ir::Module {
+    fn dot_represent(&self) -> String {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        let mut s = String::new();
+        for name in names {
+            let f = &self.functions[name];
+            if f.body.is_some() {
+                s.push_str(f.dot_represent().as_str());
+                s.push('\n');
+            }
+        }
+        s
+    }
+}
+
+/// block_name names the entry block `entry`, and others by their label
+fn block_name(index: usize, block: &ir::BasicBlock) -> String {
+    if index == 0 {
+        "entry".to_string()
+    } else {
+        format!("{}", block.label.id)
+    }
+}
+
+impl DotGraph for ir::Function {
+    fn dot_represent(&self) -> String {
+        let body = match &self.body {
+            Some(b) => b,
+            None => return String::new(),
+        };
+        let mut s = String::new();
+        s.push_str(format!("digraph \"{}\" {{\n", escape(self.name.as_str())).as_str());
+        s.push_str("  node [shape=box, fontname=monospace];\n");
+        for (index, block) in body.blocks.iter().enumerate() {
+            let name = block_name(index, block);
+            // entry block starts from the function
+            let location = if index == 0 {
+                &self.location
+            } else {
+                &block.label.location
+            };
+            let mut label = format!("{}: line {}\\l", name, location.line());
+            for inst in &block.instructions {
+                label.push_str(escape(inst.llvm_represent().as_str()).as_str());
+                label.push_str("\\l");
+            }
+            label.push_str(escape(block.terminator.llvm_represent().as_str()).as_str());
+            label.push_str("\\l");
+            s.push_str(format!("  \"{}\" [label=\"{}\"];\n", name, label).as_str());
+        }
+        for (index, block) in body.blocks.iter().enumerate() {
+            let name = block_name(index, block);
+            match &block.terminator {
+                ir::Terminator::Branch {
+                    if_true, if_false, ..
+                } => {
+                    s.push_str(edge(&name, if_true, Some("true")).as_str());
+                    s.push_str(edge(&name, if_false, Some("false")).as_str());
+                }
+                ir::Terminator::Goto(target) => {
+                    s.push_str(edge(&name, target, None).as_str());
+                }
+                ir::Terminator::Switch { default, cases, .. } => {
+                    for (case, target) in cases {
+                        s.push_str(edge(&name, target, Some(&case.to_string())).as_str());
+                    }
+                    s.push_str(edge(&name, default, Some("default")).as_str());
+                }
+                ir::Terminator::Return(..) | ir::Terminator::Unreachable => (),
+            }
+        }
+        s.push('}');
+        s
+    }
+}
+
+fn edge(from: &str, to: &ir::Label, label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!("  \"{}\" -> \"{}\" [label=\"{}\"];\n", from, to.id, label),
+        None => format!("  \"{}\" -> \"{}\";\n", from, to.id),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,38 +1,113 @@
+use super::erasure::{ErasedSignature, ERASED_TYPE};
+use super::refcount::REFCOUNT_FIELD;
+use super::tag::CodegenTag;
+use super::target::Target;
 use crate::ast;
 use crate::ast::*;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use crate::ice::{self, Phase};
+use crate::lexer::Location;
+use crate::mangle::{method_path, Symbol, SEPARATOR};
+use crate::semantic::TypeMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Formatter;
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 pub struct Module {
     // helpers
     pub(crate) known_functions: HashMap<String, Type>,
     pub(crate) known_variables: HashMap<String, Type>,
+    /// parameter types of functions, integer literal arguments take them when their types weren't
+    /// inferred
+    pub(crate) known_parameters: HashMap<String, Vec<Type>>,
+    /// default value expressions of class fields, class name -> field name -> expression
+    pub(crate) known_field_defaults: HashMap<String, HashMap<String, ast::Expr>>,
+    /// modules classes are defined in, class name -> module name
+    pub(crate) class_modules: HashMap<String, String>,
+    /// static methods of generic classes with erased values, see `erasure`
+    pub(crate) erased_signatures: HashMap<String, ErasedSignature>,
+    /// static methods tagged `@builtin`, calls to them are replaced with their code
+    pub(crate) builtin_functions: HashSet<String>,
+    /// types of expressions inferred by the semantic checker, erased results are restored to them
+    pub(crate) expr_types: TypeMap,
+    /// static methods tagged `@specialize` with their classes by their paths, see `specialize`
+    pub(crate) specialized_methods: HashMap<String, (String, ast::Function)>,
+    /// specializations called but maybe not generated yet, by paths and types of elements
+    pub(crate) pending_specializations: Vec<(String, String)>,
+    /// generic top-level functions by their names, see `specialize`
+    pub(crate) generic_functions: HashMap<String, ast::Function>,
+    /// instances of generic functions called but maybe not generated yet, by names and type
+    /// arguments
+    pub(crate) pending_instances: Vec<(String, Vec<String>)>,
+    /// element_class is the type of elements of the specialization being generated
+    pub(crate) element_class: Option<String>,
+    /// traits have no runtime representation, a value of a trait is a slot of a specialization
+    pub(crate) traits: HashSet<String>,
     // output parts
     pub(crate) functions: HashMap<String, Function>,
     pub(crate) variables: Vec<Variable>,
     pub(crate) types: HashMap<String, Type>,
+    pub(crate) target: Target,
+    /// debug_info is true when statements are marked with their locations, and the textual IR
+    /// carries debug info for debuggers
+    pub(crate) debug_info: bool,
+    /// classes whose instances are retained or released, their functions are defined after
+    /// generating the module
+    pub(crate) refcounted_classes: BTreeSet<String>,
+    /// classes defining `drop`, it's called before their instances are freed
+    pub(crate) dropped_classes: HashSet<String>,
+    /// the global keeping arguments of `main`, see `args`
+    pub(crate) args: Option<ID>,
 }
 
 impl Module {
-    pub(crate) fn new() -> Module {
+    pub(crate) fn new(target: Target) -> Module {
         Module {
             known_functions: HashMap::new(),
             known_variables: HashMap::new(),
+            known_parameters: HashMap::new(),
+            known_field_defaults: HashMap::new(),
+            class_modules: HashMap::new(),
+            erased_signatures: HashMap::new(),
+            builtin_functions: HashSet::new(),
+            expr_types: TypeMap::default(),
+            specialized_methods: HashMap::new(),
+            pending_specializations: vec![],
+            generic_functions: HashMap::new(),
+            pending_instances: vec![],
+            element_class: None,
+            traits: HashSet::new(),
             functions: HashMap::new(),
             variables: vec![],
             types: HashMap::new(),
+            target,
+            debug_info: false,
+            refcounted_classes: BTreeSet::new(),
+            dropped_classes: HashSet::new(),
+            args: None,
         }
     }
     pub(crate) fn remember_function(&mut self, f: &ast::Function) {
-        let ret_type = Type::from_ast(&f.ret_typ, self);
+        let ret_type = Type::from_ast(&f.ret_typ, &self.target);
         self.known_functions.insert(f.name.clone(), ret_type);
+        let parameters = f
+            .parameters
+            .iter()
+            .map(|p| Type::from_ast(&p.typ, &self.target))
+            .collect();
+        self.known_parameters.insert(f.name.clone(), parameters);
+    }
+    /// remember_method remembers the static method or the method by its path in code, e.g.
+    /// `Point::new`
+    pub(crate) fn remember_method(&mut self, class_name: &str, f: &ast::Function) {
+        let ret_type = Type::from_ast(&f.ret_typ, &self.target);
+        self.known_functions
+            .insert(method_path(class_name, &f.name), ret_type);
     }
     pub(crate) fn remember_variable(&mut self, v: &ast::Variable) {
         self.known_variables
-            .insert(v.name.clone(), Type::from_ast(&v.typ, self));
+            .insert(v.name.clone(), Type::from_ast(&v.typ, &self.target));
     }
     pub(crate) fn push_function(&mut self, f: Function) {
         self.functions.insert(f.name.clone(), f);
@@ -40,137 +115,647 @@ impl Module {
     pub(crate) fn push_variable(&mut self, v: Variable) {
         self.variables.push(v);
     }
+    /// push_constant defines an unnamed global of `expr`, they're numbered in the order they're
+    /// defined, e.g. `@0`, `@1`
+    pub(crate) fn push_constant(&mut self, expr: Expr) -> ID {
+        let count = self
+            .variables
+            .iter()
+            .filter(|v| matches!(v.name, GlobalName::ID(..)))
+            .count();
+        let id = ID::Number(count as u64);
+        self.variables.push(Variable {
+            name: GlobalName::ID(id),
+            expr,
+        });
+        id
+    }
+    /// push_type registers the layout of the class, the reference counter goes before fields
     pub(crate) fn push_type(&mut self, type_name: &String, fields: &Vec<ClassMember>) {
+        let counter = Field {
+            name: REFCOUNT_FIELD.to_string(),
+            typ: self.target.int_type().into(),
+        };
         let typ = Type::Struct {
             name: type_name.clone(),
-            fields: fields
-                .iter()
-                .filter(|&member| match member {
-                    ClassMember::Field(_) => true,
-                    _ => false,
-                })
-                .map(|member| match member {
-                    ClassMember::Field(field) => Field {
-                        name: field.name.clone(),
-                        typ: Type::from_ast(&field.typ, self).into(),
-                    },
-                    _ => unreachable!(),
-                })
+            fields: std::iter::once(counter)
+                .chain(
+                    fields
+                        .iter()
+                        .filter(|&member| match member {
+                            ClassMember::Field(_) => true,
+                            _ => false,
+                        })
+                        .map(|member| match member {
+                            ClassMember::Field(field) => Field {
+                                name: field.name.clone(),
+                                typ: Type::from_ast(&field.typ, &self.target).into(),
+                            },
+                            _ => unreachable!(),
+                        }),
+                )
                 .collect(),
         };
         self.types.insert(type_name.clone(), typ);
+        let mut defaults = HashMap::new();
+        for member in fields {
+            if let ClassMember::Field(ast::Field {
+                name,
+                expr: Some(expr),
+                ..
+            }) = member
+            {
+                defaults.insert(name.clone(), expr.clone());
+            }
+        }
+        self.known_field_defaults
+            .insert(type_name.clone(), defaults);
+    }
+    /// method_symbol returns the mangled symbol of a method of the class, without `@`
+    pub(crate) fn method_symbol(&self, class_name: &str, method: &str) -> String {
+        let module = self.class_modules.get(class_name).map(String::as_str);
+        Symbol::method(module, class_name, method).mangle()
+    }
+    /// class_of returns the class of values of `typ`, whose methods are called on them, `int` of
+    /// `i32` on 32 bits targets, where both are the same type
+    pub(crate) fn class_of(&self, typ: &Type) -> String {
+        match typ {
+            typ if *typ == self.target.int_type() => "int".to_string(),
+            Type::Int(1) => "bool".to_string(),
+            Type::Int(8) => "u8".to_string(),
+            Type::Int(32) => "i32".to_string(),
+            Type::Float(64) => "f64".to_string(),
+            Type::Pointer(element_type) => match element_type.deref() {
+                Type::Named(name) => name.clone(),
+                _ => unreachable!("call method on a value of `{:?}`", typ),
+            },
+            _ => unreachable!("call method on a value of `{:?}`", typ),
+        }
+    }
+    /// function_symbol returns the symbol of the function named `name` in code, without `@`, e.g.
+    /// the mangled symbol of `Point::new`
+    pub(crate) fn function_symbol(&self, name: &str) -> String {
+        match name.split_once(SEPARATOR) {
+            Some((class_name, method)) => self.method_symbol(class_name, method),
+            None => name.to_string(),
+        }
+    }
+    /// wrap_main renames `@main` to `@elz.main`, and defines `@main` taking `argc` and `argv` of
+    /// the C runtime, which stores them for `env::args`, calls it, then returns 0 after void
+    /// `main`, since the C runtime takes the result of `main` as the exit code, which would be
+    /// garbage
+    pub(crate) fn wrap_main(&mut self) {
+        let mut main = match self.functions.remove("@main") {
+            Some(main) => main,
+            None => return,
+        };
+        let renamed = "@elz.main";
+        main.name = renamed.to_string();
+        for function in self
+            .functions
+            .values_mut()
+            .chain(std::iter::once(&mut main))
+        {
+            let blocks = function.body.iter_mut().flat_map(|body| &mut body.blocks);
+            for inst in blocks.flat_map(|block| &mut block.instructions) {
+                if let Instruction::FunctionCall { func_name, .. } = inst {
+                    if func_name == "@main" {
+                        *func_name = renamed.to_string();
+                    }
+                }
+            }
+        }
+        let mut instructions = self.store_args();
+        let result = ID::new();
+        instructions.push(Instruction::FunctionCall {
+            id: result,
+            func_name: renamed.to_string(),
+            ret_type: main.ret_typ.clone().into(),
+            args_expr: vec![],
+        });
+        let code = match &main.ret_typ {
+            Type::Void => Expr::Int(Type::Int(32), 0),
+            Type::Int(32) => Expr::local_id(Type::Int(32), result),
+            typ => {
+                let code = ID::new();
+                instructions.push(Instruction::Cast {
+                    id: code,
+                    op_name: "trunc".to_string(),
+                    from: Expr::local_id(typ.clone(), result),
+                    target_type: Type::Int(32),
+                });
+                Expr::local_id(Type::Int(32), code)
+            }
+        };
+        let mut body = Body::with_blocks(vec![BasicBlock {
+            label: Label::new(ID::new(), &Location::none()),
+            instructions,
+            terminator: Terminator::Return(Some(code)),
+            statements: vec![],
+        }]);
+        body.number();
+        self.push_function(Function {
+            location: Location::none(),
+            name: "@main".to_string(),
+            parameters: vec![
+                ("argc".to_string(), Type::Int(32)),
+                ("argv".to_string(), super::args::argv_type()),
+            ],
+            ret_typ: Type::Int(32),
+            body: Some(body),
+            inline: false,
+        });
+        self.push_function(main);
+    }
+    /// eliminate_dead_functions removes functions which can't be reached from `@main`, methods are
+    /// always kept. Module without `@main` is not changed.
+    pub fn eliminate_dead_functions(&mut self) {
+        if !self.functions.contains_key("@main") {
+            return;
+        }
+        let mut worklist: Vec<String> = self
+            .functions
+            .keys()
+            .filter(|name| name.as_str() == "@main" || Symbol::demangle(&name[1..]).is_ok())
+            .cloned()
+            .collect();
+        let mut reached = HashSet::new();
+        while let Some(name) = worklist.pop() {
+            if !reached.insert(name.clone()) {
+                continue;
+            }
+            if let Some(Function {
+                body: Some(body), ..
+            }) = self.functions.get(&name)
+            {
+                for inst in body.blocks.iter().flat_map(|block| &block.instructions) {
+                    match inst {
+                        Instruction::FunctionCall {
+                            func_name,
+                            args_expr,
+                            ..
+                        } => {
+                            worklist.push(func_name.clone());
+                            for arg in args_expr {
+                                if let Expr::Identifier(_, name) = arg {
+                                    worklist.push(format!("@{}", name));
+                                }
+                            }
+                        }
+                        Instruction::Malloca { .. } => worklist.push("@malloc".to_string()),
+                        Instruction::MemCopy { size, .. } => worklist.push(memcpy_name(size)),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        self.functions.retain(|name, _| reached.contains(name));
+    }
+    /// declare_memcpy declares `llvm.memcpy` taking the size as `size_type`
+    pub(crate) fn declare_memcpy(&mut self, size_type: Type) {
+        let c_string = Type::Pointer(Type::Int(8).into());
+        let name = memcpy_name(&Expr::Zero(size_type.clone()));
+        self.functions
+            .entry(name.clone())
+            .or_insert_with(|| Function {
+                location: Location::none(),
+                name,
+                parameters: vec![
+                    ("destination".to_string(), c_string.clone()),
+                    ("source".to_string(), c_string),
+                    ("size".to_string(), size_type),
+                    ("volatile".to_string(), Type::Int(1)),
+                ],
+                ret_typ: Type::Void,
+                body: None,
+                inline: false,
+            });
+    }
+    /// constant_fields returns values of fields of the construction, if all of them are literals
+    fn constant_fields(
+        &self,
+        class_name: &String,
+        field_inits: &HashMap<String, ast::Expr>,
+    ) -> Option<Vec<Expr>> {
+        let mut values = vec![];
+        for field in self.struct_fields(class_name) {
+            if field.name == REFCOUNT_FIELD {
+                values.push(Expr::Int(field.typ.as_ref().clone(), 1));
+                continue;
+            }
+            let init = field_inits
+                .get(&field.name)
+                .or_else(|| self.known_field_defaults[class_name].get(&field.name))?;
+            let value = match (&init.value, field.typ.as_ref()) {
+                (ExprVariant::Int(i), typ @ Type::Int(..)) => Expr::Int(typ.clone(), *i),
+                (ExprVariant::F64(f), _) => Expr::F64(*f),
+                (ExprVariant::Bool(b), _) => Expr::Bool(*b),
+                _ => return None,
+            };
+            values.push(value);
+        }
+        Some(values)
     }
     fn lookup_type(&self, type_name: &String) -> &Type {
         self.types.get(type_name).unwrap()
     }
+    /// struct_fields returns fields of the struct type named `type_name` in the layout order
+    pub(crate) fn struct_fields(&self, type_name: &String) -> &Vec<Field> {
+        match self.lookup_type(type_name) {
+            Type::Struct { fields, .. } => fields,
+            typ => unreachable!("`{:?}` is not a struct type", typ),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) struct ID {
-    value: u64,
+/// memcpy_name returns the name of `llvm.memcpy` taking the size of the type of `size`
+pub(crate) fn memcpy_name(size: &Expr) -> String {
+    match size.type_() {
+        Type::Int(bits) => format!("@llvm.memcpy.p0i8.p0i8.i{}", bits),
+        typ => unreachable!("size of `{:?}` is not an integer", typ),
+    }
+}
+
+/// ID names a value or a block, `ID::new` gives a temporary ID unique in the process, which
+/// `Body::number` replaces with the number LLVM expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ID {
+    Temporary(u64),
+    Number(u64),
 }
 
+/// NEXT_TEMPORARY is the value of the next temporary ID, it's shared by threads generating modules
+static NEXT_TEMPORARY: AtomicU64 = AtomicU64::new(0);
+
 impl ID {
-    fn new() -> Rc<RefCell<ID>> {
-        Rc::new(RefCell::new(ID { value: 0 }))
-    }
-    fn set_id(&mut self, value: u64) -> bool {
-        self.value = value;
-        true
+    pub(crate) fn new() -> ID {
+        ID::Temporary(NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed))
     }
 }
 
 impl std::fmt::Display for ID {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        match self {
+            ID::Temporary(value) => write!(f, "tmp.{}", value),
+            ID::Number(value) => write!(f, "{}", value),
+        }
     }
 }
 
 /// Label represents a location which can be the target of jump instructions
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Label {
-    pub(crate) id: Rc<RefCell<ID>>,
+    pub(crate) id: ID,
+    /// location of the source code which starts from this label
+    pub(crate) location: Location,
 }
 
 impl Label {
-    pub(crate) fn new(id: Rc<RefCell<ID>>) -> Rc<Label> {
-        Rc::new(Label { id })
+    pub(crate) fn new(id: ID, location: &Location) -> Label {
+        Label {
+            id,
+            location: location.clone(),
+        }
     }
 }
 
+/// BasicBlock is a sequence of instructions, control flow only enters from its label and only
+/// leaves from its terminator
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum Instruction {
+pub(crate) struct BasicBlock {
+    pub(crate) label: Label,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) terminator: Terminator,
+    /// locations of statements starting in the block, e.g. for coverage by lines
+    pub(crate) statements: Vec<Location>,
+}
+
+/// Terminator is the last instruction of a basic block
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Terminator {
     Return(Option<Expr>),
-    Label(Rc<Label>),
     Branch {
         cond: Expr,
-        if_true: Rc<Label>,
-        if_false: Rc<Label>,
+        if_true: Label,
+        if_false: Label,
+    },
+    Goto(Label),
+    /// Switch jumps to the label of the case equal to the integer `value`, or to `default`
+    Switch {
+        value: Expr,
+        default: Label,
+        cases: Vec<(i64, Label)>,
     },
-    Goto(Rc<Label>),
+    /// the block can't be reached, e.g. the block after an if statement whose branches all return
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Instruction {
     GEP {
-        id: Rc<RefCell<ID>>,
+        id: ID,
         load_from: Expr,
         indices: Vec<u64>,
     },
     FunctionCall {
-        id: Rc<RefCell<ID>>,
+        id: ID,
         func_name: String,
         ret_type: Box<Type>,
         args_expr: Vec<Expr>,
     },
     BinaryOperation {
-        id: Rc<RefCell<ID>>,
+        id: ID,
         op_name: String,
         lhs: Expr,
         rhs: Expr,
     },
+    /// Malloca allocates `size` bytes on heap
     Malloca {
-        id: Rc<RefCell<ID>>,
+        id: ID,
+        size: Expr,
+    },
+    Alloca {
+        id: ID,
         typ: Type,
     },
+    /// BitCast reinterprets the pointer `from` as a pointer of `target_type`
     BitCast {
-        id: Rc<RefCell<ID>>,
-        from_id: Rc<RefCell<ID>>,
+        id: ID,
+        from: Expr,
+        target_type: Type,
+    },
+    /// Cast converts the value `from` to `target_type` by the LLVM conversion `op_name`, e.g.
+    /// `zext`
+    Cast {
+        id: ID,
+        op_name: String,
+        from: Expr,
         target_type: Type,
     },
+    /// ElementPointer points to the element at `index` of the array `from` points to
+    ElementPointer {
+        id: ID,
+        from: Expr,
+        index: Expr,
+    },
     Load {
-        id: Rc<RefCell<ID>>,
+        id: ID,
         load_from: Expr,
     },
     Store {
         source: Expr,
-        destination: Rc<RefCell<ID>>,
+        destination: ID,
+    },
+    /// Phi takes the value from the block control flow comes from
+    Phi {
+        id: ID,
+        typ: Type,
+        incoming: Vec<(Expr, Label)>,
+    },
+    /// MemCopy copies `size` bytes from the `i8*` `source` to the `i8*` `destination` through
+    /// `llvm.memcpy`, which is declared by `Module::declare_memcpy`
+    MemCopy {
+        destination: Expr,
+        source: Expr,
+        size: Expr,
     },
+    /// DebugLocation marks the following instructions as code of the location until the next
+    /// one, it generates no code
+    DebugLocation(Location),
 }
 
 impl Instruction {
-    pub(crate) fn is_terminator(&self) -> bool {
-        use Instruction::*;
-        match self {
-            Return(..) | Branch { .. } | Goto(..) => true,
-            _ => false,
-        }
-    }
-
-    fn set_id(&mut self, value: u64) -> bool {
+    /// result_id returns the ID of the value the instruction defines
+    pub(crate) fn result_id(&self) -> Option<ID> {
         use Instruction::*;
         match self {
-            Label(label) => label.id.borrow_mut().set_id(value),
+            // call of void function has no result, so it doesn't take a number
+            FunctionCall { .. } if self.return_void() => None,
             Load { id, .. }
             | Malloca { id, .. }
+            | Alloca { id, .. }
             | BitCast { id, .. }
+            | Cast { id, .. }
+            | ElementPointer { id, .. }
             | GEP { id, .. }
             | FunctionCall { id, .. }
-            | BinaryOperation { id, .. } => id.borrow_mut().set_id(value),
-            _ => false,
+            | BinaryOperation { id, .. }
+            | Phi { id, .. } => Some(*id),
+            Store { .. } | MemCopy { .. } | DebugLocation(..) => None,
+        }
+    }
+    /// used_ids returns IDs of local values the instruction uses
+    pub(crate) fn used_ids(&self) -> Vec<ID> {
+        use Instruction::*;
+        let mut ids: Vec<ID> = self
+            .operands()
+            .into_iter()
+            .filter_map(Expr::local_id_of)
+            .collect();
+        if let Store { destination, .. } = self {
+            ids.push(*destination);
+        }
+        ids
+    }
+    /// operands returns values the instruction uses
+    pub(crate) fn operands(&self) -> Vec<&Expr> {
+        use Instruction::*;
+        match self {
+            GEP { load_from, .. }
+            | Load { load_from, .. }
+            | BitCast {
+                from: load_from, ..
+            }
+            | Cast {
+                from: load_from, ..
+            } => {
+                vec![load_from]
+            }
+            ElementPointer { from, index, .. } => vec![from, index],
+            FunctionCall { args_expr, .. } => args_expr.iter().collect(),
+            BinaryOperation { lhs, rhs, .. } => vec![lhs, rhs],
+            Malloca { size, .. } => vec![size],
+            Store { source, .. } => vec![source],
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => vec![destination, source, size],
+            Phi { incoming, .. } => incoming.iter().map(|(value, _)| value).collect(),
+            Alloca { .. } | DebugLocation(..) => vec![],
+        }
+    }
+    /// operands_mut returns values the instruction uses
+    pub(crate) fn operands_mut(&mut self) -> Vec<&mut Expr> {
+        use Instruction::*;
+        match self {
+            GEP { load_from, .. }
+            | Load { load_from, .. }
+            | BitCast {
+                from: load_from, ..
+            }
+            | Cast {
+                from: load_from, ..
+            } => {
+                vec![load_from]
+            }
+            ElementPointer { from, index, .. } => vec![from, index],
+            FunctionCall { args_expr, .. } => args_expr.iter_mut().collect(),
+            BinaryOperation { lhs, rhs, .. } => vec![lhs, rhs],
+            Malloca { size, .. } => vec![size],
+            Store { source, .. } => vec![source],
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => vec![destination, source, size],
+            Phi { incoming, .. } => incoming.iter_mut().map(|(value, _)| value).collect(),
+            Alloca { .. } | DebugLocation(..) => vec![],
+        }
+    }
+    /// local_ids_mut returns IDs of values and blocks the instruction defines or uses
+    pub(crate) fn local_ids_mut(&mut self) -> Vec<&mut ID> {
+        use Instruction::*;
+        match self {
+            GEP { id, load_from, .. }
+            | Load { id, load_from }
+            | BitCast {
+                id,
+                from: load_from,
+                ..
+            }
+            | Cast {
+                id,
+                from: load_from,
+                ..
+            } => {
+                let mut ids = vec![id];
+                ids.extend(load_from.local_ids_mut());
+                ids
+            }
+            ElementPointer { id, from, index } => {
+                let mut ids = vec![id];
+                ids.extend(from.local_ids_mut());
+                ids.extend(index.local_ids_mut());
+                ids
+            }
+            FunctionCall { id, args_expr, .. } => {
+                let mut ids = vec![id];
+                ids.extend(args_expr.iter_mut().flat_map(Expr::local_ids_mut));
+                ids
+            }
+            BinaryOperation { id, lhs, rhs, .. } => {
+                let mut ids = vec![id];
+                ids.extend(lhs.local_ids_mut());
+                ids.extend(rhs.local_ids_mut());
+                ids
+            }
+            Malloca { id, size } => {
+                let mut ids = vec![id];
+                ids.extend(size.local_ids_mut());
+                ids
+            }
+            Alloca { id, .. } => vec![id],
+            Store {
+                source,
+                destination,
+            } => {
+                let mut ids = source.local_ids_mut();
+                ids.push(destination);
+                ids
+            }
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => {
+                let mut ids = destination.local_ids_mut();
+                ids.extend(source.local_ids_mut());
+                ids.extend(size.local_ids_mut());
+                ids
+            }
+            Phi { id, incoming, .. } => {
+                let mut ids = vec![id];
+                for (value, label) in incoming {
+                    ids.extend(value.local_ids_mut());
+                    ids.push(&mut label.id);
+                }
+                ids
+            }
+            DebugLocation(..) => vec![],
+        }
+    }
+}
+
+impl Terminator {
+    /// operands returns values the terminator uses
+    pub(crate) fn operands(&self) -> Vec<&Expr> {
+        match self {
+            Terminator::Return(Some(value)) | Terminator::Switch { value, .. } => vec![value],
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Return(None) | Terminator::Goto(..) | Terminator::Unreachable => vec![],
+        }
+    }
+    /// targets returns labels of blocks the terminator jumps to
+    pub(crate) fn targets(&self) -> Vec<&Label> {
+        match self {
+            Terminator::Branch {
+                if_true, if_false, ..
+            } => vec![if_true, if_false],
+            Terminator::Goto(label) => vec![label],
+            Terminator::Switch { default, cases, .. } => std::iter::once(default)
+                .chain(cases.iter().map(|(_, label)| label))
+                .collect(),
+            Terminator::Return(..) | Terminator::Unreachable => vec![],
+        }
+    }
+    /// operands_mut returns values the terminator uses
+    pub(crate) fn operands_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Terminator::Return(Some(value)) | Terminator::Switch { value, .. } => vec![value],
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Return(None) | Terminator::Goto(..) | Terminator::Unreachable => vec![],
+        }
+    }
+    /// local_ids_mut returns IDs of values and blocks the terminator uses
+    fn local_ids_mut(&mut self) -> Vec<&mut ID> {
+        use Terminator::*;
+        match self {
+            Return(Some(value)) => value.local_ids_mut(),
+            Branch {
+                cond,
+                if_true,
+                if_false,
+            } => {
+                let mut ids = cond.local_ids_mut();
+                ids.push(&mut if_true.id);
+                ids.push(&mut if_false.id);
+                ids
+            }
+            Goto(label) => vec![&mut label.id],
+            Switch {
+                value,
+                default,
+                cases,
+            } => {
+                let mut ids = value.local_ids_mut();
+                ids.push(&mut default.id);
+                ids.extend(cases.iter_mut().map(|(_, label)| &mut label.id));
+                ids
+            }
+            Return(None) | Unreachable => vec![],
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum LocalVariable {
-    Name { typ: Type, name: String },
+    Name {
+        typ: Type,
+        name: String,
+    },
+    /// Value is a variable bound to a computed value, e.g. the element of a `for` loop
+    Value(Expr),
 }
 
 impl LocalVariable {
@@ -181,116 +766,313 @@ impl LocalVariable {
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Body {
-    pub(crate) instructions: Vec<Instruction>,
+    /// the first block is the entry block
+    pub(crate) blocks: Vec<BasicBlock>,
     // local variables(including parameters)
-    variables: HashMap<String, LocalVariable>,
+    pub(crate) variables: HashMap<String, LocalVariable>,
+    /// the block new instructions and statement locations are appended to, `None` after it's
+    /// terminated until the next block starts
+    pub(crate) current_block: Option<(Label, Vec<Instruction>, Vec<Location>)>,
+    /// temporaries holding a reference to a class instance, see `refcount`
+    pub(crate) owned: Vec<Expr>,
+    /// values held by enclosing loops and blocks, see `for_loop` and `generate_instructions`
+    pub(crate) held: Vec<Expr>,
 }
 
 impl Body {
-    fn from_ast(b: &ast::Body, module: &mut Module, parameters: &Vec<Parameter>) -> Body {
+    fn from_ast(
+        b: &ast::Body,
+        module: &mut Module,
+        parameters: &Vec<Parameter>,
+        ret_typ: &Type,
+    ) -> Body {
         let mut variables = HashMap::new();
 
         for p in parameters {
             // FIXME: type from duplicate in ir::Function, share information
             let local_var =
-                LocalVariable::from_name(p.name.clone(), Type::from_ast(&p.typ, module));
+                LocalVariable::from_name(p.name.clone(), Type::from_ast(&p.typ, &module.target));
             variables.insert(p.name.clone(), local_var);
         }
 
+        let location = match b {
+            ast::Body::Expr(e) => &e.location,
+            ast::Body::Block(b) => &b.location,
+        };
         let mut body = Body {
-            instructions: vec![],
+            blocks: vec![],
             variables,
+            current_block: Some((Label::new(ID::new(), location), vec![], vec![])),
+            owned: vec![],
+            held: vec![],
         };
         match b {
             ast::Body::Expr(e) => {
                 let e = body.expr_from_ast(e, module);
-                body.instructions.push(Instruction::Return(Some(e)));
+                if ret_typ == &Type::Void {
+                    body.release_temporaries(module);
+                    body.terminate(Terminator::Return(None));
+                } else {
+                    let e = body.take(e, module);
+                    body.release_temporaries(module);
+                    body.terminate(Terminator::Return(Some(e)));
+                }
             }
             ast::Body::Block(b) => body.generate_instructions(&b.statements, module),
         };
-        // update local identifier value
-        let mut counter = 1;
-        for inst in &mut body.instructions {
-            if inst.set_id(counter) {
-                counter += 1;
-            }
+        // void function can end without `return`, the semantic checker ensures others return on
+        // every path, so the rest block is never reached
+        if ret_typ == &Type::Void {
+            body.terminate(Terminator::Return(None));
+        } else {
+            body.terminate(Terminator::Unreachable);
         }
+        body.number();
         body
     }
+    /// with_blocks creates a body from generated blocks, e.g. a function added by the compiler,
+    /// the blocks must be numbered by `number`
+    pub(crate) fn with_blocks(blocks: Vec<BasicBlock>) -> Body {
+        Body {
+            blocks,
+            variables: HashMap::new(),
+            current_block: None,
+            owned: vec![],
+            held: vec![],
+        }
+    }
+
+    /// number replaces local IDs with numbers in the order of blocks and instructions, LLVM
+    /// numbers the entry block as `%0`, a body can be numbered again after it's changed
+    pub(crate) fn number(&mut self) {
+        let mut numbers = HashMap::new();
+        for block in &self.blocks {
+            numbers.insert(block.label.id, ID::Number(numbers.len() as u64));
+            for inst in &block.instructions {
+                if let Some(id) = inst.result_id() {
+                    numbers.insert(id, ID::Number(numbers.len() as u64));
+                }
+            }
+        }
+        for block in &mut self.blocks {
+            let ids = std::iter::once(&mut block.label.id)
+                .chain(
+                    block
+                        .instructions
+                        .iter_mut()
+                        .flat_map(Instruction::local_ids_mut),
+                )
+                .chain(block.terminator.local_ids_mut());
+            for id in ids {
+                if let Some(number) = numbers.get(id) {
+                    *id = *number;
+                }
+            }
+        }
+    }
 
     fn lookup_variable(&self, name: &String) -> Option<&LocalVariable> {
         self.variables.get(name)
     }
 
+    /// field_pointer emits GEP to the field `access` of `from`, returns the field type and the ID
+    /// of the pointer
+    fn field_pointer(&mut self, from: &ast::Expr, access: &str, module: &mut Module) -> (Type, ID) {
+        let v = self.expr_from_ast(from, module);
+        // access on a value not of a class, or to a field it doesn't have, is a bug of the
+        // semantic checker, the index out of fields is reported by `type_check`
+        let fields = match v.type_() {
+            Type::Pointer(element_type) => match element_type.deref() {
+                Type::Named(name) if module.types.contains_key(name) => {
+                    module.struct_fields(name).clone()
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        };
+        let i = fields
+            .iter()
+            .position(|field| field.name == access)
+            .unwrap_or(fields.len());
+        let result_type = fields
+            .get(i)
+            .map_or(Type::Void, |field| field.typ.deref().clone());
+        let gep_id = ID::new();
+        let inst = Instruction::GEP {
+            id: gep_id,
+            load_from: v,
+            indices: vec![0, i as u64],
+        };
+        self.push(inst);
+        (result_type, gep_id)
+    }
+
+    /// generate_instructions generates a block, locals declared in it are held until the end of
+    /// it, and the variables they shadowed are visible again after it
     pub(crate) fn generate_instructions(&mut self, stmts: &Vec<Statement>, module: &mut Module) {
+        let outer_variables = self.variables.clone();
+        let outer_held = self.held.len();
         for stmt in stmts {
+            let _compiling = ice::compiling(Phase::Codegen, &stmt.location);
+            if let Some((_, instructions, statements)) = &mut self.current_block {
+                statements.push(stmt.location.clone());
+                if module.debug_info {
+                    instructions.push(Instruction::DebugLocation(stmt.location.clone()));
+                }
+            }
             use ast::StatementVariant::*;
             match &stmt.value {
                 Return(e) => {
-                    let inst = match e {
-                        None => Instruction::Return(None),
-                        Some(ex) => Instruction::Return(Some(self.expr_from_ast(ex, module))),
-                    };
-                    self.instructions.push(inst)
+                    let value = e.as_ref().map(|ex| {
+                        let value = self.expr_from_ast(ex, module);
+                        self.take(value, module)
+                    });
+                    self.release_temporaries(module);
+                    self.release_held(module);
+                    self.terminate(Terminator::Return(value))
                 }
                 Expression(expr) => {
                     self.expr_from_ast(expr, module);
                 }
+                // store into the field directly, without loading it as reading the field does
+                Assign { target, value } => {
+                    let (from, access) = match &target.value {
+                        ast::ExprVariant::MemberAccess(from, access) => (from, access),
+                        _ => unreachable!(
+                            "assign to non-member which unlikely happen: `{:?}`",
+                            target
+                        ),
+                    };
+                    let (typ, gep_id) = self.field_pointer(from, access, module);
+                    let source = self.expr_from_ast(value, module);
+                    // the new value is retained before the old one is released, so assigning a
+                    // field to itself keeps the instance
+                    let old = if module.is_refcounted(&typ) {
+                        let id = ID::new();
+                        self.push(Instruction::Load {
+                            id,
+                            load_from: Expr::local_id(typ.clone(), gep_id),
+                        });
+                        Some(Expr::local_id(typ, id))
+                    } else {
+                        None
+                    };
+                    let source = self.take(source, module);
+                    self.push(Instruction::Store {
+                        source,
+                        destination: gep_id,
+                    });
+                    if let Some(old) = old {
+                        self.release(old, module);
+                    }
+                }
                 IfBlock {
                     clauses,
                     else_block,
                 } => {
-                    let leave_label = Label::new(ID::new());
-                    for (cond, then_block) in clauses {
-                        let if_then_label = Label::new(ID::new());
-                        let else_then_label = Label::new(ID::new());
-                        let inst = Instruction::Branch {
-                            cond: self.expr_from_ast(cond, module),
+                    let leave_label = Label::new(ID::new(), &stmt.location);
+                    for (i, (cond, then_block)) in clauses.iter().enumerate() {
+                        let if_then_label = Label::new(ID::new(), &then_block.location);
+                        // else then part starts from the next condition, or the else block
+                        let else_then_location = match clauses.get(i + 1) {
+                            Some((next_cond, _)) => &next_cond.location,
+                            None => &else_block.location,
+                        };
+                        let else_then_label = Label::new(ID::new(), else_then_location);
+                        let cond = self.expr_from_ast(cond, module);
+                        self.release_temporaries(module);
+                        let terminator = Terminator::Branch {
+                            cond,
                             if_true: if_then_label.clone(),
                             if_false: else_then_label.clone(),
                         };
-                        self.instructions.push(inst);
+                        self.terminate(terminator);
                         // if then
-                        self.instructions
-                            .push(Instruction::Label(if_then_label.clone()));
+                        self.start_block(if_then_label.clone());
                         self.generate_instructions(&then_block.statements, module);
-                        if !self.end_with_terminator() {
-                            self.goto(&leave_label);
-                        }
+                        self.goto(&leave_label);
                         // else then
-                        self.instructions
-                            .push(Instruction::Label(else_then_label.clone()));
+                        self.start_block(else_then_label.clone());
                     }
                     self.generate_instructions(&else_block.statements, module);
-                    if !self.end_with_terminator() {
-                        self.goto(&leave_label);
-                    }
-                    self.instructions
-                        .push(Instruction::Label(leave_label.clone()));
+                    self.start_block(leave_label.clone());
                 }
                 Variable(v) => {
-                    self.expr_from_ast(&v.expr, module);
+                    let value = self.expr_from_ast(&v.expr, module);
+                    let value = self.take(value, module);
+                    self.held.push(value.clone());
+                    self.variables
+                        .insert(v.name.clone(), LocalVariable::Value(value));
                 }
+                For {
+                    name,
+                    name_location,
+                    iterable,
+                    block,
+                } => self.for_loop(name, name_location, iterable, block, module),
             }
+            if self.current_block.is_some() {
+                self.release_temporaries(module);
+            }
+        }
+        let locals = self.held.split_off(outer_held);
+        // a block ending with `return` released what it held
+        if self.current_block.is_some() {
+            for value in locals.into_iter().rev() {
+                self.release_held_value(value, module);
+            }
+        }
+        self.variables = outer_variables;
+    }
+    /// goto jumps to `label` from the current block, a block ends with `return` is left as is
+    pub(crate) fn goto(&mut self, label: &Label) {
+        self.terminate(Terminator::Goto(label.clone()));
+    }
+    /// push appends `inst` to the current block
+    pub(crate) fn push(&mut self, inst: Instruction) {
+        match &mut self.current_block {
+            Some((_, instructions, _)) => instructions.push(inst),
+            // the semantic checker rejects dead code, so nothing follows a terminator
+            None => unreachable!("instruction after terminator: {:?}", inst),
+        }
+    }
+    /// terminate ends the current block with `terminator`, it does nothing when the block was
+    /// terminated
+    pub(crate) fn terminate(&mut self, terminator: Terminator) {
+        if let Some((label, instructions, statements)) = self.current_block.take() {
+            self.blocks.push(BasicBlock {
+                label,
+                instructions,
+                terminator,
+                statements,
+            });
         }
     }
-    fn end_with_terminator(&self) -> bool {
-        match self.instructions.last() {
-            None => false,
-            Some(inst) => inst.is_terminator(),
+    /// current_label returns the label of current block
+    fn current_label(&self) -> Label {
+        match &self.current_block {
+            Some((label, _, _)) => label.clone(),
+            None => unreachable!("no current block after terminator"),
         }
     }
-    fn goto(&mut self, label: &Rc<Label>) {
-        self.instructions.push(Instruction::Goto(label.clone()));
+    /// start_block starts appending instructions to the block of `label`, the previous block
+    /// falls through to it, and LLVM requires the jump to be explicit
+    pub(crate) fn start_block(&mut self, label: Label) {
+        self.goto(&label);
+        self.current_block = Some((label, vec![], vec![]));
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Function {
+    pub(crate) location: Location,
     pub(crate) name: String,
     pub(crate) parameters: Vec<(String, Type)>,
     pub(crate) ret_typ: Type,
     pub(crate) body: Option<Body>,
+    /// inline is true for functions tagged `@inline`, calls to them are inlined regardless of the
+    /// size of their bodies
+    pub(crate) inline: bool,
 }
 
 impl Function {
@@ -299,39 +1081,48 @@ impl Function {
         class_name: Option<String>,
         module: &mut Module,
     ) -> Function {
+        let _compiling = ice::compiling(Phase::Codegen, &f.location);
+        let ret_typ = Type::from_ast(&f.ret_typ, &module.target);
         let body = match &f.body {
-            Some(b) => Some(Body::from_ast(b, module, &f.parameters)),
+            Some(b) => Some(Body::from_ast(b, module, &f.parameters, &ret_typ)),
             None => None,
         };
         let function_name = match class_name {
             None => f.name.clone(),
-            Some(class_name) => format!("\"{}::{}\"", class_name, f.name),
+            Some(class_name) => module.method_symbol(&class_name, &f.name),
         };
-        Function::new(
-            function_name,
-            &f.parameters,
-            Type::from_ast(&f.ret_typ, module),
-            body,
-            module,
-        )
+        Function {
+            inline: f.tag.is_inline(),
+            ..Function::new(
+                &f.location,
+                function_name,
+                &f.parameters,
+                ret_typ,
+                body,
+                &module.target,
+            )
+        }
     }
     fn new(
+        location: &Location,
         name: String,
         parsed_params: &Vec<Parameter>,
         ret_typ: Type,
         body: Option<Body>,
-        module: &Module,
+        target: &Target,
     ) -> Function {
         let parameters: Vec<(String, Type)> = parsed_params
             .iter()
-            .map(|p| (p.name.clone(), Type::from_ast(&p.typ, module)))
+            .map(|p| (p.name.clone(), Type::from_ast(&p.typ, target)))
             .collect();
         Function {
+            location: location.clone(),
             // function name need @, e.g. @main
             name: format!("@{}", name),
             parameters,
             ret_typ,
             body,
+            inline: false,
         }
     }
 }
@@ -344,7 +1135,7 @@ pub(crate) struct Variable {
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum GlobalName {
-    ID(Rc<RefCell<ID>>),
+    ID(ID),
     String(String),
 }
 
@@ -355,12 +1146,11 @@ impl Variable {
             expr,
         }
     }
-    pub(crate) fn from_id(id: Rc<RefCell<ID>>, expr: Expr) -> Variable {
-        Variable {
-            name: GlobalName::ID(id),
-            expr,
-        }
-    }
+}
+
+/// align_to rounds `offset` up to a multiple of `align`
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -368,254 +1158,568 @@ pub(crate) enum Type {
     Void,
     Int(usize),
     Float(usize),
-    Pointer(Rc<Type>),
-    Array { len: usize, element_type: Rc<Type> },
-    Struct { name: String, fields: Vec<Field> },
+    Pointer(Arc<Type>),
+    Array {
+        len: usize,
+        element_type: Arc<Type>,
+    },
+    /// layout of class, only stored in `Module.types` and emitted once as `%Foo = type { ... }`
+    Struct {
+        name: String,
+        fields: Vec<Field>,
+    },
+    /// reference to struct type defined in `Module.types`, e.g. `%Foo`
     Named(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Field {
     pub(crate) name: String,
-    pub(crate) typ: Rc<Type>,
+    pub(crate) typ: Arc<Type>,
 }
 
 impl Type {
-    pub(crate) fn from_ast(t: &ast::ParsedType, module: &Module) -> Type {
+    pub(crate) fn from_ast(t: &ast::ParsedType, target: &Target) -> Type {
         use Type::*;
         match t.name().as_str() {
             "void" => Void,
-            "int" => Int(64),
+            "int" => target.int_type(),
+            "i32" => Int(32),
+            "u8" => Int(8),
             "f64" => Float(64),
             "bool" => Int(1),
             "_c_string" => Pointer(Int(8).into()),
-            name => module.lookup_type(&name.to_string()).clone(),
+            ERASED_TYPE => Int(64),
+            name => Type::class(name),
         }
     }
+    /// class returns type of values of class `name`, they are pointers to the struct allocated
+    /// on heap, so the layout is not needed, and classes can refer to each other. A function
+    /// returning a class returns the pointer in a register, so it never needs `sret` of the C ABI
+    pub(crate) fn class<T: ToString>(name: T) -> Type {
+        Type::Pointer(Type::Named(name.to_string()).into())
+    }
 
-    pub(crate) fn element_type(&self) -> Rc<Type> {
+    pub(crate) fn element_type(&self) -> Arc<Type> {
         use Type::*;
         match self {
-            Struct { name, .. } => Named(name.clone()).into(),
             Pointer(element_type) | Array { element_type, .. } => element_type.clone(),
             _ => unreachable!("`{:?}` don't have element type", self),
         }
     }
 
-    pub(crate) fn size(&self) -> usize {
+    /// size returns the allocation size in bytes, a struct is padded at the end, so every element
+    /// of an array of it is aligned
+    pub(crate) fn size(&self, target: &Target) -> usize {
         use Type::*;
         match self {
-            Int(size) | Float(size) => *size,
-            Pointer(..) => 64,
-            Array { len, element_type } => len * element_type.size(),
-            Struct { fields, .. } => {
-                let mut size = 0;
-                for field in fields {
-                    size += field.typ.size();
-                }
-                size
+            // `i1` takes a whole byte
+            Int(bits) | Float(bits) => bits.div_ceil(8).next_power_of_two(),
+            Pointer(..) => target.pointer_size,
+            Array { len, element_type } => len * element_type.size(target),
+            Struct { .. } => {
+                let end = match (self.field_offsets(target).last(), self.fields().last()) {
+                    (Some(offset), Some(field)) => offset + field.typ.size(target),
+                    _ => 0,
+                };
+                align_to(end, self.align(target))
             }
-            _ => 0,
+            Void | Named(..) => unreachable!("`{:?}` has no size", self),
+        }
+    }
+    /// align returns the alignment in bytes, scalar is aligned to its size, and a struct is
+    /// aligned to its most aligned field
+    pub(crate) fn align(&self, target: &Target) -> usize {
+        use Type::*;
+        match self {
+            Int(..) | Float(..) | Pointer(..) => self.size(target),
+            Array { element_type, .. } => element_type.align(target),
+            Struct { fields, .. } => fields
+                .iter()
+                .map(|f| f.typ.align(target))
+                .max()
+                .unwrap_or(1),
+            Void | Named(..) => unreachable!("`{:?}` has no alignment", self),
+        }
+    }
+    /// field_offsets returns the byte offset of each field of the struct, each field starts from
+    /// the first offset aligned for it after the previous field
+    pub(crate) fn field_offsets(&self, target: &Target) -> Vec<usize> {
+        let mut offsets = vec![];
+        let mut end = 0;
+        for field in self.fields() {
+            let offset = align_to(end, field.typ.align(target));
+            offsets.push(offset);
+            end = offset + field.typ.size(target);
+        }
+        offsets
+    }
+    fn fields(&self) -> &Vec<Field> {
+        match self {
+            Type::Struct { fields, .. } => fields,
+            _ => unreachable!("`{:?}` is not a struct type", self),
         }
     }
 }
 
 impl Body {
-    fn expr_from_ast(&mut self, expr: &ast::Expr, module: &mut Module) -> Expr {
+    pub(crate) fn expr_from_ast(&mut self, expr: &ast::Expr, module: &mut Module) -> Expr {
         use ast::ExprVariant::*;
         match &expr.value {
             String(string_literal) => {
-                let str_literal_id = ID::new();
-                module.push_variable(Variable::from_id(
-                    str_literal_id.clone(),
-                    Expr::CString(string_literal.clone()),
-                ));
+                let c_string = Expr::CString(string_literal.clone());
+                let array_type = c_string.type_();
+                let str_literal_id = module.push_constant(c_string);
                 let str_load_id = ID::new();
-                let array_type = Type::Array {
-                    len: string_literal.len(),
-                    element_type: Type::Int(8).into(),
-                };
                 let inst = Instruction::GEP {
-                    id: str_load_id.clone(),
+                    id: str_load_id,
                     load_from: Expr::global_id(Type::Pointer(array_type.into()), str_literal_id),
                     indices: vec![0, 0],
                 };
-                self.instructions.push(inst);
+                self.push(inst);
                 let ptr_to_str = Expr::local_id(Type::Pointer(Type::Int(8).into()), str_load_id);
                 let id = ID::new();
-                let ret_type = module.lookup_type(&"string".to_string());
+                let ret_type = Type::class("string");
                 let inst = Instruction::FunctionCall {
-                    id: id.clone(),
-                    func_name: format!("@\"string::new\""),
+                    id,
+                    func_name: format!("@{}", module.method_symbol("string", "new")),
                     ret_type: ret_type.clone().into(),
                     args_expr: vec![ptr_to_str],
                 };
-                self.instructions.push(inst);
-                Expr::local_id(ret_type.clone(), id)
+                self.push(inst);
+                self.own(Expr::local_id(ret_type, id), module)
             }
             ClassConstruction(class_name, field_inits) => {
                 let alloca_id = ID::new();
-                let class_type = module.lookup_type(class_name).clone();
+                let class_type = Type::class(class_name);
+                let size = module.lookup_type(class_name).size(&module.target);
                 let inst = Instruction::Malloca {
-                    id: alloca_id.clone(),
-                    typ: class_type.clone(),
+                    id: alloca_id,
+                    size: Expr::Int(module.target.int_type(), size as i64),
                 };
-                self.instructions.push(inst);
+                self.push(inst);
+                let memory = Expr::local_id(Type::Pointer(Type::Int(8).into()), alloca_id);
                 let bitcast_id = ID::new();
                 let inst = Instruction::BitCast {
-                    id: bitcast_id.clone(),
-                    from_id: alloca_id,
+                    id: bitcast_id,
+                    from: memory.clone(),
                     target_type: class_type.clone(),
                 };
-                self.instructions.push(inst);
+                self.push(inst);
+                // an instance of literals is copied from a constant one at once
+                if let Some(values) = module.constant_fields(class_name, field_inits) {
+                    let size = Expr::Int(module.target.int_type(), size as i64);
+                    let template_id =
+                        module.push_constant(Expr::Struct(class_name.clone(), values));
+                    let source_id = ID::new();
+                    self.push(Instruction::BitCast {
+                        id: source_id,
+                        from: Expr::global_id(class_type.clone(), template_id),
+                        target_type: Type::Pointer(Type::Int(8).into()),
+                    });
+                    module.declare_memcpy(size.type_());
+                    self.push(Instruction::MemCopy {
+                        destination: memory,
+                        source: Expr::local_id(Type::Pointer(Type::Int(8).into()), source_id),
+                        size,
+                    });
+                    return self.own(Expr::local_id(class_type, bitcast_id), module);
+                }
 
                 // store value into field
-                let fields =
-                    if let Type::Struct { fields, .. } = module.lookup_type(class_name).clone() {
-                        fields
-                    } else {
-                        unreachable!("non-class type cannot access member")
-                    };
+                let fields = module.struct_fields(class_name).clone();
                 for (i, field) in fields.iter().enumerate() {
                     let gep_id = ID::new();
                     let inst = Instruction::GEP {
-                        id: gep_id.clone(),
-                        load_from: Expr::local_id(class_type.clone(), bitcast_id.clone()),
+                        id: gep_id,
+                        load_from: Expr::local_id(class_type.clone(), bitcast_id),
                         indices: vec![0, i as u64],
                     };
-                    self.instructions.push(inst);
-                    let init_value = field_inits.get(&field.name).expect(
-                        format!(
-                            "field `{}` doesn't exist, which is unlikely happened",
-                            field.name
-                        )
-                        .as_str(),
-                    );
+                    self.push(inst);
+                    // a new instance has one reference, the value of the construction
+                    if field.name == REFCOUNT_FIELD {
+                        self.push(Instruction::Store {
+                            source: Expr::Int(module.target.int_type(), 1),
+                            destination: gep_id,
+                        });
+                        continue;
+                    }
+                    // omitted field uses its default value
+                    let init_value = match field_inits.get(&field.name) {
+                        Some(init_value) => init_value.clone(),
+                        None => module.known_field_defaults[class_name]
+                            .get(&field.name)
+                            .expect(
+                                format!(
+                                    "field `{}` doesn't have value, which is unlikely happened",
+                                    field.name
+                                )
+                                .as_str(),
+                            )
+                            .clone(),
+                    };
+                    let init_value = &init_value;
                     let expr = self.expr_from_ast(init_value, module);
+                    let expr = self.take(expr, module);
                     let inst = Instruction::Store {
                         source: expr,
                         destination: gep_id,
                     };
-                    self.instructions.push(inst);
+                    self.push(inst);
                 }
 
-                Expr::local_id(class_type, bitcast_id)
+                self.own(Expr::local_id(class_type, bitcast_id), module)
             }
             MemberAccess(from, access) => {
-                let v = self.expr_from_ast(from, module);
-                let typ = if let Type::Named(name) = v.type_() {
-                    module.lookup_type(&name).clone()
-                } else {
-                    v.type_()
+                let (result_type, gep_id) = self.field_pointer(from, access, module);
+                let id = ID::new();
+                let inst = Instruction::Load {
+                    id,
+                    load_from: Expr::local_id(result_type.clone(), gep_id),
                 };
-                match typ {
-                    Type::Struct { fields, .. } => {
-                        let mut i = 0; // get index of field
-                        for field in &fields {
-                            if &field.name == access {
-                                break;
-                            } else {
-                                i += 1;
-                            }
-                        }
-                        let result_type = fields[i].typ.deref().clone();
-                        let gep_id = ID::new();
-                        let inst = Instruction::GEP {
-                            id: gep_id.clone(),
-                            load_from: v.clone(),
-                            indices: vec![0, i as u64],
-                        };
-                        self.instructions.push(inst);
-                        let id = ID::new();
-                        let inst = Instruction::Load {
-                            id: id.clone(),
-                            load_from: Expr::local_id(result_type.clone(), gep_id),
-                        };
-                        self.instructions.push(inst);
-                        Expr::local_id(result_type, id)
-                    }
-                    _ => unreachable!(
-                        "access member on non-class type which unlikely happen: from `{:?}`",
-                        from
-                    ),
-                }
+                self.push(inst);
+                Expr::local_id(result_type, id)
+            }
+            If(condition, then_expr, else_expr) => {
+                let then_label = Label::new(ID::new(), &then_expr.location);
+                let else_label = Label::new(ID::new(), &else_expr.location);
+                let leave_label = Label::new(ID::new(), &expr.location);
+                let cond = self.expr_from_ast(condition, module);
+                self.terminate(Terminator::Branch {
+                    cond,
+                    if_true: then_label.clone(),
+                    if_false: else_label.clone(),
+                });
+                // a branch can leave from another block than it starts with, e.g. a nested if
+                // expression, so the incoming block is taken after lowering the branch
+                // both branches give a reference to the result
+                self.start_block(then_label);
+                let outer = self.enter_branch();
+                let then_value = self.expr_from_ast(then_expr, module);
+                let then_value = self.take(then_value, module);
+                self.leave_branch(outer, module);
+                let then_end = self.current_label();
+                self.goto(&leave_label);
+                self.start_block(else_label);
+                let outer = self.enter_branch();
+                let else_value = self.expr_from_ast(else_expr, module);
+                let else_value = self.take(else_value, module);
+                self.leave_branch(outer, module);
+                let else_end = self.current_label();
+                self.goto(&leave_label);
+                self.start_block(leave_label);
+                let typ = then_value.type_();
+                let id = ID::new();
+                self.push(Instruction::Phi {
+                    id,
+                    typ: typ.clone(),
+                    incoming: vec![(then_value, then_end), (else_value, else_end)],
+                });
+                self.own(Expr::local_id(typ, id), module)
+            }
+            Binary(lhs, rhs, op) if op.is_logical() => {
+                // `a and b` skips `b` when `a` is false, `a or b` skips `b` when `a` is true, the
+                // result is kept in a stack slot written by both paths
+                let slot_id = ID::new();
+                self.push(Instruction::Alloca {
+                    id: slot_id,
+                    typ: Type::Int(1),
+                });
+                let lhs = self.expr_from_ast(lhs, module);
+                self.push(Instruction::Store {
+                    source: lhs.clone(),
+                    destination: slot_id,
+                });
+                let rhs_label = Label::new(ID::new(), &rhs.location);
+                let leave_label = Label::new(ID::new(), &expr.location);
+                let (if_true, if_false) = match op {
+                    Operator::And => (rhs_label.clone(), leave_label.clone()),
+                    _ => (leave_label.clone(), rhs_label.clone()),
+                };
+                self.terminate(Terminator::Branch {
+                    cond: lhs,
+                    if_true,
+                    if_false,
+                });
+                self.start_block(rhs_label);
+                let outer = self.enter_branch();
+                let rhs = self.expr_from_ast(rhs, module);
+                self.push(Instruction::Store {
+                    source: rhs,
+                    destination: slot_id,
+                });
+                self.leave_branch(outer, module);
+                self.goto(&leave_label);
+                self.start_block(leave_label);
+                let id = ID::new();
+                self.push(Instruction::Load {
+                    id,
+                    load_from: Expr::local_id(Type::Int(1), slot_id),
+                });
+                Expr::local_id(Type::Int(1), id)
             }
             Binary(lhs, rhs, op) => {
+                if let Some(s) = self.concat_pieces(expr, module) {
+                    return s;
+                }
                 let id = ID::new();
                 let lhs = self.expr_from_ast(lhs, module);
                 let rhs = self.expr_from_ast(rhs, module);
-                let result_typ = lhs.type_();
-                let op_name = match op {
-                    Operator::Plus => "add",
+                // integer literal takes the integer type of the other operand, as the semantic
+                // checker does, when the type of the literal wasn't inferred
+                let (lhs, rhs) = match (lhs, rhs) {
+                    (Expr::Int(_, i), rhs) if matches!(rhs.type_(), Type::Int(..)) => {
+                        (Expr::Int(rhs.type_(), i), rhs)
+                    }
+                    (lhs, Expr::Int(_, i)) if matches!(lhs.type_(), Type::Int(..)) => {
+                        let typ = lhs.type_();
+                        (lhs, Expr::Int(typ, i))
+                    }
+                    operands => operands,
+                };
+                let operand_typ = lhs.type_();
+                // `+` of strings calls `string::concat`
+                if operand_typ == Type::class("string") && *op == Operator::Plus {
+                    self.push(Instruction::FunctionCall {
+                        id,
+                        func_name: format!("@{}", module.method_symbol("string", "concat")),
+                        ret_type: operand_typ.clone().into(),
+                        args_expr: vec![lhs, rhs],
+                    });
+                    return self.own(Expr::local_id(operand_typ, id), module);
+                }
+                // `**` has no instruction, it calls `int::_pow` or `pow` of libm
+                if *op == Operator::Power {
+                    let path = match operand_typ {
+                        Type::Float(..) => "pow".to_string(),
+                        _ => method_path("int", "_pow"),
+                    };
+                    return self.call(&path, vec![lhs, rhs], module);
+                }
+                // `==` of instances calls `eq` of `Eq`, `!=` negates it
+                if let Type::Pointer(..) = operand_typ {
+                    let class_name = module.class_of(&operand_typ);
+                    self.push(Instruction::FunctionCall {
+                        id,
+                        func_name: format!("@{}", module.method_symbol(&class_name, "eq")),
+                        ret_type: Type::Int(1).into(),
+                        args_expr: vec![lhs, rhs],
+                    });
+                    let equal = Expr::local_id(Type::Int(1), id);
+                    if *op == Operator::Equal {
+                        return equal;
+                    }
+                    let id = ID::new();
+                    self.push(Instruction::BinaryOperation {
+                        id,
+                        op_name: "icmp eq".to_string(),
+                        lhs: equal,
+                        rhs: Expr::Bool(false),
+                    });
+                    return Expr::local_id(Type::Int(1), id);
+                }
+                // operands of different types are a bug of the semantic checker, which
+                // `type_check` reports
+                let op_name = match &operand_typ {
+                    Type::Float(..) => float_op_name(op),
+                    // u8 is the only unsigned integer type
+                    typ => integer_op_name(op, typ == &Type::Int(8)),
                 }
                 .to_string();
+                let result_typ = if op.is_comparison() {
+                    Type::Int(1)
+                } else {
+                    operand_typ
+                };
                 let inst = Instruction::BinaryOperation {
-                    id: id.clone(),
+                    id,
                     op_name,
                     lhs,
                     rhs,
                 };
-                self.instructions.push(inst);
+                self.push(inst);
                 Expr::local_id(result_typ, id)
             }
             FuncCall(f, args) => {
-                let id = self.expr_from_ast(f, module);
-                let name = match id {
-                    Expr::Identifier(_, name) => name,
-                    e => unreachable!("call on a non-function expression: {:#?}", e),
+                // a method is called with the object as `self`, e.g. `p.to_string()` calls
+                // `Point::to_string(p)`
+                let (name, mut args_expr) = match &f.value {
+                    MemberAccess(from, method) => {
+                        let object = self.expr_from_ast(from, module);
+                        let class_name = module.class_of(&object.type_());
+                        (method_path(&class_name, method), vec![object])
+                    }
+                    _ => match self.expr_from_ast(f, module) {
+                        Expr::Identifier(_, name) => (name, vec![]),
+                        e => unreachable!("call on a non-function expression: {:#?}", e),
+                    },
                 };
                 match module.known_functions.get(&name).cloned() {
                     Some(ret_type) => {
-                        let args_expr: Vec<Expr> = args
-                            .iter()
-                            .map(|arg| self.expr_from_ast(&arg.expr, module))
-                            .collect();
+                        let erased = module.erased_signatures.get(&name).cloned();
+                        let parameters = module.known_parameters.get(&name).cloned();
+                        for (i, arg) in args.iter().enumerate() {
+                            let value = self.expr_from_ast(&arg.expr, module);
+                            // integer literal takes the integer type of the parameter, as the
+                            // semantic checker does, when the type of the literal wasn't inferred
+                            let value = match (value, parameters.as_ref().and_then(|p| p.get(i))) {
+                                (Expr::Int(_, n), Some(typ @ Type::Int(..))) => {
+                                    Expr::Int(typ.clone(), n)
+                                }
+                                (value, _) => value,
+                            };
+                            args_expr.push(match &erased {
+                                Some(signature) if signature.parameters[i] => {
+                                    self.erase(value, module)
+                                }
+                                _ => value,
+                            });
+                        }
+                        if module.builtin_functions.contains(&name) {
+                            let value = self.call_builtin(&name, args_expr, ret_type, module);
+                            return match erased {
+                                Some(signature) if signature.ret => {
+                                    self.restore(value, &expr.location, module)
+                                }
+                                _ => value,
+                            };
+                        }
+                        // a specialized method is called on a list
+                        let (symbol, ret_type) = if module.specialized_methods.contains_key(&name) {
+                            (module.specialization(&name, &args[0].expr.location), ret_type)
+                        } else if module.generic_functions.contains_key(&name) {
+                            module.instance(&name, args, &expr.location)
+                        } else {
+                            (module.function_symbol(&name), ret_type)
+                        };
                         let id = ID::new();
                         let inst = Instruction::FunctionCall{
-                            id: id.clone(),
-                            func_name: format!("@{}", name),
+                            id,
+                            func_name: format!("@{}", symbol),
                             ret_type: ret_type.clone().into(),
                             args_expr,
                         };
-                        self.instructions.push(inst);
-                        Expr::local_id(ret_type.clone(), id)
+                        self.push(inst);
+                        match erased {
+                            Some(signature) if signature.ret => {
+                                self.restore(Expr::local_id(ret_type, id), &expr.location, module)
+                            }
+                            _ => self.own(Expr::local_id(ret_type, id), module),
+                        }
                     },
                     None => unreachable!("no function named: `{}` which unlikely happened, semantic module must have a bug there!", name),
                 }
             }
+            // `[a, b]` is a new list with `a` and `b` pushed
+            List(elements) => {
+                let list_type = Type::class("List");
+                let id = ID::new();
+                self.push(Instruction::FunctionCall {
+                    id,
+                    func_name: format!("@{}", module.method_symbol("List", "new")),
+                    ret_type: list_type.clone().into(),
+                    args_expr: vec![],
+                });
+                let list = self.own(Expr::local_id(list_type, id), module);
+                for element in elements.iter() {
+                    let value = self.expr_from_ast(element, module);
+                    let value = self.erase(value, module);
+                    self.push(Instruction::FunctionCall {
+                        id: ID::new(),
+                        func_name: format!("@{}", module.method_symbol("List", "push")),
+                        ret_type: Type::Void.into(),
+                        args_expr: vec![list.clone(), value],
+                    });
+                }
+                list
+            }
             Identifier(name) => match self.lookup_variable(name) {
                 Some(local_var) => match local_var {
                     LocalVariable::Name { name, typ } => {
                         Expr::Identifier(typ.clone(), name.clone())
                     }
+                    LocalVariable::Value(value) => value.clone(),
                 },
                 None => {
                     let ret_type = module.known_functions.get(name).expect(format!("no variable named: `{}` which unlikely happened, semantic module must have a bug there!", name).as_str());
                     Expr::Identifier(ret_type.clone(), name.clone())
                 }
             },
-            _ => Expr::from_ast(expr),
+            // integer literal takes the integer type the semantic checker inferred, e.g. `u8` of
+            // `1` in `x + 1` where `x: u8`
+            Int(i) => match module.inferred_type(&expr.location) {
+                Some(typ @ Type::Int(..)) => Expr::Int(typ, *i),
+                _ => Expr::from_ast(expr, &module.target),
+            },
+            _ => Expr::from_ast(expr, &module.target),
+        }
+    }
+}
+
+fn integer_op_name(op: &Operator, unsigned: bool) -> &'static str {
+    match (op, unsigned) {
+        (Operator::Plus, _) => "add",
+        (Operator::Minus, _) => "sub",
+        (Operator::Multiply, _) => "mul",
+        (Operator::Divide, false) => "sdiv",
+        (Operator::Divide, true) => "udiv",
+        (Operator::Equal, _) => "icmp eq",
+        (Operator::NotEqual, _) => "icmp ne",
+        (Operator::Less, false) => "icmp slt",
+        (Operator::Less, true) => "icmp ult",
+        (Operator::LessEqual, false) => "icmp sle",
+        (Operator::LessEqual, true) => "icmp ule",
+        (Operator::Greater, false) => "icmp sgt",
+        (Operator::Greater, true) => "icmp ugt",
+        (Operator::GreaterEqual, false) => "icmp sge",
+        (Operator::GreaterEqual, true) => "icmp uge",
+        (Operator::And, _) | (Operator::Or, _) => {
+            unreachable!("logical operator is lowered with branches")
         }
+        (Operator::Power, _) => unreachable!("`**` is lowered to a call"),
+    }
+}
+
+/// float_op_name uses ordered predicates for comparison, so comparing with NaN is false
+fn float_op_name(op: &Operator) -> &'static str {
+    match op {
+        Operator::Plus => "fadd",
+        Operator::Minus => "fsub",
+        Operator::Multiply => "fmul",
+        Operator::Divide => "fdiv",
+        Operator::Equal => "fcmp oeq",
+        Operator::NotEqual => "fcmp one",
+        Operator::Less => "fcmp olt",
+        Operator::LessEqual => "fcmp ole",
+        Operator::Greater => "fcmp ogt",
+        Operator::GreaterEqual => "fcmp oge",
+        Operator::And | Operator::Or => unreachable!("logical operator is lowered with branches"),
+        Operator::Power => unreachable!("`**` is lowered to a call"),
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Expr {
-    I64(i64),
+    /// integer constant of the integer type
+    Int(Type, i64),
     F64(f64),
     Bool(bool),
     CString(String),
     Identifier(Type, String),
-    LocalIdentifier(Type, Rc<RefCell<ID>>),
-    GlobalIdentifier(Type, Rc<RefCell<ID>>),
+    LocalIdentifier(Type, ID),
+    GlobalIdentifier(Type, ID),
+    /// Zero is the value of the type with all bits zero, e.g. initial counters of coverage
+    Zero(Type),
+    /// Struct is a constant instance of the class, with values of its fields in layout order
+    Struct(String, Vec<Expr>),
 }
 
 impl Expr {
-    pub(crate) fn from_ast(a: &ast::Expr) -> Expr {
+    pub(crate) fn from_ast(a: &ast::Expr, target: &Target) -> Expr {
         use ExprVariant::*;
         match &a.value {
             F64(f) => Expr::F64(*f),
-            Int(i) => Expr::I64(*i),
+            Int(i) => Expr::Int(target.int_type(), *i),
             Bool(b) => Expr::Bool(*b),
             String(s) => Expr::CString(s.clone()),
             expr => unimplemented!("codegen: expr {:#?}", expr),
@@ -623,23 +1727,41 @@ impl Expr {
     }
     pub(crate) fn type_(&self) -> Type {
         match self {
-            Expr::I64(..) => Type::Int(64),
+            Expr::Int(typ, ..) => typ.clone(),
             Expr::F64(..) => Type::Float(64),
             Expr::Bool(..) => Type::Int(1),
+            // UTF-8 bytes with the null terminator
             Expr::CString(s) => Type::Array {
-                len: s.len(),
+                len: s.len() + 1,
                 element_type: Type::Int(8).into(),
             },
             Expr::Identifier(typ, ..) => typ.clone(),
             Expr::LocalIdentifier(typ, ..) => typ.clone(),
             Expr::GlobalIdentifier(typ, ..) => typ.clone(),
+            Expr::Zero(typ) => typ.clone(),
+            Expr::Struct(name, _) => Type::Named(name.clone()),
+        }
+    }
+
+    /// local_id_of returns the ID of the local value the expression refers to
+    pub(crate) fn local_id_of(&self) -> Option<ID> {
+        match self {
+            Expr::LocalIdentifier(_, id) => Some(*id),
+            _ => None,
+        }
+    }
+    /// local_ids_mut returns the ID of the local value the expression refers to
+    pub(crate) fn local_ids_mut(&mut self) -> Vec<&mut ID> {
+        match self {
+            Expr::LocalIdentifier(_, id) => vec![id],
+            _ => vec![],
         }
     }
 
-    fn local_id(typ: Type, id: Rc<RefCell<ID>>) -> Expr {
+    pub(crate) fn local_id(typ: Type, id: ID) -> Expr {
         Expr::LocalIdentifier(typ, id)
     }
-    fn global_id(typ: Type, id: Rc<RefCell<ID>>) -> Expr {
+    pub(crate) fn global_id(typ: Type, id: ID) -> Expr {
         Expr::GlobalIdentifier(typ, id)
     }
 }
@@ -1,4 +1,8 @@
 use super::ir;
+use crate::lexer::Location;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
 
 pub trait LLVMValue {
     fn llvm_represent(&self) -> String;
@@ -6,29 +10,187 @@ pub trait LLVMValue {
 
 impl LLVMValue for ir::Module {
     fn llvm_represent(&self) -> String {
-        let mut s = String::new();
-        for (_, t) in &self.types {
-            s.push_str(t.llvm_def().as_str());
-            s.push_str("\n");
+        represent(|buf| self.write_llvm(buf))
+    }
+}
+
+impl ir::Module {
+    /// write_llvm writes the module as textual IR to `w` piece by piece, so the whole text is
+    /// never held in memory, e.g. writing to a file or a pipe
+    pub fn write_llvm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "target datalayout = \"{}\"", self.target.data_layout)?;
+        writeln!(w, "target triple = \"{}\"", self.target.triple)?;
+        // sorted, so the output is stable
+        let mut type_names: Vec<&String> = self.types.keys().collect();
+        type_names.sort();
+        for name in type_names {
+            writeln!(w, "{}", self.types[name].llvm_def())?;
         }
         for v in &self.variables {
-            s.push_str(v.llvm_represent().as_str());
-            s.push_str("\n");
+            writeln!(w, "{}", v.llvm_represent())?;
         }
-        for (_, f) in &self.functions {
-            s.push_str(f.llvm_represent().as_str());
-            s.push_str("\n");
+        let mut debug_info = DebugInfo::default();
+        let mut function_names: Vec<&String> = self.functions.keys().collect();
+        function_names.sort();
+        for f in function_names.into_iter().map(|name| &self.functions[name]) {
+            let debug_info = if self.debug_info {
+                Some(&mut debug_info)
+            } else {
+                None
+            };
+            f.write_llvm(&mut w, debug_info)?;
+            writeln!(w)?;
         }
-        s
+        debug_info.write_llvm(&mut w)?;
+        w.flush()
+    }
+}
+
+/// DebugInfo numbers metadata nodes of debug info as functions are written, the nodes are written
+/// after all functions since they're only known by then
+#[derive(Default)]
+struct DebugInfo {
+    /// nodes from `!4`, `!0` to `!3` are the compile unit, the type of every function and flags
+    nodes: Vec<String>,
+    files: HashMap<String, usize>,
+    locations: HashMap<(u32, u32, usize), usize>,
+    /// unit_file is the file of the compile unit, the file of `main` or the first file seen
+    unit_file: Option<usize>,
+}
+
+/// FIRST_NODE is the number of the first node of `DebugInfo::nodes`
+const FIRST_NODE: usize = 4;
+
+/// Scope is the function instructions are located in
+struct Scope<'a> {
+    subprogram: usize,
+    location: &'a Location,
+}
+
+impl Scope<'_> {
+    /// located returns `location` if it's in the file of the function, else the function location
+    fn located<'a>(&'a self, location: &'a Location) -> &'a Location {
+        if location.line() > 0 && location.file_name() == self.location.file_name() {
+            location
+        } else {
+            self.location
+        }
+    }
+}
+
+impl DebugInfo {
+    fn node(&mut self, node: String) -> usize {
+        self.nodes.push(node);
+        FIRST_NODE + self.nodes.len() - 1
+    }
+    fn file(&mut self, file_name: &str) -> usize {
+        if let Some(node) = self.files.get(file_name) {
+            return *node;
+        }
+        // relative paths are relative to the directory compiling
+        let path = Path::new(file_name);
+        let (directory, file) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(file)) if path.is_absolute() => {
+                (parent.to_path_buf(), file.to_string_lossy().to_string())
+            }
+            _ => (
+                std::env::current_dir().unwrap_or_default(),
+                file_name.to_string(),
+            ),
+        };
+        let node = self.node(format!(
+            "!DIFile(filename: \"{}\", directory: \"{}\")",
+            escape_c_string(&file),
+            escape_c_string(&directory.to_string_lossy())
+        ));
+        self.files.insert(file_name.to_string(), node);
+        node
+    }
+    /// subprogram returns the scope of the function, a function not from a file has no scope
+    fn subprogram<'a>(&mut self, f: &'a ir::Function) -> Option<Scope<'a>> {
+        let location = &f.location;
+        if location.file_name().is_empty() || location.line() == 0 {
+            return None;
+        }
+        let file = self.file(location.file_name());
+        if f.name == "@main" || self.unit_file.is_none() {
+            self.unit_file = Some(file);
+        }
+        let name = f.name.trim_start_matches('@').trim_matches('"');
+        let subprogram = self.node(format!(
+            "distinct !DISubprogram(name: \"{name}\", scope: !{file}, file: !{file}, line: {line}, type: !1, scopeLine: {line}, spFlags: DISPFlagDefinition, unit: !0)",
+            name = escape_c_string(name),
+            file = file,
+            line = location.line()
+        ));
+        Some(Scope {
+            subprogram,
+            location,
+        })
+    }
+    fn location(&mut self, location: &Location, scope: &Scope) -> usize {
+        let location = scope.located(location);
+        // columns of DWARF start from 1
+        let key = (location.line(), location.column() + 1, scope.subprogram);
+        if let Some(node) = self.locations.get(&key) {
+            return *node;
+        }
+        let node = self.node(format!(
+            "!DILocation(line: {}, column: {}, scope: !{})",
+            key.0, key.1, key.2
+        ));
+        self.locations.insert(key, node);
+        node
+    }
+    /// write_llvm writes the nodes, nothing is written if no function has debug info
+    fn write_llvm<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let unit_file = match self.unit_file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        writeln!(w, "!llvm.dbg.cu = !{{!0}}")?;
+        writeln!(w, "!llvm.module.flags = !{{!2, !3}}")?;
+        writeln!(
+            w,
+            "!0 = distinct !DICompileUnit(language: DW_LANG_C, file: !{}, producer: \"elz\", isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug)",
+            unit_file
+        )?;
+        writeln!(w, "!1 = !DISubroutineType(types: !{{}})")?;
+        writeln!(w, "!2 = !{{i32 7, !\"Dwarf Version\", i32 4}}")?;
+        writeln!(w, "!3 = !{{i32 2, !\"Debug Info Version\", i32 3}}")?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(w, "!{} = {}", FIRST_NODE + index, node)?;
+        }
+        Ok(())
     }
 }
 
+/// write_location ends the instruction written last, with its location as debug info if the
+/// function has
+fn write_location<W: Write>(
+    w: &mut W,
+    debug: &mut Option<(&mut DebugInfo, Scope)>,
+    location: &Location,
+) -> io::Result<()> {
+    if let Some((debug_info, scope)) = debug {
+        write!(w, ", !dbg !{}", debug_info.location(location, scope))?;
+    }
+    writeln!(w)
+}
+
+/// represent collects what `write` writes into a string
+fn represent(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+    let mut buf = vec![];
+    write(&mut buf).expect("writing to memory never fails");
+    String::from_utf8(buf).expect("textual IR is UTF-8")
+}
+
 impl LLVMValue for ir::GlobalName {
     fn llvm_represent(&self) -> String {
         use ir::GlobalName::*;
         match self {
             String(s) => s.clone(),
-            ID(id) => format!("@{}", id.borrow()),
+            ID(id) => format!("@{}", id),
         }
     }
 }
@@ -54,7 +216,7 @@ impl LLVMValue for ir::Instruction {
         match self {
             Load { id, load_from } => format!(
                 "%{id} = load {to_type}, {from_type} {load_from}",
-                id = id.borrow(),
+                id = id,
                 to_type = load_from.type_().llvm_represent(),
                 from_type = (ir::Type::Pointer(load_from.type_().into())).llvm_represent(),
                 load_from = load_from.llvm_represent()
@@ -68,7 +230,7 @@ impl LLVMValue for ir::Instruction {
                 s.push_str(
                     format!(
                         "%{id} = getelementptr {target}, {ptr_to_target} {load_from}",
-                        id = id.borrow(),
+                        id = id,
                         target = load_from.type_().element_type().llvm_represent(),
                         ptr_to_target = load_from.type_().llvm_represent(),
                         load_from = load_from.llvm_represent()
@@ -80,14 +242,6 @@ impl LLVMValue for ir::Instruction {
                 }
                 s
             }
-            Return(e) => match e {
-                None => "ret void".to_string(),
-                Some(ex) => {
-                    let es = ex.llvm_represent();
-                    let ret_typ = ex.type_();
-                    format!("ret {} {}", ret_typ.llvm_represent(), es)
-                }
-            },
             BinaryOperation {
                 id,
                 op_name,
@@ -99,7 +253,7 @@ impl LLVMValue for ir::Instruction {
                 s.push_str(
                     format!(
                         "%{} = {} {} {}, {}",
-                        id.borrow(),
+                        id,
                         op_name,
                         ret_type.llvm_represent(),
                         lhs.llvm_represent(),
@@ -117,7 +271,7 @@ impl LLVMValue for ir::Instruction {
             } => {
                 let mut s = String::new();
                 if !self.return_void() {
-                    s.push_str(format!("%{} = ", id.borrow()).as_str());
+                    s.push_str(format!("%{} = ", id).as_str());
                 }
                 s.push_str("call ");
                 s.push_str(format!("{} ", ret_type.llvm_represent()).as_str());
@@ -134,21 +288,48 @@ impl LLVMValue for ir::Instruction {
                 s.push_str(")");
                 s
             }
-            Malloca { id, typ } => format!(
-                "%{id} = call i8* @malloc(i64 {type_size})",
-                id = id.borrow(),
-                type_size = typ.size()
+            Alloca { id, typ } => {
+                format!("%{id} = alloca {typ}", id = id, typ = typ.llvm_represent())
+            }
+            Malloca { id, size } => format!(
+                "%{id} = call i8* @malloc({size_type} {size})",
+                id = id,
+                size_type = size.type_().llvm_represent(),
+                size = size.llvm_represent()
             ),
             BitCast {
                 id,
-                from_id,
+                from,
                 target_type,
             } => format!(
-                "%{id} = bitcast i8* %{from} to {target_type}",
-                id = id.borrow(),
-                from = from_id.borrow(),
+                "%{id} = bitcast {from_type} {from} to {target_type}",
+                id = id,
+                from_type = from.type_().llvm_represent(),
+                from = from.llvm_represent(),
                 target_type = target_type.llvm_represent()
             ),
+            Cast {
+                id,
+                op_name,
+                from,
+                target_type,
+            } => format!(
+                "%{id} = {op_name} {from_type} {from} to {target_type}",
+                id = id,
+                op_name = op_name,
+                from_type = from.type_().llvm_represent(),
+                from = from.llvm_represent(),
+                target_type = target_type.llvm_represent()
+            ),
+            ElementPointer { id, from, index } => format!(
+                "%{id} = getelementptr {element_type}, {from_type} {from}, {index_type} {index}",
+                id = id,
+                element_type = from.type_().element_type().llvm_represent(),
+                from_type = from.type_().llvm_represent(),
+                from = from.llvm_represent(),
+                index_type = index.type_().llvm_represent(),
+                index = index.llvm_represent()
+            ),
             Store {
                 source,
                 destination,
@@ -157,8 +338,49 @@ impl LLVMValue for ir::Instruction {
                 source.type_().llvm_represent(),
                 source.llvm_represent(),
                 (ir::Type::Pointer(source.type_().into())).llvm_represent(),
-                destination.borrow()
+                destination
             ),
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => format!(
+                "call void {name}(i8* {destination}, i8* {source}, {size_type} {size}, i1 false)",
+                name = ir::memcpy_name(size),
+                destination = destination.llvm_represent(),
+                source = source.llvm_represent(),
+                size_type = size.type_().llvm_represent(),
+                size = size.llvm_represent()
+            ),
+            Phi { id, typ, incoming } => {
+                let incoming: Vec<String> = incoming
+                    .iter()
+                    .map(|(value, label)| format!("[ {}, %{} ]", value.llvm_represent(), label.id))
+                    .collect();
+                format!(
+                    "%{id} = phi {typ} {incoming}",
+                    id = id,
+                    typ = typ.llvm_represent(),
+                    incoming = incoming.join(", ")
+                )
+            }
+            DebugLocation(location) => format!("; {}", location),
+        }
+    }
+}
+
+impl LLVMValue for ir::Terminator {
+    fn llvm_represent(&self) -> String {
+        use ir::Terminator::*;
+        match self {
+            Return(e) => match e {
+                None => "ret void".to_string(),
+                Some(ex) => {
+                    let es = ex.llvm_represent();
+                    let ret_typ = ex.type_();
+                    format!("ret {} {}", ret_typ.llvm_represent(), es)
+                }
+            },
             Branch {
                 cond,
                 if_true,
@@ -171,71 +393,112 @@ impl LLVMValue for ir::Instruction {
                 if_false.llvm_represent(),
             ),
             Goto(block) => format!("br {}", block.llvm_represent()),
-            Label(label) => format!("; <label>:{}:", label.id.borrow()),
+            Switch {
+                value,
+                default,
+                cases,
+            } => {
+                let typ = value.type_().llvm_represent();
+                let cases: Vec<String> = cases
+                    .iter()
+                    .map(|(case, label)| format!("{} {}, {}", typ, case, label.llvm_represent()))
+                    .collect();
+                format!(
+                    "switch {} {}, {} [ {} ]",
+                    typ,
+                    value.llvm_represent(),
+                    default.llvm_represent(),
+                    cases.join(" ")
+                )
+            }
+            Unreachable => "unreachable".to_string(),
         }
     }
 }
 
 impl LLVMValue for ir::Label {
     fn llvm_represent(&self) -> String {
-        format!("label %{}", self.id.borrow())
+        format!("label %{}", self.id)
     }
 }
 
 impl LLVMValue for ir::Body {
     fn llvm_represent(&self) -> String {
-        let mut s = String::new();
-        for instruction in &self.instructions {
-            match instruction {
-                ir::Instruction::Label(..) => {
-                    s.push_str(format!("{}\n", instruction.llvm_represent()).as_str());
-                }
-                _ => {
-                    s.push_str(format!("  {}\n", instruction.llvm_represent()).as_str());
+        represent(|buf| self.write_llvm(buf, None))
+    }
+}
+
+impl ir::Body {
+    /// write_llvm writes blocks of the body, with `debug` every instruction is located at the
+    /// last debug location before it, or the start of its block
+    fn write_llvm<W: Write>(
+        &self,
+        w: &mut W,
+        mut debug: Option<(&mut DebugInfo, Scope)>,
+    ) -> io::Result<()> {
+        for (index, block) in self.blocks.iter().enumerate() {
+            // entry block has no label
+            if index > 0 {
+                writeln!(w, "; <label>:{}:", block.label.id)?;
+            }
+            let mut location = &block.label.location;
+            for instruction in &block.instructions {
+                if let ir::Instruction::DebugLocation(next) = instruction {
+                    location = next;
+                    continue;
                 }
+                write!(w, "  {}", instruction.llvm_represent())?;
+                write_location(w, &mut debug, location)?;
             }
+            write!(w, "  {}", block.terminator.llvm_represent())?;
+            write_location(w, &mut debug, location)?;
         }
-        s
+        Ok(())
     }
 }
 
 impl LLVMValue for ir::Function {
     fn llvm_represent(&self) -> String {
-        let mut s = String::new();
-        let is_declaration = self.body.is_none();
-        if is_declaration {
-            s.push_str("declare ");
-        } else {
-            s.push_str("define ");
-        }
-        s.push_str(self.ret_typ.llvm_represent().as_str());
-        s.push_str(" ");
-        s.push_str(self.name.as_str());
-        s.push_str("(");
-        for (index, (name, typ)) in self.parameters.iter().enumerate() {
-            s.push_str(typ.llvm_represent().as_str());
-            s.push_str(" %");
-            s.push_str(name.as_str());
-            if index < self.parameters.len() - 1 {
-                s.push_str(", ");
+        represent(|buf| self.write_llvm(buf, None))
+    }
+}
+
+impl ir::Function {
+    fn write_llvm<W: Write>(
+        &self,
+        w: &mut W,
+        debug_info: Option<&mut DebugInfo>,
+    ) -> io::Result<()> {
+        let keyword = match self.body {
+            None => "declare",
+            Some(..) => "define",
+        };
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(name, typ)| format!("{} %{}", typ.llvm_represent(), name))
+            .collect();
+        write!(
+            w,
+            "{} {} {}({})",
+            keyword,
+            self.ret_typ.llvm_represent(),
+            self.name,
+            parameters.join(", ")
+        )?;
+        if let Some(b) = &self.body {
+            let debug = match debug_info {
+                Some(debug_info) => debug_info.subprogram(self).map(|scope| (debug_info, scope)),
+                None => None,
+            };
+            if let Some((_, scope)) = &debug {
+                write!(w, " !dbg !{}", scope.subprogram)?;
             }
+            writeln!(w, " {{")?;
+            b.write_llvm(w, debug)?;
+            write!(w, "}}")?;
         }
-        s.push_str(")");
-        match &self.body {
-            Some(b) => {
-                s.push_str(" {\n");
-                s.push_str(b.llvm_represent().as_str());
-                match self.ret_typ {
-                    ir::Type::Void => {
-                        s.push_str("  ret void\n");
-                    }
-                    _ => {}
-                }
-                s.push_str("}");
-            }
-            None => (),
-        };
-        s
+        Ok(())
     }
 }
 
@@ -257,11 +520,13 @@ impl LLVMValue for ir::Type {
         use ir::Type::*;
         match self {
             Void => format!("void"),
-            Float(n) => format!("f{}", n),
+            Float(32) => "float".to_string(),
+            Float(64) => "double".to_string(),
+            Float(n) => unreachable!("LLVM has no {} bits float type", n),
             Int(n) => format!("i{}", n),
             Pointer(typ) => format!("{}*", typ.llvm_represent()),
             Array { len, element_type } => format!("[{} x {}]", len, element_type.llvm_represent()),
-            Struct { name, .. } => format!("%{}*", name),
+            Struct { name, .. } => format!("%{}", name),
             Named(name) => format!("%{}", name),
         }
     }
@@ -278,7 +543,7 @@ impl ir::Type {
                 for (index, field) in fields.iter().enumerate() {
                     s.push_str(field.typ.llvm_represent().as_str());
                     if index < fields.len() - 1 {
-                        s.push_str(" ");
+                        s.push_str(", ");
                     }
                 }
                 s.push_str(" }");
@@ -293,13 +558,34 @@ impl LLVMValue for ir::Expr {
     fn llvm_represent(&self) -> String {
         use ir::Expr;
         match self {
-            Expr::F64(f) => format!("{}", f),
-            Expr::I64(i) => format!("{}", i),
+            // LLVM only accepts decimal constants which are exact, hexadecimal bits always are
+            Expr::F64(f) => format!("0x{:016X}", f.to_bits()),
+            Expr::Int(_, i) => format!("{}", i),
             Expr::Bool(b) => format!("{}", b),
-            Expr::CString(s_l) => format!("c\"{}\"", s_l),
+            Expr::CString(s_l) => format!("c\"{}\\00\"", escape_c_string(s_l)),
             Expr::Identifier(_, name) => format!("%{}", name),
-            Expr::LocalIdentifier(_, id) => format!("%{}", id.borrow()),
-            Expr::GlobalIdentifier(_, id) => format!("@{}", id.borrow()),
+            Expr::LocalIdentifier(_, id) => format!("%{}", id),
+            Expr::GlobalIdentifier(_, id) => format!("@{}", id),
+            Expr::Zero(..) => "zeroinitializer".to_string(),
+            Expr::Struct(_, values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|v| format!("{} {}", v.type_().llvm_represent(), v.llvm_represent()))
+                    .collect();
+                format!("{{ {} }}", values.join(", "))
+            }
+        }
+    }
+}
+
+/// escape_c_string keeps printable ASCII as is, other bytes, `"` and `\` are written as `\XX`
+fn escape_c_string(s: &str) -> String {
+    let mut escaped = String::new();
+    for b in s.bytes() {
+        match b {
+            0x20..=0x7e if b != b'"' && b != b'\\' => escaped.push(b as char),
+            _ => escaped.push_str(format!("\\{:02X}", b).as_str()),
         }
     }
+    escaped
 }
@@ -1,64 +1,189 @@
 use crate::ast::*;
 use crate::codegen::tag::CodegenTag;
+use crate::codegen::target::Target;
+use crate::ice::{self, Phase};
+use crate::mangle::method_path;
+use crate::semantic::const_eval::{self, Value};
+use crate::semantic::TypeMap;
+use std::collections::HashMap;
 
+mod args;
+mod concat;
+pub mod coverage;
+pub mod dump;
+mod erasure;
+mod for_loop;
 pub mod formatter;
+pub mod graph;
 pub mod ir;
 pub mod llvm;
+#[cfg(feature = "llvm")]
+pub mod native;
+pub mod optimize;
+mod refcount;
+mod specialize;
 mod tag;
+pub mod target;
+pub mod type_check;
+pub mod verify;
 
-pub struct CodeGenerator {}
+pub struct CodeGenerator {
+    target: Target,
+    debug_info: bool,
+    expr_types: TypeMap,
+}
 
 impl CodeGenerator {
+    /// new creates a generator for the host machine
     pub fn new() -> CodeGenerator {
-        CodeGenerator {}
+        CodeGenerator::with_target(Target::host())
+    }
+    pub fn with_target(target: Target) -> CodeGenerator {
+        CodeGenerator {
+            target,
+            debug_info: false,
+            expr_types: TypeMap::default(),
+        }
+    }
+    /// with_debug_info makes modules generated carry debug info, so programs built from them can
+    /// be stepped through in gdb or lldb
+    pub fn with_debug_info(mut self) -> CodeGenerator {
+        self.debug_info = true;
+        self
+    }
+    /// with_expr_types gives types of expressions inferred by the semantic checker, results of
+    /// generic methods are converted to them
+    pub fn with_expr_types(mut self, expr_types: TypeMap) -> CodeGenerator {
+        self.expr_types = expr_types;
+        self
     }
 
+    /// generate_program generates modules of a program together, symbols of methods start with
+    /// the modules of their classes, e.g. `main::Point::new`
+    pub fn generate_program(&self, modules: &[Module]) -> ir::Module {
+        let mut class_modules = HashMap::new();
+        for module in modules {
+            for top in &module.top_list {
+                if let TopAst::Class(c) = top {
+                    class_modules.insert(c.name.clone(), module.name.clone());
+                }
+            }
+        }
+        let asts: Vec<TopAst> = modules
+            .iter()
+            .flat_map(|module| module.top_list.iter().cloned())
+            .collect();
+        self.generate(&asts, class_modules)
+    }
+    /// generate_module generates a top list out of modules, symbols of methods start with their
+    /// classes, e.g. `Point::new`
     pub fn generate_module(&self, asts: &Vec<TopAst>) -> ir::Module {
-        let mut module = ir::Module::new();
+        self.generate(asts, HashMap::new())
+    }
+
+    fn generate(&self, asts: &[TopAst], class_modules: HashMap<String, String>) -> ir::Module {
+        let mut module = ir::Module::new(self.target.clone());
+        module.debug_info = self.debug_info;
+        module.expr_types = self.expr_types.clone();
+        module.class_modules = class_modules;
         for top in asts {
             use TopAst::*;
             match &top {
                 Import(_) => {}
                 Function(f) => {
                     module.remember_function(f);
+                    // generic functions are generated for types they're called with
+                    if !f.type_parameters.is_empty() {
+                        module.generic_functions.insert(f.name.clone(), f.clone());
+                    }
                 }
                 Variable(v) => {
                     module.remember_variable(v);
                 }
-                Class(_) => {}
-                Trait(_) => unimplemented!(),
+                // layout is registered before generating any function, so code can use classes
+                // defined after it
+                Class(c) => {
+                    for member in &c.members {
+                        if let ClassMember::StaticMethod(f) | ClassMember::Method(f) = member {
+                            module.remember_erased(c, f);
+                            if f.tag.is_builtin() {
+                                module
+                                    .builtin_functions
+                                    .insert(method_path(&c.name, &f.name));
+                            }
+                        }
+                    }
+                    let c = &erasure::erase_class(c);
+                    for member in &c.members {
+                        if let ClassMember::StaticMethod(f) = member {
+                            if f.tag.is_specialize() {
+                                module.specialized_methods.insert(
+                                    method_path(&c.name, &f.name),
+                                    (c.name.clone(), f.clone()),
+                                );
+                            }
+                        }
+                    }
+                    // builtin types have methods, e.g. `int::to_string`, but no layout
+                    if !is_builtin_type(&c.name) {
+                        module.push_type(&c.name, &c.members);
+                        if c.drop_method().is_some() {
+                            module.dropped_classes.insert(c.name.clone());
+                        }
+                    }
+                    for member in &c.members {
+                        if let ClassMember::StaticMethod(f) | ClassMember::Method(f) = member {
+                            module.remember_method(&c.name, f);
+                        }
+                    }
+                }
+                Trait(t) => {
+                    module.traits.insert(t.name.clone());
+                }
             }
         }
+        // values of global variables generated so far
+        let mut constants = HashMap::new();
         for top in asts {
             use TopAst::*;
             match &top {
                 Import(_) => {}
                 Function(f) => {
-                    if f.tag.is_builtin() {
+                    if f.tag.is_builtin() || !f.type_parameters.is_empty() {
                         continue;
                     }
                     let func = ir::Function::from_ast(f, None, &mut module);
                     module.push_function(func);
                 }
                 Variable(v) => {
-                    let var = ir::Variable::new(v.name.clone(), ir::Expr::from_ast(&v.expr));
-                    module.push_variable(var);
+                    let _compiling = ice::compiling(Phase::Codegen, &v.location);
+                    let value = const_eval::eval(&v.expr, &constants).expect(
+                        "global variable is not a constant which unlikely happened, semantic module must have a bug there!",
+                    );
+                    let expr = match &value {
+                        Value::Int(i) => {
+                            ir::Expr::Int(ir::Type::from_ast(&v.typ, &module.target), *i)
+                        }
+                        Value::F64(f) => ir::Expr::F64(*f),
+                        Value::Bool(b) => ir::Expr::Bool(*b),
+                        value => unreachable!(
+                            "global variable of value {:?} which unlikely happened, semantic module must have rejected it!",
+                            value
+                        ),
+                    };
+                    constants.insert(v.name.clone(), value);
+                    module.push_variable(ir::Variable::new(v.name.clone(), expr));
                 }
                 Class(c) => {
-                    match c.name.as_str() {
-                        // FIXME: provide a tag, e.g.
-                        // ```
-                        // @Codegen(Omit)
-                        // class int {}
-                        // ```
-                        "void" | "int" | "f64" | "bool" | "_c_string" | "List" => continue,
-                        _ => {}
-                    }
-                    module.push_type(&c.name, &c.members);
+                    let c = &erasure::erase_class(c);
 
                     for member in &c.members {
                         match member {
-                            ClassMember::StaticMethod(static_method) => {
+                            // specialized methods are generated for types they're called with
+                            ClassMember::StaticMethod(static_method)
+                                if !static_method.tag.is_builtin()
+                                    && !static_method.tag.is_specialize() =>
+                            {
                                 let func = ir::Function::from_ast(
                                     static_method,
                                     Some(c.name.clone()),
@@ -70,7 +195,11 @@ impl CodeGenerator {
                                 let mut method = method.clone();
                                 method.parameters.insert(
                                     0,
-                                    Parameter::new("self", ParsedType::TypeName(c.name.clone())),
+                                    Parameter::new(
+                                        method.location.clone(),
+                                        "self",
+                                        ParsedType::TypeName(c.name.clone()),
+                                    ),
                                 );
                                 let func = ir::Function::from_ast(
                                     &method,
@@ -83,12 +212,30 @@ impl CodeGenerator {
                         }
                     }
                 }
-                Trait(_) => unimplemented!(),
+                // trait has no runtime representation
+                Trait(_) => {}
             }
         }
+        module.generate_specializations();
+        module.push_refcount_functions();
         module
     }
 }
 
+/// is_builtin_type returns true for prelude classes mapped to LLVM types directly, they have no
+/// struct layout
+///
+/// FIXME: provide a tag, e.g.
+/// ```text
+/// @Codegen(Omit)
+/// class int {}
+/// ```
+fn is_builtin_type(name: &str) -> bool {
+    matches!(
+        name,
+        "void" | "int" | "i32" | "u8" | "f64" | "bool" | "_c_string"
+    )
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,607 @@
+//! native builds `ir::Module` into an LLVM module through inkwell, so the compiler can verify and
+//! emit object files or bitcode without going through textual IR
+use super::ir;
+use inkwell::builder::{Builder, BuilderError};
+use inkwell::context::Context;
+use inkwell::module::Module;
+use inkwell::targets::{
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetTriple,
+};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
+use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue, PhiValue};
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate, OptimizationLevel};
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+/// OutputKind is the kind of file `emit` writes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputKind {
+    Object,
+    Bitcode,
+}
+
+#[derive(Debug, Error)]
+pub enum NativeError {
+    #[error("LLVM rejected the generated module: {0}")]
+    Verify(String),
+    #[error("target `{triple}` is not available in LLVM: {message}")]
+    Target { triple: String, message: String },
+    #[error("failed to write `{path}`: {message}")]
+    Write { path: String, message: String },
+    #[error("can't run `main` through LLVM JIT: {0}")]
+    Jit(String),
+    #[error(transparent)]
+    Builder(#[from] BuilderError),
+}
+
+type Result<T> = std::result::Result<T, NativeError>;
+
+/// emit builds and verifies `module`, then writes it to `path` as `kind`
+pub fn emit(module: &ir::Module, kind: OutputKind, path: &Path) -> Result<()> {
+    let context = Context::create();
+    let llvm_module = build(&context, module)?;
+    let write_error = |message: String| NativeError::Write {
+        path: path.display().to_string(),
+        message,
+    };
+    match kind {
+        OutputKind::Bitcode => {
+            if !llvm_module.write_bitcode_to_path(path) {
+                return Err(write_error("LLVM can't write bitcode".to_string()));
+            }
+        }
+        OutputKind::Object => {
+            Target::initialize_all(&InitializationConfig::default());
+            let triple = TargetTriple::create(&module.target.triple);
+            let target_error = |message: String| NativeError::Target {
+                triple: module.target.triple.clone(),
+                message,
+            };
+            let target =
+                Target::from_triple(&triple).map_err(|err| target_error(err.to_string()))?;
+            let machine = target
+                .create_target_machine(
+                    &triple,
+                    "generic",
+                    "",
+                    OptimizationLevel::Default,
+                    RelocMode::PIC,
+                    CodeModel::Default,
+                )
+                .ok_or_else(|| target_error("can't create target machine".to_string()))?;
+            machine
+                .write_to_file(&llvm_module, FileType::Object, path)
+                .map_err(|err| write_error(err.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// verify builds `module` only to run LLVM's verifier on it, e.g. for `--verify` when the module
+/// is printed as text
+pub fn verify(module: &ir::Module) -> Result<()> {
+    build(&Context::create(), module).map(|_| ())
+}
+
+/// run executes `main` of `module` through LLVM's JIT with `args`, the first one is taken as the
+/// program, and returns its exit code, which is 0 when `main` returns void
+pub fn run(module: &ir::Module, args: &[String]) -> Result<i32> {
+    let context = Context::create();
+    let llvm_module = build(&context, module)?;
+    Target::initialize_native(&InitializationConfig::default()).map_err(NativeError::Jit)?;
+    let engine = llvm_module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .map_err(|err| NativeError::Jit(err.to_string()))?;
+    let main = llvm_module
+        .get_function("main")
+        .ok_or_else(|| NativeError::Jit("no `main` function".to_string()))?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let code = unsafe { engine.run_function_as_main(main, &args) };
+    match main.get_type().get_return_type() {
+        Some(..) => Ok(code),
+        None => Ok(0),
+    }
+}
+
+/// build translates `module` into an LLVM module and runs the verifier on it
+pub(crate) fn build<'ctx>(context: &'ctx Context, module: &ir::Module) -> Result<Module<'ctx>> {
+    let llvm_module = context.create_module("main");
+    llvm_module.set_triple(&TargetTriple::create(&module.target.triple));
+    llvm_module.set_data_layout(&TargetData::create(&module.target.data_layout).get_data_layout());
+    let mut builder = ModuleBuilder {
+        context,
+        module: llvm_module,
+        builder: context.create_builder(),
+        globals: HashMap::new(),
+    };
+    builder.build(module)?;
+    builder
+        .module
+        .verify()
+        .map_err(|err| NativeError::Verify(err.to_string()))?;
+    Ok(builder.module)
+}
+
+struct ModuleBuilder<'ctx> {
+    context: &'ctx Context,
+    module: Module<'ctx>,
+    builder: Builder<'ctx>,
+    /// unnamed globals, e.g. string literals
+    globals: HashMap<ir::ID, BasicValueEnum<'ctx>>,
+}
+
+/// FunctionState is what a function body refers to by name or number
+struct FunctionState<'ctx> {
+    parameters: HashMap<String, BasicValueEnum<'ctx>>,
+    values: HashMap<String, BasicValueEnum<'ctx>>,
+    blocks: HashMap<String, inkwell::basic_block::BasicBlock<'ctx>>,
+}
+
+impl<'ctx> ModuleBuilder<'ctx> {
+    fn build(&mut self, module: &ir::Module) -> Result<()> {
+        // struct types are declared before their bodies, so they can refer to each other
+        for name in module.types.keys() {
+            self.context.opaque_struct_type(name);
+        }
+        for (name, typ) in &module.types {
+            if let ir::Type::Struct { fields, .. } = typ {
+                let fields: Vec<BasicTypeEnum> =
+                    fields.iter().map(|f| self.basic_type(&f.typ)).collect();
+                self.context
+                    .get_struct_type(name)
+                    .unwrap()
+                    .set_body(&fields, false);
+            }
+        }
+        for v in &module.variables {
+            let name = match &v.name {
+                ir::GlobalName::String(name) => symbol(name),
+                ir::GlobalName::ID(_) => "",
+            };
+            let global = self
+                .module
+                .add_global(self.basic_type(&v.expr.type_()), None, name);
+            global.set_initializer(&self.constant(&v.expr));
+            if let ir::GlobalName::ID(id) = &v.name {
+                self.globals.insert(*id, global.as_pointer_value().into());
+            }
+        }
+        // functions are declared before any body, so calls can refer to functions defined later
+        for f in module.functions.values() {
+            let parameters: Vec<BasicMetadataTypeEnum> = f
+                .parameters
+                .iter()
+                .map(|(_, typ)| self.basic_type(typ).into())
+                .collect();
+            let fn_type = match &f.ret_typ {
+                ir::Type::Void => self.context.void_type().fn_type(&parameters, false),
+                typ => self.basic_type(typ).fn_type(&parameters, false),
+            };
+            self.module.add_function(symbol(&f.name), fn_type, None);
+        }
+        for f in module.functions.values() {
+            if let Some(body) = &f.body {
+                self.build_function(f, body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn build_function(&self, f: &ir::Function, body: &ir::Body) -> Result<()> {
+        let function = self.module.get_function(symbol(&f.name)).unwrap();
+        let mut state = FunctionState {
+            parameters: HashMap::new(),
+            values: HashMap::new(),
+            blocks: HashMap::new(),
+        };
+        for ((name, _), value) in f.parameters.iter().zip(function.get_param_iter()) {
+            value.set_name(name);
+            state.parameters.insert(name.clone(), value);
+        }
+        for block in &body.blocks {
+            let llvm_block = self.context.append_basic_block(function, "");
+            state.blocks.insert(block.label.id.to_string(), llvm_block);
+        }
+        // incoming values of phi can be defined in blocks built after it, so they're added last
+        let mut phis = vec![];
+        for block in &body.blocks {
+            self.builder
+                .position_at_end(state.blocks[&block.label.id.to_string()]);
+            for inst in &block.instructions {
+                if let Some(phi) = self.build_instruction(inst, &mut state)? {
+                    if let ir::Instruction::Phi { incoming, .. } = inst {
+                        phis.push((phi, incoming));
+                    }
+                }
+            }
+            self.build_terminator(&block.terminator, &state)?;
+        }
+        for (phi, incoming) in phis {
+            let incoming: Vec<(BasicValueEnum, _)> = incoming
+                .iter()
+                .map(|(value, label)| {
+                    (
+                        self.value(value, &state),
+                        state.blocks[&label.id.to_string()],
+                    )
+                })
+                .collect();
+            let incoming: Vec<(&dyn BasicValue, _)> = incoming
+                .iter()
+                .map(|(value, block)| (value as &dyn BasicValue, *block))
+                .collect();
+            phi.add_incoming(&incoming);
+        }
+        Ok(())
+    }
+
+    /// build_instruction returns the phi node if the instruction is a phi, its incoming values
+    /// are added after the whole function is built
+    fn build_instruction(
+        &self,
+        inst: &ir::Instruction,
+        state: &mut FunctionState<'ctx>,
+    ) -> Result<Option<PhiValue<'ctx>>> {
+        use ir::Instruction::*;
+        let (id, value): (_, BasicValueEnum) = match inst {
+            GEP {
+                id,
+                load_from,
+                indices,
+            } => {
+                let indices: Vec<_> = indices
+                    .iter()
+                    .map(|i| self.context.i32_type().const_int(*i, false))
+                    .collect();
+                let ptr = self.value(load_from, state).into_pointer_value();
+                // indices come from struct layouts, so they are in bounds
+                let value = unsafe { self.builder.build_gep(ptr, &indices, "")? };
+                (id, value.into())
+            }
+            FunctionCall {
+                id,
+                func_name,
+                args_expr,
+                ..
+            } => {
+                let function = self.module.get_function(symbol(func_name)).unwrap();
+                let args: Vec<_> = args_expr
+                    .iter()
+                    .map(|arg| self.value(arg, state).into())
+                    .collect();
+                let call = self.builder.build_call(function, &args, "")?;
+                match call.try_as_basic_value().left() {
+                    Some(value) => (id, value),
+                    None => return Ok(None),
+                }
+            }
+            BinaryOperation {
+                id,
+                op_name,
+                lhs,
+                rhs,
+            } => (id, self.build_binary(op_name, lhs, rhs, state)?),
+            Malloca { id, size } => {
+                let malloc = self.malloc(&size.type_());
+                let size = self.value(size, state);
+                let call = self.builder.build_call(malloc, &[size.into()], "")?;
+                (id, call.try_as_basic_value().left().unwrap())
+            }
+            Alloca { id, typ } => (
+                id,
+                self.builder.build_alloca(self.basic_type(typ), "")?.into(),
+            ),
+            BitCast {
+                id,
+                from,
+                target_type,
+            } => {
+                let from = self.value(from, state).into_pointer_value();
+                let typ = self.basic_type(target_type).into_pointer_type();
+                (id, self.builder.build_pointer_cast(from, typ, "")?.into())
+            }
+            Cast {
+                id,
+                op_name,
+                from,
+                target_type,
+            } => (id, self.build_cast(op_name, from, target_type, state)?),
+            ElementPointer { id, from, index } => {
+                let ptr = self.value(from, state).into_pointer_value();
+                let index = self.value(index, state).into_int_value();
+                // indices are checked by the code generating the instruction
+                let value = unsafe { self.builder.build_gep(ptr, &[index], "")? };
+                (id, value.into())
+            }
+            Load { id, load_from } => {
+                let ptr = self.value(load_from, state).into_pointer_value();
+                (id, self.builder.build_load(ptr, "")?)
+            }
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => {
+                let memcpy = self
+                    .module
+                    .get_function(symbol(&ir::memcpy_name(size)))
+                    .unwrap();
+                let args = [
+                    self.value(destination, state).into(),
+                    self.value(source, state).into(),
+                    self.value(size, state).into(),
+                    self.context.bool_type().const_zero().into(),
+                ];
+                self.builder.build_call(memcpy, &args, "")?;
+                return Ok(None);
+            }
+            Store {
+                source,
+                destination,
+            } => {
+                let ptr = state.values[&destination.to_string()].into_pointer_value();
+                self.builder.build_store(ptr, self.value(source, state))?;
+                return Ok(None);
+            }
+            Phi { id, typ, .. } => {
+                let phi = self.builder.build_phi(self.basic_type(typ), "")?;
+                state.values.insert(id.to_string(), phi.as_basic_value());
+                return Ok(Some(phi));
+            }
+            // debug info is only emitted in textual IR
+            DebugLocation(..) => return Ok(None),
+        };
+        state.values.insert(id.to_string(), value);
+        Ok(None)
+    }
+
+    fn build_binary(
+        &self,
+        op_name: &str,
+        lhs: &ir::Expr,
+        rhs: &ir::Expr,
+        state: &FunctionState<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let lhs = self.value(lhs, state);
+        let rhs = self.value(rhs, state);
+        let b = &self.builder;
+        let mut words = op_name.split(' ');
+        let value: BasicValueEnum = match (words.next().unwrap(), words.next()) {
+            ("icmp", Some(predicate)) => {
+                let predicate = match predicate {
+                    "eq" => IntPredicate::EQ,
+                    "ne" => IntPredicate::NE,
+                    "slt" => IntPredicate::SLT,
+                    "sle" => IntPredicate::SLE,
+                    "sgt" => IntPredicate::SGT,
+                    "sge" => IntPredicate::SGE,
+                    "ult" => IntPredicate::ULT,
+                    "ule" => IntPredicate::ULE,
+                    "ugt" => IntPredicate::UGT,
+                    "uge" => IntPredicate::UGE,
+                    p => unreachable!("unknown icmp predicate `{}`", p),
+                };
+                // pointers are compared as well, e.g. with null
+                match (lhs, rhs) {
+                    (BasicValueEnum::PointerValue(lhs), BasicValueEnum::PointerValue(rhs)) => {
+                        b.build_int_compare(predicate, lhs, rhs, "")?.into()
+                    }
+                    _ => b
+                        .build_int_compare(
+                            predicate,
+                            lhs.into_int_value(),
+                            rhs.into_int_value(),
+                            "",
+                        )?
+                        .into(),
+                }
+            }
+            ("fcmp", Some(predicate)) => {
+                let predicate = match predicate {
+                    "oeq" => FloatPredicate::OEQ,
+                    "one" => FloatPredicate::ONE,
+                    "olt" => FloatPredicate::OLT,
+                    "ole" => FloatPredicate::OLE,
+                    "ogt" => FloatPredicate::OGT,
+                    "oge" => FloatPredicate::OGE,
+                    p => unreachable!("unknown fcmp predicate `{}`", p),
+                };
+                b.build_float_compare(
+                    predicate,
+                    lhs.into_float_value(),
+                    rhs.into_float_value(),
+                    "",
+                )?
+                .into()
+            }
+            ("fadd", None) => b
+                .build_float_add(lhs.into_float_value(), rhs.into_float_value(), "")?
+                .into(),
+            ("fsub", None) => b
+                .build_float_sub(lhs.into_float_value(), rhs.into_float_value(), "")?
+                .into(),
+            ("fmul", None) => b
+                .build_float_mul(lhs.into_float_value(), rhs.into_float_value(), "")?
+                .into(),
+            ("fdiv", None) => b
+                .build_float_div(lhs.into_float_value(), rhs.into_float_value(), "")?
+                .into(),
+            (op, None) => {
+                let (lhs, rhs) = (lhs.into_int_value(), rhs.into_int_value());
+                match op {
+                    "add" => b.build_int_add(lhs, rhs, "")?,
+                    "sub" => b.build_int_sub(lhs, rhs, "")?,
+                    "mul" => b.build_int_mul(lhs, rhs, "")?,
+                    "sdiv" => b.build_int_signed_div(lhs, rhs, "")?,
+                    "udiv" => b.build_int_unsigned_div(lhs, rhs, "")?,
+                    op => unreachable!("unknown operation `{}`", op),
+                }
+                .into()
+            }
+            (op, Some(_)) => unreachable!("unknown operation `{}`", op),
+        };
+        Ok(value)
+    }
+
+    fn build_cast(
+        &self,
+        op_name: &str,
+        from: &ir::Expr,
+        target_type: &ir::Type,
+        state: &FunctionState<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>> {
+        let from = self.value(from, state);
+        let typ = self.basic_type(target_type);
+        let b = &self.builder;
+        let value: BasicValueEnum = match op_name {
+            "zext" => b
+                .build_int_z_extend(from.into_int_value(), typ.into_int_type(), "")?
+                .into(),
+            "sext" => b
+                .build_int_s_extend(from.into_int_value(), typ.into_int_type(), "")?
+                .into(),
+            "trunc" => b
+                .build_int_truncate(from.into_int_value(), typ.into_int_type(), "")?
+                .into(),
+            "sitofp" => b
+                .build_signed_int_to_float(from.into_int_value(), typ.into_float_type(), "")?
+                .into(),
+            "fptosi" => b
+                .build_float_to_signed_int(from.into_float_value(), typ.into_int_type(), "")?
+                .into(),
+            "ptrtoint" => b
+                .build_ptr_to_int(from.into_pointer_value(), typ.into_int_type(), "")?
+                .into(),
+            "inttoptr" => b
+                .build_int_to_ptr(from.into_int_value(), typ.into_pointer_type(), "")?
+                .into(),
+            "bitcast" => b.build_bit_cast(from, typ, "")?,
+            op => unreachable!("unknown conversion `{}`", op),
+        };
+        Ok(value)
+    }
+
+    fn build_terminator(
+        &self,
+        terminator: &ir::Terminator,
+        state: &FunctionState<'ctx>,
+    ) -> Result<()> {
+        use ir::Terminator::*;
+        let block = |label: &ir::Label| state.blocks[&label.id.to_string()];
+        match terminator {
+            Return(None) => self.builder.build_return(None)?,
+            Return(Some(e)) => self.builder.build_return(Some(&self.value(e, state)))?,
+            Branch {
+                cond,
+                if_true,
+                if_false,
+            } => self.builder.build_conditional_branch(
+                self.value(cond, state).into_int_value(),
+                block(if_true),
+                block(if_false),
+            )?,
+            Goto(label) => self.builder.build_unconditional_branch(block(label))?,
+            Switch {
+                value,
+                default,
+                cases,
+            } => {
+                let value = self.value(value, state).into_int_value();
+                let cases: Vec<_> = cases
+                    .iter()
+                    .map(|(case, label)| {
+                        (value.get_type().const_int(*case as u64, true), block(label))
+                    })
+                    .collect();
+                self.builder.build_switch(value, block(default), &cases)?
+            }
+            Unreachable => self.builder.build_unreachable()?,
+        };
+        Ok(())
+    }
+
+    /// malloc returns `@malloc` declared by prelude, or declares it with the size type
+    fn malloc(&self, size_type: &ir::Type) -> FunctionValue<'ctx> {
+        match self.module.get_function("malloc") {
+            Some(malloc) => malloc,
+            None => {
+                let fn_type = self
+                    .context
+                    .i8_type()
+                    .ptr_type(AddressSpace::default())
+                    .fn_type(&[self.basic_type(size_type).into()], false);
+                self.module.add_function("malloc", fn_type, None)
+            }
+        }
+    }
+
+    fn value(&self, e: &ir::Expr, state: &FunctionState<'ctx>) -> BasicValueEnum<'ctx> {
+        use ir::Expr::*;
+        match e {
+            Identifier(_, name) => match state.parameters.get(name) {
+                Some(value) => *value,
+                None => self
+                    .module
+                    .get_function(name)
+                    .unwrap()
+                    .as_global_value()
+                    .as_pointer_value()
+                    .into(),
+            },
+            LocalIdentifier(_, id) => state.values[&id.to_string()],
+            GlobalIdentifier(_, id) => self.globals[id],
+            _ => self.constant(e),
+        }
+    }
+
+    fn constant(&self, e: &ir::Expr) -> BasicValueEnum<'ctx> {
+        use ir::Expr::*;
+        match e {
+            Int(typ, i) => self
+                .basic_type(typ)
+                .into_int_type()
+                .const_int(*i as u64, true)
+                .into(),
+            F64(f) => self.context.f64_type().const_float(*f).into(),
+            Bool(b) => self.context.bool_type().const_int(*b as u64, false).into(),
+            CString(s) => self.context.const_string(s.as_bytes(), true).into(),
+            Zero(typ) => self.basic_type(typ).const_zero(),
+            Struct(name, values) => {
+                let values: Vec<_> = values.iter().map(|v| self.constant(v)).collect();
+                self.context
+                    .get_struct_type(name)
+                    .unwrap()
+                    .const_named_struct(&values)
+                    .into()
+            }
+            e => unreachable!("`{:?}` is not a constant", e),
+        }
+    }
+
+    fn basic_type(&self, typ: &ir::Type) -> BasicTypeEnum<'ctx> {
+        use ir::Type::*;
+        match typ {
+            Int(bits) => self.context.custom_width_int_type(*bits as u32).into(),
+            Float(32) => self.context.f32_type().into(),
+            Float(64) => self.context.f64_type().into(),
+            Pointer(typ) => self
+                .basic_type(typ)
+                .ptr_type(AddressSpace::default())
+                .into(),
+            Array { len, element_type } => {
+                self.basic_type(element_type).array_type(*len as u32).into()
+            }
+            Struct { name, .. } | Named(name) => self.context.get_struct_type(name).unwrap().into(),
+            Float(..) | Void => unreachable!("`{:?}` is not a first-class type", typ),
+        }
+    }
+}
+
+/// symbol returns the LLVM name of a global, e.g. `@main` is `main`, quotes of a name are removed
+fn symbol(name: &str) -> &str {
+    let name = name.strip_prefix('@').unwrap_or(name);
+    name.strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+        .unwrap_or(name)
+}
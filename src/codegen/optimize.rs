@@ -0,0 +1,759 @@
+//! optimize rewrites the IR into equivalent code which runs faster, `Module::optimize` runs the
+//! passes of the `OptLevel` given by `-O`
+use super::ir::*;
+use crate::ice::{self, Phase};
+use std::collections::{HashMap, HashSet};
+
+/// INLINE_THRESHOLD is the most instructions of a function inlined without `@inline`
+const INLINE_THRESHOLD: usize = 8;
+/// INLINE_ROUNDS is how many times calls in inlined code are inlined again, so inlining recursive
+/// functions stops
+const INLINE_ROUNDS: usize = 4;
+
+/// OptLevel is how much the IR is optimized, higher level runs passes of lower levels too
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum OptLevel {
+    /// no optimization, the IR is what the source code says
+    O0,
+    /// constant folding, promotion of stack slots to values, dead instruction and unreachable
+    /// block elimination, switches for chains of comparisons
+    O1,
+    /// inlining of small functions and functions tagged `@inline`
+    O2,
+}
+
+impl OptLevel {
+    /// from_name accepts the value of `-O`, i.e. `0`, `1` or `2`
+    pub fn from_name(name: &str) -> Option<OptLevel> {
+        match name {
+            "0" => Some(OptLevel::O0),
+            "1" => Some(OptLevel::O1),
+            "2" => Some(OptLevel::O2),
+            _ => None,
+        }
+    }
+}
+
+impl Module {
+    /// optimize runs passes of `level` on every function
+    pub fn optimize(&mut self, level: OptLevel) {
+        if level < OptLevel::O1 {
+            return;
+        }
+        if level >= OptLevel::O2 {
+            self.inline_functions();
+        }
+        for f in self.functions.values_mut() {
+            let _compiling = ice::compiling(Phase::Optimize, &f.location);
+            if let Some(body) = &mut f.body {
+                body.optimize();
+            }
+        }
+    }
+
+    /// inline_functions replaces calls to functions whose bodies are a block returning at its
+    /// end by instructions of the block, the block can have `INLINE_THRESHOLD` instructions at
+    /// most unless the function is tagged `@inline`
+    pub(crate) fn inline_functions(&mut self) {
+        for _ in 0..INLINE_ROUNDS {
+            let inlinable: HashMap<String, Function> = self
+                .functions
+                .iter()
+                .filter(|(_, f)| is_inlinable(f))
+                .map(|(name, f)| (name.clone(), f.clone()))
+                .collect();
+            let mut inlined = false;
+            for f in self.functions.values_mut() {
+                if let Some(body) = &mut f.body {
+                    inlined |= body.inline_calls(&inlinable, &f.name);
+                }
+            }
+            if !inlined {
+                return;
+            }
+        }
+    }
+}
+
+/// code_size is the number of instructions generating code, debug locations are not counted
+fn code_size(instructions: &[Instruction]) -> usize {
+    instructions
+        .iter()
+        .filter(|inst| !matches!(inst, Instruction::DebugLocation(..)))
+        .count()
+}
+
+fn is_inlinable(f: &Function) -> bool {
+    match &f.body {
+        Some(Body { blocks, .. }) => {
+            blocks.len() == 1
+                && matches!(blocks[0].terminator, Terminator::Return(..))
+                && (f.inline || code_size(&blocks[0].instructions) <= INLINE_THRESHOLD)
+        }
+        None => false,
+    }
+}
+
+impl Function {
+    /// inline_into appends instructions of the function called with `args` to `instructions`,
+    /// and returns the value returned, IDs are renamed so they don't conflict with the caller
+    fn inline_into(&self, args: &[Expr], instructions: &mut Vec<Instruction>) -> Option<Expr> {
+        let block = &self.body.as_ref().expect("function is inlinable").blocks[0];
+        let arguments: HashMap<&String, &Expr> = self
+            .parameters
+            .iter()
+            .map(|(name, _)| name)
+            .zip(args)
+            .collect();
+        let mut renamed = HashMap::new();
+        // IDs are renamed before parameters are replaced, so IDs of the caller in arguments are
+        // kept
+        let inline = |operands: Vec<&mut Expr>| {
+            for operand in operands {
+                if let Expr::Identifier(_, name) = operand {
+                    if let Some(arg) = arguments.get(name) {
+                        *operand = (*arg).clone();
+                    }
+                }
+            }
+        };
+        // inlined code is located at the call, so locations of the function are dropped
+        let code = block
+            .instructions
+            .iter()
+            .filter(|inst| !matches!(inst, Instruction::DebugLocation(..)));
+        for inst in code {
+            let mut inst = inst.clone();
+            for id in inst.local_ids_mut() {
+                *id = *renamed.entry(*id).or_insert_with(ID::new);
+            }
+            inline(inst.operands_mut());
+            instructions.push(inst);
+        }
+        match &block.terminator {
+            Terminator::Return(Some(value)) => {
+                let mut value = value.clone();
+                for id in value.local_ids_mut() {
+                    *id = renamed[id];
+                }
+                inline(vec![&mut value]);
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Body {
+    /// optimize runs passes until none of them changes the body, a pass can leave work to others,
+    /// e.g. a folded branch makes a block unreachable, and removing it leaves a phi with a value
+    fn optimize(&mut self) {
+        loop {
+            let folded = self.fold_constants();
+            let unreachable = self.eliminate_unreachable_blocks();
+            let promoted = self.promote_allocas();
+            let dead = self.eliminate_dead_instructions();
+            let switched = self.form_switches();
+            if !(folded || unreachable || promoted || dead || switched) {
+                break;
+            }
+        }
+        self.number();
+    }
+
+    /// fold_constants computes operations on constants at compile time, e.g. `1 + 2` to `3`, and
+    /// turns branches on constant conditions into jumps, until nothing can be folded
+    pub(crate) fn fold_constants(&mut self) -> bool {
+        let mut values: HashMap<ID, Expr> = HashMap::new();
+        let mut folded = false;
+        loop {
+            let mut changed = false;
+            for block in &mut self.blocks {
+                let mut instructions = vec![];
+                for mut inst in block.instructions.drain(..) {
+                    for operand in inst.operands_mut() {
+                        substitute(operand, &values);
+                    }
+                    match (inst.result_id(), fold(&inst)) {
+                        (Some(id), Some(value)) => {
+                            values.insert(id, value);
+                            changed = true;
+                        }
+                        _ => instructions.push(inst),
+                    }
+                }
+                block.instructions = instructions;
+                for operand in block.terminator.operands_mut() {
+                    substitute(operand, &values);
+                }
+            }
+            for i in 0..self.blocks.len() {
+                if self.fold_branch(i) {
+                    changed = true;
+                }
+            }
+            if !changed {
+                return folded;
+            }
+            folded = true;
+        }
+    }
+
+    /// eliminate_unreachable_blocks removes blocks control flow can't reach from the entry block,
+    /// and them from incoming blocks of phi
+    pub(crate) fn eliminate_unreachable_blocks(&mut self) -> bool {
+        let mut reached = HashSet::new();
+        let mut worklist = vec![self.blocks[0].label.id];
+        while let Some(id) = worklist.pop() {
+            if !reached.insert(id) {
+                continue;
+            }
+            if let Some(block) = self.blocks.iter().find(|block| block.label.id == id) {
+                worklist.extend(block.terminator.targets().iter().map(|label| label.id));
+            }
+        }
+        let count = self.blocks.len();
+        self.blocks
+            .retain(|block| reached.contains(&block.label.id));
+        for inst in self
+            .blocks
+            .iter_mut()
+            .flat_map(|block| &mut block.instructions)
+        {
+            if let Instruction::Phi { incoming, .. } = inst {
+                incoming.retain(|(_, label)| reached.contains(&label.id));
+            }
+        }
+        self.blocks.len() < count
+    }
+
+    /// eliminate_dead_instructions removes instructions without side effects whose values are
+    /// never used, until every one left is used
+    pub(crate) fn eliminate_dead_instructions(&mut self) -> bool {
+        let mut eliminated = false;
+        loop {
+            let used: HashSet<ID> = self
+                .blocks
+                .iter()
+                .flat_map(|block| {
+                    let terminator = block.terminator.operands().into_iter();
+                    block
+                        .instructions
+                        .iter()
+                        .flat_map(Instruction::used_ids)
+                        .chain(terminator.filter_map(Expr::local_id_of))
+                })
+                .collect();
+            let mut changed = false;
+            for block in &mut self.blocks {
+                let count = block.instructions.len();
+                block.instructions.retain(|inst| match inst.result_id() {
+                    Some(id) => used.contains(&id) || has_side_effect(inst),
+                    None => true,
+                });
+                changed |= block.instructions.len() < count;
+            }
+            if !changed {
+                return eliminated;
+            }
+            eliminated = true;
+        }
+    }
+
+    /// fold_branch turns the branch or switch on a constant at the end of the `i`th block into a
+    /// jump, and removes the block from incoming blocks of phi in blocks not jumped to
+    fn fold_branch(&mut self, i: usize) -> bool {
+        let taken = match &self.blocks[i].terminator {
+            Terminator::Branch {
+                cond: Expr::Bool(cond),
+                if_true,
+                if_false,
+            } => {
+                if *cond {
+                    if_true.clone()
+                } else {
+                    if_false.clone()
+                }
+            }
+            Terminator::Switch {
+                value: Expr::Int(_, value),
+                default,
+                cases,
+            } => cases
+                .iter()
+                .find(|(case, _)| case == value)
+                .map_or(default, |(_, label)| label)
+                .clone(),
+            _ => return false,
+        };
+        let from = self.blocks[i].label.id;
+        let dropped: HashSet<ID> = self.blocks[i]
+            .terminator
+            .targets()
+            .iter()
+            .map(|label| label.id)
+            .filter(|id| *id != taken.id)
+            .collect();
+        self.blocks[i].terminator = Terminator::Goto(taken);
+        for block in self
+            .blocks
+            .iter_mut()
+            .filter(|block| dropped.contains(&block.label.id))
+        {
+            for inst in &mut block.instructions {
+                if let Instruction::Phi { incoming, .. } = inst {
+                    incoming.retain(|(_, label)| label.id != from);
+                }
+            }
+        }
+        true
+    }
+
+    /// form_switches turns chains of branches comparing the same integer with constants into a
+    /// switch, e.g. `if x == 1 {} else if x == 2 {} else {}` jumps once on `x`
+    pub(crate) fn form_switches(&mut self) -> bool {
+        let mut formed = false;
+        let mut i = 0;
+        while i < self.blocks.len() {
+            if self.merge_comparison(i) {
+                formed = true;
+            } else {
+                i += 1;
+            }
+        }
+        formed
+    }
+
+    /// merge_comparison merges the comparison and branch of the block the `i`th block jumps to
+    /// when no case matches into the switch of the `i`th block, the `i`th block can end with a
+    /// branch on a comparison, which becomes the first case
+    fn merge_comparison(&mut self, i: usize) -> bool {
+        let (value, mut cases, default, compare) = match &self.blocks[i].terminator {
+            Terminator::Switch {
+                value,
+                default,
+                cases,
+            } => (value.clone(), cases.clone(), default.clone(), None),
+            Terminator::Branch {
+                cond,
+                if_true,
+                if_false,
+            } => match self.comparison_of(i, cond) {
+                Some((compare, value, case)) => (
+                    value,
+                    vec![(case, if_true.clone())],
+                    if_false.clone(),
+                    Some(compare),
+                ),
+                None => return false,
+            },
+            _ => return false,
+        };
+        // the next comparison must be all the block does, and only reached from the `i`th block
+        let next = match self
+            .blocks
+            .iter()
+            .position(|block| block.label.id == default.id)
+        {
+            Some(next) if next != i && self.predecessors(default.id) == 1 => next,
+            _ => return false,
+        };
+        let (if_true, if_false) = match &self.blocks[next].terminator {
+            Terminator::Branch {
+                cond,
+                if_true,
+                if_false,
+            } if self.blocks[next].instructions.len() == 1 => {
+                match self.comparison_of(next, cond) {
+                    Some((_, other, case))
+                        if other == value && cases.iter().all(|(c, _)| *c != case) =>
+                    {
+                        cases.push((case, if_true.clone()));
+                        (if_true.clone(), if_false.clone())
+                    }
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
+        // phi would need its incoming block renamed, and can't tell cases to the same block apart
+        if [&if_true, &if_false]
+            .iter()
+            .any(|label| self.has_phi_from(label.id, default.id))
+        {
+            return false;
+        }
+        if let Some(compare) = compare {
+            self.blocks[i].instructions.remove(compare);
+        }
+        self.blocks[i].terminator = Terminator::Switch {
+            value,
+            default: if_false,
+            cases,
+        };
+        self.blocks.remove(next);
+        true
+    }
+
+    /// comparison_of returns the index of the instruction of the `i`th block computing `cond` as
+    /// `value == constant`, the value and the constant, `cond` must be used by nothing else
+    fn comparison_of(&self, i: usize, cond: &Expr) -> Option<(usize, Expr, i64)> {
+        let id = cond.local_id_of()?;
+        let index = self.blocks[i]
+            .instructions
+            .iter()
+            .position(|inst| inst.result_id() == Some(id))?;
+        let (value, case) = match &self.blocks[i].instructions[index] {
+            Instruction::BinaryOperation {
+                op_name, lhs, rhs, ..
+            } if op_name == "icmp eq" => match (lhs, rhs) {
+                (Expr::Int(..), Expr::Int(..)) => return None,
+                (value, Expr::Int(_, case)) | (Expr::Int(_, case), value) => (value.clone(), *case),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let uses = self
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                let terminator = block.terminator.operands().into_iter();
+                block
+                    .instructions
+                    .iter()
+                    .flat_map(Instruction::used_ids)
+                    .chain(terminator.filter_map(Expr::local_id_of))
+            })
+            .filter(|used| *used == id)
+            .count();
+        (uses == 1).then_some((index, value, case))
+    }
+
+    /// predecessors counts jumps to the block
+    fn predecessors(&self, id: ID) -> usize {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.terminator.targets())
+            .filter(|label| label.id == id)
+            .count()
+    }
+
+    /// has_phi_from is true if the block `id` has a phi taking a value from the block `from`
+    fn has_phi_from(&self, id: ID, from: ID) -> bool {
+        self.blocks
+            .iter()
+            .filter(|block| block.label.id == id)
+            .flat_map(|block| &block.instructions)
+            .any(|inst| match inst {
+                Instruction::Phi { incoming, .. } => {
+                    incoming.iter().any(|(_, label)| label.id == from)
+                }
+                _ => false,
+            })
+    }
+
+    /// inline_calls inlines calls to `inlinable` functions, except calls of `caller` to itself
+    fn inline_calls(&mut self, inlinable: &HashMap<String, Function>, caller: &str) -> bool {
+        let mut values = HashMap::new();
+        let mut inlined = false;
+        for block in &mut self.blocks {
+            let mut instructions = vec![];
+            for inst in block.instructions.drain(..) {
+                let callee = match &inst {
+                    Instruction::FunctionCall { func_name, .. } if func_name != caller => {
+                        inlinable.get(func_name)
+                    }
+                    _ => None,
+                };
+                match (callee, &inst) {
+                    (Some(callee), Instruction::FunctionCall { id, args_expr, .. }) => {
+                        if let Some(value) = callee.inline_into(args_expr, &mut instructions) {
+                            values.insert(*id, value);
+                        }
+                        inlined = true;
+                    }
+                    _ => instructions.push(inst),
+                }
+            }
+            block.instructions = instructions;
+        }
+        if !inlined {
+            return false;
+        }
+        for block in &mut self.blocks {
+            for operand in block
+                .instructions
+                .iter_mut()
+                .flat_map(Instruction::operands_mut)
+                .chain(block.terminator.operands_mut())
+            {
+                substitute(operand, &values);
+            }
+        }
+        self.number();
+        true
+    }
+
+    /// promote_allocas replaces stack slots only loaded and stored with the values stored, a load
+    /// takes the last value stored before it in the block, or the value at the entry of the block,
+    /// which is a phi of values at the exit of predecessors when there are many of them
+    pub(crate) fn promote_allocas(&mut self) -> bool {
+        let slots = self.promotable_allocas();
+        if slots.is_empty() {
+            return false;
+        }
+        let mut promotion = Promotion {
+            slots,
+            predecessors: HashMap::new(),
+            exit_values: HashMap::new(),
+            entry_values: HashMap::new(),
+            phis: HashMap::new(),
+        };
+        for block in &self.blocks {
+            for target in block.terminator.targets() {
+                promotion
+                    .predecessors
+                    .entry(target.id)
+                    .or_default()
+                    .push(block.label.clone());
+            }
+            for inst in &block.instructions {
+                if let Instruction::Store {
+                    source,
+                    destination,
+                } = inst
+                {
+                    if promotion.slots.contains_key(destination) {
+                        promotion
+                            .exit_values
+                            .insert((block.label.id, *destination), source.clone());
+                    }
+                }
+            }
+        }
+
+        let mut values = HashMap::new();
+        for block in &mut self.blocks {
+            let label = block.label.id;
+            let mut current: HashMap<ID, Expr> = HashMap::new();
+            let mut instructions = vec![];
+            for inst in block.instructions.drain(..) {
+                match &inst {
+                    Instruction::Alloca { id, .. } if promotion.slots.contains_key(id) => (),
+                    Instruction::Store {
+                        source,
+                        destination,
+                    } if promotion.slots.contains_key(destination) => {
+                        current.insert(*destination, source.clone());
+                    }
+                    Instruction::Load {
+                        id,
+                        load_from: Expr::LocalIdentifier(_, slot),
+                    } if promotion.slots.contains_key(slot) => {
+                        let value = match current.get(slot) {
+                            Some(value) => value.clone(),
+                            None => promotion.entry_value(label, *slot),
+                        };
+                        values.insert(*id, value);
+                    }
+                    _ => instructions.push(inst),
+                }
+            }
+            block.instructions = instructions;
+        }
+        for block in &mut self.blocks {
+            if let Some(phis) = promotion.phis.remove(&block.label.id) {
+                block.instructions.splice(0..0, phis);
+            }
+            for operand in block
+                .instructions
+                .iter_mut()
+                .flat_map(Instruction::operands_mut)
+                .chain(block.terminator.operands_mut())
+            {
+                substitute(operand, &values);
+            }
+        }
+        true
+    }
+
+    /// promotable_allocas returns types of stack slots whose addresses are only loaded from and
+    /// stored to, other uses can keep the address, e.g. passing it to a function
+    fn promotable_allocas(&self) -> HashMap<ID, Type> {
+        let instructions = self.blocks.iter().flat_map(|block| &block.instructions);
+        let mut slots: HashMap<ID, Type> = instructions
+            .clone()
+            .filter_map(|inst| match inst {
+                Instruction::Alloca { id, typ } => Some((*id, typ.clone())),
+                _ => None,
+            })
+            .collect();
+        let escaped = instructions
+            .flat_map(|inst| match inst {
+                Instruction::Load { .. } => vec![],
+                Instruction::Store { source, .. } => source.local_id_of().into_iter().collect(),
+                _ => inst.used_ids(),
+            })
+            .chain(self.blocks.iter().flat_map(|block| {
+                let operands = block.terminator.operands().into_iter();
+                operands.filter_map(Expr::local_id_of)
+            }));
+        for id in escaped {
+            slots.remove(&id);
+        }
+        slots
+    }
+}
+
+/// Promotion is the state of `promote_allocas`, block and slot are both named by their IDs
+struct Promotion {
+    slots: HashMap<ID, Type>,
+    predecessors: HashMap<ID, Vec<Label>>,
+    /// exit_values are the last values stored to slots in blocks
+    exit_values: HashMap<(ID, ID), Expr>,
+    entry_values: HashMap<(ID, ID), Expr>,
+    /// phis are put at the start of blocks once all blocks are rewritten
+    phis: HashMap<ID, Vec<Instruction>>,
+}
+
+impl Promotion {
+    /// entry_value returns the value of `slot` when control flow enters `block`, a slot never
+    /// stored is zero
+    fn entry_value(&mut self, block: ID, slot: ID) -> Expr {
+        if let Some(value) = self.entry_values.get(&(block, slot)) {
+            return value.clone();
+        }
+        let typ = self.slots[&slot].clone();
+        let predecessors = self.predecessors.get(&block).cloned().unwrap_or_default();
+        let value = match predecessors.as_slice() {
+            [] => Expr::Zero(typ),
+            [predecessor] => self.exit_value(predecessor.id, slot),
+            _ => {
+                // the phi is the value before its incoming values are known, so a loop back to
+                // the block refers to the phi
+                let id = ID::new();
+                let phi = Expr::local_id(typ.clone(), id);
+                self.entry_values.insert((block, slot), phi.clone());
+                let incoming = predecessors
+                    .into_iter()
+                    .map(|predecessor| (self.exit_value(predecessor.id, slot), predecessor))
+                    .collect();
+                self.phis
+                    .entry(block)
+                    .or_default()
+                    .push(Instruction::Phi { id, typ, incoming });
+                phi
+            }
+        };
+        self.entry_values.insert((block, slot), value.clone());
+        value
+    }
+    fn exit_value(&mut self, block: ID, slot: ID) -> Expr {
+        match self.exit_values.get(&(block, slot)) {
+            Some(value) => value.clone(),
+            None => self.entry_value(block, slot),
+        }
+    }
+}
+
+/// has_side_effect is true for instructions which do more than producing their values, e.g. a call
+/// can print
+fn has_side_effect(inst: &Instruction) -> bool {
+    matches!(
+        inst,
+        Instruction::FunctionCall { .. } | Instruction::Malloca { .. } | Instruction::Store { .. }
+    )
+}
+
+/// substitute replaces the value of an instruction folded with the result
+fn substitute(operand: &mut Expr, values: &HashMap<ID, Expr>) {
+    // the result can be replaced again, e.g. a call inlined returning another call inlined
+    while let Some(value) = operand.local_id_of().and_then(|id| values.get(&id)) {
+        *operand = value.clone();
+    }
+}
+
+/// fold returns the value of the instruction if it's known at compile time, phi whose incoming
+/// values are all the same is the value
+fn fold(inst: &Instruction) -> Option<Expr> {
+    match inst {
+        Instruction::BinaryOperation {
+            op_name, lhs, rhs, ..
+        } => match (lhs, rhs) {
+            (Expr::Int(Type::Int(bits), lhs), Expr::Int(_, rhs)) => {
+                fold_integer(op_name, *bits, *lhs, *rhs)
+            }
+            (Expr::Bool(lhs), Expr::Bool(rhs)) => {
+                fold_integer(op_name, 1, *lhs as i64, *rhs as i64)
+            }
+            (Expr::F64(lhs), Expr::F64(rhs)) => fold_float(op_name, *lhs, *rhs),
+            _ => None,
+        },
+        Instruction::Phi { incoming, .. } => match incoming.split_first() {
+            Some(((value, _), rest)) if rest.iter().all(|(other, _)| other == value) => {
+                Some(value.clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// fold_integer computes the integer operation as LLVM does on `bits` bits, 8 bits integer is
+/// unsigned as `u8`, division which is undefined at runtime, e.g. by zero, isn't folded
+fn fold_integer(op_name: &str, bits: usize, lhs: i64, rhs: i64) -> Option<Expr> {
+    let unsigned = bits <= 8;
+    let (lhs, rhs) = (wrap(lhs, bits, unsigned), wrap(rhs, bits, unsigned));
+    let min = wrap(1 << (bits - 1), bits, unsigned);
+    let value = match op_name {
+        "add" => lhs.wrapping_add(rhs),
+        "sub" => lhs.wrapping_sub(rhs),
+        "mul" => lhs.wrapping_mul(rhs),
+        "sdiv" if rhs == 0 || (lhs == min && rhs == -1) => return None,
+        "sdiv" => lhs / rhs,
+        "udiv" if rhs == 0 => return None,
+        "udiv" => ((lhs as u64) / (rhs as u64)) as i64,
+        comparison => {
+            return Some(Expr::Bool(match comparison {
+                "icmp eq" => lhs == rhs,
+                "icmp ne" => lhs != rhs,
+                "icmp slt" | "icmp ult" => lhs < rhs,
+                "icmp sle" | "icmp ule" => lhs <= rhs,
+                "icmp sgt" | "icmp ugt" => lhs > rhs,
+                "icmp sge" | "icmp uge" => lhs >= rhs,
+                _ => return None,
+            }))
+        }
+    };
+    Some(Expr::Int(Type::Int(bits), wrap(value, bits, unsigned)))
+}
+
+/// wrap truncates `value` to `bits` bits, and extends it back as signed or unsigned integer
+pub(crate) fn wrap(value: i64, bits: usize, unsigned: bool) -> i64 {
+    if bits >= 64 {
+        return value;
+    }
+    let truncated = value & ((1 << bits) - 1);
+    if unsigned || truncated >> (bits - 1) == 0 {
+        truncated
+    } else {
+        truncated - (1 << bits)
+    }
+}
+
+/// fold_float computes the float operation, comparisons are ordered, so they're false on NaN
+fn fold_float(op_name: &str, lhs: f64, rhs: f64) -> Option<Expr> {
+    Some(match op_name {
+        "fadd" => Expr::F64(lhs + rhs),
+        "fsub" => Expr::F64(lhs - rhs),
+        "fmul" => Expr::F64(lhs * rhs),
+        "fdiv" => Expr::F64(lhs / rhs),
+        "fcmp oeq" => Expr::Bool(lhs == rhs),
+        "fcmp one" => Expr::Bool(!lhs.is_nan() && !rhs.is_nan() && lhs != rhs),
+        "fcmp olt" => Expr::Bool(lhs < rhs),
+        "fcmp ole" => Expr::Bool(lhs <= rhs),
+        "fcmp ogt" => Expr::Bool(lhs > rhs),
+        "fcmp oge" => Expr::Bool(lhs >= rhs),
+        _ => return None,
+    })
+}
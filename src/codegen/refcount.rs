@@ -0,0 +1,282 @@
+//! refcount frees class instances when nothing refers to them. Every instance starts with a
+//! hidden counter field, which is 1 after construction, `@elz.retain.Foo` increments it and
+//! `@elz.release.Foo` decrements it, calling `drop` of the class, then releasing the fields and
+//! freeing the instance at 0.
+//!
+//! Values of expressions are owned or borrowed. Constructions, results of calls and if
+//! expressions are owned temporaries, parameters and fields are borrowed. Arguments are passed
+//! borrowed, and storing a value into a field or returning it takes a reference, which moves an
+//! owned temporary or retains a borrowed value. Temporaries left are released at the end of the
+//! statement, or the branch they're in.
+//!
+//! Counters are not atomic, elz programs run on a single thread.
+use super::ir::*;
+use crate::ast::DROP_METHOD;
+use crate::lexer::Location;
+use std::mem;
+
+/// REFCOUNT_FIELD names the counter field, it's not an identifier, so no field collides with it
+pub(crate) const REFCOUNT_FIELD: &str = "@refcount";
+/// FREE_FUNCTION is `free` of C, which frees memory from `malloc`
+const FREE_FUNCTION: &str = "@free";
+
+impl Module {
+    /// is_refcounted is true for values of classes, which are pointers to their instances
+    pub(crate) fn is_refcounted(&self, typ: &Type) -> bool {
+        match typ {
+            Type::Pointer(element_type) => {
+                matches!(element_type.as_ref(), Type::Named(name) if self.types.contains_key(name))
+            }
+            _ => false,
+        }
+    }
+    /// refcount_call calls `@elz.retain.Foo` or `@elz.release.Foo` with the instance, the function
+    /// is defined by `push_refcount_functions`
+    fn refcount_call(&mut self, action: &str, value: Expr) -> Instruction {
+        let class_name = match value.type_() {
+            Type::Pointer(element_type) => match element_type.as_ref() {
+                Type::Named(name) => name.clone(),
+                typ => unreachable!("`{:?}` is not a class", typ),
+            },
+            typ => unreachable!("`{:?}` is not a class", typ),
+        };
+        self.refcounted_classes.insert(class_name.clone());
+        Instruction::FunctionCall {
+            id: ID::new(),
+            func_name: format!("@elz.{}.{}", action, class_name),
+            ret_type: Type::Void.into(),
+            args_expr: vec![value],
+        }
+    }
+    /// push_refcount_functions defines retain and release functions of classes whose instances
+    /// are retained or released, and of classes of their fields
+    pub(crate) fn push_refcount_functions(&mut self) {
+        if self.refcounted_classes.is_empty() {
+            return;
+        }
+        let c_string = Type::Pointer(Type::Int(8).into());
+        self.functions
+            .entry(FREE_FUNCTION.to_string())
+            .or_insert_with(|| Function {
+                location: Location::none(),
+                name: FREE_FUNCTION.to_string(),
+                parameters: vec![("pointer".to_string(), c_string)],
+                ret_typ: Type::Void,
+                body: None,
+                inline: false,
+            });
+        let mut done = std::collections::HashSet::new();
+        while let Some(class_name) = self
+            .refcounted_classes
+            .iter()
+            .find(|name| !done.contains(*name))
+            .cloned()
+        {
+            done.insert(class_name.clone());
+            let retain = self.retain_function(&class_name);
+            self.push_function(retain);
+            let release = self.release_function(&class_name);
+            self.push_function(release);
+        }
+    }
+    /// retain_function increments the counter
+    fn retain_function(&mut self, class_name: &str) -> Function {
+        let object = Expr::Identifier(Type::class(class_name), "object".to_string());
+        let (mut instructions, counter, count) = self.load_counter(&object);
+        let sum = ID::new();
+        instructions.push(Instruction::BinaryOperation {
+            id: sum,
+            op_name: "add".to_string(),
+            lhs: count,
+            rhs: Expr::Int(self.target.int_type(), 1),
+        });
+        instructions.push(Instruction::Store {
+            source: Expr::local_id(self.target.int_type(), sum),
+            destination: counter,
+        });
+        let blocks = vec![block(ID::new(), instructions, Terminator::Return(None))];
+        refcount_function("retain", class_name, blocks)
+    }
+    /// release_function decrements the counter, the last release calls `drop`, releases class
+    /// fields and frees the instance
+    fn release_function(&mut self, class_name: &str) -> Function {
+        let int_type = self.target.int_type();
+        let object = Expr::Identifier(Type::class(class_name), "object".to_string());
+        let (mut instructions, counter, count) = self.load_counter(&object);
+        let rest = ID::new();
+        instructions.push(Instruction::BinaryOperation {
+            id: rest,
+            op_name: "sub".to_string(),
+            lhs: count,
+            rhs: Expr::Int(int_type.clone(), 1),
+        });
+        let rest = Expr::local_id(int_type.clone(), rest);
+        instructions.push(Instruction::Store {
+            source: rest.clone(),
+            destination: counter,
+        });
+        let is_last = ID::new();
+        instructions.push(Instruction::BinaryOperation {
+            id: is_last,
+            op_name: "icmp eq".to_string(),
+            lhs: rest,
+            rhs: Expr::Int(int_type, 0),
+        });
+        let free_label = Label::new(ID::new(), &Location::none());
+        let leave_label = Label::new(ID::new(), &Location::none());
+        let entry = block(
+            ID::new(),
+            instructions,
+            Terminator::Branch {
+                cond: Expr::local_id(Type::Int(1), is_last),
+                if_true: free_label.clone(),
+                if_false: leave_label.clone(),
+            },
+        );
+
+        let mut instructions = vec![];
+        if self.dropped_classes.contains(class_name) {
+            // the counter is 1 while `drop` runs, so retaining and releasing `self` in it doesn't
+            // free the instance again
+            instructions.push(Instruction::Store {
+                source: Expr::Int(self.target.int_type(), 1),
+                destination: counter,
+            });
+            instructions.push(Instruction::FunctionCall {
+                id: ID::new(),
+                func_name: format!("@{}", self.method_symbol(class_name, DROP_METHOD)),
+                ret_type: Type::Void.into(),
+                args_expr: vec![object.clone()],
+            });
+        }
+        let fields = self.struct_fields(&class_name.to_string()).clone();
+        for (i, field) in fields.iter().enumerate() {
+            if !self.is_refcounted(&field.typ) {
+                continue;
+            }
+            let pointer = ID::new();
+            instructions.push(Instruction::GEP {
+                id: pointer,
+                load_from: object.clone(),
+                indices: vec![0, i as u64],
+            });
+            let value = ID::new();
+            instructions.push(Instruction::Load {
+                id: value,
+                load_from: Expr::local_id(field.typ.as_ref().clone(), pointer),
+            });
+            let release =
+                self.refcount_call("release", Expr::local_id(field.typ.as_ref().clone(), value));
+            instructions.push(release);
+        }
+        let memory = ID::new();
+        let c_string = Type::Pointer(Type::Int(8).into());
+        instructions.push(Instruction::BitCast {
+            id: memory,
+            from: object,
+            target_type: c_string.clone(),
+        });
+        instructions.push(Instruction::FunctionCall {
+            id: ID::new(),
+            func_name: FREE_FUNCTION.to_string(),
+            ret_type: Type::Void.into(),
+            args_expr: vec![Expr::local_id(c_string, memory)],
+        });
+        let blocks = vec![
+            entry,
+            block(free_label.id, instructions, Terminator::Return(None)),
+            block(leave_label.id, vec![], Terminator::Return(None)),
+        ];
+        refcount_function("release", class_name, blocks)
+    }
+    /// load_counter loads the counter of `object`, returns the instructions, the pointer to the
+    /// counter and its value
+    fn load_counter(&self, object: &Expr) -> (Vec<Instruction>, ID, Expr) {
+        let int_type = self.target.int_type();
+        let counter = ID::new();
+        let count = ID::new();
+        let instructions = vec![
+            Instruction::GEP {
+                id: counter,
+                load_from: object.clone(),
+                indices: vec![0, 0],
+            },
+            Instruction::Load {
+                id: count,
+                load_from: Expr::local_id(int_type.clone(), counter),
+            },
+        ];
+        (instructions, counter, Expr::local_id(int_type, count))
+    }
+}
+
+impl Body {
+    /// own records `value` as a temporary holding a reference, e.g. a new instance
+    pub(crate) fn own(&mut self, value: Expr, module: &Module) -> Expr {
+        if module.is_refcounted(&value.type_()) {
+            self.owned.push(value.clone());
+        }
+        value
+    }
+    /// take makes `value` hold a reference for where it's stored or returned to, an owned
+    /// temporary is moved, and a borrowed value is retained
+    pub(crate) fn take(&mut self, value: Expr, module: &mut Module) -> Expr {
+        if !module.is_refcounted(&value.type_()) {
+            return value;
+        }
+        match self.owned.iter().position(|owned| owned == &value) {
+            Some(index) => {
+                self.owned.remove(index);
+            }
+            None => {
+                let retain = module.refcount_call("retain", value.clone());
+                self.push(retain);
+            }
+        }
+        value
+    }
+    /// release drops the reference `value` holds
+    pub(crate) fn release(&mut self, value: Expr, module: &mut Module) {
+        let release = module.refcount_call("release", value);
+        self.push(release);
+    }
+    /// release_temporaries releases temporaries left, nothing is left after a terminator, since
+    /// returned values are taken before it
+    pub(crate) fn release_temporaries(&mut self, module: &mut Module) {
+        for value in mem::take(&mut self.owned) {
+            self.release(value, module);
+        }
+    }
+    /// enter_branch starts recording temporaries of a branch, which don't dominate the code after
+    /// the branches, so they're released at the end of the branch, returns temporaries before it
+    pub(crate) fn enter_branch(&mut self) -> Vec<Expr> {
+        mem::take(&mut self.owned)
+    }
+    /// leave_branch releases temporaries of the branch and restores `outer`
+    pub(crate) fn leave_branch(&mut self, outer: Vec<Expr>, module: &mut Module) {
+        self.release_temporaries(module);
+        self.owned = outer;
+    }
+}
+
+fn block(id: ID, instructions: Vec<Instruction>, terminator: Terminator) -> BasicBlock {
+    BasicBlock {
+        label: Label::new(id, &Location::none()),
+        instructions,
+        terminator,
+        statements: vec![],
+    }
+}
+
+fn refcount_function(action: &str, class_name: &str, blocks: Vec<BasicBlock>) -> Function {
+    let mut body = Body::with_blocks(blocks);
+    body.number();
+    Function {
+        location: Location::none(),
+        name: format!("@elz.{}.{}", action, class_name),
+        parameters: vec![("object".to_string(), Type::class(class_name))],
+        ret_typ: Type::Void,
+        body: Some(body),
+        inline: false,
+    }
+}
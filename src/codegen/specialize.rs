@@ -0,0 +1,181 @@
+//! specialize compiles static methods of a generic class tagged `@specialize` once for each type of
+//! elements they're called with, e.g. `List[int]::sort` and `List[string]::sort`, or of keys for
+//! `Map`. An erased value can't tell its type, so `List::_compare` of a specialization calls
+//! `compare` of the type the specialization is for, and a specialized method calls other
+//! specialized methods of the type.
+//!
+//! A generic top-level function, e.g. `assert_eq[T <: Eq + ToString]`, is compiled once for each
+//! type it's called with as well, but nothing of it is erased. Its type parameters are replaced
+//! with the types, so `left == right` of `assert_eq[string]` calls `string::eq`.
+use super::ir::*;
+use crate::ast::{self, Argument, ParsedType};
+use crate::lexer::Location;
+use crate::mangle::{Segment, Symbol};
+use std::collections::HashSet;
+
+impl Module {
+    /// specialization returns the symbol of the specialized method `path` for the type of
+    /// elements, which is the one of the specialization being generated, or the type argument of
+    /// the list at `location`, and requests to generate it
+    pub(crate) fn specialization(&mut self, path: &str, location: &Location) -> String {
+        let element_class = match &self.element_class {
+            Some(element_class) => element_class.clone(),
+            None => self.element_class_of(location),
+        };
+        let (class_name, f) = &self.specialized_methods[path];
+        let symbol = self.specialized_symbol(class_name, &f.name, &element_class);
+        self.pending_specializations
+            .push((path.to_string(), element_class));
+        symbol
+    }
+    /// element_class_of returns the class of elements of the list at `location`, e.g. `int` of
+    /// `List[int]`, or the one of keys of a map, e.g. `string` of `Map[string, int]`
+    fn element_class_of(&self, location: &Location) -> String {
+        let typ = self.expr_types.type_of(location).unwrap_or_default();
+        match typ.split_once('[') {
+            Some((_, element_type)) => element_type
+                .split(['[', ',', ']'])
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            None => unreachable!(
+                "specialized method called on `{}` which unlikely happened, semantic module must have a bug there!",
+                typ
+            ),
+        }
+    }
+    /// specialized_symbol returns the symbol of `method` of the class with `element_class` as its
+    /// type argument, e.g. the one of `prelude::List[int]::sort`
+    fn specialized_symbol(&self, class_name: &str, method: &str, element_class: &str) -> String {
+        let class = Segment::new(class_name).with_type_args(vec![Segment::new(element_class)]);
+        let segments = self
+            .class_modules
+            .get(class_name)
+            .map(Segment::new)
+            .into_iter()
+            .chain([class, Segment::new(method)])
+            .collect();
+        Symbol::new(segments).mangle()
+    }
+    /// instance returns the symbol and the return type of the generic function `name` for the
+    /// types of `args`, and requests to generate it, e.g. `assert_eq[int]` for
+    /// `assert_eq(1, 2)`. A type parameter takes the type of the first argument of it, or the
+    /// one of the call at `location` if it's only the return type
+    pub(crate) fn instance(
+        &mut self,
+        name: &str,
+        args: &[Argument],
+        location: &Location,
+    ) -> (String, Type) {
+        let f = &self.generic_functions[name];
+        let type_args: Vec<String> = f
+            .type_parameters
+            .iter()
+            .map(|p| {
+                let typ = ParsedType::type_name(&p.name);
+                let at = match f.parameters.iter().position(|param| param.typ == typ) {
+                    Some(i) => &args[i].expr.location,
+                    None => location,
+                };
+                let typ = self.expr_types.type_of(at).unwrap_or_default();
+                match typ.split('[').next() {
+                    Some(class_name) if !class_name.starts_with('\'') => class_name.to_string(),
+                    _ => unreachable!(
+                        "type parameter `{}` of `{}` bound to `{}` which unlikely happened, semantic module must have a bug there!",
+                        p.name, name, typ
+                    ),
+                }
+            })
+            .collect();
+        let ret_type = Type::from_ast(&instantiate(f, &type_args).ret_typ, &self.target);
+        let symbol = instance_symbol(name, &type_args);
+        self.pending_instances.push((name.to_string(), type_args));
+        (symbol, ret_type)
+    }
+    /// generate_specializations generates specializations and instances of generic functions
+    /// requested by calls, including the ones requested by the generated ones
+    pub(crate) fn generate_specializations(&mut self) {
+        let mut generated = HashSet::new();
+        let mut instances = HashSet::new();
+        loop {
+            if let Some((name, type_args)) = self.pending_instances.pop() {
+                if instances.insert((name.clone(), type_args.clone())) {
+                    let f = instantiate(&self.generic_functions[&name], &type_args);
+                    let mut function = Function::from_ast(&f, None, self);
+                    function.name = format!("@{}", instance_symbol(&name, &type_args));
+                    self.push_function(function);
+                }
+                continue;
+            }
+            let (path, element_class) = match self.pending_specializations.pop() {
+                Some(pending) => pending,
+                None => break,
+            };
+            if !generated.insert((path.clone(), element_class.clone())) {
+                continue;
+            }
+            let (class_name, f) = self.specialized_methods[&path].clone();
+            self.element_class = Some(element_class.clone());
+            let mut function = Function::from_ast(&f, Some(class_name.clone()), self);
+            self.element_class = None;
+            function.name = format!(
+                "@{}",
+                self.specialized_symbol(&class_name, &f.name, &element_class)
+            );
+            self.push_function(function);
+        }
+    }
+}
+
+/// instance_symbol returns the symbol of the generic function `name` for `type_args`, e.g. the
+/// mangled symbol of `assert_eq[int]`
+fn instance_symbol(name: &str, type_args: &[String]) -> String {
+    let segment = Segment::new(name).with_type_args(type_args.iter().map(Segment::new).collect());
+    Symbol::new(vec![segment]).mangle()
+}
+
+/// instantiate replaces type parameters of the generic function with `type_args` in its signature
+fn instantiate(f: &ast::Function, type_args: &[String]) -> ast::Function {
+    let substitute = |typ: &mut ParsedType| {
+        if let Some(i) = f.type_parameters.iter().position(|p| p.name == typ.name()) {
+            *typ = ParsedType::type_name(&type_args[i]);
+        }
+    };
+    let mut f = f.clone();
+    for p in &mut f.parameters {
+        substitute(&mut p.typ);
+    }
+    substitute(&mut f.ret_typ);
+    f.type_parameters = vec![];
+    f
+}
+
+impl Body {
+    /// call_element_method calls `method` of the type of elements with slots `args`, which borrow
+    /// the elements, e.g. `compare` for `List::_compare`
+    pub(crate) fn call_element_method(
+        &mut self,
+        method: &str,
+        args: Vec<Expr>,
+        ret_type: Type,
+        module: &Module,
+    ) -> Expr {
+        let element_class = match &module.element_class {
+            Some(element_class) => element_class,
+            None => unreachable!("`{}` of elements out of specialized methods", method),
+        };
+        let typ = Type::from_ast(&ParsedType::type_name(element_class), &module.target);
+        let args_expr = args
+            .into_iter()
+            .map(|arg| self.unerase(arg, &typ))
+            .collect();
+        let id = ID::new();
+        self.push(Instruction::FunctionCall {
+            id,
+            func_name: format!("@{}", module.method_symbol(element_class, method)),
+            ret_type: ret_type.clone().into(),
+            args_expr,
+        });
+        Expr::local_id(ret_type, id)
+    }
+}
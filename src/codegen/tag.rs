@@ -2,6 +2,10 @@ use crate::ast::Tag;
 
 pub(crate) trait CodegenTag {
     fn is_builtin(&self) -> bool;
+    /// is_inline returns true for `@inline`, calls to the tagged function are inlined by `-O 2`
+    fn is_inline(&self) -> bool;
+    /// is_specialize returns true for `@specialize`, see `specialize`
+    fn is_specialize(&self) -> bool;
 }
 
 impl CodegenTag for Option<Tag> {
@@ -11,4 +15,16 @@ impl CodegenTag for Option<Tag> {
             None => false,
         }
     }
+    fn is_inline(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "inline" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
+    fn is_specialize(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "specialize" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
 }
@@ -0,0 +1,109 @@
+use super::ir::Type;
+use thiserror::Error;
+
+/// Target describes the machine generated code runs on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    /// LLVM target triple, e.g. `x86_64-unknown-linux-gnu`
+    pub triple: String,
+    /// LLVM data layout of the triple, e.g. `e-m:e-p:32:32-i64:64-n32:64-S128`
+    pub data_layout: String,
+    /// size of pointer in bytes, `int` has the same width, so it can hold a size or an index
+    pub pointer_size: usize,
+}
+
+#[derive(Debug, Error)]
+#[error("unknown target `{}`, supported architectures: {}", .0, SUPPORTED_ARCHS.join(", "))]
+pub struct UnknownTarget(String);
+
+const SUPPORTED_ARCHS: [&str; 6] = ["x86_64", "aarch64", "i686", "armv7", "wasm32", "riscv64"];
+
+impl Target {
+    /// host returns the target of the machine running the compiler
+    pub fn host() -> Target {
+        use std::env::consts::{ARCH, OS};
+        let name = match OS {
+            "macos" => format!("{}-apple-darwin", ARCH),
+            "windows" => format!("{}-pc-windows-msvc", ARCH),
+            _ => ARCH.to_string(),
+        };
+        Target::from_name(&name).expect("compiler runs on unsupported architecture")
+    }
+    /// from_name accepts an architecture, e.g. `wasm32`, or a full target triple, e.g.
+    /// `armv7-unknown-linux-gnueabihf`
+    pub fn from_name(name: &str) -> Result<Target, UnknownTarget> {
+        let arch = name.split('-').next().unwrap_or(name);
+        // data layouts are the ones LLVM uses for ELF, `m:e` is replaced by the mangling of the
+        // object format below
+        let (default_triple, pointer_size, data_layout) = match arch {
+            "x86_64" => (
+                "x86_64-unknown-linux-gnu",
+                8,
+                "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128",
+            ),
+            "aarch64" => (
+                "aarch64-unknown-linux-gnu",
+                8,
+                "e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128",
+            ),
+            "riscv64" => (
+                "riscv64-unknown-linux-gnu",
+                8,
+                "e-m:e-p:64:64-i64:64-i128:128-n64-S128",
+            ),
+            "i686" | "x86" => (
+                "i686-unknown-linux-gnu",
+                4,
+                "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128",
+            ),
+            "armv7" | "arm" => (
+                "armv7-unknown-linux-gnueabihf",
+                4,
+                "e-m:e-p:32:32-Fi8-i64:64-v128:64:128-a:0:32-n32-S64",
+            ),
+            "wasm32" => (
+                "wasm32-unknown-unknown",
+                4,
+                "e-m:e-p:32:32-i64:64-n32:64-S128",
+            ),
+            _ => return Err(UnknownTarget(name.to_string())),
+        };
+        let triple = if name.contains('-') {
+            name
+        } else {
+            default_triple
+        };
+        let mangling = if triple.contains("apple") {
+            "m:o"
+        } else if triple.contains("windows") {
+            "m:w"
+        } else {
+            "m:e"
+        };
+        Ok(Target {
+            triple: triple.to_string(),
+            data_layout: data_layout.replace("m:e", mangling),
+            pointer_size,
+        })
+    }
+
+    /// int_type returns the type of `int`
+    pub(crate) fn int_type(&self) -> Type {
+        Type::Int(self.pointer_size * 8)
+    }
+    /// is_wasm is true for WebAssembly, which has no libc, so C functions are provided by the host
+    pub fn is_wasm(&self) -> bool {
+        self.triple.starts_with("wasm32")
+    }
+    /// executable_extension returns the extension of linked programs, e.g. `wasm`, or an empty
+    /// string
+    pub fn executable_extension(&self) -> &'static str {
+        if self.is_wasm() {
+            "wasm"
+        } else if self.triple.contains("windows") {
+            "exe"
+        } else {
+            ""
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use super::*;
 use crate::lexer::TkType::EOF;
+use dump::IRDump;
+use graph::DotGraph;
 use llvm::LLVMValue;
+use optimize::OptLevel;
 
 #[test]
 fn test_codegen_main() {
@@ -21,6 +24,74 @@ fn global_variable() {
     assert_eq!(module.variables[0].llvm_represent(), "@x = global i64 1");
 }
 
+#[test]
+fn global_variable_is_evaluated_at_compile_time() {
+    let code = "
+    x: int = 1 + 2;
+    y: int = x + 3;
+    ";
+    let module = gen_code(code);
+    assert_eq!(module.variables[0].llvm_represent(), "@x = global i64 3");
+    assert_eq!(module.variables[1].llvm_represent(), "@y = global i64 6");
+}
+
+#[test]
+fn global_variable_with_if_expression() {
+    let code = "x: int = if 1 < 2 { 10 } else { 20 };";
+    let module = gen_code(code);
+    assert_eq!(module.variables[0].llvm_represent(), "@x = global i64 10");
+}
+
+#[test]
+fn string_constant_is_escaped_and_null_terminated() {
+    let code = "
+    foo(): void {
+      println(\"say \\\"hi\\\"\\nπ\");
+    }
+    ";
+    let module = gen_code(code);
+    let string_constant = module
+        .variables
+        .iter()
+        .map(|v| v.llvm_represent())
+        .find(|v| v.starts_with("@0"))
+        .unwrap();
+    assert_eq!(
+        string_constant,
+        "@0 = global [12 x i8] c\"say \\22hi\\22\\0A\\CF\\80\\00\""
+    );
+    assert!(module.functions["@foo"]
+        .llvm_represent()
+        .contains("getelementptr [12 x i8], [12 x i8]* @0, i32 0, i32 0"));
+}
+
+#[test]
+fn string_constants_are_numbered() {
+    let code = "
+    foo(): void {
+      println(\"a\");
+      println(\"bc\");
+    }
+    ";
+    let module = gen_code(code);
+    let constants: Vec<String> = module
+        .variables
+        .iter()
+        .map(|v| v.llvm_represent())
+        .collect();
+    // followed by constants of prelude
+    assert_eq!(
+        constants[..2],
+        [
+            "@0 = global [2 x i8] c\"a\\00\"",
+            "@1 = global [3 x i8] c\"bc\\00\""
+        ]
+    );
+    assert!(module.functions["@foo"]
+        .llvm_represent()
+        .contains("getelementptr [3 x i8], [3 x i8]* @1, i32 0, i32 0"));
+}
+
 #[test]
 fn test_return_value() {
     let code = "foo(): int = 1;";
@@ -43,6 +114,16 @@ fn test_function_declaration_with_parameter() {
     )
 }
 
+#[test]
+fn test_function_declaration_with_smaller_int_types() {
+    let code = "pack(x: i32, y: u8): i32;";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@pack").unwrap().llvm_represent(),
+        "declare i32 @pack(i32 %x, i8 %y)"
+    )
+}
+
 #[test]
 fn test_function_define_with_parameter() {
     let code = "const(x: int): int = 1;";
@@ -72,6 +153,33 @@ fn test_function_call() {
     )
 }
 
+#[test]
+fn binary_expr_selects_instruction_by_type() {
+    let code = "
+    int_ops(x: int, y: int): int = x - y * x / y;
+    f64_ops(x: f64, y: f64): f64 = x - y * x / y;
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@int_ops").unwrap().llvm_represent(),
+        "define i64 @int_ops(i64 %x, i64 %y) {
+  %1 = mul i64 %y, %x
+  %2 = sdiv i64 %1, %y
+  %3 = sub i64 %x, %2
+  ret i64 %3
+}"
+    );
+    assert_eq!(
+        module.functions.get("@f64_ops").unwrap().llvm_represent(),
+        "define double @f64_ops(double %x, double %y) {
+  %1 = fmul double %y, %x
+  %2 = fdiv double %1, %y
+  %3 = fsub double %x, %2
+  ret double %3
+}"
+    );
+}
+
 #[test]
 fn binary_expr() {
     let code = "
@@ -98,28 +206,71 @@ fn test_class_define() {
     let module = gen_code(code);
     assert_eq!(
         module.types.get("Foo").unwrap().llvm_def(),
-        "%Foo = type { i64 }"
+        "%Foo = type { i64, i64 }"
     );
     assert_eq!(
         module
             .functions
-            .get("@\"Foo::new\"")
+            .get("@_E4main3Foo3newE")
             .unwrap()
             .llvm_represent(),
-        "declare %Foo* @\"Foo::new\"()"
+        "declare %Foo* @_E4main3Foo3newE()"
     );
     assert_eq!(
         module
             .functions
-            .get("@\"Foo::bar\"")
+            .get("@_E4main3Foo3barE")
             .unwrap()
             .llvm_represent(),
-        "define void @\"Foo::bar\"(%Foo* %self) {
+        "define void @_E4main3Foo3barE(%Foo* %self) {
   ret void
 }"
     );
 }
 
+#[test]
+fn named_struct_types_are_defined_once() {
+    let code = "
+    start_of(line: Line): Point = line.from;
+    class Line {
+      from: Point;
+      to: Point;
+      ::new(p: Point): Line = Line { from: p, to: p };
+    }
+    class Point {
+      x: int;
+      y: int;
+    }
+    ";
+    let module = gen_code(code);
+    let output = module.llvm_represent();
+    assert!(output.contains(
+        "
+%Line = type { i64, %Point*, %Point* }
+%List = type { i64, i8*, i64, i64 }
+%ListIterator = type { i64, %List*, i64 }
+%Map = type { i64, %List*, %List*, %List* }
+%Option = type { i64, %List* }
+%Point = type { i64, i64, i64 }
+%StringBuilder = type { i64, i8*, i64, i64 }
+%env = type { i64 }
+%io = type { i64 }
+%math = type { i64 }
+%process = type { i64 }
+%string = type { i64, i8* }
+"
+    ));
+    assert_eq!(
+        module.functions["@start_of"].llvm_represent(),
+        "define %Point* @start_of(%Line* %line) {
+  %1 = getelementptr %Line, %Line* %line, i32 0, i32 1
+  %2 = load %Point*, %Point** %1
+  call void @elz.retain.Point(%Point* %2)
+  ret %Point* %2
+}"
+    );
+}
+
 #[test]
 fn llvm_if_else() {
     let code = "
@@ -150,17 +301,1306 @@ fn llvm_if_else() {
     )
 }
 
-// helpers, must put tests before this line
-fn gen_code(code: &'static str) -> ir::Module {
-    let mut parser = crate::parser::Parser::new("", code);
-    let mut program = parser
+#[test]
+fn comparison_feeds_branch() {
+    let code = "
+    max(x: int, y: int): int {
+      if x > y {
+        return x;
+      }
+      return y;
+    }
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@max").unwrap().llvm_represent(),
+        "define i64 @max(i64 %x, i64 %y) {
+  %1 = icmp sgt i64 %x, %y
+  br i1 %1, label %2, label %3
+; <label>:2:
+  ret i64 %x
+; <label>:3:
+  br label %4
+; <label>:4:
+  ret i64 %y
+}"
+    )
+}
+
+#[test]
+fn comparison_predicates() {
+    let code = "
+    f64_less(x: f64, y: f64): bool = x < y;
+    u8_less(x: u8, y: u8): bool = x < y;
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@f64_less").unwrap().llvm_represent(),
+        "define i1 @f64_less(double %x, double %y) {
+  %1 = fcmp olt double %x, %y
+  ret i1 %1
+}"
+    );
+    assert_eq!(
+        module.functions.get("@u8_less").unwrap().llvm_represent(),
+        "define i1 @u8_less(i8 %x, i8 %y) {
+  %1 = icmp ult i8 %x, %y
+  ret i1 %1
+}"
+    );
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let code = "
+    both(x: bool, y: bool): bool = x and y;
+    either(x: bool, y: bool): bool = x or y;
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@both").unwrap().llvm_represent(),
+        "define i1 @both(i1 %x, i1 %y) {
+  %1 = alloca i1
+  store i1 %x, i1* %1
+  br i1 %x, label %2, label %3
+; <label>:2:
+  store i1 %y, i1* %1
+  br label %3
+; <label>:3:
+  %4 = load i1, i1* %1
+  ret i1 %4
+}"
+    );
+    assert_eq!(
+        module.functions.get("@either").unwrap().llvm_represent(),
+        "define i1 @either(i1 %x, i1 %y) {
+  %1 = alloca i1
+  store i1 %x, i1* %1
+  br i1 %x, label %3, label %2
+; <label>:2:
+  store i1 %y, i1* %1
+  br label %3
+; <label>:3:
+  %4 = load i1, i1* %1
+  ret i1 %4
+}"
+    );
+}
+
+#[test]
+fn if_expression_merges_with_phi() {
+    let code = "
+    sign(x: int): int = if x < 0 { 0 - 1 } else if x == 0 { 0 } else { 1 };
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@sign").unwrap().llvm_represent(),
+        "define i64 @sign(i64 %x) {
+  %1 = icmp slt i64 %x, 0
+  br i1 %1, label %2, label %4
+; <label>:2:
+  %3 = sub i64 0, 1
+  br label %10
+; <label>:4:
+  %5 = icmp eq i64 %x, 0
+  br i1 %5, label %6, label %7
+; <label>:6:
+  br label %8
+; <label>:7:
+  br label %8
+; <label>:8:
+  %9 = phi i64 [ 0, %6 ], [ 1, %7 ]
+  br label %10
+; <label>:10:
+  %11 = phi i64 [ %3, %2 ], [ %9, %8 ]
+  ret i64 %11
+}"
+    );
+}
+
+#[test]
+fn void_call_takes_no_number() {
+    let code = "
+    foo(): void {}
+    one(): int = 1;
+    bar(): int {
+      foo();
+      return one();
+    }
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@bar").unwrap().llvm_represent(),
+        "define i64 @bar() {
+  call void @foo()
+  %1 = call i64 @one()
+  ret i64 %1
+}"
+    );
+}
+
+#[test]
+fn write_llvm_streams_the_same_text() {
+    let module = gen_code(
+        "
+    main(): void {
+      println(\"hello\");
+    }
+    ",
+    );
+    let mut buf = vec![];
+    module.write_llvm(&mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), module.llvm_represent());
+    // errors of the writer are returned, e.g. a closed pipe
+    let mut small = [0u8; 16];
+    assert!(module.write_llvm(&mut small[..]).is_err());
+}
+
+#[test]
+fn debug_info_locates_instructions_at_their_statements() {
+    let source = "
+    add(a: int, b: int): int = a + b;
+    main(): void {
+      println(\"one\");
+      add(1, 2);
+    }
+    ";
+    let program = parse_with_prelude("/src/main.elz", source);
+    let module = CodeGenerator::new()
+        .with_debug_info()
+        .generate_program(&program);
+    let code = module.llvm_represent();
+    let main = &code[code.find("define void @main").unwrap()..];
+    let main = &main[..main.find("\n}").unwrap()];
+    let dbg_of = |text: &str| {
+        let line = main.lines().find(|line| line.contains(text)).unwrap();
+        let dbg = &line[line.find("!dbg ").unwrap() + 5..];
+        dbg.split(' ').next().unwrap().to_string()
+    };
+    let node = |name: &str| {
+        let start = code.find(&format!("\n{} = ", name)).unwrap() + 1;
+        code[start..].lines().next().unwrap().to_string()
+    };
+    // nodes are numbered in the order functions are written
+    let subprogram = node(&dbg_of("define void @main"));
+    assert!(subprogram.contains("= distinct !DISubprogram(name: \"main\","));
+    assert!(subprogram.contains(" line: 3,"));
+    let file = &subprogram[subprogram.find("file: ").unwrap() + 6..];
+    let file = file.split(',').next().unwrap();
+    assert!(node(file).ends_with("!DIFile(filename: \"main.elz\", directory: \"/src\")"));
+    assert!(node(&dbg_of("call void @println")).contains("!DILocation(line: 4, column: 7,"));
+    assert!(node(&dbg_of("call i64 @add")).contains("!DILocation(line: 5, column: 7,"));
+    assert!(code.contains("!llvm.dbg.cu = !{!0}"));
+    // without debug info, nothing changes
+    assert!(!gen_code(source).llvm_represent().contains("!dbg"));
+}
+
+#[test]
+fn module_can_be_sent_to_other_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ir::Module>();
+
+    let code = "
+    main(): void {
+      println(\"hello\");
+    }
+    ";
+    let module = std::thread::spawn(move || gen_code(code)).join().unwrap();
+    assert!(module
+        .llvm_represent()
+        .contains("call void @println(%string* %2)"));
+}
+
+#[test]
+fn block_after_returning_branches_is_unreachable() {
+    let code = "
+    foo(b: bool): int {
+      if b {
+        return 1;
+      } else {
+        return 2;
+      }
+    }
+    ";
+    let module = gen_code(code);
+    let body = module.functions["@foo"].body.as_ref().unwrap();
+    assert_eq!(body.blocks.len(), 4);
+    assert_eq!(body.blocks[3].terminator, ir::Terminator::Unreachable);
+    assert_eq!(
+        module.functions.get("@foo").unwrap().llvm_represent(),
+        "define i64 @foo(i1 %b) {
+  br i1 %b, label %1, label %2
+; <label>:1:
+  ret i64 1
+; <label>:2:
+  ret i64 2
+; <label>:3:
+  unreachable
+}"
+    );
+}
+
+#[test]
+fn control_flow_graph() {
+    let code = "
+    foo(): void {
+      if true {
+        return;
+      }
+    }
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@foo").unwrap().dot_represent(),
+        "digraph \"@foo\" {
+  node [shape=box, fontname=monospace];
+  \"entry\" [label=\"entry: line 2\\lbr i1 true, label %1, label %2\\l\"];
+  \"1\" [label=\"1: line 3\\lret void\\l\"];
+  \"2\" [label=\"2: line 3\\lbr label %3\\l\"];
+  \"3\" [label=\"3: line 3\\lret void\\l\"];
+  \"entry\" -> \"1\" [label=\"true\"];
+  \"entry\" -> \"2\" [label=\"false\"];
+  \"2\" -> \"3\";
+}"
+    )
+}
+
+#[test]
+fn eliminate_dead_functions() {
+    let code = "
+    main(): void {
+      foo();
+    }
+    foo(): void {}
+    bar(): void {}
+    ";
+    let mut module = gen_code(code);
+    module.eliminate_dead_functions();
+    assert_valid(&module);
+    // methods are always kept, with what they call
+    assert!(module.functions.contains_key("@_E7prelude6string3newE"));
+    assert!(module.functions.contains_key("@malloc"));
+    assert!(module.functions.contains_key("@main"));
+    assert!(module.functions.contains_key("@foo"));
+    assert!(!module.functions.contains_key("@bar"));
+    assert!(!module.functions.contains_key("@assert_eq"));
+}
+
+#[test]
+fn void_main_exits_with_zero() {
+    let code = "
+    main(): void {
+      foo();
+    }
+    foo(): void {
+      main();
+    }
+    ";
+    let mut module = gen_code(code);
+    module.wrap_main();
+    assert_valid(&module);
+    let main = module.functions["@main"].llvm_represent();
+    assert!(main.starts_with("define i32 @main(i32 %argc, i8** %argv) {"));
+    assert!(main.ends_with(
+        "
+  call void @elz.main()
+  ret i32 0
+}"
+    ));
+    assert!(module.functions["@foo"]
+        .llvm_represent()
+        .contains("call void @elz.main()"));
+}
+
+#[test]
+fn main_stores_args() {
+    let code = "
+    main(): int = List::length(env::args());
+    ";
+    let mut module = gen_code(code);
+    module.wrap_main();
+    assert_valid(&module);
+    let main = module.functions["@main"].llvm_represent();
+    // `argc` and `argv` are stored for `env::args` before `main` of the program runs
+    assert!(main.contains("sext i32 %argc to i64"));
+    assert!(main.contains("ptrtoint i8** %argv to i64"));
+    assert!(main.ends_with(
+        "
+  %5 = call i64 @elz.main()
+  %6 = trunc i64 %5 to i32
+  ret i32 %6
+}"
+    ));
+}
+
+#[test]
+fn static_methods_are_called_by_mangled_symbols() {
+    let code = "
+    class Foo {
+      x: int;
+      ::new(x: int): Foo = Foo {x: x};
+    }
+    foo(): Foo = Foo::new(1);
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define %Foo* @foo() {
+  %1 = call %Foo* @_E4main3Foo3newE(i64 1)
+  ret %Foo* %1
+}"
+    );
+    // a top list out of modules has no module segment
+    let program = parse_with_prelude("", code);
+    let top_list: Vec<TopAst> = program.into_iter().flat_map(|m| m.top_list).collect();
+    let module = CodeGenerator::new().generate_module(&top_list);
+    assert!(module.functions.contains_key("@_E3Foo3newE"));
+}
+
+#[test]
+fn functions_return_large_classes_by_pointer() {
+    let code = "
+    class Big {
+      a: int;
+      b: int;
+      c: int;
+      d: int;
+      e: int;
+      ::new(): Big = Big {a: 1, b: 2, c: 3, d: 4, e: 5};
+    }
+    make(): Big = Big::new();
+    ";
+    let module = gen_code(code);
+    // the struct is never returned by value, so no out-pointer is passed
+    let make = module.functions["@make"].llvm_represent();
+    assert!(make.starts_with("define %Big* @make() {"));
+    assert!(make.contains("call %Big* @_E4main3Big3newE()"));
+}
+
+#[test]
+fn class_construction_with_default_field() {
+    let code = "
+    class Foo {
+      x: int = 1;
+      y: int;
+      ::new(y: int): Foo = Foo {y: y};
+    }";
+    let module = gen_code(code);
+    assert_eq!(
+        module
+            .functions
+            .get("@_E4main3Foo3newE")
+            .unwrap()
+            .llvm_represent(),
+        "define %Foo* @_E4main3Foo3newE(i64 %y) {
+  %1 = call i8* @malloc(i64 24)
+  %2 = bitcast i8* %1 to %Foo*
+  %3 = getelementptr %Foo, %Foo* %2, i32 0, i32 0
+  store i64 1, i64* %3
+  %4 = getelementptr %Foo, %Foo* %2, i32 0, i32 1
+  store i64 1, i64* %4
+  %5 = getelementptr %Foo, %Foo* %2, i32 0, i32 2
+  store i64 %y, i64* %5
+  ret %Foo* %2
+}"
+    );
+}
+
+#[test]
+fn constant_instances_are_copied_at_once() {
+    let code = "
+    class Foo {
+      x: int = 1;
+      flag: bool;
+      y: int;
+      ::new(): Foo = Foo {flag: true, y: 2};
+    }";
+    let module = gen_code(code);
+    let output = module.llvm_represent();
+    assert!(output.contains("@0 = global %Foo { i64 1, i64 1, i1 true, i64 2 }"));
+    assert!(output.contains("declare void @llvm.memcpy.p0i8.p0i8.i64(i8* %destination, i8* %source, i64 %size, i1 %volatile)"));
+    assert_eq!(
+        module.functions["@_E4main3Foo3newE"].llvm_represent(),
+        "define %Foo* @_E4main3Foo3newE() {
+  %1 = call i8* @malloc(i64 32)
+  %2 = bitcast i8* %1 to %Foo*
+  %3 = bitcast %Foo* @0 to i8*
+  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %1, i8* %3, i64 32, i1 false)
+  ret %Foo* %2
+}"
+    );
+}
+
+#[test]
+fn assign_to_field_stores_without_load() {
+    let code = "
+    class Foo {
+      x: int;
+      y: int;
+    }
+    set_y(foo: Foo, v: int): void {
+      foo.y = v;
+    }
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@set_y").unwrap().llvm_represent(),
+        "define void @set_y(%Foo* %foo, i64 %v) {
+  %1 = getelementptr %Foo, %Foo* %foo, i32 0, i32 2
+  store i64 %v, i64* %1
+  ret void
+}"
+    );
+}
+
+#[test]
+fn instances_are_reference_counted() {
+    let code = "
+    class Point {
+      x: int;
+      ::new(x: int): Point = Point { x: x };
+    }
+    class Line {
+      from: Point;
+      ::new(p: Point): Line = Line { from: p };
+    }
+    x_of(p: Point): int = p.x;
+    start_x(line: Line): int = x_of(line.from);
+    first_x(): int = start_x(Line::new(Point::new(1)));
+    replace(line: Line): void {
+      line.from = Point::new(x_of(Point::new(1)));
+    }
+    ";
+    let module = gen_code(code);
+    // the parameter is borrowed, so it's retained when stored
+    assert!(module.functions["@_E4main4Line3newE"]
+        .llvm_represent()
+        .contains(
+            "  call void @elz.retain.Point(%Point* %p)
+  store %Point* %p, %Point** %4"
+        ));
+    // the new instance is moved into the field, the old one and the temporary are released
+    assert_eq!(
+        module.functions["@replace"].llvm_represent(),
+        "define void @replace(%Line* %line) {
+  %1 = getelementptr %Line, %Line* %line, i32 0, i32 1
+  %2 = call %Point* @_E4main5Point3newE(i64 1)
+  %3 = call i64 @x_of(%Point* %2)
+  %4 = call %Point* @_E4main5Point3newE(i64 %3)
+  %5 = load %Point*, %Point** %1
+  store %Point* %4, %Point** %1
+  call void @elz.release.Point(%Point* %5)
+  call void @elz.release.Point(%Point* %2)
+  ret void
+}"
+    );
+    // releasing a line releases its point
+    assert_eq!(
+        module.functions["@elz.release.Line"].llvm_represent(),
+        "define void @elz.release.Line(%Line* %object) {
+  %1 = getelementptr %Line, %Line* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = sub i64 %2, 1
+  store i64 %3, i64* %1
+  %4 = icmp eq i64 %3, 0
+  br i1 %4, label %5, label %9
+; <label>:5:
+  %6 = getelementptr %Line, %Line* %object, i32 0, i32 1
+  %7 = load %Point*, %Point** %6
+  call void @elz.release.Point(%Point* %7)
+  %8 = bitcast %Line* %object to i8*
+  call void @free(i8* %8)
+  ret void
+; <label>:9:
+  ret void
+}"
+    );
+}
+
+#[test]
+fn drop_is_called_before_instance_is_freed() {
+    let code = "
+    class Res {
+      id: int;
+      ::new(): Res = Res { id: 1 };
+      drop(): void {
+        println(\"closed\");
+      }
+    }
+    id_of(r: Res): int = r.id;
+    main(): void {
+      _: int = id_of(Res::new());
+    }
+    ";
+    let module = gen_code(code);
+    let release = module.functions["@elz.release.Res"].llvm_represent();
+    assert!(release.contains(
+        "; <label>:5:
+  store i64 1, i64* %1
+  call void @_E4main3Res4dropE(%Res* %object)
+  %6 = bitcast %Res* %object to i8*
+  call void @free(i8* %6)"
+    ));
+}
+
+#[test]
+fn struct_layout_is_padded_for_alignment() {
+    let code = "
+    class Mixed {
+      flag: bool;
+      n: int;
+      byte: u8;
+    }
+    class Small {
+      byte: u8;
+      n: i32;
+      flag: bool;
+    }
+    class Empty {}
+    ";
+    let module = gen_code(code);
+    let target = &module.target;
+    // fields follow the reference counter
+    let mixed = &module.types["Mixed"];
+    assert_eq!(mixed.field_offsets(target), vec![0, 8, 16, 24]);
+    assert_eq!(mixed.size(target), 32);
+    assert_eq!(mixed.align(target), 8);
+    let small = &module.types["Small"];
+    assert_eq!(small.field_offsets(target), vec![0, 8, 12, 16]);
+    assert_eq!(small.size(target), 24);
+    assert_eq!(small.align(target), 8);
+    assert_eq!(module.types["Empty"].size(target), 8);
+}
+
+#[test]
+fn int_and_pointer_follow_target_width() {
+    let code = "
+    class Node {
+      value: int;
+      next: Node;
+      flag: bool;
+    }
+    new_node(): Node {
+      return Node { value: 1, next: new_node(), flag: true };
+    }
+    double(x: int): int = x * 2;
+    ";
+    let module = gen_code_for(code, "wasm32");
+    let target = &module.target;
+    let node = &module.types["Node"];
+    assert_eq!(node.field_offsets(target), vec![0, 4, 8, 12]);
+    assert_eq!(node.size(target), 16);
+    assert_eq!(
+        module.functions.get("@double").unwrap().llvm_represent(),
+        "define i32 @double(i32 %x) {
+  %1 = mul i32 %x, 2
+  ret i32 %1
+}"
+    );
+    assert!(module
+        .functions
+        .get("@new_node")
+        .unwrap()
+        .llvm_represent()
+        .contains("call i8* @malloc(i32 16)"));
+}
+
+#[test]
+fn target_from_name() {
+    let target = Target::from_name("wasm32").unwrap();
+    assert_eq!(target.triple, "wasm32-unknown-unknown");
+    assert_eq!(target.pointer_size, 4);
+    let target = Target::from_name("armv7-unknown-linux-musleabihf").unwrap();
+    assert_eq!(target.triple, "armv7-unknown-linux-musleabihf");
+    assert_eq!(target.pointer_size, 4);
+    assert_eq!(
+        Target::from_name("x86").unwrap().triple,
+        "i686-unknown-linux-gnu"
+    );
+    assert_eq!(
+        Target::from_name("sparc").unwrap_err().to_string(),
+        "unknown target `sparc`, supported architectures: x86_64, aarch64, i686, armv7, wasm32, riscv64"
+    );
+}
+
+#[test]
+fn executable_extension_of_target() {
+    let wasm = Target::from_name("wasm32").unwrap();
+    assert!(wasm.is_wasm());
+    assert_eq!(wasm.executable_extension(), "wasm");
+    assert_eq!(
+        Target::from_name("x86_64-pc-windows-msvc")
+            .unwrap()
+            .executable_extension(),
+        "exe"
+    );
+    assert_eq!(
+        Target::from_name("aarch64").unwrap().executable_extension(),
+        ""
+    );
+}
+
+#[test]
+fn module_starts_with_target_header() {
+    let module = gen_code_for("main(): void {}", "wasm32");
+    assert!(module.llvm_represent().starts_with(
+        "target datalayout = \"e-m:e-p:32:32-i64:64-n32:64-S128\"
+target triple = \"wasm32-unknown-unknown\"
+"
+    ));
+    let target = Target::from_name("aarch64-apple-darwin").unwrap();
+    assert_eq!(
+        target.data_layout,
+        "e-m:o-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128"
+    );
+}
+
+#[cfg(feature = "llvm")]
+#[test]
+fn native_module_passes_verifier() {
+    let code = "
+    class Point {
+      x: int;
+      y: int;
+      ::new(x: int): Point = Point { x: x, y: x };
+    }
+    pick(a: int, b: int): int = if a < b { a } else { b * 2 };
+    both(a: bool, b: bool): bool = a and b;
+    main(): void {
+      println(\"hello\");
+    }
+    ";
+    let module = gen_code(code);
+    let context = inkwell::context::Context::create();
+    let llvm_module = native::build(&context, &module).unwrap();
+    assert_eq!(
+        llvm_module.get_triple().as_str().to_str(),
+        Ok("x86_64-unknown-linux-gnu")
+    );
+    let pick = llvm_module.get_function("pick").unwrap();
+    assert_eq!(pick.count_basic_blocks(), 4);
+    assert!(llvm_module.get_function("_E4main5Point3newE").is_some());
+}
+
+#[test]
+fn dump_function_with_blocks() {
+    let code = "pick(a: int, b: int): int = if a < b { a } else { b };";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions.get("@pick").unwrap().dump_represent(),
+        "function @pick(%a: i64, %b: i64): i64
+  block %0 (entry, line 1)
+    %1 = icmp slt %a, %b
+    branch %1 then %2 else %3
+  block %2 (line 1)
+    goto %4
+  block %3 (line 1)
+    goto %4
+  block %4 (line 1)
+    %5 = phi i64 [%a from %2, %b from %3]
+    return %5
+"
+    );
+}
+
+#[test]
+fn dump_type_layout() {
+    let code = "class Point { x: int; y: bool; }";
+    let module = gen_code(code);
+    assert!(module.dump_represent().contains(
+        "type Point (size 24, align 8)
+  @refcount: i64 (offset 0)
+  x: i64 (offset 8)
+  y: i1 (offset 16)
+"
+    ));
+}
+
+#[test]
+fn coverage_counts_blocks_and_dumps_at_return() {
+    let code = "
+    main(): void {
+      if true {
+        println(\"yes\");
+      }
+    }
+    ";
+    let mut module = gen_code(code);
+    module.instrument_coverage("counts.bin");
+    assert_valid(&module);
+    let main = module.functions.get("@main").unwrap().llvm_represent();
+    // entry, then, else and leave blocks
+    assert_eq!(main.matches("add i64").count(), 4);
+    assert!(main.ends_with("  call void @elz.coverage.dump()\n  ret void\n}"));
+    let dump = module
+        .functions
+        .get("@elz.coverage.dump")
+        .unwrap()
+        .llvm_represent();
+    // `write` takes the fd as `int`, as the prelude declares it
+    assert!(dump.contains(
+        "  %2 = call i32 @creat(i8* %1, i32 420)
+  %3 = sext i32 %2 to i64
+  %4 = bitcast ["
+    ));
+    assert!(dump.contains("  %5 = call i64 @write(i64 %3, i8* %4, i64 "));
+    assert!(dump.ends_with("  %6 = call i32 @close(i32 %2)\n  ret void\n}"));
+}
+
+#[test]
+fn coverage_report_by_lines() {
+    let code = "
+    abs(n: int): int {
+      if n < 0 {
+        return 0 - n;
+      }
+      return n;
+    }
+    main(): void {
+      x: int = abs(1);
+    }
+    ";
+    let mut module = gen_code_in("abs.elz", code, "x86_64");
+    // functions of prelude are only declared, so counters of `abs` come first
+    for f in module.functions.values_mut() {
+        if f.location.file_name() != "abs.elz" {
+            f.body = None;
+        }
+    }
+    let coverage_map = module.instrument_coverage("counts.bin");
+    assert_valid(&module);
+    // counters follow function names, the then block of `abs` is never run
+    let mut counts = vec![1; 100];
+    counts[1] = 0;
+    assert_eq!(
+        coverage_map.report(&counts, &["abs.elz".to_string()]),
+        "abs.elz: 3/4 lines covered (75.0%)\n  not covered: 4\n"
+    );
+}
+
+#[test]
+fn string_plus_calls_concat() {
+    let code = "
+    greet(name: string): string = \"hello, \" + name;
+    ";
+    let module = gen_code(code);
+    assert!(module.functions["@greet"]
+        .llvm_represent()
+        .contains("%3 = call %string* @_E7prelude6string6concatE(%string* %2, %string* %name)"));
+}
+
+#[test]
+fn math_calls_libm() {
+    let code = "
+    hypot(x: f64, y: f64): f64 = math::sqrt(x * x + y * y);
+    ";
+    let module = gen_code(code);
+    assert!(module.functions["@_E7prelude4math4sqrtE"]
+        .llvm_represent()
+        .contains("%1 = call double @sqrt(double %x)"));
+    assert_eq!(
+        module.functions["@sqrt"].llvm_represent(),
+        "declare double @sqrt(double %x)"
+    );
+}
+
+#[test]
+fn conversions_are_casts() {
+    let code = "
+    average(sum: int, count: int): f64 = int::to_f64(sum) / int::to_f64(count);
+    whole(x: f64): int = f64::to_int(x);
+    ";
+    let module = gen_code_for(code, "wasm32");
+    assert_eq!(
+        module.functions["@average"].llvm_represent(),
+        "define double @average(i32 %sum, i32 %count) {
+  %1 = sitofp i32 %sum to double
+  %2 = sitofp i32 %count to double
+  %3 = fdiv double %1, %2
+  ret double %3
+}"
+    );
+    assert_eq!(
+        module.functions["@whole"].llvm_represent(),
+        "define i32 @whole(double %x) {
+  %1 = fptosi double %x to i32
+  ret i32 %1
+}"
+    );
+}
+
+#[test]
+fn template_values_call_to_string() {
+    let code = "
+    class Point <: ToString {
+      x: int;
+      to_string(): string = \"({self.x})\";
+    }
+    describe(p: Point, ok: bool): string = \"{p} {ok}\";
+    ";
+    let module = gen_code_for(code, "x86_64");
+    let describe = module.functions["@describe"].llvm_represent();
+    assert!(describe.contains("call %string* @_E4main5Point9to_stringE(%Point* %p)"));
+    assert!(describe.contains("call %string* @_E7prelude4bool9to_stringE(i1 %ok)"));
+}
+
+#[test]
+fn templates_of_three_or_more_pieces_are_built_by_string_builder() {
+    let code = "
+    describe(name: string, age: int): string = \"{name} is {age}\";
+    label(n: int): string = \"n = {n}\";
+    ";
+    let module = gen_code_for(code, "x86_64");
+    assert_eq!(
+        module.functions["@describe"].llvm_represent(),
+        "define %string* @describe(%string* %name, i64 %age) {
+  %1 = call %StringBuilder* @_E7prelude13StringBuilder3newE()
+  %2 = call %string* @_E7prelude6string9to_stringE(%string* %name)
+  call void @_E7prelude13StringBuilder6appendE(%StringBuilder* %1, %string* %2)
+  %3 = getelementptr [5 x i8], [5 x i8]* @0, i32 0, i32 0
+  %4 = call %string* @_E7prelude6string3newE(i8* %3)
+  call void @_E7prelude13StringBuilder6appendE(%StringBuilder* %1, %string* %4)
+  %5 = call %string* @_E7prelude3int9to_stringE(i64 %age)
+  call void @_E7prelude13StringBuilder6appendE(%StringBuilder* %1, %string* %5)
+  %6 = call %string* @_E7prelude13StringBuilder5buildE(%StringBuilder* %1)
+  call void @elz.release.StringBuilder(%StringBuilder* %1)
+  call void @elz.release.string(%string* %2)
+  call void @elz.release.string(%string* %4)
+  call void @elz.release.string(%string* %5)
+  ret %string* %6
+}"
+    );
+    // two pieces are concatenated at once
+    let label = module.functions["@label"].llvm_represent();
+    assert!(label.contains("@_E7prelude6string6concatE"));
+    assert!(!label.contains("StringBuilder"));
+}
+
+#[test]
+fn equality_of_instances_calls_eq() {
+    let code = "
+    class Point <: Eq {
+      x: int;
+      eq(other: Point): bool = self.x == other.x;
+    }
+    same(a: Point, b: Point): bool = a == b;
+    differ(a: string, b: string): bool = a != b;
+    ";
+    let module = gen_code_for(code, "x86_64");
+    assert_eq!(
+        module.functions["@same"].llvm_represent(),
+        "define i1 @same(%Point* %a, %Point* %b) {
+  %1 = call i1 @_E4main5Point2eqE(%Point* %a, %Point* %b)
+  ret i1 %1
+}"
+    );
+    assert_eq!(
+        module.functions["@differ"].llvm_represent(),
+        "define i1 @differ(%string* %a, %string* %b) {
+  %1 = call i1 @_E7prelude6string2eqE(%string* %a, %string* %b)
+  %2 = icmp eq i1 %1, false
+  ret i1 %2
+}"
+    );
+}
+
+// helpers, must put tests before this line
+#[test]
+fn constants_are_folded() {
+    let code = "
+    foo(): int = 1 + 2 * 3;
+    wrap(): int = 9223372036854775807 + 1;
+    compare(): bool = 2 - 3 > 0;
+    by_zero(): int = 1 / 0;
+    ";
+    let mut module = gen_code(code);
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo() {
+  %1 = mul i64 2, 3
+  %2 = add i64 1, %1
+  ret i64 %2
+}"
+    );
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo() {
+  ret i64 7
+}"
+    );
+    assert_eq!(
+        module.functions["@wrap"].llvm_represent(),
+        "define i64 @wrap() {
+  ret i64 -9223372036854775808
+}"
+    );
+    assert_eq!(
+        module.functions["@compare"].llvm_represent(),
+        "define i1 @compare() {
+  ret i1 false
+}"
+    );
+    // division by zero is left to fail at runtime
+    assert_eq!(
+        module.functions["@by_zero"].llvm_represent(),
+        "define i64 @by_zero() {
+  %1 = sdiv i64 1, 0
+  ret i64 %1
+}"
+    );
+}
+
+#[test]
+fn blocks_behind_constant_conditions_are_dropped() {
+    let code = "
+    main(): void {
+      if false {
+        println(\"never\");
+      } else if true {
+        println(\"always\");
+      } else {
+        println(\"never\");
+      }
+    }
+    ";
+    let mut module = gen_code(code);
+    assert_eq!(
+        module.functions["@main"]
+            .llvm_represent()
+            .matches("call void @println")
+            .count(),
+        3
+    );
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    let main = module.functions["@main"].llvm_represent();
+    assert_eq!(main.matches("call void @println").count(), 1);
+    assert!(!main.contains("br i1"));
+}
+
+#[test]
+fn branches_on_constant_conditions_are_folded() {
+    let code = "
+    foo(): int = if 1 < 2 { 3 } else { 4 };
+    bar(x: bool): bool = false and x;
+    ";
+    let mut module = gen_code(code);
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo() {
+  %1 = icmp slt i64 1, 2
+  br i1 %1, label %2, label %3
+; <label>:2:
+  br label %4
+; <label>:3:
+  br label %4
+; <label>:4:
+  %5 = phi i64 [ 3, %2 ], [ 4, %3 ]
+  ret i64 %5
+}"
+    );
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    // the block not jumped to is removed, so the phi only has one value
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo() {
+  br label %1
+; <label>:1:
+  br label %2
+; <label>:2:
+  ret i64 3
+}"
+    );
+    assert_eq!(
+        module.functions["@bar"].llvm_represent(),
+        "define i1 @bar(i1 %x) {
+  br label %1
+; <label>:1:
+  ret i1 false
+}"
+    );
+}
+
+#[test]
+fn stack_slots_are_promoted_to_values() {
+    let code = "
+    foo(x: bool, y: bool, z: bool): bool = x and y or z;
+    ";
+    let mut module = gen_code(code);
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i1 @foo(i1 %x, i1 %y, i1 %z) {
+  br i1 %x, label %1, label %2
+; <label>:1:
+  br label %2
+; <label>:2:
+  %3 = phi i1 [ %x, %0 ], [ %y, %1 ]
+  br i1 %3, label %5, label %4
+; <label>:4:
+  br label %5
+; <label>:5:
+  %6 = phi i1 [ %3, %2 ], [ %z, %4 ]
+  ret i1 %6
+}"
+    );
+}
+
+#[test]
+fn unused_values_are_eliminated() {
+    let code = "
+    class Point {
+      x: int;
+    }
+    foo(p: Point): int {
+      x: int = p.x + 1;
+      y: bool = p.x > 0;
+      println(\"foo\");
+      return 1;
+    }
+    ";
+    let mut module = gen_code(code);
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    // the call is kept since it prints
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo(%Point* %p) {
+  %1 = getelementptr [4 x i8], [4 x i8]* @0, i32 0, i32 0
+  %2 = call %string* @_E7prelude6string3newE(i8* %1)
+  call void @println(%string* %2)
+  call void @elz.release.string(%string* %2)
+  ret i64 1
+}"
+    );
+}
+
+#[test]
+fn comparison_chains_become_switches() {
+    let code = "
+    name(n: int): int {
+      if n == 1 {
+        return 10;
+      } else if 2 == n {
+        return 20;
+      } else if n == 1 {
+        return 99;
+      }
+      return 0;
+    }
+    ";
+    let mut module = gen_code(code);
+    module.optimize(OptLevel::O1);
+    assert_valid(&module);
+    // the repeated case is left as a comparison, it's never true
+    assert_eq!(
+        module.functions["@name"].llvm_represent(),
+        "define i64 @name(i64 %n) {
+  switch i64 %n, label %3 [ i64 1, label %1 i64 2, label %2 ]
+; <label>:1:
+  ret i64 10
+; <label>:2:
+  ret i64 20
+; <label>:3:
+  %4 = icmp eq i64 %n, 1
+  br i1 %4, label %5, label %6
+; <label>:5:
+  ret i64 99
+; <label>:6:
+  br label %7
+; <label>:7:
+  ret i64 0
+}"
+    );
+    assert!(module.functions["@name"]
+        .dump_represent()
+        .contains("    switch %n [1 then %1, 2 then %2] else %3\n"));
+}
+
+#[test]
+fn small_functions_are_inlined() {
+    let code = "
+    add(a: int, b: int): int = a + b;
+    power(x: int): int = x * x * x * x * x * x * x * x * x * x;
+    @inline
+    power_inline(x: int): int = x * x * x * x * x * x * x * x * x * x;
+    count(n: int): int = if n > 0 { count(n - 1) } else { 0 };
+    foo(x: int): int = add(add(x, 1), add(1, 2)) + power(x) + power_inline(2) + count(x);
+    ";
+    let mut module = gen_code(code);
+    module.optimize(OptLevel::O2);
+    assert_valid(&module);
+    // `power` is too large without `@inline`, and functions with branches aren't inlined
+    assert_eq!(
+        module.functions["@foo"].llvm_represent(),
+        "define i64 @foo(i64 %x) {
+  %1 = add i64 %x, 1
+  %2 = add i64 %1, 3
+  %3 = call i64 @power(i64 %x)
+  %4 = add i64 %2, %3
+  %5 = add i64 %4, 1024
+  %6 = call i64 @count(i64 %x)
+  %7 = add i64 %5, %6
+  ret i64 %7
+}"
+    );
+}
+
+#[test]
+fn invalid_ir_is_reported() {
+    let code = "
+    add(a: int, b: int): int = a + b;
+    foo(x: int): int = if x > 0 { add(x, 1) } else { 0 };
+    ";
+    let mut module = gen_code(code);
+    let int = || ir::Type::Int(64);
+    let body = module
+        .functions
+        .get_mut("@foo")
+        .unwrap()
+        .body
+        .as_mut()
+        .unwrap();
+    if let ir::Instruction::FunctionCall { args_expr, .. } = &mut body.blocks[1].instructions[0] {
+        args_expr.pop();
+    }
+    if let ir::Instruction::Phi { incoming, .. } = &mut body.blocks[3].instructions[0] {
+        incoming[1].0 = ir::Expr::local_id(int(), ir::ID::Number(3));
+    }
+    body.blocks[3].terminator =
+        ir::Terminator::Return(Some(ir::Expr::local_id(int(), ir::ID::Number(8))));
+    let errors: Vec<String> = module
+        .verify()
+        .unwrap_err()
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":3:4 internal compiler error while verifying IR: invalid IR in `@foo`: `@add` is called with 1 argument(s), but it takes 2",
+            ":3:4 internal compiler error while verifying IR: invalid IR in `@foo`: %3 is defined in block %2, which doesn't dominate its use in block %4",
+            ":3:4 internal compiler error while verifying IR: invalid IR in `@foo`: %8 is used in block %5 but never defined",
+        ]
+    );
+}
+
+#[test]
+fn value_of_expression_statement_is_released() {
+    let code = "
+    shout(s: string): string = s;
+    main(): void {
+      shout(\"a\");
+    }
+    ";
+    let module = gen_code(code);
+    assert_eq!(
+        module.functions["@main"].llvm_represent(),
+        "define void @main() {
+  %1 = getelementptr [2 x i8], [2 x i8]* @0, i32 0, i32 0
+  %2 = call %string* @_E7prelude6string3newE(i8* %1)
+  %3 = call %string* @shout(%string* %2)
+  call void @elz.release.string(%string* %2)
+  call void @elz.release.string(%string* %3)
+  ret void
+}"
+    );
+}
+
+#[test]
+fn power_calls_pow() {
+    let code = "
+    power(base: int, exponent: int): int = base ** exponent;
+    power_f64(base: f64, exponent: f64): f64 = base ** exponent;
+    ";
+    let module = gen_code(code);
+    assert!(module.functions["@power"]
+        .llvm_represent()
+        .contains("call i64 @_E7prelude3int4_powE(i64 %base, i64 %exponent)"));
+    assert!(module.functions["@power_f64"]
+        .llvm_represent()
+        .contains("call double @pow(double %base, double %exponent)"));
+}
+
+#[test]
+fn mistyped_ir_is_reported_at_its_block() {
+    let code = "
+    class Point {
+      x: int;
+      ::x_of(p: Point): int {
+        return p.x + 1;
+      }
+    }";
+    let mut module = gen_code(code);
+    let body = module
+        .functions
+        .get_mut("@_E4main5Point4x_ofE")
+        .unwrap()
+        .body
+        .as_mut()
+        .unwrap();
+    for inst in &mut body.blocks[0].instructions {
+        match inst {
+            ir::Instruction::GEP { indices, .. } => indices[1] = 2,
+            ir::Instruction::BinaryOperation { rhs, .. } => *rhs = ir::Expr::Bool(true),
+            _ => {}
+        }
+    }
+    let errors: Vec<String> = module
+        .type_check()
+        .unwrap_err()
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":4:28 internal compiler error while verifying IR: mistyped IR in `@_E4main5Point4x_ofE`: getelementptr indices [0, 2] are out of `%Point*`",
+            ":4:28 internal compiler error while verifying IR: mistyped IR in `@_E4main5Point4x_ofE`: `add` on `i64` and `i1`",
+        ]
+    );
+}
+
+fn gen_code(code: &'static str) -> ir::Module {
+    gen_code_for(code, "x86_64")
+}
+fn gen_code_for(code: &'static str, target: &str) -> ir::Module {
+    gen_code_in("", code, target)
+}
+fn gen_code_in(file_name: &str, code: &'static str, target: &str) -> ir::Module {
+    let code_generator = CodeGenerator::with_target(Target::from_name(target).unwrap());
+    let module = code_generator.generate_program(&parse_with_prelude(file_name, code));
+    assert_valid(&module);
+    module
+}
+/// assert_valid fails the test with errors of the IR verifier and the IR type checker
+fn assert_valid(module: &ir::Module) {
+    if let Err(errors) = module.verify() {
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        panic!("{}", errors.join("\n"));
+    }
+    if let Err(errors) = module.type_check() {
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        panic!("{}", errors.join("\n"));
+    }
+}
+/// parse_with_prelude parses the code as module `main`
+fn parse_with_prelude(file_name: &str, code: &'static str) -> Vec<Module> {
+    let mut parser = crate::parser::Parser::new(file_name, code);
+    let top_list = parser
         .parse_top_list(EOF)
         .map_err(|err| {
             panic!("{}", err);
         })
         .unwrap();
-    let mut prelude = crate::parser::parse_prelude();
-    prelude.top_list.append(&mut program);
-    let code_generator = CodeGenerator::new();
-    code_generator.generate_module(&prelude.top_list)
+    let main = Module {
+        name: "main".to_string(),
+        top_list,
+    };
+    // prelude goes after the code, so globals of the code are numbered from 0
+    vec![main, crate::parser::parse_prelude()]
 }
@@ -0,0 +1,400 @@
+//! type_check checks operands of every instruction have the types it needs before the module is
+//! emitted, e.g. a store writes a value of the type the pointer points to. A mismatch is a bug of
+//! the semantic checker or of code generation, so it's reported as an internal compiler error at
+//! the code it comes from, rather than found by LLVM. The location is the start of the block, or
+//! the statement with debug info, which marks instructions with their statements.
+use super::ir::*;
+use super::llvm::LLVMValue;
+use crate::ice::{InternalError, Phase};
+use crate::lexer::Location;
+use std::collections::HashMap;
+
+impl Module {
+    /// type_check checks every function body, returns all mismatches found, in the order of
+    /// function names
+    pub fn type_check(&self) -> Result<(), Vec<InternalError>> {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        let mut errors = vec![];
+        for name in names {
+            let function = &self.functions[name];
+            if let Some(body) = &function.body {
+                let mut checker = TypeChecker::new(self, function, body);
+                checker.check(body);
+                errors.extend(checker.errors);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+struct TypeChecker<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    /// types of local values and of globals defined by ID
+    values: HashMap<ID, Type>,
+    globals: HashMap<ID, Type>,
+    /// location is of the code being checked, a block starts at its label
+    location: Location,
+    errors: Vec<InternalError>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(module: &'a Module, function: &'a Function, body: &Body) -> TypeChecker<'a> {
+        let mut checker = TypeChecker {
+            module,
+            function,
+            values: HashMap::new(),
+            globals: HashMap::new(),
+            location: function.location.clone(),
+            errors: vec![],
+        };
+        for variable in &module.variables {
+            if let GlobalName::ID(id) = &variable.name {
+                checker
+                    .globals
+                    .insert(*id, Type::Pointer(variable.expr.type_().into()));
+            }
+        }
+        for block in &body.blocks {
+            for inst in &block.instructions {
+                if let (Some(id), Some(typ)) = (inst.result_id(), checker.result_type(inst)) {
+                    checker.values.insert(id, typ);
+                }
+            }
+        }
+        checker
+    }
+
+    fn check(&mut self, body: &Body) {
+        for block in &body.blocks {
+            if block.label.location != Location::none() {
+                self.location = block.label.location.clone();
+            }
+            for inst in &block.instructions {
+                self.check_instruction(inst);
+            }
+            self.check_terminator(&block.terminator);
+        }
+    }
+
+    /// result_type returns the type of the value `inst` defines, `None` when the operands have no
+    /// such value, which `check_instruction` reports
+    fn result_type(&self, inst: &Instruction) -> Option<Type> {
+        use Instruction::*;
+        match inst {
+            GEP {
+                load_from, indices, ..
+            } => self
+                .indexed_type(&load_from.type_(), indices)
+                .map(|typ| Type::Pointer(typ.into())),
+            FunctionCall { ret_type, .. } => Some(ret_type.as_ref().clone()),
+            BinaryOperation { op_name, .. } if is_comparison(op_name) => Some(Type::Int(1)),
+            BinaryOperation { lhs, .. } => Some(lhs.type_()),
+            Malloca { .. } => Some(Type::Pointer(Type::Int(8).into())),
+            Alloca { typ, .. } => Some(Type::Pointer(typ.clone().into())),
+            BitCast { target_type, .. } | Cast { target_type, .. } => Some(target_type.clone()),
+            ElementPointer { from, .. } => Some(from.type_()),
+            // the operand of load carries the type of the value loaded
+            Load { load_from, .. } => Some(load_from.type_()),
+            Phi { typ, .. } => Some(typ.clone()),
+            Store { .. } | MemCopy { .. } | DebugLocation(..) => None,
+        }
+    }
+
+    /// indexed_type returns the type `indices` of GEP select from the pointer type
+    fn indexed_type(&self, pointer: &Type, indices: &[u64]) -> Option<Type> {
+        let mut typ = match pointer {
+            Type::Pointer(element_type) => element_type.as_ref().clone(),
+            _ => return None,
+        };
+        for index in indices.iter().skip(1) {
+            typ = match &typ {
+                Type::Named(name) => match self.module.types.get(name) {
+                    Some(Type::Struct { fields, .. }) => {
+                        fields.get(*index as usize)?.typ.as_ref().clone()
+                    }
+                    _ => return None,
+                },
+                Type::Array { element_type, .. } => element_type.as_ref().clone(),
+                _ => return None,
+            };
+        }
+        Some(typ)
+    }
+
+    fn check_instruction(&mut self, inst: &Instruction) {
+        use Instruction::*;
+        if let DebugLocation(location) = inst {
+            self.location = location.clone();
+            return;
+        }
+        let operands = match inst {
+            // the operand of load carries the type of the value loaded rather than the pointer
+            Load { load_from, .. } => {
+                if let Some(typ) = self.defined_type(load_from) {
+                    let expected = Type::Pointer(load_from.type_().into());
+                    if typ != expected {
+                        self.error(format!(
+                            "`{}` is loaded from `{}`",
+                            load_from.type_().llvm_represent(),
+                            typ.llvm_represent()
+                        ));
+                    }
+                }
+                vec![]
+            }
+            _ => inst.operands(),
+        };
+        for operand in operands {
+            self.check_operand(operand);
+        }
+        match inst {
+            GEP {
+                load_from, indices, ..
+            } => {
+                let typ = load_from.type_();
+                if !matches!(typ, Type::Pointer(..)) {
+                    self.error(format!(
+                        "getelementptr on `{}`, which is not a pointer",
+                        typ.llvm_represent()
+                    ));
+                } else if self.indexed_type(&typ, indices).is_none() {
+                    self.error(format!(
+                        "getelementptr indices {:?} are out of `{}`",
+                        indices,
+                        typ.llvm_represent()
+                    ));
+                }
+            }
+            FunctionCall {
+                func_name,
+                ret_type,
+                args_expr,
+                ..
+            } => self.check_call(func_name, ret_type, args_expr),
+            BinaryOperation {
+                op_name, lhs, rhs, ..
+            } => {
+                let (lhs, rhs) = (lhs.type_(), rhs.type_());
+                let fits = match &lhs {
+                    Type::Float(..) => op_name.starts_with('f'),
+                    Type::Int(..) => !op_name.starts_with('f'),
+                    Type::Pointer(..) => op_name.starts_with("icmp"),
+                    _ => false,
+                };
+                if lhs != rhs || !fits {
+                    self.error(format!(
+                        "`{}` on `{}` and `{}`",
+                        op_name,
+                        lhs.llvm_represent(),
+                        rhs.llvm_represent()
+                    ));
+                }
+            }
+            Malloca { size, .. } => self.expect_int("malloc size", size),
+            BitCast {
+                from, target_type, ..
+            } => {
+                if !matches!(from.type_(), Type::Pointer(..))
+                    || !matches!(target_type, Type::Pointer(..))
+                {
+                    self.error(format!(
+                        "bitcast of `{}` to `{}`, which are not both pointers",
+                        from.type_().llvm_represent(),
+                        target_type.llvm_represent()
+                    ));
+                }
+            }
+            Cast {
+                op_name,
+                from,
+                target_type,
+                ..
+            } => {
+                let fits = match (op_name.as_str(), from.type_(), target_type) {
+                    ("zext" | "sext" | "trunc", Type::Int(..), Type::Int(..)) => true,
+                    ("sitofp", Type::Int(..), Type::Float(..)) => true,
+                    ("fptosi", Type::Float(..), Type::Int(..)) => true,
+                    ("ptrtoint", Type::Pointer(..), Type::Int(..)) => true,
+                    ("inttoptr", Type::Int(..), Type::Pointer(..)) => true,
+                    ("bitcast", Type::Int(from), Type::Float(to))
+                    | ("bitcast", Type::Float(from), Type::Int(to)) => from == *to,
+                    _ => false,
+                };
+                if !fits {
+                    self.error(format!(
+                        "`{}` of `{}` to `{}`",
+                        op_name,
+                        from.type_().llvm_represent(),
+                        target_type.llvm_represent()
+                    ));
+                }
+            }
+            ElementPointer { from, index, .. } => {
+                if !matches!(from.type_(), Type::Pointer(..)) {
+                    self.error(format!(
+                        "getelementptr on `{}`, which is not a pointer",
+                        from.type_().llvm_represent()
+                    ));
+                }
+                self.expect_int("getelementptr index", index);
+            }
+            Store {
+                source,
+                destination,
+            } => {
+                if let Some(typ) = self.values.get(destination) {
+                    let expected = Type::Pointer(source.type_().into());
+                    if typ != &expected {
+                        self.error(format!(
+                            "`{}` is stored to `{}`",
+                            source.type_().llvm_represent(),
+                            typ.llvm_represent()
+                        ));
+                    }
+                }
+            }
+            MemCopy {
+                destination,
+                source,
+                size,
+            } => {
+                let c_pointer = Type::Pointer(Type::Int(8).into());
+                for operand in [destination, source] {
+                    if operand.type_() != c_pointer {
+                        self.error(format!(
+                            "memcpy on `{}`, which is not `i8*`",
+                            operand.type_().llvm_represent()
+                        ));
+                    }
+                }
+                self.expect_int("memcpy size", size);
+            }
+            Phi { typ, incoming, .. } => {
+                for (value, _) in incoming {
+                    if &value.type_() != typ {
+                        self.error(format!(
+                            "phi of `{}` takes `{}`",
+                            typ.llvm_represent(),
+                            value.type_().llvm_represent()
+                        ));
+                    }
+                }
+            }
+            Load { .. } | Alloca { .. } | DebugLocation(..) => {}
+        }
+    }
+
+    fn check_terminator(&mut self, terminator: &Terminator) {
+        for operand in terminator.operands() {
+            self.check_operand(operand);
+        }
+        match terminator {
+            Terminator::Return(value) => {
+                let typ = value.as_ref().map_or(Type::Void, Expr::type_);
+                if typ != self.function.ret_typ {
+                    self.error(format!(
+                        "`{}` is returned from a function returning `{}`",
+                        typ.llvm_represent(),
+                        self.function.ret_typ.llvm_represent()
+                    ));
+                }
+            }
+            Terminator::Branch { cond, .. } => {
+                if cond.type_() != Type::Int(1) {
+                    self.error(format!(
+                        "branch on `{}`, which is not `i1`",
+                        cond.type_().llvm_represent()
+                    ));
+                }
+            }
+            Terminator::Switch { value, .. } => self.expect_int("switch value", value),
+            Terminator::Goto(..) | Terminator::Unreachable => {}
+        }
+    }
+
+    /// check_call checks arguments and the result match parameters and the return type of the
+    /// callee, a callee not declared is reported by `verify`
+    fn check_call(&mut self, func_name: &str, ret_type: &Type, args: &[Expr]) {
+        let callee = match self.module.functions.get(func_name) {
+            Some(callee) => callee,
+            None => return,
+        };
+        if ret_type != &callee.ret_typ {
+            self.error(format!(
+                "`{}` is called as returning `{}`, but it returns `{}`",
+                func_name,
+                ret_type.llvm_represent(),
+                callee.ret_typ.llvm_represent()
+            ));
+        }
+        for (arg, (name, typ)) in args.iter().zip(&callee.parameters) {
+            if &arg.type_() != typ {
+                self.error(format!(
+                    "`{}` is passed `{}` as `{}`, which is `{}`",
+                    func_name,
+                    arg.type_().llvm_represent(),
+                    name,
+                    typ.llvm_represent()
+                ));
+            }
+        }
+    }
+
+    /// check_operand checks a value is used as the type it's defined with
+    fn check_operand(&mut self, operand: &Expr) {
+        if let Some(typ) = self.defined_type(operand) {
+            if typ != operand.type_() {
+                self.error(format!(
+                    "`{}` of `{}` is used as `{}`",
+                    operand.llvm_represent(),
+                    typ.llvm_represent(),
+                    operand.type_().llvm_represent()
+                ));
+            }
+        }
+    }
+
+    /// defined_type returns the type a local value, a parameter or a global is defined with
+    fn defined_type(&self, operand: &Expr) -> Option<Type> {
+        match operand {
+            Expr::LocalIdentifier(_, id) => self.values.get(id).cloned(),
+            Expr::GlobalIdentifier(_, id) => self.globals.get(id).cloned(),
+            Expr::Identifier(_, name) => self
+                .function
+                .parameters
+                .iter()
+                .find(|(parameter, _)| parameter == name)
+                .map(|(_, typ)| typ.clone()),
+            _ => None,
+        }
+    }
+
+    fn expect_int(&mut self, what: &str, value: &Expr) {
+        if !matches!(value.type_(), Type::Int(..)) {
+            self.error(format!(
+                "{} is `{}`, which is not an integer",
+                what,
+                value.type_().llvm_represent()
+            ));
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(InternalError::new(
+            Phase::Verify,
+            &self.location,
+            format!("mistyped IR in `{}`: {}", self.function.name, message),
+        ));
+    }
+}
+
+/// is_comparison is true for `icmp` and `fcmp` operations, which give `i1`
+fn is_comparison(op_name: &str) -> bool {
+    op_name.starts_with("icmp") || op_name.starts_with("fcmp")
+}
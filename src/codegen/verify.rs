@@ -0,0 +1,291 @@
+//! verify checks the IR is well formed before it's emitted, so a bug of code generation or of an
+//! optimization pass is reported by the compiler, rather than found by LLVM or at run time. Blocks
+//! always end with a terminator in the IR, so their jumps are checked, then every local value must
+//! be defined once before its uses, and every call must pass as many arguments as the function it
+//! calls takes.
+use super::ir::*;
+use crate::ice::{InternalError, Phase};
+use std::collections::{HashMap, HashSet};
+
+impl Module {
+    /// verify checks every function body, returns all problems found, in the order of function
+    /// names, at the functions they're in
+    pub fn verify(&self) -> Result<(), Vec<InternalError>> {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        let mut errors = vec![];
+        for name in names {
+            let function = &self.functions[name];
+            if let Some(body) = &function.body {
+                let mut verifier = Verifier {
+                    module: self,
+                    body,
+                    messages: vec![],
+                };
+                verifier.verify();
+                errors.extend(verifier.messages.into_iter().map(|message| {
+                    InternalError::new(
+                        Phase::Verify,
+                        &function.location,
+                        format!("invalid IR in `{}`: {}", function.name, message),
+                    )
+                }));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+struct Verifier<'a> {
+    module: &'a Module,
+    body: &'a Body,
+    messages: Vec<String>,
+}
+
+impl Verifier<'_> {
+    fn verify(&mut self) {
+        if self.body.blocks.is_empty() {
+            self.messages.push("the body has no block".to_string());
+            return;
+        }
+        let blocks: HashMap<ID, usize> = self
+            .body
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (block.label.id, i))
+            .collect();
+        let predecessors = self.verify_jumps(&blocks);
+        let definitions = self.definitions();
+        let dominators = dominators(&self.body.blocks, &blocks, &predecessors);
+        let dominates = |definition: usize, block: usize| match dominators.get(&block) {
+            Some(dominators) => dominators.contains(&definition),
+            // uses in unreachable blocks are never run
+            None => true,
+        };
+        for (i, block) in self.body.blocks.iter().enumerate() {
+            let mut phis_end = false;
+            for (j, inst) in block.instructions.iter().enumerate() {
+                match inst {
+                    Instruction::Phi { id, incoming, .. } => {
+                        if phis_end {
+                            self.messages.push(format!(
+                                "phi %{} is not at the start of block %{}",
+                                id, block.label.id
+                            ));
+                        }
+                        for (value, from) in incoming {
+                            if !predecessors[i].contains(&from.id) {
+                                self.messages.push(format!(
+                                    "phi %{} takes a value from %{}, which doesn't jump to block %{}",
+                                    id, from.id, block.label.id
+                                ));
+                            }
+                            // the value is used at the end of the block it comes from
+                            let from = match blocks.get(&from.id) {
+                                Some(from) => *from,
+                                None => continue,
+                            };
+                            if let Some(used) = value.local_id_of() {
+                                let end = self.body.blocks[from].instructions.len();
+                                self.verify_use(used, (from, end), &definitions, &dominates);
+                            }
+                        }
+                    }
+                    Instruction::DebugLocation(..) => {}
+                    _ => {
+                        phis_end = true;
+                        if let Instruction::FunctionCall {
+                            func_name,
+                            args_expr,
+                            ..
+                        } = inst
+                        {
+                            self.verify_call(func_name, args_expr);
+                        }
+                        for used in inst.used_ids() {
+                            self.verify_use(used, (i, j), &definitions, &dominates);
+                        }
+                    }
+                }
+            }
+            let end = block.instructions.len();
+            for used in block
+                .terminator
+                .operands()
+                .into_iter()
+                .filter_map(Expr::local_id_of)
+            {
+                self.verify_use(used, (i, end), &definitions, &dominates);
+            }
+        }
+    }
+
+    /// verify_jumps checks terminators jump to blocks of the body other than the entry block,
+    /// returns labels of predecessors of each block
+    fn verify_jumps(&mut self, blocks: &HashMap<ID, usize>) -> Vec<HashSet<ID>> {
+        let mut predecessors = vec![HashSet::new(); self.body.blocks.len()];
+        for block in &self.body.blocks {
+            for target in block.terminator.targets() {
+                match blocks.get(&target.id) {
+                    // LLVM runs the entry block once, before anything else
+                    Some(0) => self.messages.push(format!(
+                        "block %{} jumps to the entry block",
+                        block.label.id
+                    )),
+                    Some(i) => {
+                        predecessors[*i].insert(block.label.id);
+                    }
+                    None => self.messages.push(format!(
+                        "block %{} jumps to %{}, which is not a block of the function",
+                        block.label.id, target.id
+                    )),
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// definitions returns where every local value is defined, as indices of its block and
+    /// instruction, an ID defined twice is reported, blocks and values are numbered together
+    fn definitions(&mut self) -> HashMap<ID, (usize, usize)> {
+        let mut labels = HashSet::new();
+        let mut definitions = HashMap::new();
+        for (i, block) in self.body.blocks.iter().enumerate() {
+            if !labels.insert(block.label.id) {
+                self.messages
+                    .push(format!("%{} is defined twice", block.label.id));
+            }
+            for (j, inst) in block.instructions.iter().enumerate() {
+                if let Some(id) = inst.result_id() {
+                    if definitions.insert(id, (i, j)).is_some() {
+                        self.messages.push(format!("%{} is defined twice", id));
+                    }
+                }
+            }
+        }
+        for id in labels {
+            if definitions.contains_key(&id) {
+                self.messages.push(format!("%{} is defined twice", id));
+            }
+        }
+        definitions
+    }
+
+    /// verify_use checks the value `used` at `(block, instruction)` is defined before it, in the
+    /// same block or in a block dominating it
+    fn verify_use(
+        &mut self,
+        used: ID,
+        (block, instruction): (usize, usize),
+        definitions: &HashMap<ID, (usize, usize)>,
+        dominates: &dyn Fn(usize, usize) -> bool,
+    ) {
+        let label = self.body.blocks[block].label.id;
+        match definitions.get(&used) {
+            None => self.messages.push(format!(
+                "%{} is used in block %{} but never defined",
+                used, label
+            )),
+            Some((defined, at)) if *defined == block => {
+                if *at >= instruction {
+                    self.messages.push(format!(
+                        "%{} is used in block %{} before it's defined",
+                        used, label
+                    ));
+                }
+            }
+            Some((defined, _)) => {
+                if !dominates(*defined, block) {
+                    self.messages.push(format!(
+                        "%{} is defined in block %{}, which doesn't dominate its use in block %{}",
+                        used, self.body.blocks[*defined].label.id, label
+                    ));
+                }
+            }
+        }
+    }
+
+    /// verify_call checks the callee is declared and takes as many arguments as passed, their
+    /// types are checked by `type_check`
+    fn verify_call(&mut self, func_name: &str, args: &[Expr]) {
+        let callee = match self.module.functions.get(func_name) {
+            Some(callee) => callee,
+            None => {
+                self.messages
+                    .push(format!("`{}` is called but not declared", func_name));
+                return;
+            }
+        };
+        if args.len() != callee.parameters.len() {
+            self.messages.push(format!(
+                "`{}` is called with {} argument(s), but it takes {}",
+                func_name,
+                args.len(),
+                callee.parameters.len()
+            ));
+        }
+    }
+}
+
+/// dominators returns the blocks dominating each block reachable from the entry block, including
+/// itself, a block dominates another when every path from the entry block to it passes the block
+fn dominators(
+    body_blocks: &[BasicBlock],
+    blocks: &HashMap<ID, usize>,
+    predecessors: &[HashSet<ID>],
+) -> HashMap<usize, HashSet<usize>> {
+    let mut reachable = HashSet::from([0]);
+    let mut worklist = vec![0];
+    while let Some(i) = worklist.pop() {
+        for target in body_blocks[i].terminator.targets() {
+            if let Some(&j) = blocks.get(&target.id) {
+                if reachable.insert(j) {
+                    worklist.push(j);
+                }
+            }
+        }
+    }
+    let mut dominators: HashMap<usize, HashSet<usize>> = reachable
+        .iter()
+        .map(|&i| {
+            let all = if i == 0 {
+                HashSet::from([0])
+            } else {
+                reachable.clone()
+            };
+            (i, all)
+        })
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 1..body_blocks.len() {
+            if !reachable.contains(&i) {
+                continue;
+            }
+            let mut common: Option<HashSet<usize>> = None;
+            for predecessor in &predecessors[i] {
+                let predecessor = match dominators.get(&blocks[predecessor]) {
+                    Some(predecessor) => predecessor,
+                    None => continue,
+                };
+                common = Some(match common {
+                    None => predecessor.clone(),
+                    Some(common) => common.intersection(predecessor).copied().collect(),
+                });
+            }
+            let mut new = common.unwrap_or_default();
+            new.insert(i);
+            if new != dominators[&i] {
+                dominators.insert(i, new);
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
@@ -0,0 +1,194 @@
+//! compilation compiles a source file in one call for other Rust projects embedding elz, e.g.
+//!
+//! ```
+//! let output = elz::compile("main.elz", "module main\nmain(): void {}", elz::Options::default())
+//!     .unwrap();
+//! assert!(output.llvm_ir.contains("define void @main()"));
+//! ```
+use crate::ast::{Module, TopAst};
+use crate::cmd::compile::{prelude_import, unknown_imports};
+use crate::codegen::llvm::LLVMValue;
+use crate::codegen::target::Target;
+use crate::codegen::CodeGenerator;
+use crate::diagnostic::Diagnose;
+use crate::lexer::{self, TkType};
+use crate::parser::{parse_prelude, Parser};
+use crate::semantic::{derive_traits, CallerLocations, Lints, SemanticChecker, Severity};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Options configures `compile`
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// target of the generated IR, the host by default
+    pub target: Target,
+    pub lints: Lints,
+    /// eliminate_dead_functions drops functions which can't be reached from `main`
+    pub eliminate_dead_functions: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            target: Target::host(),
+            lints: Lints::new(),
+            eliminate_dead_functions: false,
+        }
+    }
+}
+
+/// CompilationOutput is the result of a compiled source file
+#[derive(Debug)]
+pub struct CompilationOutput {
+    /// module parsed from the source, with locations passed to `@track_caller` functions
+    pub module: Module,
+    /// types of functions and variables defined in the module by name, e.g. `(int): int`
+    pub types: BTreeMap<String, String>,
+    /// warnings of the source, lints allowed are dropped
+    pub warnings: Vec<Diagnostic>,
+    /// llvm_ir is the textual LLVM IR of the module and prelude
+    pub llvm_ir: String,
+}
+
+/// Diagnostic is an error or a warning of the source, it flattens `diagnostic::Diagnostic` into
+/// plain data, so it can be passed around, e.g. to JavaScript as JSON
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// severity is `error` or `warning`
+    pub severity: String,
+    /// code identifies the kind of diagnostic, e.g. `type_mismatched`
+    pub code: String,
+    pub message: String,
+    /// label is the short message put on the code
+    pub label: String,
+    pub file: String,
+    /// line and column start from 1
+    pub line: u32,
+    pub column: u32,
+    /// start and end are byte offsets of the code the diagnostic is on
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, diagnostic: &dyn Diagnose) -> Diagnostic {
+        let diagnostic = diagnostic.diagnostic();
+        let location = diagnostic.primary_span;
+        Diagnostic {
+            severity: severity.name().to_string(),
+            code: diagnostic.code.to_string(),
+            message: diagnostic.message,
+            label: diagnostic.label,
+            file: location.file_name().to_string(),
+            line: location.line(),
+            column: location.column() + 1,
+            start: location.start,
+            end: location.end,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}:{}:{} {}",
+            self.severity, self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+/// Diagnostics are errors which stop the compilation, with warnings found before them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl std::fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self.0.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// compile parses, checks and generates code for `source`, a module with the prelude imported,
+/// `file_name` is used in locations of diagnostics
+pub fn compile(
+    file_name: &str,
+    source: &str,
+    options: Options,
+) -> Result<CompilationOutput, Diagnostics> {
+    let tokens = lexer::lex(file_name, source);
+    let mut module = Parser::from_tokens(file_name, tokens)
+        .parse_module(TkType::EOF)
+        .map_err(|err| Diagnostics(vec![Diagnostic::new(Severity::Error, &err)]))?;
+    module.top_list.push(prelude_import());
+    let mut program = vec![parse_prelude(), module];
+    let caller_locations = CallerLocations::new(&program);
+    for module in &mut program {
+        derive_traits(&mut module.top_list);
+        caller_locations.insert(&mut module.top_list);
+    }
+    let errors = unknown_imports(&program);
+    if !errors.is_empty() {
+        return Err(Diagnostics(
+            errors
+                .iter()
+                .map(|err| Diagnostic::new(Severity::Error, err))
+                .collect(),
+        ));
+    }
+
+    let mut checker = SemanticChecker::new();
+    let mut errors = match checker.check_program(&program) {
+        Ok(..) => vec![],
+        Err(errors) => errors
+            .iter()
+            .map(|err| Diagnostic::new(Severity::Error, err))
+            .collect(),
+    };
+    let mut warnings = vec![];
+    for warning in checker.warnings() {
+        // prelude is not controlled by users
+        if warning.location().file_name() != file_name {
+            continue;
+        }
+        match options.lints.severity_of(warning) {
+            Severity::Allow => (),
+            Severity::Warning => warnings.push(Diagnostic::new(Severity::Warning, warning)),
+            Severity::Error => errors.push(Diagnostic::new(Severity::Error, warning)),
+        }
+    }
+    if !errors.is_empty() {
+        errors.append(&mut warnings);
+        return Err(Diagnostics(errors));
+    }
+
+    let mut ir_module = CodeGenerator::with_target(options.target)
+        .with_expr_types(checker.expr_types())
+        .generate_program(&program);
+    if options.eliminate_dead_functions {
+        ir_module.eliminate_dead_functions();
+    }
+    let module = program.pop().expect("module is checked");
+    let mut types = BTreeMap::new();
+    for top in &module.top_list {
+        let name = match top {
+            TopAst::Function(f) => &f.name,
+            TopAst::Variable(v) => &v.name,
+            _ => continue,
+        };
+        if let Some(typ) = checker.type_of_name(&module.name, name) {
+            types.insert(name.clone(), typ);
+        }
+    }
+    Ok(CompilationOutput {
+        module,
+        types,
+        warnings,
+        llvm_ir: ir_module.llvm_represent(),
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,44 @@
+use super::*;
+
+#[test]
+fn output_has_module_types_and_ir() {
+    let code = "module main
+limit: int = 10;
+double(x: int): int = x * 2;
+main(): void {
+  y: int = 1;
+}";
+    let output = compile("main.elz", code, Options::default()).unwrap();
+    assert_eq!(output.module.name, "main");
+    assert_eq!(
+        output.types,
+        vec![
+            ("double".to_string(), "(int): int".to_string()),
+            ("limit".to_string(), "int".to_string()),
+            ("main".to_string(), "(): void".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    );
+    let warnings: Vec<String> = output.warnings.iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "warning: main.elz:5:3 unused variable: `y`",
+            "warning: main.elz:3:1 function `double` is never called from `main`",
+        ]
+    );
+    assert!(output.llvm_ir.contains("define i64 @double(i64 %x)"));
+}
+
+#[test]
+fn denied_lint_stops_compilation() {
+    let code = "module main
+main(): void {
+  y: int = 1;
+}";
+    let mut options = Options::default();
+    options.lints.deny_warnings();
+    let err = compile("main.elz", code, options).unwrap_err();
+    assert_eq!(err.to_string(), "error: main.elz:3:3 unused variable: `y`");
+}
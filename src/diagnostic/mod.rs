@@ -1,23 +1,142 @@
 use crate::lexer::Location;
 use codespan::Files;
-use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::diagnostic::{Diagnostic as CodespanDiagnostic, Label};
 use codespan_reporting::term::emit;
-use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream, WriteColor};
+use serde::Serialize;
+use std::io::IsTerminal;
+
+/// Diagnose is implemented by errors and warnings reported to users, e.g. `ParseError` and
+/// `SemanticError`
+pub trait Diagnose {
+    /// diagnostic describes the error or warning in the form shared by all layers
+    fn diagnostic(&self) -> Diagnostic;
+}
+
+/// Severity decides how a diagnostic affects the compilation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// not reported
+    Allow,
+    /// reported, but compilation continues
+    Warning,
+    /// reported, and compilation fails
+    Error,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Allow => "allow",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Diagnostic is an error or a warning produced by the parser or the semantic checker, and
+/// rendered by the driver
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// code identifies the kind of diagnostic, e.g. `type_mismatched`
+    pub code: &'static str,
+    pub severity: Severity,
+    /// message explains the diagnostic without location
+    pub message: String,
+    /// primary_span is the code the diagnostic is on
+    pub primary_span: Location,
+    /// label is the short message put on the primary span
+    pub label: String,
+    /// labels are put on other code related to the diagnostic, e.g. the previous definition of a
+    /// redefined name
+    pub labels: Vec<(Location, String)>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// new creates a diagnostic labelled by its message
+    pub fn new<T: ToString>(
+        severity: Severity,
+        code: &'static str,
+        primary_span: &Location,
+        message: T,
+    ) -> Diagnostic {
+        let message = message.to_string();
+        Diagnostic {
+            code,
+            severity,
+            label: message.clone(),
+            message,
+            primary_span: primary_span.clone(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+    pub fn error<T: ToString>(code: &'static str, primary_span: &Location, message: T) -> Self {
+        Diagnostic::new(Severity::Error, code, primary_span, message)
+    }
+    pub fn warning<T: ToString>(code: &'static str, primary_span: &Location, message: T) -> Self {
+        Diagnostic::new(Severity::Warning, code, primary_span, message)
+    }
+    pub fn with_label<T: ToString>(mut self, location: &Location, message: T) -> Diagnostic {
+        self.labels.push((location.clone(), message.to_string()));
+        self
+    }
+    pub fn with_note<T: ToString>(mut self, note: T) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.primary_span, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// MessageFormat is how diagnostics are written to stderr
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageFormat {
+    /// code snippets with labels for humans
+    Human,
+    /// a JSON object per line for tools, see `Record`
+    JSON,
+}
+
+impl MessageFormat {
+    /// from_name accepts the value of `--message-format`, i.e. `human` or `json`
+    pub fn from_name(name: &str) -> Option<MessageFormat> {
+        match name {
+            "human" => Some(MessageFormat::Human),
+            "json" => Some(MessageFormat::JSON),
+            _ => None,
+        }
+    }
+}
 
 pub struct Reporter {
     files: Files<String>,
+    format: MessageFormat,
 }
 
 impl Reporter {
     pub fn new() -> Reporter {
+        Reporter::with_format(MessageFormat::Human)
+    }
+    pub fn with_format(format: MessageFormat) -> Reporter {
         Reporter {
             files: Files::new(),
+            format,
         }
     }
 
     pub(crate) fn for_file<T: Into<String>>(&mut self, file_name: T, source: T) -> FileID {
+        let file_name = file_name.into();
         FileID {
-            value: self.files.add(file_name.into(), source.into()),
+            value: self.files.add(file_name.clone(), source.into()),
+            file_name,
             diagnostics: vec![],
         }
     }
@@ -26,26 +145,178 @@ impl Reporter {
 #[derive(Clone)]
 pub(crate) struct FileID {
     value: codespan::FileId,
+    file_name: String,
     diagnostics: Vec<Diagnostic>,
 }
 
+/// Record is a diagnostic of a file, it's written as a line of JSON with `--message-format=json`,
+/// e.g.
+///
+/// ```json
+/// {"code":"unused_variable","severity":"warning","message":"unused variable: `x`","file":"a.elz",
+/// "span":{"start":30,"end":31,"line":3,"column":3},"label":"unused variable","labels":[],
+/// "notes":[]}
+/// ```
+///
+/// `start` and `end` are byte offsets, `line` and `column` start from 1, `labels` are secondary
+/// labels with `span` and `message` in the same file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Record {
+    code: &'static str,
+    severity: &'static str,
+    message: String,
+    file: String,
+    span: Span,
+    label: String,
+    labels: Vec<SecondaryLabel>,
+    notes: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct SecondaryLabel {
+    span: Span,
+    message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Span {
+    start: u32,
+    end: u32,
+    line: u32,
+    column: u32,
+}
+
+impl Span {
+    fn from(location: &Location) -> Span {
+        Span {
+            start: location.start,
+            end: location.end,
+            line: location.line(),
+            column: location.column() + 1,
+        }
+    }
+}
+
+impl Record {
+    fn new(file_name: &str, diagnostic: &Diagnostic) -> Record {
+        let mut labels = vec![];
+        let mut notes = vec![];
+        for (location, message) in &diagnostic.labels {
+            // code of other files can't be shown in the snippet
+            if location.file_name() == file_name {
+                labels.push(SecondaryLabel {
+                    span: Span::from(location),
+                    message: message.clone(),
+                });
+            } else {
+                notes.push(format!("{} {}", location, message));
+            }
+        }
+        notes.extend(diagnostic.notes.iter().cloned());
+        Record {
+            code: diagnostic.code,
+            severity: diagnostic.severity.name(),
+            message: diagnostic.message.clone(),
+            file: file_name.to_string(),
+            span: Span::from(&diagnostic.primary_span),
+            label: diagnostic.label.clone(),
+            labels,
+            notes,
+        }
+    }
+}
+
 impl FileID {
-    pub(crate) fn add_diagnostic(
-        &mut self,
-        location: Location,
-        long_message: String,
-        message: String,
-    ) {
-        self.diagnostics.push(Diagnostic::new_error(
-            long_message,
-            Label::new(self.value, location.start..location.end, message),
-        ));
+    pub(crate) fn add_error(&mut self, diagnostic: &dyn Diagnose) {
+        self.add(Diagnostic {
+            severity: Severity::Error,
+            ..diagnostic.diagnostic()
+        })
+    }
+    pub(crate) fn add_warning(&mut self, diagnostic: &dyn Diagnose) {
+        self.add(Diagnostic {
+            severity: Severity::Warning,
+            ..diagnostic.diagnostic()
+        })
+    }
+    pub(crate) fn add(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
     }
+    fn records(&self) -> Vec<Record> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| Record::new(&self.file_name, diagnostic))
+            .collect()
+    }
+
     pub(crate) fn report(&self, reporter: &Reporter) {
-        let writer = StandardStream::stderr(ColorChoice::Auto);
+        match reporter.format {
+            MessageFormat::Human => {
+                // colors only make sense on terminal, not in files or pipes
+                let color = if std::io::stderr().is_terminal() {
+                    ColorChoice::Auto
+                } else {
+                    ColorChoice::Never
+                };
+                let writer = StandardStream::stderr(color);
+                self.render(reporter, &mut writer.lock());
+            }
+            MessageFormat::JSON => {
+                for line in self.json_lines() {
+                    eprintln!("{}", line);
+                }
+            }
+        }
+    }
+
+    /// render writes diagnostics with code snippets, labels are underlined by `^` for the primary
+    /// one and `-` for others
+    fn render(&self, reporter: &Reporter, writer: &mut dyn WriteColor) {
         let config = codespan_reporting::term::Config::default();
-        for diagnostic in &self.diagnostics {
-            emit(&mut writer.lock(), &config, &reporter.files, &diagnostic).unwrap();
+        for diagnostic in self.codespan_diagnostics() {
+            emit(writer, &config, &reporter.files, &diagnostic).unwrap();
         }
     }
+
+    fn codespan_diagnostics(&self) -> Vec<CodespanDiagnostic> {
+        self.records()
+            .iter()
+            .map(|record| {
+                // location is part of the message, so the message can be read without snippet
+                let message = format!(
+                    "{}:{}:{} {}",
+                    record.file,
+                    record.span.line,
+                    record.span.column - 1,
+                    record.message
+                );
+                let label = Label::new(
+                    self.value,
+                    record.span.start..record.span.end,
+                    &record.label,
+                );
+                let diagnostic = if record.severity == "error" {
+                    CodespanDiagnostic::new_error(message, label)
+                } else {
+                    CodespanDiagnostic::new_warning(message, label)
+                };
+                let labels = record.labels.iter().map(|label| {
+                    Label::new(self.value, label.span.start..label.span.end, &label.message)
+                });
+                diagnostic
+                    .with_secondary_labels(labels)
+                    .with_notes(record.notes.clone())
+            })
+            .collect()
+    }
+
+    fn json_lines(&self) -> Vec<String> {
+        self.records()
+            .iter()
+            .map(|record| serde_json::to_string(record).expect("record can be serialized"))
+            .collect()
+    }
 }
+
+#[cfg(test)]
+mod tests;
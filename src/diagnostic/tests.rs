@@ -0,0 +1,71 @@
+use super::*;
+use crate::parser::Parser;
+use crate::semantic::SemanticWarning;
+use codespan_reporting::term::termcolor::Buffer;
+
+#[test]
+fn warning_as_json() {
+    let mut file = Reporter::with_format(MessageFormat::JSON).for_file("a.elz", "x");
+    let location = Location::new("a.elz", 3, 2, 30, 31);
+    file.add_warning(&SemanticWarning::unused_variable(&location, "x"));
+    assert_eq!(
+        file.json_lines(),
+        vec![
+            r#"{"code":"unused_variable","severity":"warning","message":"unused variable: `x`","file":"a.elz","span":{"start":30,"end":31,"line":3,"column":3},"label":"unused variable: `x`","labels":[],"notes":[]}"#
+        ]
+    );
+}
+
+#[test]
+fn parse_error_with_note() {
+    let code = "module main\nfn foo() {}";
+    let err = Parser::parse_program("a.elz", code).unwrap_err();
+    let mut file = Reporter::new().for_file("a.elz", code);
+    file.add(err.diagnostic().with_note("a note"));
+    let record = &file.records()[0];
+    assert_eq!(record.code, "not_expected_token");
+    assert_eq!(record.severity, "error");
+    assert_eq!(record.span.line, 2);
+    assert_eq!(record.notes, vec!["a note".to_string()]);
+}
+
+#[test]
+fn render_secondary_label() {
+    let code = "x: int = 1;\nx: int = 2;\n";
+    let mut reporter = Reporter::new();
+    let mut file = reporter.for_file("a.elz", code);
+    let warning = SemanticWarning::shadowed_variable(
+        &Location::new("a.elz", 2, 0, 12, 13),
+        "x",
+        Location::new("a.elz", 1, 0, 0, 1),
+    );
+    file.add_warning(&warning);
+    let mut buffer = Buffer::no_color();
+    file.render(&reporter, &mut buffer);
+    let output = String::from_utf8(buffer.into_inner()).unwrap();
+    assert!(output.contains("^ variable `x` shadows"));
+    assert!(output.contains("- shadowed variable"));
+}
+
+#[test]
+fn parse_and_semantic_errors_are_diagnostics() {
+    let err = Parser::parse_program("a.elz", "module main\nfn foo() {}").unwrap_err();
+    let diagnostic = err.diagnostic();
+    assert_eq!(diagnostic.code, "not_expected_token");
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.label, "not expected token");
+    assert_eq!(diagnostic.to_string(), err.to_string());
+
+    let location = Location::new("a.elz", 2, 0, 12, 13);
+    let warning =
+        SemanticWarning::shadowed_variable(&location, "x", Location::new("a.elz", 1, 0, 0, 1));
+    assert_eq!(
+        warning.diagnostic(),
+        Diagnostic::warning(
+            "shadowed_variable",
+            &location,
+            "variable `x` shadows the variable defined at a.elz:1:0"
+        )
+        .with_label(&Location::new("a.elz", 1, 0, 0, 1), "shadowed variable")
+    );
+}
@@ -0,0 +1,129 @@
+//! ice reports internal compiler errors, bugs of elz rather than of the program compiled. Phases
+//! record the code they're compiling with `compiling`, so an ICE found by a check, or a panic of
+//! the compiler, is reported at that code with the phase, rather than as a raw backtrace.
+use crate::lexer::Location;
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::{self, PanicHookInfo};
+use thiserror::Error;
+
+/// ISSUES is where bugs of elz are reported
+pub const ISSUES: &str = "https://github.com/elz-lang/elz/issues";
+
+/// Phase is the part of the compiler an internal error happens in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Check,
+    Codegen,
+    Optimize,
+    Verify,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let doing = match self {
+            Phase::Check => "checking",
+            Phase::Codegen => "generating code",
+            Phase::Optimize => "optimizing",
+            Phase::Verify => "verifying IR",
+        };
+        write!(f, "{}", doing)
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{location} internal compiler error while {phase}: {message}")]
+pub struct InternalError {
+    phase: Phase,
+    location: Location,
+    message: String,
+}
+
+impl InternalError {
+    pub fn new<T: ToString>(phase: Phase, location: &Location, message: T) -> InternalError {
+        InternalError {
+            phase,
+            location: location.clone(),
+            message: message.to_string(),
+        }
+    }
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// report prints internal errors, then asks to report the bug once
+pub fn report(errors: &[InternalError]) {
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    if !errors.is_empty() {
+        eprintln!(
+            "note: this is a bug of elz, please report it with the code at {}",
+            ISSUES
+        );
+    }
+}
+
+thread_local! {
+    /// COMPILING is the phase and the code being compiled on the thread, innermost last
+    static COMPILING: RefCell<Vec<(Phase, Location)>> = const { RefCell::new(vec![]) };
+}
+
+/// Compiling marks the code being compiled until it's dropped
+pub struct Compiling(());
+
+/// compiling records `location` as the code `phase` compiles, until the result is dropped
+pub fn compiling(phase: Phase, location: &Location) -> Compiling {
+    COMPILING.with(|compiling| compiling.borrow_mut().push((phase, location.clone())));
+    Compiling(())
+}
+
+impl Drop for Compiling {
+    fn drop(&mut self) {
+        COMPILING.with(|compiling| compiling.borrow_mut().pop());
+    }
+}
+
+/// install_panic_hook reports a panic while compiling code as an internal error at the code, the
+/// backtrace is still shown with `RUST_BACKTRACE`, other panics are reported as usual
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let current = COMPILING.with(|compiling| compiling.borrow().last().cloned());
+        match current {
+            Some((phase, location)) => {
+                report(&[InternalError::new(phase, &location, panic_message(info))]);
+                if std::env::var_os("RUST_BACKTRACE").is_some() {
+                    default_hook(info);
+                }
+            }
+            None => default_hook(info),
+        }
+    }));
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let message = match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "panicked".to_string(),
+    };
+    match info.location() {
+        Some(location) => format!("{}, at {}", message, location),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests;
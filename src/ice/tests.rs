@@ -0,0 +1,26 @@
+use super::*;
+
+#[test]
+fn compiling_code_is_recorded_until_dropped() {
+    let function = Location::from(1, 0);
+    let statement = Location::from(2, 4);
+    let current = || COMPILING.with(|compiling| compiling.borrow().last().cloned());
+    {
+        let _function = compiling(Phase::Codegen, &function);
+        {
+            let _statement = compiling(Phase::Codegen, &statement);
+            assert_eq!(current(), Some((Phase::Codegen, statement.clone())));
+        }
+        assert_eq!(current(), Some((Phase::Codegen, function.clone())));
+    }
+    assert_eq!(current(), None);
+}
+
+#[test]
+fn internal_error_tells_phase_and_location() {
+    let err = InternalError::new(Phase::Optimize, &Location::from(3, 2), "phi has no value");
+    assert_eq!(
+        err.to_string(),
+        ":3:2 internal compiler error while optimizing: phi has no value"
+    );
+}
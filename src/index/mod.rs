@@ -0,0 +1,504 @@
+//! index classifies names of a checked module for editors, it's the building block of
+//! highlighting and outline views
+use crate::ast::*;
+use crate::cmd::compile::prelude_import;
+use crate::lexer::{self, Location, TkType, Token};
+use crate::mangle::SEPARATOR;
+use crate::parser::{parse_prelude, ParseError, Parser};
+use crate::semantic::SemanticChecker;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// SymbolKind is what a name refers to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Module,
+    Class,
+    Trait,
+    /// type parameters and types from other modules, e.g. `int`
+    Type,
+    Function,
+    Method,
+    StaticMethod,
+    Field,
+    Variable,
+    Parameter,
+}
+
+/// Symbol is a definition of the module, symbols are ordered as they are defined, members are put
+/// after their class or trait with `container`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// location of the name in the definition
+    pub location: Location,
+    /// container is the class or trait of a member
+    pub container: Option<String>,
+}
+
+/// SemanticToken is a classified identifier, `definition` is true where the name is defined
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SemanticToken {
+    pub location: Location,
+    pub kind: SymbolKind,
+    pub definition: bool,
+}
+
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    tokens: Vec<SemanticToken>,
+}
+
+impl SymbolIndex {
+    /// new indexes the module checked by `checker`, `tokens` are lexed from the source of the
+    /// module, identifiers can't be classified, e.g. names in tags, are left out
+    pub fn new(checker: &SemanticChecker, module: &Module, tokens: &[Token]) -> SymbolIndex {
+        let mut indexer = Indexer::new(checker, module, tokens);
+        indexer.index_module(module);
+        indexer.classify_rest();
+        let tokens = indexer
+            .classified
+            .into_iter()
+            .map(|(index, (kind, definition))| SemanticToken {
+                location: tokens[index].location(),
+                kind,
+                definition,
+            })
+            .collect();
+        SymbolIndex {
+            symbols: indexer.symbols,
+            tokens,
+        }
+    }
+
+    pub fn symbols(&self) -> &Vec<Symbol> {
+        &self.symbols
+    }
+    /// tokens returns classified identifiers ordered by their location
+    pub fn tokens(&self) -> &Vec<SemanticToken> {
+        &self.tokens
+    }
+    /// token_at returns the classified identifier at the position, line is 1-based and column is
+    /// 0-based as `Location`
+    pub fn token_at(&self, line: u32, column: u32) -> Option<&SemanticToken> {
+        self.tokens.iter().find(|token| {
+            let location = &token.location;
+            let length = location.end - location.start;
+            location.line() == line
+                && location.column() <= column
+                && column < location.column() + length
+        })
+    }
+}
+
+/// index_source parses and checks code with prelude, then indexes it, semantic errors don't stop
+/// indexing since editors work on incomplete code, the parse error is boxed since it's large
+pub fn index_source(file_name: &str, code: &str) -> Result<SymbolIndex, Box<ParseError>> {
+    let tokens = lexer::lex(file_name, code);
+    let mut module = Parser::from_tokens(file_name, tokens.clone())
+        .parse_module(TkType::EOF)
+        .map_err(Box::new)?;
+    module.top_list.push(prelude_import());
+    let mut checker = SemanticChecker::new();
+    let _ = checker.check_program(&vec![parse_prelude(), module.clone()]);
+    Ok(SymbolIndex::new(&checker, &module, &tokens))
+}
+
+struct Indexer<'a> {
+    checker: &'a SemanticChecker,
+    module_name: &'a str,
+    tokens: &'a [Token],
+    /// index of token by its line and column
+    positions: HashMap<(u32, u32), usize>,
+    classified: BTreeMap<usize, (SymbolKind, bool)>,
+    symbols: Vec<Symbol>,
+    /// functions and variables of the module
+    globals: HashMap<&'a str, SymbolKind>,
+    /// parameters and local variables, the innermost scope is the last one
+    scopes: Vec<HashMap<String, SymbolKind>>,
+    type_parameters: HashSet<&'a str>,
+}
+
+impl<'a> Indexer<'a> {
+    fn new(checker: &'a SemanticChecker, module: &'a Module, tokens: &'a [Token]) -> Indexer<'a> {
+        let positions = tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| {
+                let location = token.location();
+                ((location.line(), location.column()), index)
+            })
+            .collect();
+        let mut globals = HashMap::new();
+        let mut type_parameters = HashSet::new();
+        for top in &module.top_list {
+            match top {
+                TopAst::Function(f) => {
+                    globals.insert(f.name.as_str(), SymbolKind::Function);
+                    type_parameters.extend(f.type_parameters.iter().map(|t| t.name.as_str()));
+                }
+                TopAst::Variable(v) => {
+                    globals.insert(v.name.as_str(), SymbolKind::Variable);
+                }
+                TopAst::Class(c) => {
+                    type_parameters.extend(c.type_parameters.iter().map(|t| t.name.as_str()));
+                }
+                TopAst::Trait(t) => {
+                    type_parameters.extend(t.type_parameters.iter().map(|t| t.name.as_str()));
+                }
+                TopAst::Import(..) => (),
+            }
+        }
+        Indexer {
+            checker,
+            module_name: module.name.as_str(),
+            tokens,
+            positions,
+            classified: BTreeMap::new(),
+            symbols: vec![],
+            globals,
+            scopes: vec![],
+            type_parameters,
+        }
+    }
+
+    fn index_module(&mut self, module: &Module) {
+        for top in &module.top_list {
+            match top {
+                TopAst::Import(..) => (),
+                TopAst::Function(f) => self.index_function(f, SymbolKind::Function, None),
+                TopAst::Variable(v) => {
+                    self.define(
+                        &v.name,
+                        SymbolKind::Variable,
+                        self.index_of(&v.location),
+                        None,
+                    );
+                    self.index_expr(&v.expr);
+                }
+                TopAst::Class(c) => {
+                    // location of class is the keyword, its name follows
+                    let index = self.index_of(&c.location).map(|index| index + 1);
+                    self.define(&c.name, SymbolKind::Class, index, None);
+                    for member in &c.members {
+                        match member {
+                            ClassMember::Field(f) => self.index_field(f, &c.name),
+                            ClassMember::Method(method) => {
+                                self.scopes.push(HashMap::new());
+                                self.add_local("self", SymbolKind::Parameter);
+                                self.index_function(method, SymbolKind::Method, Some(&c.name));
+                                self.scopes.pop();
+                            }
+                            ClassMember::StaticMethod(method) => {
+                                self.index_function(method, SymbolKind::StaticMethod, Some(&c.name))
+                            }
+                        }
+                    }
+                }
+                TopAst::Trait(t) => {
+                    let index = self.index_of(&t.location).map(|index| index + 1);
+                    self.define(&t.name, SymbolKind::Trait, index, None);
+                    for member in &t.members {
+                        match member {
+                            TraitMember::Field(f) => self.index_field(f, &t.name),
+                            TraitMember::Method(method) => {
+                                self.index_function(method, SymbolKind::Method, Some(&t.name))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn index_field(&mut self, f: &Field, container: &str) {
+        self.define(
+            &f.name,
+            SymbolKind::Field,
+            self.index_of(&f.location),
+            Some(container),
+        );
+        if let Some(expr) = &f.expr {
+            self.index_expr(expr);
+        }
+    }
+
+    fn index_function(&mut self, f: &Function, kind: SymbolKind, container: Option<&str>) {
+        let index = self.index_of(&f.location);
+        self.define(&f.name, kind, index, container);
+        self.scopes.push(HashMap::new());
+        for parameter in &f.parameters {
+            // implicit `self` of trait methods is located at the method, which is classified
+            // already
+            if let Some(index) = self.index_of(&parameter.location) {
+                self.mark(index, SymbolKind::Parameter, true);
+            }
+            self.add_local(&parameter.name, SymbolKind::Parameter);
+        }
+        match &f.body {
+            Some(Body::Expr(e)) => self.index_expr(e),
+            Some(Body::Block(b)) => self.index_block(b),
+            None => (),
+        }
+        self.scopes.pop();
+    }
+
+    fn index_block(&mut self, b: &Block) {
+        self.scopes.push(HashMap::new());
+        for stmt in &b.statements {
+            use StatementVariant::*;
+            match &stmt.value {
+                Return(e) => {
+                    if let Some(e) = e {
+                        self.index_expr(e);
+                    }
+                }
+                Variable(v) => {
+                    self.index_expr(&v.expr);
+                    if let Some(index) = self.index_of(&v.location) {
+                        self.classified.insert(index, (SymbolKind::Variable, true));
+                    }
+                    self.add_local(&v.name, SymbolKind::Variable);
+                }
+                Expression(e) => self.index_expr(e),
+                Assign { target, value } => {
+                    self.index_expr(target);
+                    self.index_expr(value);
+                }
+                IfBlock {
+                    clauses,
+                    else_block,
+                } => {
+                    for (condition, block) in clauses {
+                        self.index_expr(condition);
+                        self.index_block(block);
+                    }
+                    self.index_block(else_block);
+                }
+                // the loop variable is in the scope of the body only
+                For {
+                    name,
+                    name_location,
+                    iterable,
+                    block,
+                } => {
+                    self.index_expr(iterable);
+                    self.scopes.push(HashMap::new());
+                    if let Some(index) = self.index_of(name_location) {
+                        self.classified.insert(index, (SymbolKind::Variable, true));
+                    }
+                    self.add_local(name, SymbolKind::Variable);
+                    self.index_block(block);
+                    self.scopes.pop();
+                }
+            }
+        }
+        self.scopes.pop();
+    }
+
+    fn index_expr(&mut self, expr: &Expr) {
+        use ExprVariant::*;
+        match &expr.value {
+            Binary(l, r, _) => {
+                self.index_expr(l);
+                self.index_expr(r);
+            }
+            F64(..) | Int(..) | Bool(..) | String(..) => (),
+            List(exprs) => {
+                for e in exprs.iter() {
+                    self.index_expr(e);
+                }
+            }
+            FuncCall(callee, args) => {
+                match &callee.value {
+                    MemberAccess(from, _) => {
+                        self.index_expr(from);
+                        self.mark_member(&callee.location, SymbolKind::Method);
+                    }
+                    _ => self.index_expr(callee),
+                }
+                for arg in args.iter() {
+                    // named argument is `<name>: <expr>`
+                    if arg.name.is_some() {
+                        self.mark_before(&arg.expr, SymbolKind::Parameter);
+                    }
+                    self.index_expr(&arg.expr);
+                }
+            }
+            MemberAccess(from, _) => {
+                self.index_expr(from);
+                self.mark_member(&expr.location, SymbolKind::Field);
+            }
+            Identifier(name) => self.index_identifier(&expr.location, name),
+            If(condition, then_expr, else_expr) => {
+                self.index_expr(condition);
+                self.index_expr(then_expr);
+                self.index_expr(else_expr);
+            }
+            ClassConstruction(_, field_inits) => {
+                if let Some(index) = self.index_of(&expr.location) {
+                    self.mark(index, SymbolKind::Class, false);
+                }
+                for e in field_inits.values() {
+                    self.mark_before(e, SymbolKind::Field);
+                    self.index_expr(e);
+                }
+            }
+        }
+    }
+
+    /// index_identifier classifies name of an identifier expression, e.g. `x` or `Foo::new`
+    fn index_identifier(&mut self, location: &Location, name: &str) {
+        let index = match self.index_of(location) {
+            Some(index) => index,
+            None => return,
+        };
+        if name.contains(SEPARATOR) {
+            let components: Vec<&str> = name.split(SEPARATOR).collect();
+            for (i, component) in components.iter().enumerate() {
+                let kind = if i == components.len() - 1 {
+                    SymbolKind::StaticMethod
+                } else if self.checker.is_type(self.module_name, component) {
+                    SymbolKind::Class
+                } else {
+                    SymbolKind::Module
+                };
+                // components are separated by `::`
+                self.mark(index + i * 2, kind, false);
+            }
+            return;
+        }
+        let kind = match self.lookup(name) {
+            Some(kind) => kind,
+            None if self.checker.is_function(self.module_name, name) => SymbolKind::Function,
+            None => SymbolKind::Variable,
+        };
+        self.mark(index, kind, false);
+    }
+
+    /// classify_rest classifies identifiers not in expressions, e.g. module names and types
+    fn classify_rest(&mut self) {
+        let mut in_module_path = false;
+        let mut in_import_list = false;
+        for (index, token) in self.tokens.iter().enumerate() {
+            match token.tk_type() {
+                TkType::Module | TkType::Import => {
+                    in_module_path = true;
+                    continue;
+                }
+                TkType::OpenParen if in_module_path => {
+                    in_module_path = false;
+                    in_import_list = true;
+                    continue;
+                }
+                TkType::CloseParen if in_import_list => {
+                    in_import_list = false;
+                    continue;
+                }
+                TkType::Identifier | TkType::Dot | TkType::Comma => (),
+                _ => {
+                    in_module_path = false;
+                    in_import_list = false;
+                    continue;
+                }
+            }
+            if token.tk_type() != &TkType::Identifier || self.classified.contains_key(&index) {
+                continue;
+            }
+            let name = token.value();
+            let kind = if in_module_path {
+                SymbolKind::Module
+            } else if self.type_parameters.contains(name.as_str())
+                || self.checker.is_type(self.module_name, &name)
+            {
+                SymbolKind::Type
+            } else if in_import_list && self.checker.is_function(self.module_name, &name) {
+                SymbolKind::Function
+            } else if in_import_list {
+                SymbolKind::Variable
+            } else {
+                continue;
+            };
+            self.classified.insert(index, (kind, false));
+        }
+    }
+
+    /// define records the symbol, and its name if the token can be found
+    fn define(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        index: Option<usize>,
+        container: Option<&str>,
+    ) {
+        let index = match index {
+            Some(index) if self.tokens[index].tk_type() == &TkType::Identifier => index,
+            _ => return,
+        };
+        self.classified.insert(index, (kind, true));
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            location: self.tokens[index].location(),
+            container: container.map(|c| c.to_string()),
+        });
+    }
+    /// mark classifies the token if it's an identifier, identifiers in string templates have
+    /// the location of the string, so they are left out
+    fn mark(&mut self, index: usize, kind: SymbolKind, definition: bool) {
+        if let Some(token) = self.tokens.get(index) {
+            if token.tk_type() == &TkType::Identifier {
+                self.classified.entry(index).or_insert((kind, definition));
+            }
+        }
+    }
+    /// mark_member classifies the member of member access, which is located at the member
+    fn mark_member(&mut self, member: &Location, kind: SymbolKind) {
+        if let Some(index) = self.index_of(member) {
+            self.mark(index, kind, false);
+        }
+    }
+    /// mark_before classifies the name of `<name>: <expr>`
+    fn mark_before(&mut self, expr: &Expr, kind: SymbolKind) {
+        if let Some(index) = self.index_of(&start_of(expr)) {
+            if index >= 2 {
+                self.mark(index - 2, kind, false);
+            }
+        }
+    }
+
+    fn index_of(&self, location: &Location) -> Option<usize> {
+        self.positions
+            .get(&(location.line(), location.column()))
+            .copied()
+    }
+    fn add_local(&mut self, name: &str, kind: SymbolKind) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), kind);
+        }
+    }
+    fn lookup(&self, name: &str) -> Option<SymbolKind> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(kind) = scope.get(name) {
+                return Some(*kind);
+            }
+        }
+        self.globals.get(name).copied()
+    }
+}
+
+/// start_of returns the location of the first token of expression, member access is located at
+/// its member
+fn start_of(expr: &Expr) -> Location {
+    match &expr.value {
+        ExprVariant::MemberAccess(from, _) => start_of(from),
+        ExprVariant::FuncCall(callee, _) => start_of(callee),
+        ExprVariant::Binary(l, _, _) => start_of(l),
+        _ => expr.location.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
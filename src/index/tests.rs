@@ -0,0 +1,133 @@
+use super::*;
+
+#[test]
+fn symbols_of_module() {
+    let index = index_source(
+        "",
+        "module main
+class Point {
+  x: int;
+  ::new(x: int): Point = Point { x: x };
+  double(): int = self.x * 2;
+}
+origin: int = 0;
+main(): void {}
+",
+    )
+    .unwrap();
+    let symbols: Vec<(&str, SymbolKind, Option<&str>, (u32, u32))> = index
+        .symbols()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol.name.as_str(),
+                symbol.kind,
+                symbol.container.as_deref(),
+                (symbol.location.line(), symbol.location.column()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        symbols,
+        vec![
+            ("Point", SymbolKind::Class, None, (2, 6)),
+            ("x", SymbolKind::Field, Some("Point"), (3, 2)),
+            ("new", SymbolKind::StaticMethod, Some("Point"), (4, 4)),
+            ("double", SymbolKind::Method, Some("Point"), (5, 2)),
+            ("origin", SymbolKind::Variable, None, (7, 0)),
+            ("main", SymbolKind::Function, None, (8, 0)),
+        ]
+    );
+}
+
+#[test]
+fn identifiers_are_classified() {
+    let code = "module main
+import prelude (println)
+class Point {
+  x: int;
+  ::new(x: int): Point = Point { x: x };
+  get(): int = self.x;
+}
+add(x: int, y: int): int = x + y;
+main(): void {
+  p: Point = Point::new(add(1, y: 2));
+  println(\"{p}\");
+  n: int = p.get();
+}
+";
+    let index = index_source("", code).unwrap();
+    let tokens: Vec<(String, SymbolKind, bool)> = index
+        .tokens()
+        .iter()
+        .map(|token| {
+            let location = &token.location;
+            (
+                code[location.start as usize..location.end as usize].to_string(),
+                token.kind,
+                token.definition,
+            )
+        })
+        .collect();
+    use SymbolKind::*;
+    let expected: Vec<(String, SymbolKind, bool)> = vec![
+        ("main", Module, false),
+        ("prelude", Module, false),
+        ("println", Function, false),
+        ("Point", Class, true),
+        ("x", Field, true),
+        ("int", Type, false),
+        ("new", StaticMethod, true),
+        ("x", Parameter, true),
+        ("int", Type, false),
+        ("Point", Type, false),
+        ("Point", Class, false),
+        ("x", Field, false),
+        ("x", Parameter, false),
+        ("get", Method, true),
+        ("int", Type, false),
+        ("self", Parameter, false),
+        ("x", Field, false),
+        ("add", Function, true),
+        ("x", Parameter, true),
+        ("int", Type, false),
+        ("y", Parameter, true),
+        ("int", Type, false),
+        ("int", Type, false),
+        ("x", Parameter, false),
+        ("y", Parameter, false),
+        ("main", Function, true),
+        ("void", Type, false),
+        ("p", Variable, true),
+        ("Point", Type, false),
+        ("Point", Class, false),
+        ("new", StaticMethod, false),
+        ("add", Function, false),
+        ("y", Parameter, false),
+        ("println", Function, false),
+        ("n", Variable, true),
+        ("int", Type, false),
+        ("p", Variable, false),
+        ("get", Method, false),
+    ]
+    .into_iter()
+    .map(|(name, kind, definition)| (name.to_string(), kind, definition))
+    .collect();
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn token_at_position() {
+    let index = index_source("", "module main\nadd(x: int): int = x;").unwrap();
+    let token = index.token_at(2, 19).unwrap();
+    assert_eq!(token.kind, SymbolKind::Parameter);
+    assert_eq!(token.definition, false);
+    assert!(index.token_at(2, 3).is_none());
+}
+
+#[test]
+fn module_with_semantic_errors_is_indexed() {
+    let index = index_source("", "module main\nfoo(): int = bar;").unwrap();
+    assert_eq!(index.symbols().len(), 1);
+    assert!(index_source("", "module main\nfoo(: int;").is_err());
+}
@@ -0,0 +1,66 @@
+use crate::lexer::Location;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+#[derive(Debug, Error)]
+#[error("{location} {err}")]
+pub struct RuntimeError {
+    location: Location,
+    err: RuntimeErrorVariant,
+}
+
+#[derive(Debug, Error)]
+enum RuntimeErrorVariant {
+    #[error("integer overflow")]
+    Overflow,
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("no function named `main`")]
+    NoMain,
+    #[error("program exits with code {}", .code)]
+    Exit { code: i32 },
+    #[error("extern function `{}` is not supported by interpreter", .name)]
+    UnsupportedExtern { name: String },
+}
+
+impl RuntimeError {
+    fn new(location: &Location, err: RuntimeErrorVariant) -> RuntimeError {
+        RuntimeError {
+            location: location.clone(),
+            err,
+        }
+    }
+    pub fn location(&self) -> Location {
+        self.location.clone()
+    }
+    /// exit_code returns the code of `exit`, which stops the program rather than being a failure
+    /// of the interpreter
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.err {
+            RuntimeErrorVariant::Exit { code } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn overflow(location: &Location) -> RuntimeError {
+        RuntimeError::new(location, RuntimeErrorVariant::Overflow)
+    }
+    pub fn division_by_zero(location: &Location) -> RuntimeError {
+        RuntimeError::new(location, RuntimeErrorVariant::DivisionByZero)
+    }
+    pub fn no_main() -> RuntimeError {
+        RuntimeError::new(&Location::none(), RuntimeErrorVariant::NoMain)
+    }
+    pub fn exit(location: &Location, code: i32) -> RuntimeError {
+        RuntimeError::new(location, RuntimeErrorVariant::Exit { code })
+    }
+    pub fn unsupported_extern<T: ToString>(location: &Location, name: T) -> RuntimeError {
+        RuntimeError::new(
+            location,
+            RuntimeErrorVariant::UnsupportedExtern {
+                name: name.to_string(),
+            },
+        )
+    }
+}
@@ -0,0 +1,804 @@
+//! interpreter executes checked AST directly, it's used on platforms without LLVM and as the
+//! reference semantics of code generation
+use crate::ast::*;
+use crate::codegen::optimize::wrap;
+use crate::lexer::Location;
+use crate::mangle::{method_path, SEPARATOR};
+use crate::semantic::TypeMap;
+use error::{Result, RuntimeError};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::rc::Rc;
+use value::{Object, Value};
+
+pub mod error;
+pub mod value;
+
+/// Flow is how control leaves a statement
+enum Flow {
+    Next,
+    Return(Value),
+}
+
+pub struct Interpreter<W: Write> {
+    /// `puts` and `write` to stdout write to it
+    output: W,
+    /// `io::read_line` reads from it, stdin by default
+    input: Box<dyn BufRead>,
+    /// top-level functions and static methods, static method is named as `Class::method`
+    functions: HashMap<String, Function>,
+    classes: HashMap<String, Class>,
+    traits: HashMap<String, Trait>,
+    globals: HashMap<String, Value>,
+    /// scopes of the running function, the innermost scope is the last one
+    scopes: Vec<HashMap<String, Value>>,
+    /// `env::args` returns them, the first one is how the program is run
+    args: Vec<String>,
+    /// integer operations wrap around at the width of their types, `int` without types
+    expr_types: TypeMap,
+}
+
+impl<W: Write> Interpreter<W> {
+    pub fn new(output: W) -> Interpreter<W> {
+        Interpreter {
+            output,
+            input: Box::new(BufReader::new(std::io::stdin())),
+            functions: HashMap::new(),
+            classes: HashMap::new(),
+            traits: HashMap::new(),
+            globals: HashMap::new(),
+            scopes: vec![],
+            args: vec![],
+            expr_types: TypeMap::default(),
+        }
+    }
+    /// with_input reads input of the program from `input` instead of stdin
+    pub fn with_input<R: BufRead + 'static>(mut self, input: R) -> Interpreter<W> {
+        self.input = Box::new(input);
+        self
+    }
+    /// with_args runs the program with arguments `args`, the first one is taken as the program
+    pub fn with_args(mut self, args: Vec<String>) -> Interpreter<W> {
+        self.args = args;
+        self
+    }
+    /// with_expr_types gives types of expressions inferred by the semantic checker, so integers
+    /// wrap around as compiled code does, e.g. `u8` at 256
+    pub fn with_expr_types(mut self, expr_types: TypeMap) -> Interpreter<W> {
+        self.set_expr_types(expr_types);
+        self
+    }
+    /// set_expr_types replaces types of expressions, e.g. after the REPL checks more code
+    pub fn set_expr_types(&mut self, expr_types: TypeMap) {
+        self.expr_types = expr_types;
+    }
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// load defines `top_list`, global variables are evaluated in order
+    pub fn load(&mut self, top_list: &[TopAst]) -> Result<()> {
+        for top in top_list {
+            match top {
+                TopAst::Function(f) => {
+                    self.functions.insert(f.name.clone(), f.clone());
+                }
+                TopAst::Class(c) => {
+                    for member in &c.members {
+                        if let ClassMember::StaticMethod(method) = member {
+                            let name = method_path(&c.name, &method.name);
+                            self.functions.insert(name, method.clone());
+                        }
+                    }
+                    self.classes.insert(c.name.clone(), c.clone());
+                }
+                TopAst::Trait(t) => {
+                    self.traits.insert(t.name.clone(), t.clone());
+                }
+                TopAst::Variable(_) | TopAst::Import(_) => (),
+            }
+        }
+        for top in top_list {
+            if let TopAst::Variable(v) = top {
+                let value = self.eval_expr(&v.expr)?;
+                self.globals.insert(v.name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// run_main calls `main` and returns the exit code, which is 0 when `main` returns void
+    pub fn run_main(&mut self) -> Result<i32> {
+        if !self.functions.contains_key("main") {
+            return Err(RuntimeError::no_main());
+        }
+        match self.call(&Location::none(), "main", None, vec![])? {
+            Value::Int(code) => Ok(code as i32),
+            _ => Ok(0),
+        }
+    }
+
+    /// run_function calls the function `name` without arguments, e.g. a test, the function must
+    /// have been loaded
+    pub fn run_function(&mut self, name: &str) -> Result<Value> {
+        self.call(&Location::none(), name, None, vec![])
+    }
+
+    fn call(
+        &mut self,
+        location: &Location,
+        name: &str,
+        self_value: Option<Value>,
+        args: Vec<Value>,
+    ) -> Result<Value> {
+        let native = match name.split_once(SEPARATOR) {
+            Some(("List", _)) => self.call_list_method(name, &args),
+            Some(("Map", _)) => self.call_map_method(name, &args),
+            Some(("string", _)) => self.call_string_method(name, &args),
+            Some(("StringBuilder", _)) => self.call_string_builder_method(name, &args),
+            Some(("io", _)) => self.call_io_method(name),
+            Some(("env", _)) => self.call_env_method(name, &args),
+            Some(("int" | "i32" | "u8" | "f64", _)) => self.call_conversion(name, &args),
+            _ => None,
+        };
+        if let Some(result) = native {
+            return result;
+        }
+        let f = self.functions[name].clone();
+        self.call_function(location, &f, self_value, args)
+    }
+
+    /// call_list_method implements static methods of prelude class `List` on the native list,
+    /// which list literals evaluate to, `None` for other functions
+    fn call_list_method(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let in_bounds = |list: &Rc<RefCell<Vec<Value>>>, index: i64| {
+            index >= 0 && (index as usize) < list.borrow().len()
+        };
+        let result = match (name, args) {
+            ("List::new", []) => Ok(Value::List(Rc::new(RefCell::new(vec![])))),
+            ("List::push", [Value::List(list), value]) => {
+                list.borrow_mut().push(value.clone());
+                Ok(Value::Void)
+            }
+            ("List::get", [Value::List(list), Value::Int(index), _]) if in_bounds(list, *index) => {
+                Ok(list.borrow()[*index as usize].clone())
+            }
+            ("List::set", [Value::List(list), Value::Int(index), value, _])
+                if in_bounds(list, *index) =>
+            {
+                list.borrow_mut()[*index as usize] = value.clone();
+                Ok(Value::Void)
+            }
+            ("List::get" | "List::set", [.., location]) => {
+                self.panic("index out of bounds", location)
+            }
+            ("List::length", [Value::List(list)]) => Ok(Value::Int(list.borrow().len() as i64)),
+            ("List::_compare", [Value::Int(a), Value::Int(b)]) => Ok(ordering(a.partial_cmp(b))),
+            ("List::_compare", [Value::F64(a), Value::F64(b)]) => Ok(ordering(a.partial_cmp(b))),
+            ("List::_compare", [Value::Bool(a), Value::Bool(b)]) => Ok(ordering(a.partial_cmp(b))),
+            ("List::_compare", [a, b]) => {
+                self.call_method(&Location::none(), a.clone(), "compare", vec![b.clone()])
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// call_map_method implements builtin static methods of prelude class `Map` hashing and
+    /// comparing keys, which call methods of keys, `None` for other functions
+    fn call_map_method(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let result = match (name, args) {
+            ("Map::_hash", [key]) => {
+                self.call_method(&Location::none(), key.clone(), "hash", vec![])
+            }
+            ("Map::_equals", [a @ Value::Object(..), b]) => {
+                self.call_method(&Location::none(), a.clone(), "eq", vec![b.clone()])
+            }
+            ("Map::_equals", [a, b]) => binary(
+                &Location::none(),
+                a.clone(),
+                b.clone(),
+                &Operator::Equal,
+                None,
+            ),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// call_string_method implements static methods of prelude class `string` on the text, since
+    /// the prelude implements them over pointers, `None` for other functions
+    fn call_string_method(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let result = match (name, args) {
+            ("string::length", [s]) => Ok(Value::Int(s.text().len() as i64)),
+            // bytes end with 0, as C strings
+            ("string::_byte_at", [Value::CString(s), Value::Int(index)]) => Ok(Value::Int(
+                s.bytes().nth(*index as usize).unwrap_or(0) as i64,
+            )),
+            ("string::concat", [a, b]) => Ok(Value::string(&format!("{}{}", a.text(), b.text()))),
+            ("string::substring", [s, Value::Int(start), Value::Int(end), location]) => {
+                let bytes = s.text().as_bytes().to_vec();
+                if 0 <= *start && start <= end && *end as usize <= bytes.len() {
+                    let bytes = &bytes[*start as usize..*end as usize];
+                    Ok(Value::string(&String::from_utf8_lossy(bytes)))
+                } else {
+                    self.panic("substring out of bounds", location)
+                }
+            }
+            ("string::contains", [s, part]) => Ok(Value::Bool(s.text().contains(&*part.text()))),
+            ("string::to_int", [s]) => match s.text().parse::<i64>() {
+                Ok(n) if !s.text().starts_with('+') => {
+                    self.call_option("some", vec![Value::Int(n)])
+                }
+                _ => self.call_option("none", vec![]),
+            },
+            ("string::to_f64", [s]) => match s.text().parse::<f64>() {
+                Ok(x) if is_decimal(&s.text()) => self.call_option("some", vec![Value::F64(x)]),
+                _ => self.call_option("none", vec![]),
+            },
+            ("string::split", [s, separator, location]) => match &*separator.text() {
+                "" => self.panic("empty separator", location),
+                separator => {
+                    let parts = s.text().split(separator).map(Value::string).collect();
+                    Ok(Value::List(Rc::new(RefCell::new(parts))))
+                }
+            },
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// call_string_builder_method implements static methods of prelude class `StringBuilder` on a
+    /// native list of appended strings, `None` for other functions
+    fn call_string_builder_method(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let value = match (name, args) {
+            ("StringBuilder::new", []) => Value::List(Rc::new(RefCell::new(vec![]))),
+            ("StringBuilder::append", [Value::List(pieces), s]) => {
+                pieces.borrow_mut().push(s.clone());
+                Value::Void
+            }
+            ("StringBuilder::build", [Value::List(pieces)]) => {
+                let pieces: Vec<Rc<str>> = pieces.borrow().iter().map(Value::text).collect();
+                Value::string(&pieces.concat())
+            }
+            _ => return None,
+        };
+        Some(Ok(value))
+    }
+
+    /// call_conversion implements conversions between builtin types of prelude, `None` for other
+    /// functions
+    fn call_conversion(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let value = match (name, args) {
+            ("i32::to_int", [Value::Int(n)]) => Value::Int(*n),
+            ("int::to_f64", [Value::Int(n)]) => Value::F64(*n as f64),
+            ("u8::to_int", [Value::Int(b)]) => Value::Int(*b),
+            ("f64::to_int", [Value::F64(x)]) => Value::Int(*x as i64),
+            _ => return None,
+        };
+        Some(Ok(value))
+    }
+
+    /// call_option returns the result of the static method of prelude class `Option`
+    fn call_option(&mut self, method: &str, args: Vec<Value>) -> Result<Value> {
+        self.call(
+            &Location::none(),
+            &method_path("Option", method),
+            None,
+            args,
+        )
+    }
+
+    /// call_env_method implements static methods of prelude class `env` on arguments of the
+    /// interpreter and environment variables of the process, `None` for other functions
+    fn call_env_method(&mut self, name: &str, args: &[Value]) -> Option<Result<Value>> {
+        let result = match (name, args) {
+            ("env::_argc", []) => Ok(Value::Int(self.args.len() as i64)),
+            ("env::_argv", [Value::Int(index)]) => {
+                Ok(Value::CString(self.args[*index as usize].as_str().into()))
+            }
+            ("env::get", [name]) => match std::env::var_os(&*name.text()) {
+                Some(value) => {
+                    let value = Value::string(&value.to_string_lossy());
+                    self.call_option("some", vec![value])
+                }
+                None => self.call_option("none", vec![]),
+            },
+            ("env::set", [name, value]) => {
+                // as `setenv` of C, which fails on these names, rather than panicking
+                let name = name.text();
+                if !name.is_empty() && !name.contains(['=', '\0']) && !value.text().contains('\0') {
+                    std::env::set_var(&*name, &*value.text());
+                }
+                Ok(Value::Void)
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// call_io_method implements static methods of prelude class `io` on the input of the
+    /// interpreter, `None` for other functions
+    fn call_io_method(&mut self, name: &str) -> Option<Result<Value>> {
+        match name {
+            "io::read_line" => {
+                let mut line = String::new();
+                let _ = self.input.read_line(&mut line);
+                let line = line.strip_suffix('\n').unwrap_or(&line);
+                Some(Ok(Value::string(line)))
+            }
+            _ => None,
+        }
+    }
+
+    /// panic calls prelude `panic` with the location given to the caller
+    fn panic(&mut self, message: &str, location: &Value) -> Result<Value> {
+        self.call(
+            &Location::none(),
+            "panic",
+            None,
+            vec![Value::string(message), location.clone()],
+        )
+    }
+
+    /// call_function runs `f` in a new scope, `self_value` is bound to `self` for method
+    fn call_function(
+        &mut self,
+        location: &Location,
+        f: &Function,
+        self_value: Option<Value>,
+        args: Vec<Value>,
+    ) -> Result<Value> {
+        let body = match &f.body {
+            Some(body) => body,
+            None => return self.call_extern(location, f, args),
+        };
+        let mut scope = HashMap::new();
+        if let Some(value) = self_value {
+            scope.insert("self".to_string(), value);
+        }
+        for (p, arg) in f.parameters.iter().zip(args) {
+            scope.insert(p.name.clone(), arg);
+        }
+        // caller's scopes are not visible in the callee
+        let caller_scopes = std::mem::replace(&mut self.scopes, vec![scope]);
+        let result = match body {
+            Body::Expr(e) => self.eval_expr(e),
+            Body::Block(b) => match self.exec_block(b) {
+                Ok(Flow::Return(value)) => Ok(value),
+                Ok(Flow::Next) => Ok(Value::Void),
+                Err(err) => Err(err),
+            },
+        };
+        self.scopes = caller_scopes;
+        result
+    }
+
+    /// call_extern implements extern functions of prelude
+    fn call_extern(
+        &mut self,
+        location: &Location,
+        f: &Function,
+        args: Vec<Value>,
+    ) -> Result<Value> {
+        match (f.name.as_str(), args.as_slice()) {
+            ("puts", [Value::CString(s)]) => {
+                // as C `puts`, a newline is appended
+                let _ = writeln!(self.output, "{}", s);
+                Ok(Value::Int(0))
+            }
+            ("write", [Value::Int(fd), Value::CString(s), Value::Int(size)]) => {
+                let bytes = &s.as_bytes()[..*size as usize];
+                let _ = match fd {
+                    2 => std::io::stderr().write_all(bytes),
+                    _ => self.output.write_all(bytes),
+                };
+                Ok(Value::Int(*size))
+            }
+            ("strlen", [Value::CString(s)]) => Ok(Value::Int(s.len() as i64)),
+            ("strncmp", [Value::CString(a), Value::CString(b), Value::Int(size)]) => {
+                let prefix = |s: &str| s.bytes().take(*size as usize).collect::<Vec<_>>();
+                Ok(ordering(prefix(a).partial_cmp(&prefix(b))))
+            }
+            ("pow", [Value::F64(x), Value::F64(y)]) => Ok(Value::F64(x.powf(*y))),
+            ("atan2", [Value::F64(y), Value::F64(x)]) => Ok(Value::F64(y.atan2(*x))),
+            (name, [Value::F64(x)]) => libm(name, *x)
+                .map(Value::F64)
+                .ok_or_else(|| RuntimeError::unsupported_extern(location, name)),
+            ("exit", [Value::Int(code)]) => Err(RuntimeError::exit(location, *code as i32)),
+            (name, _) => Err(RuntimeError::unsupported_extern(location, name)),
+        }
+    }
+
+    fn exec_block(&mut self, b: &Block) -> Result<Flow> {
+        self.scopes.push(HashMap::new());
+        let mut flow = Ok(Flow::Next);
+        for stmt in &b.statements {
+            flow = self.exec_statement(stmt);
+            match flow {
+                Ok(Flow::Next) => (),
+                _ => break,
+            }
+        }
+        self.scopes.pop();
+        flow
+    }
+
+    fn exec_statement(&mut self, stmt: &Statement) -> Result<Flow> {
+        use StatementVariant::*;
+        match &stmt.value {
+            Return(None) => Ok(Flow::Return(Value::Void)),
+            Return(Some(e)) => Ok(Flow::Return(self.eval_expr(e)?)),
+            Variable(v) => {
+                let value = self.eval_expr(&v.expr)?;
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(v.name.clone(), value);
+                Ok(Flow::Next)
+            }
+            Expression(e) => {
+                self.eval_expr(e)?;
+                Ok(Flow::Next)
+            }
+            Assign { target, value } => {
+                let (from, field) = match &target.value {
+                    ExprVariant::MemberAccess(from, field) => (from, field),
+                    _ => unreachable!("parser only accepts member access as assignment target"),
+                };
+                let object = self.eval_expr(from)?;
+                let value = self.eval_expr(value)?;
+                match object {
+                    Value::Object(object) => object.set_field(field, value),
+                    v => unreachable!("assign field `{}` of non-object `{}`", field, v),
+                }
+                Ok(Flow::Next)
+            }
+            IfBlock {
+                clauses,
+                else_block,
+            } => {
+                for (condition, block) in clauses {
+                    if self.eval_condition(condition)? {
+                        return self.exec_block(block);
+                    }
+                }
+                self.exec_block(else_block)
+            }
+            For {
+                name,
+                iterable,
+                block,
+                ..
+            } => {
+                let mut iterator = self.eval_expr(iterable)?;
+                if let Value::List(..) = iterator {
+                    let iter = method_path("List", "iter");
+                    iterator = self.call(&stmt.location, &iter, None, vec![iterator])?;
+                }
+                loop {
+                    let option =
+                        self.call_method(&stmt.location, iterator.clone(), "next", vec![])?;
+                    if let Value::Bool(false) = self.call_option("is_some", vec![option.clone()])? {
+                        return Ok(Flow::Next);
+                    }
+                    let value = self.call_option("unwrap", vec![option, Value::string("")])?;
+                    self.scopes.push(HashMap::from([(name.clone(), value)]));
+                    let flow = self.exec_block(block);
+                    self.scopes.pop();
+                    if let Flow::Return(value) = flow? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+        }
+    }
+
+    fn eval_condition(&mut self, condition: &Expr) -> Result<bool> {
+        match self.eval_expr(condition)? {
+            Value::Bool(b) => Ok(b),
+            v => unreachable!("condition `{}` is not a bool", v),
+        }
+    }
+
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value> {
+        use ExprVariant::*;
+        let location = &expr.location;
+        match &expr.value {
+            Int(i) => Ok(Value::Int(*i)),
+            F64(f) => Ok(Value::F64(*f)),
+            Bool(b) => Ok(Value::Bool(*b)),
+            String(s) => Ok(Value::string(s)),
+            List(es) => {
+                let mut values = vec![];
+                for e in es.iter() {
+                    values.push(self.eval_expr(e)?);
+                }
+                Ok(Value::List(Rc::new(RefCell::new(values))))
+            }
+            // right hand side is skipped as compiled code does
+            Binary(l, r, op) if op.is_logical() => match (self.eval_condition(l)?, op) {
+                (false, Operator::And) => Ok(Value::Bool(false)),
+                (true, Operator::Or) => Ok(Value::Bool(true)),
+                _ => Ok(Value::Bool(self.eval_condition(r)?)),
+            },
+            Binary(l, r, op) => {
+                let l = self.eval_expr(l)?;
+                let r = self.eval_expr(r)?;
+                match (&l, op) {
+                    // `==` of instances calls `eq` of `Eq`, which prelude implements over C
+                    // functions for strings
+                    (Value::Object(object), Operator::Equal | Operator::NotEqual) => {
+                        let equal = if object.class_name == "string" {
+                            l.text() == r.text()
+                        } else {
+                            matches!(
+                                self.call_method(location, l, "eq", vec![r])?,
+                                Value::Bool(true)
+                            )
+                        };
+                        Ok(Value::Bool(equal == (*op == Operator::Equal)))
+                    }
+                    _ => binary(location, l, r, op, self.expr_types.type_of(location)),
+                }
+            }
+            If(condition, then_expr, else_expr) => {
+                if self.eval_condition(condition)? {
+                    self.eval_expr(then_expr)
+                } else {
+                    self.eval_expr(else_expr)
+                }
+            }
+            Identifier(name) => Ok(self.lookup(name)),
+            MemberAccess(from, field) => match self.eval_expr(from)? {
+                Value::Object(object) => Ok(object.field(field)),
+                v => unreachable!("access field `{}` of non-object `{}`", field, v),
+            },
+            FuncCall(f, args) => {
+                let mut values = vec![];
+                for arg in args.iter() {
+                    values.push(self.eval_expr(&arg.expr)?);
+                }
+                match &f.value {
+                    MemberAccess(from, method) => {
+                        let object = self.eval_expr(from)?;
+                        self.call_method(location, object, method, values)
+                    }
+                    _ => match self.eval_expr(f)? {
+                        Value::Function(name) => self.call(location, &name, None, values),
+                        v => unreachable!("call on non-function `{}`", v),
+                    },
+                }
+            }
+            ClassConstruction(class_name, field_inits) => {
+                let class = self.classes[class_name].clone();
+                let mut fields = vec![];
+                for member in &class.members {
+                    if let ClassMember::Field(field) = member {
+                        // omitted field uses its default value
+                        let init = field_inits.get(&field.name).or(field.expr.as_ref());
+                        let value = match init {
+                            Some(init) => self.eval_expr(init)?,
+                            None => unreachable!("field `{}` doesn't have value", field.name),
+                        };
+                        fields.push((field.name.clone(), value));
+                    }
+                }
+                Ok(Value::Object(Rc::new(Object {
+                    class_name: class_name.clone(),
+                    fields: RefCell::new(fields),
+                })))
+            }
+        }
+    }
+
+    /// call_method finds `method` in the class of `object`, then default methods of its traits
+    fn call_method(
+        &mut self,
+        location: &Location,
+        object: Value,
+        method: &str,
+        args: Vec<Value>,
+    ) -> Result<Value> {
+        let class_name = match &object {
+            Value::Object(o) => o.class_name.clone(),
+            value => return Ok(builtin_type_method(value, method)),
+        };
+        let class = &self.classes[&class_name];
+        for member in &class.members {
+            if let ClassMember::Method(f) = member {
+                if f.name == method {
+                    let f = f.clone();
+                    return self.call_function(location, &f, Some(object), args);
+                }
+            }
+        }
+        let mut traits = class.parents.clone();
+        while let Some(trait_name) = traits.pop() {
+            let t = &self.traits[&trait_name];
+            for member in &t.members {
+                if let TraitMember::Method(f) = member {
+                    if f.name == method && f.body.is_some() {
+                        // default method takes `self` as its first parameter
+                        let f = f.clone();
+                        let mut args = args;
+                        args.insert(0, object);
+                        return self.call_function(location, &f, None, args);
+                    }
+                }
+            }
+            traits.extend(t.with_traits.iter().cloned());
+        }
+        unreachable!("`{}` has no method `{}`", class_name, method)
+    }
+
+    /// lookup finds variable from the innermost scope to globals, then functions
+    fn lookup(&self, name: &str) -> Value {
+        for scope in self.scopes.iter().rev() {
+            if let Some(value) = scope.get(name) {
+                return value.clone();
+            }
+        }
+        match self.globals.get(name) {
+            Some(value) => value.clone(),
+            None if self.functions.contains_key(name) => Value::Function(name.to_string()),
+            None => unreachable!("no variable named `{}`", name),
+        }
+    }
+}
+
+/// builtin_type_method implements methods of prelude builtin types, which the prelude implements
+/// over C functions
+fn builtin_type_method(value: &Value, method: &str) -> Value {
+    match (value, method) {
+        (Value::Int(n), "to_string") => Value::string(&n.to_string()),
+        (Value::Bool(b), "to_string") => Value::string(&b.to_string()),
+        (Value::F64(x), "to_string") => Value::string(&format_general(*x)),
+        (Value::Int(n), "hash") => Value::Int(*n),
+        (Value::Bool(b), "hash") => Value::Int(*b as i64),
+        (v, method) => unreachable!("call method `{}` of non-object `{}`", method, v),
+    }
+}
+
+/// format_general formats `x` with at most 15 significant digits as `%.15g` of C, which prelude
+/// `f64` formats with
+fn format_general(x: f64) -> String {
+    const DIGITS: i32 = 15;
+    if x.is_nan() {
+        return "nan".to_string();
+    }
+    if x.is_infinite() {
+        return if x < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+    // the exponent is taken after rounding, e.g. 9.9999999999999999 is 1e+01
+    let scientific = format!("{:.*e}", (DIGITS - 1) as usize, x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let trim = |s: &str| {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    if !(-4..DIGITS).contains(&exponent) {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs())
+    } else {
+        trim(&format!("{:.*}", (DIGITS - 1 - exponent) as usize, x))
+    }
+}
+
+/// is_decimal is true for text prelude `string::to_f64` parses, digits with an optional leading `-`
+/// and fraction
+fn is_decimal(text: &str) -> bool {
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let text = text.strip_prefix('-').unwrap_or(text);
+    match text.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(text),
+    }
+}
+
+/// libm applies the C `libm` function of one `f64` named `name`
+fn libm(name: &str, x: f64) -> Option<f64> {
+    Some(match name {
+        "sqrt" => x.sqrt(),
+        "fabs" => x.abs(),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "exp" => x.exp(),
+        "log" => x.ln(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        _ => return None,
+    })
+}
+
+/// binary computes `l op r`, integers wrap around at the width of `type_name`, the type of the
+/// result, as compiled code does
+fn binary(
+    location: &Location,
+    l: Value,
+    r: Value,
+    op: &Operator,
+    type_name: Option<&str>,
+) -> Result<Value> {
+    use Operator::*;
+    match (l, r) {
+        (Value::Int(l), Value::Int(r)) => {
+            let (bits, unsigned) = match type_name {
+                Some("u8") => (8, true),
+                Some("i32") => (32, false),
+                _ => (64, false),
+            };
+            let v = match op {
+                Plus => l.wrapping_add(r),
+                Minus => l.wrapping_sub(r),
+                Multiply => l.wrapping_mul(r),
+                Divide if r == 0 => return Err(RuntimeError::division_by_zero(location)),
+                // the least integer divided by -1 traps in compiled code as well
+                Divide if !unsigned && r == -1 && l == wrap(1 << (bits - 1), bits, false) => {
+                    return Err(RuntimeError::overflow(location))
+                }
+                Divide => l / r,
+                Power => wrapping_pow(l, r),
+                _ => return Ok(Value::Bool(compare(l.partial_cmp(&r), op))),
+            };
+            Ok(Value::Int(wrap(v, bits, unsigned)))
+        }
+        (Value::F64(l), Value::F64(r)) => Ok(match op {
+            Plus => Value::F64(l + r),
+            Minus => Value::F64(l - r),
+            Multiply => Value::F64(l * r),
+            Divide => Value::F64(l / r),
+            Power => Value::F64(l.powf(r)),
+            _ => Value::Bool(compare(l.partial_cmp(&r), op)),
+        }),
+        (Value::Bool(l), Value::Bool(r)) => Ok(Value::Bool(compare(l.partial_cmp(&r), op))),
+        // `+` of strings concatenates them
+        (l @ Value::Object(..), r @ Value::Object(..)) if *op == Plus => {
+            Ok(Value::string(&format!("{}{}", l.text(), r.text())))
+        }
+        (l, r) => unreachable!("operator `{}` on `{}` and `{}`", op, l, r),
+    }
+}
+
+/// wrapping_pow is `base ** exponent` of `int::_pow` of prelude, which squares `base` for each bit
+/// of `exponent`, a negative exponent is taken as 0
+fn wrapping_pow(mut base: i64, mut exponent: i64) -> i64 {
+    let mut result: i64 = 1;
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent /= 2;
+    }
+    result
+}
+
+/// ordering converts `ordering` to the result of `compare` of prelude `Ord`, unordered values,
+/// e.g. NaN, are taken as equal
+fn ordering(ordering: Option<std::cmp::Ordering>) -> Value {
+    Value::Int(ordering.map_or(0, |ordering| ordering as i64))
+}
+
+/// compare is false for unordered values, e.g. NaN, as ordered comparison of compiled code
+fn compare(ordering: Option<std::cmp::Ordering>, op: &Operator) -> bool {
+    use std::cmp::Ordering::*;
+    match op {
+        Operator::Equal => ordering == Some(Equal),
+        Operator::NotEqual => ordering == Some(Less) || ordering == Some(Greater),
+        Operator::Less => ordering == Some(Less),
+        Operator::LessEqual => ordering == Some(Less) || ordering == Some(Equal),
+        Operator::Greater => ordering == Some(Greater),
+        Operator::GreaterEqual => ordering == Some(Greater) || ordering == Some(Equal),
+        _ => unreachable!("`{}` is not a comparison", op),
+    }
+}
+
+#[cfg(test)]
+mod tests;
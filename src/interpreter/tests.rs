@@ -0,0 +1,559 @@
+use super::*;
+use crate::lexer::TkType::EOF;
+use crate::semantic::{derive_traits, CallerLocations};
+
+#[test]
+fn hello_world() {
+    let code = "
+    main(): void {
+      println(\"hello\");
+    }
+    ";
+    assert_eq!(run(code), (0, "hello\n".to_string()));
+}
+
+#[test]
+fn recursion_and_if_expression() {
+    let code = "
+    fib(n: int): int = if n < 2 { n } else { fib(n - 1) + fib(n - 2) };
+    main(): int {
+      return fib(10);
+    }
+    ";
+    assert_eq!(run(code).0, 55);
+}
+
+#[test]
+fn if_statement_and_block_scope() {
+    let code = "
+    x: int = 10;
+    classify(n: int): int {
+      if n < 0 {
+        x: int = 1;
+        return x;
+      } else if n == 0 {
+        return 2;
+      }
+      return x;
+    }
+    main(): int = classify(0 - 1) * 100 + classify(0) * 10 + classify(1);
+    ";
+    assert_eq!(run(code).0, 130);
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let code = "
+    fail(): bool {
+      println(\"evaluated\");
+      return true;
+    }
+    main(): void {
+      a: bool = false and fail();
+      b: bool = true or fail();
+      c: bool = true and fail();
+    }
+    ";
+    assert_eq!(run(code).1, "evaluated\n");
+}
+
+#[test]
+fn class_methods_and_field_assignment() {
+    let code = "
+    trait Shape {
+      area(self: Shape): int;
+      describe(self: Shape): int = self.area() * 10;
+    }
+    class Rect <: Shape {
+      w: int;
+      h: int = 2;
+      ::new(w: int): Rect = Rect { w: w };
+      area(): int = self.w * self.h;
+      grow(): void {
+        self.w = self.w + 1;
+      }
+    }
+    main(): int {
+      r: Rect = Rect::new(3);
+      r.grow();
+      return r.describe();
+    }
+    ";
+    assert_eq!(run(code).0, 80);
+}
+
+#[test]
+fn runtime_errors() {
+    let code = "
+    zero(): int = 0;
+    main(): int = 1 / zero();
+    ";
+    assert_eq!(run_err(code), ":3:18 division by zero");
+    // it traps in compiled code as well, other operations wrap around
+    let code = "
+    least(): int = 0 - 9223372036854775807 - 1;
+    minus_one(): int = 0 - 1;
+    main(): int = least() / minus_one();
+    ";
+    assert_eq!(run_err(code), ":4:18 integer overflow");
+}
+
+#[test]
+fn value_display() {
+    let code = "
+    class Point {
+      x: int;
+      y: bool;
+      name: string;
+      ::new(): Point = Point { x: 1, y: false, name: \"p\" };
+    }
+    ";
+    let mut interpreter = load(code);
+    let point = interpreter.call(&Location::none(), "Point::new", None, vec![]);
+    assert_eq!(
+        point.unwrap().to_string(),
+        "Point { x: 1, y: false, name: \"p\" }"
+    );
+    let list = Value::List(Rc::new(RefCell::new(vec![
+        Value::Int(1),
+        Value::Bool(true),
+    ])));
+    assert_eq!(list.to_string(), "[1, true]");
+}
+
+#[test]
+fn failed_assertion_exits_with_its_location() {
+    let code = "
+    main(): void {
+      assert(1 < 2);
+      assert_eq(1 + 1, 3);
+    }
+    ";
+    let mut interpreter = load(code);
+    let err = interpreter.run_main().unwrap_err();
+    assert_eq!(err.exit_code(), Some(101));
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "assertion `left == right` failed\n  left: 2\n right: 3\n:4:6\n"
+    );
+}
+
+#[test]
+fn list_methods() {
+    let code = "
+    total(numbers: List[int], i: int): int =
+      if i == List::length(numbers) { 0 } else { List::get(numbers, i) + total(numbers, i + 1) };
+    grow(numbers: List[int]): int {
+      List::push(numbers, 4);
+      List::set(numbers, 0, 10);
+      return total(numbers, 0);
+    }
+    main(): int = grow([1, 2, 3]);
+    ";
+    assert_eq!(run(code).0, 19);
+    let code = "
+    at(numbers: List[int], i: int): int = List::get(numbers, i);
+    main(): int = at([1], 1);
+    ";
+    let mut interpreter = load(code);
+    let err = interpreter.run_main().unwrap_err();
+    assert_eq!(err.exit_code(), Some(101));
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "index out of bounds\n:2:42\n"
+    );
+}
+
+#[test]
+fn string_methods() {
+    let code = "
+    main(): int {
+      println(string::substring(\"hello\" + \", world\", 7, 12));
+      println(List::get(string::split(\"a,b\", \",\"), 1));
+      if string::contains(\"hello\", \"ell\") {
+        return string::length(\"hello\");
+      }
+      return 0;
+    }
+    ";
+    assert_eq!(run(code), (5, "world\nb\n".to_string()));
+    let code = "
+    main(): void {
+      println(string::substring(\"hello\", 3, 9));
+    }
+    ";
+    let mut interpreter = load(code);
+    let err = interpreter.run_main().unwrap_err();
+    assert_eq!(err.exit_code(), Some(101));
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "substring out of bounds\n:3:14\n"
+    );
+}
+
+#[test]
+fn print_to_stdout() {
+    let code = "
+    main(): void {
+      print(\"a\");
+      eprintln(\"to stderr\");
+      println(\"b\");
+    }
+    ";
+    assert_eq!(run(code).1, "ab\n");
+}
+
+#[test]
+fn read_line_from_input() {
+    let code = "
+    main(): void {
+      greet(io::read_line(), io::read_line());
+    }
+    greet(name: string, missing: string): void {
+      println(\"hello, {name}{missing}\");
+    }
+    ";
+    let mut interpreter = load(code).with_input(&b"elz\n"[..]);
+    interpreter.run_main().unwrap();
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "hello, elz\n"
+    );
+}
+
+#[test]
+fn math_functions() {
+    let code = "
+    main(): int = math::abs(0 - 3) * 100 + math::min(2, 5) * 10 + math::max(2, 5);
+    ";
+    assert_eq!(run(code).0, 325);
+}
+
+#[test]
+fn f64_math_functions() {
+    let code = "
+    main(): void {
+      println(\"{math::sqrt(16.0)} {math::pow(2.0, 10.0)} {math::fabs(0.0 - 2.5)}\");
+      println(\"{math::floor(2.7)} {math::ceil(2.1)} {math::exp(0.0)} {math::log(1.0)}\");
+      println(\"{math::sin(0.0)} {math::cos(0.0)} {math::tan(0.0)} {math::atan2(1.0, 0.0)}\");
+    }
+    ";
+    assert_eq!(
+        run(code),
+        (
+            0,
+            "4 1024 2.5\n2 3 1 0\n0 1 0 1.5707963267949\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn conversions() {
+    let code = "
+    main(): int {
+      println(\"{0 - 42} {true} {int::to_f64(1) / int::to_f64(8)} {int::to_f64(0 - 1) / int::to_f64(0)}\");
+      assert(Option::is_some(string::to_int(\"+1\")) == false);
+      assert(Option::is_some(string::to_f64(\".5\")) == false);
+      assert_eq(Option::unwrap_or(string::to_int(\"x\"), 7), 7);
+      return f64::to_int(Option::unwrap(string::to_f64(\"-1.5\")) * int::to_f64(3))
+        + Option::unwrap(string::to_int(\"10\"));
+    }
+    ";
+    assert_eq!(run(code), (6, "-42 true 0.125 -inf\n".to_string()));
+    let code = "
+    main(): int = Option::unwrap(string::to_int(\"\"));
+    ";
+    let mut interpreter = load(code);
+    let err = interpreter.run_main().unwrap_err();
+    assert_eq!(err.exit_code(), Some(101));
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "unwrap of none\n:2:18\n"
+    );
+}
+
+#[test]
+fn template_formats_with_to_string() {
+    let code = "
+    class Point <: ToString {
+      x: int;
+      y: int;
+      ::new(x: int, y: int): Point = Point { x: x, y: y };
+      to_string(): string = \"({self.x}, {self.y})\";
+    }
+    quote(s: string): string = \"'{s}'\";
+    main(): void {
+      println(\"{Point::new(1, 2)} {int::to_f64(1) / int::to_f64(3)} \" + quote(\"s\"));
+    }
+    ";
+    assert_eq!(run(code), (0, "(1, 2) 0.333333333333333 's'\n".to_string()));
+}
+
+#[test]
+fn template_braces() {
+    let code = "
+    class Foo {
+      x: int;
+      ::show(n: int): string = \"{{{n}}} {Foo { x: n + 1 }.x} \\{ \\} }}\";
+    }
+    main(): void {
+      println(Foo::show(3));
+    }
+    ";
+    assert_eq!(run(code), (0, "{3} 4 { } }\n".to_string()));
+}
+
+#[test]
+fn equality_of_instances() {
+    let code = "
+    @derive(Eq)
+    class Point {
+      x: int;
+      name: string;
+      ::new(x: int, name: string): Point = Point { x: x, name: name };
+    }
+    class Loose <: Eq {
+      x: int;
+      ::new(x: int): Loose = Loose { x: x };
+      eq(other: Loose): bool = self.x / 10 == other.x / 10;
+    }
+    point(x: int, name: string): Point = Point::new(x, name);
+    main(): void {
+      assert(point(1, \"a\") == point(1, \"a\"));
+      assert(point(1, \"a\") != point(1, \"b\"));
+      assert(point(1, \"a\") != point(2, \"a\"));
+      assert(Loose::new(11) == Loose::new(19));
+      assert(Loose::new(1) != Loose::new(19));
+      assert(\"ab\" + \"c\" == \"abc\");
+    }
+    ";
+    assert_eq!(run(code), (0, "".to_string()));
+}
+
+#[test]
+fn sort_list() {
+    let code = "
+    class Version <: Ord {
+      major: int;
+      ::new(major: int): Version = Version { major: major };
+      compare(other: Version): int = self.major - other.major;
+    }
+    major(version: Version): int = version.major;
+    first(versions: List[Version]): int {
+      List::sort(versions);
+      return major(List::get(versions, 0));
+    }
+    sorted(words: List[string]): string {
+      List::sort(words);
+      return List::get(words, 0) + List::get(words, 1) + List::get(words, 2);
+    }
+    main(): void {
+      assert_eq(first([Version::new(3), Version::new(1), Version::new(2)]), 1);
+      assert_eq(sorted([\"c\", \"ab\", \"a\"]), \"aabc\");
+    }
+    ";
+    assert_eq!(run(code), (0, "".to_string()));
+}
+
+#[test]
+fn map_of_keys() {
+    let code = "
+    @derive(Eq, Hash)
+    class Point {
+      x: int;
+      y: int;
+      ::new(x: int, y: int): Point = Point { x: x, y: y };
+    }
+    fill(squares: Map[int, int], n: int): Map[int, int] {
+      if n > 0 {
+        Map::insert(squares, n, n * n);
+        return fill(squares, n - 1);
+      }
+      return squares;
+    }
+    points(names: Map[Point, string]): Map[Point, string] {
+      Map::insert(names, Point::new(1, 2), \"a\");
+      Map::insert(names, Point::new(2, 1), \"b\");
+      Map::insert(names, Point::new(1, 2), \"c\");
+      return names;
+    }
+    main(): void {
+      assert_eq(Option::unwrap(Map::get(fill(Map::new(), 12), 10)), 100);
+      assert_eq(Map::length(fill(Map::new(), 12)), 12);
+      assert(Map::contains(fill(Map::new(), 12), 13) == false);
+      assert_eq(Option::unwrap(Map::get(points(Map::new()), Point::new(1, 2))), \"c\");
+      assert_eq(Map::length(points(Map::new())), 2);
+    }
+    ";
+    assert_eq!(run(code), (0, "".to_string()));
+}
+
+#[test]
+fn for_loop() {
+    let code = "
+    class Range <: Iterator {
+      current: int;
+      end: int;
+      ::new(start: int, end: int): Range = Range { current: start, end: end };
+      next(): Option[int] {
+        if self.current < self.end {
+          self.current = self.current + 1;
+          return Option::some(self.current - 1);
+        }
+        return Option::none();
+      }
+    }
+    first_long(words: List[string]): string {
+      for w in words {
+        if string::length(w) > 1 {
+          return w;
+        }
+      }
+      return \"\";
+    }
+    main(): void {
+      for n in [1, 2, 3] {
+        print(\"{n} \");
+      }
+      for i in Range::new(5, 8) {
+        print(\"{i * 2} \");
+      }
+      print(first_long([\"a\", \"bc\", \"de\"]));
+    }
+    ";
+    assert_eq!(run(code), (0, "1 2 3 10 12 14 bc".to_string()));
+}
+
+#[test]
+fn env_args() {
+    let code = "
+    main(): void {
+      for arg in env::args() {
+        print(\"{arg} \");
+      }
+    }
+    ";
+    let mut interpreter = load(code).with_args(vec!["main.elz".to_string(), "a b".to_string()]);
+    interpreter.run_main().unwrap();
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "main.elz a b "
+    );
+}
+
+#[test]
+fn env_vars() {
+    let code = "
+    main(): void {
+      assert(Option::is_some(env::get(\"ELZ_INTERPRETER_UNSET\")) == false);
+      env::set(\"ELZ_INTERPRETER_VAR\", \"a\");
+      env::set(\"ELZ_INTERPRETER_VAR\", \"b\");
+      print(Option::unwrap(env::get(\"ELZ_INTERPRETER_VAR\")));
+    }
+    ";
+    assert_eq!(run(code), (0, "b".to_string()));
+}
+
+#[test]
+fn process_exit_stops_the_program() {
+    let code = "
+    stop(code: int): int {
+      process::exit(code);
+      return 0;
+    }
+    main(): int {
+      println(\"before\");
+      return stop(3);
+    }
+    ";
+    let mut interpreter = load(code);
+    let err = interpreter.run_main().unwrap_err();
+    assert_eq!(err.exit_code(), Some(3));
+    assert_eq!(
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+        "before\n"
+    );
+}
+
+#[test]
+fn string_builder() {
+    let code = "
+    build(builder: StringBuilder, name: string, n: int): string {
+      StringBuilder::append(builder, \"{name} has {n} \");
+      StringBuilder::append(builder, \"items\");
+      return StringBuilder::build(builder);
+    }
+    main(): void {
+      print(build(StringBuilder::new(), \"box\", 3));
+    }
+    ";
+    assert_eq!(run(code), (0, "box has 3 items".to_string()));
+}
+
+#[test]
+fn power() {
+    let code = "
+    power_of(base: int, exponent: int): int = base ** exponent;
+    main(): void {
+      print(\"{2 ** 3 ** 2} {2 ** 0 - 1} {power_of(2, 0 - 1)} {power_of(0 - 1, 3)}\");
+      print(\" {f64::to_int(int::to_f64(2) ** int::to_f64(10))}\");
+    }
+    ";
+    assert_eq!(run(code), (0, "512 0 1 -1 1024".to_string()));
+}
+
+#[test]
+fn trait_inheritance() {
+    let code = "
+    trait Named {
+      name(): string;
+      greet(): string = \"hi \" + self.name();
+    }
+    trait Shape <: Named {
+      area(): int;
+    }
+    class Square <: Shape {
+      side: int;
+      ::new(side: int): Square = Square { side: side };
+      name(): string = \"square\";
+      area(): int = self.side * self.side;
+    }
+    describe(s: Shape): string = \"{s.greet()} of {s.area()}\";
+    main(): void {
+      println(describe(Square::new(3)));
+    }
+    ";
+    assert_eq!(run(code), (0, "hi square of 9\n".to_string()));
+}
+
+// helpers, must put tests before this line
+fn load(code: &'static str) -> Interpreter<Vec<u8>> {
+    let mut parser = crate::parser::Parser::new("", code);
+    let mut program = parser
+        .parse_top_list(EOF)
+        .map_err(|err| {
+            panic!("{}", err);
+        })
+        .unwrap();
+    let mut prelude = crate::parser::parse_prelude();
+    prelude.top_list.append(&mut program);
+    derive_traits(&mut prelude.top_list);
+    CallerLocations::new(&[prelude.clone()]).insert(&mut prelude.top_list);
+    let mut interpreter = Interpreter::new(vec![]);
+    interpreter.load(&prelude.top_list).unwrap();
+    interpreter
+}
+
+/// run returns the exit code and output of `main`
+fn run(code: &'static str) -> (i32, String) {
+    let mut interpreter = load(code);
+    let code = interpreter.run_main().unwrap();
+    (
+        code,
+        String::from_utf8(interpreter.output().clone()).unwrap(),
+    )
+}
+
+fn run_err(code: &'static str) -> String {
+    load(code).run_main().unwrap_err().to_string()
+}
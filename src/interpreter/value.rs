@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+/// Value is the result of evaluating an expression at runtime
+#[derive(Clone, Debug)]
+pub enum Value {
+    Void,
+    /// integers of all integer types are evaluated as 64 bits
+    Int(i64),
+    F64(f64),
+    Bool(bool),
+    /// `_c_string`, the builtin content of prelude class `string`
+    CString(Rc<str>),
+    List(Rc<RefCell<Vec<Value>>>),
+    /// instance of class, shared by reference since it lives on heap in compiled code
+    Object(Rc<Object>),
+    /// top-level function or static method, e.g. `foo` or `Point::new`
+    Function(String),
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub class_name: String,
+    /// fields in the order of class definition
+    pub(crate) fields: RefCell<Vec<(String, Value)>>,
+}
+
+impl Object {
+    pub(crate) fn field(&self, name: &str) -> Value {
+        self.fields
+            .borrow()
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_else(|| unreachable!("`{}` has no field `{}`", self.class_name, name))
+    }
+    pub(crate) fn set_field(&self, name: &str, value: Value) {
+        let mut fields = self.fields.borrow_mut();
+        match fields.iter_mut().find(|(field_name, _)| field_name == name) {
+            Some((_, field)) => *field = value,
+            None => unreachable!("`{}` has no field `{}`", self.class_name, name),
+        }
+    }
+}
+
+impl Value {
+    /// string returns an instance of prelude class `string`, which is what string literal
+    /// evaluates to
+    pub(crate) fn string(s: &str) -> Value {
+        Value::Object(Rc::new(Object {
+            class_name: "string".to_string(),
+            fields: RefCell::new(vec![("value".to_string(), Value::CString(s.into()))]),
+        }))
+    }
+    /// text returns the content of an instance of prelude class `string`
+    pub(crate) fn text(&self) -> Rc<str> {
+        match self {
+            Value::Object(object) if object.class_name == "string" => match object.field("value") {
+                Value::CString(s) => s,
+                v => unreachable!("`string` holds non-C string `{}`", v),
+            },
+            v => unreachable!("`{}` is not a string", v),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Void => write!(f, "void"),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::F64(v) => write!(f, "{:?}", v),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::CString(s) => write!(f, "{:?}", s),
+            Value::List(values) => {
+                let values: Vec<String> = values.borrow().iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Value::Object(object) if object.class_name == "string" => {
+                write!(f, "{}", object.field("value"))
+            }
+            Value::Object(object) => {
+                let fields: Vec<String> = object
+                    .fields
+                    .borrow()
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+                write!(f, "{} {{ {} }}", object.class_name, fields.join(", "))
+            }
+            Value::Function(name) => write!(f, "<function {}>", name),
+        }
+    }
+}
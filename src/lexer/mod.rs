@@ -1,6 +1,9 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::sync::Arc;
 use strum_macros::Display;
 
-#[derive(Display, Clone, Debug, PartialEq)]
+#[derive(Display, Clone, Debug, PartialEq, Serialize)]
 pub enum TkType {
     #[strum(serialize = "<eof>")]
     EOF,
@@ -9,6 +12,8 @@ pub enum TkType {
     Identifier,
     #[strum(serialize = "<integer>")]
     Integer,
+    #[strum(serialize = "<float>")]
+    Float,
     #[strum(serialize = "<string>")]
     String,
     // keyword
@@ -26,6 +31,14 @@ pub enum TkType {
     If,
     #[strum(serialize = "else")]
     Else,
+    #[strum(serialize = "for")]
+    For,
+    #[strum(serialize = "in")]
+    In,
+    #[strum(serialize = "and")]
+    And,
+    #[strum(serialize = "or")]
+    Or,
     #[strum(serialize = "true")]
     True,
     #[strum(serialize = "false")]
@@ -37,6 +50,8 @@ pub enum TkType {
     Minus,
     #[strum(serialize = "*")]
     Multiple,
+    #[strum(serialize = "**")]
+    Power,
     #[strum(serialize = "/")]
     Divide,
     #[strum(serialize = ",")]
@@ -65,16 +80,35 @@ pub enum TkType {
     Dot,
     #[strum(serialize = "<:")]
     IsSubTypeOf,
+    #[strum(serialize = "==")]
+    EqualEqual,
+    #[strum(serialize = "!=")]
+    NotEqual,
+    #[strum(serialize = "<")]
+    Less,
+    #[strum(serialize = "<=")]
+    LessEqual,
+    #[strum(serialize = ">")]
+    Greater,
+    #[strum(serialize = ">=")]
+    GreaterEqual,
     #[strum(serialize = "@")]
     AtSign,
     // ignored
     #[strum(serialize = "<comment>")]
     Comment,
+    /// Invalid is a character no token starts with, or a string literal without the closing `"`,
+    /// the parser reports it
+    #[strum(serialize = "<invalid>")]
+    Invalid,
 }
 
-#[derive(Clone, Debug)]
+/// Location is where a token or an AST node is, `line` and `column` are where diagnostics point,
+/// `start..end` is the byte span of the whole text, e.g. `foo(1)` spans from `foo` to `)`
+#[derive(Clone, Debug, Serialize)]
 pub struct Location {
-    file_name: String,
+    /// file_name is shared by locations of the same file, so cloning a location doesn't allocate
+    file_name: Arc<str>,
     line: u32,
     column: u32,
     pub start: u32,
@@ -96,13 +130,37 @@ impl Location {
         end: u32,
     ) -> Location {
         Location {
-            file_name: file_name.to_string(),
+            file_name: file_name.to_string().into(),
             line,
             column,
             start,
             end,
         }
     }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+    /// to extends the span to the end of `end`, line and column are kept
+    pub fn to(&self, end: &Location) -> Location {
+        Location {
+            end: end.end.max(self.end),
+            ..self.clone()
+        }
+    }
+    /// tail is the span of the last `len` bytes, e.g. `bar` of `foo.bar` located at `bar`
+    pub fn tail(&self, len: u32) -> Location {
+        Location {
+            start: self.end.saturating_sub(len).max(self.start),
+            ..self.clone()
+        }
+    }
 }
 
 impl PartialEq for Location {
@@ -130,6 +188,22 @@ impl Token {
     pub fn value(&self) -> String {
         self.2.clone()
     }
+    /// text borrows the text of the token, `value` returns a copy
+    pub fn text(&self) -> &str {
+        &self.2
+    }
+}
+
+/// Token is serialized as `{"kind": "Identifier", "text": "x", "location": {...}}` for external
+/// tools
+impl Serialize for Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut token = serializer.serialize_struct("Token", 3)?;
+        token.serialize_field("kind", &self.1)?;
+        token.serialize_field("text", &self.2)?;
+        token.serialize_field("location", &self.0)?;
+        token.end()
+    }
 }
 
 impl std::fmt::Display for Token {
@@ -150,8 +224,10 @@ enum State {
 }
 
 struct Lexer {
-    file_name: String,
+    file_name: Arc<str>,
     code: Vec<char>,
+    /// byte_offsets are byte offsets of characters in `code`, and the length of the code at last
+    byte_offsets: Vec<u32>,
     tokens: Vec<Token>,
     state_fn: State,
     start: usize,
@@ -163,9 +239,18 @@ struct Lexer {
 
 impl Lexer {
     fn new<T: Into<String>>(file_name: T, code: T) -> Lexer {
+        let code: Vec<char> = code.into().chars().collect();
+        let mut byte_offsets = Vec::with_capacity(code.len() + 1);
+        let mut byte_offset = 0;
+        for c in &code {
+            byte_offsets.push(byte_offset);
+            byte_offset += c.len_utf8() as u32;
+        }
+        byte_offsets.push(byte_offset);
         Lexer {
-            file_name: file_name.into(),
-            code: code.into().chars().collect(),
+            file_name: file_name.into().into(),
+            code,
+            byte_offsets,
             tokens: vec![],
             state_fn: State::Fn(whitespace),
             start: 0,
@@ -191,13 +276,13 @@ impl Lexer {
     }
     fn new_token(&mut self, token_type: TkType, value: String) -> Token {
         Token(
-            Location::new(
-                self.file_name.clone(),
-                self.line,
-                self.pos,
-                self.start as u32,
-                self.offset as u32,
-            ),
+            Location {
+                file_name: self.file_name.clone(),
+                line: self.line,
+                column: self.pos,
+                start: self.byte_offsets[self.start],
+                end: self.byte_offsets[self.offset],
+            },
             token_type,
             value,
         )
@@ -214,6 +299,10 @@ impl Lexer {
             "trait" => self.new_token(TkType::Trait, s),
             "if" => self.new_token(TkType::If, s),
             "else" => self.new_token(TkType::Else, s),
+            "for" => self.new_token(TkType::For, s),
+            "in" => self.new_token(TkType::In, s),
+            "and" => self.new_token(TkType::And, s),
+            "or" => self.new_token(TkType::Or, s),
             _ => self.new_token(token_type.clone(), s),
         };
         match token_type {
@@ -226,7 +315,7 @@ impl Lexer {
 
 fn whitespace(lexer: &mut Lexer) -> State {
     while let Some(c) = lexer.peek() {
-        if c == ' ' || c == '\r' || c == '\n' {
+        if c == ' ' || c == '\t' || c == '\r' || c == '\n' {
             if c == '\n' {
                 lexer.next();
                 lexer.start = lexer.offset;
@@ -245,7 +334,32 @@ fn whitespace(lexer: &mut Lexer) -> State {
         Some(_c @ '0'..='9') => State::Fn(number),
         Some('=') => {
             lexer.next();
-            lexer.emit(TkType::Equal);
+            if lexer.peek() == Some('=') {
+                lexer.next();
+                lexer.emit(TkType::EqualEqual);
+            } else {
+                lexer.emit(TkType::Equal);
+            }
+            State::Fn(whitespace)
+        }
+        Some('!') => {
+            lexer.next();
+            if lexer.peek() == Some('=') {
+                lexer.next();
+                lexer.emit(TkType::NotEqual);
+            } else {
+                lexer.emit(TkType::Invalid);
+            }
+            State::Fn(whitespace)
+        }
+        Some('>') => {
+            lexer.next();
+            if lexer.peek() == Some('=') {
+                lexer.next();
+                lexer.emit(TkType::GreaterEqual);
+            } else {
+                lexer.emit(TkType::Greater);
+            }
             State::Fn(whitespace)
         }
         Some(',') => {
@@ -265,7 +379,12 @@ fn whitespace(lexer: &mut Lexer) -> State {
         }
         Some('*') => {
             lexer.next();
-            lexer.emit(TkType::Multiple);
+            if lexer.peek() == Some('*') {
+                lexer.next();
+                lexer.emit(TkType::Power);
+            } else {
+                lexer.emit(TkType::Multiple);
+            }
             State::Fn(whitespace)
         }
         Some('/') => {
@@ -321,8 +440,11 @@ fn whitespace(lexer: &mut Lexer) -> State {
             if lexer.peek() == Some(':') {
                 lexer.next();
                 lexer.emit(TkType::IsSubTypeOf);
+            } else if lexer.peek() == Some('=') {
+                lexer.next();
+                lexer.emit(TkType::LessEqual);
             } else {
-                unimplemented!("less than operator");
+                lexer.emit(TkType::Less);
             }
             State::Fn(whitespace)
         }
@@ -356,7 +478,9 @@ fn whitespace(lexer: &mut Lexer) -> State {
             if in_identifier_set(c) {
                 State::Fn(ident)
             } else {
-                unimplemented!("char: `{}`", c);
+                lexer.next();
+                lexer.emit(TkType::Invalid);
+                State::Fn(whitespace)
             }
         }
         None => State::EOF,
@@ -377,32 +501,78 @@ fn ident(lexer: &mut Lexer) -> State {
     State::Fn(whitespace)
 }
 
+/// string lexes a string literal, the rest of code without the closing `"` is invalid
 fn string(lexer: &mut Lexer) -> State {
-    while let Some(c) = lexer.next() {
-        if c == '\\' {
-            lexer.next();
-        }
-        if c == '"' {
-            break;
+    // skip the opening `"`
+    lexer.next();
+    loop {
+        match lexer.peek() {
+            Some('"') => {
+                lexer.next();
+                lexer.emit(TkType::String);
+                return State::Fn(whitespace);
+            }
+            Some('\\') => {
+                lexer.next();
+                if lexer.peek().is_some() {
+                    lexer.next();
+                }
+            }
+            Some(_) => {
+                lexer.next();
+            }
+            None => {
+                lexer.emit(TkType::Invalid);
+                return State::EOF;
+            }
         }
     }
-    lexer.next();
-    lexer.emit(TkType::String);
-    State::Fn(whitespace)
 }
 
+/// number lexes `1` as an integer and `1.5` as a float, the `.` of `1.to_string()` is a member
+/// access since no digit follows it
 fn number(lexer: &mut Lexer) -> State {
     while let Some(c) = lexer.next() {
         if !c.is_digit(10) {
             break;
         }
     }
-    lexer.emit(TkType::Integer);
+    let fraction = match lexer.code.get(lexer.offset + 1) {
+        Some(c) => lexer.peek() == Some('.') && c.is_ascii_digit(),
+        None => false,
+    };
+    if !fraction {
+        lexer.emit(TkType::Integer);
+        return State::Fn(whitespace);
+    }
+    lexer.next();
+    while let Some(c) = lexer.next() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+    }
+    lexer.emit(TkType::Float);
     State::Fn(whitespace)
 }
 
+/// lex returns all tokens of source at once
 pub fn lex<T: Into<String>>(file_name: T, source: T) -> Vec<Token> {
+    run(Lexer::new(file_name, source))
+}
+
+/// lex_at lexes `source` found at `at` of a file, e.g. an expression interpolated in a string
+/// template, so its tokens are located in the file
+pub fn lex_at<T: Into<String>>(file_name: T, source: T, at: &Location) -> Vec<Token> {
     let mut lexer = Lexer::new(file_name, source);
+    lexer.line = at.line;
+    lexer.pos = at.column;
+    for byte_offset in &mut lexer.byte_offsets {
+        *byte_offset += at.start;
+    }
+    run(lexer)
+}
+
+fn run(mut lexer: Lexer) -> Vec<Token> {
     while let State::Fn(f) = lexer.state_fn {
         lexer.state_fn = f(&mut lexer);
     }
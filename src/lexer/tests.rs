@@ -3,7 +3,7 @@ use super::*;
 
 #[test]
 fn test_symbols() {
-    let code = "+ - * / , = ( ) [ ] { } : :: ; . <: @";
+    let code = "+ - * / ** , = ( ) [ ] { } : :: ; . <: @ == != < <= > >=";
 
     let tokens = lex("", code);
     let tk_types: Vec<_> = tokens.iter().map(|tok| tok.tk_type()).collect();
@@ -15,6 +15,7 @@ fn test_symbols() {
             &Minus,
             &Multiple,
             &Divide,
+            &Power,
             &Comma,
             &Equal,
             &OpenParen,
@@ -29,6 +30,12 @@ fn test_symbols() {
             &Dot,
             &IsSubTypeOf,
             &AtSign,
+            &EqualEqual,
+            &NotEqual,
+            &Less,
+            &LessEqual,
+            &Greater,
+            &GreaterEqual,
             &EOF,
         ]
     )
@@ -36,14 +43,14 @@ fn test_symbols() {
 
 #[test]
 fn test_keywords() {
-    let code = "module import return class trait true false if else";
+    let code = "module import return class trait true false if else and or";
 
     let tokens = lex("", code);
     let tk_types: Vec<_> = tokens.iter().map(|tok| tok.tk_type()).collect();
     use TkType::*;
     assert_eq!(
         tk_types,
-        vec![&Module, &Import, &Return, &Class, &Trait, &True, &False, &If, &Else, &EOF]
+        vec![&Module, &Import, &Return, &Class, &Trait, &True, &False, &If, &Else, &And, &Or, &EOF]
     )
 }
 
@@ -77,6 +84,21 @@ fn get_number_tokens() {
     );
 }
 
+#[test]
+fn get_float_tokens() {
+    let ts = lex("", "1.5 2.to_string");
+    assert_eq!(
+        ts,
+        vec![
+            Token(Location::from(1, 0), Float, "1.5".to_string()),
+            Token(Location::from(1, 4), Integer, "2".to_string()),
+            Token(Location::from(1, 5), Dot, ".".to_string()),
+            Token(Location::from(1, 6), Identifier, "to_string".to_string()),
+            Token(Location::from(1, 15), EOF, "".to_string()),
+        ]
+    );
+}
+
 #[test]
 fn get_ident_tokens() {
     let ts = lex("", " abc6");
@@ -112,3 +134,73 @@ fn comment_would_be_discard() {
         ]
     )
 }
+
+#[test]
+fn serialize_token_to_json() {
+    let tokens = lex("", "x");
+    assert_eq!(
+        serde_json::to_string(&tokens[0]).unwrap(),
+        r#"{"kind":"Identifier","text":"x","location":{"file_name":"","line":1,"column":0,"start":0,"end":1}}"#
+    );
+}
+
+#[test]
+fn locations_are_byte_offsets() {
+    let tokens = lex("", "\"été\" x");
+    assert_eq!(
+        (tokens[0].location().start, tokens[0].location().end),
+        (0, 7)
+    );
+    assert_eq!(
+        (tokens[1].location().start, tokens[1].location().end),
+        (8, 9)
+    );
+    // column counts characters
+    assert_eq!(tokens[1].location().column(), 6);
+}
+
+#[test]
+fn locations_share_file_name() {
+    let tokens = lex("main.elz", "x + y");
+    let location = tokens[2].location();
+    assert!(Arc::ptr_eq(&tokens[0].0.file_name, &location.file_name));
+    assert_eq!(location.file_name(), "main.elz");
+}
+
+#[test]
+fn tab_is_whitespace() {
+    let tk_types: Vec<TkType> = lex("", "x\t+\ty")
+        .iter()
+        .map(|tok| tok.tk_type().clone())
+        .collect();
+    assert_eq!(tk_types, vec![Identifier, Plus, Identifier, EOF]);
+}
+
+#[test]
+fn unknown_characters_are_invalid() {
+    let tokens = lex("", "x # ! y");
+    let tokens: Vec<(&TkType, &str)> = tokens
+        .iter()
+        .map(|tok| (tok.tk_type(), tok.text()))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (&Identifier, "x"),
+            (&Invalid, "#"),
+            (&Invalid, "!"),
+            (&Identifier, "y"),
+            (&EOF, ""),
+        ]
+    );
+}
+
+#[test]
+fn unclosed_string_is_invalid() {
+    for code in ["\"abc", "\"abc\\", "\"abc\\\""] {
+        let tokens = lex("", code);
+        assert_eq!(tokens[0].tk_type(), &Invalid, "{}", code);
+        assert_eq!(tokens[0].text(), code);
+        assert_eq!(tokens[1].tk_type(), &EOF);
+    }
+}
@@ -1,8 +1,19 @@
 pub mod ast;
 pub mod cmd;
 pub mod codegen;
+pub mod compilation;
 pub mod diagnostic;
+pub mod ice;
+pub mod index;
+pub mod interpreter;
 pub mod lexer;
+pub mod mangle;
+pub mod package;
 pub mod parser;
+pub mod playground;
 pub mod prelude;
+pub mod repl;
 pub mod semantic;
+pub mod timing;
+
+pub use compilation::{compile, CompilationOutput, Diagnostic, Diagnostics, Options};
@@ -1,7 +1,14 @@
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use elz::cmd;
+use elz::cmd::compile::Emit;
+use elz::codegen::optimize::OptLevel;
+use elz::codegen::target::{Target, UnknownTarget};
+use elz::diagnostic::MessageFormat;
+use elz::semantic::{Lints, Severity, LINT_NAMES};
+use elz::timing::{TimingFormat, Timings};
 
 fn main() {
+    elz::ice::install_panic_hook();
     let matches = App::new("elz")
         .author("Danny Lin <dannypsnl@gmail.com>")
         .subcommand(
@@ -9,9 +16,283 @@ fn main() {
                 .about("compile input file")
                 .arg(
                     Arg::with_name("INPUT")
-                        .help("input file to compile")
+                        .help("input file to compile, `-` reads standard input")
                         .required(true)
                         .min_values(1),
+                )
+                .args(&lint_args())
+                .arg(opt_level_arg())
+                .arg(debug_info_arg())
+                .arg(verify_arg())
+                .arg(
+                    Arg::with_name("eliminate-dead-functions")
+                        .long("eliminate-dead-functions")
+                        .help("drop functions which can't be reached from `main` from the output"),
+                )
+                .arg(
+                    Arg::with_name("emit")
+                        .long("emit")
+                        .takes_value(true)
+                        .possible_values(&["llvm-ir", "obj", "bc"])
+                        .default_value("llvm-ir")
+                        .help("kind of output, `obj` and `bc` are built through LLVM"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("file to write `obj` or `bc` to, defaults to the input with its extension"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .takes_value(true)
+                        .help("generate code for the target, e.g. `wasm32`, defaults to host"),
+                )
+                .arg(
+                    Arg::with_name("message-format")
+                        .long("message-format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .help("format of diagnostics, `json` writes an object per line for tools"),
+                )
+                .arg(
+                    Arg::with_name("timings")
+                        .long("timings")
+                        .takes_value(true)
+                        .min_values(0)
+                        .require_equals(true)
+                        .possible_values(&["table", "json"])
+                        .help("report time and memory of each phase to stderr, as `table` by default"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::check::CMD_NAME)
+                .about("report errors and warnings of input file without generating code")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to check, `-` is standard input, defaults to the package")
+                        .min_values(1),
+                )
+                .args(&lint_args())
+                .arg(
+                    Arg::with_name("message-format")
+                        .long("message-format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .help("format of diagnostics, `json` writes an object per line for tools"),
+                )
+                .arg(
+                    Arg::with_name("timings")
+                        .long("timings")
+                        .takes_value(true)
+                        .min_values(0)
+                        .require_equals(true)
+                        .possible_values(&["table", "json"])
+                        .help("report time and memory of each phase to stderr, as `table` by default"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::build::CMD_NAME)
+                .about("compile input file or the package of `elz.toml` into an executable")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to build, `-` is standard input, defaults to the package")
+                        .min_values(1),
+                )
+                .args(&lint_args())
+                .arg(opt_level_arg())
+                .arg(debug_info_arg())
+                .arg(verify_arg())
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("path of the executable, defaults to the input without its extension"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .takes_value(true)
+                        .help("generate code for the target, e.g. `wasm32`, defaults to host"),
+                )
+                .arg(
+                    Arg::with_name("message-format")
+                        .long("message-format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .help("format of diagnostics, `json` writes an object per line for tools"),
+                )
+                .arg(
+                    Arg::with_name("timings")
+                        .long("timings")
+                        .takes_value(true)
+                        .min_values(0)
+                        .require_equals(true)
+                        .possible_values(&["table", "json"])
+                        .help("report time and memory of each phase to stderr, as `table` by default"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::run::CMD_NAME)
+                .about("check input file or the package and execute `main` right away")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to run, defaults to sources of the package")
+                        .min_values(1),
+                )
+                .arg(program_args_arg())
+                .args(&lint_args())
+                .arg(opt_level_arg())
+                .arg(
+                    Arg::with_name("coverage")
+                        .long("coverage")
+                        .help("count lines run and report them to stderr at exit"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::eval::CMD_NAME)
+                .about("check input file and interpret its `main`, no LLVM needed")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to interpret")
+                        .required(true)
+                        .min_values(1),
+                )
+                .arg(program_args_arg())
+                .args(&lint_args()),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::test::CMD_NAME)
+                .about("check input file or the package and run its functions tagged `@test`")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to test, defaults to sources of the package")
+                        .min_values(1),
+                )
+                .args(&lint_args())
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .takes_value(true)
+                        .help("only run tests whose name contains the filter"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::bench::CMD_NAME)
+                .about("check input file or the package and time its functions tagged `@bench`")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to benchmark, defaults to sources of the package")
+                        .min_values(1),
+                )
+                .args(&lint_args())
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .takes_value(true)
+                        .help("only run benchmarks whose name contains the filter"),
+                )
+                .arg(
+                    Arg::with_name("iterations")
+                        .long("iterations")
+                        .takes_value(true)
+                        .default_value("100")
+                        .validator(|n| match n.parse::<u32>() {
+                            Ok(n) if n > 0 => Ok(()),
+                            _ => Err("iterations must be a positive integer".to_string()),
+                        })
+                        .help("number of timed iterations of each benchmark"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::repl::CMD_NAME)
+                .about("evaluate definitions and expressions interactively"),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::ast::CMD_NAME)
+                .about("show parsed syntax tree of input file")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to show, `-` reads standard input")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["debug", "json"])
+                        .default_value("debug")
+                        .help("output format, `json` is for external tools"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::tokens::CMD_NAME)
+                .about("show tokens of input file")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to show, `-` reads standard input")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["debug", "json"])
+                        .default_value("debug")
+                        .help("output format, `json` is for external tools"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::demangle::CMD_NAME)
+                .about("show paths of mangled symbols, e.g. in a backtrace")
+                .arg(
+                    Arg::with_name("SYMBOL")
+                        .help("symbols to demangle, defaults to every line of standard input")
+                        .min_values(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::graph::CMD_NAME)
+                .about("show import graph of input files or the package")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input files to show, defaults to the package")
+                        .min_values(1),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["dot", "json"])
+                        .default_value("dot")
+                        .help("output format, `dot` is for GraphViz, `json` is for external tools"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(cmd::ir::CMD_NAME)
+                .about("show intermediate representation of input file")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("input file to show")
+                        .required(true)
+                        .min_values(1),
+                )
+                .arg(
+                    Arg::with_name("graph")
+                        .long("graph")
+                        .help("emit control-flow graph of each function in GraphViz dot format"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .takes_value(true)
+                        .help("generate code for the target, e.g. `wasm32`, defaults to host"),
                 ),
         )
         .subcommand(
@@ -22,21 +303,357 @@ fn main() {
                         .help("input directories or files to format")
                         .required(true)
                         .min_values(1),
+                )
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("report unformatted files without modifying them"),
                 ),
         )
         .get_matches();
 
     if let Some(compile_args) = matches.subcommand_matches(cmd::compile::CMD_NAME) {
         let files: Vec<_> = compile_args.values_of("INPUT").unwrap().collect();
-        match cmd::compile::compile(files) {
+        let target = match target_of(compile_args) {
+            Ok(target) => target,
+            Err(err) => return println!("{}", err),
+        };
+        let lints = match lints_of(compile_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        let mut timings = timings_of(compile_args);
+        let result = cmd::compile::compile(
+            files,
+            &lints,
+            compile_args.is_present("eliminate-dead-functions"),
+            opt_level_of(compile_args),
+            compile_args.is_present("debug-info"),
+            compile_args.is_present("verify"),
+            target,
+            Emit::from_name(compile_args.value_of("emit").unwrap()).unwrap(),
+            compile_args.value_of("output"),
+            message_format_of(compile_args),
+            &mut timings,
+        );
+        timings.report();
+        match result {
+            Ok(..) => (),
+            Err(..) => {
+                println!("compile failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(check_args) = matches.subcommand_matches(cmd::check::CMD_NAME) {
+        let files: Vec<_> = check_args
+            .values_of("INPUT")
+            .into_iter()
+            .flatten()
+            .collect();
+        let lints = match lints_of(check_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        let mut timings = timings_of(check_args);
+        let result = cmd::check::check(files, &lints, message_format_of(check_args), &mut timings);
+        timings.report();
+        match result {
+            Ok(..) => (),
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(build_args) = matches.subcommand_matches(cmd::build::CMD_NAME) {
+        let files: Vec<_> = build_args
+            .values_of("INPUT")
+            .into_iter()
+            .flatten()
+            .collect();
+        let target = match target_of(build_args) {
+            Ok(target) => target,
+            Err(err) => return println!("{}", err),
+        };
+        let lints = match lints_of(build_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        let mut timings = timings_of(build_args);
+        let result = cmd::build::build(
+            files,
+            &lints,
+            opt_level_of(build_args),
+            build_args.is_present("debug-info"),
+            build_args.is_present("verify"),
+            target,
+            build_args.value_of("output"),
+            message_format_of(build_args),
+            &mut timings,
+        );
+        timings.report();
+        match result {
+            Ok(..) => (),
+            Err(..) => {
+                println!("build failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(run_args) = matches.subcommand_matches(cmd::run::CMD_NAME) {
+        let files: Vec<_> = run_args.values_of("INPUT").into_iter().flatten().collect();
+        let lints = match lints_of(run_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        let args = run_args.values_of("ARGS").into_iter().flatten().collect();
+        match cmd::run::run(
+            files,
+            args,
+            &lints,
+            opt_level_of(run_args),
+            run_args.is_present("coverage"),
+        ) {
+            Ok(code) => std::process::exit(code),
+            Err(..) => {
+                println!("run failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(eval_args) = matches.subcommand_matches(cmd::eval::CMD_NAME) {
+        let files: Vec<_> = eval_args.values_of("INPUT").unwrap().collect();
+        let lints = match lints_of(eval_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        let args = eval_args.values_of("ARGS").into_iter().flatten().collect();
+        match cmd::eval::eval(files, args, &lints) {
+            Ok(code) => std::process::exit(code),
+            Err(..) => {
+                println!("eval failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(test_args) = matches.subcommand_matches(cmd::test::CMD_NAME) {
+        let files: Vec<_> = test_args.values_of("INPUT").into_iter().flatten().collect();
+        let lints = match lints_of(test_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        match cmd::test::test(files, test_args.value_of("filter"), &lints) {
+            Ok(summary) if summary.failed == 0 => (),
+            Ok(..) => std::process::exit(1),
+            Err(..) => {
+                println!("test failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(bench_args) = matches.subcommand_matches(cmd::bench::CMD_NAME) {
+        let files: Vec<_> = bench_args
+            .values_of("INPUT")
+            .into_iter()
+            .flatten()
+            .collect();
+        let lints = match lints_of(bench_args) {
+            Ok(lints) => lints,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        };
+        // validated by clap
+        let iterations = bench_args.value_of("iterations").unwrap().parse().unwrap();
+        match cmd::bench::bench(files, bench_args.value_of("filter"), iterations, &lints) {
+            Ok(true) => (),
+            Ok(false) => std::process::exit(1),
+            Err(..) => {
+                println!("bench failed");
+                std::process::exit(1)
+            }
+        }
+    } else if matches.subcommand_matches(cmd::repl::CMD_NAME).is_some() {
+        if let Err(err) = cmd::repl::repl() {
+            println!("{}", err);
+        }
+    } else if let Some(ast_args) = matches.subcommand_matches(cmd::ast::CMD_NAME) {
+        let format = cmd::ast::Format::from_name(ast_args.value_of("format").unwrap()).unwrap();
+        match cmd::ast::ast(ast_args.value_of("INPUT").unwrap(), format) {
+            Ok(..) => (),
+            Err(..) => println!("ast failed"),
+        }
+    } else if let Some(tokens_args) = matches.subcommand_matches(cmd::tokens::CMD_NAME) {
+        let format = cmd::ast::Format::from_name(tokens_args.value_of("format").unwrap()).unwrap();
+        match cmd::tokens::tokens(tokens_args.value_of("INPUT").unwrap(), format) {
+            Ok(..) => (),
+            Err(err) => println!("{}", err),
+        }
+    } else if let Some(demangle_args) = matches.subcommand_matches(cmd::demangle::CMD_NAME) {
+        let symbols: Vec<_> = demangle_args
+            .values_of("SYMBOL")
+            .into_iter()
+            .flatten()
+            .collect();
+        if let Err(err) = cmd::demangle::demangle(symbols) {
+            println!("{}", err);
+            std::process::exit(1)
+        }
+    } else if let Some(graph_args) = matches.subcommand_matches(cmd::graph::CMD_NAME) {
+        let files: Vec<_> = graph_args
+            .values_of("INPUT")
+            .into_iter()
+            .flatten()
+            .collect();
+        let format = cmd::graph::Format::from_name(graph_args.value_of("format").unwrap()).unwrap();
+        match cmd::graph::graph(files, format) {
+            Ok(..) => (),
+            Err(..) => {
+                println!("graph failed");
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(ir_args) = matches.subcommand_matches(cmd::ir::CMD_NAME) {
+        let files: Vec<_> = ir_args.values_of("INPUT").unwrap().collect();
+        let target = match target_of(ir_args) {
+            Ok(target) => target,
+            Err(err) => return println!("{}", err),
+        };
+        match cmd::ir::ir(files, ir_args.is_present("graph"), target) {
             Ok(..) => (),
             Err(..) => println!("compile failed"),
         }
     } else if let Some(compile_args) = matches.subcommand_matches(cmd::fmt::CMD_NAME) {
         let files: Vec<_> = compile_args.values_of("INPUT").unwrap().collect();
-        match cmd::fmt::format(files) {
+        match cmd::fmt::format(files, compile_args.is_present("check")) {
             Ok(..) => (),
-            Err(..) => println!("format failed"),
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+/// target_of returns the target given by `--target`, or the host
+/// lint_args are flags to configure lints, shared by commands checking the program
+fn lint_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("allow")
+            .short("A")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("don't report the lint, e.g. `-A dead_function`"),
+        Arg::with_name("warn")
+            .short("W")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("report the lint as warning, e.g. `-W unused_variable`"),
+        Arg::with_name("deny")
+            .short("D")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("report the lint as error, e.g. `-D unused_parameter`"),
+        Arg::with_name("deny-warnings")
+            .long("deny-warnings")
+            .help("report all warnings as errors"),
+    ]
+}
+
+/// lints_of returns lints configured by `-A`, `-W`, `-D` and `--deny-warnings`
+fn lints_of(args: &ArgMatches) -> Result<Lints, String> {
+    let mut lints = Lints::new();
+    let levels = [
+        ("allow", Severity::Allow),
+        ("warn", Severity::Warning),
+        ("deny", Severity::Error),
+    ];
+    for (arg, severity) in levels.iter() {
+        for lint in args.values_of(arg).into_iter().flatten() {
+            if !lints.set(lint, *severity) {
+                return Err(format!(
+                    "unknown lint `{}`, available lints are: {}",
+                    lint,
+                    LINT_NAMES.join(", ")
+                ));
+            }
         }
     }
+    if args.is_present("deny-warnings") {
+        lints.deny_warnings();
+    }
+    Ok(lints)
+}
+
+/// opt_level_arg is `-O`, shared by commands generating code
+fn opt_level_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("opt-level")
+        .short("O")
+        .takes_value(true)
+        .possible_values(&["0", "1", "2"])
+        .default_value("0")
+        .help("optimization level, `1` folds constants and removes dead code, `2` also inlines")
+}
+
+/// debug_info_arg is `-g`, shared by commands generating code for debuggers
+fn debug_info_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("debug-info")
+        .short("g")
+        .help("emit debug info, so the program can be stepped through in gdb or lldb")
+}
+
+/// verify_arg is `--verify`, shared by commands generating code
+fn verify_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("verify")
+        .long("verify")
+        .help("check the generated IR is well formed, a problem is a bug of elz")
+}
+
+/// program_args_arg is arguments after `--`, shared by commands running the program
+fn program_args_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ARGS")
+        .last(true)
+        .multiple(true)
+        .help("arguments of the program, e.g. `elz run main.elz -- a b`")
+}
+
+fn opt_level_of(args: &ArgMatches) -> OptLevel {
+    OptLevel::from_name(args.value_of("opt-level").unwrap()).unwrap()
+}
+
+fn message_format_of(args: &ArgMatches) -> MessageFormat {
+    MessageFormat::from_name(args.value_of("message-format").unwrap()).unwrap()
+}
+
+/// timings_of returns timings enabled by `--timings`, `--timings=json` reports them as JSON
+fn timings_of(args: &ArgMatches) -> Timings {
+    if !args.is_present("timings") {
+        return Timings::disabled();
+    }
+    let format = args.value_of("timings").unwrap_or("table");
+    Timings::enabled(TimingFormat::from_name(format).unwrap())
+}
+
+fn target_of(args: &ArgMatches) -> Result<Target, UnknownTarget> {
+    match args.value_of("target") {
+        Some(name) => Target::from_name(name),
+        None => Ok(Target::host()),
+    }
 }
@@ -0,0 +1,209 @@
+//! mangle names items in symbols of object files. A symbol is `_E`, then every segment of the path
+//! of the item as its length and name, with type arguments of the segment between `I` and `E`,
+//! then `E`, e.g. `main::Point::new` is `_E4main5Point3newE`, and `prelude::List[int]::push` is
+//! `_E7prelude4ListI3intE4pushE`, so segments never collide with each other whatever they contain
+use std::fmt;
+use thiserror::Error;
+
+/// SEPARATOR separates segments of a path in code, e.g. `Point::new`
+pub const SEPARATOR: &str = "::";
+/// PREFIX starts every mangled symbol
+const PREFIX: &str = "_E";
+
+/// method_path returns the path of a method in code, e.g. `Point::new`
+pub fn method_path(class_name: &str, method: &str) -> String {
+    format!("{}{}{}", class_name, SEPARATOR, method)
+}
+
+/// Segment is a name in a path with its type arguments, e.g. `List[int]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    pub type_args: Vec<Segment>,
+}
+
+impl Segment {
+    pub fn new<T: ToString>(name: T) -> Segment {
+        Segment {
+            name: name.to_string(),
+            type_args: vec![],
+        }
+    }
+    pub fn with_type_args(mut self, type_args: Vec<Segment>) -> Segment {
+        self.type_args = type_args;
+        self
+    }
+    fn mangle_into(&self, symbol: &mut String) {
+        symbol.push_str(&self.name.len().to_string());
+        symbol.push_str(&self.name);
+        if !self.type_args.is_empty() {
+            symbol.push('I');
+            for arg in &self.type_args {
+                arg.mangle_into(symbol);
+            }
+            symbol.push('E');
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.type_args.is_empty() {
+            let args: Vec<String> = self.type_args.iter().map(|arg| arg.to_string()).collect();
+            write!(f, "[{}]", args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Symbol is the path of an item, e.g. `main::Point::new`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("`{symbol}` is not a symbol mangled by elz")]
+pub struct DemangleError {
+    symbol: String,
+}
+
+impl Symbol {
+    pub fn new(segments: Vec<Segment>) -> Symbol {
+        Symbol { segments }
+    }
+    /// method returns the symbol of a method of the class in `module`, a class out of modules,
+    /// e.g. in a top list parsed alone, has no module segment
+    pub fn method(module: Option<&str>, class_name: &str, method: &str) -> Symbol {
+        let segments = module
+            .into_iter()
+            .chain([class_name, method])
+            .map(Segment::new)
+            .collect();
+        Symbol::new(segments)
+    }
+    pub fn mangle(&self) -> String {
+        let mut symbol = PREFIX.to_string();
+        for segment in &self.segments {
+            segment.mangle_into(&mut symbol);
+        }
+        symbol.push('E');
+        symbol
+    }
+    /// demangle parses a whole symbol made by `mangle`
+    pub fn demangle(symbol: &str) -> Result<Symbol, DemangleError> {
+        match Demangler::new(symbol).symbol() {
+            Some((parsed, len)) if len == symbol.len() => Ok(parsed),
+            _ => Err(DemangleError {
+                symbol: symbol.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: Vec<String> = self.segments.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", segments.join(SEPARATOR))
+    }
+}
+
+/// demangle_text replaces symbols in the text with their paths, e.g. in frames of a backtrace,
+/// the rest of the text is kept
+pub fn demangle_text(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PREFIX) {
+        let (before, from) = rest.split_at(start);
+        result.push_str(before);
+        // a symbol is a whole word, e.g. not the tail of `foo_E4mainE`
+        let in_word = before.chars().next_back().is_some_and(is_symbol_char);
+        let symbol = match Demangler::new(from).symbol() {
+            Some((symbol, len)) if !in_word && !from[len..].starts_with(is_symbol_char) => {
+                Some((symbol, len))
+            }
+            _ => None,
+        };
+        match symbol {
+            Some((symbol, len)) => {
+                result.push_str(&symbol.to_string());
+                rest = &from[len..];
+            }
+            None => {
+                result.push_str(PREFIX);
+                rest = &from[PREFIX.len()..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// is_symbol_char is true for characters LLVM allows in names without quotes
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.' | '-')
+}
+
+/// Demangler parses a symbol from the start of the text
+struct Demangler<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Demangler<'a> {
+    fn new(text: &'a str) -> Demangler<'a> {
+        Demangler { text, offset: 0 }
+    }
+    /// symbol returns the symbol and the length of text it takes
+    fn symbol(mut self) -> Option<(Symbol, usize)> {
+        if !self.text.starts_with(PREFIX) {
+            return None;
+        }
+        self.offset = PREFIX.len();
+        let segments = self.segments()?;
+        if segments.is_empty() {
+            return None;
+        }
+        Some((Symbol::new(segments), self.offset))
+    }
+    /// segments parses segments until the `E` ending them
+    fn segments(&mut self) -> Option<Vec<Segment>> {
+        let mut segments = vec![];
+        loop {
+            match self.peek()? {
+                b'E' => {
+                    self.offset += 1;
+                    return Some(segments);
+                }
+                b'0'..=b'9' => segments.push(self.segment()?),
+                _ => return None,
+            }
+        }
+    }
+    fn segment(&mut self) -> Option<Segment> {
+        let digits = self.text[self.offset..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        let len: usize = self.text[self.offset..self.offset + digits].parse().ok()?;
+        let start = self.offset + digits;
+        let name = self.text.get(start..start.checked_add(len)?)?;
+        if name.is_empty() {
+            return None;
+        }
+        self.offset = start + len;
+        let mut segment = Segment::new(name);
+        if self.peek() == Some(b'I') {
+            self.offset += 1;
+            segment.type_args = self.segments()?;
+        }
+        Some(segment)
+    }
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.offset).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn symbols_are_mangled_and_demangled() {
+    let symbol = Symbol::method(Some("main"), "Point", "new");
+    assert_eq!(symbol.mangle(), "_E4main5Point3newE");
+    assert_eq!(Symbol::demangle("_E4main5Point3newE"), Ok(symbol.clone()));
+    assert_eq!(symbol.to_string(), "main::Point::new");
+
+    let generic = Symbol::new(vec![
+        Segment::new("prelude"),
+        Segment::new("List").with_type_args(vec![
+            Segment::new("List").with_type_args(vec![Segment::new("int")])
+        ]),
+        Segment::new("push"),
+    ]);
+    assert_eq!(generic.mangle(), "_E7prelude4ListI4ListI3intEE4pushE");
+    assert_eq!(Symbol::demangle(&generic.mangle()), Ok(generic.clone()));
+    assert_eq!(generic.to_string(), "prelude::List[List[int]]::push");
+}
+
+#[test]
+fn segments_do_not_collide() {
+    let a = Symbol::method(None, "a_b", "c").mangle();
+    let b = Symbol::method(None, "a", "b_c").mangle();
+    assert_ne!(a, b);
+    assert_eq!(Symbol::demangle(&a).unwrap().to_string(), "a_b::c");
+}
+
+#[test]
+fn other_names_are_not_demangled() {
+    for name in [
+        "main",
+        "_E",
+        "_EE",
+        "_E3fo",
+        "_E4mainEx",
+        "_E0E",
+        "_E99fooE",
+    ] {
+        assert!(Symbol::demangle(name).is_err(), "{}", name);
+    }
+}
+
+#[test]
+fn symbols_in_text_are_demangled() {
+    let backtrace = "#0 0x1000 in _E7prelude6string3newE ()\n#1 0x2000 in main ()\n_Ex foo_E4mainE";
+    assert_eq!(
+        demangle_text(backtrace),
+        "#0 0x1000 in prelude::string::new ()\n#1 0x2000 in main ()\n_Ex foo_E4mainE"
+    );
+}
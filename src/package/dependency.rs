@@ -0,0 +1,195 @@
+use super::error::{PackageError, Result};
+use super::lock::{LockedDependency, Lockfile};
+use super::{Dependency, Package, LOCK_NAME, MANIFEST_NAME};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Resolver finds the packages of dependencies, including dependencies of dependencies
+pub(super) struct Resolver<'a> {
+    cache_dir: &'a Path,
+    lockfile: Lockfile,
+    /// origin of each resolved dependency, the same name must come from the same origin
+    origins: BTreeMap<String, Origin>,
+    packages: BTreeMap<String, Package>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Origin {
+    Path(PathBuf),
+    Git { url: String, rev: Option<String> },
+}
+
+impl<'a> Resolver<'a> {
+    pub(super) fn new(cache_dir: &'a Path) -> Resolver<'a> {
+        Resolver {
+            cache_dir,
+            lockfile: Lockfile::default(),
+            origins: BTreeMap::new(),
+            packages: BTreeMap::new(),
+        }
+    }
+
+    /// resolve returns packages of all dependencies of `package` by their names, and updates the
+    /// lockfile of `package`
+    pub(super) fn resolve(mut self, package: &Package) -> Result<BTreeMap<String, Package>> {
+        let lock_path = package.root.join(LOCK_NAME);
+        let locked = Lockfile::read(&lock_path)?;
+        self.resolve_dependencies(package, &locked)?;
+        self.lockfile.write(&lock_path)?;
+        Ok(self.packages)
+    }
+
+    fn resolve_dependencies(&mut self, package: &Package, locked: &Lockfile) -> Result<()> {
+        let manifest_path = package.root.join(MANIFEST_NAME);
+        for (name, dependency) in &package.manifest.dependencies {
+            // the name is a directory name in the cache, so `../` can't escape it
+            if !is_identifier(name) {
+                return Err(PackageError::invalid_dependency_name(&manifest_path, name));
+            }
+            let origin = match dependency {
+                Dependency {
+                    path: Some(path),
+                    git: None,
+                    rev: None,
+                } => {
+                    let path = package.root.join(path);
+                    Origin::Path(
+                        path.canonicalize()
+                            .map_err(|err| PackageError::io(&path, err))?,
+                    )
+                }
+                Dependency {
+                    path: None,
+                    git: Some(url),
+                    rev,
+                } => {
+                    // manifests of dependencies are read too, values passed to git mustn't be
+                    // taken as its options
+                    if url.starts_with('-') {
+                        return Err(PackageError::option_like_git_value(
+                            &manifest_path,
+                            name,
+                            "git",
+                        ));
+                    }
+                    if rev.as_ref().is_some_and(|rev| rev.starts_with('-')) {
+                        return Err(PackageError::option_like_git_value(
+                            &manifest_path,
+                            name,
+                            "rev",
+                        ));
+                    }
+                    Origin::Git {
+                        url: url.clone(),
+                        rev: rev.clone(),
+                    }
+                }
+                _ => return Err(PackageError::invalid_dependency(&manifest_path, name)),
+            };
+            match self.origins.get(name) {
+                Some(resolved) if *resolved == origin => continue,
+                Some(..) => return Err(PackageError::conflicting_dependency(&manifest_path, name)),
+                None => (),
+            }
+            let root = match &origin {
+                Origin::Path(path) => path.clone(),
+                Origin::Git { url, rev } => {
+                    // the locked commit is used only if the dependency is not changed
+                    let locked_commit = locked
+                        .get(name)
+                        .filter(|d| d.git == *url && d.rev == *rev)
+                        .map(|d| d.commit.as_str());
+                    let (root, commit) =
+                        fetch_git(self.cache_dir, name, url, rev.as_deref(), locked_commit)?;
+                    self.lockfile.dependencies.push(LockedDependency {
+                        name: name.clone(),
+                        git: url.clone(),
+                        rev: rev.clone(),
+                        commit,
+                    });
+                    root
+                }
+            };
+            let dependency_package = Package::load(&root)?;
+            self.origins.insert(name.clone(), origin);
+            self.resolve_dependencies(&dependency_package, locked)?;
+            self.packages.insert(name.clone(), dependency_package);
+        }
+        Ok(())
+    }
+}
+
+/// fetch_git clones the repository into cache once, then checks out `locked_commit`, or else the
+/// latest commit of `rev`, returns the checkout directory and its commit
+fn fetch_git(
+    cache_dir: &Path,
+    name: &str,
+    url: &str,
+    rev: Option<&str>,
+    locked_commit: Option<&str>,
+) -> Result<(PathBuf, String)> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let dir = cache_dir
+        .join("git")
+        .join(format!("{}-{:016x}", name, hasher.finish()));
+    if !dir.join(".git").is_dir() {
+        std::fs::create_dir_all(&dir).map_err(|err| PackageError::io(&dir, err))?;
+        git(&dir, &["clone", "--quiet", "--end-of-options", url, "."])?;
+    }
+    let commit = match locked_commit.and_then(|commit| resolve_commit(&dir, commit)) {
+        Some(commit) => commit,
+        None => {
+            git(&dir, &["fetch", "--quiet", "--tags", "--force", "origin"])?;
+            let rev = locked_commit.or(rev).unwrap_or("HEAD");
+            // a branch is taken from the remote, the local branch of the clone is never updated
+            resolve_commit(&dir, &format!("origin/{}", rev))
+                .or_else(|| resolve_commit(&dir, rev))
+                .ok_or_else(|| {
+                    PackageError::git(&dir, format!("rev-parse {}", rev), "no such commit")
+                })?
+        }
+    };
+    git(&dir, &["checkout", "--quiet", "--detach", &commit])?;
+    Ok((dir, commit))
+}
+
+/// resolve_commit returns the full hash of the commit `rev` names in the repository at `dir`
+fn resolve_commit(dir: &Path, rev: &str) -> Option<String> {
+    let rev = format!("{}^{{commit}}", rev);
+    let args = ["rev-parse", "--verify", "--quiet", "--end-of-options", &rev];
+    git(dir, &args).ok()
+}
+
+/// is_identifier reports whether `name` is made of ASCII letters, digits and `_`, and doesn't start
+/// with a digit
+fn is_identifier(name: &str) -> bool {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// git runs git in `dir`, returns its trimmed output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let command = args.join(" ");
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| PackageError::git(dir, &command, err))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(PackageError::git(
+            dir,
+            &command,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ))
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, PackageError>;
+
+#[derive(Debug, Error)]
+#[error("{}: {err}", .path.display())]
+pub struct PackageError {
+    path: PathBuf,
+    err: PackageErrorVariant,
+}
+
+#[derive(Debug, Error)]
+enum PackageErrorVariant {
+    #[error(
+        "no `{}` found in this directory or any parent directory",
+        super::MANIFEST_NAME
+    )]
+    ManifestNotFound,
+    #[error("{}", .0)]
+    IO(std::io::Error),
+    #[error("invalid manifest, {}", .0)]
+    InvalidManifest(toml::de::Error),
+    #[error("dependency `{}` must have either `path` or `git`", .0)]
+    InvalidDependency(String),
+    #[error("dependency name `{}` must be an identifier", .0)]
+    InvalidDependencyName(String),
+    #[error("`{}` of dependency `{}` can't start with `-`", .field, .name)]
+    OptionLikeGitValue { name: String, field: String },
+    #[error("dependency `{}` is declared with different sources", .0)]
+    ConflictingDependency(String),
+    #[error("invalid lockfile, {}", .0)]
+    InvalidLockfile(toml::de::Error),
+    #[error("`git {}` failed: {}", .command, .message)]
+    Git { command: String, message: String },
+    #[error("no `.elz` file found in source root")]
+    NoSource,
+}
+
+impl PackageError {
+    fn new(path: &Path, err: PackageErrorVariant) -> PackageError {
+        PackageError {
+            path: path.to_path_buf(),
+            err,
+        }
+    }
+
+    pub fn manifest_not_found(path: &Path) -> PackageError {
+        PackageError::new(path, PackageErrorVariant::ManifestNotFound)
+    }
+    pub fn io(path: &Path, err: std::io::Error) -> PackageError {
+        PackageError::new(path, PackageErrorVariant::IO(err))
+    }
+    pub fn invalid_manifest(path: &Path, err: toml::de::Error) -> PackageError {
+        PackageError::new(path, PackageErrorVariant::InvalidManifest(err))
+    }
+    pub fn invalid_dependency<T: ToString>(path: &Path, name: T) -> PackageError {
+        PackageError::new(
+            path,
+            PackageErrorVariant::InvalidDependency(name.to_string()),
+        )
+    }
+    pub fn invalid_dependency_name<T: ToString>(path: &Path, name: T) -> PackageError {
+        PackageError::new(
+            path,
+            PackageErrorVariant::InvalidDependencyName(name.to_string()),
+        )
+    }
+    pub fn option_like_git_value<T: ToString>(path: &Path, name: T, field: &str) -> PackageError {
+        PackageError::new(
+            path,
+            PackageErrorVariant::OptionLikeGitValue {
+                name: name.to_string(),
+                field: field.to_string(),
+            },
+        )
+    }
+    pub fn conflicting_dependency<T: ToString>(path: &Path, name: T) -> PackageError {
+        PackageError::new(
+            path,
+            PackageErrorVariant::ConflictingDependency(name.to_string()),
+        )
+    }
+    pub fn invalid_lockfile(path: &Path, err: toml::de::Error) -> PackageError {
+        PackageError::new(path, PackageErrorVariant::InvalidLockfile(err))
+    }
+    pub fn git<T: ToString, U: ToString>(path: &Path, command: T, message: U) -> PackageError {
+        PackageError::new(
+            path,
+            PackageErrorVariant::Git {
+                command: command.to_string(),
+                message: message.to_string(),
+            },
+        )
+    }
+    pub fn no_source(path: &Path) -> PackageError {
+        PackageError::new(path, PackageErrorVariant::NoSource)
+    }
+}
@@ -0,0 +1,49 @@
+use super::error::{PackageError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Lockfile records the commits of git dependencies
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    #[serde(default, rename = "dependency")]
+    pub dependencies: Vec<LockedDependency>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedDependency {
+    pub name: String,
+    pub git: String,
+    /// rev in manifest when the commit was resolved, the commit is not used after it's changed
+    pub rev: Option<String>,
+    pub commit: String,
+}
+
+impl Lockfile {
+    /// read returns an empty lockfile if the file does not exist
+    pub fn read(path: &Path) -> Result<Lockfile> {
+        if !path.exists() {
+            return Ok(Lockfile::default());
+        }
+        let s = std::fs::read_to_string(path).map_err(|err| PackageError::io(path, err))?;
+        toml::from_str(&s).map_err(|err| PackageError::invalid_lockfile(path, err))
+    }
+
+    /// write writes lockfile unless it has no dependency, the file is not touched if nothing
+    /// changed
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if self.dependencies.is_empty() || *self == Lockfile::read(path)? {
+            return Ok(());
+        }
+        let s = format!(
+            "# generated by elz, do not edit\n{}",
+            toml::to_string(self).expect("lockfile can be serialized")
+        );
+        std::fs::write(path, s).map_err(|err| PackageError::io(path, err))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedDependency> {
+        self.dependencies.iter().find(|d| d.name == name)
+    }
+}
@@ -0,0 +1,199 @@
+//! package describes a source tree by its manifest `elz.toml`, e.g.
+//!
+//! ```toml
+//! [package]
+//! name = "hello"
+//! # directory of `.elz` files, relative to the manifest, defaults to `src`
+//! source = "src"
+//! # `bin` builds an executable, `lib` builds an object file
+//! kind = "bin"
+//!
+//! [dependencies]
+//! # modules of dependency are imported under its name, e.g. `import util.math (double)`
+//! util = { path = "../util" }
+//! json = { git = "https://github.com/elz-lang/json.git", rev = "v0.1.0" }
+//! ```
+//!
+//! git dependencies are fetched into the cache directory, their commits are recorded in `elz.lock`
+//! next to the manifest, so later builds use the same commits until the lockfile is removed
+use dependency::Resolver;
+use error::{PackageError, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+mod dependency;
+pub mod error;
+pub mod lock;
+
+/// MANIFEST_NAME is the file name of manifest
+pub const MANIFEST_NAME: &str = "elz.toml";
+/// LOCK_NAME is the file name of lockfile
+pub const LOCK_NAME: &str = "elz.lock";
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: PackageInfo,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PackageInfo {
+    pub name: String,
+    #[serde(default = "default_source")]
+    pub source: PathBuf,
+    #[serde(default)]
+    pub kind: Kind,
+}
+
+fn default_source() -> PathBuf {
+    PathBuf::from("src")
+}
+
+/// Kind is the output kind of package
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// executable, `main` is the entry
+    #[default]
+    Bin,
+    /// object file to be linked by others
+    Lib,
+}
+
+/// Dependency is another package whose modules can be imported, exactly one of `path` and `git`
+/// must be given
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// directory of the package, relative to the manifest
+    pub path: Option<PathBuf>,
+    /// URL of git repository whose root is the package
+    pub git: Option<String>,
+    /// branch, tag or commit of `git`, defaults to `HEAD` of the repository
+    pub rev: Option<String>,
+}
+
+/// Source is a file of the package or its dependencies
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+    pub path: PathBuf,
+    /// dependency where the file is from, the module of the file is named under it
+    pub dependency: Option<String>,
+}
+
+impl From<&str> for Source {
+    fn from(path: &str) -> Source {
+        Source {
+            path: PathBuf::from(path),
+            dependency: None,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn from_str(path: &Path, s: &str) -> Result<Manifest> {
+        toml::from_str(s).map_err(|err| PackageError::invalid_manifest(path, err))
+    }
+}
+
+pub struct Package {
+    /// root is the directory of manifest
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Package {
+    /// find loads the package whose manifest is in `dir` or the nearest parent of `dir`
+    pub fn find(dir: &Path) -> Result<Package> {
+        let dir = dir
+            .canonicalize()
+            .map_err(|err| PackageError::io(dir, err))?;
+        for root in dir.ancestors() {
+            if root.join(MANIFEST_NAME).is_file() {
+                return Package::load(root);
+            }
+        }
+        Err(PackageError::manifest_not_found(&dir))
+    }
+
+    /// load loads the package whose manifest is in `root`
+    pub fn load(root: &Path) -> Result<Package> {
+        let path = root.join(MANIFEST_NAME);
+        let s = std::fs::read_to_string(&path).map_err(|err| PackageError::io(&path, err))?;
+        let manifest = Manifest::from_str(&path, &s)?;
+        Ok(Package {
+            root: root.to_path_buf(),
+            manifest,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.package.name
+    }
+    pub fn kind(&self) -> Kind {
+        self.manifest.package.kind
+    }
+
+    /// sources returns all `.elz` files under source root in a stable order, each file is a module
+    pub fn sources(&self) -> Result<Vec<PathBuf>> {
+        let source_root = self.root.join(&self.manifest.package.source);
+        let mut sources = vec![];
+        for entry in WalkDir::new(&source_root).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+            let entry = entry.map_err(|err| PackageError::io(&source_root, err.into()))?;
+            if entry.file_type().is_file() && entry.path().extension() == Some(OsStr::new("elz")) {
+                sources.push(entry.into_path());
+            }
+        }
+        if sources.is_empty() {
+            return Err(PackageError::no_source(&source_root));
+        }
+        Ok(sources)
+    }
+
+    /// all_sources resolves dependencies and returns sources of the package and then of its
+    /// dependencies, git dependencies are fetched into `cache_dir` and `elz.lock` is updated
+    pub fn all_sources(&self, cache_dir: &Path) -> Result<Vec<Source>> {
+        let mut sources: Vec<Source> = self
+            .sources()?
+            .into_iter()
+            .map(|path| Source {
+                path,
+                dependency: None,
+            })
+            .collect();
+        for (name, package) in Resolver::new(cache_dir).resolve(self)? {
+            for path in package.sources()? {
+                sources.push(Source {
+                    path,
+                    dependency: Some(name.clone()),
+                });
+            }
+        }
+        Ok(sources)
+    }
+
+    /// output_dir is the directory of build outputs, i.e. `target` next to the manifest
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join("target")
+    }
+}
+
+/// default_cache_dir is `ELZ_CACHE`, or `.elz/cache` under home directory
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ELZ_CACHE") {
+        return PathBuf::from(dir);
+    }
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(".elz").join("cache"),
+        None => std::env::temp_dir().join("elz-cache"),
+    }
+}
+
+#[cfg(test)]
+mod tests;
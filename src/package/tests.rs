@@ -0,0 +1,266 @@
+use super::lock::Lockfile;
+use super::*;
+
+#[test]
+fn manifest_with_defaults() {
+    let manifest = parse("[package]\nname = \"hello\"\n").unwrap();
+    assert_eq!(
+        manifest,
+        Manifest {
+            package: PackageInfo {
+                name: "hello".to_string(),
+                source: PathBuf::from("src"),
+                kind: Kind::Bin,
+            },
+            dependencies: BTreeMap::new(),
+        }
+    );
+}
+
+#[test]
+fn manifest_of_library() {
+    let manifest = parse(
+        "[package]
+name = \"math\"
+source = \"lib\"
+kind = \"lib\"
+",
+    )
+    .unwrap();
+    assert_eq!(manifest.package.source, PathBuf::from("lib"));
+    assert_eq!(manifest.package.kind, Kind::Lib);
+}
+
+#[test]
+fn invalid_manifest() {
+    assert!(parse("[package]\n").is_err());
+    assert!(parse("[package]\nname = \"a\"\nkind = \"dylib\"\n").is_err());
+    assert!(parse("[package]\nname = \"a\"\nversion = 1\n").is_err());
+}
+
+#[test]
+fn find_package_and_sources() {
+    let root = temp_package("find");
+    std::fs::create_dir_all(root.join("src/util")).unwrap();
+    std::fs::write(root.join(MANIFEST_NAME), "[package]\nname = \"find\"\n").unwrap();
+    std::fs::write(root.join("src/main.elz"), "module main\n").unwrap();
+    std::fs::write(root.join("src/util/math.elz"), "module util.math\n").unwrap();
+    std::fs::write(root.join("src/notes.txt"), "").unwrap();
+
+    let package = Package::find(&root.join("src/util")).unwrap();
+    assert_eq!(package.name(), "find");
+    let sources: Vec<PathBuf> = package
+        .sources()
+        .unwrap()
+        .iter()
+        .map(|p| p.strip_prefix(&package.root).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        sources,
+        vec![
+            PathBuf::from("src/main.elz"),
+            PathBuf::from("src/util/math.elz")
+        ]
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn path_dependencies() {
+    let root = temp_package("path");
+    write_package(&root.join("app"), "app", "b = { path = \"../b\" }");
+    write_package(&root.join("b"), "b", "c = { path = \"../c\" }");
+    write_package(&root.join("c"), "c", "");
+
+    let package = Package::load(&root.join("app")).unwrap();
+    let sources: Vec<Option<String>> = package
+        .all_sources(&root.join("cache"))
+        .unwrap()
+        .into_iter()
+        .map(|source| source.dependency)
+        .collect();
+    assert_eq!(
+        sources,
+        vec![None, Some("b".to_string()), Some("c".to_string())]
+    );
+    // nothing to lock
+    assert!(!root.join("app").join(LOCK_NAME).exists());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn conflicting_dependencies() {
+    let root = temp_package("conflicting");
+    write_package(
+        &root.join("app"),
+        "app",
+        "b = { path = \"../b\" }\nc = { path = \"../c\" }",
+    );
+    write_package(&root.join("b"), "b", "c = { path = \"../b\" }");
+    write_package(&root.join("c"), "c", "");
+
+    let package = Package::load(&root.join("app")).unwrap();
+    assert!(package.all_sources(&root.join("cache")).is_err());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn dependency_needs_path_or_git() {
+    let root = temp_package("invalid");
+    write_package(&root.join("app"), "app", "b = { rev = \"main\" }");
+    let package = Package::load(&root.join("app")).unwrap();
+    assert!(package.all_sources(&root.join("cache")).is_err());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn git_dependency_is_locked() {
+    let root = temp_package("git");
+    let repo = root.join("b");
+    write_package(&repo, "b", "");
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "first"]);
+    let first = git(&repo, &["rev-parse", "HEAD"]);
+    write_package(
+        &root.join("app"),
+        "app",
+        &format!("b = {{ git = {:?} }}", repo.to_string_lossy()),
+    );
+
+    let package = Package::load(&root.join("app")).unwrap();
+    let cache = root.join("cache");
+    assert_eq!(package.all_sources(&cache).unwrap().len(), 2);
+    let lock_path = root.join("app").join(LOCK_NAME);
+    assert_eq!(
+        Lockfile::read(&lock_path).unwrap().get("b").unwrap().commit,
+        first
+    );
+
+    // locked commit is still used after the repository has changed
+    std::fs::write(repo.join("src/b2.elz"), "module b2\n").unwrap();
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "second"]);
+    assert_eq!(package.all_sources(&cache).unwrap().len(), 2);
+    // until the lockfile is removed
+    std::fs::remove_file(&lock_path).unwrap();
+    assert_eq!(package.all_sources(&cache).unwrap().len(), 3);
+    assert_ne!(
+        Lockfile::read(&lock_path).unwrap().get("b").unwrap().commit,
+        first
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn git_options_in_dependencies_are_rejected() {
+    let root = temp_package("git-options");
+    let repo = root.join("b");
+    write_package(&repo, "b", "");
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "first"]);
+    let pwned = root.join("pwned");
+    write_package(
+        &root.join("app"),
+        "app",
+        &format!(
+            "b = {{ git = {:?}, rev = \"--upload-pack=touch {};git-upload-pack\" }}",
+            repo.to_string_lossy(),
+            pwned.to_string_lossy()
+        ),
+    );
+    let package = Package::load(&root.join("app")).unwrap();
+    let err = package.all_sources(&root.join("cache")).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("`rev` of dependency `b` can't start with `-`"));
+    assert!(!pwned.exists());
+
+    write_package(&root.join("app"), "app", "b = { git = \"--help\" }");
+    let package = Package::load(&root.join("app")).unwrap();
+    assert!(package.all_sources(&root.join("cache")).is_err());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn dependency_name_is_an_identifier() {
+    let root = temp_package("name");
+    write_package(&root.join("b"), "b", "");
+    write_package(&root.join("app"), "app", "\"../b\" = { path = \"../b\" }");
+    let package = Package::load(&root.join("app")).unwrap();
+    let err = package.all_sources(&root.join("cache")).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("dependency name `../b` must be an identifier"));
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn git_dependency_follows_branch() {
+    let root = temp_package("git-branch");
+    let repo = root.join("b");
+    write_package(&repo, "b", "");
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["checkout", "--quiet", "-b", "stable"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "first"]);
+    write_package(
+        &root.join("app"),
+        "app",
+        &format!(
+            "b = {{ git = {:?}, rev = \"stable\" }}",
+            repo.to_string_lossy()
+        ),
+    );
+    let package = Package::load(&root.join("app")).unwrap();
+    let cache = root.join("cache");
+    assert_eq!(package.all_sources(&cache).unwrap().len(), 2);
+
+    // without the lockfile, the new commit of the branch is fetched
+    std::fs::write(repo.join("src/b2.elz"), "module b2\n").unwrap();
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "second"]);
+    std::fs::remove_file(root.join("app").join(LOCK_NAME)).unwrap();
+    assert_eq!(package.all_sources(&cache).unwrap().len(), 3);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+// helpers, must put tests before this line
+fn parse(s: &str) -> Result<Manifest> {
+    Manifest::from_str(Path::new(MANIFEST_NAME), s)
+}
+/// write_package writes manifest with dependencies and a module named as the package
+fn write_package(root: &Path, name: &str, dependencies: &str) {
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join(MANIFEST_NAME),
+        format!(
+            "[package]\nname = \"{}\"\n[dependencies]\n{}\n",
+            name, dependencies
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src").join(format!("{}.elz", name)),
+        format!("module {}\n", name),
+    )
+    .unwrap();
+}
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(&["-c", "user.name=elz", "-c", "user.email=elz@localhost"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+/// temp_package creates an empty directory for a package
+fn temp_package(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("elz-package-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root.canonicalize().unwrap()
+}
@@ -1,3 +1,5 @@
+use super::MAX_NESTING;
+use crate::diagnostic::{Diagnose, Diagnostic};
 use crate::lexer::{Location, TkType, Token};
 use thiserror::Error;
 
@@ -16,14 +18,56 @@ pub enum ParseErrorVariant {
     NotExpectedToken(Vec<TkType>, Token),
     #[error("meet eof when parsing")]
     EOF,
+    #[error("integer literal `{}` is out of range of type `int`", .0)]
+    IntegerOutOfRange(String),
+    #[error("only field can be assigned, e.g. `foo.bar = 1;`")]
+    InvalidAssignmentTarget,
+    #[error("string literal is not closed by `\"`")]
+    UnclosedString,
+    #[error("unknown character `{}`", .0)]
+    UnknownCharacter(String),
+    #[error("`{{` in string template is not closed by `}}`")]
+    UnclosedTemplate,
+    #[error("code is nested deeper than {} levels", MAX_NESTING)]
+    TooDeep,
 }
 
 impl ParseError {
-    pub fn not_expected_token(expected: Vec<TkType>, actual: Token) -> ParseError {
+    /// not_expected_token reports an invalid token as what's wrong with it, whatever is expected
+    pub fn not_expected_token(expected: Vec<TkType>, actual: &Token) -> ParseError {
         use ParseErrorVariant::*;
+        let err = match actual.tk_type() {
+            TkType::Invalid if actual.text().starts_with('"') => UnclosedString,
+            TkType::Invalid => UnknownCharacter(actual.value()),
+            _ => NotExpectedToken(expected, actual.clone()),
+        };
         ParseError {
             location: actual.location(),
-            err: NotExpectedToken(expected, actual),
+            err,
+        }
+    }
+    pub fn too_deep(location: Location) -> ParseError {
+        ParseError {
+            location,
+            err: ParseErrorVariant::TooDeep,
+        }
+    }
+    pub fn unclosed_template(location: Location) -> ParseError {
+        ParseError {
+            location,
+            err: ParseErrorVariant::UnclosedTemplate,
+        }
+    }
+    pub fn integer_out_of_range(location: Location, literal: String) -> ParseError {
+        ParseError {
+            location,
+            err: ParseErrorVariant::IntegerOutOfRange(literal),
+        }
+    }
+    pub fn invalid_assignment_target(location: Location) -> ParseError {
+        ParseError {
+            location,
+            err: ParseErrorVariant::InvalidAssignmentTarget,
         }
     }
     pub fn eof(location: &Location) -> ParseError {
@@ -41,11 +85,37 @@ impl ParseError {
         match self.err {
             NotExpectedToken(..) => "not expected token",
             EOF => "eof",
+            IntegerOutOfRange(..) => "integer out of range",
+            InvalidAssignmentTarget => "invalid assignment target",
+            UnclosedString => "unclosed string",
+            UnknownCharacter(..) => "unknown character",
+            UnclosedTemplate => "unclosed template",
+            TooDeep => "too deep",
         }
         .to_string()
     }
 }
 
+impl Diagnose for ParseError {
+    fn diagnostic(&self) -> Diagnostic {
+        use ParseErrorVariant::*;
+        let code = match self.err {
+            NotExpectedToken(..) => "not_expected_token",
+            EOF => "eof",
+            IntegerOutOfRange(..) => "integer_out_of_range",
+            InvalidAssignmentTarget => "invalid_assignment_target",
+            UnclosedString => "unclosed_string",
+            UnknownCharacter(..) => "unknown_character",
+            UnclosedTemplate => "unclosed_template",
+            TooDeep => "too_deep",
+        };
+        Diagnostic {
+            label: self.message(),
+            ..Diagnostic::error(code, &self.location, &self.err)
+        }
+    }
+}
+
 struct ShowTkTypeList(Vec<TkType>);
 impl std::fmt::Display for ShowTkTypeList {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
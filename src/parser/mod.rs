@@ -4,12 +4,15 @@ use super::lexer::{TkType, Token};
 use crate::prelude::Asset;
 
 mod error;
+pub(crate) mod precedence;
 #[cfg(test)]
 mod tests;
 
 use crate::lexer::Location;
-use error::ParseError;
+use crate::mangle::SEPARATOR;
+pub use error::ParseError;
 use error::Result;
+use precedence::{is_right_associative, precedence};
 use std::collections::HashMap;
 
 pub(crate) fn parse_prelude() -> Module {
@@ -19,27 +22,73 @@ pub(crate) fn parse_prelude() -> Module {
     prelude_program
 }
 
+/// MAX_NESTING is how deep expressions, statements and types can be nested, deeper code is reported
+/// rather than overflowing the stack of passes recursing into it
+pub const MAX_NESTING: usize = 128;
+
 /// Parser is a parsing helper
 pub struct Parser {
     file_name: String,
+    /// tokens are lexed up front rather than streamed, since `peek` borrows them and recovering from
+    /// an error goes back to the first token of the failed statement
     tokens: Vec<Token>,
     offset: usize,
+    /// in condition of `if`, `{` starts the block rather than a class construction
+    in_condition: bool,
+    /// depth is how deep the node being parsed is nested, see `MAX_NESTING`
+    depth: usize,
+    /// errors recovered from, only collected by `parse_module_recovering`, otherwise parsing stops
+    /// at the first error
+    errors: Option<Vec<ParseError>>,
+}
+
+/// Boundary is where parsing continues after an error
+#[derive(Clone, Copy)]
+enum Boundary {
+    /// after the `;` or the closing `}` of the failed definition
+    Top,
+    /// after the `;` or the closing `}` of the failed statement, or before the `}` of its block
+    Statement,
 }
 
 impl Parser {
     pub fn parse_module(&mut self, end_token_type: TkType) -> Result<Module> {
-        self.consume(vec![TkType::Module])?;
+        self.consume(&[TkType::Module])?;
         let name = self.parse_module_path()?;
         Ok(Module {
             name,
             top_list: self.parse_top_list(end_token_type)?,
         })
     }
+    /// parse_module_recovering keeps parsing after errors, definitions and statements failed are
+    /// left out of the module, e.g. for editors working on incomplete code
+    pub fn parse_module_recovering(&mut self, end_token_type: TkType) -> (Module, Vec<ParseError>) {
+        self.errors = Some(vec![]);
+        let name = match self
+            .consume(&[TkType::Module])
+            .and_then(|_| self.parse_module_path())
+        {
+            Ok(name) => name,
+            Err(err) => {
+                self.errors.as_mut().unwrap().push(err);
+                String::new()
+            }
+        };
+        let top_list = match self.parse_top_list(end_token_type) {
+            Ok(top_list) => top_list,
+            Err(err) => {
+                self.errors.as_mut().unwrap().push(err);
+                vec![]
+            }
+        };
+        let errors = self.errors.take().unwrap();
+        (Module { name, top_list }, errors)
+    }
     fn parse_module_path(&mut self) -> Result<String> {
         let mut chain = vec![];
         chain.push(self.parse_identifier()?);
         while self.peek(0)?.tk_type() == &TkType::Dot {
-            self.consume(vec![TkType::Dot])?;
+            self.consume(&[TkType::Dot])?;
             chain.push(self.parse_identifier()?);
         }
         Ok(chain.join("."))
@@ -47,12 +96,16 @@ impl Parser {
     pub fn parse_top_list(&mut self, end_token_type: TkType) -> Result<Vec<TopAst>> {
         let mut top_list = vec![];
         while self.peek(0)?.tk_type() != &end_token_type {
-            top_list.push(self.parse_top_ast()?);
+            let start = self.offset;
+            match self.parse_top_ast() {
+                Ok(top) => top_list.push(top),
+                Err(err) => self.recover(err, start, Boundary::Top)?,
+            }
         }
         Ok(top_list)
     }
     pub fn parse_tag(&mut self) -> Result<Option<Tag>> {
-        if self.consume(vec![TkType::AtSign]).is_ok() {
+        if self.consume(&[TkType::AtSign]).is_ok() {
             let tag_name = self.parse_identifier()?;
             let properties = self.parse_many_if_has_open_token(
                 TkType::OpenParen,
@@ -76,16 +129,13 @@ impl Parser {
             }
             TkType::Identifier => {
                 // found `<identifier> :`
-                if self
-                    .predict(vec![TkType::Identifier, TkType::Colon])
-                    .is_ok()
-                {
+                if self.predict(&[TkType::Identifier, TkType::Colon]).is_ok() {
                     let v = self.parse_variable(tag)?;
-                    self.consume(vec![TkType::Semicolon])?;
+                    self.consume(&[TkType::Semicolon])?;
                     Ok(Variable(v))
                 } else {
                     // else we just seems it as a function to parse
-                    let f = self.parse_function(tag)?;
+                    let f = self.parse_top_function(tag)?;
                     Ok(Function(f))
                 }
             }
@@ -98,14 +148,14 @@ impl Parser {
                 Ok(Trait(t))
             }
             _ => {
-                self.predict_one_of(vec![TkType::Identifier, TkType::Class, TkType::Trait])?;
+                self.predict_one_of(&[TkType::Identifier, TkType::Class, TkType::Trait])?;
                 unreachable!();
             }
         }
     }
     pub fn parse_import(&mut self) -> Result<Import> {
         let location = self.peek(0)?.location();
-        self.consume(vec![TkType::Import])?;
+        self.consume(&[TkType::Import])?;
         let import_path = self.parse_module_path()?;
         let imported_component = self.parse_many_if_has_open_token(
             TkType::OpenParen,
@@ -114,7 +164,7 @@ impl Parser {
             |parser| Ok(parser.parse_identifier()?),
         )?;
         Ok(Import {
-            location,
+            location: self.span_from(&location),
             import_path,
             imported_component,
         })
@@ -125,29 +175,15 @@ impl Parser {
     /// basic: `class Car { name: string; ::new(name: string): Car; }`
     /// implements trait: `class Rectangle <: Shape {}`
     pub fn parse_class(&mut self, tag: Option<Tag>) -> Result<Class> {
-        let kw_class = self.peek(0)?;
-        self.consume(vec![TkType::Class])?;
+        let location = self.peek(0)?.location();
+        self.consume(&[TkType::Class])?;
         let class_name = self.parse_identifier()?;
-        let mut parents = vec![];
-        if self.consume(vec![TkType::IsSubTypeOf]).is_ok() {
-            while self.peek(0)?.tk_type() != &TkType::OpenBracket {
-                parents.push(self.parse_identifier()?);
-                if self.consume(vec![TkType::Comma]).is_err() {
-                    break;
-                } else {
-                }
-            }
-        }
-        let type_parameters = if self.predict(vec![TkType::OpenBracket]).is_ok() {
-            self.parse_type_parameters()?
-        } else {
-            vec![]
-        };
-        self.consume(vec![TkType::OpenBrace])?;
+        let (type_parameters, parents) = self.parse_type_parameters_and_parents()?;
+        self.consume(&[TkType::OpenBrace])?;
         let members = self.parse_class_members()?;
-        self.consume(vec![TkType::CloseBrace])?;
+        self.consume(&[TkType::CloseBrace])?;
         Ok(Class::new(
-            kw_class.location().clone(),
+            self.span_from(&location),
             tag,
             parents,
             class_name,
@@ -155,6 +191,33 @@ impl Parser {
             members,
         ))
     }
+    /// parse_type_parameters_and_parents parses the optional type parameters and parents after the
+    /// name of a class or trait, type parameters are either after the name or after parents, e.g.
+    /// `class A[T] <: B` or `class A <: B [T]`
+    fn parse_type_parameters_and_parents(&mut self) -> Result<(Vec<TypeParameter>, Vec<String>)> {
+        let mut type_parameters = if self.predict(&[TkType::OpenBracket]).is_ok() {
+            self.parse_type_parameters()?
+        } else {
+            vec![]
+        };
+        let mut parents = vec![];
+        if self.consume(&[TkType::IsSubTypeOf]).is_ok() {
+            parents.push(self.parse_identifier()?);
+            // a trailing comma is allowed as in delimited lists, e.g. `class A <: B, C, {}`
+            while self.consume(&[TkType::Comma]).is_ok()
+                && !matches!(
+                    self.peek(0)?.tk_type(),
+                    TkType::OpenBrace | TkType::OpenBracket
+                )
+            {
+                parents.push(self.parse_identifier()?);
+            }
+        }
+        if type_parameters.is_empty() && self.predict(&[TkType::OpenBracket]).is_ok() {
+            type_parameters = self.parse_type_parameters()?;
+        }
+        Ok((type_parameters, parents))
+    }
     fn parse_type_parameters(&mut self) -> Result<Vec<TypeParameter>> {
         self.parse_many(
             TkType::OpenBracket,
@@ -162,11 +225,14 @@ impl Parser {
             TkType::Comma,
             |parser| {
                 let identifier = parser.parse_identifier()?;
-                let parent_types = if parser.consume(vec![TkType::IsSubTypeOf]).is_ok() {
-                    vec![parser.parse_type()?]
-                } else {
-                    vec![]
-                };
+                // a type parameter can be bounded by traits, e.g. `T <: Eq + ToString`
+                let mut parent_types = vec![];
+                if parser.consume(&[TkType::IsSubTypeOf]).is_ok() {
+                    parent_types.push(parser.parse_type()?);
+                    while parser.consume(&[TkType::Plus]).is_ok() {
+                        parent_types.push(parser.parse_type()?);
+                    }
+                }
                 Ok(TypeParameter::new(identifier, parent_types))
             },
         )
@@ -174,15 +240,12 @@ impl Parser {
     fn parse_class_members(&mut self) -> Result<Vec<ClassMember>> {
         let mut members = vec![];
         while self.peek(0)?.tk_type() != &TkType::CloseBrace {
-            if self
-                .predict(vec![TkType::Identifier, TkType::Colon])
-                .is_ok()
-            {
+            if self.predict(&[TkType::Identifier, TkType::Colon]).is_ok() {
                 let v = self.parse_class_field()?;
                 members.push(ClassMember::Field(v));
             } else {
                 let tag = self.parse_tag()?;
-                if self.consume(vec![TkType::Accessor]).is_ok() {
+                if self.consume(&[TkType::Accessor]).is_ok() {
                     members.push(ClassMember::StaticMethod(self.parse_function(tag)?));
                 } else {
                     let method = self.parse_function(tag)?;
@@ -205,17 +268,17 @@ impl Parser {
         // x: int = 1;
         let var_name = self.parse_access_identifier()?;
         // : int = 1;
-        self.consume(vec![TkType::Colon])?;
+        self.consume(&[TkType::Colon])?;
         // int = 1;
         let typ = self.parse_type()?;
         // = 1;
-        if self.consume(vec![TkType::Equal]).is_ok() {
+        if self.consume(&[TkType::Equal]).is_ok() {
             let expr = self.parse_expression(None, None)?;
-            self.consume(vec![TkType::Semicolon])?;
-            Ok(Field::new(loc, var_name, typ, Some(expr)))
+            self.consume(&[TkType::Semicolon])?;
+            Ok(Field::new(self.span_from(&loc), var_name, typ, Some(expr)))
         } else {
-            self.consume(vec![TkType::Semicolon])?;
-            Ok(Field::new(loc, var_name, typ, None))
+            self.consume(&[TkType::Semicolon])?;
+            Ok(Field::new(self.span_from(&loc), var_name, typ, None))
         }
     }
     /// parse_trait:
@@ -225,21 +288,16 @@ impl Parser {
     /// with others trait: `trait A <: B {}`
     pub fn parse_trait(&mut self, tag: Option<Tag>) -> Result<Trait> {
         let location = self.peek(0)?.location();
-        self.consume(vec![TkType::Trait])?;
+        self.consume(&[TkType::Trait])?;
         let trait_name = self.parse_identifier()?;
-        // FIXME: parse with traits part
-        let type_parameters = if self.predict(vec![TkType::OpenBracket]).is_ok() {
-            self.parse_type_parameters()?
-        } else {
-            vec![]
-        };
-        self.consume(vec![TkType::OpenBrace])?;
+        let (type_parameters, with_traits) = self.parse_type_parameters_and_parents()?;
+        self.consume(&[TkType::OpenBrace])?;
         let members = self.parse_trait_members(&trait_name)?;
-        self.consume(vec![TkType::CloseBrace])?;
+        self.consume(&[TkType::CloseBrace])?;
         Ok(Trait::new(
-            location,
+            self.span_from(&location),
             tag,
-            vec![],
+            with_traits,
             trait_name,
             type_parameters,
             members,
@@ -248,10 +306,7 @@ impl Parser {
     fn parse_trait_members(&mut self, class_name: &String) -> Result<Vec<TraitMember>> {
         let mut members = vec![];
         while self.peek(0)?.tk_type() != &TkType::CloseBrace {
-            if self
-                .predict(vec![TkType::Identifier, TkType::Colon])
-                .is_ok()
-            {
+            if self.predict(&[TkType::Identifier, TkType::Colon]).is_ok() {
                 let v = self.parse_class_field()?;
                 members.push(TraitMember::Field(v));
             } else {
@@ -259,7 +314,11 @@ impl Parser {
                 let mut method = self.parse_function(tag)?;
                 method.parameters.insert(
                     0,
-                    Parameter::new("self", ParsedType::TypeName(class_name.clone())),
+                    Parameter::new(
+                        method.location.clone(),
+                        "self",
+                        ParsedType::TypeName(class_name.clone()),
+                    ),
                 );
                 members.push(TraitMember::Method(method));
             }
@@ -274,13 +333,19 @@ impl Parser {
         // x: int = 1;
         let var_name = self.parse_identifier()?;
         // : int = 1;
-        self.consume(vec![TkType::Colon])?;
+        self.consume(&[TkType::Colon])?;
         // int = 1;
         let typ = self.parse_type()?;
         // = 1;
-        self.consume(vec![TkType::Equal])?;
+        self.consume(&[TkType::Equal])?;
         let expr = self.parse_expression(None, None)?;
-        Ok(Variable::new(loc, tag, var_name, typ, expr))
+        Ok(Variable::new(
+            self.span_from(&loc),
+            tag,
+            var_name,
+            typ,
+            expr,
+        ))
     }
     /// parse_function:
     ///
@@ -294,27 +359,61 @@ impl Parser {
         let loc = self.peek(0)?.location();
         // main(): void
         let fn_name = self.parse_identifier()?;
+        self.parse_function_from(loc, tag, fn_name)
+    }
+    /// parse_top_function parses a top-level function, which can be generic, e.g.
+    /// `max[T <: Ord](a: T, b: T): T`
+    fn parse_top_function(&mut self, tag: Option<Tag>) -> Result<Function> {
+        let loc = self.peek(0)?.location();
+        let fn_name = self.parse_identifier()?;
+        let type_parameters = if self.predict(&[TkType::OpenBracket]).is_ok() {
+            self.parse_type_parameters()?
+        } else {
+            vec![]
+        };
+        let mut f = self.parse_function_from(loc, tag, fn_name)?;
+        f.type_parameters = type_parameters;
+        Ok(f)
+    }
+    /// parse_function_from parses the rest of a function after its name at `loc`
+    fn parse_function_from(
+        &mut self,
+        loc: Location,
+        tag: Option<Tag>,
+        fn_name: String,
+    ) -> Result<Function> {
         // (): void
         let tok = self.peek(0)?;
         if tok.tk_type() == &TkType::OpenParen {
             let params = self.parse_parameters()?;
             // : void
-            self.consume(vec![TkType::Colon])?;
+            self.consume(&[TkType::Colon])?;
             // void
             let ret_typ = self.parse_type()?;
-            if self.predict(vec![TkType::Semicolon]).is_ok() {
+            if self.predict(&[TkType::Semicolon]).is_ok() {
                 // ;
                 self.take()?;
                 Ok(Function::new_declaration(
-                    loc, tag, fn_name, params, ret_typ,
+                    self.span_from(&loc),
+                    tag,
+                    fn_name,
+                    params,
+                    ret_typ,
                 ))
             } else if self
-                .predict_one_of(vec![TkType::OpenBrace, TkType::Equal])
+                .predict_one_of(&[TkType::OpenBrace, TkType::Equal])
                 .is_ok()
             {
                 // {}
                 let body = self.parse_body()?;
-                Ok(Function::new(loc, tag, fn_name, params, ret_typ, body))
+                Ok(Function::new(
+                    self.span_from(&loc),
+                    tag,
+                    fn_name,
+                    params,
+                    ret_typ,
+                    body,
+                ))
             } else {
                 Err(ParseError::not_expected_token(
                     vec![TkType::OpenBrace, TkType::Semicolon, TkType::Equal],
@@ -330,14 +429,15 @@ impl Parser {
     /// ()
     /// (x: int, y: int)
     fn parse_parameters(&mut self) -> Result<Vec<Parameter>> {
-        self.consume(vec![TkType::OpenParen])?;
+        self.consume(&[TkType::OpenParen])?;
         let mut params = vec![];
         while self.peek(0)?.tk_type() != &TkType::CloseParen {
-            self.predict(vec![TkType::Identifier, TkType::Colon])?;
+            self.predict(&[TkType::Identifier, TkType::Colon])?;
+            let location = self.peek(0)?.location();
             let param_name = self.take()?.value();
             self.take()?;
             let typ = self.parse_type()?;
-            params.push(Parameter::new(param_name, typ));
+            params.push(Parameter::new(self.span_from(&location), param_name, typ));
             let tok = self.peek(0)?;
             match tok.tk_type() {
                 TkType::Comma => {
@@ -353,7 +453,7 @@ impl Parser {
                 }
             }
         }
-        self.consume(vec![TkType::CloseParen])?;
+        self.consume(&[TkType::CloseParen])?;
         Ok(params)
     }
     fn parse_body(&mut self) -> Result<Body> {
@@ -361,9 +461,9 @@ impl Parser {
         match tok.tk_type() {
             TkType::OpenBrace => Ok(Body::Block(self.parse_block()?)),
             TkType::Equal => {
-                self.consume(vec![TkType::Equal])?;
+                self.consume(&[TkType::Equal])?;
                 let e = self.parse_expression(None, None)?;
-                self.consume(vec![TkType::Semicolon])?;
+                self.consume(&[TkType::Semicolon])?;
                 Ok(Body::Expr(e))
             }
             _ => Err(ParseError::not_expected_token(
@@ -373,7 +473,7 @@ impl Parser {
         }
     }
     fn parse_identifier(&mut self) -> Result<String> {
-        self.predict(vec![TkType::Identifier])?;
+        self.predict(&[TkType::Identifier])?;
         Ok(self.take()?.value())
     }
     /// parse_access_identifier:
@@ -381,14 +481,14 @@ impl Parser {
     /// foo::bar
     pub fn parse_access_identifier(&mut self) -> Result<String> {
         let mut chain = vec![];
-        self.predict(vec![TkType::Identifier])?;
+        self.predict(&[TkType::Identifier])?;
         chain.push(self.take()?.value());
         while self.peek(0)?.tk_type() == &TkType::Accessor {
-            self.consume(vec![TkType::Accessor])?;
-            self.predict(vec![TkType::Identifier])?;
+            self.consume(&[TkType::Accessor])?;
+            self.predict(&[TkType::Identifier])?;
             chain.push(self.take()?.value());
         }
-        Ok(chain.join("::"))
+        Ok(chain.join(SEPARATOR))
     }
 
     /// parse_type:
@@ -396,10 +496,13 @@ impl Parser {
     /// `<identifier>`
     /// | `<identifier> [ <applied-type-parameters> ]`
     pub fn parse_type(&mut self) -> Result<ParsedType> {
+        self.nested(Parser::parse_type_nested)
+    }
+    fn parse_type_nested(&mut self) -> Result<ParsedType> {
         // ensure is <identifier>
-        self.predict(vec![TkType::Identifier])?;
+        self.predict(&[TkType::Identifier])?;
         let type_name = self.parse_access_identifier()?;
-        if self.predict(vec![TkType::OpenBracket]).is_ok() {
+        if self.predict(&[TkType::OpenBracket]).is_ok() {
             let list = self.parse_many(
                 TkType::OpenBracket,
                 TkType::CloseBracket,
@@ -421,35 +524,39 @@ impl Parser {
     ///   <statement>*
     /// }
     pub fn parse_block(&mut self) -> Result<Block> {
+        self.nested(Parser::parse_block_nested)
+    }
+    fn parse_block_nested(&mut self) -> Result<Block> {
         let location = self.peek(0)?.location();
-        self.consume(vec![TkType::OpenBrace])?;
-        let mut block = Block::new(location);
+        self.consume(&[TkType::OpenBrace])?;
+        let mut block = Block::new(location.clone());
         while self.peek(0)?.tk_type() != &TkType::CloseBrace {
-            let stmt = self.parse_statement()?;
-            block.append(stmt);
+            if self.errors.is_some() && self.peek(0)?.tk_type() == &TkType::EOF {
+                break;
+            }
+            let start = self.offset;
+            match self.parse_statement() {
+                Ok(stmt) => block.append(stmt),
+                Err(err) => self.recover(err, start, Boundary::Statement)?,
+            }
         }
-        self.consume(vec![TkType::CloseBrace])?;
+        if let Err(err) = self.consume(&[TkType::CloseBrace]) {
+            // unclosed block ends at the end of file
+            self.recover(err, self.offset, Boundary::Statement)?;
+        }
+        block.location = self.span_from(&location);
         Ok(block)
     }
     pub fn parse_statement(&mut self) -> Result<Statement> {
-        let tok = self.peek(0)?;
-        match tok.tk_type() {
-            TkType::Identifier => {
-                if self.peek(1)?.tk_type() == &TkType::Colon {
-                    let var = self.parse_variable(None)?;
-                    self.consume(vec![TkType::Semicolon])?;
-                    Ok(Statement::variable(tok.location(), var))
-                } else if vec![TkType::OpenParen, TkType::Dot].contains(self.peek(1)?.tk_type()) {
-                    let unary = self.parse_unary()?;
-                    let expr = self.parse_primary(unary)?;
-                    self.consume(vec![TkType::Semicolon])?;
-                    Ok(Statement::expression(tok.location(), expr))
-                } else {
-                    Err(ParseError::not_expected_token(
-                        vec![TkType::Colon, TkType::OpenParen],
-                        tok,
-                    ))
-                }
+        self.nested(Parser::parse_statement_nested)
+    }
+    fn parse_statement_nested(&mut self) -> Result<Statement> {
+        let location = self.peek(0)?.location();
+        match self.peek(0)?.tk_type().clone() {
+            TkType::Identifier if self.peek(1)?.tk_type() == &TkType::Colon => {
+                let var = self.parse_variable(None)?;
+                self.consume(&[TkType::Semicolon])?;
+                Ok(Statement::variable(self.span_from(&location), var))
             }
             // `return 1;`
             TkType::Return => {
@@ -459,41 +566,118 @@ impl Parser {
                 } else {
                     Some(self.parse_expression(None, None)?)
                 };
-                self.consume(vec![TkType::Semicolon])?;
-                Ok(Statement::return_stmt(tok.location(), expr))
+                self.consume(&[TkType::Semicolon])?;
+                Ok(Statement::return_stmt(self.span_from(&location), expr))
             }
             TkType::If => {
                 self.take()?;
                 let mut clauses = vec![];
-                clauses.push((self.parse_expression(None, None)?, self.parse_block()?));
-                while self.consume(vec![TkType::Else]).is_ok() {
+                clauses.push((self.parse_condition()?, self.parse_block()?));
+                while self.consume(&[TkType::Else]).is_ok() {
                     // and remember that else block was optional, so failed at this condition was fine
-                    if self.consume(vec![TkType::If]).is_ok() {
+                    if self.consume(&[TkType::If]).is_ok() {
                         // else if
-                        clauses.push((self.parse_expression(None, None)?, self.parse_block()?));
+                        clauses.push((self.parse_condition()?, self.parse_block()?));
                         continue;
                     } else {
                         // else
+                        let else_block = self.parse_block()?;
                         return Ok(Statement::if_block(
-                            tok.location(),
+                            self.span_from(&location),
                             clauses,
-                            self.parse_block()?,
+                            else_block,
                         ));
                     }
                 }
                 Ok(Statement::if_block(
-                    tok.location(),
+                    self.span_from(&location),
                     clauses,
-                    Block::new(tok.location()),
+                    Block::new(location),
+                ))
+            }
+            // `for x in xs {}`
+            TkType::For => {
+                self.take()?;
+                let name_location = self.peek(0)?.location();
+                let name = self.parse_identifier()?;
+                self.consume(&[TkType::In])?;
+                let iterable = self.parse_condition()?;
+                let block = self.parse_block()?;
+                Ok(Statement::for_loop(
+                    self.span_from(&location),
+                    name,
+                    name_location,
+                    iterable,
+                    block,
                 ))
             }
-            _ => unimplemented!("{}", tok),
+            // any expression, e.g. `println("hello");` or `"{x}".to_string();`
+            _ => {
+                let expr = self.parse_expression(None, None)?;
+                // `foo.bar = 1;`
+                if self.peek(0)?.tk_type() == &TkType::Equal {
+                    if !matches!(expr.value, ExprVariant::MemberAccess(..)) {
+                        return Err(ParseError::invalid_assignment_target(expr.location));
+                    }
+                    self.take()?;
+                    let value = self.parse_expression(None, None)?;
+                    self.consume(&[TkType::Semicolon])?;
+                    return Ok(Statement::assign(self.span_from(&location), expr, value));
+                }
+                self.consume(&[TkType::Semicolon])?;
+                Ok(Statement::expression(self.span_from(&location), expr))
+            }
         }
     }
 }
 
 // for expression
 impl Parser {
+    /// parse_condition:
+    ///
+    /// `x < y` in `if x < y {}`, the `y {}` is not a class construction
+    pub fn parse_condition(&mut self) -> Result<Expr> {
+        let in_condition = std::mem::replace(&mut self.in_condition, true);
+        let result = self.parse_expression(None, None);
+        self.in_condition = in_condition;
+        result
+    }
+    /// parse_if_expression:
+    ///
+    /// if <condition> { <expr> } else { <expr> }
+    /// | if <condition> { <expr> } else <if_expression>
+    pub fn parse_if_expression(&mut self) -> Result<Expr> {
+        let location = self.peek(0)?.location();
+        self.consume(&[TkType::If])?;
+        let condition = self.parse_condition()?;
+        let then_expr = self.parse_branch_expression()?;
+        // the expression must have a value, so else is required
+        self.consume(&[TkType::Else])?;
+        let else_expr = if self.peek(0)?.tk_type() == &TkType::If {
+            self.parse_if_expression()?
+        } else {
+            self.parse_branch_expression()?
+        };
+        Ok(Expr::if_expr(
+            self.span_from(&location),
+            condition,
+            then_expr,
+            else_expr,
+        ))
+    }
+    /// parse_branch_expression:
+    ///
+    /// `{ x }` in `if x < y { x } else { y }`
+    fn parse_branch_expression(&mut self) -> Result<Expr> {
+        self.consume(&[TkType::OpenBrace])?;
+        // braces end the condition, so class construction can be used in the branch again
+        let in_condition = std::mem::replace(&mut self.in_condition, false);
+        let result = self.parse_expression(None, None);
+        self.in_condition = in_condition;
+        let expr = result?;
+        self.consume(&[TkType::CloseBrace])?;
+        Ok(expr)
+    }
     /// parse_expression:
     ///
     /// 1 + 2
@@ -502,25 +686,39 @@ impl Parser {
         left_hand_side: Option<Expr>,
         previous_primary: Option<u64>,
     ) -> Result<Expr> {
-        let unary = self.parse_unary()?;
-        let mut lhs = left_hand_side.unwrap_or(self.parse_primary(unary)?);
-        let mut lookahead = self.peek(0)?;
-        while precedence(lookahead.clone()) >= previous_primary.unwrap_or(1) {
-            let operator = lookahead.clone();
-            self.take()?;
+        self.nested(|parser| parser.parse_expression_nested(left_hand_side, previous_primary))
+    }
+    fn parse_expression_nested(
+        &mut self,
+        left_hand_side: Option<Expr>,
+        previous_primary: Option<u64>,
+    ) -> Result<Expr> {
+        let mut lhs = match left_hand_side {
+            Some(lhs) => lhs,
+            None => {
+                let unary = self.parse_unary()?;
+                self.parse_primary(unary)?
+            }
+        };
+        while precedence(self.peek(0)?) >= previous_primary.unwrap_or(1) {
+            // the left hand side is nested in the binary expression
+            self.deepen()?;
+            let operator = self.take()?.clone();
             let unary = self.parse_unary()?;
             let mut rhs = self.parse_primary(unary)?;
-            lookahead = self.peek(0)?;
-            while precedence(lookahead.clone()) > precedence(operator.clone())
-                || (is_right_associative(lookahead.clone())
-                    && (precedence(lookahead.clone()) == precedence(operator.clone())))
-            {
-                rhs =
-                    self.parse_expression(Some(lhs.clone()), Some(precedence(lookahead.clone())))?;
-                lookahead = self.peek(0)?;
+            loop {
+                let lookahead = self.peek(0)?;
+                if !(precedence(lookahead) > precedence(&operator)
+                    || (is_right_associative(lookahead)
+                        && (precedence(lookahead) == precedence(&operator))))
+                {
+                    break;
+                }
+                let lookahead_precedence = precedence(lookahead);
+                rhs = self.parse_expression(Some(rhs), Some(lookahead_precedence))?;
             }
             lhs = Expr::binary(
-                lhs.location.clone(),
+                lhs.location.to(&rhs.location),
                 lhs,
                 rhs,
                 Operator::from_token(operator),
@@ -536,9 +734,14 @@ impl Parser {
         match tok.tk_type() {
             TkType::OpenParen => self.parse_function_call(unary),
             TkType::Dot => {
-                self.consume(vec![TkType::Dot])?;
+                self.deepen()?;
+                self.consume(&[TkType::Dot])?;
+                // member access is located at the member, e.g. `bar` of `foo.bar`, but spans the
+                // whole expression
+                let mut location = self.peek(0)?.location();
                 let field_name = self.parse_identifier()?;
-                self.parse_primary(Expr::member_access(tok.location(), unary, field_name))
+                location.start = unary.location.start;
+                self.parse_primary(Expr::member_access(location, unary, field_name))
             }
             _ => Ok(unary),
         }
@@ -551,27 +754,28 @@ impl Parser {
     /// | <access_identifier>
     /// | <bool>
     /// | <list>
+    /// | <if_expression>
+    /// | ( <expression> )
     pub fn parse_unary(&mut self) -> Result<Expr> {
-        let tok = self.peek(0)?;
-        match tok.tk_type() {
-            // FIXME: lexer should emit int & float token directly
+        let location = self.peek(0)?.location();
+        match self.peek(0)?.tk_type().clone() {
             TkType::Integer => {
                 let num = self.take()?.value();
-                if num.parse::<i64>().is_ok() {
-                    Ok(Expr::int(tok.location(), num.parse::<i64>().unwrap()))
-                } else if num.parse::<f64>().is_ok() {
-                    Ok(Expr::f64(tok.location(), num.parse::<f64>().unwrap()))
-                } else {
-                    panic!(
-                        "lexing bug causes a number token can't be convert to number: {:?}",
-                        num
-                    )
+                match num.parse::<i64>() {
+                    Ok(i) => Ok(Expr::int(location, i)),
+                    // all digits but too large for i64
+                    Err(_) => Err(ParseError::integer_out_of_range(location, num)),
                 }
             }
+            TkType::Float => {
+                // the lexer emits a float only for digits around a `.`, which always parse
+                let num = self.take()?.value();
+                Ok(Expr::f64(location, num.parse().unwrap()))
+            }
             TkType::Identifier => {
                 let name = self.parse_access_identifier()?;
                 match self.peek(0)?.tk_type() {
-                    TkType::OpenBrace => {
+                    TkType::OpenBrace if !self.in_condition => {
                         let mut field_inits = HashMap::new();
                         let exprs = self.parse_many(
                             TkType::OpenBrace,
@@ -580,7 +784,7 @@ impl Parser {
                             |parser| {
                                 // x: 1
                                 let identifier = parser.take()?.value();
-                                parser.consume(vec![TkType::Colon])?;
+                                parser.consume(&[TkType::Colon])?;
                                 let expr = parser.parse_expression(None, None)?;
                                 Ok((identifier, expr))
                             },
@@ -588,59 +792,87 @@ impl Parser {
                         for (name, expr) in exprs {
                             field_inits.insert(name, expr);
                         }
-                        Ok(Expr::class_construction(tok.location(), name, field_inits))
+                        Ok(Expr::class_construction(
+                            self.span_from(&location),
+                            name,
+                            field_inits,
+                        ))
                     }
-                    _ => Ok(Expr::identifier(tok.location(), name)),
+                    _ => Ok(Expr::identifier(self.span_from(&location), name)),
                 }
             }
             TkType::True => {
                 self.take()?;
-                Ok(Expr::bool(tok.location(), true))
+                Ok(Expr::bool(location, true))
             }
             TkType::False => {
                 self.take()?;
-                Ok(Expr::bool(tok.location(), false))
+                Ok(Expr::bool(location, false))
             }
             TkType::String => self.parse_string(),
             TkType::OpenBracket => {
                 let list = self.parse_list()?;
-                Ok(Expr::list(tok.location(), list))
+                Ok(Expr::list(self.span_from(&location), list))
+            }
+            TkType::If => self.parse_if_expression(),
+            // `(a + b) * c`, the grouped expression is kept as is, parentheses only decide its shape
+            TkType::OpenParen => {
+                self.take()?;
+                // parentheses end the condition, as braces of a branch do
+                let in_condition = std::mem::replace(&mut self.in_condition, false);
+                let result = self.parse_expression(None, None);
+                self.in_condition = in_condition;
+                let expr = result?;
+                self.consume(&[TkType::CloseParen])?;
+                Ok(expr)
             }
             _ => {
                 use TkType::*;
                 Err(ParseError::not_expected_token(
-                    vec![Integer, Identifier, True, False, String, OpenBracket],
-                    tok,
+                    vec![
+                        Integer,
+                        Float,
+                        Identifier,
+                        True,
+                        False,
+                        String,
+                        OpenBracket,
+                        If,
+                        OpenParen,
+                    ],
+                    self.peek(0)?,
                 ))
             }
         }
     }
     pub fn parse_function_call(&mut self, func: Expr) -> Result<Expr> {
-        self.consume(vec![TkType::OpenParen])?;
+        self.consume(&[TkType::OpenParen])?;
 
         let mut args = vec![];
         while self.peek(0)?.tk_type() != &TkType::CloseParen {
-            let identifier = if self
-                .predict(vec![TkType::Identifier, TkType::Colon])
-                .is_ok()
-            {
+            let location = self.peek(0)?.location();
+            let identifier = if self.predict(&[TkType::Identifier, TkType::Colon]).is_ok() {
                 let identifier = self.take()?.value();
-                self.consume(vec![TkType::Colon])?;
+                self.consume(&[TkType::Colon])?;
                 Some(identifier)
             } else {
                 None
             };
             let expr = self.parse_expression(None, None)?;
-            args.push(Argument::new(expr.location.clone(), identifier, expr));
-            if self.predict(vec![TkType::Comma]).is_err() {
+            let location = match identifier {
+                Some(..) => self.span_from(&location),
+                None => expr.location.clone(),
+            };
+            args.push(Argument::new(location, identifier, expr));
+            if self.predict(&[TkType::Comma]).is_err() {
                 break;
             } else {
-                self.consume(vec![TkType::Comma])?;
+                self.consume(&[TkType::Comma])?;
             }
         }
-        self.consume(vec![TkType::CloseParen])?;
+        self.consume(&[TkType::CloseParen])?;
 
-        Ok(Expr::func_call(func.location.clone(), func, args))
+        Ok(Expr::func_call(self.span_from(&func.location), func, args))
     }
     pub fn parse_list(&mut self) -> Result<Vec<Expr>> {
         let list = self.parse_many(
@@ -652,46 +884,83 @@ impl Parser {
         Ok(list)
     }
     pub fn parse_string(&mut self) -> Result<Expr> {
-        self.predict(vec![TkType::String])?;
+        self.predict(&[TkType::String])?;
         let tok = self.take()?;
-        // lexer didn't trim "" of string, so here we have to remove it.
-        let s = tok.value();
-        let s = s.trim_start_matches('"').trim_end_matches('"');
-        self.parse_string_template(tok.location(), s.chars().collect())
+        let location = tok.location();
+        // lexer didn't trim "" of string, so here we have to remove it. only one quote is removed
+        // from each side, since the last character of content can be an escaped quote: `"a\""`
+        let s = tok.text();
+        let s = s.strip_prefix('"').unwrap_or(s);
+        let s = s.strip_suffix('"').unwrap_or(s);
+        let s: Vec<char> = s.chars().collect();
+        self.parse_string_template(&location, &s, 0)
     }
-    fn parse_string_template(&mut self, location: lexer::Location, s: Vec<char>) -> Result<Expr> {
+    /// parse_string_template parses the template `s` from `from`, `{{` and `}}` are braces and
+    /// `{expr}` is the value of `expr`, which can have braces in pairs, e.g. `{Foo { x: 1 }.x}`
+    fn parse_string_template(
+        &mut self,
+        location: &lexer::Location,
+        s: &[char],
+        from: usize,
+    ) -> Result<Expr> {
         let mut tmp_s = String::new();
-        let mut index = 0;
+        let mut index = from;
         while index < s.len() {
             let c = s[index];
             match c {
                 '\\' => {
                     index += 1;
                     if index < s.len() {
-                        tmp_s.push(s[index]);
+                        tmp_s.push(match s[index] {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            '0' => '\0',
+                            // `\"`, `\\`, `\{` and `\}` are the character itself
+                            c => c,
+                        });
                         index += 1;
                     } else {
                         break;
                     }
                 }
+                '{' | '}' if s.get(index + 1) == Some(&c) => {
+                    tmp_s.push(c);
+                    index += 2;
+                }
                 '{' => {
-                    let left_string = Expr::string(location.clone(), tmp_s.clone());
-                    // consume `{`
-                    index += 1;
-                    // reset it
-                    tmp_s = String::new();
-                    while index < s.len() && s[index] != '}' && s[index - 1] != '\\' {
-                        tmp_s.push(s[index]);
-                        index += 1;
-                    }
-                    let mut p = Parser::new(self.file_name.clone(), tmp_s);
+                    // the rest of the template is nested in the concatenation
+                    self.deepen()
+                        .map_err(|_| ParseError::too_deep(location.clone()))?;
+                    let left_string = Expr::string(location.clone(), tmp_s);
+                    let start = index + 1;
+                    let end = interpolation_end(s, start)
+                        .ok_or_else(|| ParseError::unclosed_template(location.clone()))?;
+                    let code: String = s[start..end].iter().collect();
+                    let at = location_in_template(location, s, start);
+                    let tokens = lexer::lex_at(self.file_name.clone(), code, &at);
+                    let mut p = Parser::from_tokens(self.file_name.clone(), tokens);
+                    p.depth = self.depth;
                     let mid_expr = p.parse_expression(None, None)?;
-                    index += 1;
-                    let rest_string =
-                        self.parse_string_template(location.clone(), s[index..].to_vec())?;
+                    p.predict(&[TkType::EOF])?;
+                    // the value is formatted by `ToString` of prelude, the call spans the braces
+                    // as well, so its type is recorded apart from the type of the value
+                    let mid_location = lexer::Location::new(
+                        mid_expr.location.file_name(),
+                        mid_expr.location.line(),
+                        mid_expr.location.column(),
+                        at.start - 1,
+                        location_in_template(location, s, end + 1).start,
+                    );
+                    let mid_expr = Expr::func_call(
+                        mid_location.clone(),
+                        Expr::member_access(mid_location, mid_expr, "to_string"),
+                        vec![],
+                    );
+                    let rest_string = self.parse_string_template(location, s, end + 1)?;
                     let result = Expr::binary(
                         location.clone(),
-                        Expr::binary(location, left_string, mid_expr, Operator::Plus),
+                        Expr::binary(location.clone(), left_string, mid_expr, Operator::Plus),
                         rest_string,
                         Operator::Plus,
                     );
@@ -703,61 +972,199 @@ impl Parser {
                 }
             }
         }
-        Ok(Expr::string(location, tmp_s))
+        Ok(Expr::string(location.clone(), tmp_s))
     }
 }
 
-fn is_right_associative(_op: Token) -> bool {
-    false
+/// interpolation_end returns the index of `}` closing the interpolation starting at `start` of the
+/// template `s`, braces in it are counted, and escaped characters are skipped, so a quoted `}` of
+/// an escaped string doesn't close it
+fn interpolation_end(s: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut index = start;
+    while index < s.len() {
+        match s[index] {
+            '\\' => index += 1,
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+        index += 1;
+    }
+    None
 }
 
-fn precedence(op: Token) -> u64 {
-    match op.tk_type() {
-        TkType::Plus => 2,
-        _ => 0,
+/// location_in_template locates the character at `index` of the template `s` located at
+/// `location`, which starts at the opening `"`
+fn location_in_template(location: &lexer::Location, s: &[char], index: usize) -> lexer::Location {
+    let mut line = location.line();
+    // skip the opening `"`
+    let mut column = location.column() + 1;
+    let mut start = location.start + 1;
+    for c in &s[..index] {
+        if *c == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += 1;
+        }
+        start += c.len_utf8() as u32;
     }
+    lexer::Location::new(location.file_name(), line, column, start, start)
 }
 
 /// This block puts fundamental helpers
 impl Parser {
+    /// nested parses a node nested in the node being parsed, then restores the depth
+    fn nested<T, F>(&mut self, parse: F) -> Result<T>
+    where
+        F: FnOnce(&mut Parser) -> Result<T>,
+    {
+        let depth = self.depth;
+        let result = self.deepen().and_then(|_| parse(self));
+        self.depth = depth;
+        result
+    }
+    /// deepen nests the following nodes one more level, until `nested` restores the depth
+    fn deepen(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(ParseError::too_deep(self.peek(0)?.location()));
+        }
+        Ok(())
+    }
     pub fn parse_program<T: Into<String> + Clone>(file_name: T, code: T) -> Result<Module> {
         let mut parser = Parser::new(file_name, code);
         parser.parse_module(TkType::EOF)
     }
+    /// try_parse parses the whole code as `parse_program`, any input gives a module or an error
+    /// without panic, it's the entry point of fuzzing
+    pub fn try_parse(file_name: &str, code: &str) -> Result<Module> {
+        Parser::parse_program(file_name, code)
+    }
+    /// parse_program_recovering parses the whole code as `parse_module_recovering`
+    pub fn parse_program_recovering<T: Into<String> + Clone>(
+        file_name: T,
+        code: T,
+    ) -> (Module, Vec<ParseError>) {
+        let mut parser = Parser::new(file_name, code);
+        parser.parse_module_recovering(TkType::EOF)
+    }
+    /// parse_expression_str parses the whole code as an expression, e.g. `fib(10)`, a trailing `;`
+    /// is allowed, so a statement of expression can be evaluated as is
+    pub fn parse_expression_str<T: Into<String> + Clone>(file_name: T, code: T) -> Result<Expr> {
+        let mut parser = Parser::new(file_name, code);
+        let expr = parser.parse_expression(None, None)?;
+        let _ = parser.consume(&[TkType::Semicolon]);
+        parser.predict(&[TkType::EOF])?;
+        Ok(expr)
+    }
+    /// parse_statement_str parses the whole code as a statement, e.g. `x: int = 1;`
+    pub fn parse_statement_str<T: Into<String> + Clone>(
+        file_name: T,
+        code: T,
+    ) -> Result<Statement> {
+        let mut parser = Parser::new(file_name, code);
+        let stmt = parser.parse_statement()?;
+        parser.predict(&[TkType::EOF])?;
+        Ok(stmt)
+    }
     /// new create Parser from code
     pub fn new<T: Into<String> + Clone>(f_name: T, code: T) -> Parser {
         let file_name = f_name.clone().into();
         let tokens = lexer::lex(f_name, code);
+        Parser::from_tokens(file_name, tokens)
+    }
+    /// from_tokens create Parser from tokens lexed before, e.g. to measure lexing alone
+    pub fn from_tokens<T: Into<String>>(file_name: T, tokens: Vec<Token>) -> Parser {
         Parser {
-            file_name,
+            file_name: file_name.into(),
             tokens,
             offset: 0,
+            in_condition: false,
+            depth: 0,
+            errors: None,
+        }
+    }
+    /// recover records the error and skips tokens from `start`, the first token of the failed
+    /// definition or statement, to the boundary, the error is returned when not recovering
+    fn recover(&mut self, err: ParseError, start: usize, boundary: Boundary) -> Result<()> {
+        match &mut self.errors {
+            Some(errors) => errors.push(err),
+            None => return Err(err),
+        }
+        self.offset = start;
+        let mut depth = 0;
+        loop {
+            match self.peek(0)?.tk_type() {
+                TkType::EOF => return Ok(()),
+                TkType::Semicolon => {
+                    self.take()?;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                TkType::OpenBrace => {
+                    self.take()?;
+                    depth += 1;
+                }
+                TkType::CloseBrace => {
+                    // `}` of the enclosing block
+                    if depth == 0 {
+                        if let Boundary::Statement = boundary {
+                            return Ok(());
+                        }
+                    }
+                    self.take()?;
+                    depth = depth.max(1) - 1;
+                    let continued = match boundary {
+                        Boundary::Top => false,
+                        // `} else {`
+                        Boundary::Statement => self.peek(0)?.tk_type() == &TkType::Else,
+                    };
+                    if depth == 0 && !continued {
+                        return Ok(());
+                    }
+                }
+                _ => {
+                    self.take()?;
+                }
+            }
+        }
+    }
+    /// span_from spans from `start` to the end of the last taken token
+    fn span_from(&self, start: &Location) -> Location {
+        match self.offset.checked_sub(1).and_then(|n| self.tokens.get(n)) {
+            Some(tok) => start.to(&tok.location()),
+            None => start.clone(),
         }
     }
     /// peek get the token by (current position + n)
-    pub fn peek(&self, n: usize) -> Result<Token> {
+    pub fn peek(&self, n: usize) -> Result<&Token> {
         self.get_token(self.offset + n)
     }
     /// take increment current token position
-    pub fn take(&mut self) -> Result<Token> {
+    pub fn take(&mut self) -> Result<&Token> {
         self.offset += 1;
         self.get_token(self.offset - 1)
     }
-    fn get_token(&self, n: usize) -> Result<Token> {
-        if self.tokens.len() <= n {
-            let loc = match self.tokens.last() {
-                None => Location::new(self.file_name.clone(), 0, 0, 0, 0),
-                Some(tk) => tk.location(),
-            };
-            Err(ParseError::eof(&loc))
-        } else {
-            Ok(self.tokens[n].clone())
+    fn get_token(&self, n: usize) -> Result<&Token> {
+        match self.tokens.get(n) {
+            Some(tok) => Ok(tok),
+            None => {
+                let loc = match self.tokens.last() {
+                    None => Location::new(self.file_name.clone(), 0, 0, 0, 0),
+                    Some(tk) => tk.location(),
+                };
+                Err(ParseError::eof(&loc))
+            }
         }
     }
     fn matched(&self, token_type: &TkType, expected_type: &TkType) -> bool {
         *token_type == *expected_type
     }
-    pub fn consume(&mut self, wants: Vec<TkType>) -> Result<()> {
+    pub fn consume(&mut self, wants: &[TkType]) -> Result<()> {
         let len = wants.len();
         self.predict(wants)?;
         for _ in 1..=len {
@@ -765,25 +1172,27 @@ impl Parser {
         }
         Ok(())
     }
-    pub fn predict(&self, wants: Vec<TkType>) -> Result<()> {
+    pub fn predict(&self, wants: &[TkType]) -> Result<()> {
         for (i, v) in wants.iter().enumerate() {
             let tk = self.peek(i)?;
             if !self.matched(tk.tk_type(), v) {
-                return Err(ParseError::not_expected_token(wants, tk));
+                return Err(ParseError::not_expected_token(wants.to_vec(), tk));
             }
         }
         Ok(())
     }
-    pub fn predict_one_of(&self, wants: Vec<TkType>) -> Result<()> {
+    pub fn predict_one_of(&self, wants: &[TkType]) -> Result<()> {
         let tok = self.peek(0)?;
-        for want in &wants {
+        for want in wants {
             if self.matched(tok.tk_type(), want) {
                 return Ok(());
             }
         }
-        Err(ParseError::not_expected_token(wants, tok))
+        Err(ParseError::not_expected_token(wants.to_vec(), tok))
     }
 
+    /// parse_many parses `step_fn` separated by `separator` between `open_token` and `close_token`,
+    /// a trailing separator is allowed, e.g. `[1, 2,]`
     fn parse_many<F, T>(
         &mut self,
         open_token: TkType,
@@ -795,18 +1204,18 @@ impl Parser {
         F: Fn(&mut Parser) -> Result<T>,
     {
         let mut result = vec![];
-        self.consume(vec![open_token])?;
+        self.consume(&[open_token])?;
         while self.peek(0)?.tk_type() != &close_token {
             // the step like parse parameter or argument we want to repeat
             result.push(step_fn(self)?);
             // parse separator or leave loop and consume the close terminate symbol
-            if self.predict(vec![separator.clone()]).is_err() {
+            if self.predict(std::slice::from_ref(&separator)).is_err() {
                 break;
             } else {
-                self.consume(vec![separator.clone()])?;
+                self.consume(std::slice::from_ref(&separator))?;
             }
         }
-        self.consume(vec![close_token])?;
+        self.consume(&[close_token])?;
         Ok(result)
     }
 
@@ -0,0 +1,76 @@
+//! precedence is the table of binary operators `parse_expression` climbs, an operator of a later
+//! level binds tighter, e.g. `a + b * c` is `a + (b * c)`, and operators of the same level group to
+//! the left unless they're right associative, e.g. `a - b - c` is `(a - b) - c`
+use super::lexer::{TkType, Token};
+use crate::ast::Operator;
+
+/// LEVELS lists binary operators by levels from the loosest binding one
+const LEVELS: &[&[TkType]] = &[
+    // logical
+    &[TkType::Or],
+    &[TkType::And],
+    // comparison
+    &[
+        TkType::EqualEqual,
+        TkType::NotEqual,
+        TkType::Less,
+        TkType::LessEqual,
+        TkType::Greater,
+        TkType::GreaterEqual,
+    ],
+    // arithmetic
+    &[TkType::Plus, TkType::Minus],
+    &[TkType::Multiple, TkType::Divide],
+    &[TkType::Power],
+];
+
+/// RIGHT_ASSOCIATIVE lists operators grouping to the right, e.g. `a op b op c` is `a op (b op c)`,
+/// operators of a level must group the same way
+const RIGHT_ASSOCIATIVE: &[TkType] = &[
+    // `a ** b ** c` is `a ** (b ** c)`, as in math
+    TkType::Power,
+];
+
+/// precedence returns the level of `op` from 1, 0 for tokens ending an expression
+pub(crate) fn precedence(op: &Token) -> u64 {
+    level(op.tk_type())
+}
+
+pub(crate) fn is_right_associative(op: &Token) -> bool {
+    RIGHT_ASSOCIATIVE.contains(op.tk_type())
+}
+
+/// operator_precedence returns the level of a parsed operator, the formatter puts an operand binding
+/// looser than its operator in parentheses
+pub(crate) fn operator_precedence(op: &Operator) -> u64 {
+    level(&token_type(op))
+}
+
+pub(crate) fn is_operator_right_associative(op: &Operator) -> bool {
+    RIGHT_ASSOCIATIVE.contains(&token_type(op))
+}
+
+fn level(tk_type: &TkType) -> u64 {
+    LEVELS
+        .iter()
+        .position(|operators| operators.contains(tk_type))
+        .map_or(0, |index| index as u64 + 1)
+}
+
+fn token_type(op: &Operator) -> TkType {
+    match op {
+        Operator::Plus => TkType::Plus,
+        Operator::Minus => TkType::Minus,
+        Operator::Multiply => TkType::Multiple,
+        Operator::Divide => TkType::Divide,
+        Operator::Power => TkType::Power,
+        Operator::Equal => TkType::EqualEqual,
+        Operator::NotEqual => TkType::NotEqual,
+        Operator::Less => TkType::Less,
+        Operator::LessEqual => TkType::LessEqual,
+        Operator::Greater => TkType::Greater,
+        Operator::GreaterEqual => TkType::GreaterEqual,
+        Operator::And => TkType::And,
+        Operator::Or => TkType::Or,
+    }
+}
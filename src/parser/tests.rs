@@ -41,8 +41,8 @@ fn parse_function_with_expression_body() {
             None,
             "add",
             vec![
-                Parameter::new("x", ParsedType::type_name("int")),
-                Parameter::new("y", ParsedType::type_name("int")),
+                Parameter::new(Location::from(1, 4), "x", ParsedType::type_name("int")),
+                Parameter::new(Location::from(1, 12), "y", ParsedType::type_name("int")),
             ],
             ParsedType::type_name("int"),
             Body::Expr(Expr::binary(
@@ -97,6 +97,27 @@ fn parse_variable_define() {
     )
 }
 
+#[test]
+fn parse_variable_define_with_float_value() {
+    let code = "\
+    x: f64 = 2.0;
+    ";
+
+    let mut parser = Parser::new("", code);
+
+    let var = parser.parse_variable(None).unwrap();
+    assert_eq!(
+        var,
+        Variable::new(
+            Location::from(1, 0),
+            None,
+            "x",
+            ParsedType::type_name("f64"),
+            Expr::f64(Location::from(1, 9), 2.0)
+        )
+    )
+}
+
 #[test]
 fn parse_variable_define_with_list_value() {
     let code = "\
@@ -150,6 +171,179 @@ fn parse_statement_if_block() {
     )
 }
 
+#[test]
+fn parse_statement_if_block_with_comparison() {
+    let code = "if x < y {}";
+
+    let mut parser = Parser::new("", code);
+
+    assert_eq!(
+        parser.parse_statement().unwrap(),
+        Statement::if_block(
+            Location::from(1, 0),
+            vec![(
+                Expr::binary(
+                    Location::from(1, 3),
+                    Expr::identifier(Location::from(1, 3), "x"),
+                    Expr::identifier(Location::from(1, 7), "y"),
+                    Operator::Less
+                ),
+                Block::new(Location::from(1, 9))
+            )],
+            Block::new(Location::from(1, 0))
+        )
+    )
+}
+
+#[test]
+fn parse_statement_for() {
+    let code = "for x in xs {}";
+
+    let mut parser = Parser::new("", code);
+
+    assert_eq!(
+        parser.parse_statement().unwrap(),
+        Statement::for_loop(
+            Location::from(1, 0),
+            "x",
+            Location::from(1, 4),
+            Expr::identifier(Location::from(1, 9), "xs"),
+            Block::new(Location::from(1, 12))
+        )
+    )
+}
+
+#[test]
+fn parse_statement_assign_to_member() {
+    let code = "foo.bar.x = 1;";
+
+    let mut parser = Parser::new("", code);
+
+    assert_eq!(
+        parser.parse_statement().unwrap(),
+        Statement::assign(
+            Location::from(1, 0),
+            Expr::member_access(
+                Location::from(1, 8),
+                Expr::member_access(
+                    Location::from(1, 4),
+                    Expr::identifier(Location::from(1, 0), "foo"),
+                    "bar"
+                ),
+                "x"
+            ),
+            Expr::int(Location::from(1, 12), 1)
+        )
+    )
+}
+
+#[test]
+fn parse_statement_parenthesized_call() {
+    let code = "(f());";
+
+    let mut parser = Parser::new("", code);
+
+    assert_eq!(
+        parser.parse_statement().unwrap(),
+        Statement::expression(
+            Location::from(1, 0),
+            Expr::func_call(
+                Location::from(1, 1),
+                Expr::identifier(Location::from(1, 1), "f"),
+                vec![]
+            )
+        )
+    )
+}
+
+#[test]
+fn parse_statement_assign_to_call_is_invalid() {
+    let code = "foo.bar() = 1;";
+    let mut parser = Parser::new("", code);
+    let err = parser.parse_statement().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ":1:4 only field can be assigned, e.g. `foo.bar = 1;`"
+    );
+}
+
+#[test]
+fn parse_statement_call_static_method() {
+    let code = "List::push(l, 1);";
+    let mut parser = Parser::new("", code);
+    assert_eq!(
+        parser.parse_statement().unwrap(),
+        Statement::expression(
+            Location::from(1, 0),
+            Expr::func_call(
+                Location::from(1, 0),
+                Expr::identifier(Location::from(1, 0), "List::push"),
+                vec![
+                    Argument::new(
+                        Location::from(1, 11),
+                        None,
+                        Expr::identifier(Location::from(1, 11), "l")
+                    ),
+                    Argument::new(
+                        Location::from(1, 14),
+                        None,
+                        Expr::int(Location::from(1, 14), 1)
+                    ),
+                ]
+            )
+        )
+    )
+}
+
+#[test]
+fn parse_statement_of_any_expression() {
+    for code in ["\"{x}\".to_string();", "1 + f(x);", "[1, 2];", "x;"].iter() {
+        let mut parser = Parser::new("", code);
+        let expr = Parser::parse_expression_str("", code).unwrap();
+        assert_eq!(
+            parser.parse_statement().unwrap(),
+            Statement::expression(Location::from(1, 0), expr)
+        );
+    }
+    let mut parser = Parser::new("", "1 + 2 = 3;");
+    assert_eq!(
+        parser.parse_statement().unwrap_err().to_string(),
+        ":1:0 only field can be assigned, e.g. `foo.bar = 1;`"
+    );
+}
+
+#[test]
+fn parse_expr_if() {
+    let code = "if x < y { x } else if b { Foo {} } else { y }";
+    let mut parser = Parser::new("", code);
+    assert_eq!(
+        parser.parse_expression(None, None).unwrap(),
+        Expr::if_expr(
+            Location::from(1, 0),
+            Expr::binary(
+                Location::from(1, 3),
+                Expr::identifier(Location::from(1, 3), "x"),
+                Expr::identifier(Location::from(1, 7), "y"),
+                Operator::Less
+            ),
+            Expr::identifier(Location::from(1, 11), "x"),
+            Expr::if_expr(
+                Location::from(1, 20),
+                Expr::identifier(Location::from(1, 23), "b"),
+                Expr::class_construction(Location::from(1, 27), "Foo", HashMap::new()),
+                Expr::identifier(Location::from(1, 43), "y")
+            )
+        )
+    )
+}
+
+#[test]
+fn parse_expr_if_requires_else() {
+    let code = "if x { 1 };";
+    let mut parser = Parser::new("", code);
+    assert!(parser.parse_expression(None, None).is_err());
+}
+
 #[test]
 fn parse_expr_string() {
     let code = "\
@@ -160,12 +354,22 @@ fn parse_expr_string() {
 
     let s = parser.parse_string().unwrap();
     let location = Location::from(1, 0);
+    // the value is located in the template
+    let a_location = Location::from(1, 17);
     let expected = Expr::binary(
         location.clone(),
         Expr::binary(
             location.clone(),
             Expr::string(location.clone(), "str \"\\ value "),
-            Expr::identifier(location.clone(), "a"),
+            Expr::func_call(
+                a_location.clone(),
+                Expr::member_access(
+                    a_location.clone(),
+                    Expr::identifier(a_location, "a"),
+                    "to_string",
+                ),
+                vec![],
+            ),
             Operator::Plus,
         ),
         Expr::string(location, ""),
@@ -174,6 +378,185 @@ fn parse_expr_string() {
     assert_eq!(s, expected)
 }
 
+#[test]
+fn parse_expr_string_escapes() {
+    let code = "\"a\\tb\\n\\\"\"";
+    let mut parser = Parser::new("", code);
+    assert_eq!(
+        parser.parse_string().unwrap(),
+        Expr::string(Location::from(1, 0), "a\tb\n\"")
+    )
+}
+
+#[test]
+fn parse_expr_string_template_braces() {
+    let template = |code: &str| {
+        let mut parser = Parser::new("", code);
+        parser.parse_string()
+    };
+    assert_eq!(
+        template("\"{{a}} \\{ \\} }\"").unwrap(),
+        Expr::string(Location::from(1, 0), "{a} { } }")
+    );
+    // braces in the value go in pairs
+    assert_eq!(
+        grouped("\"{{{Foo { x: 1 }.x}}} {b + c}\""),
+        "((\"{\" + Foo {x}.x.to_string()) + ((\"} \" + (b + c).to_string()) + \"\"))"
+    );
+    for code in ["\"{a\"", "\"{{ {a\"", "\"{a {b}\""].iter() {
+        assert_eq!(
+            template(code).unwrap_err().to_string(),
+            ":1:0 `{` in string template is not closed by `}`"
+        );
+    }
+    assert!(template("\"{a b}\"").is_err());
+}
+
+#[test]
+fn parse_expr_operator_precedence() {
+    let code = "1 - 2 * 3";
+    let mut parser = Parser::new("", code);
+    let expr = parser.parse_expression(None, None).unwrap();
+    assert_eq!(
+        expr,
+        Expr::binary(
+            Location::from(1, 0),
+            Expr::int(Location::from(1, 0), 1),
+            Expr::binary(
+                Location::from(1, 4),
+                Expr::int(Location::from(1, 4), 2),
+                Expr::int(Location::from(1, 8), 3),
+                Operator::Multiply
+            ),
+            Operator::Minus
+        )
+    );
+}
+
+#[test]
+fn parse_expr_comparison_precedence() {
+    let code = "1 + 2 >= 3";
+    let mut parser = Parser::new("", code);
+    let expr = parser.parse_expression(None, None).unwrap();
+    assert_eq!(
+        expr,
+        Expr::binary(
+            Location::from(1, 0),
+            Expr::binary(
+                Location::from(1, 0),
+                Expr::int(Location::from(1, 0), 1),
+                Expr::int(Location::from(1, 4), 2),
+                Operator::Plus
+            ),
+            Expr::int(Location::from(1, 9), 3),
+            Operator::GreaterEqual
+        )
+    );
+}
+
+#[test]
+fn parse_expr_logical_precedence() {
+    let code = "a or b and c";
+    let mut parser = Parser::new("", code);
+    let expr = parser.parse_expression(None, None).unwrap();
+    assert_eq!(
+        expr,
+        Expr::binary(
+            Location::from(1, 0),
+            Expr::identifier(Location::from(1, 0), "a"),
+            Expr::binary(
+                Location::from(1, 5),
+                Expr::identifier(Location::from(1, 5), "b"),
+                Expr::identifier(Location::from(1, 11), "c"),
+                Operator::And
+            ),
+            Operator::Or
+        )
+    );
+}
+
+#[test]
+fn parse_expr_precedence_table() {
+    // every level binds tighter than the ones before it
+    assert_eq!(
+        grouped("a or b and c == d + e * f"),
+        "(a or (b and (c == (d + (e * f)))))"
+    );
+    assert_eq!(
+        grouped("a * b + c / d < e - f and g or h"),
+        "(((((a * b) + (c / d)) < (e - f)) and g) or h)"
+    );
+    // operators of a level group to the left
+    assert_eq!(grouped("a - b + c - d"), "(((a - b) + c) - d)");
+    assert_eq!(grouped("a / b * c"), "((a / b) * c)");
+    assert_eq!(grouped("a < b == c != d"), "(((a < b) == c) != d)");
+    assert_eq!(
+        grouped("a and b and c or d or e"),
+        "((((a and b) and c) or d) or e)"
+    );
+    // a tighter level in the middle doesn't end the looser one
+    assert_eq!(grouped("a + b * c + d"), "((a + (b * c)) + d)");
+    assert_eq!(
+        grouped("a or b + c * d == e"),
+        "(a or ((b + (c * d)) == e))"
+    );
+    // calls and member access bind tighter than any operator
+    assert_eq!(grouped("f(a + b) * c.d"), "(f((a + b)) * c.d)");
+}
+
+#[test]
+fn parse_expr_right_associative() {
+    assert_eq!(grouped("a ** b ** c"), "(a ** (b ** c))");
+    // `**` binds tighter than `*`, which ends the right hand side of it
+    assert_eq!(grouped("a ** b * c ** d"), "((a ** b) * (c ** d))");
+    assert_eq!(
+        grouped("a * b ** c ** d + e"),
+        "((a * (b ** (c ** d))) + e)"
+    );
+    assert_eq!(grouped("a ** b ** c < d"), "((a ** (b ** c)) < d)");
+}
+
+#[test]
+fn parse_expr_parentheses() {
+    // parentheses group against precedence and associativity
+    assert_eq!(grouped("(a + b) * c"), "((a + b) * c)");
+    assert_eq!(grouped("a - (b - c)"), "(a - (b - c))");
+    assert_eq!(grouped("(a ** b) ** c"), "((a ** b) ** c)");
+    assert_eq!(grouped("(a or b) and c"), "((a or b) and c)");
+    assert_eq!(grouped("((a))"), "a");
+    assert_eq!(grouped("(a + b).c(d)"), "(a + b).c(d)");
+    // a class construction in parentheses is not ended by a condition
+    let mut parser = Parser::new("", "(Point { x: 1 }).x == 1 {");
+    let condition = parser.parse_condition().unwrap();
+    assert!(matches!(condition.value, ExprVariant::Binary(..)));
+    let err = Parser::new("", "(a + b")
+        .parse_expression(None, None)
+        .unwrap_err();
+    assert_eq!(err.to_string(), ":1:6 expected one of `)`  but got <eof>");
+}
+
+#[test]
+fn parse_expr_integer_out_of_range() {
+    let code = "99999999999999999999";
+    let mut parser = Parser::new("", code);
+    let err = parser.parse_expression(None, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ":1:0 integer literal `99999999999999999999` is out of range of type `int`"
+    );
+}
+
+#[test]
+fn parse_expr_float_beyond_int_range() {
+    let code = "99999999999999999999.5";
+    let mut parser = Parser::new("", code);
+    let expr = parser.parse_expression(None, None).unwrap();
+    assert_eq!(
+        expr,
+        Expr::f64(Location::from(1, 0), 99999999999999999999.5)
+    );
+}
+
 #[test]
 fn parse_expr_class_construction() {
     let code = "Car { name: \"\", price: 10000 }";
@@ -220,14 +603,22 @@ fn parse_class() {
                     Location::from(3, 2),
                     None,
                     "new",
-                    vec![Parameter::new("name", ParsedType::type_name("string"))],
+                    vec![Parameter::new(
+                        Location::from(3, 6),
+                        "name",
+                        ParsedType::type_name("string")
+                    )],
                     ParsedType::type_name("Car"),
                 )),
                 ClassMember::Method(Function::new_declaration(
                     Location::from(4, 0),
                     None,
                     "bar",
-                    vec![Parameter::new("i", ParsedType::type_name("int"))],
+                    vec![Parameter::new(
+                        Location::from(4, 4),
+                        "i",
+                        ParsedType::type_name("int")
+                    )],
                     ParsedType::type_name("void"),
                 )),
             ]
@@ -273,6 +664,69 @@ fn parse_class_with_type_parameters() {
     )
 }
 
+#[test]
+fn parse_class_with_type_parameters_and_parent() {
+    let code = "class Foo[T] <: Bar {}";
+
+    let mut parser = Parser::new("", code);
+    let class = parser.parse_class(None).unwrap();
+    assert_eq!(
+        class,
+        Class::new(
+            Location::from(1, 0),
+            None,
+            vec!["Bar".to_string()],
+            "Foo",
+            vec![TypeParameter::new("T", vec![])],
+            vec![],
+        )
+    )
+}
+
+#[test]
+fn parse_generic_function() {
+    let code = "max[T <: Ord + ToString, U](a: T, b: U): T = a;";
+
+    let mut parser = Parser::new("", code);
+    let f = match parser.parse_top_ast().unwrap() {
+        TopAst::Function(f) => f,
+        top => panic!("expected a function but got {:?}", top),
+    };
+    assert_eq!(
+        f.type_parameters,
+        vec![
+            TypeParameter::new(
+                "T",
+                vec![
+                    ParsedType::type_name("Ord"),
+                    ParsedType::type_name("ToString")
+                ]
+            ),
+            TypeParameter::new("U", vec![]),
+        ]
+    );
+    assert_eq!(f.parameters[1].typ, ParsedType::type_name("U"));
+}
+
+#[test]
+fn parse_trait_inherit() {
+    let code = "trait Foo <: Bar, Baz {}";
+
+    let mut parser = Parser::new("", code);
+    let t = parser.parse_trait(None).unwrap();
+    assert_eq!(
+        t,
+        Trait::new(
+            Location::from(1, 0),
+            None,
+            vec!["Bar".to_string(), "Baz".to_string()],
+            "Foo",
+            vec![],
+            vec![],
+        )
+    )
+}
+
 #[test]
 fn module() {
     let code = "module foo.bar";
@@ -328,3 +782,242 @@ fn parse_tag() {
     let tag = parser.parse_tag().unwrap().unwrap();
     assert_eq!(tag, Tag::new("builtin", vec![]))
 }
+
+#[test]
+fn serialize_to_json() {
+    let code = "x + 1";
+
+    let mut parser = Parser::new("", code);
+    let expr = parser.parse_expression(None, None).unwrap();
+    let location = |column: u32, start: u32, end: u32| serde_json::json!({"file_name": "", "line": 1, "column": column, "start": start, "end": end});
+    assert_eq!(
+        serde_json::to_value(&expr).unwrap(),
+        serde_json::json!({
+            "location": location(0, 0, 5),
+            "value": {"Binary": [
+                {"location": location(0, 0, 1), "value": {"Identifier": "x"}},
+                {"location": location(4, 4, 5), "value": {"Int": 1}},
+                "Plus"
+            ]}
+        })
+    )
+}
+
+#[test]
+fn parse_expression_from_string() {
+    assert_eq!(
+        Parser::parse_expression_str("", "x + 1;").unwrap(),
+        Expr::binary(
+            Location::from(1, 0),
+            Expr::identifier(Location::from(1, 0), "x"),
+            Expr::int(Location::from(1, 4), 1),
+            Operator::Plus
+        )
+    );
+    assert_eq!(
+        Parser::parse_expression_str("", "x + 1 y")
+            .unwrap_err()
+            .to_string(),
+        ":1:6 expected one of `<eof>`  but got <identifier>"
+    );
+}
+
+#[test]
+fn parse_statement_from_string() {
+    assert_eq!(
+        Parser::parse_statement_str("", "return 1;").unwrap(),
+        Statement::return_stmt(
+            Location::from(1, 0),
+            Some(Expr::int(Location::from(1, 7), 1))
+        )
+    );
+    assert!(Parser::parse_statement_str("", "return 1").is_err());
+    assert!(Parser::parse_statement_str("", "return 1; return 2;").is_err());
+}
+
+#[test]
+fn nodes_span_their_whole_text() {
+    let code = "module main
+main(): int {
+  p.move(x: 1, [2]);
+  if p.x { return; }
+  return p.x + 3;
+}";
+    let module = Parser::parse_program("", code).unwrap();
+    let text =
+        |location: &Location| code[location.start as usize..location.end as usize].to_string();
+    let f = match &module.top_list[0] {
+        TopAst::Function(f) => f,
+        _ => unreachable!(),
+    };
+    assert_eq!(text(&f.location), &code[12..]);
+    let block = match &f.body {
+        Some(Body::Block(block)) => block,
+        _ => unreachable!(),
+    };
+    assert_eq!(text(&block.location), &code[24..]);
+    let stmts = &block.statements;
+    assert_eq!(text(&stmts[0].location), "p.move(x: 1, [2]);");
+    assert_eq!(text(&stmts[1].location), "if p.x { return; }");
+    assert_eq!(text(&stmts[2].location), "return p.x + 3;");
+    match &stmts[0].value {
+        StatementVariant::Expression(call) => {
+            assert_eq!(text(&call.location), "p.move(x: 1, [2])");
+            // member access points at the member
+            assert_eq!(call.location.column(), 4);
+            match &call.value {
+                ExprVariant::FuncCall(_, args) => {
+                    assert_eq!(text(&args[0].location), "x: 1");
+                    assert_eq!(text(&args[1].location), "[2]");
+                }
+                _ => unreachable!(),
+            }
+        }
+        _ => unreachable!(),
+    }
+    match &stmts[2].value {
+        StatementVariant::Return(Some(expr)) => assert_eq!(text(&expr.location), "p.x + 3"),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn recover_from_errors() {
+    let code = "module main
+foo(: int;
+main(): void {
+  x: int = ;
+  if x { return 1 } else { y: int = 2; }
+  println(\"ok\");
+}
+class A { x: int = }
+bar(): int = 1;
+";
+    let (module, errors) = Parser::parse_program_recovering("", code);
+    let lines: Vec<u32> = errors.iter().map(|err| err.location().line()).collect();
+    assert_eq!(lines, vec![2, 4, 5, 8]);
+    let names: Vec<&str> = module
+        .top_list
+        .iter()
+        .map(|top| match top {
+            TopAst::Function(f) => f.name.as_str(),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(names, vec!["main", "bar"]);
+    match &module.top_list[0] {
+        TopAst::Function(Function {
+            body: Some(Body::Block(block)),
+            ..
+        }) => {
+            // `if` is kept with its else block, only `return 1` of it failed
+            assert_eq!(block.statements.len(), 2);
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn trailing_commas() {
+    // each trailing comma ends its line, so tokens after it are located the same without it
+    let code = "module main
+import foo(bar, baz,
+)
+@derive(Eq, Hash,
+)
+class Point[T, U,
+] <: Eq, Hash,
+{
+  x: int;
+  ::new(x: int, y: int,
+  ): Point[int, string,
+  ] = Point { x: x, y: y,
+  };
+}
+trait Shape <: Eq,
+{}
+main(): void {
+  println(add(1, b: 2,
+  ), [1, 2,
+  ]);
+}
+";
+    let without_trailing_commas = code.replace(",\n", "\n");
+    assert_eq!(
+        Parser::parse_program("", code).unwrap(),
+        Parser::parse_program("", without_trailing_commas.as_str()).unwrap()
+    );
+}
+
+#[test]
+fn recover_from_unclosed_block() {
+    let code = "module main
+main(): void {
+  x: int = 1;
+  y: int = x";
+    let (module, errors) = Parser::parse_program_recovering("", code);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(module.name, "main");
+    assert_eq!(module.top_list.len(), 1);
+    assert!(Parser::parse_program("", code).is_err());
+}
+
+#[test]
+fn invalid_tokens_are_reported() {
+    let errors: Vec<String> = ["x: int = 1 # 2;", "x: string = \"a;", "x: string = \"{a\";"]
+        .iter()
+        .map(|code| {
+            let code = format!("module main\n{}", code);
+            Parser::try_parse("", &code).unwrap_err().to_string()
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":2:11 unknown character `#`",
+            ":2:12 string literal is not closed by `\"`",
+            ":2:12 `{` in string template is not closed by `}`",
+        ]
+    );
+}
+
+#[test]
+fn nesting_is_limited() {
+    let chain = |depth: usize| format!("module main\nx: int = {}1;", "1 + ".repeat(depth));
+    assert!(Parser::try_parse("", &chain(MAX_NESTING - 2)).is_ok());
+    let err = Parser::try_parse("", &chain(MAX_NESTING)).unwrap_err();
+    assert_eq!(err.message(), "too deep");
+    let blocks = |depth: usize| {
+        format!(
+            "module main\nmain(): void {{ {}{} }}",
+            "if true { ".repeat(depth),
+            "} ".repeat(depth)
+        )
+    };
+    assert!(Parser::try_parse("", &blocks(MAX_NESTING / 2 - 2)).is_ok());
+    assert!(Parser::try_parse("", &blocks(MAX_NESTING)).is_err());
+}
+
+/// grouped parses `code` as an expression and shows it with binary expressions in parentheses
+fn grouped(code: &str) -> String {
+    fn show(expr: &Expr) -> String {
+        match &expr.value {
+            ExprVariant::Binary(lhs, rhs, op) => format!("({} {} {})", show(lhs), op, show(rhs)),
+            ExprVariant::Identifier(name) => name.clone(),
+            ExprVariant::String(s) => format!("{:?}", s),
+            ExprVariant::ClassConstruction(name, fields) => {
+                let mut fields: Vec<&str> = fields.keys().map(|field| field.as_str()).collect();
+                fields.sort_unstable();
+                format!("{} {{{}}}", name, fields.join(", "))
+            }
+            ExprVariant::MemberAccess(from, name) => format!("{}.{}", show(from), name),
+            ExprVariant::FuncCall(f, args) => {
+                let args: Vec<String> = args.iter().map(|arg| show(&arg.expr)).collect();
+                format!("{}({})", show(f), args.join(", "))
+            }
+            e => unreachable!("{:?} isn't in tests of grouping", e),
+        }
+    }
+    let mut parser = Parser::new("", code);
+    show(&parser.parse_expression(None, None).unwrap())
+}
@@ -0,0 +1,25 @@
+//! playground compiles code from memory for the browser playground, nothing is read from files or
+//! written to stderr, so the compiler can be built to WebAssembly with it as the entry
+use crate::codegen::target::Target;
+pub use crate::compilation::Diagnostic;
+use crate::compilation::Options;
+
+/// FILE_NAME is the file name in locations of diagnostics
+pub const FILE_NAME: &str = "playground.elz";
+/// TARGET is the target of generated IR, the compiler doesn't run on it in browsers
+const TARGET: &str = "x86_64";
+
+/// compile checks `source`, a module with the prelude imported, and returns the generated LLVM
+/// IR, or its errors with warnings
+pub fn compile(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let options = Options {
+        target: Target::from_name(TARGET).expect("target is supported"),
+        ..Options::default()
+    };
+    crate::compile(FILE_NAME, source, options)
+        .map(|output| output.llvm_ir)
+        .map_err(|diagnostics| diagnostics.0)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn valid_code_generates_ir() {
+    let ir = compile("module main\nmain(): void { println(\"hi\"); }").unwrap();
+    assert!(ir.contains("target triple = \"x86_64-unknown-linux-gnu\""));
+    assert!(ir.contains("define void @main() {"));
+}
+
+#[test]
+fn errors_are_returned_with_warnings() {
+    let code = "module main
+main(): void {
+  x: int = 1;
+  y: int = true;
+}";
+    let diagnostics: Vec<(String, String, u32)> = compile(code)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| (d.severity, d.code, d.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            ("error".to_string(), "type_mismatched".to_string(), 4),
+            ("warning".to_string(), "unused_variable".to_string(), 3),
+            ("warning".to_string(), "unused_variable".to_string(), 4),
+        ]
+    );
+}
+
+#[test]
+fn parse_error_is_returned() {
+    assert_eq!(
+        compile("main(): void {}"),
+        Err(vec![Diagnostic {
+            severity: "error".to_string(),
+            code: "not_expected_token".to_string(),
+            message: "expected one of `module`  but got <identifier>".to_string(),
+            label: "not expected token".to_string(),
+            file: FILE_NAME.to_string(),
+            line: 1,
+            column: 1,
+            start: 0,
+            end: 4,
+        }])
+    );
+}
@@ -0,0 +1,170 @@
+//! repl evaluates code piece by piece, definitions are kept for the following input
+use crate::ast::{Expr, Module, TopAst};
+use crate::cmd::compile::prelude_import;
+use crate::interpreter::value::Value;
+use crate::interpreter::Interpreter;
+use crate::lexer::TkType;
+use crate::parser::{parse_prelude, ParseError, Parser};
+use crate::semantic::{derive_traits, CallerLocations, SemanticChecker};
+use std::io::Write;
+
+/// MODULE_NAME is the module of definitions from input
+const MODULE_NAME: &str = "main";
+/// FILE_NAME is the file name in locations of errors
+const FILE_NAME: &str = "<repl>";
+
+pub struct Repl<W: Write> {
+    checker: SemanticChecker,
+    interpreter: Interpreter<W>,
+    /// definitions accepted so far, the checker is rebuilt from them after input is rejected,
+    /// since the input might have defined part of its names
+    definitions: Vec<TopAst>,
+    caller_locations: CallerLocations,
+}
+
+/// Input is code evaluated at once
+enum Input {
+    Expr(Expr),
+    Definitions(Vec<TopAst>),
+}
+
+impl<W: Write> Repl<W> {
+    pub fn new(output: W) -> Repl<W> {
+        let prelude = parse_prelude();
+        let mut interpreter = Interpreter::new(output);
+        interpreter
+            .load(&prelude.top_list)
+            .expect("prelude can be loaded");
+        Repl {
+            checker: new_checker(&[]),
+            interpreter,
+            definitions: vec![],
+            caller_locations: CallerLocations::new(&[prelude]),
+        }
+    }
+    pub fn output(&self) -> &W {
+        self.interpreter.output()
+    }
+
+    /// eval evaluates input, returns `<value>: <type>` for expression, definitions and expression
+    /// of `void` have nothing to show
+    pub fn eval(&mut self, input: &str) -> Result<Option<String>, String> {
+        match parse(input)? {
+            Input::Expr(mut expr) => {
+                self.caller_locations.insert_in_expr(&mut expr);
+                let typ = self
+                    .checker
+                    .type_of_expr(MODULE_NAME, &expr)
+                    .map_err(|err| err.to_string())?;
+                self.interpreter.set_expr_types(self.checker.expr_types());
+                match self
+                    .interpreter
+                    .eval_expr(&expr)
+                    .map_err(|err| err.to_string())?
+                {
+                    Value::Void => Ok(None),
+                    value => Ok(Some(format!("{}: {}", value, typ))),
+                }
+            }
+            Input::Definitions(mut top_list) => {
+                derive_traits(&mut top_list);
+                self.caller_locations.insert(&mut top_list);
+                let result = define(&mut self.checker, &top_list).and_then(|_| {
+                    self.interpreter.set_expr_types(self.checker.expr_types());
+                    self.interpreter
+                        .load(&top_list)
+                        .map_err(|err| err.to_string())
+                });
+                match result {
+                    Ok(..) => self.definitions.extend(top_list),
+                    Err(..) => self.checker = new_checker(&self.definitions),
+                }
+                result.map(|_| None)
+            }
+        }
+    }
+}
+
+/// needs_more_input returns true while input has unclosed `{`, so a definition can be written in
+/// many lines
+pub fn needs_more_input(input: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth -= 1,
+            _ => (),
+        }
+    }
+    depth > 0
+}
+
+fn new_checker(definitions: &[TopAst]) -> SemanticChecker {
+    let mut checker = SemanticChecker::new();
+    let module = Module {
+        name: MODULE_NAME.to_string(),
+        top_list: vec![prelude_import()],
+    };
+    if checker
+        .check_program(&vec![parse_prelude(), module])
+        .is_err()
+    {
+        unreachable!("prelude is invalid");
+    }
+    define(&mut checker, definitions).expect("definitions were accepted");
+    checker
+}
+
+/// define checks definitions, variables are defined after the others, since they are evaluated
+/// at once rather than at compile time
+fn define(checker: &mut SemanticChecker, top_list: &[TopAst]) -> Result<(), String> {
+    let (variables, others): (Vec<&TopAst>, Vec<&TopAst>) = top_list
+        .iter()
+        .partition(|top| matches!(top, TopAst::Variable(..)));
+    let module = Module {
+        name: MODULE_NAME.to_string(),
+        top_list: others.into_iter().cloned().collect(),
+    };
+    checker.check_program(&vec![module]).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        errors.join("\n")
+    })?;
+    for top in variables {
+        if let TopAst::Variable(v) = top {
+            checker
+                .define_variable(MODULE_NAME, v)
+                .map_err(|err| err.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// parse reads input as an expression, e.g. `fib(10)`, or else as definitions, the error of
+/// parsing reaching further is returned
+fn parse(input: &str) -> Result<Input, String> {
+    let expr_err = match Parser::parse_expression_str(FILE_NAME, input) {
+        Ok(expr) => return Ok(Input::Expr(expr)),
+        Err(err) => err,
+    };
+    let mut parser = Parser::new(FILE_NAME, input);
+    match parser.parse_top_list(TkType::EOF) {
+        Ok(top_list) => Ok(Input::Definitions(top_list)),
+        Err(err) => {
+            let position = |err: &ParseError| (err.location().line(), err.location().column());
+            if position(&expr_err) > position(&err) {
+                Err(expr_err.to_string())
+            } else {
+                Err(err.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
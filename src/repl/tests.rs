@@ -0,0 +1,69 @@
+use super::*;
+
+#[test]
+fn expression_shows_value_and_type() {
+    let mut repl = Repl::new(vec![]);
+    assert_eq!(repl.eval("1 + 2 * 3"), Ok(Some("7: int".to_string())));
+    assert_eq!(repl.eval("1 < 2;"), Ok(Some("true: bool".to_string())));
+    assert_eq!(repl.eval("\"hi\""), Ok(Some("\"hi\": string".to_string())));
+}
+
+#[test]
+fn void_expression_shows_nothing() {
+    let mut repl = Repl::new(vec![]);
+    assert_eq!(repl.eval("println(\"hello\");"), Ok(None));
+    assert_eq!(repl.output(), b"hello\n");
+}
+
+#[test]
+fn definitions_are_kept() {
+    let mut repl = Repl::new(vec![]);
+    let fib = "fib(n: int): int = if n < 2 { n } else { fib(n - 1) + fib(n - 2) };";
+    assert_eq!(repl.eval(fib), Ok(None));
+    // variable can be initialized by any expression
+    assert_eq!(repl.eval("x: int = fib(10);"), Ok(None));
+    assert_eq!(repl.eval("x * 2"), Ok(Some("110: int".to_string())));
+}
+
+#[test]
+fn class_definition() {
+    let mut repl = Repl::new(vec![]);
+    let class = "class Point {
+      x: int;
+      ::new(x: int): Point = Point { x: x };
+      double(): int = self.x * 2;
+    }";
+    assert_eq!(repl.eval(class), Ok(None));
+    assert_eq!(repl.eval("p: Point = Point::new(3);"), Ok(None));
+    assert_eq!(repl.eval("p.double()"), Ok(Some("6: int".to_string())));
+    assert_eq!(
+        repl.eval("p"),
+        Ok(Some("Point { x: 3 }: Point".to_string()))
+    );
+}
+
+#[test]
+fn rejected_definition_is_dropped() {
+    let mut repl = Repl::new(vec![]);
+    assert!(repl.eval("f(): int = true;").is_err());
+    assert!(repl.eval("f()").is_err());
+    assert_eq!(repl.eval("f(): int = 1;"), Ok(None));
+    assert_eq!(repl.eval("f()"), Ok(Some("1: int".to_string())));
+}
+
+#[test]
+fn errors_are_reported() {
+    let mut repl = Repl::new(vec![]);
+    assert!(repl.eval("1 + true").is_err());
+    assert!(repl.eval("undefined_name").is_err());
+    assert!(repl.eval("1 / 0").is_err());
+    assert!(repl.eval("x: int = ;").is_err());
+}
+
+#[test]
+fn unclosed_brace_needs_more_input() {
+    assert!(needs_more_input("main(): void {"));
+    assert!(needs_more_input("class A {\n  f(): string = \"}\";"));
+    assert!(!needs_more_input("class A {}"));
+    assert!(!needs_more_input("1 + 2"));
+}
@@ -0,0 +1,77 @@
+use super::tag::SemanticTag;
+use crate::ast::visit::{walk_expr_mut, VisitorMut};
+use crate::ast::*;
+use crate::mangle::method_path;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// CallerLocations passes the location of the caller to functions tagged `@track_caller`, their
+/// last parameter is the location and filled by the compiler, e.g. `panic("oops")` is called as
+/// `panic("oops", "main.elz:3:2")`
+#[derive(Default)]
+pub struct CallerLocations {
+    /// arities are the number of arguments given by callers, i.e. without the location
+    arities: HashMap<String, usize>,
+}
+
+impl CallerLocations {
+    pub fn new(modules: &[Module]) -> CallerLocations {
+        let mut arities = HashMap::new();
+        for module in modules {
+            for top in &module.top_list {
+                match top {
+                    TopAst::Function(f) if f.tag.is_track_caller() && !f.parameters.is_empty() => {
+                        arities.insert(f.name.clone(), f.parameters.len() - 1);
+                    }
+                    // static methods are called by their paths, e.g. `List::get`
+                    TopAst::Class(c) => {
+                        for member in &c.members {
+                            match member {
+                                ClassMember::StaticMethod(f)
+                                    if f.tag.is_track_caller() && !f.parameters.is_empty() =>
+                                {
+                                    arities.insert(
+                                        method_path(&c.name, &f.name),
+                                        f.parameters.len() - 1,
+                                    );
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        CallerLocations { arities }
+    }
+
+    /// insert fills locations in calls of `top_list`, call already giving the location, e.g.
+    /// `assert` passing its location to `panic`, is kept
+    pub fn insert(&self, top_list: &mut [TopAst]) {
+        for top in top_list {
+            Inserter(self).visit_top_mut(top);
+        }
+    }
+
+    pub fn insert_in_expr(&self, expr: &mut Expr) {
+        Inserter(self).visit_expr_mut(expr);
+    }
+}
+
+struct Inserter<'a>(&'a CallerLocations);
+
+impl VisitorMut for Inserter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+        if let ExprVariant::FuncCall(f, args) = &mut expr.value {
+            if let ExprVariant::Identifier(name) = &f.value {
+                if self.0.arities.get(name) == Some(&args.len()) {
+                    let location = f.location.clone();
+                    let caller = Expr::string(location.clone(), location.to_string());
+                    Arc::make_mut(args).push(Argument::new(location, None, caller));
+                }
+            }
+        }
+    }
+}
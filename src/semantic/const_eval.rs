@@ -0,0 +1,133 @@
+use crate::ast::*;
+use crate::lexer::Location;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Value is the result of evaluating an expression at compile time
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Int(i64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    List(Vec<Value>),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum EvalError {
+    /// the expression at location can only be evaluated at runtime
+    NotConstant(Location),
+    /// the expression at location overflows
+    Overflow(Location),
+    /// the expression at location divides by zero
+    DivisionByZero(Location),
+}
+
+/// eval evaluates `expr` at compile time, `constants` are values of global variables evaluated
+/// before, so a global variable can refer to the previous ones
+pub(crate) fn eval(
+    expr: &Expr,
+    constants: &HashMap<String, Value>,
+) -> std::result::Result<Value, EvalError> {
+    use ExprVariant::*;
+    match &expr.value {
+        Int(i) => Ok(Value::Int(*i)),
+        F64(f) => Ok(Value::F64(*f)),
+        Bool(b) => Ok(Value::Bool(*b)),
+        String(s) => Ok(Value::String(s.clone())),
+        List(es) => {
+            let mut values = vec![];
+            for e in es.iter() {
+                values.push(eval(e, constants)?);
+            }
+            Ok(Value::List(values))
+        }
+        // right hand side is skipped as runtime does, so `false and f()` is constant
+        Binary(l, r, op) if op.is_logical() => match (eval(l, constants)?, op) {
+            (Value::Bool(false), Operator::And) => Ok(Value::Bool(false)),
+            (Value::Bool(true), Operator::Or) => Ok(Value::Bool(true)),
+            (Value::Bool(_), _) => eval(r, constants),
+            // semantic checking rejects others, so they are not constant
+            _ => Err(EvalError::NotConstant(expr.location.clone())),
+        },
+        Binary(l, r, op) if op.is_comparison() => {
+            let ordering = match (eval(l, constants)?, eval(r, constants)?) {
+                (Value::Int(l), Value::Int(r)) => l.partial_cmp(&r),
+                (Value::F64(l), Value::F64(r)) => l.partial_cmp(&r),
+                (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(&r),
+                // semantic checking rejects others, so they are not constant
+                _ => return Err(EvalError::NotConstant(expr.location.clone())),
+            };
+            use std::cmp::Ordering::*;
+            Ok(Value::Bool(match op {
+                Operator::Equal => ordering == Some(Equal),
+                Operator::NotEqual => ordering != Some(Equal),
+                Operator::Less => ordering == Some(Less),
+                Operator::LessEqual => ordering == Some(Less) || ordering == Some(Equal),
+                Operator::Greater => ordering == Some(Greater),
+                Operator::GreaterEqual => ordering == Some(Greater) || ordering == Some(Equal),
+                _ => unreachable!(),
+            }))
+        }
+        Binary(l, r, op) => match (eval(l, constants)?, eval(r, constants)?) {
+            (Value::Int(l), Value::Int(r)) => {
+                if *op == Operator::Divide && r == 0 {
+                    return Err(EvalError::DivisionByZero(expr.location.clone()));
+                }
+                let v = match op {
+                    Operator::Plus => l.checked_add(r),
+                    Operator::Minus => l.checked_sub(r),
+                    Operator::Multiply => l.checked_mul(r),
+                    Operator::Divide => l.checked_div(r),
+                    Operator::Power => checked_pow(l, r),
+                    _ => unreachable!(),
+                };
+                match v {
+                    Some(v) => Ok(Value::Int(v)),
+                    None => Err(EvalError::Overflow(expr.location.clone())),
+                }
+            }
+            (Value::F64(l), Value::F64(r)) => Ok(Value::F64(match op {
+                Operator::Plus => l + r,
+                Operator::Minus => l - r,
+                Operator::Multiply => l * r,
+                Operator::Divide => l / r,
+                Operator::Power => l.powf(r),
+                _ => unreachable!(),
+            })),
+            // semantic checking rejects others, so they are not constant
+            _ => Err(EvalError::NotConstant(expr.location.clone())),
+        },
+        // only the taken branch is evaluated as runtime does
+        If(condition, then_expr, else_expr) => match eval(condition, constants)? {
+            Value::Bool(true) => eval(then_expr, constants),
+            Value::Bool(false) => eval(else_expr, constants),
+            // semantic checking rejects others, so they are not constant
+            _ => Err(EvalError::NotConstant(expr.location.clone())),
+        },
+        Identifier(name) => match constants.get(name) {
+            Some(v) => Ok(v.clone()),
+            None => Err(EvalError::NotConstant(expr.location.clone())),
+        },
+        FuncCall(..) | MemberAccess(..) | ClassConstruction(..) => {
+            Err(EvalError::NotConstant(expr.location.clone()))
+        }
+    }
+}
+
+/// checked_pow is `base ** exponent` of `int`, `None` when it overflows, a negative exponent is
+/// taken as 0 as compiled code does
+pub(crate) fn checked_pow(base: i64, exponent: i64) -> Option<i64> {
+    if exponent <= 0 {
+        return Some(1);
+    }
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent),
+        // only 0, 1 and -1 have powers that large fitting in `int`
+        Err(_) => match base {
+            0 | 1 => Some(base),
+            -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+            _ => None,
+        },
+    }
+}
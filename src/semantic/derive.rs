@@ -0,0 +1,102 @@
+use super::tag::SemanticTag;
+use crate::ast::*;
+
+/// DERIVABLE_TRAITS are traits `@derive(...)` can implement for a class
+pub(crate) const DERIVABLE_TRAITS: [&str; 2] = ["Eq", "Hash"];
+
+/// derive_traits implements traits listed by `@derive(...)` of classes in `top_list`, e.g.
+/// `@derive(Eq)` adds `Eq` to parents of the class with method `eq` comparing fields in order,
+/// traits can't be derived are reported by the semantic checker
+pub fn derive_traits(top_list: &mut [TopAst]) {
+    for top in top_list {
+        if let TopAst::Class(c) = top {
+            for trait_name in c.tag.derived_traits() {
+                match trait_name.as_str() {
+                    "Eq" => derive_eq(c),
+                    "Hash" => derive_hash(c),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// derive_parent adds `trait_name` to parents of class `c` unless it's there
+fn derive_parent(c: &mut Class, trait_name: &str) {
+    if !c.parents.iter().any(|parent| parent == trait_name) {
+        c.parents.push(trait_name.to_string());
+    }
+}
+
+/// derive_eq adds `eq(other: C): bool = self.a == other.a and self.b == other.b;` to class `C`
+fn derive_eq(c: &mut Class) {
+    derive_parent(c, "Eq");
+    let location = &c.location;
+    let body = c
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            ClassMember::Field(field) => {
+                let location = &field.location;
+                let field_of = |object: &str| {
+                    let object = Expr::identifier(location.clone(), object);
+                    Expr::member_access(location.clone(), object, &field.name)
+                };
+                Some(Expr::binary(
+                    location.clone(),
+                    field_of("self"),
+                    field_of("other"),
+                    Operator::Equal,
+                ))
+            }
+            _ => None,
+        })
+        .reduce(|left, right| Expr::binary(right.location.clone(), left, right, Operator::And))
+        .unwrap_or_else(|| Expr::bool(location.clone(), true));
+    c.members.push(ClassMember::Method(Function::new(
+        location.clone(),
+        None,
+        "eq",
+        vec![Parameter::new(
+            location.clone(),
+            "other",
+            ParsedType::type_name(&c.name),
+        )],
+        ParsedType::type_name("bool"),
+        Body::Expr(body),
+    )));
+}
+
+/// derive_hash adds `hash(): int = int::_hash_mix(int::_hash_mix(0, self.a.hash()), self.b.hash());`
+/// to class `C`
+fn derive_hash(c: &mut Class) {
+    derive_parent(c, "Hash");
+    let location = &c.location;
+    let body = c
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            ClassMember::Field(field) => Some(field),
+            _ => None,
+        })
+        .fold(Expr::int(location.clone(), 0), |h, field| {
+            let location = &field.location;
+            let object = Expr::identifier(location.clone(), "self");
+            let value = Expr::member_access(location.clone(), object, &field.name);
+            let hash = Expr::member_access(location.clone(), value, "hash");
+            let mix = Expr::identifier(location.clone(), "int::_hash_mix");
+            let args = vec![h, Expr::func_call(location.clone(), hash, vec![])]
+                .into_iter()
+                .map(|expr| Argument::new(location.clone(), None, expr))
+                .collect();
+            Expr::func_call(location.clone(), mix, args)
+        });
+    c.members.push(ClassMember::Method(Function::new(
+        location.clone(),
+        None,
+        "hash",
+        vec![],
+        ParsedType::type_name("int"),
+        Body::Expr(body),
+    )));
+}
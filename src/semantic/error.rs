@@ -1,5 +1,11 @@
+use super::derive::DERIVABLE_TRAITS;
 use super::type_checker::Type;
+use crate::ast::Operator;
+pub use crate::diagnostic::Severity;
+use crate::diagnostic::{Diagnose, Diagnostic};
+use crate::ice::{self, InternalError, Phase};
 use crate::lexer::Location;
+use std::collections::HashMap;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, SemanticError>;
@@ -9,6 +15,8 @@ pub type Result<T> = std::result::Result<T, SemanticError>;
 pub struct SemanticError {
     location: Location,
     err: SemanticErrorVariant,
+    /// labels on other code related to the error
+    labels: Vec<(Location, String)>,
 }
 
 #[derive(Debug, Error)]
@@ -33,21 +41,93 @@ enum SemanticErrorVariant {
     OnlyTraitCanBeSuperType { got_type: Type },
     #[error("dead code after return statement")]
     DeadCodeAfterReturnStatement,
+    #[error("not all control paths of function `{}` return a value of type `{}`", .function_name, .return_type)]
+    MissingReturnStatement {
+        function_name: String,
+        return_type: Type,
+    },
     #[error("redefined member `{}` in class `{}`, already defined at {}", .member_name, .class_name, .previous_definition)]
     RedefinedMember {
         member_name: String,
         class_name: String,
         previous_definition: Location,
     },
-    #[error("class `{}` has no member named `{}`", .class_name, .member_name)]
+    #[error("no such member `{}` on type `{}`, available members: {}", .member_name, .type_name, ShowFieldsList(.available_members.to_vec()))]
     NoMemberNamed {
+        type_name: String,
+        member_name: String,
+        available_members: Vec<String>,
+    },
+    #[error("no such member `{}` on type `{}`, it has no members", .member_name, .type_name)]
+    TypeHasNoMembers {
+        type_name: String,
+        member_name: String,
+    },
+    #[error("class `{}` has no field named `{}`, available fields: {}", .class_name, .field_name, ShowFieldsList(.available_fields.to_vec()))]
+    UnknownField {
         class_name: String,
+        field_name: String,
+        available_fields: Vec<String>,
+    },
+    #[error("cannot assign to method `{}` of type `{}`, only fields can be assigned", .member_name, .type_name)]
+    CannotAssignToMethod {
+        type_name: String,
         member_name: String,
     },
+    #[error("class `{}` has infinite size, it contains itself by value through: {}", .class_name, .cycle.join(" -> "))]
+    InfinitelySizedType {
+        class_name: String,
+        cycle: Vec<String>,
+    },
+    #[error("{} `{}` cannot have type `void`, `void` is only valid as return type", .kind, .name)]
+    VoidValue { kind: &'static str, name: String },
+    #[error("integer literal `{}` is out of range of type `{}`", .value, .type_name)]
+    IntegerOutOfRange { value: i64, type_name: String },
+    #[error("initializer of global variable `{}` is not a compile-time constant", .name)]
+    GlobalNotConstant { name: String },
+    #[error("global variable `{}` holds a `{}`, only numbers and `bool` can be global constants", .name, .type_name)]
+    UnsupportedGlobal { name: String, type_name: String },
+    #[error("overflow when evaluating initializer of global variable `{}`", .name)]
+    ConstantOverflow { name: String },
+    #[error("operator `{}` cannot be applied to `{}` and `{}`", .operator, .left, .right)]
+    UnsupportedOperator {
+        operator: Operator,
+        left: Type,
+        right: Type,
+    },
+    #[error("division by zero when evaluating initializer of global variable `{}`", .name)]
+    ConstantDivisionByZero { name: String },
     #[error("function `{}` is not an extern function, must have a body", .function_name)]
     NonExternFunctionMustHaveBody { function_name: String },
     #[error("no module named: `{}`", .module_name)]
     NoModuleNamed { module_name: String },
+    #[error("function `{}` tagged `@{}` must take no parameters and return `void`", .function_name, .tag_name)]
+    InvalidTaggedFunction {
+        function_name: String,
+        tag_name: String,
+    },
+    #[error("function `main` must take no parameters and return `void` or `int`")]
+    InvalidMainFunction,
+    #[error("method `drop` of class `{}` must take no parameters and return `void`", .class_name)]
+    InvalidDropMethod { class_name: String },
+    #[error("`drop` of `{}` is called once its instance is freed, it can't be called manually", .type_name)]
+    DropCalledManually { type_name: String },
+    #[error("trait `{}` can't be derived, `@derive` implements {}", .trait_name, ShowFieldsList(DERIVABLE_TRAITS.iter().map(|t| t.to_string()).collect()))]
+    CannotDerive { trait_name: String },
+    #[error("class `{}` implements `Hash` without `Eq`, keys of `Map` are compared by `eq`", .class_name)]
+    HashWithoutEq { class_name: String },
+    #[error("`for` can't iterate `{}`, it takes a `List` or an `Iterator` whose `next` returns an `Option`", .type_name)]
+    NotIterable { type_name: String },
+    #[error("trait `{}` inherits itself", .trait_name)]
+    TraitInheritsItself { trait_name: String },
+    #[error("`{}` requires `{}` to implement `{}`", .function_name, .type_name, .trait_name)]
+    UnsatisfiedBound {
+        function_name: String,
+        type_name: String,
+        trait_name: String,
+    },
+    #[error("internal compiler error while {}: {}", .0.phase(), .0.message())]
+    Internal(InternalError),
 }
 
 impl SemanticError {
@@ -55,12 +135,17 @@ impl SemanticError {
         SemanticError {
             location: location.clone(),
             err,
+            labels: vec![],
         }
     }
+    pub(crate) fn with_label<T: ToString>(mut self, location: &Location, label: T) -> Self {
+        self.labels.push((location.clone(), label.to_string()));
+        self
+    }
     pub(crate) fn location(&self) -> Location {
         self.location.clone()
     }
-    pub(crate) fn message(&self) -> String {
+    pub fn message(&self) -> String {
         format!("{}", self)
     }
 
@@ -72,6 +157,183 @@ impl SemanticError {
             },
         )
     }
+    pub fn invalid_tagged_function<T: ToString, U: ToString>(
+        location: &Location,
+        function_name: T,
+        tag_name: U,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::InvalidTaggedFunction {
+                function_name: function_name.to_string(),
+                tag_name: tag_name.to_string(),
+            },
+        )
+    }
+    pub fn invalid_main_function(location: &Location) -> SemanticError {
+        SemanticError::new(location, SemanticErrorVariant::InvalidMainFunction)
+    }
+    pub fn invalid_drop_method<T: ToString>(location: &Location, class_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::InvalidDropMethod {
+                class_name: class_name.to_string(),
+            },
+        )
+    }
+    pub fn drop_called_manually<T: ToString>(location: &Location, type_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::DropCalledManually {
+                type_name: type_name.to_string(),
+            },
+        )
+    }
+    pub fn cannot_derive<T: ToString>(location: &Location, trait_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::CannotDerive {
+                trait_name: trait_name.to_string(),
+            },
+        )
+    }
+    pub fn hash_without_eq<T: ToString>(location: &Location, class_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::HashWithoutEq {
+                class_name: class_name.to_string(),
+            },
+        )
+    }
+    pub fn trait_inherits_itself<T: ToString>(location: &Location, trait_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::TraitInheritsItself {
+                trait_name: trait_name.to_string(),
+            },
+        )
+    }
+    pub fn unsatisfied_bound<T: ToString>(
+        location: &Location,
+        function_name: T,
+        typ: &Type,
+        bound: &Type,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::UnsatisfiedBound {
+                function_name: function_name.to_string(),
+                type_name: typ.to_string(),
+                trait_name: bound.to_string(),
+            },
+        )
+    }
+    pub fn not_iterable<T: ToString>(location: &Location, type_name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::NotIterable {
+                type_name: type_name.to_string(),
+            },
+        )
+    }
+    /// internal is a bug of the checker found while checking the code at `location`
+    pub fn internal<T: ToString>(location: &Location, message: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::Internal(InternalError::new(Phase::Check, location, message)),
+        )
+    }
+    pub fn infinitely_sized_type<T: ToString>(
+        location: &Location,
+        class_name: T,
+        cycle: Vec<String>,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::InfinitelySizedType {
+                class_name: class_name.to_string(),
+                cycle,
+            },
+        )
+    }
+    pub fn void_value<T: ToString>(
+        location: &Location,
+        kind: &'static str,
+        name: T,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::VoidValue {
+                kind,
+                name: name.to_string(),
+            },
+        )
+    }
+    pub fn integer_out_of_range<T: ToString>(
+        location: &Location,
+        value: i64,
+        type_name: T,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::IntegerOutOfRange {
+                value,
+                type_name: type_name.to_string(),
+            },
+        )
+    }
+    pub fn global_not_constant<T: ToString>(location: &Location, name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::GlobalNotConstant {
+                name: name.to_string(),
+            },
+        )
+    }
+    pub fn unsupported_global<T: ToString>(
+        location: &Location,
+        name: T,
+        type_name: &str,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::UnsupportedGlobal {
+                name: name.to_string(),
+                type_name: type_name.to_string(),
+            },
+        )
+    }
+    pub fn constant_overflow<T: ToString>(location: &Location, name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::ConstantOverflow {
+                name: name.to_string(),
+            },
+        )
+    }
+    pub fn unsupported_operator(
+        location: &Location,
+        operator: &Operator,
+        left: &Type,
+        right: &Type,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::UnsupportedOperator {
+                operator: operator.clone(),
+                left: left.clone(),
+                right: right.clone(),
+            },
+        )
+    }
+    pub fn constant_division_by_zero<T: ToString>(location: &Location, name: T) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::ConstantDivisionByZero {
+                name: name.to_string(),
+            },
+        )
+    }
     pub fn non_extern_function_must_have_body<T: ToString>(
         location: &Location,
         function_name: T,
@@ -136,6 +398,19 @@ impl SemanticError {
     pub fn dead_code_after_return_statement(location: &Location) -> SemanticError {
         SemanticError::new(location, SemanticErrorVariant::DeadCodeAfterReturnStatement)
     }
+    pub fn missing_return_statement<T: ToString>(
+        location: &Location,
+        function_name: T,
+        return_type: &Type,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::MissingReturnStatement {
+                function_name: function_name.to_string(),
+                return_type: return_type.clone(),
+            },
+        )
+    }
     pub fn redefined_member(
         location: &Location,
         member_name: String,
@@ -151,21 +426,291 @@ impl SemanticError {
             },
         )
     }
-    pub fn no_member_named(
+    pub fn type_has_no_members(
+        location: &Location,
+        type_name: String,
+        member_name: String,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::TypeHasNoMembers {
+                type_name,
+                member_name,
+            },
+        )
+    }
+    pub fn unknown_field(
         location: &Location,
         class_name: String,
+        field_name: String,
+        available_fields: Vec<String>,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::UnknownField {
+                class_name,
+                field_name,
+                available_fields,
+            },
+        )
+    }
+    pub fn cannot_assign_to_method(
+        location: &Location,
+        type_name: String,
+        member_name: String,
+    ) -> SemanticError {
+        SemanticError::new(
+            location,
+            SemanticErrorVariant::CannotAssignToMethod {
+                type_name,
+                member_name,
+            },
+        )
+    }
+    pub fn no_member_named(
+        location: &Location,
+        type_name: String,
         member_name: String,
+        available_members: Vec<String>,
     ) -> SemanticError {
         SemanticError::new(
             location,
             SemanticErrorVariant::NoMemberNamed {
-                class_name,
+                type_name,
                 member_name,
+                available_members,
             },
         )
     }
 }
 
+/// LINT_NAMES are names of all lints, which can be configured by `-A`/`-W`/`-D` and `@allow(...)`
+pub const LINT_NAMES: &[&str] = &[
+    "unused_variable",
+    "unused_parameter",
+    "dead_function",
+    "shadowed_variable",
+    "constant_condition",
+    "unused_result",
+];
+
+/// Lints records the severity of each lint, lint not be configured uses its default severity
+#[derive(Clone, Debug, Default)]
+pub struct Lints {
+    levels: HashMap<String, Severity>,
+    /// deny_warnings turns every lint reported as warning into error, e.g. for CI
+    deny_warnings: bool,
+}
+
+impl Lints {
+    pub fn new() -> Lints {
+        Lints {
+            levels: HashMap::new(),
+            deny_warnings: false,
+        }
+    }
+    /// set configures the severity of the lint, returns false if no lint has the name
+    pub fn set<T: ToString>(&mut self, lint_name: T, severity: Severity) -> bool {
+        let lint_name = lint_name.to_string();
+        let known = LINT_NAMES.contains(&lint_name.as_str());
+        self.levels.insert(lint_name, severity);
+        known
+    }
+    pub fn deny_warnings(&mut self) {
+        self.deny_warnings = true;
+    }
+    pub fn severity_of(&self, warning: &SemanticWarning) -> Severity {
+        let severity = match self.levels.get(warning.lint_name()) {
+            Some(severity) => *severity,
+            None => warning.default_severity(),
+        };
+        if self.deny_warnings && severity == Severity::Warning {
+            Severity::Error
+        } else {
+            severity
+        }
+    }
+}
+
+impl Diagnose for SemanticError {
+    fn diagnostic(&self) -> Diagnostic {
+        use SemanticErrorVariant::*;
+        let code = match self.err {
+            NameRedefined { .. } => "name_redefined",
+            TypeMismatched { .. } => "type_mismatched",
+            NoVariableNamed { .. } => "no_variable_named",
+            NoTypeNamed { .. } => "no_type_named",
+            CallOnNonFunctionType { .. } => "call_on_non_function_type",
+            FieldsMissingInit { .. } => "fields_missing_init",
+            CannotConstructNonClassType { .. } => "cannot_construct_non_class_type",
+            CannotUseClassConstructionOutOfClass() => "cannot_use_class_construction_out_of_class",
+            OnlyTraitCanBeSuperType { .. } => "only_trait_can_be_super_type",
+            DeadCodeAfterReturnStatement => "dead_code_after_return_statement",
+            MissingReturnStatement { .. } => "missing_return_statement",
+            RedefinedMember { .. } => "redefined_member",
+            NoMemberNamed { .. } => "no_member_named",
+            TypeHasNoMembers { .. } => "type_has_no_members",
+            UnknownField { .. } => "unknown_field",
+            CannotAssignToMethod { .. } => "cannot_assign_to_method",
+            InfinitelySizedType { .. } => "infinitely_sized_type",
+            VoidValue { .. } => "void_value",
+            IntegerOutOfRange { .. } => "integer_out_of_range",
+            GlobalNotConstant { .. } => "global_not_constant",
+            UnsupportedGlobal { .. } => "unsupported_global",
+            ConstantOverflow { .. } => "constant_overflow",
+            UnsupportedOperator { .. } => "unsupported_operator",
+            ConstantDivisionByZero { .. } => "constant_division_by_zero",
+            NonExternFunctionMustHaveBody { .. } => "non_extern_function_must_have_body",
+            NoModuleNamed { .. } => "no_module_named",
+            InvalidTaggedFunction { .. } => "invalid_tagged_function",
+            InvalidMainFunction => "invalid_main_function",
+            InvalidDropMethod { .. } => "invalid_drop_method",
+            DropCalledManually { .. } => "drop_called_manually",
+            CannotDerive { .. } => "cannot_derive",
+            HashWithoutEq { .. } => "hash_without_eq",
+            NotIterable { .. } => "not_iterable",
+            TraitInheritsItself { .. } => "trait_inherits_itself",
+            UnsatisfiedBound { .. } => "unsatisfied_bound",
+            Internal(..) => "internal_compiler_error",
+        };
+        let mut diagnostic = Diagnostic::error(code, &self.location, &self.err);
+        for (location, label) in &self.labels {
+            diagnostic = diagnostic.with_label(location, label);
+        }
+        if let RedefinedMember {
+            previous_definition,
+            ..
+        } = &self.err
+        {
+            diagnostic = diagnostic.with_label(previous_definition, "first defined here");
+        }
+        if let Internal(..) = &self.err {
+            diagnostic = diagnostic.with_note(format!(
+                "this is a bug of elz, please report it with the code at {}",
+                ice::ISSUES
+            ));
+        }
+        diagnostic
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("{location} {warning}")]
+pub struct SemanticWarning {
+    location: Location,
+    warning: SemanticWarningVariant,
+}
+
+#[derive(Debug, Error)]
+enum SemanticWarningVariant {
+    #[error("unused variable: `{}`", .0)]
+    UnusedVariable(String),
+    #[error("unused parameter: `{}`", .0)]
+    UnusedParameter(String),
+    #[error("function `{}` is never called from `main`", .0)]
+    DeadFunction(String),
+    #[error("variable `{}` shadows the variable defined at {}", .name, .previous_definition)]
+    ShadowedVariable {
+        name: String,
+        previous_definition: Location,
+    },
+    #[error("condition is always `{}`", .0)]
+    ConstantCondition(bool),
+    #[error("result of type `{}` is unused", .0)]
+    UnusedResult(String),
+}
+
+impl SemanticWarning {
+    fn new(location: &Location, warning: SemanticWarningVariant) -> SemanticWarning {
+        SemanticWarning {
+            location: location.clone(),
+            warning,
+        }
+    }
+    pub(crate) fn location(&self) -> Location {
+        self.location.clone()
+    }
+    pub fn message(&self) -> String {
+        format!("{}", self)
+    }
+    /// lint_name is the name used by `-A`/`-W`/`-D` flags and `@allow(...)` to control this warning
+    pub fn lint_name(&self) -> &'static str {
+        use SemanticWarningVariant::*;
+        match self.warning {
+            UnusedVariable(..) => "unused_variable",
+            UnusedParameter(..) => "unused_parameter",
+            DeadFunction(..) => "dead_function",
+            ShadowedVariable { .. } => "shadowed_variable",
+            ConstantCondition(..) => "constant_condition",
+            UnusedResult(..) => "unused_result",
+        }
+    }
+    /// default_severity is used when users didn't configure the lint
+    pub fn default_severity(&self) -> Severity {
+        use SemanticWarningVariant::*;
+        match self.warning {
+            // shadowing is allowed, users have to opt-in the warning by `-W shadowed_variable`
+            ShadowedVariable { .. } => Severity::Allow,
+            _ => Severity::Warning,
+        }
+    }
+
+    pub fn unused_variable<T: ToString>(location: &Location, name: T) -> SemanticWarning {
+        SemanticWarning::new(
+            location,
+            SemanticWarningVariant::UnusedVariable(name.to_string()),
+        )
+    }
+    pub fn constant_condition(location: &Location, value: bool) -> SemanticWarning {
+        SemanticWarning::new(location, SemanticWarningVariant::ConstantCondition(value))
+    }
+    pub fn shadowed_variable<T: ToString>(
+        location: &Location,
+        name: T,
+        previous_definition: Location,
+    ) -> SemanticWarning {
+        SemanticWarning::new(
+            location,
+            SemanticWarningVariant::ShadowedVariable {
+                name: name.to_string(),
+                previous_definition,
+            },
+        )
+    }
+    pub fn dead_function<T: ToString>(location: &Location, name: T) -> SemanticWarning {
+        SemanticWarning::new(
+            location,
+            SemanticWarningVariant::DeadFunction(name.to_string()),
+        )
+    }
+    pub fn unused_parameter<T: ToString>(location: &Location, name: T) -> SemanticWarning {
+        SemanticWarning::new(
+            location,
+            SemanticWarningVariant::UnusedParameter(name.to_string()),
+        )
+    }
+    pub fn unused_result<T: ToString>(location: &Location, type_name: T) -> SemanticWarning {
+        SemanticWarning::new(
+            location,
+            SemanticWarningVariant::UnusedResult(type_name.to_string()),
+        )
+    }
+}
+
+impl Diagnose for SemanticWarning {
+    /// code of warning is its lint name
+    fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::warning(self.lint_name(), &self.location, &self.warning);
+        match &self.warning {
+            SemanticWarningVariant::ShadowedVariable {
+                previous_definition,
+                ..
+            } => diagnostic.with_label(previous_definition, "shadowed variable"),
+            _ => diagnostic,
+        }
+    }
+}
+
 struct ShowFieldsList(Vec<String>);
 impl std::fmt::Display for ShowFieldsList {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
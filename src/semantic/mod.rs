@@ -1,92 +1,292 @@
 use crate::ast::*;
+use crate::cmd::compile::{prelude_import, unknown_imports};
+use crate::ice::{self, Phase};
 use crate::lexer::Location;
+use crate::mangle::method_path;
+use crate::parser::parse_prelude;
 
+mod caller_location;
+pub(crate) mod const_eval;
+mod derive;
 mod error;
-mod tag;
+mod reachability;
+mod resolution;
+pub(crate) mod tag;
 mod type_checker;
+mod type_cycle;
+mod type_map;
 
-use error::{Result, SemanticError};
+pub use caller_location::CallerLocations;
+use const_eval::{EvalError, Value};
+pub use derive::derive_traits;
+use derive::DERIVABLE_TRAITS;
+use error::Result;
+pub use error::{Lints, SemanticError, SemanticWarning, Severity, LINT_NAMES};
+pub use resolution::ResolutionMap;
 use std::collections::HashMap;
 use tag::SemanticTag;
-use type_checker::{Type, TypeEnv};
+use type_checker::{Type, TypeEnv, TypeInfo};
+pub use type_map::TypeMap;
+
+/// MAX_ERRORS is the maximum number of errors collected by `check_program`, checking stops after
+/// reaching it since following errors usually are caused by previous errors
+pub const MAX_ERRORS: usize = 20;
 
 pub struct SemanticChecker {
-    top_env: TypeEnv,
+    /// boxed since environments of modules refer it as parent
+    top_env: Box<TypeEnv>,
+    /// module_envs are kept across `check_program`, so a module can be checked piece by piece
+    module_envs: HashMap<String, TypeEnv>,
+    errors: Vec<SemanticError>,
+    warnings: Vec<SemanticWarning>,
+    /// allowed_lints are lints allowed by `@allow(...)` of the item being checked
+    allowed_lints: Vec<String>,
 }
 
 impl SemanticChecker {
     pub fn new() -> SemanticChecker {
         SemanticChecker {
-            top_env: TypeEnv::new(),
+            top_env: Box::new(TypeEnv::new()),
+            module_envs: HashMap::new(),
+            errors: vec![],
+            warnings: vec![],
+            allowed_lints: vec![],
+        }
+    }
+    /// warnings returns warnings found by `check_program`, they won't stop the checking
+    pub fn warnings(&self) -> &Vec<SemanticWarning> {
+        &self.warnings
+    }
+    /// resolutions returns definitions of names used in modules checked so far
+    pub fn resolutions(&self) -> ResolutionMap {
+        self.top_env.resolutions.borrow().clone()
+    }
+    /// expr_types returns types of expressions in modules checked so far
+    pub fn expr_types(&self) -> TypeMap {
+        self.top_env.expr_types.borrow().clone()
+    }
+
+    fn report(&mut self, result: Result<()>) {
+        if let Err(err) = result {
+            if !self.too_many_errors() {
+                self.errors.push(err);
+            }
+        }
+    }
+    fn too_many_errors(&self) -> bool {
+        self.errors.len() >= MAX_ERRORS
+    }
+    /// warn drops the warning if its lint is allowed by the item being checked
+    fn warn(&mut self, warning: SemanticWarning) {
+        if !self
+            .allowed_lints
+            .iter()
+            .any(|lint| lint.as_str() == warning.lint_name())
+        {
+            self.warnings.push(warning);
         }
     }
 }
 
 impl SemanticChecker {
-    pub fn check_program(&mut self, modules: &Vec<Module>) -> Result<()> {
-        let mut module_envs = HashMap::new();
+    /// check_program:
+    ///
+    /// checks all modules and returns all errors found(at most `MAX_ERRORS`), a module checked
+    /// before is extended by the new definitions
+    pub fn check_program(
+        &mut self,
+        modules: &Vec<Module>,
+    ) -> std::result::Result<(), Vec<SemanticError>> {
+        let mut module_envs = std::mem::take(&mut self.module_envs);
+        let result = self.check_modules(modules, &mut module_envs);
+        self.module_envs = module_envs;
+        result
+    }
+    /// try_check checks the module with prelude imported as `elz::compile` does, any module
+    /// parsed gives errors rather than panic, it's the entry point of fuzzing
+    pub fn try_check(&mut self, mut module: Module) -> std::result::Result<(), Vec<SemanticError>> {
+        module.top_list.push(prelude_import());
+        let mut program = vec![parse_prelude(), module];
+        let caller_locations = CallerLocations::new(&program);
+        for module in &mut program {
+            derive_traits(&mut module.top_list);
+            caller_locations.insert(&mut module.top_list);
+        }
+        let errors = unknown_imports(&program);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.check_program(&program)
+    }
+    fn check_modules(
+        &mut self,
+        modules: &Vec<Module>,
+        module_envs: &mut HashMap<String, TypeEnv>,
+    ) -> std::result::Result<(), Vec<SemanticError>> {
         for m in modules {
-            let module_env = self.prepare_imports(m)?;
-            module_envs.insert(m.name.clone(), module_env);
+            let module_env = module_envs
+                .entry(m.name.clone())
+                .or_insert_with(|| TypeEnv::with_parent(&self.top_env));
+            prepare_imports(m, module_env);
         }
         for m in modules {
-            self.prepare_types(m, &mut module_envs)?;
+            self.declare_types(m, module_envs);
         }
         for m in modules {
-            self.prepare_terms(m, &mut module_envs)?;
+            self.prepare_types(m, module_envs);
         }
         for m in modules {
-            self.check_module(m, &mut module_envs)?;
+            self.prepare_terms(m, module_envs);
+        }
+        // names must be ready before checking bodies, else we would get a lot of errors caused by
+        // the missing names
+        if self.errors.is_empty() {
+            for m in modules {
+                self.check_module(m, module_envs);
+            }
+        }
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+        for m in modules {
+            for f in reachability::unreachable_functions(m) {
+                self.allowed_lints = f.tag.allowed_lints();
+                self.warn(SemanticWarning::dead_function(&f.location, &f.name));
+            }
+            self.allowed_lints.clear();
         }
         Ok(())
     }
 
-    fn prepare_imports(&mut self, module: &Module) -> Result<TypeEnv> {
-        let mut module_env = TypeEnv::with_parent(&self.top_env);
+    /// declare_types defines traits and classes without their members, which are filled by
+    /// `prepare_types` once all types are declared, so a type can refer to types defined after
+    /// it, e.g. `to_string(): string` of `int`
+    fn declare_types(&mut self, module: &Module, module_envs: &mut HashMap<String, TypeEnv>) {
+        let module_env = module_envs.get_mut(&module.name).unwrap();
+        // traits first, since classes refer them as parents
+        for top in &module.top_list {
+            if let TopAst::Trait(t) = &top {
+                let typ = TypeEnv::declare_trait(t);
+                let result = self.declare_type(module, module_env, &t.location, &t.name, typ);
+                self.report(result);
+            }
+        }
+        self.declare_trait_parents(module, module_env);
+        // class contains itself can't be laid out, and its type can't be created either
+        let mut infinitely_sized = vec![];
+        for cycle in type_cycle::type_cycles(module) {
+            infinitely_sized.extend(cycle.class_names.iter().map(|name| name.to_string()));
+            self.report(Err(SemanticError::infinitely_sized_type(
+                &cycle.class.location,
+                &cycle.class.name,
+                cycle.fields,
+            )));
+        }
         for top in &module.top_list {
-            use TopAst::*;
             match &top {
-                Import(i) => {
-                    for component in &i.imported_component {
-                        module_env.imports.insert(
-                            component.clone(),
-                            with_module_name(i.import_path.clone(), component),
+                TopAst::Class(c) if !infinitely_sized.contains(&c.name) => {
+                    let result = module_env.declare_class(c).and_then(|typ| {
+                        self.declare_type(module, module_env, &c.location, &c.name, typ)
+                    });
+                    self.report(result);
+                }
+                _ => (),
+            }
+        }
+    }
+    /// declare_trait_parents sets parents of traits declared by `declare_types`, a trait is set
+    /// after traits it inherits in the module, so it has their ancestors as well
+    fn declare_trait_parents(&mut self, module: &Module, module_env: &mut TypeEnv) {
+        let mut pending: Vec<&Trait> = module
+            .top_list
+            .iter()
+            .filter_map(|top| match top {
+                TopAst::Trait(t) if !t.with_traits.is_empty() => Some(t),
+                _ => None,
+            })
+            .collect();
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<&Trait>, Vec<&Trait>) = pending.iter().partition(|t| {
+                !t.with_traits
+                    .iter()
+                    .any(|parent| pending.iter().any(|p| &p.name == parent))
+            });
+            // every trait left waits for another one, some of them inherit themselves and others
+            // inherit those
+            if ready.is_empty() {
+                for t in &waiting {
+                    if inherits(&waiting, t, &t.name, &mut vec![]) {
+                        self.report(Err(SemanticError::trait_inherits_itself(
+                            &t.location,
+                            &t.name,
+                        )));
+                    }
+                }
+                return;
+            }
+            for t in ready {
+                let declared = match module_env.declaration(&t.location, &t.name) {
+                    Some(declared) => declared,
+                    None => continue,
+                };
+                let result = module_env
+                    .trait_parents(&t.location, &t.with_traits)
+                    .map(|parents| TypeEnv::declare_trait_parents(declared, parents));
+                match result {
+                    Ok(typ) => {
+                        self.top_env.replace_type(
+                            &with_module_name(module.name.clone(), &t.name),
+                            typ.clone(),
                         );
+                        module_env.replace_type(&t.name, typ);
                     }
+                    Err(err) => self.report(Err(err)),
                 }
-                _ => (),
             }
+            pending = waiting;
         }
-        Ok(module_env)
     }
-    fn prepare_types(
+    fn declare_type(
         &mut self,
         module: &Module,
-        module_envs: &mut HashMap<String, TypeEnv>,
+        module_env: &mut TypeEnv,
+        location: &Location,
+        name: &str,
+        typ: Type,
     ) -> Result<()> {
+        self.top_env.add_type(
+            location,
+            &with_module_name(module.name.clone(), name),
+            typ.clone(),
+        )?;
+        module_env.add_type(location, name, typ)
+    }
+    fn prepare_types(&mut self, module: &Module, module_envs: &mut HashMap<String, TypeEnv>) {
         let module_env = module_envs.get_mut(&module.name).unwrap();
         for top in &module.top_list {
-            use TopAst::*;
-            match &top {
-                Class(c) => {
-                    let typ = module_env.new_class(c)?;
-                    self.top_env.add_type(
-                        &c.location,
-                        &with_module_name(module.name.clone(), &c.name),
-                        typ.clone(),
-                    )?;
-                    module_env.add_type(&c.location, &c.name, typ)?;
+            // a type failed to be declared, or redefined, has no declaration of its own
+            let (name, result) = match &top {
+                TopAst::Trait(t) => match module_env.declaration(&t.location, &t.name) {
+                    Some(declared) => (&t.name, module_env.new_trait(declared, t)),
+                    None => continue,
+                },
+                TopAst::Class(c) => match module_env.declaration(&c.location, &c.name) {
+                    Some(declared) => (&c.name, module_env.new_class(declared, c)),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            match result {
+                Ok(typ) => {
+                    self.top_env
+                        .replace_type(&with_module_name(module.name.clone(), name), typ.clone());
+                    module_env.replace_type(name, typ);
                 }
-                _ => (),
+                Err(err) => self.report(Err(err)),
             }
         }
-        Ok(())
     }
-    fn prepare_terms(
-        &mut self,
-        module: &Module,
-        module_envs: &mut HashMap<String, TypeEnv>,
-    ) -> Result<()> {
+    fn prepare_terms(&mut self, module: &Module, module_envs: &mut HashMap<String, TypeEnv>) {
         let module_env = module_envs.get_mut(&module.name).unwrap();
         for top in &module.top_list {
             use TopAst::*;
@@ -95,20 +295,21 @@ impl SemanticChecker {
                     for member in &c.members {
                         match member {
                             ClassMember::StaticMethod(static_method) => {
-                                let typ = module_env.new_function_type(static_method)?;
-                                self.top_env.add_variable(
-                                    &static_method.location,
-                                    &with_module_name(
-                                        module.name.clone(),
-                                        &format!("{}::{}", c.name, static_method.name),
-                                    ),
-                                    typ.clone(),
-                                )?;
-                                module_env.add_variable(
+                                // signatures can refer type parameters of the class
+                                let typ = TypeEnv::with_type_parameters(
+                                    module_env,
+                                    &c.location,
+                                    &c.type_parameters,
+                                )
+                                .and_then(|class_env| class_env.new_function_type(static_method));
+                                let result = self.prepare_term(
+                                    &module.name,
+                                    module_env,
                                     &static_method.location,
-                                    &format!("{}::{}", c.name, static_method.name),
+                                    &method_path(&c.name, &static_method.name),
                                     typ,
-                                )?;
+                                );
+                                self.report(result);
                             }
                             _ => (),
                         }
@@ -119,106 +320,316 @@ impl SemanticChecker {
         }
         for top in &module.top_list {
             use TopAst::*;
-            match &top {
-                Variable(v) => {
-                    let typ = module_env.from(&v.typ)?;
-                    self.top_env.add_variable(
-                        &v.location,
-                        &with_module_name(module.name.clone(), &v.name),
-                        typ.clone(),
-                    )?;
-                    module_env.add_variable(&v.location, &v.name, typ)?;
-                }
-                Function(f) => {
-                    let typ = module_env.new_function_type(f)?;
-                    self.top_env.add_variable(
+            let result = match &top {
+                Variable(v) => self.prepare_term(
+                    &module.name,
+                    module_env,
+                    &v.location,
+                    &v.name,
+                    module_env.value_type_from(&v.location, "variable", &v.name, &v.typ),
+                ),
+                Function(f) if !f.type_parameters.is_empty() => {
+                    // the signature refers type parameters, calls must satisfy their bounds
+                    match TypeEnv::with_bounded_type_parameters(
+                        module_env,
                         &f.location,
-                        &with_module_name(module.name.clone(), &f.name),
-                        typ.clone(),
-                    )?;
-                    module_env.add_variable(&f.location, &f.name, typ)?;
+                        &f.type_parameters,
+                    ) {
+                        Ok(function_env) => {
+                            let typ = function_env.new_function_type(f);
+                            let bounds = function_env.type_parameter_bounds();
+                            let result = self.prepare_term(
+                                &module.name,
+                                module_env,
+                                &f.location,
+                                &f.name,
+                                typ,
+                            );
+                            self.top_env.bound_variable(
+                                &with_module_name(module.name.clone(), &f.name),
+                                bounds.clone(),
+                            );
+                            module_env.bound_variable(&f.name, bounds);
+                            result
+                        }
+                        Err(err) => Err(err),
+                    }
                 }
-                _ => (),
-            }
+                Function(f) => self.prepare_term(
+                    &module.name,
+                    module_env,
+                    &f.location,
+                    &f.name,
+                    module_env.new_function_type(f),
+                ),
+                _ => Ok(()),
+            };
+            self.report(result);
         }
-        Ok(())
     }
-
-    fn check_module(
+    fn prepare_term(
         &mut self,
-        module: &Module,
-        module_envs: &mut HashMap<String, TypeEnv>,
+        module_name: &str,
+        module_env: &mut TypeEnv,
+        location: &Location,
+        name: &str,
+        typ: Result<Type>,
     ) -> Result<()> {
+        let typ = typ?;
+        self.top_env.add_variable(
+            location,
+            &with_module_name(module_name.to_string(), name),
+            typ.clone(),
+        )?;
+        module_env.add_variable(location, name, typ)
+    }
+
+    fn check_module(&mut self, module: &Module, module_envs: &mut HashMap<String, TypeEnv>) {
         let module_env = module_envs.get_mut(&module.name).unwrap();
+        // values of global variables checked so far
+        let mut constants = HashMap::new();
         for top in &module.top_list {
+            if self.too_many_errors() {
+                break;
+            }
             use TopAst::*;
+            self.allowed_lints = match &top {
+                Import(_) => vec![],
+                Variable(v) => v.tag.allowed_lints(),
+                Function(f) => f.tag.allowed_lints(),
+                Class(c) => c.tag.allowed_lints(),
+                Trait(t) => t.tag.allowed_lints(),
+            };
             match &top {
                 Import(_) => (),
                 Variable(v) => {
-                    let typ = module_env.type_of_expr(&v.expr)?;
-                    // show where error happened
-                    // we are unifying <expr> and <type>, so <expr> location is better than
-                    // variable define statement location
-                    module_env.unify(&v.expr.location, &module_env.from(&v.typ)?, &typ)?
+                    let result = module_env.from(&v.typ).and_then(|typ| {
+                        // show where error happened
+                        // we are unifying <expr> and <type>, so <expr> location is better than
+                        // variable define statement location
+                        module_env.check_expr(&v.expr.location, &v.expr, &typ)
+                    });
+                    let typed = result.is_ok();
+                    self.report(result);
+                    // global variable is initialized at compile time
+                    let result = match const_eval::eval(&v.expr, &constants) {
+                        // strings and lists are allocated at runtime, codegen has no constant of
+                        // them
+                        Ok(Value::String(_)) if typed => Err(SemanticError::unsupported_global(
+                            &v.expr.location,
+                            &v.name,
+                            "string",
+                        )),
+                        Ok(Value::List(_)) if typed => Err(SemanticError::unsupported_global(
+                            &v.expr.location,
+                            &v.name,
+                            "List",
+                        )),
+                        Ok(value) => {
+                            constants.insert(v.name.clone(), value);
+                            Ok(())
+                        }
+                        Err(EvalError::NotConstant(location)) => {
+                            Err(SemanticError::global_not_constant(&location, &v.name))
+                        }
+                        Err(EvalError::Overflow(location)) => {
+                            Err(SemanticError::constant_overflow(&location, &v.name))
+                        }
+                        Err(EvalError::DivisionByZero(location)) => {
+                            Err(SemanticError::constant_division_by_zero(&location, &v.name))
+                        }
+                    };
+                    self.report(result);
+                }
+                Function(f) => {
+                    // tests and benchmarks are called without arguments by their runners
+                    let is_runnable = f.tag.is_test() || f.tag.is_bench();
+                    if is_runnable
+                        && (!f.parameters.is_empty() || f.ret_typ != ParsedType::type_name("void"))
+                    {
+                        self.report(Err(SemanticError::invalid_tagged_function(
+                            &f.location,
+                            &f.name,
+                            &f.tag.as_ref().unwrap().name,
+                        )));
+                    }
+                    // `main` is called with nothing, what it returns is the exit code
+                    if f.name == "main"
+                        && (!f.parameters.is_empty()
+                            || (f.ret_typ != ParsedType::type_name("void")
+                                && f.ret_typ != ParsedType::type_name("int")))
+                    {
+                        self.report(Err(SemanticError::invalid_main_function(&f.location)));
+                    }
+                    // a generic function is checked once, values of its type parameters have
+                    // members of their bounds
+                    let result = match TypeEnv::with_bounded_type_parameters(
+                        module_env,
+                        &f.location,
+                        &f.type_parameters,
+                    ) {
+                        Ok(function_env) => {
+                            self.check_function_body(&f.location, &f, &function_env)
+                        }
+                        Err(err) => Err(err),
+                    };
+                    self.report(result);
                 }
-                Function(f) => self.check_function_body(&f.location, &f, &module_env)?,
                 Class(c) => {
-                    let mut class_type_env = TypeEnv::with_parent(&module_env);
+                    for trait_name in c.tag.derived_traits() {
+                        if !DERIVABLE_TRAITS.contains(&trait_name.as_str()) {
+                            self.report(Err(SemanticError::cannot_derive(&c.location, trait_name)));
+                        }
+                    }
+                    let implements = |trait_name: &str| c.parents.iter().any(|p| p == trait_name);
+                    if implements("Hash") && !implements("Eq") {
+                        self.report(Err(SemanticError::hash_without_eq(&c.location, &c.name)));
+                    }
+                    // `drop` is called by the compiler with nothing but the instance
+                    if let Some(drop) = c.drop_method() {
+                        if !drop.parameters.is_empty()
+                            || drop.ret_typ != ParsedType::type_name("void")
+                        {
+                            self.report(Err(SemanticError::invalid_drop_method(
+                                &drop.location,
+                                &c.name,
+                            )));
+                        }
+                    }
+                    let mut class_type_env = match TypeEnv::with_type_parameters(
+                        module_env,
+                        &c.location,
+                        &c.type_parameters,
+                    ) {
+                        Ok(env) => env,
+                        Err(err) => {
+                            self.report(Err(err));
+                            continue;
+                        }
+                    };
                     for member in &c.members {
                         match member {
                             ClassMember::Field(f) => {
-                                let typ = class_type_env.from(&f.typ)?;
-                                class_type_env.add_variable(&f.location, &f.name, typ)?;
+                                let result = class_type_env.from(&f.typ).and_then(|typ| {
+                                    class_type_env.add_variable(&f.location, &f.name, typ)
+                                });
+                                self.report(result);
                             }
                             _ => (),
                         }
                     }
                     class_type_env.in_class_scope = true;
+                    // method can refer its instance via `self`
+                    let mut method_type_env = TypeEnv::with_parent(&class_type_env);
+                    let result = module_env
+                        .lookup_type(&c.location, &c.name)
+                        .and_then(|info| {
+                            method_type_env.add_variable(&c.location, "self", info.typ)
+                        });
+                    self.report(result);
                     for member in &c.members {
                         match member {
-                            ClassMember::StaticMethod(static_method) => {
-                                self.check_function_body(
-                                    &static_method.location,
-                                    &static_method,
+                            ClassMember::StaticMethod(method) => {
+                                // methods can allow more lints than their class
+                                self.allowed_lints = c.tag.allowed_lints();
+                                self.allowed_lints.extend(method.tag.allowed_lints());
+                                let result = self.check_function_body(
+                                    &method.location,
+                                    method,
                                     &class_type_env,
-                                )?;
+                                );
+                                self.report(result);
                             }
                             ClassMember::Method(method) => {
-                                self.check_function_body(
+                                self.allowed_lints = c.tag.allowed_lints();
+                                self.allowed_lints.extend(method.tag.allowed_lints());
+                                let result = self.check_function_body(
                                     &method.location,
-                                    &method,
-                                    &class_type_env,
-                                )?;
+                                    method,
+                                    &method_type_env,
+                                );
+                                self.report(result);
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                Trait(t) => {
+                    let mut trait_type_env = TypeEnv::with_parent(module_env);
+                    for member in &t.members {
+                        if let TraitMember::Field(f) = member {
+                            let result = trait_type_env.from(&f.typ).and_then(|typ| {
+                                trait_type_env.add_variable(&f.location, &f.name, typ)
+                            });
+                            self.report(result);
+                        }
+                    }
+                    for member in &t.members {
+                        match member {
+                            // only default methods have body, others are signatures for the
+                            // implementors
+                            TraitMember::Method(method) if method.body.is_some() => {
+                                // methods can allow more lints than their trait
+                                self.allowed_lints = t.tag.allowed_lints();
+                                self.allowed_lints.extend(method.tag.allowed_lints());
+                                let result = self.check_function_body(
+                                    &method.location,
+                                    method,
+                                    &trait_type_env,
+                                );
+                                self.report(result);
                             }
                             _ => (),
                         }
                     }
                 }
-                Trait(_) => unimplemented!(),
             }
         }
-        Ok(())
+        self.allowed_lints.clear();
     }
 
-    fn check_function_body(&self, location: &Location, f: &Function, env: &TypeEnv) -> Result<()> {
+    fn check_function_body(
+        &mut self,
+        location: &Location,
+        f: &Function,
+        env: &TypeEnv,
+    ) -> Result<()> {
+        let _compiling = ice::compiling(Phase::Check, location);
         let return_type = env.from(&f.ret_typ)?;
         let mut type_env = TypeEnv::with_parent(env);
-        for Parameter { name, typ } in &f.parameters {
+        for Parameter {
+            location,
+            name,
+            typ,
+        } in &f.parameters
+        {
             type_env.add_variable(location, name, type_env.from(typ)?)?;
         }
-        match &f.body {
-            Some(Body::Expr(e)) => {
-                let e_type = type_env.type_of_expr(e)?;
-                type_env.unify(location, &return_type, &e_type)
+        let result = match &f.body {
+            Some(Body::Expr(e)) => type_env.check_expr(location, e, &return_type),
+            Some(Body::Block(b)) => {
+                self.check_block(&type_env, b, &return_type);
+                let void_type = type_env.lookup_type(location, "void")?.typ;
+                if !block_always_return(b)
+                    && type_env.unify(location, &return_type, &void_type).is_err()
+                {
+                    Err(SemanticError::missing_return_statement(
+                        &b.location,
+                        f.name.as_str(),
+                        &return_type,
+                    ))
+                } else {
+                    Ok(())
+                }
             }
-            Some(Body::Block(b)) => self.check_block(&type_env, b, &return_type),
             None => {
-                if f.tag.is_extern() {
-                    // extern function declaration don't have body need to check
-                    // e.g.
-                    // ```
-                    // foo(): void;
-                    // ```
+                // extern function declaration don't have body need to check, neither does
+                // builtin function whose code is generated by the compiler
+                // e.g.
+                // ```
+                // foo(): void;
+                // ```
+                if f.tag.is_extern() || f.tag.is_builtin() {
                     Ok(())
                 } else {
                     Err(SemanticError::non_extern_function_must_have_body(
@@ -227,89 +638,292 @@ impl SemanticChecker {
                     ))
                 }
             }
+        };
+        if f.body.is_some() {
+            for (name, location) in type_env.unused_variables() {
+                if !name.starts_with('_') {
+                    self.warn(SemanticWarning::unused_parameter(&location, name));
+                }
+            }
         }
+        result
     }
 
-    fn check_block(&self, type_env: &TypeEnv, b: &Block, return_type: &Type) -> Result<()> {
+    /// check_block:
+    ///
+    /// statements are independent, so errors of each statement are reported and checking goes on
+    fn check_block(&mut self, type_env: &TypeEnv, b: &Block, return_type: &Type) {
         let mut type_env = TypeEnv::with_parent(type_env);
-        let location = &b.location;
-        if b.statements.len() == 0 {
-            if type_env
-                .unify(
-                    location,
-                    return_type,
-                    &type_env.lookup_type(location, "void")?.typ,
-                )
-                .is_err()
-            {
-                return Err(SemanticError::dead_code_after_return_statement(location));
+        for (i, stmt) in b.statements.iter().enumerate() {
+            if self.too_many_errors() {
+                return;
             }
-        } else {
-            for (i, stmt) in b.statements.iter().enumerate() {
-                use StatementVariant::*;
-                let location = &stmt.location;
-                match &stmt.value {
-                    Return(e) => {
-                        let typ = match e {
-                            Some(e) => type_env.type_of_expr(e)?,
-                            None => type_env.lookup_type(location, "void")?.typ,
-                        };
-                        if i != b.statements.len() - 1 {
-                            return Err(SemanticError::dead_code_after_return_statement(location));
-                        }
-                        type_env.unify(location, return_type, &typ)?;
-                    }
-                    Variable(v) => {
-                        let var_def_typ = type_env.from(&v.typ)?;
-                        let var_typ = type_env.type_of_expr(&v.expr)?;
-                        type_env.unify(location, &var_def_typ, &var_typ)?;
-                        type_env.add_variable(location, &v.name, var_def_typ)?;
-                        if i == b.statements.len() - 1 {
-                            type_env.unify(
-                                location,
-                                return_type,
-                                &type_env.lookup_type(location, "void")?.typ,
-                            )?;
-                        }
+            let result = self.check_statement(&mut type_env, stmt, return_type);
+            self.report(result);
+            // any statement after a statement that always returns can never be reached
+            if statement_always_return(stmt) && i != b.statements.len() - 1 {
+                self.report(Err(SemanticError::dead_code_after_return_statement(
+                    &b.statements[i + 1].location,
+                )));
+                break;
+            }
+        }
+        for (name, location) in type_env.unused_variables() {
+            if !name.starts_with('_') {
+                self.warn(SemanticWarning::unused_variable(&location, name));
+            }
+        }
+    }
+
+    fn check_statement(
+        &mut self,
+        type_env: &mut TypeEnv,
+        stmt: &Statement,
+        return_type: &Type,
+    ) -> Result<()> {
+        use StatementVariant::*;
+        let location = &stmt.location;
+        let _compiling = ice::compiling(Phase::Check, location);
+        match &stmt.value {
+            Return(Some(e)) => type_env.check_expr(location, e, return_type),
+            Return(None) => {
+                let typ = type_env.lookup_type(location, "void")?.typ;
+                type_env.unify(location, return_type, &typ)
+            }
+            Variable(v) => {
+                let var_def_typ =
+                    type_env.value_type_from(location, "variable", &v.name, &v.typ)?;
+                // checked before defining the variable, so the expression can't refer to itself
+                let checked = type_env.check_expr(location, &v.expr, &var_def_typ);
+                // variable in nested scope can shadow the outer one, but redefining in the same
+                // scope is an error reported by `add_variable`
+                if let Some(shadowed) = type_env.lookup_shadowed_variable(&v.name) {
+                    self.warn(SemanticWarning::shadowed_variable(
+                        location,
+                        &v.name,
+                        shadowed.location,
+                    ));
+                }
+                // define the variable even the expression is invalid, so later statements won't
+                // get errors about the missing variable
+                type_env.add_variable(location, &v.name, var_def_typ)?;
+                checked
+            }
+            // the value of the expression is dropped, which is likely a mistake unless it's `void`
+            Expression(expr) => {
+                let typ = type_env.type_of_expr(expr)?;
+                let void = type_env.lookup_type(location, "void")?.typ;
+                if type_env.unify(location, &void, &typ).is_err() {
+                    self.warn(SemanticWarning::unused_result(location, typ));
+                }
+                Ok(())
+            }
+            Assign { target, value } => {
+                let field_typ = type_env.type_of_assign_target(target)?;
+                type_env.check_expr(location, value, &field_typ)
+            }
+            IfBlock {
+                clauses,
+                else_block,
+            } => {
+                // once a condition is always true, the following clauses never run, so their
+                // conditions aren't warned, the optimizer drops blocks never jumped to
+                let mut always_true = false;
+                for (condition, then_block) in clauses {
+                    if let (false, Some(value)) = (always_true, constant_condition(condition)) {
+                        self.warn(SemanticWarning::constant_condition(
+                            &condition.location,
+                            value,
+                        ));
+                        always_true = value;
                     }
-                    Expression(func_call) => {
-                        let func_call_ret_typ = type_env.type_of_expr(func_call)?;
+                    let result = type_env.type_of_expr(condition).and_then(|cond_type| {
                         type_env.unify(
                             location,
-                            &type_env.lookup_type(location, "void")?.typ,
-                            &func_call_ret_typ,
-                        )?;
-                        if i == b.statements.len() - 1 {
-                            type_env.unify(
-                                location,
-                                return_type,
-                                &type_env.lookup_type(location, "void")?.typ,
-                            )?;
-                        }
-                    }
-                    IfBlock {
-                        clauses,
-                        else_block,
-                    } => {
-                        for (condition, then_block) in clauses {
-                            let cond_type = type_env.type_of_expr(condition)?;
-                            type_env.unify(
-                                location,
-                                &type_env.lookup_type(location, "bool")?.typ,
-                                &cond_type,
-                            )?;
-                            self.check_block(&type_env, then_block, return_type)?;
-                        }
-                        self.check_block(&type_env, else_block, return_type)?;
+                            &type_env.lookup_type(location, "bool")?.typ,
+                            &cond_type,
+                        )
+                    });
+                    self.report(result);
+                    self.check_block(&type_env, then_block, return_type);
+                }
+                self.check_block(&type_env, else_block, return_type);
+                Ok(())
+            }
+            // the loop variable is defined in a scope of its own, around the body
+            For {
+                name,
+                name_location,
+                iterable,
+                block,
+            } => {
+                let element_type = type_env.type_of_loop_variable(name_location, iterable)?;
+                let mut loop_env = TypeEnv::with_parent(type_env);
+                if let Some(shadowed) = loop_env.lookup_shadowed_variable(name) {
+                    self.warn(SemanticWarning::shadowed_variable(
+                        name_location,
+                        name,
+                        shadowed.location,
+                    ));
+                }
+                loop_env.add_variable(name_location, name, element_type)?;
+                self.check_block(&loop_env, block, return_type);
+                for (name, location) in loop_env.unused_variables() {
+                    if !name.starts_with('_') {
+                        self.warn(SemanticWarning::unused_variable(&location, name));
                     }
                 }
+                Ok(())
             }
         }
-        Ok(())
     }
 }
 
-fn with_module_name(mut module_name: String, name: &String) -> String {
+// for REPL
+impl SemanticChecker {
+    /// define_variable checks and defines a variable in a checked module, unlike global variable
+    /// the initial value needn't be constant, it's evaluated when defining
+    pub fn define_variable(
+        &mut self,
+        module_name: &str,
+        v: &Variable,
+    ) -> std::result::Result<(), SemanticError> {
+        let mut module_envs = std::mem::take(&mut self.module_envs);
+        let module_env = module_envs.get_mut(module_name).expect("module is checked");
+        let result = module_env
+            .value_type_from(&v.location, "variable", &v.name, &v.typ)
+            .and_then(|typ| {
+                TypeEnv::with_parent(module_env).check_expr(&v.expr.location, &v.expr, &typ)?;
+                self.prepare_term(module_name, module_env, &v.location, &v.name, Ok(typ))
+            });
+        self.module_envs = module_envs;
+        result
+    }
+    /// type_of_expr returns the name of type of expression in a checked module
+    pub fn type_of_expr(
+        &self,
+        module_name: &str,
+        expr: &Expr,
+    ) -> std::result::Result<String, SemanticError> {
+        let module_env = self
+            .module_envs
+            .get(module_name)
+            .expect("module is checked");
+        let typ = TypeEnv::with_parent(module_env).type_of_expr(expr)?;
+        Ok(typ.to_string())
+    }
+}
+
+// for editors
+impl SemanticChecker {
+    /// is_type returns true if the name refers a type in a checked module
+    pub fn is_type(&self, module_name: &str, name: &str) -> bool {
+        match self.module_envs.get(module_name) {
+            Some(module_env) => module_env.lookup_type(&Location::none(), name).is_ok(),
+            None => false,
+        }
+    }
+    /// type_of_name returns the type of the function or variable named in a checked module, e.g.
+    /// `(int): int`
+    pub fn type_of_name(&self, module_name: &str, name: &str) -> Option<String> {
+        self.module_envs
+            .get(module_name)?
+            .type_of_variable(name)
+            .map(|typ| typ.to_string())
+    }
+    /// is_function returns true if the name refers a function in a checked module
+    pub fn is_function(&self, module_name: &str, name: &str) -> bool {
+        match self.module_envs.get(module_name) {
+            Some(module_env) => matches!(
+                module_env.lookup_variable(&Location::none(), name),
+                Ok(TypeInfo {
+                    typ: Type::FunctionType(..),
+                    ..
+                })
+            ),
+            None => false,
+        }
+    }
+}
+
+/// constant_condition returns the value of condition which is known at compile time, e.g. `true`
+/// or `1 < 2`
+fn constant_condition(condition: &Expr) -> Option<bool> {
+    match const_eval::eval(condition, &HashMap::new()) {
+        Ok(const_eval::Value::Bool(b)) => Some(b),
+        _ => None,
+    }
+}
+
+/// block_always_return:
+///
+/// a block always returns when any of its statements always returns,
+/// statements after it would be rejected as dead code by `check_block`
+fn block_always_return(b: &Block) -> bool {
+    b.statements.iter().any(statement_always_return)
+}
+
+/// statement_always_return:
+///
+/// - `return` statement always returns
+/// - if-chain always returns only when it has an else block and every branch always returns,
+///   since an if without else can fall through
+/// - loop never counts as always returning, since its body can run zero times
+fn statement_always_return(stmt: &Statement) -> bool {
+    use StatementVariant::*;
+    match &stmt.value {
+        Return(_) => true,
+        IfBlock {
+            clauses,
+            else_block,
+        } => {
+            clauses
+                .iter()
+                .all(|(_, then_block)| block_always_return(then_block))
+                && block_always_return(else_block)
+        }
+        Variable(_) | Expression(_) | Assign { .. } | For { .. } => false,
+    }
+}
+
+fn prepare_imports(module: &Module, module_env: &mut TypeEnv) {
+    for top in &module.top_list {
+        use TopAst::*;
+        match &top {
+            Import(i) => {
+                for component in &i.imported_component {
+                    module_env.imports.insert(
+                        component.clone(),
+                        with_module_name(i.import_path.clone(), component),
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// inherits is true when trait `t` inherits trait `name` through traits of `traits`, `visited` are
+/// the traits visited already
+fn inherits<'a>(
+    traits: &[&'a Trait],
+    t: &'a Trait,
+    name: &str,
+    visited: &mut Vec<&'a str>,
+) -> bool {
+    if visited.contains(&t.name.as_str()) {
+        return false;
+    }
+    visited.push(&t.name);
+    t.with_traits.iter().any(|parent| {
+        parent == name
+            || traits
+                .iter()
+                .any(|p| &p.name == parent && inherits(traits, p, name, visited))
+    })
+}
+
+fn with_module_name(mut module_name: String, name: &str) -> String {
     module_name.push('.');
     module_name.push_str(name);
     module_name
@@ -0,0 +1,157 @@
+use super::tag::SemanticTag;
+use crate::ast::*;
+use std::collections::HashSet;
+
+/// unreachable_functions returns top-level functions of the module which can't be reached from
+/// any root, roots are:
+///
+/// - `main`
+/// - functions tagged `@test` or `@bench`, they are run by `elz test` and `elz bench`
+/// - initializer of global variables
+/// - methods and static methods of classes, since they are reachable through their class
+/// - default methods of traits, for the same reason
+///
+/// a module without `main` is a library, so all functions in it are reachable
+pub(crate) fn unreachable_functions(module: &Module) -> Vec<&Function> {
+    let mut functions = vec![];
+    let mut has_main = false;
+    for top in &module.top_list {
+        if let TopAst::Function(f) = top {
+            if f.name == "main" {
+                has_main = true;
+            }
+            functions.push(f);
+        }
+    }
+    if !has_main {
+        return vec![];
+    }
+
+    let mut worklist = vec!["main".to_string()];
+    for f in &functions {
+        if f.tag.is_test() || f.tag.is_bench() {
+            worklist.push(f.name.clone());
+        }
+    }
+    for top in &module.top_list {
+        match top {
+            TopAst::Variable(v) => referenced_names_in_expr(&v.expr, &mut worklist),
+            TopAst::Class(c) => {
+                for member in &c.members {
+                    match member {
+                        ClassMember::Field(field) => {
+                            if let Some(expr) = &field.expr {
+                                referenced_names_in_expr(expr, &mut worklist);
+                            }
+                        }
+                        ClassMember::Method(method) | ClassMember::StaticMethod(method) => {
+                            referenced_names_in_function(method, &mut worklist)
+                        }
+                    }
+                }
+            }
+            TopAst::Trait(t) => {
+                for member in &t.members {
+                    match member {
+                        TraitMember::Field(field) => {
+                            if let Some(expr) = &field.expr {
+                                referenced_names_in_expr(expr, &mut worklist);
+                            }
+                        }
+                        TraitMember::Method(method) => {
+                            referenced_names_in_function(method, &mut worklist)
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut reached = HashSet::new();
+    while let Some(name) = worklist.pop() {
+        if !reached.insert(name.clone()) {
+            continue;
+        }
+        if let Some(f) = functions.iter().find(|f| f.name == name) {
+            referenced_names_in_function(f, &mut worklist);
+        }
+    }
+    functions
+        .into_iter()
+        .filter(|f| !reached.contains(&f.name))
+        .collect()
+}
+
+fn referenced_names_in_function(f: &Function, names: &mut Vec<String>) {
+    match &f.body {
+        Some(Body::Expr(e)) => referenced_names_in_expr(e, names),
+        Some(Body::Block(b)) => referenced_names_in_block(b, names),
+        None => (),
+    }
+}
+
+fn referenced_names_in_block(b: &Block, names: &mut Vec<String>) {
+    for stmt in &b.statements {
+        use StatementVariant::*;
+        match &stmt.value {
+            Return(Some(e)) | Expression(e) => referenced_names_in_expr(e, names),
+            Return(None) => (),
+            Variable(v) => referenced_names_in_expr(&v.expr, names),
+            Assign { target, value } => {
+                referenced_names_in_expr(target, names);
+                referenced_names_in_expr(value, names);
+            }
+            IfBlock {
+                clauses,
+                else_block,
+            } => {
+                for (cond, then_block) in clauses {
+                    referenced_names_in_expr(cond, names);
+                    referenced_names_in_block(then_block, names);
+                }
+                referenced_names_in_block(else_block, names);
+            }
+            For {
+                iterable, block, ..
+            } => {
+                referenced_names_in_expr(iterable, names);
+                referenced_names_in_block(block, names);
+            }
+        }
+    }
+}
+
+fn referenced_names_in_expr(expr: &Expr, names: &mut Vec<String>) {
+    use ExprVariant::*;
+    match &expr.value {
+        Binary(l, r, _) => {
+            referenced_names_in_expr(l, names);
+            referenced_names_in_expr(r, names);
+        }
+        List(es) => {
+            for e in es.iter() {
+                referenced_names_in_expr(e, names);
+            }
+        }
+        FuncCall(f, args) => {
+            referenced_names_in_expr(f, names);
+            for arg in args.iter() {
+                referenced_names_in_expr(&arg.expr, names);
+            }
+        }
+        MemberAccess(from, _) => referenced_names_in_expr(from, names),
+        If(condition, then_expr, else_expr) => {
+            referenced_names_in_expr(condition, names);
+            referenced_names_in_expr(then_expr, names);
+            referenced_names_in_expr(else_expr, names);
+        }
+        Identifier(name) => names.push(name.clone()),
+        ClassConstruction(_, field_inits) => {
+            for e in field_inits.values() {
+                referenced_names_in_expr(e, names);
+            }
+        }
+        F64(_) | Int(_) | Bool(_) | String(_) => (),
+    }
+}
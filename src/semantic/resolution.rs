@@ -0,0 +1,68 @@
+use crate::lexer::Location;
+use std::collections::HashMap;
+
+/// ResolutionMap maps where names are used to where they are defined, e.g. an identifier to its
+/// variable, function or parameter, a member access to its field or method, for go-to-definition
+/// and find-references
+#[derive(Clone, Debug, Default)]
+pub struct ResolutionMap {
+    /// uses and their definitions keyed by file name, line and column of the use
+    resolutions: HashMap<(String, u32, u32), (Location, Location)>,
+}
+
+impl ResolutionMap {
+    pub(crate) fn record(&mut self, use_location: &Location, definition: &Location) {
+        self.resolutions.insert(
+            key_of(use_location),
+            (use_location.clone(), definition.clone()),
+        );
+    }
+
+    /// definition_of returns the definition of the name used at the location
+    pub fn definition_of(&self, use_location: &Location) -> Option<&Location> {
+        self.resolutions
+            .get(&key_of(use_location))
+            .map(|(_, definition)| definition)
+    }
+    /// definition_at returns the definition of the name covering the position, line is 1-based
+    /// and column is 0-based as `Location`, the innermost use is taken since uses can nest, e.g.
+    /// `x` in `Point { x: x }`
+    pub fn definition_at(&self, file_name: &str, line: u32, column: u32) -> Option<&Location> {
+        self.resolutions
+            .values()
+            .filter(|(use_location, _)| {
+                let length = use_location.end - use_location.start;
+                use_location.file_name() == file_name
+                    && use_location.line() == line
+                    && use_location.column() <= column
+                    && column < use_location.column() + length
+            })
+            .max_by_key(|(use_location, _)| use_location.column())
+            .map(|(_, definition)| definition)
+    }
+    /// references_to returns uses of the definition ordered by their location
+    pub fn references_to(&self, definition: &Location) -> Vec<&Location> {
+        let mut references: Vec<&Location> = self
+            .resolutions
+            .values()
+            .filter(|(_, d)| d == definition)
+            .map(|(use_location, _)| use_location)
+            .collect();
+        references.sort_by_key(|l| (l.file_name().to_string(), l.line(), l.column()));
+        references
+    }
+    pub fn len(&self) -> usize {
+        self.resolutions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.resolutions.is_empty()
+    }
+}
+
+fn key_of(location: &Location) -> (String, u32, u32) {
+    (
+        location.file_name().to_string(),
+        location.line(),
+        location.column(),
+    )
+}
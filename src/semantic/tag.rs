@@ -2,6 +2,20 @@ use crate::ast::Tag;
 
 pub(crate) trait SemanticTag {
     fn is_extern(&self) -> bool;
+    /// is_builtin returns true for `@builtin`, the tagged declaration is implemented by the
+    /// compiler
+    fn is_builtin(&self) -> bool;
+    /// is_test returns true for `@test`, the tagged function is run by `elz test`
+    fn is_test(&self) -> bool;
+    /// is_track_caller returns true for `@track_caller`, see `CallerLocations`
+    fn is_track_caller(&self) -> bool;
+    /// is_bench returns true for `@bench`, the tagged function is run by `elz bench`
+    fn is_bench(&self) -> bool;
+    /// allowed_lints returns lints listed by `@allow(...)`, their warnings are dropped inside the
+    /// tagged item
+    fn allowed_lints(&self) -> Vec<String>;
+    /// derived_traits returns traits listed by `@derive(...)`, see `derive_traits`
+    fn derived_traits(&self) -> Vec<String>;
 }
 
 impl SemanticTag for Option<Tag> {
@@ -15,4 +29,40 @@ impl SemanticTag for Option<Tag> {
             None => false,
         }
     }
+    fn is_builtin(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "builtin" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
+    fn is_test(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "test" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
+    fn is_track_caller(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "track_caller" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
+    fn is_bench(&self) -> bool {
+        match self {
+            Some(tag) => tag.name.as_str() == "bench" && tag.properties.is_empty(),
+            None => false,
+        }
+    }
+    fn allowed_lints(&self) -> Vec<String> {
+        match self {
+            Some(tag) if tag.name.as_str() == "allow" => tag.properties.clone(),
+            _ => vec![],
+        }
+    }
+    fn derived_traits(&self) -> Vec<String> {
+        match self {
+            Some(tag) if tag.name.as_str() == "derive" => tag.properties.clone(),
+            _ => vec![],
+        }
+    }
 }
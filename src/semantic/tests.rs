@@ -57,15 +57,22 @@ fn test_type_mismatched() {
 }
 
 #[test]
-fn call_non_void_function_as_statement() {
+fn call_non_void_function_as_statement() -> Result<()> {
     let code = "
     main(): void {
       foo();
+      \"{foo()}\".to_string();
+      foo() + 1;
+      println(\"{foo()}\");
     }
     foo(): int = 1;
     ";
-    let result = check_code(code);
-    assert_eq!(result.is_err(), true);
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(
+        warnings,
+        vec!["unused_result", "unused_result", "unused_result"]
+    );
+    Ok(())
 }
 
 #[test]
@@ -83,15 +90,85 @@ fn call_void_function_as_statement() -> Result<()> {
 fn test_check_function_call() -> Result<()> {
     let code = "
     x(a: int): int = a;
-    y: int = x(2);
+    main(): void {
+      y: int = x(2);
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn global_variable_can_be_evaluated_at_compile_time() -> Result<()> {
+    let code = "
+    x: int = 1 + 2;
+    y: int = x + 3;
     ";
     check_code(code)
 }
 
+#[test]
+fn global_variable_must_be_constant() {
+    let code = "
+    x(a: int): int = a;
+    y: int = x(2);
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":3:13 initializer of global variable `y` is not a compile-time constant"
+    );
+}
+
+#[test]
+fn global_variable_can_only_refer_previous_global_variables() {
+    let code = "
+    y: int = x + 3;
+    x: int = 1;
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn global_variable_overflow() {
+    let code = "
+    x: int = 9223372036854775807 + 1;
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:13 overflow when evaluating initializer of global variable `x`"
+    );
+}
+
+#[test]
+fn global_variable_must_be_number_or_bool() {
+    let code = "
+    s: string = \"abc\";
+    xs: List[int] = [1, 2];
+    ";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.message())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":2:16 global variable `s` holds a `string`, only numbers and `bool` can be global constants",
+            ":3:20 global variable `xs` holds a `List`, only numbers and `bool` can be global constants",
+        ]
+    );
+}
+
 #[test]
 fn test_unify_list_type() -> Result<()> {
     let code = "
-    x: List[int] = [1, 2, 3];
+    foo(): List[int] {
+      x: List[int] = [1, 2, 3];
+      return x;
+    }
     ";
     check_code(code)
 }
@@ -99,7 +176,10 @@ fn test_unify_list_type() -> Result<()> {
 #[test]
 fn test_unify_free_var() -> Result<()> {
     let code = "
-    x: List[int] = [];
+    foo(): List[int] {
+      x: List[int] = [];
+      return x;
+    }
     ";
     check_code(code)
 }
@@ -254,6 +334,435 @@ fn only_trait_can_be_super_type() {
     assert_eq!(result.is_err(), true);
 }
 
+#[test]
+fn trait_declaration() -> Result<()> {
+    let code = "
+    trait Shape {
+      name: string;
+      area(): int;
+      describe(): string = self.name;
+    }
+    class Square <: Shape {
+      name: string = \"square\";
+      ::new(): Square = Square {};
+      area(): int = 1;
+    }
+    get_area(s: Shape): int = s.area();
+    main(): void {
+      area: int = get_area(Square::new());
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn trait_default_method_body_is_checked() {
+    let code = "
+    trait Shape {
+      area(): int = \"1\";
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn trait_method_signature_is_used_for_member_access() {
+    let code = "
+    trait Shape {
+      area(): int;
+    }
+    name_of(s: Shape): string = s.area();
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn class_is_not_a_trait_it_does_not_implement() {
+    let code = "
+    trait Shape {}
+    class Foo {
+      ::new(): Foo = Foo {};
+    }
+    get_shape(s: Shape): void {}
+    main(): void {
+      get_shape(Foo::new());
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn generic_function_bounds() -> Result<()> {
+    let code = "
+    show_eq[T <: Eq + ToString](a: T, b: T): string = if a == b { a.to_string() } else { b.to_string() };
+    main(): void {
+      println(show_eq(1, 2));
+      println(show_eq(\"a\", \"b\"));
+    }
+    ";
+    check_code(code)?;
+    let code = "
+    class Point {
+      ::new(): Point = Point {};
+    }
+    same[T <: Eq](a: T, b: T): bool = a == b;
+    show[T](a: T): string = a.to_string();
+    main(): void {
+      same(1, \"a\");
+      same(Point::new(), Point::new());
+      same(int::to_f64(1), int::to_f64(2));
+    }
+    ";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":6:30 no such member `to_string` on type `'1`, it has no members",
+            ":8:14 type mismatched, expected: `int` but got: `string`",
+            ":9:6 `same` requires `Point` to implement `Eq`",
+            ":10:6 `same` requires `f64` to implement `Eq`",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn trait_inherits_its_parents() -> Result<()> {
+    let code = "
+    class Foo <: Shape {
+      ::new(): Foo = Foo {};
+      area(): int = 1;
+      name(): string = \"foo\";
+    }
+    trait Shape <: Named {
+      area(): int;
+    }
+    trait Named {
+      name(): string;
+    }
+    name_of(n: Named): string = n.name();
+    describe(s: Shape): string = name_of(s) + s.name();
+    main(): void {
+      println(name_of(Foo::new()));
+      println(describe(Foo::new()));
+    }
+    ";
+    check_code(code)?;
+    let code = "
+    trait A <: B {}
+    trait B <: A {}
+    trait C <: A {}
+    trait D <: int {}
+    ";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.message())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":5:4 only trait can be super type, but got: int",
+            ":2:4 trait `A` inherits itself",
+            ":3:4 trait `B` inherits itself",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn class_contains_itself_has_infinite_size() {
+    let code = "
+    class Node {
+      value: int;
+      next: Node;
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:4 class `Node` has infinite size, it contains itself by value through: Node.next: Node"
+    );
+}
+
+#[test]
+fn classes_contain_each_other_have_infinite_size() {
+    let code = "
+    class A {
+      b: B;
+    }
+    class B {
+      a: A;
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:4 class `A` has infinite size, it contains itself by value through: A.b: B -> B.a: A"
+    );
+}
+
+#[test]
+fn drop_takes_nothing_and_is_never_called() {
+    let code = "
+    class Res {
+      id: int;
+      ::new(): Res = Res { id: 1 };
+      drop(code: int): int = code;
+    }
+    close(r: Res): void {
+      r.drop(1);
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].message(),
+        ":5:6 method `drop` of class `Res` must take no parameters and return `void`"
+    );
+    assert_eq!(
+        errors[1].message(),
+        ":8:8 `drop` of `Res` is called once its instance is freed, it can't be called manually"
+    );
+}
+
+#[test]
+fn void_variable_is_invalid() {
+    let code = "
+    foo(): void {}
+    main(): void {
+      x: void = foo();
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":4:6 variable `x` cannot have type `void`, `void` is only valid as return type"
+    );
+}
+
+#[test]
+fn void_global_variable_is_invalid() {
+    let code = "
+    foo(): void {}
+    x: void = foo();
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn void_parameter_is_invalid() {
+    let code = "
+    foo(p: void): int = 1;
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:8 parameter `p` cannot have type `void`, `void` is only valid as return type"
+    );
+}
+
+#[test]
+fn void_field_is_invalid() {
+    let code = "
+    class Foo {
+      v: void;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn integer_literal_can_be_smaller_int_types() -> Result<()> {
+    let code = "
+    byte(b: u8): u8 = b;
+    main(): void {
+      x: i32 = 2147483647;
+      y: u8 = byte(255);
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn integer_literal_out_of_range_of_annotated_type() {
+    let code = "
+    main(): void {
+      x: u8 = 256;
+      y: i32 = 2147483648;
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].message(),
+        ":3:14 integer literal `256` is out of range of type `u8`"
+    );
+    assert_eq!(
+        errors[1].message(),
+        ":4:15 integer literal `2147483648` is out of range of type `i32`"
+    );
+}
+
+#[test]
+fn integer_types_are_different_types() {
+    let code = "
+    main(): void {
+      x: int = 1;
+      y: i32 = x;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn arithmetic_operators() -> Result<()> {
+    let code = "
+    int_ops(x: int, y: int): int = x + y - x * y / 2;
+    f64_ops(x: f64, y: f64): f64 = x + y - x * y / y;
+    u8_ops(x: u8): u8 = x * 2 + 1;
+    ";
+    check_code(code)
+}
+
+#[test]
+fn operator_on_mixed_types() {
+    let code = "
+    foo(x: int, y: f64): int = x + y;
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:31 operator `+` cannot be applied to `int` and `f64`"
+    );
+}
+
+#[test]
+fn power_of_int_or_f64() {
+    let code = "
+    x: int = 2 ** 3 ** 2;
+    y: int = 2 ** 64;
+    f(a: int, b: f64): f64 = int::to_f64(a ** a) ** b;
+    g(a: u8): u8 = a ** a;
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].message(),
+        ":3:13 overflow when evaluating initializer of global variable `y`"
+    );
+    assert_eq!(
+        errors[1].message(),
+        ":5:19 operator `**` cannot be applied to `u8` and `u8`"
+    );
+}
+
+#[test]
+fn operator_on_non_numeric_types() {
+    let code = "
+    foo(x: bool, y: bool): bool = x * y;
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn global_variable_division_by_zero() {
+    let code = "
+    x: int = 1 / 0;
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn comparison_operators() -> Result<()> {
+    let code = "
+    max(x: int, y: int): int {
+      if x > y {
+        return x;
+      }
+      return y;
+    }
+    less(x: f64, y: f64): bool = x < y;
+    same(x: bool, y: bool): bool = x == y;
+    ";
+    check_code(code)
+}
+
+#[test]
+fn bool_cannot_be_ordered() {
+    let code = "
+    less(x: bool, y: bool): bool = x < y;
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn constant_comparison_condition_is_warned() -> Result<()> {
+    let code = "
+    main(): void {
+      if 1 < 2 {
+        println(\"always\");
+      }
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["constant_condition"]);
+    Ok(())
+}
+
+#[test]
+fn logical_operators() -> Result<()> {
+    let code = "
+    in_range(x: int, min: int, max: int): bool = min <= x and x <= max;
+    out_of_range(x: int, min: int, max: int): bool = x < min or max < x;
+    ";
+    check_code(code)
+}
+
+#[test]
+fn logical_operators_need_bool() {
+    let code = "
+    foo(x: int, y: int): bool = x and y;
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:32 operator `and` cannot be applied to `int` and `int`"
+    );
+}
+
+#[test]
+fn short_circuit_condition_can_be_constant() -> Result<()> {
+    let code = "
+    f(): bool = true;
+    main(): void {
+      if false and f() {
+        println(\"never\");
+      }
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["constant_condition"]);
+    Ok(())
+}
+
 #[test]
 fn if_else_block_must_return_same_type_as_return_type() {
     let code = "
@@ -351,33 +860,999 @@ fn no_member_name() {
     assert_eq!(result.is_err(), true);
 }
 
-// helpers, must put tests before this line
-fn check_code(code: &'static str) -> Result<()> {
-    let mut parser = Parser::new("", code);
-    let mut code = parser
-        .parse_top_list(TkType::EOF)
-        .map_err(|err| {
-            println!("{}", err);
+#[test]
+fn no_member_name_lists_available_members() {
+    let code = "
+    class Foo {
+      x: int;
+      ::new(): Foo = Foo { x: 1 };
+      bar(): int = 1;
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      foo.a();
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":9:10 no such member `a` on type `Foo`, available members: `x` `bar()` "
+    );
+}
+
+#[test]
+fn member_access_on_non_class_type() {
+    let code = "
+    main(): void {
+      x: int = 1;
+      x.a;
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":4:8 no such member `a` on type `int`, available members: `to_string()` `eq()` `compare()` `hash()` "
+    );
+}
+
+#[test]
+fn if_expression_has_type_of_branches() -> Result<()> {
+    let code = "
+    max(x: int, y: int): int = if x > y { x } else { y };
+    clamp(b: u8): u8 = if b > 100 { 100 } else { b };
+    main(): void {
+      small: u8 = if true { 1 } else { 255 };
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn if_expression_branches_must_have_same_type() {
+    let code = "
+    foo(b: bool): int = if b { 1 } else { true };
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn if_expression_condition_must_be_bool() {
+    let code = "
+    foo(x: int): int = if x { 1 } else { 2 };
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn assign_to_field() -> Result<()> {
+    let code = "
+    class Foo {
+      x: int;
+      small: u8;
+      ::new(): Foo = Foo { x: 1, small: 2 };
+      reset(): void {
+        self.x = 0;
+      }
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      foo.x = 2;
+      foo.small = 255;
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn assign_to_field_with_wrong_type() {
+    let code = "
+    class Foo {
+      x: int;
+      ::new(): Foo = Foo { x: 1 };
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      foo.x = true;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn assign_to_method_is_invalid() {
+    let code = "
+    class Foo {
+      ::new(): Foo = Foo {};
+      bar(): int = 1;
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      foo.bar = 1;
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":8:10 cannot assign to method `bar` of type `Foo`, only fields can be assigned"
+    );
+}
+
+#[test]
+fn method_call_has_return_type_of_method() {
+    let code = "
+    class Foo {
+      ::new(): Foo = Foo {};
+      bar(x: int): int = x;
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      s: string = foo.bar(1);
+    }
+    ";
+    assert_eq!(check_code(code).is_err(), true);
+}
+
+#[test]
+fn method_call_checks_arguments() {
+    let code = "
+    class Foo {
+      ::new(): Foo = Foo {};
+      bar(x: int): int = x;
+    }
+    main(): void {
+      foo: Foo = Foo::new();
+      foo.bar(\"1\");
+    }
+    ";
+    assert_eq!(check_code(code).is_err(), true);
+}
+
+#[test]
+fn method_can_access_self() -> Result<()> {
+    let code = "
+    class Foo {
+      x: int;
+      ::new(): Foo = Foo { x: 1 };
+      get_x(): int = self.x;
+      twice(): int = self.get_x() + self.get_x();
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn static_method_has_no_self() {
+    let code = "
+    class Foo {
+      x: int;
+      ::new(): Foo = self;
+    }
+    ";
+    assert_eq!(check_code(code).is_err(), true);
+}
+
+#[test]
+fn if_without_else_can_fall_through() -> Result<()> {
+    let code = "
+    foo(): int {
+      if true {
+        x: int = 1;
+      }
+      return 2;
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn all_branches_return_is_valid() -> Result<()> {
+    let code = "
+    foo(): int {
+      if true {
+        return 1;
+      } else if true {
+        return 2;
+      } else {
+        return 3;
+      }
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn missing_return_after_if_without_else() {
+    let code = "
+    foo(): int {
+      if true {
+        return 1;
+      }
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn dead_code_after_if_chain_which_always_return() {
+    let code = "
+    foo(): int {
+      if true {
+        return 1;
+      } else {
+        return 2;
+      }
+      return 3;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn unused_local_variable_and_parameter_are_warned() -> Result<()> {
+    let code = "
+    foo(a: int, _b: int): int {
+      x: int = 1;
+      _y: int = 1;
+      return 2;
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["unused_variable", "unused_parameter"]);
+    Ok(())
+}
+
+#[test]
+fn used_variable_is_not_warned() -> Result<()> {
+    let code = "
+    foo(a: int): int {
+      x: int = a;
+      return x;
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn function_not_reachable_from_main_is_warned() -> Result<()> {
+    let code = "
+    main(): void {
+      foo();
+    }
+    foo(): void {
+      bar();
+    }
+    bar(): void {}
+    unused(): void {}
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["dead_function"]);
+    Ok(())
+}
+
+#[test]
+fn function_called_by_test_is_reachable() -> Result<()> {
+    let code = "
+    main(): void {}
+    @test
+    foo_works(): void {
+      assert(foo() == 1, \"foo is not 1\");
+    }
+    foo(): int = 1;
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn main_returns_void_or_int() {
+    let code = "
+    main(args: List[string]): string = \"\";
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":2:4 function `main` must take no parameters and return `void` or `int`"
+    );
+    let code = "
+    main(): int = 1;
+    ";
+    assert_eq!(check_code_errors(code).len(), 0);
+}
+
+#[test]
+fn test_function_cannot_take_parameters() {
+    let code = "
+    @test
+    foo(x: int): void {}
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        ":3:4 function `foo` tagged `@test` must take no parameters and return `void`"
+    );
+}
+
+#[test]
+fn allow_tag_drops_warnings_inside_the_item() -> Result<()> {
+    let code = "
+    main(): void {
+      foo();
+      bar();
+    }
+    @allow(unused_variable, unused_parameter)
+    foo(x: int): void {
+      y: int = 1;
+    }
+    bar(): void {
+      z: int = 1;
+    }
+    @allow(dead_function)
+    unused(): void {}
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["unused_variable"]);
+    Ok(())
+}
+
+#[test]
+fn allow_tag_of_class_applies_to_its_methods() -> Result<()> {
+    let code = "
+    @allow(unused_variable)
+    class Foo {
+      @allow(unused_parameter)
+      bar(x: int): void {
+        y: int = 1;
+      }
+      baz(x: int): void {}
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(warnings, vec!["unused_parameter"]);
+    Ok(())
+}
+
+#[test]
+fn deny_warnings_turns_warnings_into_errors() {
+    let warning = SemanticWarning::unused_variable(&Location::none(), "x");
+    let shadowed = SemanticWarning::shadowed_variable(&Location::none(), "x", Location::none());
+    let mut lints = Lints::new();
+    assert!(lints.set("unused_variable", Severity::Allow));
+    assert!(!lints.set("no_such_lint", Severity::Error));
+    lints.deny_warnings();
+    assert_eq!(lints.severity_of(&warning), Severity::Allow);
+    assert_eq!(lints.severity_of(&shadowed), Severity::Allow);
+    lints.set("shadowed_variable", Severity::Warning);
+    assert_eq!(lints.severity_of(&shadowed), Severity::Error);
+}
+
+#[test]
+fn uses_are_resolved_to_definitions() {
+    let code = "
+    class Point {
+      x: int;
+      ::new(x: int): Point = Point { x: x };
+      get(): int = self.x;
+    }
+    add(a: int, b: int): int = a + b;
+    main(): void {
+      p: Point = Point::new(add(1, 2));
+      n: int = p.get() + p.x;
+      println(\"done\");
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let resolutions = checker.resolutions();
+    let definition_at = |line: u32, column: u32| {
+        let definition = resolutions.definition_at("", line, column)?;
+        Some((definition.line(), definition.column()))
+    };
+    // parameters
+    assert_eq!(definition_at(4, 40), Some((4, 12)));
+    assert_eq!(definition_at(7, 31), Some((7, 8)));
+    // class
+    assert_eq!(definition_at(4, 29), Some((2, 4)));
+    // field
+    assert_eq!(definition_at(5, 24), Some((3, 6)));
+    // static method and function
+    assert_eq!(definition_at(9, 17), Some((4, 8)));
+    assert_eq!(definition_at(9, 28), Some((7, 4)));
+    // variable and method
+    assert_eq!(definition_at(10, 15), Some((9, 6)));
+    assert_eq!(definition_at(10, 17), Some((5, 6)));
+    // function of prelude
+    assert_eq!(
+        resolutions
+            .definition_at("", 11, 6)
+            .map(|definition| definition.file_name()),
+        Some("prelude.elz")
+    );
+    // type name isn't a use of value
+    assert_eq!(definition_at(9, 9), None);
+}
+
+#[test]
+fn references_to_definition() {
+    let code = "
+    main(): void {
+      x: int = 1;
+      y: int = x + x;
+      println(\"done\");
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let resolutions = checker.resolutions();
+    let definition = resolutions.definition_at("", 4, 15).unwrap().clone();
+    let references: Vec<(u32, u32)> = resolutions
+        .references_to(&definition)
+        .iter()
+        .map(|location| (location.line(), location.column()))
+        .collect();
+    assert_eq!(references, vec![(4, 15), (4, 19)]);
+}
+
+#[test]
+fn types_of_expressions_are_queried_by_location() {
+    let code = "
+    class Point {
+      x: u8;
+      ::new(x: u8): Point = Point { x: x };
+    }
+    main(): void {
+      p: Point = Point::new(1);
+      n: bool = p.x + 2 < 10;
+      println(\"done\");
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let types = checker.expr_types();
+    let type_at = |text: &str| {
+        let start = code.find(text).unwrap() as u32;
+        let (location, typ) = types.type_at(&Location::new("", 0, 0, start, start))?;
+        Some((&code[location.start as usize..location.end as usize], typ))
+    };
+    assert_eq!(type_at("Point { x"), Some(("Point { x: x }", "Point")));
+    assert_eq!(type_at("x }"), Some(("x", "u8")));
+    assert_eq!(type_at("(1)"), Some(("Point::new(1)", "Point")));
+    // integer literal takes the type it's checked with
+    assert_eq!(type_at("1)"), Some(("1", "u8")));
+    assert_eq!(type_at("2 <"), Some(("2", "u8")));
+    // innermost expression is taken
+    assert_eq!(type_at("p.x"), Some(("p", "Point")));
+    assert_eq!(type_at(".x +"), Some(("p.x", "u8")));
+    assert_eq!(type_at("+ 2"), Some(("p.x + 2", "u8")));
+    assert_eq!(type_at("< 10"), Some(("p.x + 2 < 10", "bool")));
+    // type name isn't an expression
+    assert_eq!(type_at("Point ="), None);
+    assert_eq!(type_at("Point::new("), Some(("Point::new", "(u8): Point")));
+
+    let start = code.find("p.x + 2").unwrap() as u32;
+    let location = Location::new("", 0, 0, start, start + 7);
+    assert_eq!(types.type_of(&location), Some("u8"));
+}
+
+#[test]
+fn interpolated_values_keep_their_types() {
+    let code = "
+    main(): void {
+      println(\"{1 + 2}\");
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let types = checker.expr_types();
+    let start = code.find("1 + 2").unwrap() as u32;
+    let value = Location::new("", 0, 0, start, start + 5);
+    assert_eq!(types.type_of(&value), Some("int"));
+    // `to_string()` formatting the value spans the braces
+    let call = Location::new("", 0, 0, start - 1, start + 6);
+    assert_eq!(types.type_of(&call), Some("string"));
+}
+
+#[test]
+fn errors_of_independent_items_and_statements_are_all_reported() {
+    let code = "
+    x: int = \"str\";
+    foo(): void {
+      a: int = true;
+      b: bool = a;
+      c: int = b;
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), 4);
+}
+
+#[test]
+fn errors_are_capped() {
+    let code = "
+    foo(): void {
+      a: int = true; a: int = true; a: int = true; a: int = true; a: int = true;
+      a: int = true; a: int = true; a: int = true; a: int = true; a: int = true;
+      a: int = true; a: int = true; a: int = true; a: int = true; a: int = true;
+      a: int = true; a: int = true; a: int = true; a: int = true; a: int = true;
+      a: int = true; a: int = true; a: int = true; a: int = true; a: int = true;
+    }
+    ";
+    let errors = check_code_errors(code);
+    assert_eq!(errors.len(), MAX_ERRORS);
+}
+
+#[test]
+fn variable_in_nested_scope_can_shadow_outer_variable() -> Result<()> {
+    let code = "
+    foo(x: int): int {
+      if true {
+        x: bool = true;
+        return 1;
+      }
+      return x;
+    }
+    ";
+    let warnings = check_code_warnings(code)?;
+    assert_eq!(
+        warnings,
+        vec!["constant_condition", "shadowed_variable", "unused_variable"]
+    );
+    Ok(())
+}
+
+#[test]
+fn constant_condition_is_warned() -> Result<()> {
+    let code = "
+    main(): void {
+      if false {
+        println(\"never\");
+      } else if true {
+        println(\"always\");
+      } else if true {
+        println(\"never\");
+      } else {
+        println(\"never\");
+      }
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let warnings: Vec<String> = checker
+        .warnings()
+        .iter()
+        .filter(|warning| warning.location().file_name() == "")
+        .map(|warning| warning.message())
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            ":3:9 condition is always `false`",
+            ":5:16 condition is always `true`"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn redefine_variable_in_the_same_scope_is_invalid() {
+    let code = "
+    foo(): void {
+      x: int = 1;
+      x: int = 2;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn field_initializer_must_have_same_type_as_field() {
+    let code = "
+    class Foo {
+      bar: int;
+      ::new(): Foo = Foo {bar: true};
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn field_with_default_value_can_be_omitted() -> Result<()> {
+    let code = "
+    class Foo {
+      bar: int = 1;
+      baz: bool;
+      ::new(): Foo = Foo {baz: true};
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn unknown_field_in_class_construction() {
+    let code = "
+    class Point {
+      x: int;
+      y: int;
+      ::new(): Point = Point {x: 1, y: 2, z: 3};
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn method_cannot_be_initialized_in_class_construction() {
+    let code = "
+    class Point {
+      ::new(): Point = Point {bar: 1};
+      bar(): int = 1;
+    }
+    ";
+    let result = check_code(code);
+    assert_eq!(result.is_err(), true);
+}
+
+#[test]
+fn try_check_imports_prelude() {
+    let check = |code: &str| {
+        let module = Parser::try_parse("main.elz", code).unwrap();
+        SemanticChecker::new()
+            .try_check(module)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(
+        check("module main\nmain(): void { println(\"hi\"); }"),
+        Ok(())
+    );
+    assert_eq!(
+        check("module main\nimport util\nmain(): void {}"),
+        Err(vec!["main.elz:2:0 no module named: `util`".to_string()])
+    );
+}
+
+#[test]
+fn static_method_of_generic_class_binds_type_parameter() {
+    let code = "
+    second(names: List[string]): string = List::get(names, 1);
+    add(numbers: List[u8]): void {
+      List::push(numbers, 4);
+    }
+    main(): void {
+      add([2, 3]);
+    }
+    ";
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker).unwrap();
+    let types = checker.expr_types();
+    let type_at = |text: &str| {
+        let start = code.find(text).unwrap() as u32;
+        let (location, typ) = types.type_at(&Location::new("", 0, 0, start, start))?;
+        Some((&code[location.start as usize..location.end as usize], typ))
+    };
+    assert_eq!(
+        type_at("(names, 1)"),
+        Some(("List::get(names, 1)", "string"))
+    );
+    // integer literal takes the type bound to the type parameter
+    assert_eq!(type_at("4);"), Some(("4", "u8")));
+    assert_eq!(type_at("[2, 3]"), Some(("[2, 3]", "List[u8]")));
+}
+
+#[test]
+fn argument_must_match_bound_type_parameter() {
+    let code = "
+    add(numbers: List[int]): void {
+      List::push(numbers, \"1\");
+    }
+    ";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![":3:26 type mismatched, expected: `int` but got: `string`"]
+    );
+    let code = "first(numbers: List[int]): string = List::get(numbers, 0);";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![":1:36 type mismatched, expected: `string` but got: `int`"]
+    );
+}
+
+#[test]
+fn plus_concatenates_strings() -> Result<()> {
+    let code = "greet(name: string): string = \"hello, \" + name;";
+    check_code(code)?;
+    let code = "greet(name: string): string = name - \"!\";";
+    assert_eq!(
+        check_code(code).unwrap_err().to_string(),
+        ":1:30 operator `-` cannot be applied to `string` and `string`"
+    );
+    Ok(())
+}
+
+#[test]
+fn class_refers_to_class_defined_later() -> Result<()> {
+    let code = "
+    class Line {
+      start: Point;
+      ::new(): Line = Line { start: Point::origin() };
+      length(): int = self.start.x;
+    }
+    class Point {
+      x: int;
+      ::origin(): Point = Point { x: 0 };
+    }
+    ";
+    check_code(code)
+}
+
+#[test]
+fn template_formats_values_with_to_string() -> Result<()> {
+    let code = "
+    class Point <: ToString {
+      x: int;
+      to_string(): string = \"({self.x})\";
+    }
+    describe(p: Point, n: u8, x: f64): string = \"{p} {n} {x} {n > 1}\";
+    ";
+    check_code(code)?;
+    let code = "
+    class Point { x: int; }
+    describe(p: Point): string = \"{p}\";
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":3:35 no such member `to_string` on type `Point`, available members: `x` "
+    );
+    Ok(())
+}
+
+#[test]
+fn equality_of_instances_calls_eq() -> Result<()> {
+    let code = "
+    @derive(Eq)
+    class Point {
+      x: int;
+      name: string;
+    }
+    class Loose <: Eq {
+      x: int;
+      eq(other: Loose): bool = self.x / 10 == other.x / 10;
+    }
+    same(a: Point, b: Point, c: Loose, d: Loose): bool = a == b and c != d and a.name == b.name;
+    ";
+    check_code(code)?;
+    let code = "
+    class Point { x: int; }
+    same(a: Point, b: Point): bool = a == b;
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":3:37 operator `==` cannot be applied to `Point` and `Point`"
+    );
+    Ok(())
+}
+
+#[test]
+fn derive_only_derivable_traits() {
+    let code = "
+    @derive(Eq)
+    class Line { start: Point; }
+    class Point { x: int; }
+    @derive(ToString)
+    class Name { value: string; }
+    ";
+    let errors: Vec<String> = check_code_errors(code)
+        .iter()
+        .map(|err| err.message())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ":3:17 operator `==` cannot be applied to `Point` and `Point`",
+            ":6:4 trait `ToString` can't be derived, `@derive` implements `Eq` `Hash` ",
+        ]
+    );
+}
+
+#[test]
+fn sort_list_of_ord() -> Result<()> {
+    let code = "
+    sort(numbers: List[int], words: List[string]): void {
+      List::sort(numbers);
+      List::sort(words);
+    }
+    ";
+    check_code(code)?;
+    let code = "
+    class Point { x: int; }
+    sort(points: List[Point]): void {
+      List::sort(points);
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":4:17 type mismatched, expected: `Ord` but got: `Point`"
+    );
+    Ok(())
+}
+
+#[test]
+fn map_keys_implement_hash() -> Result<()> {
+    let code = "
+    @derive(Eq, Hash)
+    class Point { x: int; y: int; }
+    lookup(names: Map[Point, string], ages: Map[string, int], point: Point): bool {
+      Map::insert(ages, \"a\", 1);
+      return Map::contains(names, point) and Option::unwrap(Map::get(ages, \"a\")) == 1;
+    }
+    ";
+    check_code(code)?;
+    let code = "
+    class Point { x: int; }
+    lookup(names: Map[Point, string], point: Point): bool = Map::contains(names, point);
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":3:74 type mismatched, expected: `Hash` but got: `Point`"
+    );
+    let code = "
+    @derive(Hash)
+    class Point { x: int; }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":3:4 class `Point` implements `Hash` without `Eq`, keys of `Map` are compared by `eq`"
+    );
+    Ok(())
+}
+
+#[test]
+fn for_loop_over_list_or_iterator() -> Result<()> {
+    let code = "
+    class Countdown <: Iterator {
+      n: int;
+      next(): Option[int] = if self.n > 0 { Option::some(self.n) } else { Option::none() };
+    }
+    first_length(words: List[string]): int {
+      for w in words {
+        return string::length(w);
+      }
+      return 0;
+    }
+    first(countdown: Countdown): int {
+      for n in countdown {
+        return n + 1;
+      }
+      return 0;
+    }
+    ";
+    check_code(code)?;
+    let code = "
+    first_length(numbers: List[int]): int {
+      for n in numbers {
+        return string::length(n);
+      }
+      return 0;
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":4:30 type mismatched, expected: `string` but got: `int`"
+    );
+    let code = "
+    count(n: int): void {
+      for i in n {}
+    }
+    ";
+    let err = check_code(code).unwrap_err();
+    assert_eq!(
+        err.message(),
+        ":3:15 `for` can't iterate `int`, it takes a `List` or an `Iterator` whose `next` returns an `Option`"
+    );
+    Ok(())
+}
+
+// helpers, must put tests before this line
+fn check_code(code: &'static str) -> Result<()> {
+    check_code_with_checker(code, &mut SemanticChecker::new())
+        .map_err(|errors| errors.into_iter().next().unwrap())
+}
+
+/// check_code_errors returns all errors found in the code
+fn check_code_errors(code: &'static str) -> Vec<SemanticError> {
+    match check_code_with_checker(code, &mut SemanticChecker::new()) {
+        Ok(..) => vec![],
+        Err(errors) => errors,
+    }
+}
+
+/// check_code_warnings returns lint names of warnings found in the test module
+fn check_code_warnings(code: &'static str) -> Result<Vec<&'static str>> {
+    let mut checker = SemanticChecker::new();
+    check_code_with_checker(code, &mut checker)
+        .map_err(|errors| errors.into_iter().next().unwrap())?;
+    Ok(checker
+        .warnings()
+        .iter()
+        .filter(|warning| warning.location().file_name() == "")
+        .map(|warning| warning.lint_name())
+        .collect())
+}
+
+fn check_code_with_checker(
+    code: &'static str,
+    checker: &mut SemanticChecker,
+) -> std::result::Result<(), Vec<SemanticError>> {
+    let mut parser = Parser::new("", code);
+    let mut code = parser
+        .parse_top_list(TkType::EOF)
+        .map_err(|err| {
+            println!("{}", err);
             err
         })
         .unwrap();
+    derive_traits(&mut code);
 
     code.push(TopAst::Import(Import {
         location: Location::none(),
         import_path: "prelude".to_string(),
         imported_component: vec![
             "int".to_string(),
+            "i32".to_string(),
+            "u8".to_string(),
             "void".to_string(),
             "f64".to_string(),
             "bool".to_string(),
             "string".to_string(),
+            "StringBuilder".to_string(),
             "List".to_string(),
+            "Option".to_string(),
+            "Map".to_string(),
+            "ToString".to_string(),
+            "Eq".to_string(),
+            "Ord".to_string(),
+            "Hash".to_string(),
+            "Iterator".to_string(),
+            "ListIterator".to_string(),
+            "io".to_string(),
+            "env".to_string(),
+            "process".to_string(),
+            "math".to_string(),
+            "print".to_string(),
             "println".to_string(),
+            "eprint".to_string(),
+            "eprintln".to_string(),
+            "panic".to_string(),
+            "assert".to_string(),
+            "assert_eq".to_string(),
         ],
     }));
 
     let prelude = parse_prelude();
-    let mut checker = SemanticChecker::new();
     checker
         .check_program(&vec![
             prelude,
@@ -386,9 +1861,11 @@ fn check_code(code: &'static str) -> Result<()> {
                 top_list: code,
             },
         ])
-        .map_err(|err| {
-            // map origin error and report at here
-            println!("{}", err);
-            err
+        .map_err(|errors| {
+            // map origin errors and report at here
+            for err in &errors {
+                println!("{}", err);
+            }
+            errors
         })
 }
@@ -1,45 +1,285 @@
 use super::error::Result;
 use super::error::SemanticError;
+use super::resolution::ResolutionMap;
+use super::type_map::TypeMap;
 use crate::ast;
 use crate::ast::*;
 use crate::ast::{Function, ParsedType};
 use crate::lexer::Location;
-use std::collections::HashMap;
+use crate::mangle::{method_path, SEPARATOR};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 pub struct TypeEnv {
     parent: Option<*const TypeEnv>,
     /// imports store information about how to lookup imported name
     pub(crate) imports: HashMap<String, String>,
     variables: HashMap<String, TypeInfo>,
+    /// used_variables records which variables defined in this environment have been looked up
+    used_variables: RefCell<HashSet<String>>,
     types: HashMap<String, TypeInfo>,
+    /// resolutions are shared by all environments derived from the same top environment
+    pub(crate) resolutions: Rc<RefCell<ResolutionMap>>,
+    /// expr_types are shared as `resolutions` are
+    pub(crate) expr_types: Rc<RefCell<TypeMap>>,
     free_var_count: usize,
+    /// bounds are traits bounding type parameters of a generic function defined in this
+    /// environment, by the free variables standing for them
+    bounds: HashMap<usize, Vec<Type>>,
     // flag
     pub in_class_scope: bool,
 }
 
 impl TypeEnv {
+    /// check_expr checks `expr` has `expected` type, integer literal can be any integer type if
+    /// it's in the range of the type
+    pub(crate) fn check_expr(
+        &mut self,
+        location: &Location,
+        expr: &Expr,
+        expected: &Type,
+    ) -> Result<()> {
+        // both branches are checked with the expected type, so `x: u8 = if c { 1 } else { 2 };`
+        // works like `x: u8 = 1;`
+        if let ExprVariant::If(condition, then_expr, else_expr) = &expr.value {
+            self.check_condition(condition)?;
+            self.check_expr(location, then_expr, expected)?;
+            self.check_expr(location, else_expr, expected)?;
+            self.record_type(&expr.location, expected);
+            return Ok(());
+        }
+        // elements of a list literal are checked with the element type, so `x: List[u8] = [1];`
+        // works as well
+        if let (
+            ExprVariant::List(es),
+            Type::ClassType {
+                type_parameters, ..
+            },
+        ) = (&expr.value, expected)
+        {
+            if let [element_type] = type_parameters.as_slice() {
+                for e in es.iter() {
+                    self.check_expr(&e.location, e, element_type)?;
+                }
+                let list_type = self
+                    .lookup_type(&expr.location, "List")?
+                    .typ
+                    .with_type_arguments(vec![element_type.clone()]);
+                self.unify(&expr.location, expected, &list_type)?;
+                self.record_type(&expr.location, expected);
+                return Ok(());
+            }
+        }
+        if let (ExprVariant::Int(i), Type::ClassType { name, .. }) = (&expr.value, expected) {
+            if let Some((min, max)) = integer_range(name) {
+                return if min <= *i && *i <= max {
+                    self.record_type(&expr.location, expected);
+                    Ok(())
+                } else {
+                    Err(SemanticError::integer_out_of_range(
+                        &expr.location,
+                        *i,
+                        name.as_str(),
+                    ))
+                };
+            }
+        }
+        let typ = self.type_of_expr(expr)?;
+        // the error is put on the expression, the expected type usually comes from the definition
+        // at `location`
+        self.unify(&expr.location, expected, &typ).map_err(|err| {
+            if *location == expr.location {
+                err
+            } else {
+                err.with_label(location, format!("expected `{}` because of this", expected))
+            }
+        })
+    }
+    /// check_condition checks `condition` is a bool
+    fn check_condition(&mut self, condition: &Expr) -> Result<()> {
+        let bool_type = self.lookup_type(&condition.location, "bool")?.typ;
+        self.check_expr(&condition.location, condition, &bool_type)
+    }
+    /// type_of_loop_variable returns the type of elements `for` takes from `iterable`, elements of
+    /// a list, or values of `Option` that `next` of `Iterator` returns, and records it for the loop
+    /// variable at `location`
+    pub(crate) fn type_of_loop_variable(
+        &mut self,
+        location: &Location,
+        iterable: &Expr,
+    ) -> Result<Type> {
+        let typ = self.type_of_expr(iterable)?;
+        let element_type = match &typ {
+            Type::ClassType {
+                name,
+                type_parameters,
+                ..
+            } if name == "List" => type_parameters.first().cloned(),
+            Type::ClassType { name, members, .. } if implements(&typ, "Iterator") => {
+                let next = members.get_member(location, name.clone(), &"next".to_string())?;
+                match next.typ {
+                    Type::FunctionType(_, ret) => match *ret {
+                        Type::ClassType {
+                            name,
+                            type_parameters,
+                            ..
+                        } if name == "Option" => type_parameters.first().cloned(),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match element_type {
+            Some(element_type) => {
+                self.record_type(location, &element_type);
+                Ok(element_type)
+            }
+            None => Err(SemanticError::not_iterable(&iterable.location, &typ)),
+        }
+    }
+    /// type_of_assign_target returns the type of field `target` refers to, methods are not
+    /// assignable
+    pub(crate) fn type_of_assign_target(&mut self, target: &Expr) -> Result<Type> {
+        let location = &target.location;
+        let (from, access) = match &target.value {
+            ExprVariant::MemberAccess(from, access) => (from, access),
+            // the parser only accepts member access as assignment target
+            _ => {
+                return Err(SemanticError::internal(
+                    location,
+                    "assignment to an expression other than a field",
+                ))
+            }
+        };
+        let typ = self.type_of_expr(from)?;
+        match &typ {
+            Type::ClassType { name, members, .. } | Type::TraitType { name, members, .. } => {
+                let member = match typ.find_member(access) {
+                    Some(member) => member,
+                    None => members.get_member(location, name.clone(), access)?,
+                };
+                self.resolve(&location.tail(access.len() as u32), &member.location);
+                match member.kind {
+                    MemberKind::Field => Ok(member.typ),
+                    MemberKind::Method => Err(SemanticError::cannot_assign_to_method(
+                        location,
+                        name.clone(),
+                        access.clone(),
+                    )),
+                }
+            }
+            typ => Err(SemanticError::type_has_no_members(
+                location,
+                typ.to_string(),
+                access.clone(),
+            )),
+        }
+    }
+    /// type_of_expr returns the type of `expr`, and records it for queries of `TypeMap`
     pub(crate) fn type_of_expr(&mut self, expr: &Expr) -> Result<Type> {
+        let typ = self.infer_type(expr)?;
+        self.record_type(&expr.location, &typ);
+        Ok(typ)
+    }
+    fn infer_type(&mut self, expr: &Expr) -> Result<Type> {
         use ExprVariant::*;
         let location = &expr.location;
         match &expr.value {
             Binary(l, r, op) => {
+                // integer literal takes the integer type of the other operand, e.g. `x + 1` where
+                // `x: u8`
                 let left_type = self.type_of_expr(l)?;
-                let right_type = self.type_of_expr(r)?;
-                match (left_type, right_type, op) {
-                    (
-                        Type::ClassType { name: n1, .. },
-                        Type::ClassType { name: n2, .. },
-                        Operator::Plus,
-                    ) => {
-                        if n1.as_str() == "int" && n1 == n2 {
-                            Ok(self.lookup_type(location, "int")?.typ)
+                let right_type = match &r.value {
+                    Int(_) if is_integer_type(&left_type) => {
+                        self.check_expr(&r.location, r, &left_type)?;
+                        left_type.clone()
+                    }
+                    _ => self.type_of_expr(r)?,
+                };
+                let left_type = match &l.value {
+                    Int(_) if is_integer_type(&right_type) => {
+                        self.check_expr(&l.location, l, &right_type)?;
+                        right_type.clone()
+                    }
+                    _ => left_type,
+                };
+                match (&left_type, &right_type) {
+                    (Type::ClassType { name: n1, .. }, Type::ClassType { name: n2, .. })
+                        if n1 == "bool" && n2 == "bool" && op.is_logical() =>
+                    {
+                        Ok(left_type)
+                    }
+                    (Type::ClassType { name: n1, .. }, Type::ClassType { name: n2, .. })
+                        if n1 == n2
+                            && !op.is_logical()
+                            && (is_integer_type(&left_type) || n1 == "f64")
+                            // `**` calls `int::_pow` or `pow` of libm, which take `int` or `f64`
+                            && (*op != Operator::Power || n1 == "int" || n1 == "f64") =>
+                    {
+                        if op.is_comparison() {
+                            Ok(self.lookup_type(location, "bool")?.typ)
                         } else {
-                            panic!("sadasdasda")
+                            Ok(left_type)
                         }
                     }
-                    (l, r, op) => panic!("unsupported operator, {} {:?} {}", l, op, r),
+                    // `+` of strings concatenates them
+                    (Type::ClassType { name: n1, .. }, Type::ClassType { name: n2, .. })
+                        if n1 == "string" && n2 == "string" && *op == Operator::Plus =>
+                    {
+                        Ok(left_type)
+                    }
+                    // bool can only be compared for equality
+                    (Type::ClassType { name: n1, .. }, Type::ClassType { name: n2, .. })
+                        if n1 == "bool"
+                            && n2 == "bool"
+                            && (*op == Operator::Equal || *op == Operator::NotEqual) =>
+                    {
+                        Ok(left_type)
+                    }
+                    // `==` of instances calls `eq` of `Eq`
+                    (Type::ClassType { name, members, .. }, Type::ClassType { .. })
+                        if (*op == Operator::Equal || *op == Operator::NotEqual)
+                            && implements(&left_type, "Eq")
+                            && self.unify(location, &left_type, &right_type).is_ok() =>
+                    {
+                        members.get_member(location, name.clone(), &"eq".to_string())?;
+                        Ok(self.lookup_type(location, "bool")?.typ)
+                    }
+                    // so does `==` of values of a type parameter bounded by `Eq`
+                    (Type::FreeVar(n), Type::FreeVar(m))
+                        if n == m
+                            && (*op == Operator::Equal || *op == Operator::NotEqual)
+                            && self.satisfies(
+                                location,
+                                &left_type,
+                                &self.lookup_type(location, "Eq")?.typ,
+                            ) =>
+                    {
+                        Ok(self.lookup_type(location, "bool")?.typ)
+                    }
+                    _ => Err(SemanticError::unsupported_operator(
+                        location,
+                        op,
+                        &left_type,
+                        &right_type,
+                    )),
                 }
             }
+            If(condition, then_expr, else_expr) => {
+                self.check_condition(condition)?;
+                // integer literal branch takes the integer type of the other branch, as binary
+                // operation does
+                let typ = match &then_expr.value {
+                    Int(_) => self.type_of_expr(else_expr)?,
+                    _ => self.type_of_expr(then_expr)?,
+                };
+                self.check_expr(location, then_expr, &typ)?;
+                self.check_expr(location, else_expr, &typ)?;
+                Ok(typ)
+            }
             F64(_) => Ok(self.lookup_type(location, "f64")?.typ),
             Int(_) => Ok(self.lookup_type(location, "int")?.typ),
             Bool(_) => Ok(self.lookup_type(location, "bool")?.typ),
@@ -50,7 +290,7 @@ impl TypeEnv {
                 } else {
                     self.type_of_expr(&es[0])?
                 };
-                for e in es {
+                for e in es.iter() {
                     if expr_type != self.type_of_expr(e)? {
                         return Err(SemanticError::type_mismatched(
                             &e.location,
@@ -59,17 +299,46 @@ impl TypeEnv {
                         ));
                     }
                 }
-                Ok(self.lookup_type(location, "List")?.typ)
+                let list_type = self.lookup_type(location, "List")?.typ;
+                Ok(list_type.with_type_arguments(vec![expr_type]))
             }
             FuncCall(f, args) => {
                 let f_type = self.type_of_expr(f)?;
                 match f_type {
                     Type::FunctionType(params, ret_typ) => {
+                        // type parameters of a generic class are bound to types of arguments, e.g.
+                        // `T` of `List::get` to `int` by a `List[int]`, then the result takes them
+                        let mut bindings: HashMap<usize, Type> = HashMap::new();
                         for (p, arg) in params.iter().zip(args.iter()) {
-                            let typ = self.type_of_expr(&arg.expr)?;
-                            self.unify(&arg.location, p, &typ)?;
+                            if !p.has_free_var() {
+                                self.check_expr(&arg.location, &arg.expr, p)?;
+                                continue;
+                            }
+                            let arg_type = match (&arg.expr.value, p) {
+                                (Int(_), Type::FreeVar(n)) if bindings.contains_key(n) => {
+                                    let bound = bindings[n].clone();
+                                    self.check_expr(&arg.location, &arg.expr, &bound)?;
+                                    bound
+                                }
+                                _ => self.type_of_expr(&arg.expr)?,
+                            };
+                            self.bind(&arg.expr.location, p, &arg_type, &mut bindings)?;
+                        }
+                        // type arguments of a generic function must implement the bounds of its
+                        // type parameters
+                        if let Identifier(name) = &f.value {
+                            for (n, bound) in self.lookup_variable(&f.location, name)?.bounds {
+                                match bindings.get(&n) {
+                                    Some(typ) if !self.satisfies(location, typ, &bound) => {
+                                        return Err(SemanticError::unsatisfied_bound(
+                                            location, name, typ, &bound,
+                                        ));
+                                    }
+                                    _ => (),
+                                }
+                            }
                         }
-                        Ok(*ret_typ)
+                        Ok(ret_typ.substitute(&bindings))
                     }
                     _ => Err(SemanticError::call_on_non_function_type(
                         &f.location,
@@ -79,16 +348,46 @@ impl TypeEnv {
             }
             MemberAccess(from, access) => {
                 let typ = self.type_of_expr(from)?;
-                match typ {
-                    Type::ClassType { name, members, .. } => {
-                        let member = members.get_member(location, name, access)?;
-                        Ok(member.typ.clone())
+                match &typ {
+                    // both fields and methods can be accessed, method has function type so
+                    // `obj.method(...)` is checked as a function call on it
+                    Type::ClassType { name, members, .. }
+                    | Type::TraitType { name, members, .. } => {
+                        let member = match typ.find_member(access) {
+                            Some(member) => member,
+                            None => members.get_member(location, name.clone(), access)?,
+                        };
+                        self.resolve(&location.tail(access.len() as u32), &member.location);
+                        if member.kind == MemberKind::Method && access == DROP_METHOD {
+                            return Err(SemanticError::drop_called_manually(location, name));
+                        }
+                        Ok(member.typ)
                     }
-                    _ => unreachable!(),
+                    // a value of a type parameter has members of the traits bounding it
+                    Type::FreeVar(n) => {
+                        let bounds = self.bounds_of(*n);
+                        match bounds.iter().find_map(|bound| bound.find_member(access)) {
+                            Some(member) => {
+                                self.resolve(&location.tail(access.len() as u32), &member.location);
+                                Ok(member.typ)
+                            }
+                            None => Err(SemanticError::type_has_no_members(
+                                location,
+                                typ.to_string(),
+                                access.clone(),
+                            )),
+                        }
+                    }
+                    typ => Err(SemanticError::type_has_no_members(
+                        location,
+                        typ.to_string(),
+                        access.clone(),
+                    )),
                 }
             }
             Identifier(id) => {
                 let type_info = self.lookup_variable(location, id.as_str())?;
+                self.resolve(location, &type_info.location);
                 Ok(type_info.typ)
             }
             ClassConstruction(name, field_inits) => {
@@ -98,9 +397,12 @@ impl TypeEnv {
                     ));
                 }
                 let type_info = self.lookup_type(location, name)?;
+                self.resolve(location, &type_info.location);
                 match &type_info.typ {
                     Type::ClassType {
+                        name,
                         uninitialized_fields,
+                        members,
                         ..
                     } => {
                         let should_inits = uninitialized_fields;
@@ -116,6 +418,15 @@ impl TypeEnv {
                                 missing_init_fields,
                             ));
                         }
+                        // sort by location to report the first wrong initializer in source code
+                        let mut inits: Vec<(&std::string::String, &Expr)> =
+                            field_inits.iter().collect();
+                        inits.sort_by_key(|(_, e)| (e.location.line(), e.location.column()));
+                        for (field_name, init_expr) in inits {
+                            let field =
+                                members.get_field(&init_expr.location, name.clone(), field_name)?;
+                            self.check_expr(&init_expr.location, init_expr, &field.typ)?;
+                        }
                     }
                     rest => {
                         return Err(SemanticError::cannot_construct_non_class_type(
@@ -157,10 +468,29 @@ impl TypeEnv {
                     Ok(())
                 }
             }
+            (TraitType { name, .. }, TraitType { name: name2, .. }) if name == name2 => Ok(()),
+            // trait can be used as the traits it inherits
+            (TraitType { name, .. }, TraitType { parents, .. })
+                if parents
+                    .iter()
+                    .any(|parent| matches!(parent, TraitType { name: n, .. } if n == name)) =>
+            {
+                Ok(())
+            }
+            // class can be used as the trait it implements
+            (TraitType { .. }, ClassType { parents, .. }) => {
+                for parent in parents {
+                    if self.unify(location, expected, parent).is_ok() {
+                        return Ok(());
+                    }
+                }
+                Err(SemanticError::type_mismatched(location, expected, actual))
+            }
             (FunctionType(ft, arg), FunctionType(ft_p, arg_p)) => {
                 self.unify_type_list(location, ft, ft_p)?;
                 self.unify(location, arg, arg_p)
             }
+            (FreeVar(_), FreeVar(_)) => Ok(()),
             (FreeVar(_), t) => self.unify(location, t, expected),
             (t, f @ FreeVar(_)) => {
                 if t == f || !f.occurs(t.clone()) {
@@ -188,6 +518,57 @@ impl TypeEnv {
         }
         Ok(())
     }
+    /// bind binds free variables of `param` to types at the same places of `arg`, a variable
+    /// bound by a previous argument must get the same type again
+    fn bind(
+        &self,
+        location: &Location,
+        param: &Type,
+        arg: &Type,
+        bindings: &mut HashMap<usize, Type>,
+    ) -> Result<()> {
+        use Type::*;
+        match (param, arg) {
+            (FreeVar(n), _) => match bindings.get(n) {
+                Some(bound) => self.unify(location, bound, arg),
+                None => {
+                    bindings.insert(*n, arg.clone());
+                    Ok(())
+                }
+            },
+            (
+                ClassType {
+                    type_parameters, ..
+                },
+                ClassType {
+                    type_parameters: arg_type_parameters,
+                    ..
+                },
+            ) => {
+                self.unify(location, param, arg)?;
+                for (p, a) in type_parameters.iter().zip(arg_type_parameters.iter()) {
+                    self.bind(location, p, a, bindings)?;
+                }
+                Ok(())
+            }
+            (FunctionType(params, ret), FunctionType(arg_params, arg_ret)) => {
+                for (p, a) in params.iter().zip(arg_params.iter()) {
+                    self.bind(location, p, a, bindings)?;
+                }
+                self.bind(location, ret, arg_ret, bindings)
+            }
+            _ => self.unify(location, param, arg),
+        }
+    }
+
+    /// resolve records the name used at `location` is defined at `definition`
+    fn resolve(&self, location: &Location, definition: &Location) {
+        self.resolutions.borrow_mut().record(location, definition);
+    }
+    /// record_type records the expression at `location` has type `typ`
+    fn record_type(&self, location: &Location, typ: &Type) {
+        self.expr_types.borrow_mut().record(location, typ);
+    }
 
     fn free_var(&mut self) -> Type {
         let typ = Type::FreeVar(self.free_var_count);
@@ -202,55 +583,183 @@ impl TypeEnv {
             parent: None,
             imports: HashMap::new(),
             variables: HashMap::new(),
+            used_variables: RefCell::new(HashSet::new()),
             types: HashMap::new(),
+            resolutions: Rc::new(RefCell::new(ResolutionMap::default())),
+            expr_types: Rc::new(RefCell::new(TypeMap::default())),
             free_var_count: 1,
+            bounds: HashMap::new(),
             in_class_scope: false,
         }
     }
     pub fn with_parent(parent: &TypeEnv) -> TypeEnv {
         let mut type_env = TypeEnv::new();
         type_env.parent = Some(parent);
+        type_env.resolutions = parent.resolutions.clone();
+        type_env.expr_types = parent.expr_types.clone();
         // inherit the attribute from parent
         // if parent is in class scope, this of course is in class scope
         type_env.in_class_scope = parent.in_class_scope;
         type_env
     }
+    /// with_type_parameters creates an environment where the type parameters of a generic class
+    /// are types, each one is a free variable standing for its type argument
+    pub fn with_type_parameters(
+        parent: &TypeEnv,
+        location: &Location,
+        type_parameters: &[TypeParameter],
+    ) -> Result<TypeEnv> {
+        let mut type_env = TypeEnv::with_parent(parent);
+        for p in type_parameters {
+            let typ = type_env.free_var();
+            type_env.add_type(location, &p.name, typ)?;
+        }
+        Ok(type_env)
+    }
+    /// with_bounded_type_parameters is `with_type_parameters` for a generic function, whose type
+    /// parameters can be bounded by traits, e.g. `T <: Eq + ToString`, a value of `T` has members
+    /// of the traits
+    pub fn with_bounded_type_parameters(
+        parent: &TypeEnv,
+        location: &Location,
+        type_parameters: &[TypeParameter],
+    ) -> Result<TypeEnv> {
+        let mut type_env = TypeEnv::with_type_parameters(parent, location, type_parameters)?;
+        for p in type_parameters {
+            if let Type::FreeVar(n) = type_env.types[&p.name].typ {
+                let mut bounds = vec![];
+                for parent_type in &p.parent_types {
+                    bounds.push(type_env.from(parent_type)?);
+                }
+                type_env.bounds.insert(n, bounds);
+            }
+        }
+        Ok(type_env)
+    }
+    /// type_parameter_bounds returns bounds of type parameters defined in this environment, see
+    /// `TypeInfo::bounds`
+    pub fn type_parameter_bounds(&self) -> Vec<(usize, Type)> {
+        let mut bounds: Vec<(usize, Type)> = self
+            .bounds
+            .iter()
+            .flat_map(|(n, traits)| traits.iter().map(move |t| (*n, t.clone())))
+            .collect();
+        bounds.sort_by_key(|(n, _)| *n);
+        bounds
+    }
+    /// bounds_of returns traits bounding the type parameter standing by the free variable `n`
+    fn bounds_of(&self, n: usize) -> Vec<Type> {
+        match (self.bounds.get(&n), self.parent) {
+            (Some(bounds), _) => bounds.clone(),
+            (None, Some(env)) => unsafe { env.as_ref() }.unwrap().bounds_of(n),
+            (None, None) => vec![],
+        }
+    }
+    /// satisfies returns true if `typ` implements `bound`, a type parameter does if one of its
+    /// bounds is or inherits `bound`
+    fn satisfies(&self, location: &Location, typ: &Type, bound: &Type) -> bool {
+        match typ {
+            Type::FreeVar(n) => self
+                .bounds_of(*n)
+                .iter()
+                .any(|t| self.unify(location, bound, t).is_ok()),
+            typ => self.unify(location, bound, typ).is_ok(),
+        }
+    }
     pub fn from(&self, typ: &ParsedType) -> Result<Type> {
-        Ok(self
+        let t = self
             .lookup_type(&Location::none(), typ.name().as_str())?
-            .typ)
+            .typ;
+        match typ {
+            // type arguments are kept, so calls of generic methods can tell `List[int]` from
+            // `List[string]`
+            ParsedType::GenericType {
+                type_parameters, ..
+            } => {
+                let mut arguments = vec![];
+                for p in type_parameters {
+                    arguments.push(self.from(p)?);
+                }
+                Ok(t.with_type_arguments(arguments))
+            }
+            ParsedType::TypeName(_) => Ok(t),
+        }
+    }
+    /// value_type_from is `from` for the type of a variable, parameter or field, `void` has no
+    /// value so it's only valid as return type
+    pub fn value_type_from(
+        &self,
+        location: &Location,
+        kind: &'static str,
+        name: &str,
+        typ: &ParsedType,
+    ) -> Result<Type> {
+        let t = self.from(typ)?;
+        match &t {
+            Type::ClassType { name: typ_name, .. } if typ_name == "void" => {
+                Err(SemanticError::void_value(location, kind, name))
+            }
+            _ => Ok(t),
+        }
     }
     pub fn new_function_type(&self, f: &Function) -> Result<Type> {
         let mut param_types = vec![];
         for param in &f.parameters {
-            param_types.push(self.from(&param.typ)?);
+            param_types.push(self.value_type_from(
+                &param.location,
+                "parameter",
+                &param.name,
+                &param.typ,
+            )?);
         }
         Ok(Type::FunctionType(
             param_types,
             self.from(&f.ret_typ)?.into(),
         ))
     }
-    pub fn new_class(&mut self, c: &Class) -> Result<Type> {
+    /// declare_class returns the class without members, they're filled by `new_class`
+    pub fn declare_class(&self, c: &Class) -> Result<Type> {
+        Ok(Type::ClassType {
+            name: c.name.clone(),
+            parents: self.trait_parents(&c.location, &c.parents)?,
+            type_parameters: vec![],
+            uninitialized_fields: vec![],
+            members: ClassMembers::new(),
+        })
+    }
+    /// new_class fills members of the class `declared` by `declare_class`, types referring the
+    /// declaration share its members
+    pub fn new_class(&mut self, declared: Type, c: &Class) -> Result<Type> {
+        let mut class_env = TypeEnv::with_type_parameters(self, &c.location, &c.type_parameters)?;
+        class_env.new_class_members(declared, c)
+    }
+    fn new_class_members(&mut self, declared: Type, c: &Class) -> Result<Type> {
+        let (parents, members) = match declared {
+            Type::ClassType {
+                parents, members, ..
+            } => (parents, members),
+            typ => unreachable!("class `{}` is declared as `{}`", c.name, typ),
+        };
         let mut uninitialized_fields = vec![];
-        let mut members = ClassMembers::new();
         for member in &c.members {
             match member {
                 ast::ClassMember::Field(field) => {
-                    let field_type = self.from(&field.typ)?;
+                    let field_type =
+                        self.value_type_from(&field.location, "field", &field.name, &field.typ)?;
                     members.add_member(
                         c.name.clone(),
                         ClassMember {
                             name: field.name.clone(),
                             location: field.location.clone(),
                             typ: field_type.clone(),
+                            kind: MemberKind::Field,
                         },
                     )?;
                     match &field.expr {
                         None => uninitialized_fields.push(field.name.clone()),
                         Some(expr) => {
                             // check expression type same as field type
-                            let expr_type = self.type_of_expr(expr)?;
-                            self.unify(&field.location, &field_type, &expr_type)?;
+                            self.check_expr(&field.location, expr, &field_type)?;
                         }
                     }
                 }
@@ -261,20 +770,13 @@ impl TypeEnv {
                             name: method.name.clone(),
                             location: method.location.clone(),
                             typ: self.new_function_type(method)?,
+                            kind: MemberKind::Method,
                         },
                     )?;
                 }
                 _ => (),
             }
         }
-        let mut parents = vec![];
-        for p_name in &c.parents {
-            let parent_typ = self.lookup_type(&c.location, p_name.as_str())?;
-            match &parent_typ.typ {
-                Type::TraitType => parents.push(parent_typ.typ),
-                t => return Err(SemanticError::only_trait_can_be_super_type(&c.location, t)),
-            }
-        }
         Ok(Type::ClassType {
             name: c.name.clone(),
             parents,
@@ -283,6 +785,97 @@ impl TypeEnv {
             members,
         })
     }
+    /// declare_trait returns the trait without parents and members, parents are set by
+    /// `declare_trait_parents` once all traits are declared, and members are filled by `new_trait`
+    pub fn declare_trait(t: &Trait) -> Type {
+        Type::TraitType {
+            name: t.name.clone(),
+            parents: vec![],
+            members: ClassMembers::new(),
+        }
+    }
+    /// declare_trait_parents returns the trait `declared` by `declare_trait` with `parents` from
+    /// `trait_parents`
+    pub fn declare_trait_parents(declared: Type, parents: Vec<Type>) -> Type {
+        match declared {
+            Type::TraitType { name, members, .. } => Type::TraitType {
+                name,
+                parents,
+                members,
+            },
+            typ => unreachable!("`{}` is declared as a trait", typ),
+        }
+    }
+    /// trait_parents returns traits named `names` with their ancestors, so a class or trait is
+    /// also every trait its parents inherit, e.g. `Ord` of `trait Ord <: Eq` is also `Eq`
+    pub(crate) fn trait_parents(&self, location: &Location, names: &[String]) -> Result<Vec<Type>> {
+        let mut parents: Vec<Type> = vec![];
+        for p_name in names {
+            let parent_typ = self.lookup_type(location, p_name.as_str())?.typ;
+            let ancestors = match &parent_typ {
+                Type::TraitType { parents, .. } => parents.clone(),
+                t => return Err(SemanticError::only_trait_can_be_super_type(location, t)),
+            };
+            for typ in std::iter::once(parent_typ).chain(ancestors) {
+                if !parents.contains(&typ) {
+                    parents.push(typ);
+                }
+            }
+        }
+        Ok(parents)
+    }
+    /// new_trait records signatures of trait members, method signature excludes the implicit
+    /// `self` parameter, the same as method of class
+    pub fn new_trait(&mut self, declared: Type, t: &Trait) -> Result<Type> {
+        let mut trait_env = TypeEnv::with_type_parameters(self, &t.location, &t.type_parameters)?;
+        trait_env.new_trait_members(declared, t)
+    }
+    fn new_trait_members(&mut self, declared: Type, t: &Trait) -> Result<Type> {
+        let (parents, members) = match declared {
+            Type::TraitType {
+                parents, members, ..
+            } => (parents, members),
+            typ => unreachable!("trait `{}` is declared as `{}`", t.name, typ),
+        };
+        for member in &t.members {
+            match member {
+                ast::TraitMember::Field(field) => {
+                    let field_type =
+                        self.value_type_from(&field.location, "field", &field.name, &field.typ)?;
+                    members.add_member(
+                        t.name.clone(),
+                        ClassMember {
+                            name: field.name.clone(),
+                            location: field.location.clone(),
+                            typ: field_type.clone(),
+                            kind: MemberKind::Field,
+                        },
+                    )?;
+                    if let Some(expr) = &field.expr {
+                        self.check_expr(&field.location, expr, &field_type)?;
+                    }
+                }
+                ast::TraitMember::Method(method) => {
+                    let mut signature = method.clone();
+                    signature.parameters.remove(0);
+                    members.add_member(
+                        t.name.clone(),
+                        ClassMember {
+                            name: method.name.clone(),
+                            location: method.location.clone(),
+                            typ: self.new_function_type(&signature)?,
+                            kind: MemberKind::Method,
+                        },
+                    )?;
+                }
+            }
+        }
+        Ok(Type::TraitType {
+            name: t.name.clone(),
+            parents,
+            members,
+        })
+    }
 }
 
 impl TypeEnv {
@@ -295,25 +888,84 @@ impl TypeEnv {
             Ok(())
         }
     }
+    /// bound_variable sets `bounds` of the type parameters of the generic function defined as
+    /// `key`
+    pub(crate) fn bound_variable(&mut self, key: &str, bounds: Vec<(usize, Type)>) {
+        if let Some(type_info) = self.variables.get_mut(key) {
+            type_info.bounds = bounds;
+        }
+    }
     pub(crate) fn lookup_variable(&self, location: &Location, k: &str) -> Result<TypeInfo> {
+        self.find_variable(location, k, true)
+    }
+    /// lookup_shadowed_variable finds the variable would be shadowed by defining `k` in this
+    /// environment, lookup by this function won't mark the variable as used
+    pub(crate) fn lookup_shadowed_variable(&self, k: &str) -> Option<TypeInfo> {
+        match self.parent {
+            Some(env) => {
+                let k = self.imported_name(k);
+                unsafe { env.as_ref() }
+                    .unwrap()
+                    .find_variable(&Location::none(), &k, false)
+                    .ok()
+            }
+            None => None,
+        }
+    }
+    /// imported_name returns the name `k` is imported as, static methods of an imported class,
+    /// e.g. `List::get`, are imported with the class
+    fn imported_name(&self, k: &str) -> String {
+        if let Some(v) = self.imports.get(k) {
+            return v.clone();
+        }
+        match k.split_once(SEPARATOR) {
+            Some((class_name, method)) if self.imports.contains_key(class_name) => {
+                method_path(&self.imports[class_name], method)
+            }
+            _ => k.to_string(),
+        }
+    }
+    /// type_of_variable returns the type of `k` without marking it as used, e.g. for tools
+    pub(crate) fn type_of_variable(&self, k: &str) -> Option<Type> {
+        self.find_variable(&Location::none(), k, false)
+            .ok()
+            .map(|info| info.typ)
+    }
+    fn find_variable(&self, location: &Location, k: &str, mark_used: bool) -> Result<TypeInfo> {
         let result = self.variables.get(k);
         match result {
-            Some(t) => Ok(t.clone()),
+            Some(t) => {
+                if mark_used {
+                    self.used_variables.borrow_mut().insert(k.to_string());
+                }
+                Ok(t.clone())
+            }
             None => match self.parent {
                 Some(env) => {
-                    let k = match self.imports.get(k) {
-                        None => k,
-                        Some(v) => v,
-                    };
+                    let k = self.imported_name(k);
                     unsafe { env.as_ref() }
                         .unwrap()
-                        .lookup_variable(location, k)
+                        .find_variable(location, &k, mark_used)
                 }
                 None => Err(SemanticError::no_variable(location, k)),
             },
         }
     }
 
+    /// unused_variables returns variables defined in this environment but never be looked up,
+    /// ordered by their definition location
+    pub(crate) fn unused_variables(&self) -> Vec<(String, Location)> {
+        let used_variables = self.used_variables.borrow();
+        let mut result: Vec<(String, Location)> = self
+            .variables
+            .iter()
+            .filter(|(name, _)| !used_variables.contains(*name))
+            .map(|(name, info)| (name.clone(), info.location.clone()))
+            .collect();
+        result.sort_by_key(|(_, location)| (location.line(), location.column()));
+        result
+    }
+
     pub(crate) fn add_type(&mut self, location: &Location, key: &str, typ: Type) -> Result<()> {
         if self.types.contains_key(key) {
             Err(SemanticError::name_redefined(location, key))
@@ -323,6 +975,20 @@ impl TypeEnv {
             Ok(())
         }
     }
+    /// declaration returns the type declared as `key` at `location`, `None` for another type
+    /// declared with the same name
+    pub(crate) fn declaration(&self, location: &Location, key: &str) -> Option<Type> {
+        self.types
+            .get(key)
+            .filter(|type_info| &type_info.location == location)
+            .map(|type_info| type_info.typ.clone())
+    }
+    /// replace_type replaces the type defined as `key`, e.g. a class declared before its members
+    pub(crate) fn replace_type(&mut self, key: &str, typ: Type) {
+        if let Some(type_info) = self.types.get_mut(key) {
+            type_info.typ = typ;
+        }
+    }
     pub(crate) fn lookup_type(&self, location: &Location, k: &str) -> Result<TypeInfo> {
         let result = self.types.get(k);
         match result {
@@ -345,6 +1011,9 @@ impl TypeEnv {
 pub struct TypeInfo {
     pub location: Location,
     pub typ: Type,
+    /// bounds are traits type arguments of a generic function must implement, by the free
+    /// variables standing for its type parameters, e.g. `T` of `f[T <: Eq](x: T): void`
+    pub bounds: Vec<(usize, Type)>,
 }
 
 impl TypeInfo {
@@ -352,6 +1021,7 @@ impl TypeInfo {
         TypeInfo {
             location: location.clone(),
             typ,
+            bounds: vec![],
         }
     }
 }
@@ -361,19 +1031,41 @@ pub struct ClassMember {
     name: String,
     location: Location,
     typ: Type,
+    kind: MemberKind,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct ClassMembers(HashMap<String, ClassMember>);
+pub enum MemberKind {
+    Field,
+    Method,
+}
+
+/// ClassMembers are members of a class or trait, shared by all types referring it, since they're
+/// filled after the type is declared
+#[derive(Clone)]
+pub struct ClassMembers(Rc<RefCell<HashMap<String, ClassMember>>>);
+
+impl PartialEq for ClassMembers {
+    fn eq(&self, other: &ClassMembers) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for ClassMembers {
+    // members refer their class, e.g. by the type of `self`, so only names are shown
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.member_names()).finish()
+    }
+}
 
 impl ClassMembers {
     fn new() -> ClassMembers {
-        ClassMembers(HashMap::new())
+        ClassMembers(Rc::new(RefCell::new(HashMap::new())))
     }
-    fn add_member(&mut self, class_name: String, member: ClassMember) -> Result<()> {
+    fn add_member(&self, class_name: String, member: ClassMember) -> Result<()> {
         let location = &member.location.clone();
         let member_name = member.name.clone();
-        match self.0.insert(member.name.clone(), member) {
+        match self.0.borrow_mut().insert(member.name.clone(), member) {
             Some(previous_field) => Err(SemanticError::redefined_member(
                 location,
                 member_name,
@@ -388,22 +1080,100 @@ impl ClassMembers {
         location: &Location,
         class_name: String,
         name: &String,
-    ) -> Result<&ClassMember> {
-        match self.0.get(name) {
-            Some(v) => Ok(v),
+    ) -> Result<ClassMember> {
+        let members = self.0.borrow();
+        match members.get(name) {
+            Some(v) => Ok(v.clone()),
+            None if members.is_empty() => Err(SemanticError::type_has_no_members(
+                location,
+                class_name,
+                name.clone(),
+            )),
             None => Err(SemanticError::no_member_named(
                 location,
                 class_name,
                 name.clone(),
+                self.member_names(),
             )),
         }
     }
+    fn get_field(
+        &self,
+        location: &Location,
+        class_name: String,
+        name: &str,
+    ) -> Result<ClassMember> {
+        match self.0.borrow().get(name) {
+            Some(v) if v.kind == MemberKind::Field => Ok(v.clone()),
+            _ => Err(SemanticError::unknown_field(
+                location,
+                class_name,
+                name.to_string(),
+                self.field_names(),
+            )),
+        }
+    }
+    /// member_names returns names of all members in the order of definition, methods are shown
+    /// with `()`
+    fn member_names(&self) -> Vec<String> {
+        let members = self.0.borrow();
+        let mut members: Vec<&ClassMember> = members.values().collect();
+        members.sort_by_key(|member| (member.location.line(), member.location.column()));
+        members
+            .iter()
+            .map(|member| match member.kind {
+                MemberKind::Field => member.name.clone(),
+                MemberKind::Method => format!("{}()", member.name),
+            })
+            .collect()
+    }
+    /// field_names returns names of fields in the order of definition
+    fn field_names(&self) -> Vec<String> {
+        let members = self.0.borrow();
+        let mut fields: Vec<&ClassMember> = members
+            .values()
+            .filter(|member| member.kind == MemberKind::Field)
+            .collect();
+        fields.sort_by_key(|member| (member.location.line(), member.location.column()));
+        fields.iter().map(|member| member.name.clone()).collect()
+    }
+}
+
+/// implements is true for a class declaring the trait as its parent
+fn implements(typ: &Type, trait_name: &str) -> bool {
+    match typ {
+        Type::ClassType { parents, .. } => parents
+            .iter()
+            .any(|parent| matches!(parent, Type::TraitType { name, .. } if name == trait_name)),
+        _ => false,
+    }
+}
+
+fn is_integer_type(typ: &Type) -> bool {
+    match typ {
+        Type::ClassType { name, .. } => integer_range(name).is_some(),
+        _ => false,
+    }
+}
+
+/// integer_range returns the range of values of integer type
+fn integer_range(type_name: &str) -> Option<(i64, i64)> {
+    match type_name {
+        "int" => Some((i64::MIN, i64::MAX)),
+        "i32" => Some((i32::MIN as i64, i32::MAX as i64)),
+        "u8" => Some((u8::MIN as i64, u8::MAX as i64)),
+        _ => None,
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Type {
-    // TODO: complete definition
-    TraitType,
+    TraitType {
+        name: String,
+        /// parents are all traits the trait inherits, its parents and their ancestors
+        parents: Vec<Type>,
+        members: ClassMembers,
+    },
     ClassType {
         name: String,
         parents: Vec<Type>,
@@ -416,6 +1186,75 @@ pub enum Type {
 }
 
 impl Type {
+    /// find_member finds the member `access` of a class or trait, a trait also has members of the
+    /// traits it inherits
+    fn find_member(&self, access: &str) -> Option<ClassMember> {
+        match self {
+            Type::ClassType { members, .. } => members.0.borrow().get(access).cloned(),
+            Type::TraitType {
+                parents, members, ..
+            } => members
+                .0
+                .borrow()
+                .get(access)
+                .cloned()
+                .or_else(|| parents.iter().find_map(|parent| parent.find_member(access))),
+            _ => None,
+        }
+    }
+    /// with_type_arguments returns the class type applied to `arguments`, e.g. `List[int]`
+    fn with_type_arguments(self, arguments: Vec<Type>) -> Type {
+        match self {
+            Type::ClassType {
+                name,
+                parents,
+                uninitialized_fields,
+                members,
+                ..
+            } => Type::ClassType {
+                name,
+                parents,
+                type_parameters: arguments,
+                uninitialized_fields,
+                members,
+            },
+            typ => typ,
+        }
+    }
+    /// has_free_var is true when the type refers a type parameter, e.g. `List[T]`
+    fn has_free_var(&self) -> bool {
+        use Type::*;
+        match self {
+            FreeVar(_) => true,
+            ClassType {
+                type_parameters, ..
+            } => type_parameters.iter().any(Type::has_free_var),
+            FunctionType(params, ret) => {
+                params.iter().any(Type::has_free_var) || ret.has_free_var()
+            }
+            TraitType { .. } => false,
+        }
+    }
+    /// substitute replaces free variables bound by `bind` with their types
+    fn substitute(&self, bindings: &HashMap<usize, Type>) -> Type {
+        use Type::*;
+        match self {
+            FreeVar(n) => bindings.get(n).cloned().unwrap_or_else(|| self.clone()),
+            ClassType {
+                type_parameters, ..
+            } => self.clone().with_type_arguments(
+                type_parameters
+                    .iter()
+                    .map(|t| t.substitute(bindings))
+                    .collect(),
+            ),
+            FunctionType(params, ret) => FunctionType(
+                params.iter().map(|t| t.substitute(bindings)).collect(),
+                ret.substitute(bindings).into(),
+            ),
+            TraitType { .. } => self.clone(),
+        }
+    }
     fn occurs(&self, t: Type) -> bool {
         use Type::*;
         match t {
@@ -442,7 +1281,7 @@ impl Type {
                     false
                 }
             },
-            TraitType => unimplemented!("trait type"),
+            TraitType { .. } => false,
             FreeVar(_) => self.clone() == t,
         }
     }
@@ -471,9 +1310,11 @@ impl std::fmt::Display for Type {
                 }
                 write!(f, "")
             }
-            TraitType => unimplemented!("trait type"),
-            // FIXME: print format: `(int, int): int` not `<function>`
-            FunctionType(_params, _ret) => write!(f, "<function>"),
+            TraitType { name, .. } => write!(f, "{}", name),
+            FunctionType(params, ret) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "({}): {}", params.join(", "), ret)
+            }
             FreeVar(n) => write!(f, "'{}", n),
         }
     }
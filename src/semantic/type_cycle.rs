@@ -0,0 +1,79 @@
+use crate::ast::*;
+use std::collections::{HashMap, HashSet};
+
+pub(crate) struct TypeCycle<'a> {
+    /// class defined first in the cycle
+    pub(crate) class: &'a Class,
+    /// all classes in the cycle
+    pub(crate) class_names: Vec<&'a str>,
+    /// fields forming the cycle, e.g. `["Node.next: Node"]`
+    pub(crate) fields: Vec<String>,
+}
+
+/// type_cycles returns cycles of classes in the module which contain themselves by value, every
+/// cycle is reported once
+///
+/// all fields are stored by value for now, cycle should be allowed through indirection once
+/// pointer/optional types exist
+pub(crate) fn type_cycles(module: &Module) -> Vec<TypeCycle<'_>> {
+    let mut classes = vec![];
+    let mut by_name = HashMap::new();
+    for top in &module.top_list {
+        if let TopAst::Class(c) = top {
+            classes.push(c);
+            by_name.insert(c.name.as_str(), c);
+        }
+    }
+
+    let mut in_reported_cycle = HashSet::new();
+    let mut result = vec![];
+    for c in classes {
+        if in_reported_cycle.contains(c.name.as_str()) {
+            continue;
+        }
+        let mut visited = HashSet::new();
+        if let Some(path) = find_cycle(&by_name, c, c.name.as_str(), &mut visited) {
+            let class_names: Vec<&str> = path.iter().map(|(class_name, _)| *class_name).collect();
+            in_reported_cycle.extend(class_names.iter().cloned());
+            let fields = path
+                .iter()
+                .map(|(class_name, field)| {
+                    format!("{}.{}: {}", class_name, field.name, field.typ.name())
+                })
+                .collect();
+            result.push(TypeCycle {
+                class: c,
+                class_names,
+                fields,
+            });
+        }
+    }
+    result
+}
+
+/// find_cycle searches a path of fields from `from` back to class `target`
+fn find_cycle<'a>(
+    by_name: &HashMap<&str, &'a Class>,
+    from: &'a Class,
+    target: &str,
+    visited: &mut HashSet<&'a str>,
+) -> Option<Vec<(&'a str, &'a Field)>> {
+    if !visited.insert(from.name.as_str()) {
+        return None;
+    }
+    for member in &from.members {
+        if let ClassMember::Field(field) = member {
+            let field_type = field.typ.name();
+            if field_type == target {
+                return Some(vec![(from.name.as_str(), field)]);
+            }
+            if let Some(next) = by_name.get(field_type.as_str()) {
+                if let Some(mut path) = find_cycle(by_name, next, target, visited) {
+                    path.insert(0, (from.name.as_str(), field));
+                    return Some(path);
+                }
+            }
+        }
+    }
+    None
+}
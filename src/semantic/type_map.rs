@@ -0,0 +1,51 @@
+use crate::lexer::Location;
+use std::collections::HashMap;
+
+/// TypeMap records the type of each expression checked, keyed by its span, for hover, inlay
+/// hints and debuggers
+#[derive(Clone, Debug, Default)]
+pub struct TypeMap {
+    /// expressions and the names of their types keyed by file name and byte span
+    types: HashMap<(String, u32, u32), (Location, String)>,
+}
+
+impl TypeMap {
+    pub(crate) fn record<T: ToString>(&mut self, location: &Location, typ: T) {
+        self.types
+            .insert(key_of(location), (location.clone(), typ.to_string()));
+    }
+
+    /// type_of returns the type of the expression spanning exactly the location
+    pub fn type_of(&self, location: &Location) -> Option<&str> {
+        self.types
+            .get(&key_of(location))
+            .map(|(_, typ)| typ.as_str())
+    }
+    /// type_at returns the innermost expression covering the start of the location and its type,
+    /// e.g. `x` rather than `x + 1` at the start of `x + 1`
+    pub fn type_at(&self, location: &Location) -> Option<(&Location, &str)> {
+        self.types
+            .values()
+            .filter(|(expr_location, _)| {
+                expr_location.file_name() == location.file_name()
+                    && expr_location.start <= location.start
+                    && location.start < expr_location.end
+            })
+            .min_by_key(|(expr_location, _)| expr_location.end - expr_location.start)
+            .map(|(expr_location, typ)| (expr_location, typ.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+fn key_of(location: &Location) -> (String, u32, u32) {
+    (
+        location.file_name().to_string(),
+        location.start,
+        location.end,
+    )
+}
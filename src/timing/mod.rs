@@ -0,0 +1,161 @@
+//! timing measures phases of compilation, it's reported by `--timings` so performance regressions
+//! of the compiler are visible
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// PROGRAM is the unit of phases working on the whole program rather than a file
+pub const PROGRAM: &str = "<program>";
+
+/// TimingFormat is how `--timings` writes the report to stderr
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimingFormat {
+    /// aligned columns for humans
+    Table,
+    /// a JSON object per phase for tools, see `Record`
+    JSON,
+}
+
+impl TimingFormat {
+    /// from_name accepts the value of `--timings`, i.e. `table` or `json`
+    pub fn from_name(name: &str) -> Option<TimingFormat> {
+        match name {
+            "table" => Some(TimingFormat::Table),
+            "json" => Some(TimingFormat::JSON),
+            _ => None,
+        }
+    }
+}
+
+/// Timings records phases in the order they finished, a disabled one only runs the phases
+pub struct Timings {
+    format: Option<TimingFormat>,
+    records: Vec<Record>,
+}
+
+/// Record is a finished phase, it's written as a line of JSON with `--timings=json`, e.g.
+///
+/// ```json
+/// {"phase":"parse","unit":"a.elz","seconds":0.000153,"memory":3178496}
+/// ```
+///
+/// `unit` is the input file for lexing and parsing, else `<program>`, `memory` is bytes of
+/// resident memory after the phase, `null` if the platform doesn't tell
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Record {
+    phase: &'static str,
+    unit: String,
+    seconds: f64,
+    memory: Option<u64>,
+}
+
+impl Timings {
+    pub fn disabled() -> Timings {
+        Timings {
+            format: None,
+            records: vec![],
+        }
+    }
+    pub fn enabled(format: TimingFormat) -> Timings {
+        Timings {
+            format: Some(format),
+            records: vec![],
+        }
+    }
+
+    /// time runs the phase, which is recorded if timings are enabled
+    pub fn time<T, F: FnOnce() -> T>(&mut self, phase: &'static str, unit: &str, f: F) -> T {
+        if self.format.is_none() {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        self.record(phase, unit, start.elapsed(), resident_memory());
+        result
+    }
+    fn record(&mut self, phase: &'static str, unit: &str, elapsed: Duration, memory: Option<u64>) {
+        self.records.push(Record {
+            phase,
+            unit: unit.to_string(),
+            seconds: elapsed.as_secs_f64(),
+            memory,
+        });
+    }
+
+    /// report writes recorded phases to stderr, phases failed are not recorded
+    pub fn report(&self) {
+        match self.format {
+            None => (),
+            Some(TimingFormat::Table) => eprint!("{}", self.table()),
+            Some(TimingFormat::JSON) => {
+                for record in &self.records {
+                    eprintln!("{}", serde_json::to_string(record).unwrap());
+                }
+            }
+        }
+    }
+
+    /// table shows a phase per row and the total time at the end
+    fn table(&self) -> String {
+        let unit_width = self
+            .records
+            .iter()
+            .map(|record| record.unit.len())
+            .chain(std::iter::once("unit".len()))
+            .max()
+            .unwrap();
+        let mut s = format!(
+            "{:<10} {:<width$} {:>10} {:>10}\n",
+            "phase",
+            "unit",
+            "time",
+            "memory",
+            width = unit_width
+        );
+        for record in &self.records {
+            let memory = match record.memory {
+                Some(bytes) => format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0)),
+                None => "-".to_string(),
+            };
+            s.push_str(&format!(
+                "{:<10} {:<width$} {:>10} {:>10}\n",
+                record.phase,
+                record.unit,
+                format_seconds(record.seconds),
+                memory,
+                width = unit_width
+            ));
+        }
+        let total: f64 = self.records.iter().map(|record| record.seconds).sum();
+        s.push_str(&format!(
+            "{:<10} {:<width$} {:>10}\n",
+            "total",
+            "",
+            format_seconds(total),
+            width = unit_width
+        ));
+        s
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format!("{:.3}ms", seconds * 1000.0)
+}
+
+/// resident_memory returns bytes of physical memory used by the compiler, from `VmRSS` of
+/// `/proc/self/status` on Linux
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    // e.g. `VmRSS:      3104 kB`
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,51 @@
+use super::*;
+
+#[test]
+fn disabled_timings_record_nothing() {
+    let mut timings = Timings::disabled();
+    assert_eq!(timings.time("parse", "a.elz", || 1 + 1), 2);
+    assert!(timings.records.is_empty());
+}
+
+#[test]
+fn phases_are_recorded_in_order() {
+    let mut timings = Timings::enabled(TimingFormat::Table);
+    timings.time("lex", "a.elz", || ());
+    timings.time("check", PROGRAM, || ());
+    let phases: Vec<(&str, &str)> = timings
+        .records
+        .iter()
+        .map(|record| (record.phase, record.unit.as_str()))
+        .collect();
+    assert_eq!(phases, vec![("lex", "a.elz"), ("check", "<program>")]);
+}
+
+#[test]
+fn table_shows_phases_and_total() {
+    let mut timings = Timings::enabled(TimingFormat::Table);
+    timings.record(
+        "lex",
+        "a.elz",
+        Duration::from_micros(1500),
+        Some(3 * 1024 * 1024),
+    );
+    timings.record("check", PROGRAM, Duration::from_micros(500), None);
+    assert_eq!(
+        timings.table(),
+        "phase      unit            time     memory
+lex        a.elz        1.500ms     3.0MiB
+check      <program>    0.500ms          -
+total                   2.000ms
+"
+    );
+}
+
+#[test]
+fn record_to_json() {
+    let mut timings = Timings::enabled(TimingFormat::JSON);
+    timings.record("parse", "a.elz", Duration::from_millis(2), Some(4096));
+    assert_eq!(
+        serde_json::to_string(&timings.records[0]).unwrap(),
+        r#"{"phase":"parse","unit":"a.elz","seconds":0.002,"memory":4096}"#
+    );
+}
@@ -0,0 +1,106 @@
+//! codegen compiles every `.elz` file under `tests/codegen` for `x86_64`, and compares the LLVM IR
+//! with the `.ll` snapshot next to it, functions not reached from `main` are left out, and so are
+//! definitions of the prelude, which every program has, so a snapshot shows the code of its program.
+//! `UPDATE_SNAPSHOTS=1 cargo test --test codegen` writes snapshots of the current output instead,
+//! so a change of the output is reviewed in the diff of snapshots.
+use elz::codegen::target::Target;
+use elz::Options;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn codegen_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut failures = vec![];
+    for source in sources() {
+        let name = source.file_name().unwrap().to_string_lossy().to_string();
+        let code = fs::read_to_string(&source).unwrap();
+        let options = Options {
+            target: Target::from_name("x86_64").unwrap(),
+            eliminate_dead_functions: true,
+            ..Options::default()
+        };
+        let output = match elz::compile(&name, &code, options) {
+            Ok(output) => without_prelude(&output.llvm_ir),
+            Err(errors) => {
+                failures.push(format!("{} can't be compiled: {}", name, errors));
+                continue;
+            }
+        };
+        let snapshot = source.with_extension("ll");
+        if update {
+            fs::write(&snapshot, &output).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == output => {}
+            Ok(expected) => failures.push(format!(
+                "{} differs from `{}`:\n{}",
+                name,
+                snapshot.display(),
+                diff(&expected, &output)
+            )),
+            Err(..) => failures.push(format!("{} has no snapshot `{}`", name, snapshot.display())),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nrun `UPDATE_SNAPSHOTS=1 cargo test --test codegen` to accept the output",
+        failures.join("\n")
+    );
+}
+
+/// sources returns `.elz` files under `tests/codegen` in the order of names
+fn sources() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("codegen");
+    let mut sources: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "elz"))
+        .collect();
+    sources.sort();
+    sources
+}
+
+/// without_prelude drops definitions of functions of the prelude, whose names start with
+/// `_E7prelude`, from the IR
+fn without_prelude(ir: &str) -> String {
+    let mut result = String::new();
+    let mut in_prelude = false;
+    for line in ir.lines() {
+        if line.starts_with("define ") && line.contains(" @_E7prelude") {
+            in_prelude = true;
+        }
+        if !in_prelude {
+            result.push_str(line);
+            result.push('\n');
+        }
+        if line == "}" {
+            in_prelude = false;
+        }
+    }
+    result
+}
+
+/// diff shows lines of the snapshot and the output which differ, line by line, with their line
+/// numbers
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut result = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(i), actual.get(i));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            result.push_str(&format!("{:>4} - {}\n", i + 1, old));
+        }
+        if let Some(new) = new {
+            result.push_str(&format!("{:>4} + {}\n", i + 1, new));
+        }
+    }
+    result
+}
@@ -0,0 +1,185 @@
+target datalayout = "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+target triple = "x86_64-unknown-linux-gnu"
+%List = type { i64, i8*, i64, i64 }
+%ListIterator = type { i64, %List*, i64 }
+%Map = type { i64, %List*, %List*, %List* }
+%Option = type { i64, %List* }
+%Point = type { i64, i64 }
+%StringBuilder = type { i64, i8*, i64, i64 }
+%env = type { i64 }
+%io = type { i64 }
+%math = type { i64 }
+%process = type { i64 }
+%string = type { i64, i8* }
+@0 = global [2 x i8] c"-\00"
+@1 = global [11 x i8] c"0123456789\00"
+@2 = global [5 x i8] c"true\00"
+@3 = global [6 x i8] c"false\00"
+@4 = global [24 x i8] c"substring out of bounds\00"
+@5 = global [16 x i8] c"empty separator\00"
+@6 = global [20 x i8] c"index out of bounds\00"
+@7 = global [20 x i8] c"index out of bounds\00"
+@8 = global [1 x i8] c"\00"
+@9 = global [15 x i8] c"unwrap of none\00"
+@10 = global [1 x i8] c"\00"
+@11 = global [1 x i8] c"\00"
+@12 = global [2 x i8] c"\0A\00"
+@13 = global [2 x i8] c"\0A\00"
+@14 = global [2 x i64] zeroinitializer
+@15 = global [17 x i8] c"assertion failed\00"
+@16 = global [1 x i8] c"\00"
+@17 = global [1 x i8] c"\00"
+define %Point* @_E4main5Point3newE(i64 %x) {
+  %1 = call i8* @malloc(i64 16)
+  %2 = bitcast i8* %1 to %Point*
+  %3 = getelementptr %Point, %Point* %2, i32 0, i32 0
+  store i64 1, i64* %3
+  %4 = getelementptr %Point, %Point* %2, i32 0, i32 1
+  store i64 %x, i64* %4
+  ret %Point* %2
+}
+define void @_write(i64 %fd, %string* %content) {
+  %1 = getelementptr %string, %string* %content, i32 0, i32 1
+  %2 = load i8*, i8** %1
+  %3 = getelementptr %string, %string* %content, i32 0, i32 1
+  %4 = load i8*, i8** %3
+  %5 = call i64 @strlen(i8* %4)
+  %6 = call i64 @write(i64 %fd, i8* %2, i64 %5)
+  ret void
+}
+declare double @atan2(double %y, double %x)
+declare double @atof(i8* %str)
+declare double @ceil(double %x)
+declare double @cos(double %x)
+define void @elz.release.List(%List* %object) {
+  %1 = getelementptr %List, %List* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = sub i64 %2, 1
+  store i64 %3, i64* %1
+  %4 = icmp eq i64 %3, 0
+  br i1 %4, label %5, label %7
+; <label>:5:
+  store i64 1, i64* %1
+  call void @_E7prelude4List4dropE(%List* %object)
+  %6 = bitcast %List* %object to i8*
+  call void @free(i8* %6)
+  ret void
+; <label>:7:
+  ret void
+}
+define void @elz.release.Point(%Point* %object) {
+  %1 = getelementptr %Point, %Point* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = sub i64 %2, 1
+  store i64 %3, i64* %1
+  %4 = icmp eq i64 %3, 0
+  br i1 %4, label %5, label %7
+; <label>:5:
+  %6 = bitcast %Point* %object to i8*
+  call void @free(i8* %6)
+  ret void
+; <label>:7:
+  ret void
+}
+define void @elz.release.string(%string* %object) {
+  %1 = getelementptr %string, %string* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = sub i64 %2, 1
+  store i64 %3, i64* %1
+  %4 = icmp eq i64 %3, 0
+  br i1 %4, label %5, label %7
+; <label>:5:
+  %6 = bitcast %string* %object to i8*
+  call void @free(i8* %6)
+  ret void
+; <label>:7:
+  ret void
+}
+define void @elz.retain.List(%List* %object) {
+  %1 = getelementptr %List, %List* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = add i64 %2, 1
+  store i64 %3, i64* %1
+  ret void
+}
+define void @elz.retain.Point(%Point* %object) {
+  %1 = getelementptr %Point, %Point* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = add i64 %2, 1
+  store i64 %3, i64* %1
+  ret void
+}
+define void @elz.retain.string(%string* %object) {
+  %1 = getelementptr %string, %string* %object, i32 0, i32 0
+  %2 = load i64, i64* %1
+  %3 = add i64 %2, 1
+  store i64 %3, i64* %1
+  ret void
+}
+declare void @exit(i64 %code)
+declare double @exp(double %x)
+declare double @fabs(double %x)
+declare double @floor(double %x)
+declare void @free(i8* %pointer)
+declare i8* @gcvt(double %x, i64 %digits, i8* %buffer)
+declare i8* @getenv(i8* %name)
+declare double @log(double %x)
+define void @main() {
+  %1 = call %Point* @_E4main5Point3newE(i64 1)
+  %2 = getelementptr %Point, %Point* %1, i32 0, i32 1
+  %3 = load i64, i64* %2
+  %4 = icmp eq i64 %3, 1
+  br i1 %4, label %5, label %15
+; <label>:5:
+  call void @elz.retain.Point(%Point* %1)
+  %6 = getelementptr [1 x i8], [1 x i8]* @16, i32 0, i32 0
+  %7 = call %string* @_E7prelude6string3newE(i8* %6)
+  %8 = getelementptr %Point, %Point* %1, i32 0, i32 1
+  %9 = load i64, i64* %8
+  %10 = call %string* @_E7prelude3int9to_stringE(i64 %9)
+  %11 = call %string* @_E7prelude6string6concatE(%string* %7, %string* %10)
+  %12 = getelementptr [1 x i8], [1 x i8]* @17, i32 0, i32 0
+  %13 = call %string* @_E7prelude6string3newE(i8* %12)
+  %14 = call %string* @_E7prelude6string6concatE(%string* %11, %string* %13)
+  call void @println(%string* %14)
+  call void @elz.release.string(%string* %7)
+  call void @elz.release.string(%string* %10)
+  call void @elz.release.string(%string* %11)
+  call void @elz.release.string(%string* %13)
+  call void @elz.release.string(%string* %14)
+  call void @elz.release.Point(%Point* %1)
+  br label %16
+; <label>:15:
+  br label %16
+; <label>:16:
+  call void @elz.release.Point(%Point* %1)
+  ret void
+}
+declare i8* @malloc(i64 %size)
+define void @panic(%string* %message, %string* %location) {
+  call void @println(%string* %message)
+  call void @println(%string* %location)
+  call void @exit(i64 101)
+  ret void
+}
+declare double @pow(double %x, double %y)
+define void @println(%string* %content) {
+  call void @_write(i64 1, %string* %content)
+  %1 = getelementptr [2 x i8], [2 x i8]* @12, i32 0, i32 0
+  %2 = call %string* @_E7prelude6string3newE(i8* %1)
+  call void @_write(i64 1, %string* %2)
+  call void @elz.release.string(%string* %2)
+  ret void
+}
+declare i64 @read(i64 %fd, i8* %buffer, i64 %size)
+declare i8* @realloc(i8* %pointer, i64 %size)
+declare i32 @setenv(i8* %name, i8* %value, i32 %overwrite)
+declare double @sin(double %x)
+declare double @sqrt(double %x)
+declare i8* @strcat(i8* %destination, i8* %source)
+declare i8* @strcpy(i8* %destination, i8* %source)
+declare i64 @strlen(i8* %str)
+declare i32 @strncmp(i8* %left, i8* %right, i64 %size)
+declare i8* @strndup(i8* %str, i64 %size)
+declare double @tan(double %x)
+declare i64 @write(i64 %fd, i8* %buffer, i64 %size)
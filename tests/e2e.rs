@@ -0,0 +1,142 @@
+//! e2e runs every `.elz` file under `tests/e2e` with `elz eval` and `elz run`, and runs the
+//! executable `elz build` links from it, with the `.stdin` file next to it as input, then compares
+//! stdout with the `.stdout` file next to it, and the exit code with the `// exit code: <n>` comment
+//! of the program, 0 without the comment. The interpreter of `elz eval` is the reference semantics,
+//! compiled programs must behave the same. Comparing snapshots of IR can't find a miscompilation,
+//! e.g. a wrong index of getelementptr, running the program can. Ways to run missing their tools,
+//! `lli`, `llc` or a C compiler, are skipped.
+#![cfg(feature = "e2e")]
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+#[test]
+fn e2e_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("e2e");
+    let run = cfg!(feature = "llvm") || available("lli");
+    let build =
+        (cfg!(feature = "llvm") || available("llc")) && (available("cc") || available("clang"));
+    if !run && !build {
+        eprintln!(
+            "compiled e2e programs are skipped, neither `lli` nor `llc` and a C compiler are found"
+        );
+    }
+    let mut failures = vec![];
+    for source in sources(&dir) {
+        let name = source.file_name().unwrap().to_string_lossy().to_string();
+        let code = fs::read_to_string(&source).unwrap();
+        let stdin = fs::read(source.with_extension("stdin")).unwrap_or_default();
+        let expected = Expected {
+            stdout: fs::read_to_string(source.with_extension("stdout")).unwrap_or_default(),
+            code: exit_code(&code),
+        };
+        let output = run_with_input(
+            Command::new(env!("CARGO_BIN_EXE_elz")).args(["eval", &name]),
+            &dir,
+            &stdin,
+        );
+        failures.extend(expected.check(&format!("`elz eval {}`", name), &output));
+        if run {
+            let output = run_with_input(
+                Command::new(env!("CARGO_BIN_EXE_elz")).args(["run", &name]),
+                &dir,
+                &stdin,
+            );
+            failures.extend(expected.check(&format!("`elz run {}`", name), &output));
+        }
+        if build {
+            let executable = std::env::temp_dir().join(format!(
+                "elz-e2e-{}-{}",
+                std::process::id(),
+                source.file_stem().unwrap().to_string_lossy()
+            ));
+            let output = elz(&dir, &["build", &name, "-o", &executable.to_string_lossy()]);
+            if !output.status.success() {
+                failures.push(format!(
+                    "`elz build {}` failed:\n{}{}",
+                    name,
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+                continue;
+            }
+            let output = run_with_input(&mut Command::new(&executable), &dir, &stdin);
+            let _ = fs::remove_file(&executable);
+            failures.extend(expected.check(&format!("executable of {}", name), &output));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Expected is what a program prints to stdout and exits with
+struct Expected {
+    stdout: String,
+    code: i32,
+}
+
+impl Expected {
+    fn check(&self, what: &str, output: &Output) -> Option<String> {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout == self.stdout && output.status.code() == Some(self.code) {
+            return None;
+        }
+        Some(format!(
+            "{} exits with {}, expected {}, prints:\n{}expected:\n{}stderr:\n{}",
+            what,
+            output.status,
+            self.code,
+            stdout,
+            self.stdout,
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+/// exit_code returns the code of the `// exit code: <n>` comment, 0 without the comment
+fn exit_code(code: &str) -> i32 {
+    code.lines()
+        .find_map(|line| line.trim().strip_prefix("// exit code:"))
+        .map_or(0, |n| n.trim().parse().unwrap())
+}
+
+/// elz runs the compiler in `dir`, so locations printed by programs are relative to it
+fn elz(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_elz"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+/// run_with_input runs the command in `dir` with `stdin` as its input
+fn run_with_input(command: &mut Command, dir: &Path, stdin: &[u8]) -> Output {
+    let mut child = command
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // the program may exit without reading all input
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().unwrap()
+}
+
+/// available is true when the tool can be run
+fn available(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok()
+}
+
+/// sources returns `.elz` files under `dir` in the order of names
+fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut sources: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "elz"))
+        .collect();
+    sources.sort();
+    sources
+}